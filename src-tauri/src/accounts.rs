@@ -0,0 +1,208 @@
+// ── Account metadata & confirmations ───────────────────────────────────────
+// Display label / colour / emoji per connected account, so every
+// account-scoped screen and event makes it obvious which shop is affected.
+//
+// Destructive bulk commands are two-step: called without a token they only
+// return a `confirmation_required` response naming the account and item count;
+// they run when called again with that one-time token. Tokens are bound to the
+// account, the action and the exact set of items, and expire after two minutes.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use tauri::Manager;
+
+const STORE_NAME: &str = "accounts";
+const CONFIRMATION_TTL_SECS: u64 = 120;
+const MAX_LABEL_CHARS: usize = 40;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct AccountMetadata {
+    #[serde(default)]
+    pub label: Option<String>,
+    /// "#rrggbb".
+    #[serde(default)]
+    pub colour: Option<String>,
+    #[serde(default)]
+    pub emoji: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AccountSummary {
+    account: String,
+    /// The label, or the account id when none is set.
+    display_name: String,
+    #[serde(flatten)]
+    meta: AccountMetadata,
+}
+
+struct PendingConfirmation {
+    account: String,
+    action: String,
+    fingerprint: String,
+    expires_at: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ConfirmationRequest {
+    token: String,
+    action: String,
+    account: String,
+    account_label: String,
+    account_meta: Option<AccountMetadata>,
+    item_count: usize,
+    expires_at: u64,
+}
+
+/// Response of a guarded bulk command.
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Guarded<T> {
+    ConfirmationRequired(ConfirmationRequest),
+    Completed { results: T },
+}
+
+pub struct AccountState {
+    accounts: Mutex<BTreeMap<String, AccountMetadata>>,
+    pending: Mutex<HashMap<String, PendingConfirmation>>,
+}
+
+impl AccountState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        AccountState {
+            accounts: Mutex::new(crate::store::load(app, STORE_NAME)),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Make sure `account` is listed, so accounts show up in `list_accounts` as
+/// soon as they're used, before anyone has labelled them.
+pub fn remember(app: &tauri::AppHandle, account: &str) {
+    let Some(state) = app.try_state::<AccountState>() else {
+        return;
+    };
+    let mut accounts = state.accounts.lock().unwrap();
+    if account.trim().is_empty() || accounts.contains_key(account) {
+        return;
+    }
+    accounts.insert(account.to_string(), AccountMetadata::default());
+    if let Err(e) = crate::store::save(app, STORE_NAME, &*accounts) {
+        log::warn!("saving accounts failed: {e}");
+    }
+}
+
+/// Metadata for `account`, for attaching to event payloads.
+pub fn meta(app: &tauri::AppHandle, account: &str) -> Option<AccountMetadata> {
+    let state = app.try_state::<AccountState>()?;
+    let accounts = state.accounts.lock().unwrap();
+    accounts.get(account).cloned()
+}
+
+fn display_name(account: &str, meta: Option<&AccountMetadata>) -> String {
+    meta.and_then(|m| m.label.clone()).unwrap_or_else(|| account.to_string())
+}
+
+/// Identifies the exact set of items a confirmation covers.
+fn fingerprint<S: AsRef<str>>(items: &[S]) -> String {
+    let mut sorted: Vec<&str> = items.iter().map(AsRef::as_ref).collect();
+    sorted.sort_unstable();
+    hex::encode(Sha256::digest(sorted.join("\n").as_bytes()))
+}
+
+/// Gate for a destructive bulk command. Without a token, registers a pending
+/// confirmation and returns it for the caller to hand back to the UI. With a
+/// token, consumes it and returns `Ok(None)` when it matches this account,
+/// action and item set.
+pub fn confirm<S: AsRef<str>>(
+    app: &tauri::AppHandle,
+    account: &str,
+    action: &str,
+    items: &[S],
+    token: Option<&str>,
+) -> Result<Option<ConfirmationRequest>, String> {
+    remember(app, account);
+    let state = app.state::<AccountState>();
+    let now = crate::unix_now();
+    let mut pending = state.pending.lock().unwrap();
+    pending.retain(|_, p| p.expires_at > now);
+
+    let Some(token) = token else {
+        let token = hex::encode(rand::random::<[u8; 16]>());
+        let expires_at = now + CONFIRMATION_TTL_SECS;
+        pending.insert(
+            token.clone(),
+            PendingConfirmation {
+                account: account.to_string(),
+                action: action.to_string(),
+                fingerprint: fingerprint(items),
+                expires_at,
+            },
+        );
+        let account_meta = state.accounts.lock().unwrap().get(account).cloned();
+        return Ok(Some(ConfirmationRequest {
+            token,
+            action: action.to_string(),
+            account: account.to_string(),
+            account_label: display_name(account, account_meta.as_ref()),
+            account_meta,
+            item_count: items.len(),
+            expires_at,
+        }));
+    };
+
+    let found = pending
+        .remove(token)
+        .ok_or("Confirmation expired or already used — confirm again")?;
+    if found.account != account || found.action != action || found.fingerprint != fingerprint(items) {
+        return Err("Confirmation doesn't match this account or selection — confirm again".to_string());
+    }
+    Ok(None)
+}
+
+#[tauri::command]
+pub fn list_accounts(state: tauri::State<'_, AccountState>) -> Vec<AccountSummary> {
+    state
+        .accounts
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(account, meta)| AccountSummary {
+            account: account.clone(),
+            display_name: display_name(account, Some(meta)),
+            meta: meta.clone(),
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn set_account_metadata(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AccountState>,
+    account: String,
+    meta: AccountMetadata,
+) -> Result<(), String> {
+    if account.trim().is_empty() {
+        return Err("Account is required".to_string());
+    }
+    let label = meta.label.map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+    if label.as_ref().is_some_and(|l| l.chars().count() > MAX_LABEL_CHARS) {
+        return Err(format!("Label must be at most {MAX_LABEL_CHARS} characters"));
+    }
+    let colour = meta.colour.map(|c| c.trim().to_ascii_lowercase()).filter(|c| !c.is_empty());
+    if let Some(c) = &colour {
+        let valid = c.len() == 7 && c.starts_with('#') && c[1..].chars().all(|ch| ch.is_ascii_hexdigit());
+        if !valid {
+            return Err(format!("'{c}' isn't a #rrggbb colour"));
+        }
+    }
+    let emoji = meta.emoji.map(|e| e.trim().to_string()).filter(|e| !e.is_empty());
+    if emoji.as_ref().is_some_and(|e| e.chars().count() > 8) {
+        return Err("Emoji must be a single emoji".to_string());
+    }
+
+    let mut accounts = state.accounts.lock().unwrap();
+    accounts.insert(account, AccountMetadata { label, colour, emoji });
+    crate::store::save(&app, STORE_NAME, &*accounts)
+}
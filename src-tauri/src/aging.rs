@@ -0,0 +1,185 @@
+// ── Inventory aging report ─────────────────────────────────────────────────
+// How long unsold stock has been sitting, bucketed by days held, with the
+// money tied up in each bucket. The money is `inventory.cost`, which for an
+// item from a purchase lot is the share `allocate_lot_cost` gave it, so a
+// £40 box shows up spread over its items. Lot items still waiting for an
+// allocation are counted separately instead of being valued at nothing, and
+// every item carries its lot reference.
+
+use crate::lots::number;
+use crate::privacy::Private;
+use crate::supabase::{self, SupabaseSession};
+use reqwest::Method;
+use serde::Serialize;
+use serde_json::Value;
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+/// Upper bound (days held, inclusive) and label of each bucket.
+const BUCKETS: &[(u64, &str)] = &[(30, "0-30"), (90, "31-90"), (180, "91-180"), (u64::MAX, "180+")];
+
+#[derive(Serialize)]
+pub struct AgedItem {
+    id: String,
+    name: String,
+    sku: Option<String>,
+    days_held: u64,
+    quantity: i64,
+    /// Per unit; None when nothing has been recorded or allocated yet.
+    cost: Option<f64>,
+    /// Cost of all units still held.
+    value: f64,
+    lot_reference: Option<String>,
+    allocation_method: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AgingBucket {
+    label: &'static str,
+    items: usize,
+    units: i64,
+    value: f64,
+}
+
+#[derive(Serialize)]
+pub struct AgingReport {
+    buckets: Vec<AgingBucket>,
+    total_value: f64,
+    /// Lot items with no allocated cost yet.
+    unallocated_lot_items: usize,
+    /// Oldest first.
+    items: Vec<AgedItem>,
+}
+
+/// Unix seconds of a Postgres timestamp.
+fn timestamp(value: &Value) -> Option<u64> {
+    let parsed = chrono::DateTime::parse_from_rfc3339(value.as_str()?).ok()?;
+    u64::try_from(parsed.timestamp()).ok()
+}
+
+/// The report over `inventory` rows (with `lot` embedded) as of `now`.
+fn report(rows: &[Value], now: u64) -> AgingReport {
+    let mut buckets: Vec<AgingBucket> =
+        BUCKETS.iter().map(|(_, label)| AgingBucket { label, items: 0, units: 0, value: 0.0 }).collect();
+    let mut unallocated_lot_items = 0;
+    let mut items = Vec::new();
+
+    for row in rows {
+        let quantity = row["quantity"].as_i64().unwrap_or(1);
+        let Some(id) = row["id"].as_str() else { continue };
+        if quantity <= 0 {
+            continue;
+        }
+        let gift = row["acquisition_type"] == "gift";
+        let cost = if gift { Some(0.0) } else { number(&row["cost"]) };
+        if row["lot_id"].is_string() && cost.is_none() {
+            unallocated_lot_items += 1;
+        }
+        let days_held = timestamp(&row["created_at"]).map_or(0, |t| now.saturating_sub(t) / DAY_SECS);
+        let value = crate::fees::round_cents(cost.unwrap_or(0.0) * quantity as f64);
+
+        let bucket = BUCKETS.iter().position(|(max, _)| days_held <= *max).unwrap_or(BUCKETS.len() - 1);
+        let bucket = &mut buckets[bucket];
+        bucket.items += 1;
+        bucket.units += quantity;
+        bucket.value += value;
+
+        items.push(AgedItem {
+            id: id.to_string(),
+            name: row["name"].as_str().unwrap_or_default().to_string(),
+            sku: row["sku"].as_str().map(str::to_string),
+            days_held,
+            quantity,
+            cost,
+            value,
+            lot_reference: row["lot"]["reference"].as_str().map(str::to_string),
+            allocation_method: row["cost_allocation_method"].as_str().map(str::to_string),
+        });
+    }
+
+    for bucket in &mut buckets {
+        bucket.value = crate::fees::round_cents(bucket.value);
+    }
+    items.sort_by(|a, b| b.days_held.cmp(&a.days_held).then_with(|| a.name.cmp(&b.name)));
+    AgingReport {
+        total_value: crate::fees::round_cents(buckets.iter().map(|b| b.value).sum()),
+        buckets,
+        unallocated_lot_items,
+        items,
+    }
+}
+
+/// Unsold inventory by how long it's been held.
+#[tauri::command]
+pub async fn get_inventory_aging(session: SupabaseSession) -> Result<Private<AgingReport>, String> {
+    let client = supabase::client()?;
+    let req = session.rest(&client, Method::GET, "inventory").query(&[
+        (
+            "select",
+            "id,name,sku,cost,quantity,created_at,lot_id,acquisition_type,cost_allocation_method,lot:purchase_lots(reference)",
+        ),
+        ("deleted_at", "is.null"),
+        ("quantity", "gt.0"),
+    ]);
+    let rows = match supabase::send(req).await? {
+        Value::Array(rows) => rows,
+        _ => Vec::new(),
+    };
+    Ok(Private::new(report(&rows, crate::unix_now())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// 2024-06-30T00:00:00Z.
+    const NOW: u64 = 1_719_705_600;
+
+    fn item(id: &str, created: &str, cost: Value, quantity: i64) -> Value {
+        json!({ "id": id, "name": id, "created_at": created, "cost": cost, "quantity": quantity })
+    }
+
+    #[test]
+    fn items_land_in_buckets_by_days_held() {
+        let rows = [
+            item("fresh", "2024-06-20T09:00:00+00:00", json!(5), 2),
+            item("month", "2024-05-31T00:00:00+00:00", json!("4.50"), 1),
+            item("spring", "2024-03-01T12:00:00.123456+00:00", json!(10), 1),
+            item("ancient", "2023-01-01T00:00:00+00:00", json!(1.25), 4),
+            item("sold out", "2023-01-01T00:00:00+00:00", json!(99), 0),
+        ];
+        let report = report(&rows, NOW);
+        let summary: Vec<_> = report.buckets.iter().map(|b| (b.label, b.items, b.units, b.value)).collect();
+        assert_eq!(
+            summary,
+            vec![("0-30", 2, 3, 14.5), ("31-90", 0, 0, 0.0), ("91-180", 1, 1, 10.0), ("180+", 1, 4, 5.0)]
+        );
+        assert_eq!(report.total_value, 29.5);
+        let order: Vec<_> = report.items.iter().map(|i| (i.id.as_str(), i.days_held)).collect();
+        assert_eq!(order, vec![("ancient", 546), ("spring", 120), ("month", 30), ("fresh", 9)]);
+    }
+
+    #[test]
+    fn lot_items_are_valued_at_their_allocated_share() {
+        let mut allocated = item("jacket", "2024-06-01T00:00:00+00:00", json!(13.34), 1);
+        allocated["lot_id"] = json!("lot-1");
+        allocated["cost_allocation_method"] = json!("equal");
+        allocated["lot"] = json!({ "reference": "BOX-7" });
+        let mut waiting = item("scarf", "2024-06-01T00:00:00+00:00", Value::Null, 1);
+        waiting["lot_id"] = json!("lot-1");
+        waiting["lot"] = json!({ "reference": "BOX-7" });
+        let mut gift = item("freebie", "2024-06-01T00:00:00+00:00", Value::Null, 1);
+        gift["lot_id"] = json!("lot-1");
+        gift["acquisition_type"] = json!("gift");
+
+        let report = report(&[allocated, waiting, gift], NOW);
+        assert_eq!(report.total_value, 13.34);
+        assert_eq!(report.unallocated_lot_items, 1);
+        let jacket = report.items.iter().find(|i| i.id == "jacket").unwrap();
+        assert_eq!(
+            (jacket.lot_reference.as_deref(), jacket.allocation_method.as_deref()),
+            (Some("BOX-7"), Some("equal"))
+        );
+        assert_eq!(report.items.iter().find(|i| i.id == "freebie").unwrap().cost, Some(0.0));
+    }
+}
@@ -0,0 +1,65 @@
+// ── Audit log ──────────────────────────────────────────────────────────────
+// Append-only JSONL record of every marketplace write (and dry run) issued
+// from the Rust side, so bulk operations can be reviewed after the fact.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Write;
+
+const AUDIT_FILE: &str = "audit.jsonl";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub at: u64,
+    pub action: String,
+    pub account: Option<String>,
+    pub dry_run: bool,
+    pub details: Value,
+}
+
+/// Append one entry. Failures are logged, never propagated — an unwritable
+/// audit file must not abort the operation being audited.
+pub fn record(app: &tauri::AppHandle, action: &str, account: Option<&str>, dry_run: bool, details: Value) {
+    let entry = AuditEntry {
+        at: crate::unix_now(),
+        action: action.to_string(),
+        account: account.map(str::to_string),
+        dry_run,
+        details,
+    };
+    let result = crate::store::data_path(app, AUDIT_FILE).and_then(|path| {
+        let mut line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+        line.push('\n');
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut f| f.write_all(line.as_bytes()))
+            .map_err(|e| format!("{}: {e}", path.display()))
+    });
+    if let Err(e) = result {
+        log::warn!("audit write failed: {e}");
+    }
+}
+
+/// Most recent entries first, optionally filtered by action.
+#[tauri::command]
+pub fn get_audit_log(
+    app: tauri::AppHandle,
+    action: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<AuditEntry>, String> {
+    let path = crate::store::data_path(&app, AUDIT_FILE)?;
+    let raw = match std::fs::read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("{}: {e}", path.display())),
+    };
+    Ok(raw
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str::<AuditEntry>(line).ok())
+        .filter(|e| action.as_deref().map_or(true, |a| e.action == a))
+        .take(limit.unwrap_or(500))
+        .collect())
+}
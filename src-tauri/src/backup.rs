@@ -0,0 +1,935 @@
+// ── Remote backup targets ──────────────────────────────────────────────────
+// Copies finished backup archives to an S3-compatible bucket (AWS, B2, R2,
+// MinIO, Wasabi) or a WebDAV folder (Nextcloud, ownCloud, any RFC 4918
+// server). The archive is written locally first and `upload_backup` ships
+// it; the local file is never touched, so a network failure only queues a
+// retry with backoff.
+//
+// Archives larger than one part go up in pieces: an S3 multipart upload, or
+// Nextcloud's chunked upload API when the WebDAV URL is a Nextcloud files
+// endpoint. Finished parts are checkpointed in the queue, so a retry resumes
+// instead of starting over. Each upload is verified with a HEAD (size, plus
+// the SHA-256 attached as object metadata on S3) before the remote copies
+// this app made are pruned down to `keep`.
+//
+// Access keys and passwords live in the OS keychain; the stores only hold
+// the non-secret half of the config.
+
+use crate::request_context::{CancelToken, Finished, RequestContext};
+use hmac::{Hmac, Mac};
+use reqwest::{Method, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+const TARGET_STORE: &str = "backup_target";
+const QUEUE_STORE: &str = "backup_uploads";
+const COPIES_STORE: &str = "backup_remote_copies";
+const KEYRING_SERVICE: &str = "FlipTools backup";
+const KEYRING_USER: &str = "remote-target";
+/// S3's minimum part size is 5 MiB; Nextcloud accepts anything.
+const PART_SIZE: u64 = 8 * 1024 * 1024;
+const DEFAULT_KEEP: u32 = 7;
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+const MAX_RETRY_DELAY_SECS: u64 = 6 * 60 * 60;
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetKind {
+    S3,
+    Webdav,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct S3Target {
+    /// `https://s3.eu-west-2.amazonaws.com`, `https://<account>.r2.cloudflarestorage.com`, ...
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    /// Key prefix, e.g. `fliptools/`.
+    #[serde(default)]
+    pub prefix: String,
+    /// `endpoint/bucket/key` rather than `bucket.endpoint/key`; MinIO and
+    /// most self-hosted servers need this.
+    #[serde(default)]
+    pub path_style: bool,
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WebDavTarget {
+    /// Folder the archives go in, e.g.
+    /// `https://cloud.example.com/remote.php/dav/files/me/Backups`.
+    pub url: String,
+    pub username: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Target {
+    S3(S3Target),
+    Webdav(WebDavTarget),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TargetSettings {
+    #[serde(flatten)]
+    pub target: Target,
+    /// Remote copies to keep; older ones this app uploaded are deleted after
+    /// each successful upload.
+    pub keep: u32,
+}
+
+impl TargetSettings {
+    fn kind(&self) -> TargetKind {
+        match self.target {
+            Target::S3(_) => TargetKind::S3,
+            Target::Webdav(_) => TargetKind::Webdav,
+        }
+    }
+}
+
+/// Kept in the keychain as JSON.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Credentials {
+    S3 { access_key_id: String, secret_access_key: String },
+    Webdav { password: String },
+}
+
+/// Where an interrupted upload got to.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Resume {
+    #[default]
+    Fresh,
+    /// S3 multipart upload: part number -> ETag for the parts already sent.
+    S3 { upload_id: String, parts: Vec<(u64, String)> },
+    /// Nextcloud chunked upload folder and the chunks already sent.
+    Chunked { transfer_id: String, chunks_done: u64 },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PendingUpload {
+    pub archive_path: String,
+    pub remote_name: String,
+    pub size: u64,
+    pub sha256: String,
+    pub attempts: u32,
+    pub next_attempt_at: u64,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(default)]
+    resume: Resume,
+}
+
+#[derive(Clone, Serialize)]
+pub struct UploadOutcome {
+    archive_path: String,
+    remote_name: String,
+    /// `uploaded`, `queued` or `cancelled` (still queued).
+    status: &'static str,
+    error: Option<String>,
+    next_attempt_at: Option<u64>,
+    pruned: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct TargetTest {
+    write_ms: u64,
+    read_ms: u64,
+    delete_ms: u64,
+}
+
+pub struct BackupState {
+    target: Mutex<Option<TargetSettings>>,
+    queue: Mutex<Vec<PendingUpload>>,
+    /// Remote names this app uploaded to the current target, oldest first.
+    copies: Mutex<Vec<String>>,
+    /// One upload at a time, so the retry job never races a manual upload.
+    busy: tokio::sync::Mutex<()>,
+}
+
+impl BackupState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        BackupState {
+            target: Mutex::new(crate::store::load(app, TARGET_STORE)),
+            queue: Mutex::new(crate::store::load(app, QUEUE_STORE)),
+            copies: Mutex::new(crate::store::load(app, COPIES_STORE)),
+            busy: tokio::sync::Mutex::new(()),
+        }
+    }
+}
+
+// ── Keychain ───────────────────────────────────────────────────────────────
+
+fn keychain() -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER).map_err(|e| format!("keychain: {e}"))
+}
+
+fn load_credentials() -> Result<Credentials, String> {
+    let raw = keychain()?.get_password().map_err(|e| match e {
+        keyring::Error::NoEntry => "No backup credentials in the keychain; configure the target again".to_string(),
+        e => format!("keychain: {e}"),
+    })?;
+    serde_json::from_str(&raw).map_err(|e| format!("keychain: unreadable backup credentials: {e}"))
+}
+
+// ── Helpers ────────────────────────────────────────────────────────────────
+
+fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(120))
+        .build()
+        .map_err(|e| format!("client build: {e}"))
+}
+
+/// RFC 3986 percent-encoding of everything but unreserved characters, as
+/// SigV4 requires.
+fn uri_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn xml_tag(body: &str, tag: &str) -> Option<String> {
+    let start = body.find(&format!("<{tag}>"))? + tag.len() + 2;
+    let end = start + body[start..].find(&format!("</{tag}>"))?;
+    Some(crate::links::decode_entities(&body[start..end]))
+}
+
+fn header(resp: &reqwest::Response, name: &str) -> Option<String> {
+    resp.headers().get(name)?.to_str().ok().map(str::to_string)
+}
+
+/// Turn a non-2xx response into an error carrying the server's message.
+async fn check(resp: Result<reqwest::Response, reqwest::Error>, what: &str) -> Result<reqwest::Response, String> {
+    let resp = resp.map_err(|e| format!("{what}: {e}"))?;
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let body = resp.text().await.unwrap_or_default();
+    let detail = xml_tag(&body, "Message").unwrap_or_else(|| body.chars().take(200).collect());
+    Err(format!("{what}: HTTP {status} {}", detail.trim()))
+}
+
+fn read_chunk(path: &str, offset: u64, len: u64) -> Result<Vec<u8>, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("{path}: {e}"))?;
+    file.seek(SeekFrom::Start(offset)).map_err(|e| format!("{path}: {e}"))?;
+    let mut buf = Vec::with_capacity(len as usize);
+    file.take(len).read_to_end(&mut buf).map_err(|e| format!("{path}: {e}"))?;
+    Ok(buf)
+}
+
+fn file_sha256(path: &str) -> Result<String, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("{path}: {e}"))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| format!("{path}: {e}"))?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn checkpoint(app: &tauri::AppHandle, pending: &PendingUpload) {
+    let state = app.state::<BackupState>();
+    let mut queue = state.queue.lock().unwrap();
+    match queue.iter_mut().find(|p| p.archive_path == pending.archive_path) {
+        Some(entry) => *entry = pending.clone(),
+        None => queue.push(pending.clone()),
+    }
+    if let Err(e) = crate::store::save(app, QUEUE_STORE, &*queue) {
+        log::warn!("saving backup upload queue failed: {e}");
+    }
+}
+
+fn emit_progress(app: &tauri::AppHandle, pending: &PendingUpload, uploaded: u64) {
+    let _ = app.emit(
+        "backup-upload-progress",
+        json!({ "archive_path": pending.archive_path, "uploaded": uploaded.min(pending.size), "total": pending.size }),
+    );
+}
+
+/// What a HEAD on the remote copy reports.
+struct RemoteObject {
+    size: Option<u64>,
+    sha256: Option<String>,
+}
+
+// ── S3 ─────────────────────────────────────────────────────────────────────
+
+struct S3<'a> {
+    target: &'a S3Target,
+    access_key_id: &'a str,
+    secret_access_key: &'a str,
+    client: reqwest::Client,
+}
+
+impl S3<'_> {
+    fn key(&self, name: &str) -> String {
+        format!("{}{name}", self.target.prefix)
+    }
+
+    /// Build a SigV4-signed request. `headers` are signed along with host,
+    /// date and payload hash.
+    fn signed(
+        &self,
+        method: Method,
+        key: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, &str)],
+        body: Vec<u8>,
+    ) -> Result<reqwest::RequestBuilder, String> {
+        let endpoint = Url::parse(&self.target.endpoint).map_err(|e| format!("S3 endpoint: {e}"))?;
+        let base_host = endpoint.host_str().ok_or("S3 endpoint has no host")?;
+        let port = endpoint.port().map(|p| format!(":{p}")).unwrap_or_default();
+        let encoded_key = key.split('/').map(uri_encode).collect::<Vec<_>>().join("/");
+        let (host, path) = if self.target.path_style {
+            let path = if key.is_empty() {
+                format!("/{}", self.target.bucket)
+            } else {
+                format!("/{}/{encoded_key}", self.target.bucket)
+            };
+            (format!("{base_host}{port}"), path)
+        } else {
+            (format!("{}.{base_host}{port}", self.target.bucket), format!("/{encoded_key}"))
+        };
+
+        let mut query: Vec<(String, String)> = query.iter().map(|(k, v)| (uri_encode(k), uri_encode(v))).collect();
+        query.sort();
+        let query = query.iter().map(|(k, v)| format!("{k}={v}")).collect::<Vec<_>>().join("&");
+
+        let iso = crate::netlog::iso8601(crate::netlog::unix_millis());
+        let amz_date = format!(
+            "{}{}{}T{}{}{}Z",
+            &iso[0..4], &iso[5..7], &iso[8..10], &iso[11..13], &iso[14..16], &iso[17..19]
+        );
+        let date = &amz_date[..8];
+        let payload_hash = hex::encode(Sha256::digest(&body));
+
+        let mut signed: Vec<(String, String)> =
+            headers.iter().map(|(k, v)| (k.to_ascii_lowercase(), v.trim().to_string())).collect();
+        signed.push(("host".to_string(), host.clone()));
+        signed.push(("x-amz-content-sha256".to_string(), payload_hash.clone()));
+        signed.push(("x-amz-date".to_string(), amz_date.clone()));
+        signed.sort();
+        let canonical_headers: String = signed.iter().map(|(k, v)| format!("{k}:{v}\n")).collect();
+        let names = signed.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>().join(";");
+        let canonical = format!("{method}\n{path}\n{query}\n{canonical_headers}\n{names}\n{payload_hash}");
+
+        let scope = format!("{date}/{}/s3/aws4_request", self.target.region);
+        let to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical.as_bytes()))
+        );
+        let mut signing_key = hmac(format!("AWS4{}", self.secret_access_key).as_bytes(), date.as_bytes());
+        for part in [self.target.region.as_str(), "s3", "aws4_request"] {
+            signing_key = hmac(&signing_key, part.as_bytes());
+        }
+        let signature = hex::encode(hmac(&signing_key, to_sign.as_bytes()));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={names}, Signature={signature}",
+            self.access_key_id
+        );
+
+        let query = if query.is_empty() { String::new() } else { format!("?{query}") };
+        let mut req = self
+            .client
+            .request(method, format!("{}://{host}{path}{query}", endpoint.scheme()))
+            .header("authorization", authorization);
+        for (k, v) in signed.iter().filter(|(k, _)| k != "host") {
+            req = req.header(k.as_str(), v.as_str());
+        }
+        if !body.is_empty() {
+            req = req.body(body);
+        }
+        Ok(req)
+    }
+
+    async fn put(&self, name: &str, body: Vec<u8>, sha256: &str) -> Result<(), String> {
+        let req = self.signed(Method::PUT, &self.key(name), &[], &[("x-amz-meta-sha256", sha256)], body)?;
+        check(req.send().await, "upload").await.map(drop)
+    }
+
+    async fn get(&self, name: &str) -> Result<Vec<u8>, String> {
+        let req = self.signed(Method::GET, &self.key(name), &[], &[], Vec::new())?;
+        let resp = check(req.send().await, "download").await?;
+        resp.bytes().await.map(|b| b.to_vec()).map_err(|e| format!("download: {e}"))
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), String> {
+        let req = self.signed(Method::DELETE, &self.key(name), &[], &[], Vec::new())?;
+        check(req.send().await, "delete").await.map(drop)
+    }
+
+    async fn head(&self, name: &str) -> Result<Option<RemoteObject>, String> {
+        let req = self.signed(Method::HEAD, &self.key(name), &[], &[], Vec::new())?;
+        let resp = req.send().await.map_err(|e| format!("verify: {e}"))?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let resp = check(Ok(resp), "verify").await?;
+        Ok(Some(RemoteObject {
+            size: header(&resp, "content-length").and_then(|v| v.parse().ok()),
+            sha256: header(&resp, "x-amz-meta-sha256"),
+        }))
+    }
+
+    async fn upload(&self, app: &tauri::AppHandle, pending: &mut PendingUpload) -> Result<(), String> {
+        if pending.size <= PART_SIZE {
+            let body = read_chunk(&pending.archive_path, 0, pending.size)?;
+            self.put(&pending.remote_name, body, &pending.sha256).await?;
+            emit_progress(app, pending, pending.size);
+            return Ok(());
+        }
+        let key = self.key(&pending.remote_name);
+        let (upload_id, mut parts) = match &pending.resume {
+            Resume::S3 { upload_id, parts } => (upload_id.clone(), parts.clone()),
+            _ => {
+                let req = self.signed(
+                    Method::POST,
+                    &key,
+                    &[("uploads", "")],
+                    &[("x-amz-meta-sha256", &pending.sha256)],
+                    Vec::new(),
+                )?;
+                let body = check(req.send().await, "start upload").await?.text().await.unwrap_or_default();
+                let id = xml_tag(&body, "UploadId").ok_or("start upload: no UploadId in the response")?;
+                pending.resume = Resume::S3 { upload_id: id.clone(), parts: Vec::new() };
+                checkpoint(app, pending);
+                (id, Vec::new())
+            }
+        };
+
+        let total = pending.size.div_ceil(PART_SIZE);
+        for number in (parts.len() as u64 + 1)..=total {
+            let offset = (number - 1) * PART_SIZE;
+            let body = read_chunk(&pending.archive_path, offset, PART_SIZE)?;
+            let part = number.to_string();
+            let req = self.signed(Method::PUT, &key, &[("partNumber", &part), ("uploadId", &upload_id)], &[], body)?;
+            let resp = req.send().await.map_err(|e| format!("upload part {number}: {e}"))?;
+            if resp.status() == StatusCode::NOT_FOUND {
+                // The server dropped the multipart upload (expired or
+                // aborted by a lifecycle rule); start over next attempt.
+                pending.resume = Resume::Fresh;
+                return Err("upload session expired on the server".to_string());
+            }
+            let resp = check(Ok(resp), &format!("upload part {number}")).await?;
+            let etag = header(&resp, "etag").ok_or_else(|| format!("upload part {number}: no ETag"))?;
+            parts.push((number, etag));
+            pending.resume = Resume::S3 { upload_id: upload_id.clone(), parts: parts.clone() };
+            checkpoint(app, pending);
+            emit_progress(app, pending, offset + PART_SIZE);
+        }
+
+        let manifest: String = parts
+            .iter()
+            .map(|(n, etag)| format!("<Part><PartNumber>{n}</PartNumber><ETag>{etag}</ETag></Part>"))
+            .collect();
+        let body = format!("<CompleteMultipartUpload>{manifest}</CompleteMultipartUpload>").into_bytes();
+        let req = self.signed(Method::POST, &key, &[("uploadId", &upload_id)], &[], body)?;
+        let resp = check(req.send().await, "finish upload").await?;
+        // S3 can report a failed completion inside a 200 response.
+        let text = resp.text().await.unwrap_or_default();
+        if text.contains("<Error>") {
+            let detail = xml_tag(&text, "Message").unwrap_or_default();
+            return Err(format!("finish upload: {detail}"));
+        }
+        pending.resume = Resume::Fresh;
+        Ok(())
+    }
+}
+
+// ── WebDAV ─────────────────────────────────────────────────────────────────
+
+struct Dav<'a> {
+    target: &'a WebDavTarget,
+    password: &'a str,
+    client: reqwest::Client,
+}
+
+impl Dav<'_> {
+    fn url(&self, name: &str) -> String {
+        format!("{}/{}", self.target.url.trim_end_matches('/'), uri_encode(name))
+    }
+
+    fn request(&self, method: Method, url: &str) -> reqwest::RequestBuilder {
+        self.client.request(method, url).basic_auth(&self.target.username, Some(self.password))
+    }
+
+    /// Nextcloud's upload collection for this user, when the target is a
+    /// Nextcloud/ownCloud files URL.
+    fn uploads_base(&self) -> Option<String> {
+        let (origin, rest) = self.target.url.split_once("/remote.php/dav/files/")?;
+        let user = rest.split('/').next().filter(|u| !u.is_empty())?;
+        Some(format!("{origin}/remote.php/dav/uploads/{user}"))
+    }
+
+    async fn put(&self, name: &str, body: Vec<u8>) -> Result<(), String> {
+        check(self.request(Method::PUT, &self.url(name)).body(body).send().await, "upload").await.map(drop)
+    }
+
+    async fn get(&self, name: &str) -> Result<Vec<u8>, String> {
+        let resp = check(self.request(Method::GET, &self.url(name)).send().await, "download").await?;
+        resp.bytes().await.map(|b| b.to_vec()).map_err(|e| format!("download: {e}"))
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), String> {
+        check(self.request(Method::DELETE, &self.url(name)).send().await, "delete").await.map(drop)
+    }
+
+    async fn head(&self, name: &str) -> Result<Option<RemoteObject>, String> {
+        let resp = self.request(Method::HEAD, &self.url(name)).send().await.map_err(|e| format!("verify: {e}"))?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let resp = check(Ok(resp), "verify").await?;
+        Ok(Some(RemoteObject { size: header(&resp, "content-length").and_then(|v| v.parse().ok()), sha256: None }))
+    }
+
+    async fn upload(&self, app: &tauri::AppHandle, pending: &mut PendingUpload) -> Result<(), String> {
+        let base = match self.uploads_base() {
+            Some(base) if pending.size > PART_SIZE => base,
+            _ => {
+                let body = read_chunk(&pending.archive_path, 0, pending.size)?;
+                self.put(&pending.remote_name, body).await?;
+                emit_progress(app, pending, pending.size);
+                return Ok(());
+            }
+        };
+        let (transfer_id, done) = match &pending.resume {
+            Resume::Chunked { transfer_id, chunks_done } => (transfer_id.clone(), *chunks_done),
+            _ => {
+                let id = format!("fliptools-{}", hex::encode(rand::random::<[u8; 8]>()));
+                let mkcol = Method::from_bytes(b"MKCOL").expect("valid method");
+                check(self.request(mkcol, &format!("{base}/{id}")).send().await, "start upload").await?;
+                pending.resume = Resume::Chunked { transfer_id: id.clone(), chunks_done: 0 };
+                checkpoint(app, pending);
+                (id, 0)
+            }
+        };
+
+        let folder = format!("{base}/{transfer_id}");
+        let total = pending.size.div_ceil(PART_SIZE);
+        for index in done..total {
+            let offset = index * PART_SIZE;
+            let body = read_chunk(&pending.archive_path, offset, PART_SIZE)?;
+            // Chunks are assembled in name order.
+            let url = format!("{folder}/{:05}", index + 1);
+            let resp = self.request(Method::PUT, &url).body(body).send().await;
+            let resp = resp.map_err(|e| format!("upload chunk {}: {e}", index + 1))?;
+            if resp.status() == StatusCode::NOT_FOUND {
+                // Nextcloud cleans up stale upload folders.
+                pending.resume = Resume::Fresh;
+                return Err("upload session expired on the server".to_string());
+            }
+            check(Ok(resp), &format!("upload chunk {}", index + 1)).await?;
+            pending.resume = Resume::Chunked { transfer_id: transfer_id.clone(), chunks_done: index + 1 };
+            checkpoint(app, pending);
+            emit_progress(app, pending, offset + PART_SIZE);
+        }
+
+        let assemble = Method::from_bytes(b"MOVE").expect("valid method");
+        let req = self
+            .request(assemble, &format!("{folder}/.file"))
+            .header("Destination", self.url(&pending.remote_name))
+            .header("OC-Total-Length", pending.size.to_string())
+            .header("Overwrite", "T");
+        check(req.send().await, "finish upload").await?;
+        pending.resume = Resume::Fresh;
+        Ok(())
+    }
+}
+
+// ── Dispatch ───────────────────────────────────────────────────────────────
+
+enum Remote<'a> {
+    S3(S3<'a>),
+    Dav(Dav<'a>),
+}
+
+impl<'a> Remote<'a> {
+    fn new(settings: &'a TargetSettings, credentials: &'a Credentials) -> Result<Self, String> {
+        let client = client()?;
+        match (&settings.target, credentials) {
+            (Target::S3(target), Credentials::S3 { access_key_id, secret_access_key }) => {
+                Ok(Remote::S3(S3 { target, access_key_id, secret_access_key, client }))
+            }
+            (Target::Webdav(target), Credentials::Webdav { password }) => {
+                Ok(Remote::Dav(Dav { target, password, client }))
+            }
+            _ => Err("Keychain credentials don't match the backup target; configure it again".to_string()),
+        }
+    }
+
+    async fn put(&self, name: &str, body: Vec<u8>) -> Result<(), String> {
+        match self {
+            Remote::S3(s3) => {
+                let sha = hex::encode(Sha256::digest(&body));
+                s3.put(name, body, &sha).await
+            }
+            Remote::Dav(dav) => dav.put(name, body).await,
+        }
+    }
+
+    async fn get(&self, name: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Remote::S3(s3) => s3.get(name).await,
+            Remote::Dav(dav) => dav.get(name).await,
+        }
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), String> {
+        match self {
+            Remote::S3(s3) => s3.delete(name).await,
+            Remote::Dav(dav) => dav.delete(name).await,
+        }
+    }
+
+    async fn head(&self, name: &str) -> Result<Option<RemoteObject>, String> {
+        match self {
+            Remote::S3(s3) => s3.head(name).await,
+            Remote::Dav(dav) => dav.head(name).await,
+        }
+    }
+
+    async fn upload(&self, app: &tauri::AppHandle, pending: &mut PendingUpload) -> Result<(), String> {
+        match self {
+            Remote::S3(s3) => s3.upload(app, pending).await,
+            Remote::Dav(dav) => dav.upload(app, pending).await,
+        }
+    }
+
+    /// HEAD the uploaded copy and compare it with the local archive.
+    async fn verify(&self, pending: &PendingUpload) -> Result<(), String> {
+        let remote = self
+            .head(&pending.remote_name)
+            .await?
+            .ok_or_else(|| format!("verify: {} is missing after upload", pending.remote_name))?;
+        if let Some(size) = remote.size.filter(|s| *s != pending.size) {
+            return Err(format!("verify: remote copy is {size} bytes, expected {}", pending.size));
+        }
+        if let Some(sha) = remote.sha256.filter(|s| !s.eq_ignore_ascii_case(&pending.sha256)) {
+            return Err(format!("verify: remote checksum {sha} doesn't match {}", pending.sha256));
+        }
+        Ok(())
+    }
+}
+
+// ── Uploads ────────────────────────────────────────────────────────────────
+
+fn retry_delay(attempts: u32) -> u64 {
+    60u64.saturating_mul(1 << attempts.min(16)).min(MAX_RETRY_DELAY_SECS)
+}
+
+/// Record the upload, then delete the oldest copies beyond `keep`. Pruning
+/// failures are logged and the copy stays listed for the next pass.
+async fn prune(app: &tauri::AppHandle, remote: &Remote<'_>, keep: u32, uploaded: &str) -> Vec<String> {
+    let state = app.state::<BackupState>();
+    let excess = {
+        let mut copies = state.copies.lock().unwrap();
+        copies.retain(|c| c != uploaded);
+        copies.push(uploaded.to_string());
+        let excess = copies.len().saturating_sub(keep as usize);
+        copies[..excess].to_vec()
+    };
+    let mut pruned = Vec::new();
+    for name in excess {
+        match remote.delete(&name).await {
+            Ok(()) => pruned.push(name),
+            // Already gone (deleted by hand or by a bucket lifecycle rule).
+            Err(e) if e.contains("HTTP 404") => pruned.push(name),
+            Err(e) => log::warn!("pruning remote backup {name} failed: {e}"),
+        }
+    }
+    let mut copies = state.copies.lock().unwrap();
+    copies.retain(|c| !pruned.contains(c));
+    if let Err(e) = crate::store::save(app, COPIES_STORE, &*copies) {
+        log::warn!("saving remote backup list failed: {e}");
+    }
+    pruned
+}
+
+/// `fut`, raced against `cancel` when there is one.
+async fn race<F: std::future::Future>(cancel: Option<&CancelToken>, fut: F) -> Result<F::Output, String> {
+    match cancel {
+        Some(token) => token.run(fut).await,
+        None => Ok(fut.await),
+    }
+}
+
+/// One upload attempt. On failure the upload stays queued with its resume
+/// point and a backoff; the local archive is left alone either way. With a
+/// `cancel` token the wait for the queue, the upload and the check can be
+/// cut short; a cancelled upload stays queued too, without counting as a
+/// failed attempt, and resumes from its last finished part.
+async fn attempt(app: &tauri::AppHandle, mut pending: PendingUpload, cancel: Option<&CancelToken>) -> UploadOutcome {
+    let state = app.state::<BackupState>();
+    let busy = race(cancel, state.busy.lock()).await;
+    let result = match &busy {
+        Err(e) => Err(e.clone()),
+        Ok(_) => {
+            async {
+                let settings = state.target.lock().unwrap().clone().ok_or("No backup target configured")?;
+                let credentials = load_credentials()?;
+                let remote = Remote::new(&settings, &credentials)?;
+                race(cancel, remote.upload(app, &mut pending)).await??;
+                if let Err(e) = race(cancel, remote.verify(&pending)).await? {
+                    pending.resume = Resume::Fresh;
+                    return Err(e);
+                }
+                Ok::<_, String>(prune(app, &remote, settings.keep, &pending.remote_name).await)
+            }
+            .await
+        }
+    };
+
+    let outcome = match result {
+        Ok(pruned) => {
+            let mut queue = state.queue.lock().unwrap();
+            queue.retain(|p| p.archive_path != pending.archive_path);
+            if let Err(e) = crate::store::save(app, QUEUE_STORE, &*queue) {
+                log::warn!("saving backup upload queue failed: {e}");
+            }
+            UploadOutcome {
+                archive_path: pending.archive_path.clone(),
+                remote_name: pending.remote_name.clone(),
+                status: "uploaded",
+                error: None,
+                next_attempt_at: None,
+                pruned,
+            }
+        }
+        Err(e) => {
+            let cancelled = cancel.is_some_and(CancelToken::is_cancelled);
+            if cancelled {
+                log::info!("backup upload of {} cancelled", pending.archive_path);
+            } else {
+                log::warn!("backup upload of {} failed: {e}", pending.archive_path);
+                pending.attempts += 1;
+            }
+            pending.next_attempt_at = crate::unix_now() + retry_delay(pending.attempts.max(1));
+            pending.last_error = Some(e.clone());
+            checkpoint(app, &pending);
+            UploadOutcome {
+                archive_path: pending.archive_path.clone(),
+                remote_name: pending.remote_name.clone(),
+                status: if cancelled { "cancelled" } else { "queued" },
+                error: Some(e),
+                next_attempt_at: Some(pending.next_attempt_at),
+                pruned: Vec::new(),
+            }
+        }
+    };
+    crate::audit::record(
+        app,
+        "backup_upload",
+        None,
+        false,
+        json!({ "archive": outcome.remote_name, "status": outcome.status, "error": outcome.error, "pruned": outcome.pruned }),
+    );
+    let _ = app.emit("backup-upload", &outcome);
+    outcome
+}
+
+/// Retry queued uploads whose backoff has elapsed.
+pub fn start_retry_job(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(RETRY_INTERVAL).await;
+            let now = crate::unix_now();
+            let due: Vec<PendingUpload> = {
+                let state = app.state::<BackupState>();
+                let mut queue = state.queue.lock().unwrap();
+                let before = queue.len();
+                // An archive removed locally (retention, or by hand) can't be
+                // sent any more.
+                queue.retain(|p| Path::new(&p.archive_path).is_file());
+                if queue.len() != before {
+                    let _ = crate::store::save(&app, QUEUE_STORE, &*queue);
+                }
+                queue.iter().filter(|p| p.next_attempt_at <= now).cloned().collect()
+            };
+            for pending in due {
+                attempt(&app, pending, None).await;
+            }
+        }
+    });
+}
+
+// ── Commands ───────────────────────────────────────────────────────────────
+
+/// Set the remote target. `config` carries the target fields plus its
+/// secrets (`access_key_id`/`secret_access_key` for S3, `password` for
+/// WebDAV); omit the secrets to keep the ones already in the keychain.
+#[tauri::command]
+pub fn configure_backup_target(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, BackupState>,
+    kind: TargetKind,
+    config: Value,
+) -> Result<TargetSettings, String> {
+    let Value::Object(mut config) = config else {
+        return Err("Backup target config must be an object".to_string());
+    };
+    config.insert("kind".to_string(), serde_json::to_value(kind).map_err(|e| e.to_string())?);
+    config.entry("keep").or_insert(json!(DEFAULT_KEEP));
+    let config = Value::Object(config);
+    let settings: TargetSettings =
+        serde_json::from_value(config.clone()).map_err(|e| format!("Invalid backup target: {e}"))?;
+    if settings.keep == 0 {
+        return Err("Keep at least one remote copy".to_string());
+    }
+    let url = match &settings.target {
+        Target::S3(t) if t.bucket.trim().is_empty() => return Err("Bucket is required".to_string()),
+        Target::S3(t) if t.prefix.starts_with('/') => return Err("Key prefix must not start with '/'".to_string()),
+        Target::S3(t) => &t.endpoint,
+        Target::Webdav(t) => &t.url,
+    };
+    let parsed = Url::parse(url).map_err(|e| format!("Invalid URL {url:?}: {e}"))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("Backup target URL must be http(s)".to_string());
+    }
+
+    let existing = state.target.lock().unwrap().clone();
+    let same_kind = existing.as_ref().is_some_and(|e| e.kind() == kind);
+    match serde_json::from_value::<Credentials>(config) {
+        Ok(credentials) => {
+            let raw = serde_json::to_string(&credentials).map_err(|e| e.to_string())?;
+            keychain()?.set_password(&raw).map_err(|e| format!("keychain: {e}"))?;
+        }
+        Err(_) if same_kind => {}
+        Err(_) => return Err("Credentials are required for a new backup target".to_string()),
+    }
+
+    // Copies on a different target aren't ours to prune.
+    let same_target = existing.as_ref().is_some_and(|e| match (&e.target, &settings.target) {
+        (Target::S3(a), Target::S3(b)) => a.endpoint == b.endpoint && a.bucket == b.bucket && a.prefix == b.prefix,
+        (Target::Webdav(a), Target::Webdav(b)) => a.url == b.url,
+        _ => false,
+    });
+    if !same_target {
+        let mut copies = state.copies.lock().unwrap();
+        copies.clear();
+        crate::store::save(&app, COPIES_STORE, &*copies)?;
+    }
+    *state.target.lock().unwrap() = Some(settings.clone());
+    crate::store::save(&app, TARGET_STORE, &Some(settings.clone()))?;
+    Ok(settings)
+}
+
+#[tauri::command]
+pub fn get_backup_target(state: tauri::State<'_, BackupState>) -> Option<TargetSettings> {
+    state.target.lock().unwrap().clone()
+}
+
+/// Write, read back and delete a small object on the configured target.
+#[tauri::command]
+pub async fn test_backup_target(app: tauri::AppHandle) -> Result<TargetTest, String> {
+    let settings = app.state::<BackupState>().target.lock().unwrap().clone().ok_or("No backup target configured")?;
+    let credentials = load_credentials()?;
+    let remote = Remote::new(&settings, &credentials)?;
+    let token = hex::encode(rand::random::<[u8; 16]>());
+    let name = format!(".fliptools-test-{token}.txt");
+    let payload = format!("FlipTools backup target test {token}").into_bytes();
+
+    let started = std::time::Instant::now();
+    remote.put(&name, payload.clone()).await?;
+    let write_ms = started.elapsed().as_millis() as u64;
+
+    let started = std::time::Instant::now();
+    let read = remote.get(&name).await;
+    let read_ms = started.elapsed().as_millis() as u64;
+    let started = std::time::Instant::now();
+    let deleted = remote.delete(&name).await;
+    let delete_ms = started.elapsed().as_millis() as u64;
+
+    if read? != payload {
+        return Err("Read back different bytes than were written".to_string());
+    }
+    deleted?;
+    Ok(TargetTest { write_ms, read_ms, delete_ms })
+}
+
+/// Ship a finished local backup archive to the remote target. A failed
+/// upload is queued for retry rather than returned as an error. Runs under a
+/// RequestContext (see request_context); a cancelled upload keeps its
+/// finished parts and is picked up again by the retry job or the next call.
+#[tauri::command]
+pub async fn upload_backup(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    archive_path: String,
+    invocation_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<Finished<UploadOutcome>, String> {
+    let ctx = RequestContext::begin(&app, &window, "upload_backup", invocation_id, timeout_ms)?;
+    let result = upload(&app, &ctx, archive_path).await;
+    ctx.finish(result)
+}
+
+async fn upload(app: &tauri::AppHandle, ctx: &RequestContext, archive_path: String) -> Result<UploadOutcome, String> {
+    let path = Path::new(&archive_path);
+    let size = std::fs::metadata(path).map_err(|e| format!("{archive_path}: {e}"))?.len();
+    let remote_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| format!("{archive_path} has no file name"))?
+        .to_string();
+    if app.state::<BackupState>().target.lock().unwrap().is_none() {
+        return Err("No backup target configured".to_string());
+    }
+    let hash_path = archive_path.clone();
+    let sha256 = ctx
+        .run(tauri::async_runtime::spawn_blocking(move || file_sha256(&hash_path)))
+        .await?
+        .map_err(|e| e.to_string())??;
+
+    // Resume an earlier attempt at the same archive if nothing changed.
+    let queued = app
+        .state::<BackupState>()
+        .queue
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|p| p.archive_path == archive_path && p.sha256 == sha256)
+        .cloned();
+    let pending = queued.unwrap_or(PendingUpload {
+        archive_path,
+        remote_name,
+        size,
+        sha256,
+        attempts: 0,
+        next_attempt_at: 0,
+        last_error: None,
+        resume: Resume::Fresh,
+    });
+    checkpoint(app, &pending);
+    let token = ctx.token();
+    let outcome = attempt(app, pending, Some(&token)).await;
+    // Err so `finish` reports the cancellation; the outcome went out as a
+    // "backup-upload" event already.
+    ctx.check()?;
+    Ok(outcome)
+}
+
+#[tauri::command]
+pub fn get_pending_backup_uploads(state: tauri::State<'_, BackupState>) -> Vec<PendingUpload> {
+    state.queue.lock().unwrap().clone()
+}
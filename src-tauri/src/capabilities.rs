@@ -0,0 +1,431 @@
+// ── Command capabilities ───────────────────────────────────────────────────
+// Every command is declared with the capability it needs, and each window
+// is granted a set of capabilities. `guarded` wraps the invoke handler and
+// refuses a command the calling window hasn't been granted, before any
+// handler code runs, logging the window's label. The main window has them
+// all; the Depop login window (and any window added later) only gets
+// `app-info`, so a page loaded there can't reach tokens, files or bulk
+// operations. A command missing from COMMANDS needs `system`.
+//
+// Checked on the webview's label, which Tauri sets from the window that
+// sent the message, not from anything the page says. `get_capability_map`
+// shows the whole matrix and recent refusals on the diagnostics screen.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tauri::ipc::Invoke;
+use tauri::Runtime;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Capability {
+    /// Harmless reads any window may make (version, language, cancelling
+    /// its own requests).
+    AppInfo,
+    /// Inventory, sales, settings and reports.
+    ReadInventory,
+    /// Changes to local data and settings.
+    WriteInventory,
+    /// Requests to marketplaces and the outside world.
+    WriteMarketplace,
+    /// Sessions, cookies, keychain entries and webhook secrets.
+    ManageSecrets,
+    /// Updates, files on disk, plugins and app maintenance.
+    System,
+}
+
+const ALL: &[Capability] = &[
+    Capability::AppInfo,
+    Capability::ReadInventory,
+    Capability::WriteInventory,
+    Capability::WriteMarketplace,
+    Capability::ManageSecrets,
+    Capability::System,
+];
+const MINIMAL: &[Capability] = &[Capability::AppInfo];
+
+/// Capabilities granted to a window, by label.
+pub fn granted(label: &str) -> &'static [Capability] {
+    match label {
+        "main" => ALL,
+        _ => MINIMAL,
+    }
+}
+
+use Capability::*;
+
+/// The capability each command needs.
+const COMMANDS: &[(&str, Capability)] = &[
+    // Updates
+    ("check_for_update", ReadInventory),
+    ("install_update", System),
+    ("download_update", System),
+    ("apply_downloaded_update", System),
+    ("install_update_from_file", System),
+    ("cancel_update", System),
+    ("get_last_update_check", ReadInventory),
+    ("get_pending_update", ReadInventory),
+    ("get_update_settings", ReadInventory),
+    ("set_update_check_interval", System),
+    ("set_update_download_attempts", System),
+    ("get_current_version", AppInfo),
+    ("get_app_status", AppInfo),
+    ("set_privacy_mode", WriteInventory),
+    ("toggle_privacy_mode", WriteInventory),
+    ("get_changelog", AppInfo),
+    ("get_changelog_structured", AppInfo),
+    // Network
+    ("native_fetch", WriteMarketplace),
+    ("native_fetch_stream", System),
+    ("start_sse", WriteMarketplace),
+    ("stop_sse", AppInfo),
+    ("ws_connect", WriteMarketplace),
+    ("ws_send", WriteMarketplace),
+    ("ws_close", AppInfo),
+    ("native_download", System),
+    ("cancel_command", AppInfo),
+    ("cancel_fetch", AppInfo),
+    ("clear_http_cache", System),
+    ("resolve_url", WriteMarketplace),
+    ("get_proxy", ReadInventory),
+    ("set_proxy", ManageSecrets),
+    ("get_rate_limit_status", ReadInventory),
+    ("get_rate_limits", ReadInventory),
+    ("set_rate_limit", System),
+    ("reset_rate_limit", System),
+    ("export_har", System),
+    ("get_network_log_settings", ReadInventory),
+    ("set_network_capture_bodies", System),
+    ("get_cookies", ManageSecrets),
+    ("clear_cookie_jar", ManageSecrets),
+    ("clear_cookies", ManageSecrets),
+    ("save_credential", ManageSecrets),
+    ("get_credential", ManageSecrets),
+    ("delete_credential", ManageSecrets),
+    // Sessions and secrets
+    ("open_depop_login", ManageSecrets),
+    ("restore_depop_session", ManageSecrets),
+    ("navigate_depop_window", ManageSecrets),
+    ("scan_depop_auth", ManageSecrets),
+    ("list_accounts", ReadInventory),
+    ("set_account_metadata", WriteInventory),
+    ("register_inbound_webhook", ManageSecrets),
+    ("list_inbound_webhooks", ReadInventory),
+    ("remove_inbound_webhook", ManageSecrets),
+    ("get_inbound_webhook_log", ReadInventory),
+    ("set_webhook_session", ManageSecrets),
+    ("list_tasks", ReadInventory),
+    ("create_task", WriteInventory),
+    ("set_task_done", WriteInventory),
+    ("delete_task", WriteInventory),
+    ("configure_backup_target", ManageSecrets),
+    ("get_backup_target", ReadInventory),
+    ("test_backup_target", ManageSecrets),
+    ("upload_backup", System),
+    ("get_pending_backup_uploads", ReadInventory),
+    // Marketplaces
+    ("depop_soft_bump", WriteMarketplace),
+    ("get_depop_category_attributes", ReadInventory),
+    ("refresh_depop_category_attributes", WriteMarketplace),
+    ("report_depop_rejection", WriteInventory),
+    ("get_depop_schema_drift", ReadInventory),
+    ("repair_listing_photos", WriteMarketplace),
+    ("download_listing_photos", System),
+    ("depop_fetch_listings", WriteMarketplace),
+    ("depop_fetch_liked", WriteMarketplace),
+    ("refresh_pipeline_prices", WriteMarketplace),
+    ("update_pipeline_status", WriteInventory),
+    ("get_pipeline", ReadInventory),
+    ("ebay_import_active_listings", WriteMarketplace),
+    ("record_depop_offers", WriteInventory),
+    ("list_depop_offers", ReadInventory),
+    ("get_offer_nudge_settings", ReadInventory),
+    ("set_offer_nudge_settings", WriteInventory),
+    ("depop_send_message", WriteMarketplace),
+    ("depop_sync_message_attachments", System),
+    ("get_conversation_attachments", ReadInventory),
+    ("get_message_attachment_settings", ReadInventory),
+    ("set_message_attachment_settings", WriteInventory),
+    ("depop_send_message_with_image", WriteMarketplace),
+    ("depop_upload_video", WriteMarketplace),
+    ("record_depop_receipts", WriteInventory),
+    ("get_boost_report", ReadInventory),
+    ("depop_set_boost", WriteMarketplace),
+    ("depop_fetch_account_health", WriteMarketplace),
+    ("get_account_health", ReadInventory),
+    ("get_health_thresholds", ReadInventory),
+    ("set_health_thresholds", WriteInventory),
+    ("propagate_footer", WriteMarketplace),
+    ("depop_set_international_shipping", WriteMarketplace),
+    ("suggest_international_prices", ReadInventory),
+    ("check_search_rank", WriteMarketplace),
+    ("get_rank_history", ReadInventory),
+    ("track_search_rank", WriteInventory),
+    ("get_tracked_search_ranks", ReadInventory),
+    ("push_marketplace_event", WriteInventory),
+    ("get_marketplace_limits", ReadInventory),
+    ("get_marketplace_limits_snapshot", ReadInventory),
+    ("set_marketplace_limit_overrides", WriteInventory),
+    // Inventory and listings
+    ("allocate_lot_cost", WriteInventory),
+    ("get_inventory_aging", ReadInventory),
+    ("simulate_repricing", ReadInventory),
+    ("suggest_price", ReadInventory),
+    ("check_inventory_prices", ReadInventory),
+    ("scan_listing", ReadInventory),
+    ("list_compliance_rules", ReadInventory),
+    ("add_compliance_rule", WriteInventory),
+    ("remove_compliance_rule", WriteInventory),
+    ("get_high_risk_brand_settings", ReadInventory),
+    ("set_high_risk_brand_settings", WriteInventory),
+    ("set_item_authenticated", WriteInventory),
+    ("preview_listing", ReadInventory),
+    ("approve_listing_publish", WriteInventory),
+    ("process_images", System),
+    ("list_image_presets", ReadInventory),
+    ("save_image_preset", WriteInventory),
+    ("remove_image_preset", WriteInventory),
+    ("set_marketplace_image_preset", WriteInventory),
+    ("set_image_watermark", WriteInventory),
+    ("generate_listing_qr", ReadInventory),
+    ("generate_qr_sheet", System),
+    ("get_qr_settings", ReadInventory),
+    ("set_qr_marketplace_priority", WriteInventory),
+    ("list_write_intents", ReadInventory),
+    ("get_crosslist_matrix", ReadInventory),
+    ("list_custom_fields", ReadInventory),
+    ("define_custom_field", WriteInventory),
+    ("rename_custom_field", WriteInventory),
+    ("set_custom_field_options", WriteInventory),
+    ("remove_custom_field", WriteInventory),
+    ("restore_custom_field", WriteInventory),
+    ("validate_custom_field_values", ReadInventory),
+    ("get_digest_settings", ReadInventory),
+    ("set_digest_settings", WriteInventory),
+    ("flush_notification_digest", WriteInventory),
+    ("get_listing_stats", ReadInventory),
+    ("get_fee_schedules", ReadInventory),
+    ("set_fee_schedule", WriteInventory),
+    ("remove_fee_schedule", WriteInventory),
+    ("calculate_fees", ReadInventory),
+    ("recompute_fees", WriteInventory),
+    ("list_views", ReadInventory),
+    ("save_view", WriteInventory),
+    ("delete_view", WriteInventory),
+    ("apply_view", ReadInventory),
+    ("record_unshipped_sales", WriteInventory),
+    ("record_tracking", WriteInventory),
+    ("get_shipping_deadlines", ReadInventory),
+    ("get_shipping_settings", ReadInventory),
+    ("set_shipping_settings", WriteInventory),
+    ("get_carrier_rates", ReadInventory),
+    ("set_carrier_rates", WriteInventory),
+    ("get_price_rules", ReadInventory),
+    ("set_price_rules", WriteInventory),
+    ("refresh_exchange_rates", WriteMarketplace),
+    ("convert_and_round_price", ReadInventory),
+    ("apply_bulk_discount", WriteInventory),
+    ("get_pricing_rules", ReadInventory),
+    ("create_pricing_rule", WriteInventory),
+    ("preview_pricing_rule", ReadInventory),
+    ("run_due_pricing_rules", WriteMarketplace),
+    ("delete_pricing_rule", WriteInventory),
+    ("get_photo_metadata", ReadInventory),
+    ("set_photo_metadata", WriteInventory),
+    ("set_photos_metadata", WriteInventory),
+    ("get_tax_summary", ReadInventory),
+    ("generate_tax_summary_pdf", System),
+    ("list_consignors", ReadInventory),
+    ("save_consignor", WriteInventory),
+    ("assign_consignor", WriteInventory),
+    ("get_consignor_statement", ReadInventory),
+    ("generate_consignor_statement_pdf", System),
+    ("record_consignor_payout", WriteInventory),
+    ("get_tax_thresholds", ReadInventory),
+    ("set_tax_threshold", WriteInventory),
+    ("get_payout_settings", ReadInventory),
+    ("set_payout_settings", WriteInventory),
+    ("import_bank_csv", System),
+    ("reconcile_payouts", ReadInventory),
+    ("get_payout_reconciliation", ReadInventory),
+    ("match_payout", WriteInventory),
+    ("unmatch_payout", WriteInventory),
+    ("export_payout_reconciliation", System),
+    ("list_import_mappings", ReadInventory),
+    ("save_import_mapping", WriteInventory),
+    ("remove_import_mapping", WriteInventory),
+    ("get_csv_hotfolder", ReadInventory),
+    ("configure_csv_hotfolder", System),
+    ("get_pending_hotfolder_imports", ReadInventory),
+    ("import_hotfolder_batch", WriteInventory),
+    ("record_offline_sale", WriteInventory),
+    ("record_offline_sales", WriteInventory),
+    ("get_session_offline_sales", ReadInventory),
+    ("undo_offline_sale", WriteInventory),
+    ("get_market_mode", ReadInventory),
+    ("set_market_mode", WriteInventory),
+    ("handle_scan", WriteInventory),
+    ("get_marketplace_status", AppInfo),
+    ("set_marketplace_status_page", System),
+    ("get_listing_footers", ReadInventory),
+    ("set_listing_footer", WriteInventory),
+    ("get_sku_settings", ReadInventory),
+    ("set_sku_settings", WriteInventory),
+    ("generate_sku", WriteInventory),
+    ("clone_item", WriteInventory),
+    ("clone_from_listing", WriteMarketplace),
+    ("get_item_variants", ReadInventory),
+    ("assign_photo_groups", WriteInventory),
+    ("get_team_sync_status", ReadInventory),
+    ("export_changes", System),
+    ("import_changes", System),
+    ("get_audit_log", ReadInventory),
+    // App
+    ("get_metrics", ReadInventory),
+    ("record_timing", AppInfo),
+    ("ping_activity", AppInfo),
+    ("get_maintenance_settings", ReadInventory),
+    ("set_maintenance_settings", System),
+    ("run_maintenance_now", System),
+    ("get_clipboard_watch", ReadInventory),
+    ("set_clipboard_watch", System),
+    ("get_retention_settings", ReadInventory),
+    ("set_retention_settings", System),
+    ("preview_retention_run", ReadInventory),
+    ("run_retention", System),
+    ("undo_retention_run", System),
+    ("locate_photos", System),
+    ("analyze_photo_store", ReadInventory),
+    ("clean_photo_store", System),
+    ("get_pending_repoints", ReadInventory),
+    ("ack_photo_repoints", WriteInventory),
+    ("validate_video", ReadInventory),
+    ("transcode_video", System),
+    ("get_ffmpeg_status", ReadInventory),
+    ("get_system_preferences", AppInfo),
+    ("get_app_language", AppInfo),
+    ("set_app_language", WriteInventory),
+    ("list_plugins", ReadInventory),
+    ("set_plugin_enabled", System),
+    ("test_plugin", System),
+    ("generate_plugin_sku", System),
+    ("get_plugin_price_suggestions", System),
+    ("get_capability_map", ReadInventory),
+];
+
+/// The capability `command` needs; `system` for anything undeclared.
+pub fn required(command: &str) -> Capability {
+    COMMANDS.iter().find(|(name, _)| *name == command).map_or(System, |(_, cap)| *cap)
+}
+
+pub fn allowed(label: &str, command: &str) -> bool {
+    granted(label).contains(&required(command))
+}
+
+#[derive(Clone, Serialize)]
+pub struct Violation {
+    pub window: String,
+    pub command: String,
+    pub required: Capability,
+    pub at: u64,
+}
+
+const MAX_VIOLATIONS: usize = 50;
+static VIOLATIONS: Mutex<VecDeque<Violation>> = Mutex::new(VecDeque::new());
+
+/// Wrap `handler` so each invoke is checked against the calling window's
+/// capabilities first. A refused command is answered with an error and never
+/// reaches its handler.
+pub fn guarded<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let label = invoke.message.webview().label().to_string();
+        let command = invoke.message.command().to_string();
+        if allowed(&label, &command) {
+            return handler(invoke);
+        }
+        let required = required(&command);
+        log::warn!("window {label} refused {command}: needs {required:?}");
+        let mut violations = VIOLATIONS.lock().unwrap();
+        if violations.len() >= MAX_VIOLATIONS {
+            violations.pop_front();
+        }
+        violations.push_back(Violation { window: label.clone(), command: command.clone(), required, at: crate::unix_now() });
+        invoke.resolver.reject(format!("Window {label} isn't allowed to call {command}"));
+        true
+    }
+}
+
+#[derive(Serialize)]
+pub struct CommandCapability {
+    pub command: &'static str,
+    pub capability: Capability,
+}
+
+#[derive(Serialize)]
+pub struct WindowGrant {
+    pub window: &'static str,
+    pub capabilities: &'static [Capability],
+}
+
+#[derive(Serialize)]
+pub struct CapabilityMap {
+    pub commands: Vec<CommandCapability>,
+    pub windows: Vec<WindowGrant>,
+    /// What any other window gets.
+    pub default_grant: &'static [Capability],
+    /// Most recent last.
+    pub recent_violations: Vec<Violation>,
+}
+
+#[tauri::command]
+pub fn get_capability_map() -> CapabilityMap {
+    CapabilityMap {
+        commands: COMMANDS.iter().map(|(command, capability)| CommandCapability { command, capability: *capability }).collect(),
+        windows: ["main", "depop-login"].iter().map(|w| WindowGrant { window: w, capabilities: granted(w) }).collect(),
+        default_grant: MINIMAL,
+        recent_violations: VIOLATIONS.lock().unwrap().iter().cloned().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn login_window_cant_reach_secrets() {
+        for command in ["get_credential", "save_credential", "delete_credential", "get_cookies", "open_depop_login"] {
+            assert_eq!(required(command), ManageSecrets, "{command}");
+            assert!(!allowed("depop-login", command), "{command}");
+            assert!(allowed("main", command), "{command}");
+        }
+        assert!(!granted("depop-login").contains(&ManageSecrets));
+        for (command, capability) in COMMANDS {
+            assert_eq!(allowed("depop-login", command), *capability == AppInfo, "{command}");
+        }
+    }
+
+    #[test]
+    fn undeclared_commands_need_system() {
+        assert_eq!(required("no_such_command"), System);
+        assert!(!allowed("depop-login", "no_such_command"));
+        assert!(!allowed("some-new-window", "get_credential"));
+    }
+
+    #[test]
+    fn commands_are_declared_once() {
+        let mut names: Vec<&str> = COMMANDS.iter().map(|(name, _)| *name).collect();
+        names.sort_unstable();
+        let before = names.len();
+        names.dedup();
+        assert_eq!(names.len(), before);
+    }
+
+    #[test]
+    fn writes_arent_declared_as_reads() {
+        assert_eq!(required("assign_photo_groups"), WriteInventory);
+        assert_eq!(required("recompute_fees"), WriteInventory);
+    }
+}
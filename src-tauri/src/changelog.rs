@@ -0,0 +1,97 @@
+// ── Changelog ──────────────────────────────────────────────────────────────
+// CHANGELOG.md is compiled in. `get_changelog_structured` splits it at its
+// `## ` headers so the UI can show what changed since a given version.
+// Headers are Keep a Changelog style (`## [1.2.0] - 2026-03-01`) or this
+// repo's older `## v0.1.0 - Initial Release`; an `Unreleased` section comes
+// through with no version number and is always treated as newest.
+
+use serde::Serialize;
+
+const CHANGELOG: &str = include_str!("../../CHANGELOG.md");
+
+#[derive(Serialize)]
+pub struct ChangelogEntry {
+    /// "1.2.0", or "Unreleased".
+    version: String,
+    /// YYYY-MM-DD when the header has one.
+    date: Option<String>,
+    /// Any other text after the version ("Initial Release").
+    title: Option<String>,
+    body: String,
+}
+
+impl ChangelogEntry {
+    fn semver(&self) -> Option<semver::Version> {
+        semver::Version::parse(&self.version).ok()
+    }
+}
+
+fn is_date(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() == 10
+        && b.iter().enumerate().all(|(i, c)| if i == 4 || i == 7 { *c == b'-' } else { c.is_ascii_digit() })
+}
+
+/// Parse a `## ` header line's text into (version, date, title).
+fn parse_header(header: &str) -> (String, Option<String>, Option<String>) {
+    let header = header.trim();
+    let (version, rest) = match header.strip_prefix('[').and_then(|h| h.split_once(']')) {
+        Some((version, rest)) => (version, rest),
+        None => header.split_at(header.find(char::is_whitespace).unwrap_or(header.len())),
+    };
+    let version = version.trim();
+    let version = version.strip_prefix(['v', 'V']).filter(|v| v.starts_with(|c: char| c.is_ascii_digit())).unwrap_or(version);
+
+    let mut date = None;
+    let mut title = Vec::new();
+    for part in rest.split(" - ").map(|p| p.trim().trim_start_matches('-').trim()).filter(|p| !p.is_empty()) {
+        let bare = part.trim_matches(|c| c == '(' || c == ')');
+        if date.is_none() && is_date(bare) {
+            date = Some(bare.to_string());
+        } else {
+            title.push(part);
+        }
+    }
+    let title = Some(title.join(" - ")).filter(|t| !t.is_empty());
+    (version.to_string(), date, title)
+}
+
+fn parse(text: &str) -> Vec<ChangelogEntry> {
+    let mut entries: Vec<ChangelogEntry> = Vec::new();
+    for line in text.lines() {
+        if let Some(header) = line.strip_prefix("## ") {
+            let (version, date, title) = parse_header(header);
+            entries.push(ChangelogEntry { version, date, title, body: String::new() });
+        } else if let Some(entry) = entries.last_mut() {
+            entry.body.push_str(line);
+            entry.body.push('\n');
+        }
+    }
+    for entry in &mut entries {
+        entry.body = entry.body.trim().to_string();
+    }
+    entries
+}
+
+#[tauri::command]
+pub fn get_changelog() -> String {
+    CHANGELOG.to_string()
+}
+
+/// The changelog as entries, newest first as written. With `since`, only
+/// entries newer than that version (plus Unreleased); entries whose version
+/// doesn't parse are left out then.
+#[tauri::command]
+pub fn get_changelog_structured(since: Option<String>) -> Result<Vec<ChangelogEntry>, String> {
+    let entries = parse(CHANGELOG);
+    let Some(since) = since else {
+        return Ok(entries);
+    };
+    let since = since.trim();
+    let since = semver::Version::parse(since.strip_prefix(['v', 'V']).unwrap_or(since))
+        .map_err(|e| format!("Invalid version {since:?}: {e}"))?;
+    Ok(entries
+        .into_iter()
+        .filter(|e| e.version.eq_ignore_ascii_case("unreleased") || e.semver().is_some_and(|v| v > since))
+        .collect())
+}
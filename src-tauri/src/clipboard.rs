@@ -0,0 +1,112 @@
+// ── Clipboard watcher ──────────────────────────────────────────────────────
+// Opt-in: while enabled, the clipboard is polled and any copied marketplace or
+// auction link is resolved and offered to the UI ("add to watchlist?") via a
+// `clipboard-link-detected` event.
+//
+// Off on every launch — the setting is deliberately not persisted — and every
+// change is broadcast as `clipboard-watch-changed` so the UI can show a
+// persistent indicator while it's on. Clipboard text is only ever compared by
+// hash in memory; nothing that isn't a recognised link is kept or logged.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(1000);
+/// Links already announced this session; copying one again stays quiet.
+const RECENT_LINKS: usize = 100;
+
+#[derive(Default)]
+pub struct ClipboardWatchState {
+    enabled: AtomicBool,
+    /// Bumped on every enable so a previous poll loop notices it's stale.
+    generation: AtomicU64,
+    recent: Mutex<VecDeque<String>>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ClipboardWatchStatus {
+    enabled: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct ClipboardLinkEvent {
+    url: String,
+    resolved: Option<crate::links::ResolvedUrl>,
+    error: Option<String>,
+}
+
+fn digest(text: &str) -> [u8; 32] {
+    Sha256::digest(text.as_bytes()).into()
+}
+
+fn spawn_poller(app: tauri::AppHandle, generation: u64) {
+    tauri::async_runtime::spawn(async move {
+        // Whatever is on the clipboard when watching starts isn't "new".
+        let mut last = app.clipboard().read_text().ok().map(|t| digest(&t));
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let state = app.state::<ClipboardWatchState>();
+            if !state.enabled.load(Ordering::SeqCst) || state.generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            let Ok(text) = app.clipboard().read_text() else {
+                continue;
+            };
+            let hash = digest(&text);
+            if last == Some(hash) {
+                continue;
+            }
+            last = Some(hash);
+
+            let Some(url) = crate::links::sourcing_link(&text) else {
+                continue;
+            };
+            drop(text);
+            {
+                let mut recent = state.recent.lock().unwrap();
+                if recent.contains(&url) {
+                    continue;
+                }
+                recent.push_front(url.clone());
+                recent.truncate(RECENT_LINKS);
+            }
+
+            let (resolved, error) = match crate::links::resolve_url(url.clone()).await {
+                Ok(r) => (Some(r), None),
+                Err(e) => (None, Some(e)),
+            };
+            let _ = app.emit("clipboard-link-detected", ClipboardLinkEvent { url, resolved, error });
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_clipboard_watch(state: tauri::State<'_, ClipboardWatchState>) -> ClipboardWatchStatus {
+    ClipboardWatchStatus { enabled: state.enabled.load(Ordering::SeqCst) }
+}
+
+/// Turn the watcher on or off. Emits "clipboard-watch-changed" with `{ enabled }`.
+#[tauri::command]
+pub fn set_clipboard_watch(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ClipboardWatchState>,
+    enabled: bool,
+) -> ClipboardWatchStatus {
+    let was = state.enabled.swap(enabled, Ordering::SeqCst);
+    if enabled && !was {
+        let generation = state.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        spawn_poller(app.clone(), generation);
+    }
+    if !enabled {
+        state.recent.lock().unwrap().clear();
+    }
+    let status = ClipboardWatchStatus { enabled };
+    let _ = app.emit("clipboard-watch-changed", status.clone());
+    status
+}
@@ -0,0 +1,499 @@
+// ── Item cloning ───────────────────────────────────────────────────────────
+// Ten nearly identical band tees shouldn't mean entering the same data ten
+// times. `clone_item` copies an inventory item, and its listing as a new
+// draft, under a fresh SKU. What describes the kind of item carries over
+// (category, brand, size, colour, description, price, cost, shipping
+// weight, tags); what belongs to the one physical unit doesn't: photos,
+// condition notes and per-unit custom fields (serials, certificates,
+// measurements). Overrides are applied last.
+//
+// `clone_from_listing` starts from one of the seller's live Depop or eBay
+// listings instead, whether or not it was ever in FlipTools. Its photos are
+// downloaded as references and flagged `reference_only` in photo_meta,
+// since relisting another unit with them would misdescribe it.
+//
+// Clones keep a link to their source (migration 020): `cloned_from` points
+// at the original item, never at another clone, so `get_item_variants`
+// finds the whole family in one query. Clones of an outside listing are
+// linked by `cloned_from_listing` ("depop:<id>").
+
+use crate::privacy::Private;
+use crate::supabase::{self, SupabaseSession};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::path::PathBuf;
+
+/// Custom fields whose name contains one of these describe a single unit.
+const PER_UNIT_WORDS: &[&str] = &["serial", "cert", "imei", "measure", "pit to pit", "inseam", "length", "batch"];
+
+/// Replacements for the copied values.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct CloneOverrides {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub price: Option<f64>,
+    pub cost: Option<f64>,
+    pub category: Option<String>,
+    pub condition: Option<String>,
+    pub brand: Option<String>,
+    pub size: Option<String>,
+    pub color: Option<String>,
+    pub quantity: Option<i64>,
+    pub location: Option<String>,
+    /// Custom field id -> value, merged over the copied ones.
+    pub custom_fields: Map<String, Value>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CloneSource {
+    Item { item_id: String, sku: Option<String> },
+    Listing { marketplace: String, listing_id: String },
+}
+
+#[derive(Serialize)]
+pub struct ClonedItem {
+    pub item_id: String,
+    pub sku: String,
+    /// The new draft listing, when one was created.
+    pub listing_id: Option<String>,
+    pub source: CloneSource,
+    /// What wasn't copied because it belongs to the source unit.
+    pub cleared: Vec<String>,
+    /// Custom field id -> value for the clone, per-unit fields removed and
+    /// overrides applied; saved with the item like an import's.
+    pub custom_fields: Map<String, Value>,
+    /// Downloaded photos of the source listing, flagged reference-only.
+    pub reference_photos: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ItemVariant {
+    pub id: String,
+    #[serde(default)]
+    pub sku: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub quantity: Option<i64>,
+    #[serde(default)]
+    pub listing_id: Option<String>,
+    #[serde(default)]
+    pub cloned_from: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+/// The copyable part of an item, whichever source it came from.
+#[derive(Default)]
+struct Template {
+    name: String,
+    description: Option<String>,
+    price: Option<f64>,
+    cost: Option<f64>,
+    category: Option<String>,
+    condition: Option<String>,
+    brand: Option<String>,
+    size: Option<String>,
+    color: Option<String>,
+    shipping_weight: Option<f64>,
+    tags: Vec<String>,
+    location: Option<String>,
+    custom_fields: Map<String, Value>,
+    /// Always 1 unless overridden; the source's stock isn't copied.
+    quantity: i64,
+}
+
+impl Template {
+    fn apply(&mut self, o: CloneOverrides) {
+        let set = |field: &mut Option<String>, value: Option<String>| {
+            if let Some(v) = value {
+                *field = Some(v.trim().to_string()).filter(|v| !v.is_empty());
+            }
+        };
+        if let Some(title) = o.title.map(|t| t.trim().to_string()).filter(|t| !t.is_empty()) {
+            self.name = title;
+        }
+        set(&mut self.description, o.description);
+        set(&mut self.category, o.category);
+        set(&mut self.condition, o.condition);
+        set(&mut self.brand, o.brand);
+        set(&mut self.size, o.size);
+        set(&mut self.color, o.color);
+        set(&mut self.location, o.location);
+        self.price = o.price.or(self.price);
+        self.cost = o.cost.or(self.cost);
+        self.quantity = o.quantity.unwrap_or(1).max(1);
+        self.custom_fields.extend(o.custom_fields);
+    }
+}
+
+fn text(row: &Value, key: &str) -> Option<String> {
+    row.get(key).and_then(Value::as_str).map(str::trim).filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+fn number(row: &Value, key: &str) -> Option<f64> {
+    match row.get(key)? {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// A name from whichever shape a marketplace uses: a string, an object with
+/// a name, or a list of either.
+fn name_of(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        Value::Object(o) => ["name", "title", "value"].iter().find_map(|k| name_of(o.get(*k))),
+        Value::Array(items) => items.iter().find_map(|v| name_of(Some(v))),
+        _ => None,
+    }
+}
+
+/// Drop per-unit custom fields, returning the names of those dropped.
+fn clear_per_unit(
+    values: &mut Map<String, Value>,
+    fields: &[crate::custom_fields::CustomField],
+) -> Vec<String> {
+    let mut cleared = Vec::new();
+    values.retain(|id, _| {
+        let name = fields.iter().find(|f| f.id == *id).map_or(id.as_str(), |f| f.name.as_str());
+        let lower = name.to_lowercase();
+        let per_unit = PER_UNIT_WORDS.iter().any(|w| lower.contains(w));
+        if per_unit {
+            cleared.push(name.to_string());
+        }
+        !per_unit
+    });
+    cleared
+}
+
+async fn rows(req: reqwest::RequestBuilder) -> Result<Vec<Value>, String> {
+    match supabase::send(req).await? {
+        Value::Array(rows) => Ok(rows),
+        Value::Null => Ok(Vec::new()),
+        other => Ok(vec![other]),
+    }
+}
+
+async fn insert(session: &SupabaseSession, client: &reqwest::Client, table: &str, row: &Value) -> Result<String, String> {
+    let inserted =
+        rows(session.rest(client, Method::POST, table).header("Prefer", "return=representation").json(row)).await?;
+    inserted
+        .first()
+        .and_then(|r| r["id"].as_str())
+        .map(str::to_string)
+        .ok_or_else(|| format!("The new {table} row wasn't returned"))
+}
+
+/// Links a clone keeps to where it came from.
+struct Origin {
+    cloned_from: Option<String>,
+    cloned_from_listing: Option<String>,
+}
+
+/// Create the inventory item (and a draft listing when `with_listing`),
+/// returning their ids.
+async fn create(
+    session: &SupabaseSession,
+    client: &reqwest::Client,
+    sku: &str,
+    template: &Template,
+    images: &[String],
+    origin: Origin,
+    with_listing: bool,
+) -> Result<(String, Option<String>), String> {
+    let user_id = session.user_id(client).await?;
+    let listing_id = if with_listing {
+        let listing = json!({
+            "user_id": user_id,
+            "title": template.name,
+            "description": template.description,
+            "price": template.price,
+            "cost": template.cost,
+            "category": template.category,
+            "condition": template.condition,
+            "brand": template.brand,
+            "size": template.size,
+            "color": template.color,
+            "shipping_weight": template.shipping_weight,
+            "tags": template.tags,
+            "images": images,
+            "status": "draft",
+            "quantity": template.quantity,
+        });
+        Some(insert(session, client, "listings", &listing).await?)
+    } else {
+        None
+    };
+    let item = json!({
+        "user_id": user_id,
+        "name": template.name,
+        "description": template.description,
+        "cost": template.cost,
+        "quantity": template.quantity,
+        "category": template.category,
+        "images": images,
+        "location": template.location,
+        "sku": sku,
+        "listing_id": listing_id,
+        "cloned_from": origin.cloned_from,
+        "cloned_from_listing": origin.cloned_from_listing,
+    });
+    match insert(session, client, "inventory", &item).await {
+        Ok(item_id) => Ok((item_id, listing_id)),
+        Err(e) => {
+            // Don't leave an orphaned draft behind.
+            if let Some(id) = &listing_id {
+                let _ = supabase::send(session.rest(client, Method::DELETE, &format!("listings?id=eq.{id}"))).await;
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Copy the inventory item with `source_sku` under a fresh SKU. Its listing
+/// is copied as a new draft unless `include_listing` is false.
+/// `custom_fields` are the source's values, which the frontend holds.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn clone_item(
+    app: tauri::AppHandle,
+    skus: tauri::State<'_, crate::sku::SkuState>,
+    fields: tauri::State<'_, crate::custom_fields::CustomFieldState>,
+    session: SupabaseSession,
+    source_sku: String,
+    custom_fields: Option<Map<String, Value>>,
+    overrides: Option<CloneOverrides>,
+    include_listing: Option<bool>,
+) -> Result<Private<ClonedItem>, String> {
+    let client = supabase::client()?;
+    let source_sku = source_sku.trim();
+    let path = format!("inventory?select=*&sku=eq.{}&deleted_at=is.null&limit=2", crate::depop::sync::urlencode(source_sku));
+    let found = rows(session.rest(&client, Method::GET, &path)).await?;
+    let source = match &found[..] {
+        [] => return Err(format!("No item has SKU {source_sku}")),
+        [item] => item,
+        _ => return Err(format!("More than one item has SKU {source_sku}")),
+    };
+    let source_id = text(source, "id").ok_or("The item has no id")?;
+    let listing = match text(source, "listing_id").filter(|_| include_listing.unwrap_or(true)) {
+        Some(id) => rows(session.rest(&client, Method::GET, &format!("listings?select=*&id=eq.{id}"))).await?.pop(),
+        None => None,
+    };
+    let from_listing = |key: &str| listing.as_ref().and_then(|l| text(l, key));
+
+    let mut template = Template {
+        name: from_listing("title").or_else(|| text(source, "name")).unwrap_or_else(|| source_sku.to_string()),
+        description: from_listing("description").or_else(|| text(source, "description")),
+        price: listing.as_ref().and_then(|l| number(l, "price")),
+        cost: number(source, "cost").or_else(|| listing.as_ref().and_then(|l| number(l, "cost"))),
+        category: text(source, "category").or_else(|| from_listing("category")),
+        condition: from_listing("condition"),
+        brand: from_listing("brand"),
+        size: from_listing("size"),
+        color: from_listing("color"),
+        shipping_weight: listing.as_ref().and_then(|l| number(l, "shipping_weight")),
+        tags: listing
+            .as_ref()
+            .and_then(|l| l.get("tags")?.as_array().cloned())
+            .map(|tags| tags.iter().filter_map(Value::as_str).map(str::to_string).collect())
+            .unwrap_or_default(),
+        location: text(source, "location"),
+        custom_fields: custom_fields.unwrap_or_default(),
+        quantity: 1,
+    };
+    let mut cleared = vec!["photos".to_string()];
+    if listing.as_ref().is_some_and(|l| text(l, "condition_notes").is_some()) {
+        cleared.push("condition notes".to_string());
+    }
+    cleared.extend(clear_per_unit(&mut template.custom_fields, &fields.active_fields()));
+    template.apply(overrides.unwrap_or_default());
+
+    let sku = crate::sku::next_skus(&skus.settings(), &session, template.category.as_deref().unwrap_or_default(), 1)
+        .await?
+        .pop()
+        .ok_or("No SKU generated")?;
+    // Clones of clones hang off the original so a family stays one level deep.
+    let root = text(source, "cloned_from").unwrap_or_else(|| source_id.clone());
+    let origin = Origin { cloned_from: Some(root), cloned_from_listing: text(source, "cloned_from_listing") };
+    let (item_id, listing_id) =
+        create(&session, &client, &sku, &template, &[], origin, listing.is_some()).await?;
+
+    crate::audit::record(
+        &app,
+        "clone_item",
+        None,
+        false,
+        json!({ "source_sku": source_sku, "sku": sku, "item_id": item_id, "listing_id": listing_id }),
+    );
+    Ok(Private::new(ClonedItem {
+        item_id,
+        sku,
+        listing_id,
+        source: CloneSource::Item { item_id: source_id, sku: Some(source_sku.to_string()) },
+        cleared,
+        custom_fields: template.custom_fields,
+        reference_photos: Vec::new(),
+        warnings: Vec::new(),
+    }))
+}
+
+/// Read a live Depop listing into a template and its photo URLs.
+async fn depop_template(token: &str, listing_id: &str) -> Result<(Template, Vec<String>), String> {
+    let product = crate::depop::get_product(token, listing_id).await?;
+    let description = text(&product, "description");
+    let first_line = description.as_deref().and_then(|d| d.lines().next()).map(|l| l.chars().take(80).collect());
+    let photos = ["pictures", "photos"]
+        .iter()
+        .find_map(|k| product.get(*k)?.as_array())
+        .map(|photos| photos.iter().filter_map(crate::depop::photos::photo_url).collect())
+        .unwrap_or_default();
+    let template = Template {
+        name: text(&product, "title").or(first_line).unwrap_or_else(|| format!("Depop {listing_id}")),
+        description,
+        price: crate::depop::liked::price_of(&product).0,
+        brand: name_of(product.get("brand")).or_else(|| text(&product, "brandName")),
+        size: name_of(product.get("sizes")).or_else(|| name_of(product.get("size"))),
+        color: name_of(product.get("colour")),
+        condition: name_of(product.get("condition")),
+        ..Template::default()
+    };
+    Ok((template, photos))
+}
+
+/// Read a live eBay listing, by its inventory SKU, into a template.
+async fn ebay_template(
+    token: &str,
+    sku: &str,
+    fields: &[crate::custom_fields::CustomField],
+) -> Result<(Template, Vec<String>), String> {
+    let item = crate::ebay::fetch_listing(token, sku, fields).await?;
+    let template = Template {
+        name: item.title,
+        description: Some(item.description).filter(|d| !d.trim().is_empty()),
+        price: item.price,
+        condition: item.condition,
+        brand: item.brand,
+        size: item.size,
+        color: item.color,
+        custom_fields: item.custom_fields,
+        ..Template::default()
+    };
+    Ok((template, item.photos))
+}
+
+/// Start a new draft from one of the seller's live listings on
+/// `marketplace` ("depop", or "ebay" with the listing's inventory SKU as
+/// `listing_id`). Photos are saved under `photo_dir` (default: the app's
+/// clone-references folder) as references only.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn clone_from_listing(
+    app: tauri::AppHandle,
+    skus: tauri::State<'_, crate::sku::SkuState>,
+    fields: tauri::State<'_, crate::custom_fields::CustomFieldState>,
+    photo_meta: tauri::State<'_, crate::photo_meta::PhotoMetaState>,
+    session: SupabaseSession,
+    marketplace: String,
+    listing_id: String,
+    token: String,
+    overrides: Option<CloneOverrides>,
+    photo_dir: Option<String>,
+) -> Result<Private<ClonedItem>, String> {
+    let marketplace = marketplace.trim().to_lowercase();
+    let listing_id = listing_id.trim().to_string();
+    let active_fields = fields.active_fields();
+    let (mut template, photo_urls) = match marketplace.as_str() {
+        "depop" => depop_template(&token, &listing_id).await?,
+        "ebay" => ebay_template(&token, &listing_id, &active_fields).await?,
+        other => return Err(format!("Cloning from {other} listings isn't supported")),
+    };
+    let mut cleared = clear_per_unit(&mut template.custom_fields, &active_fields);
+    template.apply(overrides.unwrap_or_default());
+
+    let client = supabase::client()?;
+    let sku = crate::sku::next_skus(&skus.settings(), &session, template.category.as_deref().unwrap_or_default(), 1)
+        .await?
+        .pop()
+        .ok_or("No SKU generated")?;
+
+    let mut warnings = Vec::new();
+    let mut reference_photos = Vec::new();
+    if !photo_urls.is_empty() {
+        let dir = match photo_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => crate::store::data_path(&app, "clone-references")?,
+        }
+        .join(&sku);
+        match crate::depop::photos::download_listing_photos(
+            format!("reference-{listing_id}"),
+            photo_urls,
+            dir.to_string_lossy().into_owned(),
+        )
+        .await
+        {
+            Ok(paths) => {
+                photo_meta.mark_reference(
+                    &app,
+                    &paths,
+                    &format!("Reference photo from {marketplace} listing {listing_id}; reuse not recommended"),
+                )?;
+                reference_photos = paths;
+            }
+            Err(e) => warnings.push(format!("Reference photos not downloaded: {e}")),
+        }
+    }
+    cleared.insert(0, "photos (downloaded as references)".to_string());
+
+    let origin = Origin { cloned_from: None, cloned_from_listing: Some(format!("{marketplace}:{listing_id}")) };
+    let (item_id, new_listing_id) =
+        create(&session, &client, &sku, &template, &reference_photos, origin, true).await?;
+
+    crate::audit::record(
+        &app,
+        "clone_from_listing",
+        None,
+        false,
+        json!({ "marketplace": marketplace, "listing_id": listing_id, "sku": sku, "item_id": item_id }),
+    );
+    Ok(Private::new(ClonedItem {
+        item_id,
+        sku,
+        listing_id: new_listing_id,
+        source: CloneSource::Listing { marketplace, listing_id },
+        cleared,
+        custom_fields: template.custom_fields,
+        reference_photos,
+        warnings,
+    }))
+}
+
+/// The item's family: its source and every clone of that source (or of the
+/// same outside listing), oldest first.
+#[tauri::command]
+pub async fn get_item_variants(session: SupabaseSession, item_id: String) -> Result<Vec<ItemVariant>, String> {
+    let client = supabase::client()?;
+    let item = rows(session.rest(
+        &client,
+        Method::GET,
+        &format!("inventory?select=id,cloned_from,cloned_from_listing&id=eq.{item_id}"),
+    ))
+    .await?
+    .pop()
+    .ok_or("That item doesn't exist")?;
+    let root = text(&item, "cloned_from").unwrap_or_else(|| item_id.clone());
+    let mut filters = vec![format!("id.eq.{root}"), format!("cloned_from.eq.{root}")];
+    if let Some(listing) = text(&item, "cloned_from_listing") {
+        filters.push(format!("cloned_from_listing.eq.\"{}\"", listing.replace('"', "")));
+    }
+    let path = format!(
+        "inventory?select=id,sku,name,quantity,listing_id,cloned_from,created_at&deleted_at=is.null&or=({})&order=created_at",
+        crate::depop::sync::urlencode(&filters.join(","))
+    );
+    let found = rows(session.rest(&client, Method::GET, &path)).await?;
+    found.into_iter().map(|row| serde_json::from_value(row).map_err(|e| e.to_string())).collect()
+}
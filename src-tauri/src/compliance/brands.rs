@@ -0,0 +1,293 @@
+// High-risk brand pack: luxury brands whose listings get removed (and
+// accounts flagged) unless they show provenance. For an item whose brand is
+// on the list, publishing to a covered marketplace needs:
+// - provenance wording in the description (receipt, serial, authentication
+//   ... — configurable regexes);
+// - at least `min_photos` photos;
+// - one photo labelled as the authenticity detail shot.
+//
+// Each unmet requirement is a severe, non-overridable compliance hit: the
+// generic compliance override note doesn't cover them. Items authenticated
+// elsewhere are marked with `set_item_authenticated` (note + audit entry),
+// which replaces the checks with an info hit saying so.
+
+use super::{ComplianceHit, ComplianceState, Severity};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+pub const SETTINGS_STORE: &str = "high_risk_brands";
+pub const AUTHENTICATED_STORE: &str = "authenticated_items";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HighRiskBrandSettings {
+    pub brands: Vec<String>,
+    /// Regexes, matched case-insensitively; any one is enough.
+    pub provenance_patterns: Vec<String>,
+    pub min_photos: usize,
+    /// Photo label that marks the authenticity detail shot.
+    pub authenticity_slot: String,
+    /// Marketplace ids the pack applies to; empty = all.
+    #[serde(default)]
+    pub marketplaces: Vec<String>,
+}
+
+impl Default for HighRiskBrandSettings {
+    fn default() -> Self {
+        serde_json::from_str(include_str!("high_risk_brands.json")).expect("embedded high-risk brands are valid JSON")
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Authenticated {
+    pub note: String,
+    pub at: u64,
+}
+
+/// What the brand checks look at.
+pub struct BrandCheckItem<'a> {
+    /// Inventory item id, for the authenticated override.
+    pub id: Option<&'a str>,
+    pub brand: Option<&'a str>,
+    pub description: &'a str,
+    pub photo_count: usize,
+    pub photo_labels: &'a [String],
+}
+
+/// "Hermès" == "hermes" == "HERMES ": lowercase, ASCII-folded letters and
+/// digits only.
+fn brand_key(brand: &str) -> String {
+    brand
+        .chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'à' | 'á' | 'â' | 'ä' | 'ã' => 'a',
+            'è' | 'é' | 'ê' | 'ë' => 'e',
+            'ì' | 'í' | 'î' | 'ï' => 'i',
+            'ò' | 'ó' | 'ô' | 'ö' | 'õ' => 'o',
+            'ù' | 'ú' | 'û' | 'ü' => 'u',
+            other => other,
+        })
+        .filter(char::is_ascii_alphanumeric)
+        .collect()
+}
+
+fn hit(rule_id: &str, field: &str, severity: Severity, message: String) -> ComplianceHit {
+    ComplianceHit {
+        rule_id: rule_id.to_string(),
+        field: field.to_string(),
+        start: 0,
+        end: 0,
+        matched: String::new(),
+        severity,
+        message,
+        suggestion: None,
+        overridable: false,
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex, String> {
+    Regex::new(&format!("(?i){pattern}")).map_err(|e| format!("provenance pattern {pattern:?}: {e}"))
+}
+
+/// Hits for `item` on `marketplace`; empty when the brand isn't high-risk.
+pub fn check(state: &ComplianceState, item: &BrandCheckItem, marketplace: &str) -> Vec<ComplianceHit> {
+    let settings = state.brand_settings.lock().unwrap().clone();
+    if !settings.marketplaces.is_empty() && !settings.marketplaces.iter().any(|m| m == marketplace) {
+        return Vec::new();
+    }
+    let Some(brand) = item.brand.map(brand_key).filter(|b| !b.is_empty()) else {
+        return Vec::new();
+    };
+    let Some(listed) = settings.brands.iter().find(|b| brand_key(b) == brand) else {
+        return Vec::new();
+    };
+
+    if let Some(auth) = item.id.and_then(|id| state.authenticated.lock().unwrap().get(id).cloned()) {
+        return vec![hit(
+            "high-risk-brand-authenticated",
+            "brand",
+            Severity::Info,
+            format!("{listed} checks skipped: authenticated ({})", auth.note),
+        )];
+    }
+
+    let mut hits = Vec::new();
+    let has_provenance = settings.provenance_patterns.iter().any(|p| match compile(p) {
+        Ok(re) => re.is_match(item.description),
+        Err(e) => {
+            log::warn!("skipping {e}");
+            false
+        }
+    });
+    if !has_provenance {
+        hits.push(hit(
+            "high-risk-brand-provenance",
+            "description",
+            Severity::Severe,
+            format!("{listed} listings need provenance in the description (receipt, serial number, authentication)"),
+        ));
+    }
+    if item.photo_count < settings.min_photos {
+        hits.push(hit(
+            "high-risk-brand-photos",
+            "photos",
+            Severity::Severe,
+            format!("{listed} listings need at least {} photos ({} added)", settings.min_photos, item.photo_count),
+        ));
+    }
+    let slot = settings.authenticity_slot.trim();
+    if !item.photo_labels.iter().any(|l| l.trim().eq_ignore_ascii_case(slot)) {
+        hits.push(hit(
+            "high-risk-brand-authenticity-shot",
+            "photos",
+            Severity::Severe,
+            format!("{listed} listings need a photo labelled \"{slot}\" showing the authenticity details"),
+        ));
+    }
+    hits
+}
+
+#[tauri::command]
+pub fn get_high_risk_brand_settings(state: tauri::State<'_, ComplianceState>) -> HighRiskBrandSettings {
+    state.brand_settings.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_high_risk_brand_settings(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ComplianceState>,
+    settings: HighRiskBrandSettings,
+) -> Result<(), String> {
+    for pattern in &settings.provenance_patterns {
+        compile(pattern)?;
+    }
+    if settings.authenticity_slot.trim().is_empty() {
+        return Err("The authenticity photo label can't be empty".to_string());
+    }
+    let mut current = state.brand_settings.lock().unwrap();
+    *current = settings;
+    crate::store::save(&app, SETTINGS_STORE, &*current)
+}
+
+/// Mark an item as authenticated elsewhere (`note` says how), or clear the
+/// mark with no note. Both are audited.
+#[tauri::command]
+pub fn set_item_authenticated(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ComplianceState>,
+    item_id: String,
+    note: Option<String>,
+) -> Result<(), String> {
+    let note = note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    let mut authenticated = state.authenticated.lock().unwrap();
+    match &note {
+        Some(note) => {
+            authenticated.insert(item_id.clone(), Authenticated { note: note.clone(), at: crate::unix_now() });
+        }
+        None => {
+            if authenticated.remove(&item_id).is_none() {
+                return Ok(());
+            }
+        }
+    }
+    crate::store::save(&app, AUTHENTICATED_STORE, &*authenticated)?;
+    crate::audit::record(
+        &app,
+        "set_item_authenticated",
+        None,
+        false,
+        json!({ "item_id": item_id, "authenticated": note.is_some(), "note": note }),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn state() -> ComplianceState {
+        ComplianceState {
+            custom_rules: Mutex::new(Vec::new()),
+            compiled: Mutex::new(Vec::new()),
+            brand_settings: Mutex::new(HighRiskBrandSettings::default()),
+            authenticated: Mutex::new(Default::default()),
+        }
+    }
+
+    fn rule_ids(state: &ComplianceState, id: &str, brand: &str, marketplace: &str) -> Vec<String> {
+        let labels = ["front".to_string()];
+        let item = BrandCheckItem {
+            id: Some(id),
+            brand: Some(brand),
+            description: "Lovely bag, barely used",
+            photo_count: 2,
+            photo_labels: &labels,
+        };
+        check(state, &item, marketplace).into_iter().map(|h| h.rule_id).collect()
+    }
+
+    const ALL_CHECKS: [&str; 3] =
+        ["high-risk-brand-provenance", "high-risk-brand-photos", "high-risk-brand-authenticity-shot"];
+
+    #[test]
+    fn listed_brands_match_whatever_the_case_accents_or_spacing() {
+        let state = state();
+        for spelling in ["Hermès", "HERMES", " hermes ", "HeRmÈs"] {
+            assert_eq!(rule_ids(&state, "item", spelling, "depop"), ALL_CHECKS, "{spelling}");
+        }
+        assert_eq!(rule_ids(&state, "item", "louis-vuitton", "depop"), ALL_CHECKS);
+        assert_eq!(rule_ids(&state, "item", "SAINTLAURENT", "depop"), ALL_CHECKS);
+    }
+
+    #[test]
+    fn a_listing_with_provenance_and_the_photos_passes() {
+        let state = state();
+        let labels: Vec<String> =
+            ["front", "back", "Authenticity ", "strap", "inside", "base"].map(String::from).into();
+        let item = BrandCheckItem {
+            id: Some("item"),
+            brand: Some("Chanel"),
+            description: "Comes with the original receipt and serial card",
+            photo_count: labels.len(),
+            photo_labels: &labels,
+        };
+        assert!(check(&state, &item, "depop").is_empty());
+    }
+
+    #[test]
+    fn authenticated_items_get_an_info_hit_instead_of_the_checks() {
+        let state = state();
+        state
+            .authenticated
+            .lock()
+            .unwrap()
+            .insert("checked".to_string(), Authenticated { note: "Entrupy cert 1234".to_string(), at: 0 });
+        let labels = Vec::new();
+        let item = BrandCheckItem {
+            id: Some("checked"),
+            brand: Some("gucci"),
+            description: "",
+            photo_count: 1,
+            photo_labels: &labels,
+        };
+        let hits = check(&state, &item, "depop");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].rule_id, "high-risk-brand-authenticated");
+        assert_eq!(hits[0].severity, Severity::Info);
+        assert!(hits[0].message.contains("Entrupy cert 1234"));
+        // The mark is per item: the same brand on another item is still checked.
+        assert_eq!(rule_ids(&state, "other", "gucci", "depop"), ALL_CHECKS);
+    }
+
+    #[test]
+    fn other_brands_and_marketplaces_are_left_alone() {
+        let state = state();
+        assert!(rule_ids(&state, "item", "Levi's", "depop").is_empty());
+        // Close, but not the listed brand.
+        assert!(rule_ids(&state, "item", "Gucci Mane", "depop").is_empty());
+        assert!(rule_ids(&state, "item", "", "depop").is_empty());
+        assert!(rule_ids(&state, "item", "Chanel", "ebay").is_empty());
+    }
+}
@@ -0,0 +1,47 @@
+// Flaw disclosure: a listing whose photos are flagged as showing a flaw
+// (photo_meta.rs) should say so in its description. Not blocking, since the
+// wording varies too much to be sure, but it shows as a warning.
+
+use super::{ComplianceHit, Severity};
+use crate::photo_meta::PhotoMetaState;
+
+/// Words that count as mentioning a flaw.
+const FLAW_WORDS: &[&str] = &[
+    "flaw", "flaws", "flawed", "defect", "defects", "damage", "damaged", "hole", "holes", "stain", "stains", "stained",
+    "tear", "tears", "torn", "rip", "ripped", "snag", "snags", "pilling", "faded", "fading", "scuff", "scuffs",
+    "scuffed", "crack", "cracked", "mark", "marks", "marked", "wear", "worn", "imperfection", "imperfections",
+];
+
+fn mentions_flaw(description: &str) -> bool {
+    description
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|w| FLAW_WORDS.iter().any(|f| w.eq_ignore_ascii_case(f)))
+}
+
+pub fn check(state: &PhotoMetaState, images: &[String], description: &str) -> Vec<ComplianceHit> {
+    let flawed: Vec<usize> = images
+        .iter()
+        .enumerate()
+        .filter(|(_, image)| state.get(image).is_some_and(|m| m.flaw))
+        .map(|(i, _)| i + 1)
+        .collect();
+    if flawed.is_empty() || mentions_flaw(description) {
+        return Vec::new();
+    }
+    let photos = flawed.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ");
+    vec![ComplianceHit {
+        rule_id: "undisclosed-flaw".to_string(),
+        field: "description".to_string(),
+        start: 0,
+        end: 0,
+        matched: String::new(),
+        severity: Severity::Warning,
+        message: if flawed.len() == 1 {
+            format!("Photo {photos} shows a flaw, but the description doesn't mention one")
+        } else {
+            format!("Photos {photos} show flaws, but the description doesn't mention any")
+        },
+        suggestion: None,
+        overridable: true,
+    }]
+}
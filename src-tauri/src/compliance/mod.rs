@@ -0,0 +1,368 @@
+// ── Listing compliance scanner ─────────────────────────────────────────────
+// Rule packs of words/regexes that marketplaces remove or shadow-ban
+// listings for. Defaults ship embedded (default_rules.json); users can add
+// their own with `add_compliance_rule`, persisted in the app data dir.
+//
+// Word rules are matched case-insensitively on word boundaries, so "ass"
+// never fires inside "grass" or "class". Spaces and hyphens inside a word
+// rule match any run of whitespace/hyphens ("knock off" = "knock-off").
+//
+// The high-risk brand pack (brands.rs) adds requirements for luxury brands
+// on top of the word rules, and flaws.rs warns about flaw photos the
+// description doesn't disclose.
+
+pub mod brands;
+pub mod flaws;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+const STORE_NAME: &str = "compliance_rules";
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Severe,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleKind {
+    Word,
+    Regex,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ComplianceRule {
+    pub id: String,
+    pub kind: RuleKind,
+    pub pattern: String,
+    pub severity: Severity,
+    /// Marketplace ids this rule applies to; empty = all.
+    #[serde(default)]
+    pub marketplaces: Vec<String>,
+    pub message: String,
+    /// Replacement text to offer. `""` means "remove it", `null` means no
+    /// mechanical fix exists.
+    #[serde(default)]
+    pub suggestion: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ComplianceHit {
+    pub rule_id: String,
+    pub field: String,
+    /// Offsets in UTF-16 code units so they index JS strings directly.
+    pub start: usize,
+    pub end: usize,
+    pub matched: String,
+    pub severity: Severity,
+    pub message: String,
+    pub suggestion: Option<String>,
+    /// Whether a compliance override note unblocks this hit. High-risk brand
+    /// requirements can't be overridden that way.
+    pub overridable: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ComplianceReport {
+    pub marketplace: String,
+    pub hits: Vec<ComplianceHit>,
+    /// True if any hit is severe — publishing is blocked without an override.
+    pub blocking: bool,
+}
+
+impl ComplianceReport {
+    /// Add hits from another check, keeping the severity order.
+    pub fn extend(&mut self, hits: Vec<ComplianceHit>) {
+        self.hits.extend(hits);
+        self.hits.sort_by_key(|h| std::cmp::Reverse(h.severity));
+        self.blocking = self.hits.iter().any(|h| h.severity == Severity::Severe);
+    }
+
+    /// Whether publishing stays blocked, given whether an override note was
+    /// supplied.
+    pub fn blocks(&self, overridden: bool) -> bool {
+        self.hits
+            .iter()
+            .any(|h| h.severity == Severity::Severe && !(overridden && h.overridable))
+    }
+}
+
+/// The text fields of a listing the scanner looks at.
+#[derive(Clone, Deserialize, Default)]
+pub struct ScanItem {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A rule with its pattern compiled, so scans don't recompile anything.
+struct CompiledRule {
+    rule: ComplianceRule,
+    re: Regex,
+}
+
+pub struct ComplianceState {
+    custom_rules: Mutex<Vec<ComplianceRule>>,
+    /// The default pack plus `custom_rules`, compiled once at startup and
+    /// updated as custom rules are added or removed.
+    compiled: Mutex<Vec<CompiledRule>>,
+    brand_settings: Mutex<brands::HighRiskBrandSettings>,
+    /// Item id -> authenticated-elsewhere override.
+    authenticated: Mutex<BTreeMap<String, brands::Authenticated>>,
+}
+
+impl ComplianceState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        let custom_rules: Vec<ComplianceRule> = crate::store::load(app, STORE_NAME);
+        ComplianceState {
+            compiled: Mutex::new(compile_all(default_rules().into_iter().chain(custom_rules.iter().cloned()))),
+            custom_rules: Mutex::new(custom_rules),
+            brand_settings: Mutex::new(crate::store::load(app, brands::SETTINGS_STORE)),
+            authenticated: Mutex::new(crate::store::load(app, brands::AUTHENTICATED_STORE)),
+        }
+    }
+}
+
+fn default_rules() -> Vec<ComplianceRule> {
+    serde_json::from_str(include_str!("default_rules.json"))
+        .expect("embedded compliance rules are valid JSON")
+}
+
+/// Invalid rules are skipped (custom ones are validated on add, this only
+/// guards hand-edited files).
+fn compile_all(rules: impl IntoIterator<Item = ComplianceRule>) -> Vec<CompiledRule> {
+    rules
+        .into_iter()
+        .filter_map(|rule| match rule.compile() {
+            Ok(re) => Some(CompiledRule { rule, re }),
+            Err(e) => {
+                log::warn!("skipping compliance rule: {e}");
+                None
+            }
+        })
+        .collect()
+}
+
+impl ComplianceRule {
+    fn compile(&self) -> Result<Regex, String> {
+        let body = match self.kind {
+            RuleKind::Word => {
+                let words: Vec<String> = self
+                    .pattern
+                    .split(|c: char| c.is_whitespace() || c == '-')
+                    .filter(|w| !w.is_empty())
+                    .map(regex::escape)
+                    .collect();
+                if words.is_empty() {
+                    return Err(format!("rule {}: empty word pattern", self.id));
+                }
+                format!(r"\b{}\b", words.join(r"[\s\-]+"))
+            }
+            RuleKind::Regex => self.pattern.clone(),
+        };
+        Regex::new(&format!("(?i){body}")).map_err(|e| format!("rule {}: {e}", self.id))
+    }
+
+    fn applies_to(&self, marketplace: &str) -> bool {
+        self.marketplaces.is_empty() || self.marketplaces.iter().any(|m| m == marketplace)
+    }
+}
+
+fn utf16_offset(s: &str, byte_idx: usize) -> usize {
+    s[..byte_idx].encode_utf16().count()
+}
+
+/// Scan with the default pack plus the user's rules.
+pub fn scan(state: &ComplianceState, item: &ScanItem, marketplace: &str) -> ComplianceReport {
+    scan_with(&state.compiled.lock().unwrap(), item, marketplace)
+}
+
+fn scan_with(rules: &[CompiledRule], item: &ScanItem, marketplace: &str) -> ComplianceReport {
+    let mut fields: Vec<(String, &str)> = vec![
+        ("title".to_string(), item.title.as_str()),
+        ("description".to_string(), item.description.as_str()),
+    ];
+    for (i, tag) in item.tags.iter().enumerate() {
+        fields.push((format!("tags[{i}]"), tag.as_str()));
+    }
+
+    let mut hits = Vec::new();
+    for CompiledRule { rule, re } in rules.iter().filter(|r| r.rule.applies_to(marketplace)) {
+        for (field, text) in &fields {
+            for m in re.find_iter(text) {
+                hits.push(ComplianceHit {
+                    rule_id: rule.id.clone(),
+                    field: field.clone(),
+                    start: utf16_offset(text, m.start()),
+                    end: utf16_offset(text, m.end()),
+                    matched: m.as_str().to_string(),
+                    severity: rule.severity,
+                    message: rule.message.clone(),
+                    suggestion: rule.suggestion.clone(),
+                    overridable: true,
+                });
+            }
+        }
+    }
+    hits.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| a.field.cmp(&b.field))
+            .then(a.start.cmp(&b.start))
+    });
+
+    ComplianceReport {
+        marketplace: marketplace.to_string(),
+        blocking: hits.iter().any(|h| h.severity == Severity::Severe),
+        hits,
+    }
+}
+
+#[tauri::command]
+pub fn scan_listing(
+    state: tauri::State<'_, ComplianceState>,
+    item: ScanItem,
+    marketplace: String,
+) -> ComplianceReport {
+    scan(&state, &item, &marketplace)
+}
+
+#[tauri::command]
+pub fn list_compliance_rules(state: tauri::State<'_, ComplianceState>) -> Vec<ComplianceRule> {
+    let mut rules = default_rules();
+    rules.extend(state.custom_rules.lock().unwrap().iter().cloned());
+    rules
+}
+
+/// Add or replace a user rule. Ids of embedded rules are reserved.
+#[tauri::command]
+pub fn add_compliance_rule(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ComplianceState>,
+    rule: ComplianceRule,
+) -> Result<(), String> {
+    if rule.id.trim().is_empty() {
+        return Err("Rule id is required".to_string());
+    }
+    if default_rules().iter().any(|r| r.id == rule.id) {
+        return Err(format!("'{}' is a built-in rule id", rule.id));
+    }
+    let re = rule.compile()?;
+
+    let mut custom = state.custom_rules.lock().unwrap();
+    custom.retain(|r| r.id != rule.id);
+    custom.push(rule.clone());
+    crate::store::save(&app, STORE_NAME, &*custom)?;
+    let mut compiled = state.compiled.lock().unwrap();
+    compiled.retain(|c| c.rule.id != rule.id);
+    compiled.push(CompiledRule { rule, re });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn remove_compliance_rule(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ComplianceState>,
+    id: String,
+) -> Result<(), String> {
+    let mut custom = state.custom_rules.lock().unwrap();
+    let before = custom.len();
+    custom.retain(|r| r.id != id);
+    if custom.len() == before {
+        return Err(format!("No custom rule '{id}'"));
+    }
+    crate::store::save(&app, STORE_NAME, &*custom)?;
+    state.compiled.lock().unwrap().retain(|c| c.rule.id != id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(pattern: &str) -> ComplianceRule {
+        ComplianceRule {
+            id: "test".to_string(),
+            kind: RuleKind::Word,
+            pattern: pattern.to_string(),
+            severity: Severity::Severe,
+            marketplaces: Vec::new(),
+            message: String::new(),
+            suggestion: None,
+        }
+    }
+
+    fn matches(rule: &ComplianceRule, text: &str) -> bool {
+        rule.compile().unwrap().is_match(text)
+    }
+
+    #[test]
+    fn word_rules_match_whole_words_only() {
+        let ass = word("ass");
+        assert!(!matches(&ass, "fresh grass stains"));
+        assert!(!matches(&ass, "first class post"));
+        assert!(!matches(&ass, "assorted colours"));
+        assert!(matches(&ass, "what an ass"));
+        assert!(matches(&ass, "(ass)"));
+    }
+
+    #[test]
+    fn word_rules_ignore_case() {
+        assert!(matches(&word("replica"), "REPLICA bag"));
+        assert!(matches(&word("Replica"), "a replica"));
+    }
+
+    #[test]
+    fn spaces_and_hyphens_are_interchangeable() {
+        let rule = word("knock off");
+        assert!(matches(&rule, "a knock-off"));
+        assert!(matches(&rule, "a knock  off"));
+        assert!(matches(&rule, "a knock - off"));
+        assert!(matches(&word("knock-off"), "a knock off"));
+        assert!(!matches(&rule, "a knockoff"));
+        assert!(word(" - ").compile().is_err());
+    }
+
+    #[test]
+    fn regex_special_characters_in_word_rules_are_literal() {
+        assert!(!matches(&word("a.b"), "axb"));
+        assert!(matches(&word("a.b"), "see a.b here"));
+    }
+
+    #[test]
+    fn hit_offsets_are_utf16() {
+        let rules = compile_all([word("fake")]);
+        let item = ScanItem { title: "🔥 Café fake bag".to_string(), ..ScanItem::default() };
+        let report = scan_with(&rules, &item, "depop");
+        let hit = &report.hits[0];
+        // The emoji is two UTF-16 units, "é" one.
+        assert_eq!((hit.start, hit.end), (8, 12));
+        let utf16: Vec<u16> = item.title.encode_utf16().collect();
+        assert_eq!(String::from_utf16(&utf16[hit.start..hit.end]).unwrap(), "fake");
+        assert!(report.blocking);
+    }
+
+    #[test]
+    fn rules_only_apply_to_their_marketplaces() {
+        let mut rule = word("fake");
+        rule.marketplaces = vec!["ebay".to_string()];
+        let rules = compile_all([rule]);
+        let item = ScanItem { title: "fake".to_string(), ..ScanItem::default() };
+        assert!(scan_with(&rules, &item, "depop").hits.is_empty());
+        assert_eq!(scan_with(&rules, &item, "ebay").hits.len(), 1);
+    }
+
+    #[test]
+    fn default_rules_compile() {
+        assert_eq!(compile_all(default_rules()).len(), default_rules().len());
+    }
+}
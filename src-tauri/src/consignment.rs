@@ -0,0 +1,511 @@
+// ── Consignment ────────────────────────────────────────────────────────────
+// Items sold on behalf of someone else (migration 022). A consignor has
+// contact details and a default split: their share, in percent, of what a
+// sale nets after marketplace fees. Assigning an item copies the split onto
+// the item, so changing the default later only affects items assigned after
+// that and never rewrites what earlier sales owe.
+//
+// A sale's fees are the recorded `platform_fees` when there is one (what the
+// marketplace actually took), otherwise the fee schedule in force on the
+// sale date (fees.rs). Sales are tied to items through their listing.
+//
+// Statements run over a date range: the balance brought forward from
+// before it, each sale in it with fees, the consignor's share and the
+// seller's commission, settlements paid in it, and the balance owed at the
+// end. `record_consignor_payout` records a settlement against that balance.
+
+use crate::fees::{round_cents, FeeScheduleState};
+use crate::pdf_layout::{self, PaperSize, PdfDocument};
+use crate::privacy::Private;
+use crate::repricing::{in_range, DateRange};
+use crate::supabase::{self, SupabaseSession};
+use base64::Engine;
+use pdf_writer::Content;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Listing ids per sales query, to keep the URL a sensible length.
+const CHUNK: usize = 200;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Consignor {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// The consignor's share of the net after fees, 0-100.
+    pub default_split_percent: f64,
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn rows(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(rows) => rows,
+        _ => Vec::new(),
+    }
+}
+
+fn in_list(ids: &[String]) -> String {
+    let list: Vec<String> = ids.iter().map(|id| format!("\"{}\"", id.replace(['"', '\\'], ""))).collect();
+    crate::depop::sync::urlencode(&format!("({})", list.join(",")))
+}
+
+fn check_split(split: f64) -> Result<(), String> {
+    if split.is_finite() && (0.0..=100.0).contains(&split) {
+        Ok(())
+    } else {
+        Err("The split must be between 0 and 100 percent".to_string())
+    }
+}
+
+async fn fetch_consignor(session: &SupabaseSession, client: &reqwest::Client, id: &str) -> Result<Consignor, String> {
+    let path = format!("consignors?select=*&id=eq.{}", crate::depop::sync::urlencode(id));
+    let row = rows(supabase::send(session.rest(client, Method::GET, &path)).await?)
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("No consignor {id}"))?;
+    serde_json::from_value(row).map_err(|e| format!("invalid consignor row: {e}"))
+}
+
+#[tauri::command]
+pub async fn list_consignors(session: SupabaseSession) -> Result<Private<Vec<Consignor>>, String> {
+    let client = supabase::client()?;
+    let found = supabase::send(session.rest(&client, Method::GET, "consignors?select=*&order=name.asc")).await?;
+    rows(found)
+        .into_iter()
+        .map(|row| serde_json::from_value(row).map_err(|e| format!("invalid consignor row: {e}")))
+        .collect::<Result<_, _>>()
+        .map(Private::people)
+}
+
+/// Create a consignor, or update the one with `consignor.id`. A new default
+/// split applies to items assigned from now on.
+#[tauri::command]
+pub async fn save_consignor(session: SupabaseSession, consignor: Consignor) -> Result<Private<Consignor>, String> {
+    if consignor.name.trim().is_empty() {
+        return Err("A consignor needs a name".to_string());
+    }
+    check_split(consignor.default_split_percent)?;
+    let client = supabase::client()?;
+    let mut row = json!({
+        "name": consignor.name.trim(),
+        "email": consignor.email,
+        "phone": consignor.phone,
+        "notes": consignor.notes,
+        "default_split_percent": consignor.default_split_percent,
+    });
+    let req = match &consignor.id {
+        Some(id) => {
+            row["updated_at"] = json!(crate::netlog::iso8601(crate::netlog::unix_millis()));
+            let path = format!("consignors?id=eq.{}", crate::depop::sync::urlencode(id));
+            session.rest(&client, Method::PATCH, &path)
+        }
+        None => {
+            row["user_id"] = json!(session.user_id(&client).await?);
+            session.rest(&client, Method::POST, "consignors")
+        }
+    };
+    let saved = supabase::send(req.header("Prefer", "return=representation").json(&row)).await?;
+    let row = rows(saved).into_iter().next().ok_or("Consignor not found")?;
+    serde_json::from_value(row).map(Private::people).map_err(|e| format!("invalid consignor row: {e}"))
+}
+
+/// Put items on consignment for `consignor_id` at `split_percent` (default:
+/// the consignor's default split), or with no consignor take them off
+/// consignment. Returns how many items changed.
+#[tauri::command]
+pub async fn assign_consignor(
+    session: SupabaseSession,
+    item_ids: Vec<String>,
+    consignor_id: Option<String>,
+    split_percent: Option<f64>,
+) -> Result<usize, String> {
+    if item_ids.is_empty() {
+        return Ok(0);
+    }
+    let client = supabase::client()?;
+    let patch = match &consignor_id {
+        Some(id) => {
+            let split = match split_percent {
+                Some(split) => split,
+                None => fetch_consignor(&session, &client, id).await?.default_split_percent,
+            };
+            check_split(split)?;
+            json!({ "consignor_id": id, "consignor_split_percent": split })
+        }
+        None => json!({ "consignor_id": null, "consignor_split_percent": null }),
+    };
+    let mut changed = 0;
+    for chunk in item_ids.chunks(CHUNK) {
+        let path = format!("inventory?id=in.{}", in_list(chunk));
+        let req = session.rest(&client, Method::PATCH, &path).header("Prefer", "return=representation").json(&patch);
+        changed += rows(supabase::send(req).await?).len();
+    }
+    Ok(changed)
+}
+
+#[derive(Serialize, Clone)]
+pub struct StatementLine {
+    sale_id: String,
+    item_id: String,
+    name: String,
+    sku: Option<String>,
+    platform: String,
+    sold_on: String,
+    sale_price: f64,
+    fees: f64,
+    /// Whether `fees` came from the fee model rather than the recorded sale.
+    fees_estimated: bool,
+    net: f64,
+    split_percent: f64,
+    consignor_share: f64,
+    commission: f64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ConsignorPayout {
+    id: String,
+    amount: f64,
+    paid_on: String,
+    note: Option<String>,
+}
+
+#[derive(Serialize, Default)]
+pub struct StatementTotals {
+    sales: usize,
+    gross: f64,
+    fees: f64,
+    net: f64,
+    consignor_share: f64,
+    commission: f64,
+    paid_out: f64,
+}
+
+#[derive(Serialize)]
+pub struct ConsignorStatement {
+    consignor: Consignor,
+    start: String,
+    end: String,
+    /// Owed from before `start`, less settlements paid before it.
+    opening_balance: f64,
+    lines: Vec<StatementLine>,
+    payouts: Vec<ConsignorPayout>,
+    totals: StatementTotals,
+    /// Owed at the end of the range.
+    balance_owed: f64,
+}
+
+/// Every sale of the consignor's items, oldest first. Items in the trash
+/// (soft-deleted) don't count.
+async fn consigned_sales(
+    schedules: &FeeScheduleState,
+    session: &SupabaseSession,
+    client: &reqwest::Client,
+    consignor: &Consignor,
+) -> Result<Vec<StatementLine>, String> {
+    let id = consignor.id.as_deref().unwrap_or_default();
+    let path = format!(
+        "inventory?select=id,name,sku,listing_id,consignor_split_percent&consignor_id=eq.{}&deleted_at=is.null",
+        crate::depop::sync::urlencode(id)
+    );
+    let items = rows(supabase::send(session.rest(client, Method::GET, &path)).await?);
+    let by_listing: HashMap<String, &Value> =
+        items.iter().filter_map(|item| Some((item["listing_id"].as_str()?.to_string(), item))).collect();
+    let listing_ids: Vec<String> = by_listing.keys().cloned().collect();
+
+    let mut lines = Vec::new();
+    for chunk in listing_ids.chunks(CHUNK) {
+        let path = format!(
+            "sales?select=id,listing_id,platform,sale_price,platform_fees,sold_at&listing_id=in.{}",
+            in_list(chunk)
+        );
+        for sale in rows(supabase::send(session.rest(client, Method::GET, &path)).await?) {
+            let Some(item) = sale["listing_id"].as_str().and_then(|l| by_listing.get(l)) else {
+                continue;
+            };
+            let sold_on = sale["sold_at"].as_str().unwrap_or_default().get(..10).unwrap_or_default().to_string();
+            let platform = sale["platform"].as_str().unwrap_or_default().to_string();
+            let sale_price = number(&sale["sale_price"]).unwrap_or(0.0);
+            let (fees, fees_estimated) = match number(&sale["platform_fees"]).filter(|f| *f > 0.0) {
+                Some(recorded) => (recorded, false),
+                None => (schedules.calculate(&platform, sale_price, &sold_on).total_fees, true),
+            };
+            let split_percent =
+                number(&item["consignor_split_percent"]).unwrap_or(consignor.default_split_percent);
+            let net = round_cents(sale_price - fees);
+            let consignor_share = round_cents((net * split_percent / 100.0).max(0.0));
+            lines.push(StatementLine {
+                sale_id: sale["id"].as_str().unwrap_or_default().to_string(),
+                item_id: item["id"].as_str().unwrap_or_default().to_string(),
+                name: item["name"].as_str().unwrap_or_default().to_string(),
+                sku: item["sku"].as_str().map(str::to_string),
+                platform,
+                sold_on,
+                sale_price: round_cents(sale_price),
+                fees: round_cents(fees),
+                fees_estimated,
+                net,
+                split_percent,
+                consignor_share,
+                commission: round_cents(net - consignor_share),
+            });
+        }
+    }
+    lines.sort_by(|a, b| a.sold_on.cmp(&b.sold_on).then_with(|| a.sale_id.cmp(&b.sale_id)));
+    Ok(lines)
+}
+
+async fn payouts(
+    session: &SupabaseSession,
+    client: &reqwest::Client,
+    consignor_id: &str,
+) -> Result<Vec<ConsignorPayout>, String> {
+    let path = format!(
+        "consignor_payouts?select=id,amount,paid_on,note&consignor_id=eq.{}&order=paid_on.asc",
+        crate::depop::sync::urlencode(consignor_id)
+    );
+    Ok(rows(supabase::send(session.rest(client, Method::GET, &path)).await?)
+        .into_iter()
+        .map(|row| ConsignorPayout {
+            id: row["id"].as_str().unwrap_or_default().to_string(),
+            amount: number(&row["amount"]).unwrap_or(0.0),
+            paid_on: row["paid_on"].as_str().unwrap_or_default().to_string(),
+            note: row["note"].as_str().map(str::to_string),
+        })
+        .collect())
+}
+
+async fn statement(
+    fees: &FeeScheduleState,
+    session: &SupabaseSession,
+    consignor_id: &str,
+    range: DateRange,
+) -> Result<ConsignorStatement, String> {
+    if range.start > range.end {
+        return Err("Range start is after range end".to_string());
+    }
+    let client = supabase::client()?;
+    let consignor = fetch_consignor(session, &client, consignor_id).await?;
+    let sales = consigned_sales(fees, session, &client, &consignor).await?;
+    let settled = payouts(session, &client, consignor_id).await?;
+
+    let owed_before: f64 = sales.iter().filter(|l| l.sold_on < range.start).map(|l| l.consignor_share).sum();
+    let paid_before: f64 = settled.iter().filter(|p| p.paid_on < range.start).map(|p| p.amount).sum();
+    let lines: Vec<StatementLine> = sales.into_iter().filter(|l| in_range(&l.sold_on, &range)).collect();
+    let payouts: Vec<ConsignorPayout> = settled.into_iter().filter(|p| in_range(&p.paid_on, &range)).collect();
+
+    let mut totals = StatementTotals { sales: lines.len(), ..Default::default() };
+    for line in &lines {
+        totals.gross += line.sale_price;
+        totals.fees += line.fees;
+        totals.net += line.net;
+        totals.consignor_share += line.consignor_share;
+        totals.commission += line.commission;
+    }
+    totals.paid_out = payouts.iter().map(|p| p.amount).sum();
+    for amount in [
+        &mut totals.gross,
+        &mut totals.fees,
+        &mut totals.net,
+        &mut totals.consignor_share,
+        &mut totals.commission,
+        &mut totals.paid_out,
+    ] {
+        *amount = round_cents(*amount);
+    }
+    let opening_balance = round_cents(owed_before - paid_before);
+    Ok(ConsignorStatement {
+        consignor,
+        start: range.start,
+        end: range.end,
+        balance_owed: round_cents(opening_balance + totals.consignor_share - totals.paid_out),
+        opening_balance,
+        lines,
+        payouts,
+        totals,
+    })
+}
+
+/// Itemized statement of what `consignor_id` is owed for sales in `range`.
+#[tauri::command]
+pub async fn get_consignor_statement(
+    fees: tauri::State<'_, FeeScheduleState>,
+    session: SupabaseSession,
+    consignor_id: String,
+    range: DateRange,
+) -> Result<Private<ConsignorStatement>, String> {
+    statement(&fees, &session, &consignor_id, range).await.map(Private::new)
+}
+
+fn money(amount: f64) -> String {
+    format!("{amount:.2}")
+}
+
+fn render(statement: &ConsignorStatement, paper: PaperSize) -> Vec<u8> {
+    use pdf_layout::{draw_text, FONT, FONT_BOLD};
+
+    let page = paper.dimensions();
+    let margin = 40.0;
+    let columns = [margin, margin + 62.0, margin + 232.0, margin + 292.0, margin + 352.0, margin + 412.0, margin + 472.0];
+    let mut doc = PdfDocument::new();
+    let mut content = Content::new();
+    let mut y = page.1 - margin;
+    let line = |content: &mut Content, y: &mut f32, font, size: f32, text: &str| {
+        draw_text(content, font, size, margin, *y, &pdf_layout::fit_text(text, size, page.0 - 2.0 * margin));
+        *y -= size * 1.6;
+    };
+
+    let c = &statement.consignor;
+    line(&mut content, &mut y, FONT_BOLD, 16.0, &format!("Consignment statement: {}", c.name));
+    let contact: Vec<&str> = [c.email.as_deref(), c.phone.as_deref()].into_iter().flatten().collect();
+    if !contact.is_empty() {
+        line(&mut content, &mut y, FONT, 10.0, &contact.join("  "));
+    }
+    line(&mut content, &mut y, FONT, 10.0, &format!("{} to {}", statement.start, statement.end));
+    line(&mut content, &mut y, FONT, 10.0, &format!("Balance brought forward: {}", money(statement.opening_balance)));
+    y -= 8.0;
+
+    let header = ["Date", "Item", "Price", "Fees", "Net", "Share", "Commission"];
+    for (x, h) in columns.iter().zip(header) {
+        draw_text(&mut content, FONT_BOLD, 9.0, *x, y, h);
+    }
+    y -= 14.0;
+    for l in &statement.lines {
+        if y < margin + 40.0 {
+            doc.add_page(page, std::mem::replace(&mut content, Content::new()));
+            y = page.1 - margin;
+        }
+        let fees = if l.fees_estimated { format!("{}*", money(l.fees)) } else { money(l.fees) };
+        let cells = [
+            l.sold_on.clone(),
+            pdf_layout::fit_text(&l.name, 9.0, 165.0),
+            money(l.sale_price),
+            fees,
+            money(l.net),
+            format!("{} ({}%)", money(l.consignor_share), l.split_percent),
+            money(l.commission),
+        ];
+        for (x, cell) in columns.iter().zip(&cells) {
+            draw_text(&mut content, FONT, 9.0, *x, y, cell);
+        }
+        y -= 14.0;
+    }
+    let t = &statement.totals;
+    let cells = [
+        "Total".to_string(),
+        format!("{} sales", t.sales),
+        money(t.gross),
+        money(t.fees),
+        money(t.net),
+        money(t.consignor_share),
+        money(t.commission),
+    ];
+    for (x, cell) in columns.iter().zip(&cells) {
+        draw_text(&mut content, FONT_BOLD, 9.0, *x, y, cell);
+    }
+    y -= 24.0;
+
+    if y < margin + 80.0 {
+        doc.add_page(page, std::mem::replace(&mut content, Content::new()));
+        y = page.1 - margin;
+    }
+    line(&mut content, &mut y, FONT_BOLD, 12.0, "Payouts");
+    if statement.payouts.is_empty() {
+        line(&mut content, &mut y, FONT, 10.0, "None in this period.");
+    }
+    for p in &statement.payouts {
+        let note = p.note.as_deref().map(|n| format!("  {n}")).unwrap_or_default();
+        line(&mut content, &mut y, FONT, 10.0, &format!("{}  {}{note}", p.paid_on, money(p.amount)));
+    }
+    y -= 8.0;
+    line(&mut content, &mut y, FONT_BOLD, 12.0, &format!("Balance owed: {}", money(statement.balance_owed)));
+    if statement.lines.iter().any(|l| l.fees_estimated) {
+        line(&mut content, &mut y, FONT, 8.0, "* Fees estimated from the marketplace fee schedule on the sale date.");
+    }
+    doc.add_page(page, content);
+    doc.finish()
+}
+
+#[derive(Serialize)]
+pub struct ConsignorStatementPdf {
+    mime: &'static str,
+    /// File contents, base64-encoded.
+    data: String,
+}
+
+/// The same statement as `get_consignor_statement`, as a printable PDF.
+#[tauri::command]
+pub async fn generate_consignor_statement_pdf(
+    fees: tauri::State<'_, FeeScheduleState>,
+    session: SupabaseSession,
+    consignor_id: String,
+    range: DateRange,
+    paper: Option<PaperSize>,
+) -> Result<ConsignorStatementPdf, String> {
+    crate::privacy::ensure_off("generate the consignor statement")?;
+    let statement = statement(&fees, &session, &consignor_id, range).await?;
+    Ok(ConsignorStatementPdf {
+        mime: "application/pdf",
+        data: base64::engine::general_purpose::STANDARD.encode(render(&statement, paper.unwrap_or_default())),
+    })
+}
+
+#[derive(Serialize)]
+pub struct RecordedPayout {
+    payout: ConsignorPayout,
+    /// Owed after this payout, over all sales and payouts to date.
+    balance_owed: f64,
+}
+
+/// Record a settlement paid to `consignor_id` on `date` (`YYYY-MM-DD`).
+#[tauri::command]
+pub async fn record_consignor_payout(
+    fees: tauri::State<'_, FeeScheduleState>,
+    session: SupabaseSession,
+    consignor_id: String,
+    amount: f64,
+    date: String,
+    note: Option<String>,
+) -> Result<Private<RecordedPayout>, String> {
+    if !amount.is_finite() || amount <= 0.0 {
+        return Err("The payout amount must be positive".to_string());
+    }
+    let date = date.trim().to_string();
+    if !crate::custom_fields::valid_date(&date) {
+        return Err(format!("Invalid date {date:?}; expected YYYY-MM-DD"));
+    }
+    let client = supabase::client()?;
+    let row = json!({
+        "user_id": session.user_id(&client).await?,
+        "consignor_id": consignor_id,
+        "amount": round_cents(amount),
+        "paid_on": date,
+        "note": note.filter(|n| !n.trim().is_empty()),
+    });
+    let req = session.rest(&client, Method::POST, "consignor_payouts").header("Prefer", "return=representation").json(&row);
+    let saved = rows(supabase::send(req).await?).into_iter().next().ok_or("Payout wasn't saved")?;
+    let payout = ConsignorPayout {
+        id: saved["id"].as_str().unwrap_or_default().to_string(),
+        amount: number(&saved["amount"]).unwrap_or(amount),
+        paid_on: date,
+        note: saved["note"].as_str().map(str::to_string),
+    };
+    let today = crate::fees::today();
+    let to_date = DateRange { start: "0000-01-01".to_string(), end: today.max(payout.paid_on.clone()) };
+    let balance_owed = statement(&fees, &session, &consignor_id, to_date).await?.balance_owed;
+    Ok(Private::new(RecordedPayout { payout, balance_owed }))
+}
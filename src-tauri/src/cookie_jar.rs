@@ -0,0 +1,257 @@
+// ── native_fetch cookie jar ────────────────────────────────────────────────
+// Some marketplace flows need a session set up by an earlier request (a CSRF
+// cookie fetched from one URL, then sent with a POST to another). Requests
+// made with `use_cookies` share a jar; passing `session` picks a separate,
+// named jar instead (one per marketplace login, say) and implies
+// `use_cookies`.
+//
+// Cookies with an expiry are written to disk (cookie_jar.json) by
+// `start_persist_job` shortly after they change, so logins survive a
+// restart; session cookies last until the app quits, as in a browser.
+//
+// Cookies are kept and sent per RFC 6265 domain and path matching, so a
+// Depop session never reaches eBay and vice versa. `get_cookies` and
+// `clear_cookie_jar` work per domain for the same reason; `clear_cookies`
+// empties a whole jar.
+
+use reqwest::header::HeaderValue;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tauri::Manager;
+
+const STORE: &str = "cookie_jar";
+/// The jar used without a `session`.
+const DEFAULT_SESSION: &str = "default";
+const MAX_SESSIONS: usize = 32;
+const PERSIST_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Default)]
+pub struct CookieJar {
+    store: RwLock<cookie_store::CookieStore>,
+    /// Changed since the last save.
+    dirty: AtomicBool,
+}
+
+impl reqwest::cookie::CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &reqwest::Url) {
+        let cookies = cookie_headers.filter_map(|v| {
+            let v = std::str::from_utf8(v.as_bytes()).ok()?;
+            cookie_store::RawCookie::parse(v).ok().map(|c| c.into_owned())
+        });
+        self.store.write().unwrap().store_response_cookies(cookies, url);
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    fn cookies(&self, url: &reqwest::Url) -> Option<HeaderValue> {
+        let header = self
+            .store
+            .read()
+            .unwrap()
+            .get_request_values(url)
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if header.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&header).ok()
+    }
+}
+
+/// Named jars; each lives for the whole run so clients built around one
+/// stay valid after it's cleared.
+pub struct CookieJarState {
+    jars: Mutex<HashMap<String, Arc<CookieJar>>>,
+}
+
+impl CookieJarState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        let saved: BTreeMap<String, Vec<cookie_store::Cookie<'static>>> = crate::store::load(app, STORE);
+        let jars = saved
+            .into_iter()
+            .filter_map(|(session, cookies)| {
+                let store = cookie_store::CookieStore::from_cookies(cookies.into_iter().map(Ok::<_, ()>), false).ok()?;
+                Some((session, Arc::new(CookieJar { store: RwLock::new(store), dirty: AtomicBool::new(false) })))
+            })
+            .collect();
+        CookieJarState { jars: Mutex::new(jars) }
+    }
+
+    /// The jar for `session` (the default jar when None), created on first use.
+    pub fn jar(&self, session: Option<&str>) -> Result<Arc<CookieJar>, String> {
+        let name = session_name(session)?;
+        let mut jars = self.jars.lock().unwrap();
+        if let Some(jar) = jars.get(&name) {
+            return Ok(jar.clone());
+        }
+        if jars.len() >= MAX_SESSIONS {
+            return Err(format!("At most {MAX_SESSIONS} cookie sessions can exist at once"));
+        }
+        Ok(jars.entry(name).or_default().clone())
+    }
+
+    /// The jar a request should use: `session`'s when one is named, the
+    /// default jar with `use_cookies`, otherwise none.
+    pub fn for_request(&self, use_cookies: Option<bool>, session: Option<&str>) -> Result<Option<Arc<CookieJar>>, String> {
+        if session.is_none() && !use_cookies.unwrap_or(false) {
+            return Ok(None);
+        }
+        self.jar(session).map(Some)
+    }
+
+    /// Existing jar for `session`, without creating one.
+    fn existing(&self, session: Option<&str>) -> Result<Option<Arc<CookieJar>>, String> {
+        Ok(self.jars.lock().unwrap().get(&session_name(session)?).cloned())
+    }
+}
+
+fn session_name(session: Option<&str>) -> Result<String, String> {
+    let name = session.map(str::trim).unwrap_or(DEFAULT_SESSION);
+    if name.is_empty() || name.len() > 64 {
+        return Err("Cookie session names must be 1-64 characters".to_string());
+    }
+    Ok(name.to_string())
+}
+
+/// Write every jar's persistent cookies to disk.
+fn save(app: &tauri::AppHandle, state: &CookieJarState) -> Result<(), String> {
+    let jars: Vec<(String, Arc<CookieJar>)> =
+        state.jars.lock().unwrap().iter().map(|(name, jar)| (name.clone(), jar.clone())).collect();
+    let mut saved = BTreeMap::new();
+    for (name, jar) in jars {
+        jar.dirty.store(false, Ordering::Relaxed);
+        let store = jar.store.read().unwrap();
+        let cookies: Vec<cookie_store::Cookie<'static>> =
+            store.iter_unexpired().filter(|c| c.is_persistent()).cloned().collect();
+        if !cookies.is_empty() {
+            saved.insert(name, cookies);
+        }
+    }
+    crate::store::save(app, STORE, &saved)
+}
+
+/// Save the jars shortly after any of them changes.
+pub fn start_persist_job(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(PERSIST_INTERVAL).await;
+            let state = app.state::<CookieJarState>();
+            let changed = state.jars.lock().unwrap().values().any(|jar| jar.dirty.load(Ordering::Relaxed));
+            if changed {
+                if let Err(e) = save(&app, &state) {
+                    log::warn!("saving cookie jar failed: {e}");
+                }
+            }
+        }
+    });
+}
+
+#[derive(Serialize)]
+pub struct CookieInfo {
+    name: String,
+    value: String,
+    domain: String,
+    path: String,
+    secure: bool,
+    http_only: bool,
+    /// Has an expiry; otherwise it lasts until the jar is cleared.
+    persistent: bool,
+}
+
+/// Whether a cookie stored for `cookie_domain` belongs to `domain`: the same
+/// host, a subdomain of it, or a parent domain it would be sent to.
+fn belongs_to(cookie_domain: &str, domain: &str) -> bool {
+    cookie_domain == domain
+        || cookie_domain.ends_with(&format!(".{domain}"))
+        || domain.ends_with(&format!(".{cookie_domain}"))
+}
+
+fn normalise(domain: &str) -> String {
+    domain.trim().trim_start_matches('.').to_ascii_lowercase()
+}
+
+#[tauri::command]
+pub fn get_cookies(
+    state: tauri::State<'_, CookieJarState>,
+    domain: String,
+    session: Option<String>,
+) -> Result<Vec<CookieInfo>, String> {
+    let domain = normalise(&domain);
+    let Some(jar) = state.existing(session.as_deref())? else {
+        return Ok(Vec::new());
+    };
+    let store = jar.store.read().unwrap();
+    let cookies = store
+        .iter_unexpired()
+        .filter_map(|c| {
+            let cookie_domain = c.domain.as_cow()?.into_owned();
+            belongs_to(&cookie_domain, &domain).then(|| CookieInfo {
+                name: c.name().to_string(),
+                value: c.value().to_string(),
+                domain: cookie_domain,
+                path: c.path.to_string(),
+                secure: c.secure().unwrap_or(false),
+                http_only: c.http_only().unwrap_or(false),
+                persistent: c.is_persistent(),
+            })
+        })
+        .collect();
+    Ok(cookies)
+}
+
+/// Drop the cookies for `domain` from `session`'s jar (the default jar when
+/// None), or every cookie in it when no domain is given. Returns how many
+/// were removed.
+#[tauri::command]
+pub fn clear_cookie_jar(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, CookieJarState>,
+    domain: Option<String>,
+    session: Option<String>,
+) -> Result<usize, String> {
+    let Some(jar) = state.existing(session.as_deref())? else {
+        return Ok(0);
+    };
+    let removed = {
+        let mut store = jar.store.write().unwrap();
+        match domain.map(|d| normalise(&d)) {
+            None => {
+                let count = store.iter_any().count();
+                store.clear();
+                count
+            }
+            Some(domain) => {
+                let doomed: Vec<(String, String, String)> = store
+                    .iter_any()
+                    .filter_map(|c| {
+                        let cookie_domain = c.domain.as_cow()?.into_owned();
+                        belongs_to(&cookie_domain, &domain)
+                            .then(|| (cookie_domain, c.path.to_string(), c.name().to_string()))
+                    })
+                    .collect();
+                for (domain, path, name) in &doomed {
+                    store.remove(domain, path, name);
+                }
+                doomed.len()
+            }
+        }
+    };
+    if removed > 0 {
+        save(&app, &state)?;
+    }
+    Ok(removed)
+}
+
+/// Empty `session`'s jar (the default jar when None). Returns how many
+/// cookies were removed.
+#[tauri::command]
+pub fn clear_cookies(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, CookieJarState>,
+    session: Option<String>,
+) -> Result<usize, String> {
+    clear_cookie_jar(app, state, None, session)
+}
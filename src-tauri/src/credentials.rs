@@ -0,0 +1,189 @@
+// ── Credentials ────────────────────────────────────────────────────────────
+// Secrets the frontend would otherwise keep in localStorage (the captured
+// Depop token, API keys) live in the OS keychain: Windows Credential
+// Manager, the macOS Keychain or the Secret Service on Linux. Entries are
+// namespaced under "FlipTools/<service>" so these commands can't read other
+// apps' keychain entries.
+//
+// Linux desktops without a running Secret Service (minimal window managers,
+// some headless setups) fall back to an AES-256-GCM encrypted file in the
+// app data directory, keyed by a random key file next to it. That only
+// protects the secret as well as the user's account does, so results say so
+// in `warning`.
+
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+const SERVICE_PREFIX: &str = "FlipTools/";
+const FALLBACK_STORE: &str = "credentials_fallback";
+const KEY_FILE: &str = "credentials.key";
+const FALLBACK_WARNING: &str = "No keychain service is running, so this was stored in an encrypted file in the app data \
+     folder instead. Anyone who can read your user account's files can recover it; install and unlock a Secret \
+     Service keyring (e.g. GNOME Keyring or KWallet) for proper protection.";
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Storage {
+    Keychain,
+    EncryptedFile,
+}
+
+#[derive(Serialize)]
+pub struct CredentialResult {
+    pub storage: Storage,
+    pub warning: Option<String>,
+}
+
+impl CredentialResult {
+    pub fn new(storage: Storage) -> Self {
+        let warning = (storage == Storage::EncryptedFile).then(|| FALLBACK_WARNING.to_string());
+        CredentialResult { storage, warning }
+    }
+}
+
+fn entry(service: &str, account: &str) -> Result<keyring::Entry, String> {
+    let (service, account) = (service.trim(), account.trim());
+    if service.is_empty() || account.is_empty() {
+        return Err("Service and account can't be empty".to_string());
+    }
+    keyring::Entry::new(&format!("{SERVICE_PREFIX}{service}"), account).map_err(|e| format!("keychain: {e}"))
+}
+
+/// Whether `e` means there's no keychain to talk to, rather than a problem
+/// with the entry. Only Linux falls back; elsewhere the keychain is always there.
+fn unavailable(e: &keyring::Error) -> bool {
+    cfg!(target_os = "linux") && matches!(e, keyring::Error::NoStorageAccess(_) | keyring::Error::PlatformFailure(_))
+}
+
+// ── Encrypted file fallback ────────────────────────────────────────────────
+
+fn fallback_key(app: &tauri::AppHandle) -> Result<LessSafeKey, String> {
+    let path = crate::store::data_path(app, KEY_FILE)?;
+    let bytes = match std::fs::read(&path) {
+        Ok(bytes) if bytes.len() == 32 => bytes,
+        Ok(_) => return Err(format!("{} is damaged", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let key: [u8; 32] = rand::random();
+            std::fs::write(&path, key).map_err(|e| format!("write {}: {e}", path.display()))?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600));
+            }
+            key.to_vec()
+        }
+        Err(e) => return Err(format!("read {}: {e}", path.display())),
+    };
+    let key = UnboundKey::new(&AES_256_GCM, &bytes).map_err(|_| "invalid credential key".to_string())?;
+    Ok(LessSafeKey::new(key))
+}
+
+fn fallback_id(service: &str, account: &str) -> String {
+    format!("{}\n{}", service.trim(), account.trim())
+}
+
+fn fallback_save(app: &tauri::AppHandle, service: &str, account: &str, secret: &str) -> Result<(), String> {
+    let key = fallback_key(app)?;
+    let id = fallback_id(service, account);
+    let nonce_bytes: [u8; NONCE_LEN] = rand::random();
+    let mut sealed = secret.as_bytes().to_vec();
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce_bytes), Aad::from(id.as_bytes()), &mut sealed)
+        .map_err(|_| "encrypting the credential failed".to_string())?;
+    let mut blob = nonce_bytes.to_vec();
+    blob.extend(sealed);
+    let mut entries: BTreeMap<String, String> = crate::store::load(app, FALLBACK_STORE);
+    entries.insert(id, base64::engine::general_purpose::STANDARD.encode(blob));
+    crate::store::save(app, FALLBACK_STORE, &entries)
+}
+
+fn fallback_get(app: &tauri::AppHandle, service: &str, account: &str) -> Result<Option<String>, String> {
+    let id = fallback_id(service, account);
+    let entries: BTreeMap<String, String> = crate::store::load(app, FALLBACK_STORE);
+    let Some(encoded) = entries.get(&id) else {
+        return Ok(None);
+    };
+    let blob = base64::engine::general_purpose::STANDARD.decode(encoded).map_err(|_| "stored credential is damaged")?;
+    if blob.len() < NONCE_LEN {
+        return Err("stored credential is damaged".to_string());
+    }
+    let (nonce, sealed) = blob.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| "stored credential is damaged")?;
+    let mut sealed = sealed.to_vec();
+    let plain = fallback_key(app)?
+        .open_in_place(nonce, Aad::from(id.as_bytes()), &mut sealed)
+        .map_err(|_| "stored credential can't be decrypted (was the key file replaced?)")?;
+    String::from_utf8(plain.to_vec()).map(Some).map_err(|_| "stored credential is damaged".to_string())
+}
+
+fn fallback_delete(app: &tauri::AppHandle, service: &str, account: &str) -> Result<bool, String> {
+    let mut entries: BTreeMap<String, String> = crate::store::load(app, FALLBACK_STORE);
+    if entries.remove(&fallback_id(service, account)).is_none() {
+        return Ok(false);
+    }
+    crate::store::save(app, FALLBACK_STORE, &entries)?;
+    Ok(true)
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+pub fn save(app: &tauri::AppHandle, service: &str, account: &str, secret: &str) -> Result<Storage, String> {
+    if secret.is_empty() {
+        return Err("Secret can't be empty".to_string());
+    }
+    match entry(service, account)?.set_password(secret) {
+        Ok(()) => {
+            // Drop any copy an earlier fallback left on disk.
+            let _ = fallback_delete(app, service, account);
+            Ok(Storage::Keychain)
+        }
+        Err(e) if unavailable(&e) => {
+            log::warn!("keychain unavailable ({e}); storing {service} credential in the encrypted file");
+            fallback_save(app, service, account, secret)?;
+            Ok(Storage::EncryptedFile)
+        }
+        Err(e) => Err(format!("keychain: {e}")),
+    }
+}
+
+pub fn get(app: &tauri::AppHandle, service: &str, account: &str) -> Result<Option<String>, String> {
+    match entry(service, account)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => fallback_get(app, service, account),
+        Err(e) if unavailable(&e) => fallback_get(app, service, account),
+        Err(e) => Err(format!("keychain: {e}")),
+    }
+}
+
+pub fn delete(app: &tauri::AppHandle, service: &str, account: &str) -> Result<bool, String> {
+    let in_keychain = match entry(service, account)?.delete_credential() {
+        Ok(()) => true,
+        Err(keyring::Error::NoEntry) => false,
+        Err(e) if unavailable(&e) => false,
+        Err(e) => return Err(format!("keychain: {e}")),
+    };
+    Ok(fallback_delete(app, service, account)? || in_keychain)
+}
+
+#[tauri::command]
+pub fn save_credential(
+    app: tauri::AppHandle,
+    service: String,
+    account: String,
+    secret: String,
+) -> Result<CredentialResult, String> {
+    save(&app, &service, &account, &secret).map(CredentialResult::new)
+}
+
+/// The stored secret, or None when there isn't one.
+#[tauri::command]
+pub fn get_credential(app: tauri::AppHandle, service: String, account: String) -> Result<Option<String>, String> {
+    get(&app, &service, &account)
+}
+
+/// Remove a stored secret. False when there wasn't one.
+#[tauri::command]
+pub fn delete_credential(app: tauri::AppHandle, service: String, account: String) -> Result<bool, String> {
+    delete(&app, &service, &account)
+}
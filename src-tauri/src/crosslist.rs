@@ -0,0 +1,360 @@
+// ── Crosslisting matrix ────────────────────────────────────────────────────
+// One call gives the inventory grid a badge per item and marketplace. Three
+// things go into a cell:
+//   - the item's listing and its per-marketplace links (`listings.platforms`
+//     in Supabase), read in one query per 200 SKUs,
+//   - what the last Depop sync saw of the listing (depop/sync.rs), which
+//     wins over the stored status because it came from Depop itself,
+//   - any write to that listing still pending, or whose outcome is unknown
+//     (write_queue.rs).
+//
+// Whenever a sync or a queued write changes what a cell would show,
+// `crosslist-status-changed` is emitted with the marketplace and listing ids
+// so the grid can ask again for just those rows.
+
+use crate::supabase::{self, SupabaseSession};
+use crate::write_queue::IntentStatus;
+use reqwest::Method;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tauri::Emitter;
+
+/// SKUs per query, to keep the URL a sensible length.
+const CHUNK: usize = 200;
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CellState {
+    Listed,
+    Unlisted,
+    Sold,
+    Error,
+}
+
+#[derive(Serialize)]
+pub struct Cell {
+    pub state: CellState,
+    /// The marketplace's id for the listing.
+    pub listing_id: Option<String>,
+    pub url: Option<String>,
+    /// Operation of a write to this listing that hasn't finished.
+    pub pending: Option<String>,
+    /// A write to this listing may or may not have happened; see
+    /// `list_write_intents`.
+    pub outcome_unknown: bool,
+    /// The state was confirmed by a marketplace sync.
+    pub synced: bool,
+}
+
+#[derive(Serialize)]
+pub struct CrosslistMatrix {
+    /// SKU -> marketplace -> cell. Items that aren't listed anywhere have an
+    /// empty map.
+    pub items: HashMap<String, BTreeMap<String, Cell>>,
+    /// Requested SKUs with no live inventory item.
+    pub missing: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct CrosslistChange<'a> {
+    marketplace: &'a str,
+    listing_ids: &'a [&'a str],
+}
+
+/// Tell the grid that cells for these listings may have changed.
+pub fn notify(app: &tauri::AppHandle, marketplace: &str, listing_ids: &[&str]) {
+    if !listing_ids.is_empty() {
+        let _ = app.emit("crosslist-status-changed", CrosslistChange { marketplace, listing_ids });
+    }
+}
+
+fn state_of(status: &str) -> CellState {
+    match status.trim().to_lowercase().as_str() {
+        "sold" | "sold_out" => CellState::Sold,
+        "error" | "failed" | "rejected" => CellState::Error,
+        "ended" | "delisted" | "draft" | "inactive" | "deleted" | "removed" | "hidden" => CellState::Unlisted,
+        _ => CellState::Listed,
+    }
+}
+
+fn cells(
+    listing: &Value,
+    syncs: &crate::depop::sync::ListingSyncState,
+    intents: &crate::write_queue::IntentState,
+) -> BTreeMap<String, Cell> {
+    let sold_on = listing["sold_on_platform"].as_str();
+    let Some(platforms) = listing["platforms"].as_object() else {
+        return BTreeMap::new();
+    };
+    platforms
+        .iter()
+        .map(|(marketplace, link)| {
+            let listing_id = match &link["id"] {
+                Value::String(s) if !s.is_empty() => Some(s.clone()),
+                Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            };
+            let mut state = link["status"].as_str().map_or(CellState::Listed, state_of);
+            if sold_on == Some(marketplace.as_str()) {
+                state = CellState::Sold;
+            }
+            let mut synced = false;
+            if let (Some(id), "depop") = (&listing_id, marketplace.as_str()) {
+                match syncs.last_seen(id) {
+                    crate::depop::sync::LastSeen::Listed { status } => {
+                        state = status.as_deref().map_or(CellState::Listed, state_of);
+                        synced = true;
+                    }
+                    crate::depop::sync::LastSeen::Gone if state == CellState::Listed => {
+                        state = CellState::Unlisted;
+                        synced = true;
+                    }
+                    _ => {}
+                }
+            }
+            let unresolved = listing_id.as_deref().map(|id| intents.unresolved_for(marketplace, id)).unwrap_or_default();
+            let cell = Cell {
+                state,
+                url: link["url"].as_str().map(str::to_string),
+                listing_id,
+                pending: unresolved.first().map(|(operation, _)| operation.clone()),
+                outcome_unknown: unresolved.iter().any(|(_, status)| *status == IntentStatus::Unknown),
+                synced,
+            };
+            (marketplace.clone(), cell)
+        })
+        .collect()
+}
+
+async fn fetch_chunk(session: &SupabaseSession, client: &reqwest::Client, skus: &[String]) -> Result<Vec<Value>, String> {
+    let list: Vec<String> = skus.iter().map(|s| format!("\"{}\"", s.replace(['"', '\\'], ""))).collect();
+    let path = format!(
+        "inventory?select=sku,listing:listings(status,platforms,sold_on_platform)&deleted_at=is.null&sku=in.{}",
+        crate::depop::sync::urlencode(&format!("({})", list.join(",")))
+    );
+    match supabase::send(session.rest(client, Method::GET, &path)).await? {
+        Value::Array(rows) => Ok(rows),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Marketplace status of each item in `skus`, for the inventory grid.
+#[tauri::command]
+pub async fn get_crosslist_matrix(
+    syncs: tauri::State<'_, crate::depop::sync::ListingSyncState>,
+    intents: tauri::State<'_, crate::write_queue::IntentState>,
+    session: SupabaseSession,
+    skus: Vec<String>,
+) -> Result<CrosslistMatrix, String> {
+    matrix(&session, &supabase::client()?, &syncs, &intents, skus).await
+}
+
+async fn matrix(
+    session: &SupabaseSession,
+    client: &reqwest::Client,
+    syncs: &crate::depop::sync::ListingSyncState,
+    intents: &crate::write_queue::IntentState,
+    skus: Vec<String>,
+) -> Result<CrosslistMatrix, String> {
+    let mut seen = HashSet::new();
+    let skus: Vec<String> =
+        skus.into_iter().map(|s| s.trim().to_string()).filter(|s| !s.is_empty() && seen.insert(s.clone())).collect();
+    let chunks = skus.chunks(CHUNK).map(|chunk| fetch_chunk(session, client, chunk));
+    let rows: Vec<Value> = futures_util::future::try_join_all(chunks).await?.into_iter().flatten().collect();
+
+    let mut items = HashMap::with_capacity(rows.len());
+    for row in &rows {
+        let Some(sku) = row["sku"].as_str() else {
+            continue;
+        };
+        items.insert(sku.to_string(), cells(&row["listing"], syncs, intents));
+    }
+    let missing = skus.into_iter().filter(|s| !items.contains_key(s)).collect();
+    Ok(CrosslistMatrix { items, missing })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::depop::sync::ListingSyncState;
+    use crate::write_queue::IntentState;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// A stand-in for PostgREST over a seeded `inventory` table (SKU ->
+    /// row with its listing embedded). It answers the `sku=in.(...)` filter
+    /// and counts the queries.
+    async fn inventory_server(rows: HashMap<String, Value>) -> (SupabaseSession, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (rows, queries) = (Arc::new(rows), Arc::new(AtomicUsize::new(0)));
+        let count = queries.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let (rows, count) = (rows.clone(), count.clone());
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    count.fetch_add(1, Ordering::SeqCst);
+                    let head = String::from_utf8_lossy(&buf).to_string();
+                    let path = head.split_whitespace().nth(1).unwrap_or("/");
+                    let url = reqwest::Url::parse(&format!("http://db{path}")).unwrap();
+                    let filter = url.query_pairs().find(|(k, _)| k == "sku").map(|(_, v)| v.to_string());
+                    let list = filter.as_deref().and_then(|f| f.strip_prefix("in.(")?.strip_suffix(')'));
+                    let found: Vec<&Value> =
+                        list.unwrap_or_default().split(',').filter_map(|sku| rows.get(sku.trim_matches('"'))).collect();
+                    let body = serde_json::to_string(&found).unwrap();
+                    let reply = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                         Connection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                    let _ = stream.write_all(reply.as_bytes()).await;
+                });
+            }
+        });
+        let session = serde_json::from_value(json!({
+            "url": format!("http://{addr}/"),
+            "anon_key": "anon",
+            "access_token": "token",
+        }))
+        .unwrap();
+        (session, queries)
+    }
+
+    fn row(sku: &str, listing: Value) -> (String, Value) {
+        (sku.to_string(), json!({ "sku": sku, "listing": listing }))
+    }
+
+    fn strings(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn cells_join_links_syncs_and_pending_writes() {
+        let rows = HashMap::from([
+            row(
+                "JKT-1",
+                json!({
+                    "status": "active",
+                    "sold_on_platform": null,
+                    "platforms": {
+                        "depop": { "id": "d-1", "status": "active", "url": "https://depop.com/products/d-1" },
+                        "ebay": { "id": 123, "status": "ended" },
+                    },
+                }),
+            ),
+            row(
+                "JKT-2",
+                json!({
+                    "status": "sold",
+                    "sold_on_platform": "vinted",
+                    "platforms": { "depop": { "id": "d-2" }, "vinted": { "id": "v-2", "status": "active" } },
+                }),
+            ),
+            row("JKT-3", Value::Null),
+        ]);
+        let (session, queries) = inventory_server(rows).await;
+        // The last full sync saw d-1 sold, and didn't see d-2 at all.
+        let syncs = ListingSyncState::synced(&[("d-1", Some("sold"))]);
+        let intents = IntentState::with(&[
+            ("ebay", "relist", "123", IntentStatus::Unknown),
+            ("ebay", "update", "123", IntentStatus::Pending),
+            ("ebay", "delist", "456", IntentStatus::Pending),
+            ("depop", "update", "d-1", IntentStatus::Succeeded),
+        ]);
+
+        let skus = strings(&["JKT-1", " JKT-1 ", "JKT-2", "", "JKT-3", "GONE-9"]);
+        let client = reqwest::Client::new();
+        let result = matrix(&session, &client, &syncs, &intents, skus).await.unwrap();
+        assert_eq!(queries.load(Ordering::SeqCst), 1);
+        assert_eq!(result.missing, strings(&["GONE-9"]));
+        assert_eq!(result.items.len(), 3);
+
+        let summary = |sku: &str| {
+            result.items[sku]
+                .iter()
+                .map(|(m, c)| {
+                    let (id, pending) = (c.listing_id.as_deref(), c.pending.as_deref());
+                    (m.as_str(), c.state, id, pending, c.outcome_unknown, c.synced)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            summary("JKT-1"),
+            vec![
+                ("depop", CellState::Sold, Some("d-1"), None, false, true),
+                ("ebay", CellState::Unlisted, Some("123"), Some("update"), true, false),
+            ]
+        );
+        assert_eq!(result.items["JKT-1"]["depop"].url.as_deref(), Some("https://depop.com/products/d-1"));
+        assert_eq!(
+            summary("JKT-2"),
+            vec![
+                ("depop", CellState::Unlisted, Some("d-2"), None, false, true),
+                ("vinted", CellState::Sold, Some("v-2"), None, false, false),
+            ]
+        );
+        assert!(result.items["JKT-3"].is_empty());
+    }
+
+    /// The grid asks for 500 SKUs at a time and should get them back in
+    /// under 50ms. The seeded table is served from memory over loopback, so
+    /// this times the queries, the chunking and the join rather than
+    /// Postgres itself. Run with `cargo test --release -- --ignored`.
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "timing benchmark"]
+    async fn five_hundred_skus_come_back_within_50ms() {
+        let rows: HashMap<String, Value> = (0..10_000)
+            .map(|i| {
+                let status = ["active", "sold", "ended", "draft"][i % 4];
+                row(
+                    &format!("SKU-{i:05}"),
+                    json!({
+                        "status": status,
+                        "sold_on_platform": (i % 4 == 1).then_some("depop"),
+                        "platforms": {
+                            "depop": { "id": format!("d-{i}"), "status": status },
+                            "ebay": { "id": i, "status": status, "url": format!("https://ebay.com/itm/{i}") },
+                            "vinted": { "id": format!("v-{i}") },
+                        },
+                    }),
+                )
+            })
+            .collect();
+        let (session, queries) = inventory_server(rows).await;
+        let ids: Vec<String> = (0..10_000).step_by(2).map(|i| format!("d-{i}")).collect();
+        let seen: Vec<(&str, Option<&str>)> = ids.iter().map(|id| (id.as_str(), Some("active"))).collect();
+        let syncs = ListingSyncState::synced(&seen);
+        let targets: Vec<String> = (0..10_000).step_by(7).map(|i| i.to_string()).collect();
+        let pending: Vec<(&str, &str, &str, IntentStatus)> =
+            targets.iter().map(|t| ("ebay", "update", t.as_str(), IntentStatus::Pending)).collect();
+        let intents = IntentState::with(&pending);
+        let client = reqwest::Client::new();
+        let skus: Vec<String> = (0..500).map(|i| format!("SKU-{:05}", i * 19)).collect();
+
+        // Warm the connection pool, then keep the best of a few runs.
+        matrix(&session, &client, &syncs, &intents, skus.clone()).await.unwrap();
+        let mut best = std::time::Duration::MAX;
+        for _ in 0..5 {
+            let started = std::time::Instant::now();
+            let result = matrix(&session, &client, &syncs, &intents, skus.clone()).await.unwrap();
+            best = best.min(started.elapsed());
+            assert_eq!(result.items.len(), 500);
+            assert!(result.missing.is_empty());
+        }
+        // One query per CHUNK SKUs, per run.
+        assert_eq!(queries.load(Ordering::SeqCst), 6 * skus.len().div_ceil(CHUNK));
+        println!("500 SKUs in {best:?}");
+        assert!(best < std::time::Duration::from_millis(50), "500 SKUs took {best:?}");
+    }
+}
@@ -0,0 +1,426 @@
+// ── Custom inventory fields ────────────────────────────────────────────────
+// User-defined fields on inventory items (consignor, source bin, cert
+// number, ...). Definitions live here; values travel with the item as a JSON
+// object keyed by field id, and every write goes through
+// `validate_custom_field_values`, which type-checks and normalises them.
+//
+// Values are keyed by id, not name, so renaming a field never touches them.
+// Removing a field is always explicit: archiving keeps its values (read-only),
+// migrating points it at another field and values move across the next time
+// the item is validated. Removing a select option needs a replacement for the
+// same reason.
+
+use crate::depop::attributes::FieldError;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const STORE_NAME: &str = "custom_fields";
+const MAX_TEXT_CHARS: usize = 500;
+const MAX_NAME_CHARS: usize = 40;
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    Text,
+    Number,
+    /// "YYYY-MM-DD".
+    Date,
+    Select,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CustomField {
+    pub id: String,
+    pub name: String,
+    pub field_type: FieldType,
+    /// Allowed values for `select`.
+    #[serde(default)]
+    pub options: Vec<String>,
+    #[serde(default)]
+    pub required: bool,
+    pub created_at: u64,
+    #[serde(default)]
+    pub archived_at: Option<u64>,
+    /// Set when the field was deleted in favour of another one.
+    #[serde(default)]
+    pub migrated_into: Option<String>,
+    /// Removed select option -> the option that replaced it.
+    #[serde(default)]
+    pub option_renames: HashMap<String, String>,
+}
+
+impl CustomField {
+    fn active(&self) -> bool {
+        self.archived_at.is_none() && self.migrated_into.is_none()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum RemoveMode {
+    /// Hide the field but keep its values.
+    Archive,
+    /// Move values into another field of a compatible type.
+    MigrateInto { field: String },
+}
+
+#[derive(Serialize)]
+pub struct ValidatedValues {
+    values: Map<String, Value>,
+    errors: Vec<FieldError>,
+}
+
+pub struct CustomFieldState {
+    fields: Mutex<Vec<CustomField>>,
+}
+
+impl CustomFieldState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        CustomFieldState { fields: Mutex::new(crate::store::load(app, STORE_NAME)) }
+    }
+
+    /// Fields that can take new values (not archived or migrated).
+    pub fn active_fields(&self) -> Vec<CustomField> {
+        self.fields.lock().unwrap().iter().filter(|f| f.active()).cloned().collect()
+    }
+}
+
+fn slug(name: &str) -> String {
+    let mut out = String::new();
+    for c in name.trim().chars() {
+        if c.is_ascii_alphanumeric() {
+            out.push(c.to_ascii_lowercase());
+        } else if !out.ends_with('_') && !out.is_empty() {
+            out.push('_');
+        }
+    }
+    let out = out.trim_end_matches('_').to_string();
+    if out.is_empty() { "field".to_string() } else { out }
+}
+
+fn clean_name(fields: &[CustomField], name: &str, except: Option<&str>) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Field name is required".to_string());
+    }
+    if name.chars().count() > MAX_NAME_CHARS {
+        return Err(format!("Field names are at most {MAX_NAME_CHARS} characters"));
+    }
+    if fields
+        .iter()
+        .any(|f| f.active() && Some(f.id.as_str()) != except && f.name.eq_ignore_ascii_case(name))
+    {
+        return Err(format!("A field called '{name}' already exists"));
+    }
+    Ok(name.to_string())
+}
+
+fn clean_options(field_type: FieldType, options: Vec<String>) -> Result<Vec<String>, String> {
+    if field_type != FieldType::Select {
+        return Ok(Vec::new());
+    }
+    let mut out: Vec<String> = Vec::new();
+    for o in options.into_iter().map(|o| o.trim().to_string()).filter(|o| !o.is_empty()) {
+        if !out.iter().any(|x| x.eq_ignore_ascii_case(&o)) {
+            out.push(o);
+        }
+    }
+    if out.is_empty() {
+        return Err("A select field needs at least one option".to_string());
+    }
+    Ok(out)
+}
+
+pub fn valid_date(s: &str) -> bool {
+    let parts: Vec<&str> = s.split('-').collect();
+    let [y, m, d] = parts[..] else {
+        return false;
+    };
+    let (Ok(y), Ok(m), Ok(d)) = (y.parse::<u32>(), m.parse::<u32>(), d.parse::<u32>()) else {
+        return false;
+    };
+    let leap = (y % 4 == 0 && y % 100 != 0) || y % 400 == 0;
+    let days = match m {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    s.len() == 10 && (1000..=9999).contains(&y) && (1..=days).contains(&d)
+}
+
+/// Type-check one value for `field`, returning the normalised value.
+fn coerce(field: &CustomField, value: &Value) -> Result<Value, String> {
+    match (field.field_type, value) {
+        (_, Value::Null) => Ok(Value::Null),
+        (FieldType::Text, Value::String(s)) => {
+            if s.chars().count() > MAX_TEXT_CHARS {
+                return Err(format!("{} is longer than {MAX_TEXT_CHARS} characters", field.name));
+            }
+            Ok(Value::String(s.trim().to_string()))
+        }
+        (FieldType::Text, Value::Number(n)) => Ok(Value::String(n.to_string())),
+        (FieldType::Number, Value::Number(n)) => Ok(Value::Number(n.clone())),
+        (FieldType::Number, Value::String(s)) if s.trim().is_empty() => Ok(Value::Null),
+        (FieldType::Number, Value::String(s)) => s
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|n| n.is_finite())
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number)
+            .ok_or_else(|| format!("{} must be a number", field.name)),
+        (FieldType::Date, Value::String(s)) if s.trim().is_empty() => Ok(Value::Null),
+        (FieldType::Date, Value::String(s)) if valid_date(s.trim()) => Ok(Value::String(s.trim().to_string())),
+        (FieldType::Date, _) => Err(format!("{} must be a date (YYYY-MM-DD)", field.name)),
+        (FieldType::Select, Value::String(s)) if s.trim().is_empty() => Ok(Value::Null),
+        (FieldType::Select, Value::String(s)) => {
+            let s = field.option_renames.get(s.trim()).map_or(s.trim(), String::as_str);
+            field
+                .options
+                .iter()
+                .find(|o| o.eq_ignore_ascii_case(s))
+                .map(|o| Value::String(o.clone()))
+                .ok_or_else(|| format!("'{s}' isn't an option for {}", field.name))
+        }
+        _ => Err(format!("{} must be {}", field.name, type_name(field.field_type))),
+    }
+}
+
+fn type_name(t: FieldType) -> &'static str {
+    match t {
+        FieldType::Text => "text",
+        FieldType::Number => "a number",
+        FieldType::Date => "a date",
+        FieldType::Select => "one of its options",
+    }
+}
+
+/// Follow `migrated_into` to the field that holds the values now.
+fn resolve<'a>(fields: &'a [CustomField], id: &str) -> Option<&'a CustomField> {
+    let mut field = fields.iter().find(|f| f.id == id)?;
+    for _ in 0..fields.len() {
+        match &field.migrated_into {
+            Some(next) => field = fields.iter().find(|f| &f.id == next)?,
+            None => return Some(field),
+        }
+    }
+    None
+}
+
+/// Check and normalise an item's custom values. Migrated fields' values move
+/// to their replacement (unless it already has one), archived fields' values
+/// pass through untouched, and empty values are dropped.
+pub fn validate(fields: &[CustomField], values: &Map<String, Value>) -> ValidatedValues {
+    let mut out = Map::new();
+    let mut errors = Vec::new();
+
+    // Direct values first so a migrated value never overwrites a current one.
+    let mut keys: Vec<&String> = values.keys().collect();
+    keys.sort_by_key(|k| fields.iter().any(|f| &f.id == *k && f.migrated_into.is_some()));
+
+    for key in keys {
+        let value = &values[key];
+        let Some(field) = resolve(fields, key) else {
+            errors.push(FieldError { field: key.clone(), message: format!("'{key}' isn't a custom field") });
+            continue;
+        };
+        if field.archived_at.is_some() {
+            out.insert(field.id.clone(), value.clone());
+            continue;
+        }
+        if out.contains_key(&field.id) {
+            continue;
+        }
+        match coerce(field, value) {
+            Ok(Value::Null) => {}
+            Ok(v) => {
+                out.insert(field.id.clone(), v);
+            }
+            Err(message) => errors.push(FieldError { field: field.id.clone(), message }),
+        }
+    }
+    for field in fields.iter().filter(|f| f.active() && f.required) {
+        if !out.contains_key(&field.id) && !errors.iter().any(|e| e.field == field.id) {
+            errors.push(FieldError { field: field.id.clone(), message: format!("{} is required", field.name) });
+        }
+    }
+    ValidatedValues { values: out, errors }
+}
+
+#[tauri::command]
+pub fn list_custom_fields(state: tauri::State<'_, CustomFieldState>, include_archived: Option<bool>) -> Vec<CustomField> {
+    let include_archived = include_archived.unwrap_or(false);
+    state
+        .fields
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|f| f.active() || (include_archived && f.migrated_into.is_none()))
+        .cloned()
+        .collect()
+}
+
+#[tauri::command]
+pub fn define_custom_field(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, CustomFieldState>,
+    name: String,
+    field_type: FieldType,
+    options: Option<Vec<String>>,
+    required: Option<bool>,
+) -> Result<CustomField, String> {
+    let mut fields = state.fields.lock().unwrap();
+    let name = clean_name(&fields, &name, None)?;
+    let options = clean_options(field_type, options.unwrap_or_default())?;
+
+    let base = slug(&name);
+    let mut id = base.clone();
+    let mut n = 2;
+    while fields.iter().any(|f| f.id == id) {
+        id = format!("{base}_{n}");
+        n += 1;
+    }
+    let field = CustomField {
+        id,
+        name,
+        field_type,
+        options,
+        required: required.unwrap_or(false),
+        created_at: crate::unix_now(),
+        archived_at: None,
+        migrated_into: None,
+        option_renames: HashMap::new(),
+    };
+    fields.push(field.clone());
+    crate::store::save(&app, STORE_NAME, &*fields)?;
+    Ok(field)
+}
+
+/// Rename a field. Values are keyed by id, so nothing else changes.
+#[tauri::command]
+pub fn rename_custom_field(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, CustomFieldState>,
+    id: String,
+    name: String,
+) -> Result<CustomField, String> {
+    let mut fields = state.fields.lock().unwrap();
+    let name = clean_name(&fields, &name, Some(&id))?;
+    let field = fields.iter_mut().find(|f| f.id == id).ok_or("No such custom field")?;
+    field.name = name;
+    let renamed = field.clone();
+    crate::store::save(&app, STORE_NAME, &*fields)?;
+    Ok(renamed)
+}
+
+/// Replace a select field's options. Every removed option must be mapped to
+/// a remaining one in `replacements` so existing values stay valid.
+#[tauri::command]
+pub fn set_custom_field_options(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, CustomFieldState>,
+    id: String,
+    options: Vec<String>,
+    replacements: Option<HashMap<String, String>>,
+) -> Result<CustomField, String> {
+    let mut fields = state.fields.lock().unwrap();
+    let field = fields.iter_mut().find(|f| f.id == id).ok_or("No such custom field")?;
+    if field.field_type != FieldType::Select {
+        return Err(format!("{} isn't a select field", field.name));
+    }
+    let options = clean_options(FieldType::Select, options)?;
+    let replacements = replacements.unwrap_or_default();
+    for removed in field.options.iter().filter(|o| !options.iter().any(|n| n.eq_ignore_ascii_case(o))) {
+        let target = replacements
+            .get(removed)
+            .ok_or_else(|| format!("Choose what existing '{removed}' values become"))?;
+        if !options.iter().any(|n| n.eq_ignore_ascii_case(target)) {
+            return Err(format!("'{target}' isn't one of the new options"));
+        }
+        field.option_renames.insert(removed.clone(), target.clone());
+    }
+    // Older renames that pointed at a removed option follow it to its replacement.
+    for target in field.option_renames.values_mut() {
+        if let Some(next) = replacements.get(target.as_str()) {
+            *target = next.clone();
+        }
+    }
+    field.options = options;
+    let updated = field.clone();
+    crate::store::save(&app, STORE_NAME, &*fields)?;
+    Ok(updated)
+}
+
+/// Remove a field from use without orphaning its values: archive it, or
+/// migrate it into another field of a compatible type.
+#[tauri::command]
+pub fn remove_custom_field(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, CustomFieldState>,
+    id: String,
+    mode: RemoveMode,
+) -> Result<(), String> {
+    let mut fields = state.fields.lock().unwrap();
+    let source = fields.iter().find(|f| f.id == id && f.active()).ok_or("No such custom field")?.clone();
+    if let RemoveMode::MigrateInto { field: target } = &mode {
+        let target = fields
+            .iter()
+            .find(|f| &f.id == target && f.active())
+            .ok_or("The field to migrate into doesn't exist")?;
+        if target.id == source.id {
+            return Err("A field can't be migrated into itself".to_string());
+        }
+        let compatible = target.field_type == source.field_type || target.field_type == FieldType::Text;
+        if !compatible {
+            return Err(format!(
+                "{} values can't move into {} ({})",
+                source.name,
+                target.name,
+                type_name(target.field_type)
+            ));
+        }
+        if target.field_type == FieldType::Select
+            && !source.options.iter().all(|o| target.options.iter().any(|t| t.eq_ignore_ascii_case(o)))
+        {
+            return Err(format!("{} has options {} doesn't", source.name, target.name));
+        }
+    }
+
+    let field = fields.iter_mut().find(|f| f.id == id).ok_or("No such custom field")?;
+    match mode {
+        RemoveMode::Archive => field.archived_at = Some(crate::unix_now()),
+        RemoveMode::MigrateInto { field: target } => field.migrated_into = Some(target),
+    }
+    crate::store::save(&app, STORE_NAME, &*fields)
+}
+
+#[tauri::command]
+pub fn restore_custom_field(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, CustomFieldState>,
+    id: String,
+) -> Result<(), String> {
+    let mut fields = state.fields.lock().unwrap();
+    let name = fields.iter().find(|f| f.id == id).ok_or("No such custom field")?.name.clone();
+    clean_name(&fields, &name, Some(&id))?;
+    let field = fields.iter_mut().find(|f| f.id == id).ok_or("No such custom field")?;
+    if field.migrated_into.is_some() {
+        return Err("Migrated fields can't be restored — their values live in the other field now".to_string());
+    }
+    field.archived_at = None;
+    crate::store::save(&app, STORE_NAME, &*fields)
+}
+
+/// Check an item's custom values before it is saved. The frontend stores the
+/// returned `values`, and only when `errors` is empty.
+#[tauri::command]
+pub fn validate_custom_field_values(
+    state: tauri::State<'_, CustomFieldState>,
+    values: Map<String, Value>,
+) -> ValidatedValues {
+    validate(&state.fields.lock().unwrap(), &values)
+}
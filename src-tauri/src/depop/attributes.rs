@@ -0,0 +1,308 @@
+// ── Depop category attributes ──────────────────────────────────────────────
+// Which attributes each Depop category requires (clothing needs a size, shoes
+// an EU size, ...) so listings are validated before any photo is uploaded
+// instead of failing with a bare 400 afterwards.
+//
+// The schema comes from Depop's category metadata, cached in the app data dir
+// and refreshed weekly; an embedded snapshot covers first launch and offline
+// use. Field names are canonical snake_case ("size", "shoe_size_eu", ...) and
+// match the keys of `ListingDraft.attributes`.
+//
+// When Depop still rejects a listing that passed validation, the frontend
+// reports it with `report_depop_rejection`; those land in the schema-drift
+// report so stale requirements get noticed.
+
+use super::API_URL;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+const STORE_NAME: &str = "depop_category_attributes";
+const DRIFT_STORE: &str = "depop_schema_drift";
+const MAX_DRIFT_ENTRIES: usize = 200;
+const REFRESH_EVERY_SECS: u64 = 7 * 24 * 60 * 60;
+/// How often the background job wakes up to check whether a refresh is due.
+const CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CategoryAttributes {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub required: Vec<String>,
+    #[serde(default)]
+    pub optional: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct AttributeSchema {
+    /// Unix seconds of the last successful fetch; None = embedded snapshot.
+    #[serde(default)]
+    pub fetched_at: Option<u64>,
+    /// Required for every category.
+    #[serde(default)]
+    pub common_required: Vec<String>,
+    #[serde(default)]
+    pub categories: Vec<CategoryAttributes>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DriftEntry {
+    at: u64,
+    /// What noticed the drift; None = a listing rejection.
+    #[serde(default)]
+    source: Option<String>,
+    category: Option<String>,
+    status: u16,
+    /// Canonical fields we sent.
+    sent_fields: Vec<String>,
+    /// Known field names that appear in Depop's error body.
+    mentioned_fields: Vec<String>,
+    response: String,
+}
+
+pub struct DepopAttributeState {
+    schema: Mutex<AttributeSchema>,
+    drift: Mutex<VecDeque<DriftEntry>>,
+}
+
+fn embedded_schema() -> AttributeSchema {
+    serde_json::from_str(include_str!("category_attributes.json"))
+        .expect("embedded Depop category attributes are valid JSON")
+}
+
+impl DepopAttributeState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        let cached: AttributeSchema = crate::store::load(app, STORE_NAME);
+        let schema = if cached.categories.is_empty() { embedded_schema() } else { cached };
+        DepopAttributeState {
+            schema: Mutex::new(schema),
+            drift: Mutex::new(crate::store::load(app, DRIFT_STORE)),
+        }
+    }
+}
+
+/// Validate a listing for `category`. `has` reports whether a canonical field
+/// carries a non-empty value.
+pub fn validate(state: &DepopAttributeState, category: Option<&str>, has: impl Fn(&str) -> bool) -> Vec<FieldError> {
+    let schema = state.schema.lock().unwrap();
+    let mut errors: Vec<FieldError> = schema
+        .common_required
+        .iter()
+        .filter(|f| !has(f))
+        .map(|f| FieldError { field: f.clone(), message: format!("Depop requires {}", f.replace('_', " ")) })
+        .collect();
+
+    let Some(category) = category.filter(|c| !c.trim().is_empty()) else {
+        errors.push(FieldError { field: "category".to_string(), message: "Pick a Depop category".to_string() });
+        return errors;
+    };
+    match schema.categories.iter().find(|c| c.id.eq_ignore_ascii_case(category)) {
+        Some(cat) => {
+            let label = cat.name.as_deref().unwrap_or(&cat.id);
+            errors.extend(cat.required.iter().filter(|f| !has(f)).map(|f| FieldError {
+                field: f.clone(),
+                message: format!("Depop requires {} for {label}", f.replace('_', " ")),
+            }));
+        }
+        None => errors.push(FieldError {
+            field: "category".to_string(),
+            message: format!("'{category}' isn't a Depop category"),
+        }),
+    }
+    errors
+}
+
+/// camelCase / kebab-case attribute names from Depop -> our canonical names.
+fn canonical_field(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.push(if c == '-' || c == ' ' { '_' } else { c.to_ascii_lowercase() });
+    }
+    match snake.as_str() {
+        "color" => "colour".to_string(),
+        "eu_size" | "shoe_size" | "size_eu" => "shoe_size_eu".to_string(),
+        "size_id" | "variant" | "variants" => "size".to_string(),
+        _ => snake,
+    }
+}
+
+fn string_list(v: Option<&Value>) -> Vec<String> {
+    v.and_then(Value::as_array)
+        .map(|a| a.iter().filter_map(Value::as_str).map(canonical_field).collect())
+        .unwrap_or_default()
+}
+
+/// Accepts either a bare array or `{ categories: [...] }`, with requirements
+/// as `attributes: [{ name, required }]` or `required_attributes` lists.
+fn parse_metadata(body: &Value) -> Vec<CategoryAttributes> {
+    let list = body
+        .as_array()
+        .or_else(|| body.get("categories").and_then(Value::as_array));
+    list.into_iter()
+        .flatten()
+        .filter_map(|c| {
+            let id = match c.get("slug").or_else(|| c.get("id"))? {
+                Value::String(s) => s.clone(),
+                Value::Number(n) => n.to_string(),
+                _ => return None,
+            };
+            let mut required = string_list(c.get("required_attributes").or_else(|| c.get("requiredAttributes")));
+            let mut optional = string_list(c.get("optional_attributes").or_else(|| c.get("optionalAttributes")));
+            for attr in c.get("attributes").and_then(Value::as_array).into_iter().flatten() {
+                let Some(name) = attr.get("name").or_else(|| attr.get("id")).and_then(Value::as_str) else {
+                    continue;
+                };
+                if attr.get("required").and_then(Value::as_bool).unwrap_or(false) {
+                    required.push(canonical_field(name));
+                } else {
+                    optional.push(canonical_field(name));
+                }
+            }
+            Some(CategoryAttributes {
+                id,
+                name: c.get("name").and_then(Value::as_str).map(str::to_string),
+                required,
+                optional,
+            })
+        })
+        .collect()
+}
+
+/// Fetch the category metadata and replace the cache. The endpoint is public;
+/// a response we can't make sense of leaves the current schema in place.
+pub async fn refresh(app: &tauri::AppHandle) -> Result<usize, String> {
+    let resp = super::client()?
+        .get(format!("{API_URL}/categories/"))
+        .send()
+        .await
+        .map_err(|e| format!("request: {e}"))?;
+    let body = super::json_or_error(resp).await?;
+    let categories = parse_metadata(&body);
+    if categories.is_empty() {
+        return Err("Unrecognised Depop category metadata — keeping the cached schema".to_string());
+    }
+
+    let state = app.state::<DepopAttributeState>();
+    let mut schema = state.schema.lock().unwrap();
+    schema.fetched_at = Some(crate::unix_now());
+    if schema.common_required.is_empty() {
+        schema.common_required = embedded_schema().common_required;
+    }
+    schema.categories = categories;
+    crate::store::save(app, STORE_NAME, &*schema)?;
+    Ok(schema.categories.len())
+}
+
+/// Weekly refresh in the background; checks are cheap, fetches only when stale.
+pub fn start_refresh_job(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let fetched_at = app.state::<DepopAttributeState>().schema.lock().unwrap().fetched_at;
+            let due = fetched_at.map_or(true, |t| crate::unix_now().saturating_sub(t) >= REFRESH_EVERY_SECS);
+            if due && !crate::outage::is_degraded("depop") {
+                match refresh(&app).await {
+                    Ok(n) => log::info!("refreshed Depop category attributes ({n} categories)"),
+                    Err(e) => log::warn!("Depop category attribute refresh failed: {e}"),
+                }
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_depop_category_attributes(state: tauri::State<'_, DepopAttributeState>) -> AttributeSchema {
+    state.schema.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub async fn refresh_depop_category_attributes(app: tauri::AppHandle) -> Result<usize, String> {
+    refresh(&app).await
+}
+
+/// Record a 400 from Depop for a listing that passed `validate`. Emits
+/// "depop-schema-drift" with the entry.
+#[tauri::command]
+pub fn report_depop_rejection(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, DepopAttributeState>,
+    category: Option<String>,
+    sent_fields: Vec<String>,
+    status: u16,
+    response: String,
+) -> Result<(), String> {
+    if status != 400 && status != 422 {
+        return Ok(());
+    }
+    let known: Vec<String> = {
+        let schema = state.schema.lock().unwrap();
+        let mut known: Vec<String> = schema.common_required.clone();
+        for c in &schema.categories {
+            known.extend(c.required.iter().cloned());
+            known.extend(c.optional.iter().cloned());
+        }
+        known.sort();
+        known.dedup();
+        known
+    };
+    let lower = response.to_lowercase();
+    let mentioned_fields = known
+        .into_iter()
+        .filter(|f| lower.contains(f.as_str()) || lower.contains(&f.replace('_', "")))
+        .collect();
+
+    let entry = DriftEntry {
+        at: crate::unix_now(),
+        source: None,
+        category,
+        status,
+        sent_fields,
+        mentioned_fields,
+        response: response.chars().take(1000).collect(),
+    };
+    push_drift(&app, &state, entry)
+}
+
+/// Record a Depop response whose shape `source` no longer recognises (the
+/// search format changing, say). Emits "depop-schema-drift" like a rejection.
+pub fn report_format_drift(app: &tauri::AppHandle, source: &str, status: u16, response: &str) {
+    let entry = DriftEntry {
+        at: crate::unix_now(),
+        source: Some(source.to_string()),
+        category: None,
+        status,
+        sent_fields: Vec::new(),
+        mentioned_fields: Vec::new(),
+        response: response.chars().take(1000).collect(),
+    };
+    if let Err(e) = push_drift(app, &app.state::<DepopAttributeState>(), entry) {
+        log::warn!("saving Depop drift entry: {e}");
+    }
+}
+
+fn push_drift(app: &tauri::AppHandle, state: &DepopAttributeState, entry: DriftEntry) -> Result<(), String> {
+    let _ = app.emit("depop-schema-drift", &entry);
+    let mut drift = state.drift.lock().unwrap();
+    drift.push_front(entry);
+    drift.truncate(MAX_DRIFT_ENTRIES);
+    crate::store::save(app, DRIFT_STORE, &*drift)
+}
+
+#[tauri::command]
+pub fn get_depop_schema_drift(state: tauri::State<'_, DepopAttributeState>) -> Vec<DriftEntry> {
+    state.drift.lock().unwrap().iter().cloned().collect()
+}
@@ -0,0 +1,365 @@
+// Boosted Listings spend tracking.
+//
+// Depop charges an extra fee (8% of the sale) when a sale came through a
+// boosted listing. The sales sync passes each Depop receipt to
+// `record_depop_receipts`, which splits its fee lines into categories
+// (selling, payment processing, boost, other) and keeps one record per sale.
+// Receipts that carry the boosted flag but no separate fee line are charged
+// the standard rate, so the report never undercounts boost spend.
+//
+// `get_boost_report` compares boosted and non-boosted sales: margin after all
+// fees, plus sales per 100 likes/saves from the listing-stats store as a
+// conversion proxy (Depop doesn't give us view counts). Toggling boost on
+// listings goes through the write queue like any other listing write.
+
+use crate::accounts::Guarded;
+use crate::fees::{round_cents, FeeScheduleState};
+use crate::privacy::Private;
+use crate::repricing::{in_range, DateRange};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::Manager;
+
+const STORE_NAME: &str = "depop_sale_fees";
+/// Depop's Boosted Listings fee, as a percentage of the sale.
+pub const BOOST_FEE_PERCENT: f64 = 8.0;
+
+/// Flags Depop has used on receipts and products for boosted sales.
+const BOOST_FLAGS: &[&str] = &["boosted", "is_boosted", "isBoosted", "boosted_listing", "promoted"];
+/// Where receipts list their fee lines.
+const FEE_LINE_KEYS: &[&str] = &["fees", "fee_lines", "feeLines", "fee_breakdown", "feeBreakdown", "charges"];
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct FeeLines {
+    /// Depop's selling fee.
+    pub selling: f64,
+    pub payment: f64,
+    pub boost: f64,
+    pub other: f64,
+}
+
+impl FeeLines {
+    pub fn total(&self) -> f64 {
+        round_cents(self.selling + self.payment + self.boost + self.other)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SaleFees {
+    pub account: String,
+    pub receipt_id: String,
+    pub product_id: Option<String>,
+    /// ISO-8601.
+    pub sold_at: String,
+    pub price: f64,
+    pub boosted: bool,
+    pub fees: FeeLines,
+    /// False when the receipt had no fee lines and `fees` came from the fee
+    /// schedule.
+    pub itemised: bool,
+}
+
+pub struct BoostState {
+    sales: Mutex<BTreeMap<String, SaleFees>>,
+}
+
+impl BoostState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        BoostState { sales: Mutex::new(crate::store::load(app, STORE_NAME)) }
+    }
+}
+
+fn string_of(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// A money amount as a number, a numeric string, or `{ "amount": ... }`.
+fn amount_of(v: &Value) -> Option<f64> {
+    match v {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        Value::Object(o) => ["amount", "value", "price_amount"].iter().find_map(|k| amount_of(o.get(*k)?)),
+        _ => None,
+    }
+}
+
+fn first<'a>(receipt: &'a Value, keys: &[&str]) -> Option<&'a Value> {
+    keys.iter().find_map(|k| receipt.get(*k).filter(|v| !v.is_null()))
+}
+
+fn truthy(v: &Value) -> bool {
+    match v {
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => matches!(s.to_lowercase().as_str(), "true" | "yes" | "1"),
+        Value::Object(o) => !o.is_empty(),
+        _ => false,
+    }
+}
+
+/// Fee lines by category, or None when the receipt doesn't itemise them.
+fn fee_lines(receipt: &Value) -> Option<FeeLines> {
+    let lines = first(receipt, FEE_LINE_KEYS)?.as_array()?;
+    let mut fees = FeeLines::default();
+    for line in lines {
+        let Some(amount) = first(line, &["amount", "value", "total"]).and_then(amount_of) else {
+            continue;
+        };
+        let label = ["type", "kind", "name", "label", "description"]
+            .iter()
+            .filter_map(|k| line.get(*k)?.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        let slot = if label.contains("boost") || label.contains("promot") {
+            &mut fees.boost
+        } else if label.contains("payment") || label.contains("processing") || label.contains("transaction") {
+            &mut fees.payment
+        } else if label.contains("selling") || label.contains("depop") || label.contains("final") {
+            &mut fees.selling
+        } else {
+            &mut fees.other
+        };
+        *slot += amount.abs();
+    }
+    Some(fees)
+}
+
+/// ISO-8601 sale time; epoch values may be seconds or milliseconds.
+fn sold_at(receipt: &Value) -> Option<String> {
+    match first(receipt, &["sold_at", "soldAt", "date_sold", "created_at", "createdAt", "date"])? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => {
+            let t = n.as_f64()? as u64;
+            Some(crate::netlog::iso8601(if t > 100_000_000_000 { t } else { t * 1000 }))
+        }
+        _ => None,
+    }
+}
+
+fn parse_receipt(account: &str, receipt: &Value, schedules: &FeeScheduleState) -> Result<SaleFees, String> {
+    let receipt_id = first(receipt, &["id", "receipt_id", "purchase_id"])
+        .and_then(string_of)
+        .ok_or("Receipt without an id")?;
+    let product = receipt.get("product");
+    let product_id = first(receipt, &["product_id", "productId"])
+        .or_else(|| product?.get("id"))
+        .and_then(string_of);
+    let price = first(receipt, &["price", "item_price", "total_price", "amount"])
+        .and_then(amount_of)
+        .ok_or_else(|| format!("Receipt {receipt_id} has no price"))?;
+    let sold_at = sold_at(receipt).ok_or_else(|| format!("Receipt {receipt_id} has no sale date"))?;
+    let flagged = BOOST_FLAGS
+        .iter()
+        .any(|k| receipt.get(*k).is_some_and(truthy) || product.and_then(|p| p.get(*k)).is_some_and(truthy));
+
+    let (mut fees, itemised) = match fee_lines(receipt) {
+        Some(lines) => (lines, true),
+        None => {
+            let date = sold_at.get(..10).unwrap_or(&sold_at);
+            let b = schedules.calculate("depop", price, date);
+            (FeeLines { selling: b.final_value_fee, payment: b.payment_processing_fee, ..FeeLines::default() }, false)
+        }
+    };
+    let boosted = flagged || fees.boost > 0.0;
+    if boosted && fees.boost == 0.0 {
+        fees.boost = price * BOOST_FEE_PERCENT / 100.0;
+    }
+    for amount in [&mut fees.selling, &mut fees.payment, &mut fees.boost, &mut fees.other] {
+        *amount = round_cents(*amount);
+    }
+    Ok(SaleFees { account: account.to_string(), receipt_id, product_id, sold_at, price, boosted, fees, itemised })
+}
+
+/// Record fee breakdowns for the receipts a sales sync fetched. Returns the
+/// parsed records (for the frontend's `sales` rows); receipts that can't be
+/// read are skipped with a warning.
+#[tauri::command]
+pub fn record_depop_receipts(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, BoostState>,
+    schedules: tauri::State<'_, FeeScheduleState>,
+    account: String,
+    receipts: Vec<Value>,
+) -> Result<Vec<SaleFees>, String> {
+    let parsed: Vec<SaleFees> = receipts
+        .iter()
+        .filter_map(|r| match parse_receipt(&account, r, &schedules) {
+            Ok(sale) => Some(sale),
+            Err(e) => {
+                log::warn!("skipping Depop receipt: {e}");
+                None
+            }
+        })
+        .collect();
+    let mut sales = state.sales.lock().unwrap();
+    for sale in &parsed {
+        sales.insert(format!("{}:{}", sale.account, sale.receipt_id), sale.clone());
+    }
+    crate::store::save(&app, STORE_NAME, &*sales)?;
+    Ok(parsed)
+}
+
+#[derive(Serialize, Default)]
+pub struct BoostGroup {
+    sales: usize,
+    gross: f64,
+    fees: f64,
+    boost_fees: f64,
+    net: f64,
+    average_price: Option<f64>,
+    /// Net as a percentage of gross, after every fee.
+    net_margin_percent: Option<f64>,
+    /// Likes + saves on the sold listings that have listing stats.
+    engagement: u64,
+    listings_with_stats: usize,
+    /// Sales per 100 likes/saves, over listings with stats.
+    sales_per_100_engagements: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct BoostReport {
+    boosted: BoostGroup,
+    not_boosted: BoostGroup,
+    /// Boosted margin minus non-boosted margin, in percentage points.
+    margin_difference_points: Option<f64>,
+    /// Sales without itemised fee lines, priced from the fee schedule.
+    estimated_sales: usize,
+}
+
+fn summarise(sales: &[&SaleFees], stats: &crate::notifications::NotificationState) -> BoostGroup {
+    let mut group = BoostGroup { sales: sales.len(), ..BoostGroup::default() };
+    let mut sales_with_stats = 0usize;
+    for sale in sales {
+        group.gross += sale.price;
+        group.fees += sale.fees.total();
+        group.boost_fees += sale.fees.boost;
+        if let Some(stat) = sale.product_id.as_deref().and_then(|id| stats.stat("depop", id)) {
+            group.engagement += stat.likes + stat.saves;
+            group.listings_with_stats += 1;
+            sales_with_stats += 1;
+        }
+    }
+    group.gross = round_cents(group.gross);
+    group.fees = round_cents(group.fees);
+    group.boost_fees = round_cents(group.boost_fees);
+    group.net = round_cents(group.gross - group.fees);
+    if group.sales > 0 {
+        group.average_price = Some(round_cents(group.gross / group.sales as f64));
+    }
+    if group.gross > 0.0 {
+        group.net_margin_percent = Some(round_cents(group.net / group.gross * 100.0));
+    }
+    if group.engagement > 0 {
+        group.sales_per_100_engagements = Some(round_cents(sales_with_stats as f64 * 100.0 / group.engagement as f64));
+    }
+    group
+}
+
+/// Boosted vs non-boosted Depop sales in `range`, optionally for one account.
+#[tauri::command]
+pub fn get_boost_report(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, BoostState>,
+    range: DateRange,
+    account: Option<String>,
+) -> Result<Private<BoostReport>, String> {
+    if range.start > range.end {
+        return Err("Range start is after range end".to_string());
+    }
+    let sales = state.sales.lock().unwrap();
+    let matched: Vec<&SaleFees> = sales
+        .values()
+        .filter(|s| in_range(&s.sold_at, &range) && account.as_deref().map_or(true, |a| s.account == a))
+        .collect();
+    let stats = app.state::<crate::notifications::NotificationState>();
+    let (boosted, not_boosted): (Vec<&SaleFees>, Vec<&SaleFees>) = matched.iter().partition(|s| s.boosted);
+    let boosted = summarise(&boosted, &stats);
+    let not_boosted = summarise(&not_boosted, &stats);
+    let margin_difference_points = match (boosted.net_margin_percent, not_boosted.net_margin_percent) {
+        (Some(b), Some(n)) => Some(round_cents(b - n)),
+        _ => None,
+    };
+    Ok(Private::new(BoostReport {
+        boosted,
+        not_boosted,
+        margin_difference_points,
+        estimated_sales: matched.iter().filter(|s| !s.itemised).count(),
+    }))
+}
+
+#[derive(Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BoostOutcome {
+    Applied,
+    DryRun,
+    Failed,
+}
+
+#[derive(Serialize)]
+pub struct BoostResult {
+    product_id: String,
+    outcome: BoostOutcome,
+    error: Option<String>,
+}
+
+/// Turn Boosted Listings on or off for `product_ids`, through the Depop write
+/// lane. Setting the state is safe to repeat, so ambiguous failures are
+/// resent.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn depop_set_boost(
+    app: tauri::AppHandle,
+    account: String,
+    token: String,
+    product_ids: Vec<String>,
+    enabled: bool,
+    dry_run: Option<bool>,
+    confirmation_token: Option<String>,
+) -> Result<Guarded<Vec<BoostResult>>, String> {
+    if product_ids.is_empty() {
+        return Err("No listings selected".to_string());
+    }
+    let dry_run = dry_run.unwrap_or(false);
+    if !dry_run {
+        if let Some(request) =
+            crate::accounts::confirm(&app, &account, "depop_set_boost", &product_ids, confirmation_token.as_deref())?
+        {
+            return Ok(Guarded::ConfirmationRequired(request));
+        }
+    }
+    let mut results = Vec::with_capacity(product_ids.len());
+    for product_id in &product_ids {
+        let result = if dry_run {
+            Ok(())
+        } else {
+            crate::write_queue::submit(&app, "depop", crate::rate_limit::Priority::Background, || {
+                crate::write_queue::resend(&app, "depop", "set_boost", Some(product_id), |_| {
+                    super::set_boost(&token, product_id, enabled)
+                })
+            })
+            .await
+            .map(|_| ())
+        };
+        let (outcome, error) = match result {
+            Ok(()) if dry_run => (BoostOutcome::DryRun, None),
+            Ok(()) => (BoostOutcome::Applied, None),
+            Err(e) => (BoostOutcome::Failed, Some(e)),
+        };
+        crate::audit::record(
+            &app,
+            "depop_set_boost",
+            Some(&account),
+            dry_run,
+            json!({ "product_id": product_id, "enabled": enabled, "outcome": outcome, "error": error }),
+        );
+        results.push(BoostResult { product_id: product_id.clone(), outcome, error });
+    }
+    Ok(Guarded::Completed { results })
+}
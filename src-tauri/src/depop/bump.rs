@@ -0,0 +1,203 @@
+// Soft bump: re-save a listing so Depop refreshes its feed position without
+// the likes/link reset a full relist causes. Whether Depop actually honoured
+// the bump is checked by comparing the listing's updated time before/after;
+// listings where it demonstrably does nothing get `recommend_relist`.
+
+use crate::accounts::Guarded;
+use crate::write_queue::{Checked, Expected, ListingConflict};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BumpStrategy {
+    /// PUT the listing back exactly as fetched.
+    #[default]
+    Unchanged,
+    /// Toggle a trailing space on the description. The next bump with this
+    /// strategy removes it again, so the text never drifts.
+    WhitespaceToggle,
+}
+
+#[derive(Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BumpOutcome {
+    Bumped,
+    /// Saved, but the updated time didn't move.
+    NoEffect,
+    /// Saved, but this listing doesn't expose an updated time to compare.
+    Unverified,
+    DryRun,
+    /// The listing changed elsewhere after the bump was queued; skipped.
+    Conflict,
+    Failed,
+}
+
+#[derive(Serialize)]
+pub struct SoftBumpResult {
+    product_id: String,
+    outcome: BumpOutcome,
+    updated_before: Option<String>,
+    updated_after: Option<String>,
+    recommend_relist: bool,
+    /// Both versions of the listing when the outcome is `Conflict`.
+    conflict: Option<ListingConflict>,
+    error: Option<String>,
+}
+
+impl SoftBumpResult {
+    fn failed(product_id: &str, error: String) -> Self {
+        SoftBumpResult {
+            product_id: product_id.to_string(),
+            outcome: BumpOutcome::Failed,
+            updated_before: None,
+            updated_after: None,
+            recommend_relist: false,
+            conflict: None,
+            error: Some(error),
+        }
+    }
+}
+
+fn bump_payload(product: &Value, strategy: BumpStrategy) -> Value {
+    let mut payload = super::editable_payload(product);
+    if let BumpStrategy::WhitespaceToggle = strategy {
+        if let Some(Value::String(desc)) = payload.get_mut("description") {
+            if desc.ends_with(' ') {
+                desc.pop();
+            } else {
+                desc.push(' ');
+            }
+        }
+    }
+    payload
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn depop_soft_bump(
+    app: tauri::AppHandle,
+    account: String,
+    token: String,
+    product_ids: Vec<String>,
+    strategy: Option<BumpStrategy>,
+    dry_run: Option<bool>,
+    confirmation_token: Option<String>,
+    force: Option<bool>,
+) -> Result<Guarded<Vec<SoftBumpResult>>, String> {
+    if product_ids.is_empty() {
+        return Err("No listings selected".to_string());
+    }
+    let strategy = strategy.unwrap_or_default();
+    let dry_run = dry_run.unwrap_or(false);
+    let force = force.unwrap_or(false);
+    if !dry_run {
+        if let Some(request) =
+            crate::accounts::confirm(&app, &account, "depop_soft_bump", &product_ids, confirmation_token.as_deref())?
+        {
+            return Ok(Guarded::ConfirmationRequired(request));
+        }
+    }
+    let mut results = Vec::with_capacity(product_ids.len());
+
+    for product_id in &product_ids {
+        let result = bump_one(&app, &token, product_id, strategy, dry_run, force).await;
+        crate::audit::record(
+            &app,
+            "depop_soft_bump",
+            Some(&account),
+            dry_run,
+            json!({
+                "product_id": product_id,
+                "strategy": strategy,
+                "outcome": result.outcome,
+                "updated_before": result.updated_before,
+                "updated_after": result.updated_after,
+                "error": result.error,
+            }),
+        );
+        results.push(result);
+    }
+    Ok(Guarded::Completed { results })
+}
+
+async fn bump_one(
+    app: &tauri::AppHandle,
+    token: &str,
+    product_id: &str,
+    strategy: BumpStrategy,
+    dry_run: bool,
+    force: bool,
+) -> SoftBumpResult {
+    let before = match super::get_product(token, product_id).await {
+        Ok(p) => p,
+        Err(e) => return SoftBumpResult::failed(product_id, e),
+    };
+    let updated_before = super::updated_marker(&before);
+
+    if dry_run {
+        return SoftBumpResult {
+            product_id: product_id.to_string(),
+            outcome: BumpOutcome::DryRun,
+            updated_before,
+            updated_after: None,
+            recommend_relist: false,
+            conflict: None,
+            error: None,
+        };
+    }
+
+    let expected = Expected { listing_id: product_id, record: &before };
+    let saved = crate::write_queue::submit_checked(
+        app,
+        "depop",
+        crate::rate_limit::Priority::Background,
+        &super::VERSIONING,
+        expected,
+        force,
+        || super::get_product(token, product_id),
+        |current| async move {
+            let payload = bump_payload(&current, strategy);
+            crate::write_queue::resend(app, "depop", "update_listing", Some(product_id), |_| {
+                super::put_product(token, product_id, &payload)
+            })
+            .await
+        },
+    )
+    .await;
+    match saved {
+        Ok(Checked::Applied(_)) => {}
+        Ok(Checked::Conflict(conflict)) => {
+            return SoftBumpResult {
+                product_id: product_id.to_string(),
+                outcome: BumpOutcome::Conflict,
+                updated_before,
+                updated_after: None,
+                recommend_relist: false,
+                conflict: Some(conflict),
+                error: None,
+            };
+        }
+        Err(e) => return SoftBumpResult::failed(product_id, e),
+    }
+
+    let updated_after = match super::get_product(token, product_id).await {
+        Ok(after) => super::updated_marker(&after),
+        Err(e) => return SoftBumpResult::failed(product_id, format!("saved, but re-fetch failed: {e}")),
+    };
+
+    let outcome = match (&updated_before, &updated_after) {
+        (Some(b), Some(a)) if a != b => BumpOutcome::Bumped,
+        (Some(_), Some(_)) => BumpOutcome::NoEffect,
+        _ => BumpOutcome::Unverified,
+    };
+    SoftBumpResult {
+        product_id: product_id.to_string(),
+        recommend_relist: outcome == BumpOutcome::NoEffect,
+        outcome,
+        updated_before,
+        updated_after,
+        conflict: None,
+        error: None,
+    }
+}
@@ -0,0 +1,172 @@
+// Footer propagation: rewrite the managed footer (see crate::footer) on the
+// account's existing listings after it changes. Only the text between the
+// footer markers is replaced; a listing without a footer gets one appended,
+// and clearing the footer removes the block. Listings whose description
+// leaves too little room get a shortened footer (or none) and a warning.
+
+use crate::accounts::Guarded;
+use crate::footer::FooterState;
+use crate::marketplace_limits::LimitsState;
+use crate::write_queue::{Checked, Expected, ListingConflict};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::Manager;
+
+#[derive(Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FooterOutcome {
+    Updated,
+    /// The listing already has the current footer.
+    Unchanged,
+    DryRun,
+    /// The listing changed elsewhere after the update was queued; skipped.
+    Conflict,
+    Failed,
+}
+
+#[derive(Serialize)]
+pub struct FooterResult {
+    product_id: String,
+    outcome: FooterOutcome,
+    /// The description after the update (or as it would be, for dry runs).
+    description: Option<String>,
+    /// Set when the footer was shortened or left off to fit.
+    warning: Option<String>,
+    /// Both versions of the listing when the outcome is `Conflict`.
+    conflict: Option<ListingConflict>,
+    error: Option<String>,
+}
+
+impl FooterResult {
+    fn new(product_id: &str, outcome: FooterOutcome) -> Self {
+        FooterResult {
+            product_id: product_id.to_string(),
+            outcome,
+            description: None,
+            warning: None,
+            conflict: None,
+            error: None,
+        }
+    }
+
+    fn failed(product_id: &str, error: String) -> Self {
+        FooterResult { error: Some(error), ..FooterResult::new(product_id, FooterOutcome::Failed) }
+    }
+}
+
+fn description_of(product: &Value) -> &str {
+    product.get("description").and_then(Value::as_str).unwrap_or_default()
+}
+
+/// Apply the Depop footer to every listing in `product_ids`, or to every
+/// listing from the account's last sync when none are given.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn propagate_footer(
+    app: tauri::AppHandle,
+    sync: tauri::State<'_, super::sync::ListingSyncState>,
+    account: String,
+    token: String,
+    product_ids: Option<Vec<String>>,
+    dry_run: Option<bool>,
+    confirmation_token: Option<String>,
+    force: Option<bool>,
+) -> Result<Guarded<Vec<FooterResult>>, String> {
+    let product_ids = product_ids.unwrap_or_else(|| sync.listing_ids(&account));
+    if product_ids.is_empty() {
+        return Err("No listings to update; sync the account first".to_string());
+    }
+    let dry_run = dry_run.unwrap_or(false);
+    let force = force.unwrap_or(false);
+    if !dry_run {
+        if let Some(request) =
+            crate::accounts::confirm(&app, &account, "propagate_footer", &product_ids, confirmation_token.as_deref())?
+        {
+            return Ok(Guarded::ConfirmationRequired(request));
+        }
+    }
+    let footer = app.state::<FooterState>().get("depop");
+    let max_chars = app.state::<LimitsState>().get("depop").and_then(|l| l.limits.description_max_chars);
+    let mut results = Vec::with_capacity(product_ids.len());
+
+    for product_id in &product_ids {
+        let result = update_one(&app, &token, product_id, footer.as_deref(), max_chars, dry_run, force).await;
+        crate::audit::record(
+            &app,
+            "propagate_footer",
+            Some(&account),
+            dry_run,
+            json!({
+                "product_id": product_id,
+                "outcome": result.outcome,
+                "warning": result.warning,
+                "error": result.error,
+            }),
+        );
+        results.push(result);
+    }
+    Ok(Guarded::Completed { results })
+}
+
+async fn update_one(
+    app: &tauri::AppHandle,
+    token: &str,
+    product_id: &str,
+    footer: Option<&str>,
+    max_chars: Option<usize>,
+    dry_run: bool,
+    force: bool,
+) -> FooterResult {
+    let before = match super::get_product(token, product_id).await {
+        Ok(p) => p,
+        Err(e) => return FooterResult::failed(product_id, e),
+    };
+    let applied = crate::footer::apply(description_of(&before), footer, max_chars);
+    let outcome = if applied.description == description_of(&before) {
+        FooterOutcome::Unchanged
+    } else if dry_run {
+        FooterOutcome::DryRun
+    } else {
+        FooterOutcome::Updated
+    };
+    let result = FooterResult {
+        description: Some(applied.description),
+        warning: applied.warning,
+        ..FooterResult::new(product_id, outcome)
+    };
+    if result.outcome != FooterOutcome::Updated {
+        return result;
+    }
+
+    let expected = Expected { listing_id: product_id, record: &before };
+    let saved = crate::write_queue::submit_checked(
+        app,
+        "depop",
+        crate::rate_limit::Priority::Background,
+        &super::VERSIONING,
+        expected,
+        force,
+        || super::get_product(token, product_id),
+        |current| async move {
+            // Re-apply to the current version so edits made since the fetch
+            // above (under `force`) aren't overwritten.
+            let mut payload = super::editable_payload(&current);
+            let description = crate::footer::apply(description_of(&current), footer, max_chars).description;
+            payload["description"] = Value::String(description);
+            crate::write_queue::resend(app, "depop", "update_listing", Some(product_id), |_| {
+                super::put_product(token, product_id, &payload)
+            })
+            .await
+        },
+    )
+    .await;
+    match saved {
+        Ok(Checked::Applied(_)) => result,
+        Ok(Checked::Conflict(conflict)) => FooterResult {
+            outcome: FooterOutcome::Conflict,
+            conflict: Some(conflict),
+            ..result
+        },
+        Err(e) => FooterResult::failed(product_id, e),
+    }
+}
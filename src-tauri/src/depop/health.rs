@@ -0,0 +1,435 @@
+// Account health: the seller metrics Depop uses to rank (and quietly
+// throttle) shops. `depop_fetch_account_health` reads what the seller hub
+// exposes for the session: shipping time, response rate and time, active
+// violations. It keeps one snapshot per day so trends show up.
+//
+// When the hub doesn't report a metric, a local estimate stands in and is
+// marked `source: estimate`:
+// - shipping time comes from our own sale-to-tracking times (shipping.rs);
+// - response rate and time come from the message samples the frontend passes
+//   from its messages table.
+//
+// A metric that crosses its threshold raises a desktop notification and an
+// "account-health-warning" event, once per crossing.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+const SNAPSHOT_STORE: &str = "depop_account_health";
+const THRESHOLD_STORE: &str = "account_health_thresholds";
+/// Daily snapshots kept per account.
+const KEEP_DAYS: usize = 180;
+/// A message counts as answered if the reply came within this long.
+const ANSWERED_WITHIN_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricSource {
+    /// Reported by Depop.
+    Api,
+    /// Derived locally; an approximation of what Depop measures.
+    Estimate,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Metric {
+    pub value: f64,
+    pub source: MetricSource,
+    /// Local measurements behind an estimate.
+    #[serde(default)]
+    pub samples: Option<usize>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct HealthMetrics {
+    pub shipping_hours: Option<Metric>,
+    /// Percent of messages answered.
+    pub response_rate: Option<Metric>,
+    pub response_hours: Option<Metric>,
+    pub violations: Option<Metric>,
+}
+
+impl HealthMetrics {
+    fn get(&self, name: MetricName) -> Option<&Metric> {
+        match name {
+            MetricName::ShippingHours => self.shipping_hours.as_ref(),
+            MetricName::ResponseRate => self.response_rate.as_ref(),
+            MetricName::ResponseHours => self.response_hours.as_ref(),
+            MetricName::Violations => self.violations.as_ref(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricName {
+    ShippingHours,
+    ResponseRate,
+    ResponseHours,
+    Violations,
+}
+
+impl MetricName {
+    const ALL: [MetricName; 4] =
+        [MetricName::ShippingHours, MetricName::ResponseRate, MetricName::ResponseHours, MetricName::Violations];
+
+    /// Whether a higher value is better.
+    fn higher_is_better(self) -> bool {
+        self == MetricName::ResponseRate
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            MetricName::ShippingHours => "Average shipping time",
+            MetricName::ResponseRate => "Response rate",
+            MetricName::ResponseHours => "Response time",
+            MetricName::Violations => "Active violations",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthThresholds {
+    pub max_shipping_hours: f64,
+    pub min_response_rate: f64,
+    pub max_response_hours: f64,
+    pub max_violations: f64,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        HealthThresholds {
+            max_shipping_hours: 72.0,
+            min_response_rate: 90.0,
+            max_response_hours: 24.0,
+            max_violations: 0.0,
+        }
+    }
+}
+
+impl HealthThresholds {
+    fn limit(&self, name: MetricName) -> f64 {
+        match name {
+            MetricName::ShippingHours => self.max_shipping_hours,
+            MetricName::ResponseRate => self.min_response_rate,
+            MetricName::ResponseHours => self.max_response_hours,
+            MetricName::Violations => self.max_violations,
+        }
+    }
+
+    fn breached(&self, name: MetricName, value: f64) -> bool {
+        if name.higher_is_better() {
+            value < self.limit(name)
+        } else {
+            value > self.limit(name)
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HealthWarning {
+    metric: MetricName,
+    value: f64,
+    threshold: f64,
+    source: MetricSource,
+    message: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HealthSnapshot {
+    /// `YYYY-MM-DD`.
+    date: String,
+    fetched_at: u64,
+    metrics: HealthMetrics,
+    warnings: Vec<HealthWarning>,
+    /// Why nothing (or only part) came from the seller hub.
+    #[serde(default)]
+    api_error: Option<String>,
+}
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Improving,
+    Worsening,
+    Steady,
+}
+
+#[derive(Serialize)]
+pub struct MetricTrend {
+    metric: MetricName,
+    current: f64,
+    change_7d: Option<f64>,
+    change_30d: Option<f64>,
+    /// Over the longest window with data.
+    direction: Option<Direction>,
+}
+
+#[derive(Serialize)]
+pub struct AccountHealth {
+    account: String,
+    latest: Option<HealthSnapshot>,
+    trends: Vec<MetricTrend>,
+    history: Vec<HealthSnapshot>,
+}
+
+/// A message the seller received, and when they replied (if they did).
+#[derive(Deserialize)]
+pub struct MessageSample {
+    /// Unix seconds.
+    received_at: u64,
+    #[serde(default)]
+    replied_at: Option<u64>,
+}
+
+pub struct AccountHealthState {
+    snapshots: Mutex<BTreeMap<String, Vec<HealthSnapshot>>>,
+    thresholds: Mutex<HealthThresholds>,
+}
+
+impl AccountHealthState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        AccountHealthState {
+            snapshots: Mutex::new(crate::store::load(app, SNAPSHOT_STORE)),
+            thresholds: Mutex::new(crate::store::load(app, THRESHOLD_STORE)),
+        }
+    }
+}
+
+fn number(v: &Value) -> Option<f64> {
+    match v {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().trim_end_matches('%').parse().ok(),
+        Value::Array(a) => Some(a.len() as f64),
+        Value::Object(o) => ["value", "count", "hours", "rate"].iter().find_map(|k| number(o.get(*k)?)),
+        _ => None,
+    }
+}
+
+fn find(body: &Value, keys: &[&str]) -> Option<f64> {
+    let body = body.get("data").unwrap_or(body);
+    keys.iter().find_map(|k| number(body.get(*k)?))
+}
+
+fn api_metric(value: f64) -> Option<Metric> {
+    value.is_finite().then_some(Metric { value: round1(value), source: MetricSource::Api, samples: None })
+}
+
+fn round1(v: f64) -> f64 {
+    (v * 10.0).round() / 10.0
+}
+
+/// Metrics from the seller hub response, normalised to hours and percent.
+fn parse_hub(body: &Value) -> HealthMetrics {
+    let shipping_hours = find(body, &["average_shipping_time_hours", "avg_shipping_hours", "shippingTimeHours"])
+        .or_else(|| {
+            find(body, &["average_shipping_time_days", "avg_dispatch_days", "shippingTimeDays"]).map(|d| d * 24.0)
+        });
+    let response_rate = find(body, &["response_rate", "responseRate", "reply_rate"])
+        .map(|r| if r <= 1.0 { r * 100.0 } else { r });
+    let response_hours = find(body, &["average_response_time_hours", "response_time_hours", "responseTimeHours"])
+        .or_else(|| find(body, &["average_response_time_minutes", "responseTimeMinutes"]).map(|m| m / 60.0));
+    let violations = find(body, &["active_violations", "violations", "infractions", "activeViolations"]);
+    HealthMetrics {
+        shipping_hours: shipping_hours.and_then(api_metric),
+        response_rate: response_rate.and_then(api_metric),
+        response_hours: response_hours.and_then(api_metric),
+        violations: violations.and_then(api_metric),
+    }
+}
+
+fn estimate(value: f64, samples: usize) -> Metric {
+    Metric { value: round1(value), source: MetricSource::Estimate, samples: Some(samples) }
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    match values.len() {
+        0 => None,
+        n if n % 2 == 0 => Some((values[mid - 1] + values[mid]) / 2.0),
+        _ => Some(values[mid]),
+    }
+}
+
+/// Fill metrics the hub didn't report from local measurements.
+fn fill_estimates(metrics: &mut HealthMetrics, ship_times: &[u64], messages: &[MessageSample], now: u64) {
+    if metrics.shipping_hours.is_none() && !ship_times.is_empty() {
+        let hours: Vec<f64> = ship_times.iter().map(|s| *s as f64 / 3600.0).collect();
+        let mean = hours.iter().sum::<f64>() / hours.len() as f64;
+        metrics.shipping_hours = Some(estimate(mean, hours.len()));
+    }
+    // Messages still inside the reply window can't be judged yet.
+    let judged: Vec<&MessageSample> = messages
+        .iter()
+        .filter(|m| m.replied_at.is_some() || now.saturating_sub(m.received_at) >= ANSWERED_WITHIN_SECS)
+        .collect();
+    if metrics.response_rate.is_none() && !judged.is_empty() {
+        let answered = judged
+            .iter()
+            .filter(|m| m.replied_at.is_some_and(|r| r.saturating_sub(m.received_at) <= ANSWERED_WITHIN_SECS))
+            .count();
+        metrics.response_rate = Some(estimate(answered as f64 * 100.0 / judged.len() as f64, judged.len()));
+    }
+    if metrics.response_hours.is_none() {
+        let latencies: Vec<f64> = messages
+            .iter()
+            .filter_map(|m| Some(m.replied_at?.saturating_sub(m.received_at) as f64 / 3600.0))
+            .collect();
+        let samples = latencies.len();
+        if let Some(hours) = median(latencies) {
+            metrics.response_hours = Some(estimate(hours, samples));
+        }
+    }
+}
+
+fn warnings(metrics: &HealthMetrics, thresholds: &HealthThresholds) -> Vec<HealthWarning> {
+    MetricName::ALL
+        .into_iter()
+        .filter_map(|name| {
+            let metric = metrics.get(name)?;
+            if !thresholds.breached(name, metric.value) {
+                return None;
+            }
+            let estimate = if metric.source == MetricSource::Estimate { " (estimated)" } else { "" };
+            let threshold = thresholds.limit(name);
+            Some(HealthWarning {
+                metric: name,
+                value: metric.value,
+                threshold,
+                source: metric.source,
+                message: format!("{} is {}{estimate}, past your limit of {threshold}", name.label(), metric.value),
+            })
+        })
+        .collect()
+}
+
+/// Latest value minus the value in the newest snapshot at least `days` old.
+fn change(history: &[HealthSnapshot], name: MetricName, current: f64, days: u64) -> Option<f64> {
+    let cutoff = crate::unix_now().saturating_sub(days * 86_400);
+    let past = history.iter().rev().find(|s| s.fetched_at <= cutoff)?;
+    Some(round1(current - past.metrics.get(name)?.value))
+}
+
+fn trends(history: &[HealthSnapshot]) -> Vec<MetricTrend> {
+    let Some(latest) = history.last() else {
+        return Vec::new();
+    };
+    MetricName::ALL
+        .into_iter()
+        .filter_map(|name| {
+            let current = latest.metrics.get(name)?.value;
+            let change_7d = change(history, name, current, 7);
+            let change_30d = change(history, name, current, 30);
+            let direction = change_30d.or(change_7d).map(|delta| match delta {
+                0.0 => Direction::Steady,
+                d if (d > 0.0) == name.higher_is_better() => Direction::Improving,
+                _ => Direction::Worsening,
+            });
+            Some(MetricTrend { metric: name, current, change_7d, change_30d, direction })
+        })
+        .collect()
+}
+
+/// Pull the account's seller metrics, fill gaps with local estimates, and
+/// store today's snapshot. `messages` are recent received messages from the
+/// messages table, for the response estimates.
+#[tauri::command]
+pub async fn depop_fetch_account_health(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AccountHealthState>,
+    account: String,
+    token: String,
+    messages: Option<Vec<MessageSample>>,
+) -> Result<AccountHealth, String> {
+    crate::accounts::remember(&app, &account);
+    let (mut metrics, api_error) = match super::get_seller_health(&token).await {
+        Ok(body) => (parse_hub(&body), None),
+        Err(e) => (HealthMetrics::default(), Some(e)),
+    };
+    let now = crate::unix_now();
+    let ship_times = app.state::<crate::shipping::ShippingState>().ship_times("depop", &account);
+    fill_estimates(&mut metrics, &ship_times, messages.as_deref().unwrap_or_default(), now);
+
+    let thresholds = state.thresholds.lock().unwrap().clone();
+    let warnings = warnings(&metrics, &thresholds);
+    let snapshot = HealthSnapshot { date: crate::fees::today(), fetched_at: now, metrics, warnings, api_error };
+
+    let newly_breached: Vec<HealthWarning> = {
+        let mut all = state.snapshots.lock().unwrap();
+        let history = all.entry(account.clone()).or_default();
+        // Compare against the previous day's snapshot, not an earlier fetch
+        // today, so a warning raised this morning isn't raised again.
+        let previous = history.iter().rev().find(|s| s.date != snapshot.date);
+        let fresh = snapshot
+            .warnings
+            .iter()
+            .filter(|w| previous.map_or(true, |p| !p.warnings.iter().any(|pw| pw.metric == w.metric)))
+            .cloned()
+            .collect();
+        history.retain(|s| s.date != snapshot.date);
+        history.push(snapshot);
+        if history.len() > KEEP_DAYS {
+            history.drain(..history.len() - KEEP_DAYS);
+        }
+        crate::store::save(&app, SNAPSHOT_STORE, &*all)?;
+        fresh
+    };
+
+    if !newly_breached.is_empty() {
+        let meta = crate::accounts::meta(&app, &account);
+        let shop = meta.as_ref().and_then(|m| m.label.clone()).unwrap_or_else(|| account.clone());
+        for warning in &newly_breached {
+            crate::notifications::show(&app, &format!("Shop health · {shop}"), &warning.message);
+        }
+        let _ = app.emit(
+            "account-health-warning",
+            serde_json::json!({ "account": account, "account_meta": meta, "warnings": newly_breached }),
+        );
+    }
+    Ok(health(&state, &account))
+}
+
+fn health(state: &AccountHealthState, account: &str) -> AccountHealth {
+    let history = state.snapshots.lock().unwrap().get(account).cloned().unwrap_or_default();
+    AccountHealth { account: account.to_string(), latest: history.last().cloned(), trends: trends(&history), history }
+}
+
+/// Stored health for `account`: the latest snapshot, trends and history.
+/// Estimated metrics carry `source: estimate`.
+#[tauri::command]
+pub fn get_account_health(state: tauri::State<'_, AccountHealthState>, account: String) -> AccountHealth {
+    health(&state, &account)
+}
+
+#[tauri::command]
+pub fn get_health_thresholds(state: tauri::State<'_, AccountHealthState>) -> HealthThresholds {
+    state.thresholds.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_health_thresholds(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AccountHealthState>,
+    thresholds: HealthThresholds,
+) -> Result<(), String> {
+    let values = [
+        thresholds.max_shipping_hours,
+        thresholds.min_response_rate,
+        thresholds.max_response_hours,
+        thresholds.max_violations,
+    ];
+    if values.iter().any(|v| !v.is_finite() || *v < 0.0) {
+        return Err("Thresholds must be non-negative numbers".to_string());
+    }
+    if thresholds.min_response_rate > 100.0 {
+        return Err("Response rate is a percentage (0-100)".to_string());
+    }
+    let mut current = state.thresholds.lock().unwrap();
+    *current = thresholds;
+    crate::store::save(&app, THRESHOLD_STORE, &*current)
+}
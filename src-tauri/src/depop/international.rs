@@ -0,0 +1,275 @@
+// International shipping: turn it on or off and set per-region prices across
+// many listings at once. Regions come from the marketplace limits registry;
+// prices are checked against its price range and, unless allowed, against the
+// carrier rate tables (shipping.rs) so a region isn't priced below what any
+// carrier charges. The previous shipping config of each listing goes into the
+// audit log so a bulk change can be undone.
+//
+// `suggest_international_prices` builds a config from an item's weight, the
+// cheapest carrier per region and a margin.
+
+use crate::accounts::Guarded;
+use crate::marketplace_limits::LimitsState;
+use crate::privacy::Private;
+use crate::shipping::ShippingState;
+use crate::write_queue::{Checked, Expected, ListingConflict};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct IntlShippingConfig {
+    pub enabled: bool,
+    /// Region -> shipping price. Ignored (and cleared) when disabled.
+    #[serde(default)]
+    pub regions: BTreeMap<String, f64>,
+}
+
+impl IntlShippingConfig {
+    fn to_value(&self) -> Value {
+        let regions: Vec<Value> = if self.enabled {
+            self.regions.iter().map(|(region, price)| json!({ "region": region, "price": price })).collect()
+        } else {
+            Vec::new()
+        };
+        json!({ "enabled": self.enabled, "regions": regions })
+    }
+}
+
+fn validate(
+    config: &IntlShippingConfig,
+    limits: &LimitsState,
+    shipping: &ShippingState,
+    allow_below_cost: bool,
+) -> Result<(), String> {
+    if !config.enabled {
+        return Ok(());
+    }
+    if config.regions.is_empty() {
+        return Err("Choose at least one region, or turn international shipping off".to_string());
+    }
+    let limits = limits.get("depop").ok_or("No Depop limits")?.limits;
+    for (region, price) in &config.regions {
+        if !limits.shipping_regions.contains(region) {
+            return Err(format!(
+                "Depop doesn't ship to region {region:?} (one of: {})",
+                limits.shipping_regions.join(", ")
+            ));
+        }
+        if !price.is_finite() || *price < 0.0 {
+            return Err(format!("{region}: shipping price must be zero or more"));
+        }
+        if let Some(max) = limits.max_price.filter(|max| price > max) {
+            return Err(format!("{region}: shipping price is above Depop's maximum of {max:.2}"));
+        }
+        if let Some(lowest) = shipping.lowest_rate(region).filter(|lowest| price < lowest && !allow_below_cost) {
+            return Err(format!("{region}: {price:.2} is below your cheapest carrier rate of {lowest:.2}"));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IntlShippingOutcome {
+    Updated,
+    /// The listing already has this config.
+    Unchanged,
+    DryRun,
+    /// The listing changed elsewhere after the update was queued; skipped.
+    Conflict,
+    Failed,
+}
+
+#[derive(Serialize)]
+pub struct IntlShippingResult {
+    product_id: String,
+    outcome: IntlShippingOutcome,
+    /// The listing's shipping config before the change.
+    previous: Option<Value>,
+    /// Both versions of the listing when the outcome is `Conflict`.
+    conflict: Option<ListingConflict>,
+    error: Option<String>,
+}
+
+impl IntlShippingResult {
+    fn new(product_id: &str, outcome: IntlShippingOutcome, previous: Option<Value>) -> Self {
+        IntlShippingResult { product_id: product_id.to_string(), outcome, previous, conflict: None, error: None }
+    }
+
+    fn failed(product_id: &str, previous: Option<Value>, error: String) -> Self {
+        IntlShippingResult {
+            error: Some(error),
+            ..IntlShippingResult::new(product_id, IntlShippingOutcome::Failed, previous)
+        }
+    }
+}
+
+/// `allow_below_cost` accepts region prices under the cheapest carrier rate.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn depop_set_international_shipping(
+    app: tauri::AppHandle,
+    limits: tauri::State<'_, LimitsState>,
+    shipping: tauri::State<'_, ShippingState>,
+    account: String,
+    token: String,
+    product_ids: Vec<String>,
+    config: IntlShippingConfig,
+    dry_run: Option<bool>,
+    confirmation_token: Option<String>,
+    force: Option<bool>,
+    allow_below_cost: Option<bool>,
+) -> Result<Guarded<Vec<IntlShippingResult>>, String> {
+    if product_ids.is_empty() {
+        return Err("No listings selected".to_string());
+    }
+    validate(&config, &limits, &shipping, allow_below_cost.unwrap_or(false))?;
+    let dry_run = dry_run.unwrap_or(false);
+    let force = force.unwrap_or(false);
+    if !dry_run {
+        if let Some(request) = crate::accounts::confirm(
+            &app,
+            &account,
+            "depop_set_international_shipping",
+            &product_ids,
+            confirmation_token.as_deref(),
+        )? {
+            return Ok(Guarded::ConfirmationRequired(request));
+        }
+    }
+    let shipping_value = config.to_value();
+    let mut results = Vec::with_capacity(product_ids.len());
+
+    for product_id in &product_ids {
+        let result = update_one(&app, &token, product_id, &shipping_value, dry_run, force).await;
+        crate::audit::record(
+            &app,
+            "depop_set_international_shipping",
+            Some(&account),
+            dry_run,
+            json!({
+                "product_id": product_id,
+                "outcome": result.outcome,
+                "previous": result.previous,
+                "config": config,
+                "error": result.error,
+            }),
+        );
+        results.push(result);
+    }
+    Ok(Guarded::Completed { results })
+}
+
+async fn update_one(
+    app: &tauri::AppHandle,
+    token: &str,
+    product_id: &str,
+    shipping: &Value,
+    dry_run: bool,
+    force: bool,
+) -> IntlShippingResult {
+    let before = match super::get_product(token, product_id).await {
+        Ok(p) => p,
+        Err(e) => return IntlShippingResult::failed(product_id, None, e),
+    };
+    let previous = before.get(super::INTERNATIONAL_SHIPPING).cloned();
+    if previous.as_ref() == Some(shipping) {
+        return IntlShippingResult::new(product_id, IntlShippingOutcome::Unchanged, previous);
+    }
+    if dry_run {
+        return IntlShippingResult::new(product_id, IntlShippingOutcome::DryRun, previous);
+    }
+
+    let expected = Expected { listing_id: product_id, record: &before };
+    let saved = crate::write_queue::submit_checked(
+        app,
+        "depop",
+        crate::rate_limit::Priority::Background,
+        &super::VERSIONING,
+        expected,
+        force,
+        || super::get_product(token, product_id),
+        |current| async move {
+            let mut payload = super::editable_payload(&current);
+            payload[super::INTERNATIONAL_SHIPPING] = shipping.clone();
+            crate::write_queue::resend(app, "depop", "update_listing", Some(product_id), |_| {
+                super::put_product(token, product_id, &payload)
+            })
+            .await
+        },
+    )
+    .await;
+    match saved {
+        Ok(Checked::Applied(_)) => IntlShippingResult::new(product_id, IntlShippingOutcome::Updated, previous),
+        Ok(Checked::Conflict(conflict)) => IntlShippingResult {
+            conflict: Some(conflict),
+            ..IntlShippingResult::new(product_id, IntlShippingOutcome::Conflict, previous)
+        },
+        Err(e) => IntlShippingResult::failed(product_id, previous, e),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ShippingItem {
+    weight_kg: f64,
+    /// Regions to price; defaults to every Depop region.
+    #[serde(default)]
+    regions: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+pub struct RegionQuote {
+    region: String,
+    carrier: String,
+    cost: f64,
+    price: f64,
+}
+
+#[derive(Serialize)]
+pub struct IntlPriceSuggestion {
+    config: IntlShippingConfig,
+    quotes: Vec<RegionQuote>,
+    /// Regions no carrier rate covers at this weight; left out of `config`.
+    unpriced: Vec<String>,
+}
+
+/// Region prices for `item`: the cheapest carrier's cost for its weight,
+/// plus `margin_percent` and `margin_flat`, rounded up to the cent.
+#[tauri::command]
+pub fn suggest_international_prices(
+    limits: tauri::State<'_, LimitsState>,
+    shipping: tauri::State<'_, ShippingState>,
+    item: ShippingItem,
+    margin_percent: Option<f64>,
+    margin_flat: Option<f64>,
+) -> Result<Private<IntlPriceSuggestion>, String> {
+    if !item.weight_kg.is_finite() || item.weight_kg <= 0.0 {
+        return Err("Weight must be more than zero".to_string());
+    }
+    let margin_percent = margin_percent.unwrap_or(0.0);
+    let margin_flat = margin_flat.unwrap_or(0.0);
+    if !margin_percent.is_finite() || !margin_flat.is_finite() || margin_percent < 0.0 || margin_flat < 0.0 {
+        return Err("Margins must be zero or more".to_string());
+    }
+    let regions = match item.regions {
+        Some(regions) => regions,
+        None => limits.get("depop").map(|l| l.limits.shipping_regions).unwrap_or_default(),
+    };
+    let mut suggestion = IntlPriceSuggestion {
+        config: IntlShippingConfig { enabled: true, regions: BTreeMap::new() },
+        quotes: Vec::new(),
+        unpriced: Vec::new(),
+    };
+    for region in regions {
+        let Some((carrier, cost)) = shipping.cheapest_rate(&region, item.weight_kg) else {
+            suggestion.unpriced.push(region);
+            continue;
+        };
+        let price = ((cost * (1.0 + margin_percent / 100.0) + margin_flat) * 100.0 - 1e-6).ceil() / 100.0;
+        suggestion.config.regions.insert(region.clone(), price);
+        suggestion.quotes.push(RegionQuote { region, carrier, cost, price });
+    }
+    suggestion.config.enabled = !suggestion.config.regions.is_empty();
+    Ok(Private::new(suggestion))
+}
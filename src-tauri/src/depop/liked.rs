@@ -0,0 +1,365 @@
+// Sourcing pipeline: items liked on Depop, tracked as a shortlist.
+//
+// `depop_fetch_liked` imports the account's likes as "considering" with the
+// price at the time they were first seen. The frontend's price-watch poller
+// calls `refresh_pipeline_prices` to re-read open items: a lower price emits
+// `price-drop`, and an item that sells to someone else moves to "missed" with
+// its final price kept, so offers can be calibrated later.
+
+use super::sync::{next_page, product_id, urlencode};
+use super::API_URL;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+const STORE_NAME: &str = "sourcing_pipeline";
+const PAGE_SIZE: u32 = 100;
+const MAX_PAGES: u32 = 50;
+/// Gap between product reads while refreshing prices.
+const REFRESH_PACING: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineStatus {
+    Considering,
+    Offered,
+    Bought,
+    Passed,
+    /// Sold to someone else.
+    Missed,
+}
+
+impl PipelineStatus {
+    /// Still being watched for price changes and sales.
+    fn is_open(self) -> bool {
+        matches!(self, PipelineStatus::Considering | PipelineStatus::Offered)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PipelineItem {
+    /// Depop product id.
+    pub id: String,
+    pub account: String,
+    pub title: String,
+    pub url: Option<String>,
+    pub seller: Option<String>,
+    pub image: Option<String>,
+    pub currency: Option<String>,
+    pub price_at_like: Option<f64>,
+    pub current_price: Option<f64>,
+    pub status: PipelineStatus,
+    #[serde(default)]
+    pub note: Option<String>,
+    /// When the like was first imported.
+    pub liked_at: u64,
+    pub status_changed_at: u64,
+    #[serde(default)]
+    pub price_checked_at: Option<u64>,
+    /// Final price when the item went to "missed".
+    #[serde(default)]
+    pub sold_price: Option<f64>,
+}
+
+pub struct PipelineState {
+    items: Mutex<BTreeMap<String, PipelineItem>>,
+}
+
+impl PipelineState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        PipelineState { items: Mutex::new(crate::store::load(app, STORE_NAME)) }
+    }
+}
+
+#[derive(Serialize)]
+pub struct LikedImport {
+    pages: u32,
+    fetched: usize,
+    added: Vec<String>,
+    already_tracked: usize,
+}
+
+#[derive(Clone, Serialize)]
+pub struct PriceDrop {
+    id: String,
+    title: String,
+    url: Option<String>,
+    previous: f64,
+    current: f64,
+    price_at_like: Option<f64>,
+    currency: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PriceRefresh {
+    checked: usize,
+    drops: Vec<PriceDrop>,
+    missed: Vec<String>,
+    /// Product id → error; those items keep their previous price.
+    errors: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct PipelineFilter {
+    #[serde(default)]
+    statuses: Option<Vec<PipelineStatus>>,
+    #[serde(default)]
+    account: Option<String>,
+    #[serde(default)]
+    title_contains: Option<String>,
+}
+
+fn as_amount(v: &Value) -> Option<f64> {
+    match v {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Current asking price and currency, preferring a discounted price.
+pub(crate) fn price_of(product: &Value) -> (Option<f64>, Option<String>) {
+    let price = product.get("price");
+    let field = |keys: &[&str]| keys.iter().find_map(|k| price.and_then(|p| p.get(*k)).and_then(as_amount));
+    let amount = field(&["discountedPriceAmount", "discounted_price_amount"])
+        .or_else(|| field(&["priceAmount", "price_amount", "amount"]))
+        .or_else(|| price.and_then(as_amount));
+    let currency = price
+        .and_then(|p| p.get("currencyName").or_else(|| p.get("currency_name")))
+        .or_else(|| product.get("currency"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    (amount, currency)
+}
+
+fn is_sold(product: &Value) -> bool {
+    product.get("sold").and_then(Value::as_bool) == Some(true)
+        || product.get("status").and_then(Value::as_str).is_some_and(|s| s.eq_ignore_ascii_case("sold"))
+}
+
+fn text<'a>(product: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter().find_map(|k| product.get(*k)?.as_str()).filter(|s| !s.is_empty())
+}
+
+fn item_from(product: &Value, id: String, account: &str, now: u64) -> PipelineItem {
+    let (price, currency) = price_of(product);
+    let slug = text(product, &["slug"]);
+    let title = text(product, &["title"])
+        .or(slug)
+        .or_else(|| text(product, &["description"]).and_then(|d| d.lines().next()))
+        .map(|t| t.chars().take(80).collect())
+        .unwrap_or_else(|| id.clone());
+    let image = product
+        .get("pictures")
+        .or_else(|| product.get("photos"))
+        .and_then(|p| p.get(0))
+        .and_then(|p| p.get("url").or_else(|| p.get("formats").and_then(|f| f.get("P0")).and_then(|f| f.get("url"))))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let seller = product
+        .get("seller")
+        .and_then(|s| s.get("username"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    PipelineItem {
+        id,
+        account: account.to_string(),
+        title,
+        url: slug.map(|s| format!("https://www.depop.com/products/{s}/")),
+        seller,
+        image,
+        currency,
+        price_at_like: price,
+        current_price: price,
+        status: PipelineStatus::Considering,
+        note: None,
+        liked_at: now,
+        status_changed_at: now,
+        price_checked_at: Some(now),
+        sold_price: None,
+    }
+}
+
+async fn fetch_likes_page(token: &str, next: Option<&(&'static str, String)>) -> Result<Value, String> {
+    let mut url = format!("{API_URL}/users/me/likes/?limit={PAGE_SIZE}");
+    if let Some((key, value)) = next {
+        url.push_str(&format!("&{key}={}", urlencode(value)));
+    }
+    let started = Instant::now();
+    let resp = super::client()?
+        .get(url)
+        .header("Authorization", super::bearer(token)?)
+        .send()
+        .await
+        .map_err(|e| format!("request: {e}"));
+    super::timed(started, resp).await
+}
+
+/// Import the account's liked items into the pipeline. Items already tracked
+/// keep their status, note and price-at-like.
+#[tauri::command]
+pub async fn depop_fetch_liked(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PipelineState>,
+    account: String,
+    token: String,
+) -> Result<LikedImport, String> {
+    crate::accounts::remember(&app, &account);
+    let mut liked = Vec::new();
+    let mut next = None;
+    let mut pages = 0;
+    loop {
+        let body = fetch_likes_page(&token, next.as_ref()).await?;
+        pages += 1;
+        let entries = body
+            .get("objects")
+            .or_else(|| body.get("products"))
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        let page_len = entries.len();
+        // Likes come back either as products or wrapped as `{ product: {...} }`.
+        liked.extend(entries.into_iter().map(|e| match e.get("product") {
+            Some(p) if p.is_object() => p.clone(),
+            _ => e,
+        }));
+        next = next_page(&body);
+        if page_len == 0 || next.is_none() || pages >= MAX_PAGES {
+            break;
+        }
+    }
+
+    let now = crate::unix_now();
+    let mut report = LikedImport { pages, fetched: liked.len(), added: Vec::new(), already_tracked: 0 };
+    let mut items = state.items.lock().unwrap();
+    for product in &liked {
+        let Some(id) = product_id(product) else {
+            continue;
+        };
+        if items.contains_key(&id) {
+            report.already_tracked += 1;
+            continue;
+        }
+        items.insert(id.clone(), item_from(product, id.clone(), &account, now));
+        report.added.push(id);
+    }
+    crate::store::save(&app, STORE_NAME, &*items)?;
+    Ok(report)
+}
+
+/// Re-read every open pipeline item. Emits "price-drop" for each item that got
+/// cheaper since the last check; sold items move to "missed".
+#[tauri::command]
+pub async fn refresh_pipeline_prices(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PipelineState>,
+    token: String,
+) -> Result<PriceRefresh, String> {
+    let open: Vec<String> = state
+        .items
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|i| i.status.is_open())
+        .map(|i| i.id.clone())
+        .collect();
+
+    let mut report = PriceRefresh { checked: 0, drops: Vec::new(), missed: Vec::new(), errors: BTreeMap::new() };
+    for (n, id) in open.iter().enumerate() {
+        if n > 0 {
+            tokio::time::sleep(REFRESH_PACING).await;
+        }
+        let product = match super::get_product(&token, id).await {
+            Ok(p) => p,
+            Err(e) => {
+                report.errors.insert(id.clone(), e);
+                continue;
+            }
+        };
+        report.checked += 1;
+        let (price, currency) = price_of(&product);
+        let now = crate::unix_now();
+
+        let mut items = state.items.lock().unwrap();
+        let Some(item) = items.get_mut(id) else {
+            continue;
+        };
+        item.price_checked_at = Some(now);
+        if currency.is_some() {
+            item.currency = currency;
+        }
+        if is_sold(&product) {
+            item.status = PipelineStatus::Missed;
+            item.status_changed_at = now;
+            item.sold_price = price.or(item.current_price);
+            report.missed.push(id.clone());
+        }
+        if let (Some(previous), Some(current)) = (item.current_price, price) {
+            if current < previous && item.status.is_open() {
+                let drop = PriceDrop {
+                    id: id.clone(),
+                    title: item.title.clone(),
+                    url: item.url.clone(),
+                    previous,
+                    current,
+                    price_at_like: item.price_at_like,
+                    currency: item.currency.clone(),
+                };
+                let _ = app.emit("price-drop", drop.clone());
+                report.drops.push(drop);
+            }
+        }
+        if price.is_some() {
+            item.current_price = price;
+        }
+    }
+    crate::store::save(&app, STORE_NAME, &*state.items.lock().unwrap())?;
+    Ok(report)
+}
+
+/// Move an item along the pipeline. `note` replaces the current note when
+/// given; an empty note clears it.
+#[tauri::command]
+pub fn update_pipeline_status(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PipelineState>,
+    id: String,
+    status: PipelineStatus,
+    note: Option<String>,
+) -> Result<PipelineItem, String> {
+    let mut items = state.items.lock().unwrap();
+    let item = items.get_mut(&id).ok_or_else(|| format!("{id} is not in the sourcing pipeline"))?;
+    if item.status != status {
+        item.status = status;
+        item.status_changed_at = crate::unix_now();
+    }
+    if let Some(note) = note {
+        let note = note.trim();
+        item.note = (!note.is_empty()).then(|| note.to_string());
+    }
+    let item = item.clone();
+    crate::store::save(&app, STORE_NAME, &*items)?;
+    Ok(item)
+}
+
+/// Pipeline items, most recently moved first.
+#[tauri::command]
+pub fn get_pipeline(state: tauri::State<'_, PipelineState>, filter: Option<PipelineFilter>) -> Vec<PipelineItem> {
+    let filter = filter.unwrap_or_default();
+    let needle = filter.title_contains.as_deref().map(str::to_lowercase);
+    let mut items: Vec<PipelineItem> = state
+        .items
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|i| filter.statuses.as_ref().map_or(true, |s| s.contains(&i.status)))
+        .filter(|i| filter.account.as_deref().map_or(true, |a| i.account == a))
+        .filter(|i| needle.as_deref().map_or(true, |n| i.title.to_lowercase().contains(n)))
+        .cloned()
+        .collect();
+    items.sort_by(|a, b| b.status_changed_at.cmp(&a.status_changed_at).then_with(|| a.id.cmp(&b.id)));
+    items
+}
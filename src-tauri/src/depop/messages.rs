@@ -0,0 +1,422 @@
+// ── Depop message attachments ──────────────────────────────────────────────
+// Buyers send photos in chat (damage claims, "does it look like this?").
+// The frontend passes the conversation's raw messages to
+// `depop_sync_message_attachments`; image attachments are downloaded (with
+// the account's token when served from Depop) into
+// `message_attachments/<conversation>/` under app data, with a JPEG thumbnail
+// beside each, and indexed so later syncs skip them and exports can list them
+// (`get_conversation_attachments`).
+//
+// Each conversation keeps at most `max_mb_per_conversation` of originals;
+// attachments past the cap are reported as skipped. With `skip_when_metered`
+// set, nothing is downloaded while the caller says the connection is metered.
+//
+// `depop_send_message_with_image` goes the other way: the photo is always
+// re-encoded through the image pipeline before upload, which drops EXIF
+// (GPS position, camera serial) from whatever the user picked.
+
+use crate::images::{self, AspectMode, ImageOptions, OutputFormat};
+use crate::rate_limit::Priority;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+
+const SETTINGS_STORE: &str = "message_attachment_settings";
+const INDEX_STORE: &str = "message_attachments";
+const ATTACHMENT_DIR: &str = "message_attachments";
+/// Keys under which Depop's message payloads carry images.
+const ATTACHMENT_KEYS: &[&str] = &["attachments", "pictures", "images", "picture", "image"];
+const THUMBNAIL_SIZE: u32 = 320;
+/// Longest side of a photo sent in chat.
+const SEND_MAX_DIMENSION: u32 = 1600;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AttachmentSettings {
+    pub enabled: bool,
+    pub skip_when_metered: bool,
+    pub max_mb_per_conversation: u64,
+}
+
+impl Default for AttachmentSettings {
+    fn default() -> Self {
+        AttachmentSettings { enabled: true, skip_when_metered: true, max_mb_per_conversation: 50 }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StoredAttachment {
+    pub message_id: String,
+    pub url: String,
+    pub path: String,
+    pub thumbnail: Option<String>,
+    pub bytes: u64,
+    /// Unix seconds.
+    pub downloaded_at: u64,
+}
+
+pub struct MessageAttachmentState {
+    settings: Mutex<AttachmentSettings>,
+    /// conversation id -> downloaded attachments, oldest first.
+    index: Mutex<BTreeMap<String, Vec<StoredAttachment>>>,
+}
+
+impl MessageAttachmentState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        MessageAttachmentState {
+            settings: Mutex::new(crate::store::load(app, SETTINGS_STORE)),
+            index: Mutex::new(crate::store::load(app, INDEX_STORE)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentStatus {
+    Downloaded,
+    /// Downloaded by an earlier sync.
+    Stored,
+    Disabled,
+    Metered,
+    /// The conversation is at its size cap.
+    OverCap,
+    Failed,
+}
+
+#[derive(Serialize)]
+pub struct MessageAttachment {
+    message_id: String,
+    url: String,
+    status: AttachmentStatus,
+    path: Option<String>,
+    thumbnail: Option<String>,
+    bytes: u64,
+    error: Option<String>,
+}
+
+fn id_string(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// The largest image URL in one attachment entry: a plain URL string, an
+/// object with `url`, or Depop's picture shape with sized `formats`.
+fn attachment_url(entry: &Value) -> Option<String> {
+    if let Some(url) = entry.as_str() {
+        return Some(url.to_string());
+    }
+    if let Some(kind) = entry.get("type").and_then(Value::as_str) {
+        if !kind.eq_ignore_ascii_case("image") && !kind.eq_ignore_ascii_case("picture") {
+            return None;
+        }
+    }
+    let direct = ["url", "image_url", "src"].iter().find_map(|k| entry.get(*k).and_then(Value::as_str));
+    if let Some(url) = direct {
+        return Some(url.to_string());
+    }
+    // Format keys are sizes (P0..P8, with P0 the original); take the first.
+    let formats = entry.get("formats")?.as_object()?;
+    let mut keys: Vec<&String> = formats.keys().collect();
+    keys.sort();
+    keys.iter().find_map(|k| formats[k.as_str()].get("url").and_then(Value::as_str)).map(str::to_string)
+}
+
+/// (message id, image URL) for every image attachment in `messages`.
+fn image_attachments(messages: &[Value]) -> Vec<(String, String)> {
+    let mut out = Vec::new();
+    let mut seen = BTreeSet::new();
+    for message in messages {
+        let Some(message_id) = id_string(message.get("id")) else {
+            continue;
+        };
+        for key in ATTACHMENT_KEYS {
+            let entries = match message.get(*key) {
+                Some(Value::Array(entries)) => entries.iter().collect(),
+                Some(entry @ (Value::Object(_) | Value::String(_))) => vec![entry],
+                _ => continue,
+            };
+            for url in entries.into_iter().filter_map(attachment_url) {
+                if url.starts_with("https://") && seen.insert(url.clone()) {
+                    out.push((message_id.clone(), url));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// File-name-safe form of an id from the API.
+fn safe_name(id: &str) -> String {
+    id.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect()
+}
+
+fn conversation_dir(app: &tauri::AppHandle, conversation_id: &str) -> Result<PathBuf, String> {
+    let dir = crate::store::data_path(app, ATTACHMENT_DIR)?.join(safe_name(conversation_id));
+    std::fs::create_dir_all(dir.join("thumbs")).map_err(|e| format!("create {}: {e}", dir.display()))?;
+    Ok(dir)
+}
+
+fn is_depop_host(url: &str) -> bool {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_ascii_lowercase))
+        .is_some_and(|host| host == "depop.com" || host.ends_with(".depop.com"))
+}
+
+/// Download `url` into `dir`, refusing bodies over `limit` bytes.
+async fn download(
+    client: &reqwest::Client,
+    token: &str,
+    url: &str,
+    dir: &Path,
+    stem: &str,
+    limit: u64,
+) -> Result<Option<(PathBuf, u64)>, String> {
+    let mut req = client.get(url);
+    // Image CDNs outside Depop never see the token.
+    if is_depop_host(url) {
+        req = req.header("Authorization", super::bearer(token)?);
+    }
+    let mut resp = req.send().await.map_err(|e| format!("request: {e}"))?;
+    if !resp.status().is_success() {
+        return Err(format!("{url} returned {}", resp.status().as_u16()));
+    }
+    if resp.content_length().is_some_and(|len| len > limit) {
+        return Ok(None);
+    }
+    let ext = match resp.headers().get(reqwest::header::CONTENT_TYPE).and_then(|v| v.to_str().ok()) {
+        Some(ct) if ct.starts_with("image/png") => "png",
+        Some(ct) if ct.starts_with("image/webp") => "webp",
+        Some(ct) if ct.starts_with("image/gif") => "gif",
+        Some(ct) if ct.starts_with("image/") => "jpg",
+        Some(ct) => return Err(format!("not an image (Content-Type {ct:?})")),
+        None => "jpg",
+    };
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await.map_err(|e| format!("body: {e}"))? {
+        if (body.len() + chunk.len()) as u64 > limit {
+            return Ok(None);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let path = dir.join(format!("{stem}.{ext}"));
+    std::fs::write(&path, &body).map_err(|e| format!("write {}: {e}", path.display()))?;
+    Ok(Some((path, body.len() as u64)))
+}
+
+fn write_thumbnail(original: &Path, out: &Path) -> Result<(), String> {
+    let img = image::open(original).map_err(|e| format!("decode {}: {e}", original.display()))?;
+    let opts = ImageOptions {
+        max_dimension: THUMBNAIL_SIZE,
+        aspect: AspectMode::Fit,
+        format: OutputFormat::Jpeg,
+        quality: 80,
+        watermark: false,
+    };
+    let bytes = images::encode(&images::render(img, &opts, None), &opts)?;
+    std::fs::write(out, bytes).map_err(|e| format!("write {}: {e}", out.display()))
+}
+
+/// Download the image attachments in one conversation's `messages` (the raw
+/// Depop message objects). Returns one entry per attachment found, including
+/// ones already stored or skipped. `metered` is the frontend's read of the
+/// current connection.
+#[tauri::command]
+pub async fn depop_sync_message_attachments(
+    app: tauri::AppHandle,
+    token: String,
+    conversation_id: String,
+    messages: Vec<Value>,
+    metered: Option<bool>,
+) -> Result<Vec<MessageAttachment>, String> {
+    let conversation_id = conversation_id.trim().to_string();
+    if conversation_id.is_empty() {
+        return Err("conversation_id can't be empty".to_string());
+    }
+    let state = app.state::<MessageAttachmentState>();
+    let settings = state.settings.lock().unwrap().clone();
+    let (stored, mut used) = {
+        let index = state.index.lock().unwrap();
+        let stored: BTreeMap<String, StoredAttachment> = index
+            .get(&conversation_id)
+            .into_iter()
+            .flatten()
+            .map(|a| (a.url.clone(), a.clone()))
+            .collect();
+        let used: u64 = stored.values().map(|a| a.bytes).sum();
+        (stored, used)
+    };
+    let cap = settings.max_mb_per_conversation.saturating_mul(1024 * 1024);
+    let blocked = if !settings.enabled {
+        Some(AttachmentStatus::Disabled)
+    } else if settings.skip_when_metered && metered.unwrap_or(false) {
+        Some(AttachmentStatus::Metered)
+    } else {
+        None
+    };
+
+    let found = image_attachments(&messages);
+    let mut results = Vec::with_capacity(found.len());
+    let mut fresh = Vec::new();
+    let mut dir = None;
+    let client = super::client()?;
+    for (n, (message_id, url)) in found.into_iter().enumerate() {
+        let mut result = MessageAttachment {
+            message_id: message_id.clone(),
+            url: url.clone(),
+            status: AttachmentStatus::Failed,
+            path: None,
+            thumbnail: None,
+            bytes: 0,
+            error: None,
+        };
+        if let Some(existing) = stored.get(&url) {
+            result.status = AttachmentStatus::Stored;
+            result.path = Some(existing.path.clone());
+            result.thumbnail = existing.thumbnail.clone();
+            result.bytes = existing.bytes;
+            results.push(result);
+            continue;
+        }
+        if let Some(status) = blocked {
+            result.status = status;
+            results.push(result);
+            continue;
+        }
+        if used >= cap {
+            result.status = AttachmentStatus::OverCap;
+            results.push(result);
+            continue;
+        }
+        if dir.is_none() {
+            dir = Some(conversation_dir(&app, &conversation_id)?);
+        }
+        let dir_path = dir.as_deref().unwrap_or(Path::new("."));
+        let stem = format!("{}-{}", safe_name(&message_id), n + 1);
+        match download(&client, &token, &url, dir_path, &stem, cap - used).await {
+            Ok(Some((path, bytes))) => {
+                used += bytes;
+                let thumb = dir_path.join("thumbs").join(format!("{stem}.jpg"));
+                let thumbnail = match write_thumbnail(&path, &thumb) {
+                    Ok(()) => Some(thumb.to_string_lossy().into_owned()),
+                    Err(e) => {
+                        log::warn!("Thumbnail for {}: {e}", path.display());
+                        None
+                    }
+                };
+                result.status = AttachmentStatus::Downloaded;
+                result.path = Some(path.to_string_lossy().into_owned());
+                result.thumbnail = thumbnail.clone();
+                result.bytes = bytes;
+                fresh.push(StoredAttachment {
+                    message_id,
+                    url,
+                    path: path.to_string_lossy().into_owned(),
+                    thumbnail,
+                    bytes,
+                    downloaded_at: crate::unix_now(),
+                });
+            }
+            Ok(None) => result.status = AttachmentStatus::OverCap,
+            Err(e) => result.error = Some(e),
+        }
+        results.push(result);
+    }
+
+    if !fresh.is_empty() {
+        let mut index = state.index.lock().unwrap();
+        index.entry(conversation_id).or_default().extend(fresh);
+        crate::store::save(&app, INDEX_STORE, &*index)?;
+    }
+    Ok(results)
+}
+
+/// Attachments downloaded for a conversation, oldest first — for the
+/// conversation view and for exports.
+#[tauri::command]
+pub fn get_conversation_attachments(
+    state: tauri::State<'_, MessageAttachmentState>,
+    conversation_id: String,
+) -> Vec<StoredAttachment> {
+    state.index.lock().unwrap().get(conversation_id.trim()).cloned().unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn get_message_attachment_settings(state: tauri::State<'_, MessageAttachmentState>) -> AttachmentSettings {
+    state.settings.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_message_attachment_settings(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, MessageAttachmentState>,
+    settings: AttachmentSettings,
+) -> Result<(), String> {
+    if settings.max_mb_per_conversation == 0 {
+        return Err("The per-conversation limit must be at least 1 MB".to_string());
+    }
+    crate::store::save(&app, SETTINGS_STORE, &settings)?;
+    *state.settings.lock().unwrap() = settings;
+    Ok(())
+}
+
+/// Decode and re-encode a photo for sending. Re-encoding from pixels is what
+/// strips EXIF, so there's deliberately no pass-through for JPEGs.
+fn prepare_image(path: &Path) -> Result<Vec<u8>, String> {
+    let img = image::open(path).map_err(|e| format!("decode {}: {e}", path.display()))?;
+    let opts = ImageOptions {
+        max_dimension: SEND_MAX_DIMENSION,
+        aspect: AspectMode::Fit,
+        format: OutputFormat::Jpeg,
+        quality: 85,
+        watermark: false,
+    };
+    images::encode(&images::render(img, &opts, None), &opts)
+}
+
+/// Send a Depop chat message with a photo about a product. The image is
+/// uploaded first, then the message referencing it. Audited; not retried on
+/// an ambiguous failure.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn depop_send_message_with_image(
+    app: tauri::AppHandle,
+    account: String,
+    token: String,
+    recipient: String,
+    product_id: String,
+    text: String,
+    image_path: String,
+) -> Result<Value, String> {
+    let path = PathBuf::from(&image_path);
+    let bytes = tauri::async_runtime::spawn_blocking(move || prepare_image(&path))
+        .await
+        .map_err(|e| format!("image task: {e}"))??;
+    let result = crate::write_queue::submit(&app, "depop", Priority::Interactive, || async {
+        let uploaded = super::upload_picture(&token, bytes, "image/jpeg").await?;
+        let picture_id = super::photos::id_of(&uploaded).ok_or("Upload response had no picture id")?;
+        crate::write_queue::idempotent(
+            &app,
+            "depop",
+            "send_message",
+            Some(&product_id),
+            |_| async { Err("sent messages can't be looked up".to_string()) },
+            |_| super::send_message_with_picture(&token, &recipient, &product_id, &text, &picture_id),
+        )
+        .await
+    })
+    .await;
+    crate::audit::record(
+        &app,
+        "depop_send_message_with_image",
+        Some(&account),
+        false,
+        json!({ "recipient": recipient, "product_id": product_id, "error": result.as_ref().err() }),
+    );
+    result
+}
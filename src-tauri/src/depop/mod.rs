@@ -0,0 +1,257 @@
+// ── Depop API (Rust side) ──────────────────────────────────────────────────
+// Thin helpers over Depop's web API for operations that run in the backend
+// (bulk writes that need pacing, auditing and dry runs). Callers pass the
+// access token captured by the login flow, the same way the TS adapter does.
+
+pub mod attributes;
+pub mod boost;
+pub mod bump;
+pub mod footer;
+pub mod health;
+pub mod international;
+pub mod liked;
+pub mod messages;
+pub mod offers;
+pub mod photos;
+pub mod search_rank;
+pub mod sync;
+pub mod video;
+
+use crate::metrics::METRICS;
+use serde_json::Value;
+use std::time::Instant;
+
+pub const API_URL: &str = "https://webapi.depop.com/api/v2";
+
+/// Fields we send back when re-saving a listing; everything else in the GET
+/// response is read-only metadata.
+const EDITABLE_FIELDS: &[&str] = &[
+    "description",
+    "price",
+    "currency",
+    "condition",
+    "categories",
+    "photos",
+    "videos",
+    "brand",
+    "size",
+    "colour",
+    "quantity",
+    INTERNATIONAL_SHIPPING,
+];
+
+/// Per-region international shipping on a product (see international).
+pub const INTERNATIONAL_SHIPPING: &str = "international_shipping";
+
+/// Conflict checks for queued writes compare `updated_marker`, showing the
+/// editable fields.
+pub const VERSIONING: crate::write_queue::Versioning =
+    crate::write_queue::Versioning { version_of: updated_marker, fields: EDITABLE_FIELDS };
+
+/// `DEPOP_WEB:{slug}` identifiers come from the cookie-session path and
+/// can't authorize API calls.
+fn bearer(token: &str) -> Result<String, String> {
+    if token.starts_with("DEPOP_WEB:") {
+        return Err("This Depop connection only has a web session — reconnect to capture an API token".to_string());
+    }
+    Ok(format!("Bearer {token}"))
+}
+
+fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(20))
+        .gzip(true)
+        .build()
+        .map_err(|e| format!("client build: {e}"))
+}
+
+async fn json_or_error(resp: reqwest::Response) -> Result<Value, String> {
+    let status = resp.status();
+    let body = resp.text().await.map_err(|e| format!("body: {e}"))?;
+    if !status.is_success() {
+        let snippet: String = body.chars().take(300).collect();
+        return Err(format!("Depop returned {}: {snippet}", status.as_u16()));
+    }
+    if body.trim().is_empty() {
+        return Ok(Value::Null);
+    }
+    serde_json::from_str(&body).map_err(|e| format!("invalid JSON from Depop: {e}"))
+}
+
+pub async fn get_product(token: &str, product_id: &str) -> Result<Value, String> {
+    let started = Instant::now();
+    let resp = client()?
+        .get(format!("{API_URL}/products/{product_id}/"))
+        .header("Authorization", bearer(token)?)
+        .send()
+        .await
+        .map_err(|e| format!("request: {e}"));
+    timed(started, resp).await
+}
+
+pub async fn put_product(token: &str, product_id: &str, payload: &Value) -> Result<Value, String> {
+    let started = Instant::now();
+    let resp = client()?
+        .put(format!("{API_URL}/products/{product_id}/"))
+        .header("Authorization", bearer(token)?)
+        .json(payload)
+        .send()
+        .await
+        .map_err(crate::write_queue::request_error);
+    timed(started, resp).await
+}
+
+/// Upload one image through the media endpoint; the response carries the new
+/// picture id to reference from a product's `photos`.
+pub async fn upload_picture(token: &str, bytes: Vec<u8>, content_type: &str) -> Result<Value, String> {
+    let started = Instant::now();
+    let resp = client()?
+        .post(format!("{API_URL}/pictures/"))
+        .header("Authorization", bearer(token)?)
+        .header(reqwest::header::CONTENT_TYPE, content_type)
+        .body(bytes)
+        .send()
+        .await
+        .map_err(crate::write_queue::request_error);
+    timed(started, resp).await
+}
+
+/// Pictures uploaded for a product, attached to it yet or not; how an
+/// ambiguous `upload_picture` is reconciled.
+pub async fn list_pictures(token: &str, product_id: &str) -> Result<Value, String> {
+    let started = Instant::now();
+    let resp = client()?
+        .get(format!("{API_URL}/products/{product_id}/pictures/"))
+        .header("Authorization", bearer(token)?)
+        .send()
+        .await
+        .map_err(|e| format!("request: {e}"));
+    timed(started, resp).await
+}
+
+/// Upload a listing video through the media endpoint; the response carries
+/// the video id to reference from a product's `videos`. Videos get a longer
+/// timeout than the other calls.
+pub async fn upload_video(token: &str, body: reqwest::Body, content_type: &str, len: u64) -> Result<Value, String> {
+    let started = Instant::now();
+    let resp = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(600))
+        .build()
+        .map_err(|e| format!("client build: {e}"))?
+        .post(format!("{API_URL}/videos/"))
+        .header("Authorization", bearer(token)?)
+        .header(reqwest::header::CONTENT_TYPE, content_type)
+        .header(reqwest::header::CONTENT_LENGTH, len)
+        .body(body)
+        .send()
+        .await
+        .map_err(crate::write_queue::request_error);
+    timed(started, resp).await
+}
+
+/// Turn Boosted Listings on or off for one listing (the seller hub's
+/// per-listing toggle).
+pub async fn set_boost(token: &str, product_id: &str, enabled: bool) -> Result<Value, String> {
+    let started = Instant::now();
+    let resp = client()?
+        .put(format!("{API_URL}/products/{product_id}/boost/"))
+        .header("Authorization", bearer(token)?)
+        .json(&serde_json::json!({ "boosted": enabled }))
+        .send()
+        .await
+        .map_err(crate::write_queue::request_error);
+    timed(started, resp).await
+}
+
+/// Seller metrics from the seller hub (shipping time, response rate,
+/// violations) for the token's shop.
+pub async fn get_seller_health(token: &str) -> Result<Value, String> {
+    let started = Instant::now();
+    let resp = client()?
+        .get(format!("{API_URL}/sellers/me/performance/"))
+        .header("Authorization", bearer(token)?)
+        .send()
+        .await
+        .map_err(|e| format!("request: {e}"));
+    timed(started, resp).await
+}
+
+/// Send a chat message to `recipient` about `product_id`.
+pub async fn send_message(token: &str, recipient: &str, product_id: &str, text: &str) -> Result<Value, String> {
+    let started = Instant::now();
+    let resp = client()?
+        .post(format!("{API_URL}/conversations/messages/"))
+        .header("Authorization", bearer(token)?)
+        .json(&serde_json::json!({ "receiver": recipient, "product_id": product_id, "text": text }))
+        .send()
+        .await
+        .map_err(crate::write_queue::request_error);
+    timed(started, resp).await
+}
+
+/// Send a chat message carrying a picture uploaded with `upload_picture`.
+pub async fn send_message_with_picture(
+    token: &str,
+    recipient: &str,
+    product_id: &str,
+    text: &str,
+    picture_id: &str,
+) -> Result<Value, String> {
+    let started = Instant::now();
+    let resp = client()?
+        .post(format!("{API_URL}/conversations/messages/"))
+        .header("Authorization", bearer(token)?)
+        .json(&serde_json::json!({
+            "receiver": recipient,
+            "product_id": product_id,
+            "text": text,
+            "picture_ids": [picture_id],
+        }))
+        .send()
+        .await
+        .map_err(crate::write_queue::request_error);
+    timed(started, resp).await
+}
+
+/// Decode the response and record the call in the depop_api metrics.
+async fn timed(started: Instant, resp: Result<reqwest::Response, String>) -> Result<Value, String> {
+    let result = match resp {
+        Ok(resp) => {
+            crate::outage::record("depop", resp.status().is_server_error());
+            json_or_error(resp).await
+        }
+        Err(e) => {
+            if e.contains("timed out") {
+                crate::outage::record("depop", true);
+            }
+            Err(e)
+        }
+    };
+    METRICS.depop_api.since(started);
+    if result.is_err() {
+        METRICS.depop_api_errors.inc();
+    }
+    result
+}
+
+/// The subset of a fetched product that can be PUT back unchanged.
+pub fn editable_payload(product: &Value) -> Value {
+    let mut out = serde_json::Map::new();
+    for key in EDITABLE_FIELDS {
+        if let Some(v) = product.get(*key) {
+            out.insert((*key).to_string(), v.clone());
+        }
+    }
+    Value::Object(out)
+}
+
+/// Last-modified marker of a product, whichever name this API version uses.
+pub fn updated_marker(product: &Value) -> Option<String> {
+    ["updated_at", "updatedAt", "date_updated", "dateUpdated", "last_modified"]
+        .iter()
+        .find_map(|k| match product.get(*k)? {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        })
+}
@@ -0,0 +1,454 @@
+// Offer and counter-offer tracking with expiry nudges.
+//
+// Depop offers and counters expire after 24 hours. Every offer synced or sent
+// is recorded here with its expiry time, and anything past it is marked
+// expired locally on every read and scheduler tick, whether or not a
+// marketplace sync has run since. That way the UI never shows a dead offer as
+// actionable.
+//
+// The scheduler looks at counters the seller sent. When one is within
+// `hours_before` of expiring with no response:
+// - with auto-nudge on, it sends the configured message (at most one per
+//   buyer per item, paced, audited);
+// - otherwise it emits `offer-expiring` once.
+//
+// Nudges reuse the last token the frontend passed for the account. Tokens are
+// kept in memory only, so after a restart nothing is sent until the frontend
+// records or syncs offers again; the event is emitted instead.
+
+use crate::privacy::Private;
+use crate::rate_limit::Priority;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+const OFFERS_STORE: &str = "depop_offers";
+const SETTINGS_STORE: &str = "offer_nudge_settings";
+const OFFER_LIFETIME_SECS: u64 = 24 * 60 * 60;
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Cap on nudges sent per rolling hour, across all accounts.
+const MAX_NUDGES_PER_HOUR: usize = 10;
+/// Closed offers older than this are dropped from the store.
+const KEEP_CLOSED_SECS: u64 = 30 * 24 * 60 * 60;
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OfferKind {
+    Offer,
+    Counter,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Received,
+    Sent,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OfferStatus {
+    Open,
+    Accepted,
+    Declined,
+    /// The other side countered.
+    Countered,
+    Expired,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OfferRecord {
+    pub id: String,
+    pub account: String,
+    pub product_id: String,
+    #[serde(default)]
+    pub product_title: Option<String>,
+    pub buyer: String,
+    pub kind: OfferKind,
+    pub direction: Direction,
+    pub amount: f64,
+    #[serde(default)]
+    pub currency: Option<String>,
+    pub created_at: u64,
+    pub expires_at: u64,
+    pub status: OfferStatus,
+    #[serde(default)]
+    pub status_changed_at: Option<u64>,
+    #[serde(default)]
+    pub expiring_notified: bool,
+    #[serde(default)]
+    pub nudged_at: Option<u64>,
+}
+
+/// An offer as reported by the sync or the send path.
+#[derive(Deserialize)]
+pub struct OfferInput {
+    id: String,
+    product_id: String,
+    #[serde(default)]
+    product_title: Option<String>,
+    buyer: String,
+    kind: OfferKind,
+    direction: Direction,
+    amount: f64,
+    #[serde(default)]
+    currency: Option<String>,
+    /// Unix seconds; defaults to now.
+    #[serde(default)]
+    created_at: Option<u64>,
+    /// Unix seconds; defaults to 24 hours after `created_at`.
+    #[serde(default)]
+    expires_at: Option<u64>,
+    #[serde(default)]
+    status: Option<OfferStatus>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NudgeSettings {
+    pub auto_nudge: bool,
+    pub hours_before: u64,
+    /// `{buyer}`, `{item}`, `{amount}` and `{hours}` are filled in.
+    pub message: String,
+}
+
+impl Default for NudgeSettings {
+    fn default() -> Self {
+        NudgeSettings {
+            auto_nudge: false,
+            hours_before: 4,
+            message: "Hi {buyer}! Just a reminder that my offer of {amount} on {item} expires in about {hours} hours. \
+                      Let me know if you have any questions!"
+                .to_string(),
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct OfferBook {
+    offers: BTreeMap<String, OfferRecord>,
+    /// "{account}:{buyer}:{product_id}" pairs already nudged.
+    nudged: BTreeSet<String>,
+}
+
+pub struct OfferState {
+    book: Mutex<OfferBook>,
+    settings: Mutex<NudgeSettings>,
+    tokens: Mutex<HashMap<String, String>>,
+    sent_times: Mutex<Vec<u64>>,
+}
+
+impl OfferState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        OfferState {
+            book: Mutex::new(crate::store::load(app, OFFERS_STORE)),
+            settings: Mutex::new(crate::store::load(app, SETTINGS_STORE)),
+            tokens: Mutex::new(HashMap::new()),
+            sent_times: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct ExpiringOffer {
+    offer: OfferRecord,
+    account_meta: Option<crate::accounts::AccountMetadata>,
+    seconds_left: u64,
+}
+
+/// Mark open offers past their expiry as expired and drop long-closed ones.
+/// Returns the ids that just expired.
+fn expire(book: &mut OfferBook, now: u64) -> Vec<String> {
+    let mut expired = Vec::new();
+    for offer in book.offers.values_mut() {
+        if offer.status == OfferStatus::Open && offer.expires_at <= now {
+            offer.status = OfferStatus::Expired;
+            offer.status_changed_at = Some(now);
+            expired.push(offer.id.clone());
+        }
+    }
+    book.offers.retain(|_, o| {
+        o.status == OfferStatus::Open || o.status_changed_at.unwrap_or(o.expires_at) + KEEP_CLOSED_SECS > now
+    });
+    expired
+}
+
+fn save_book(app: &tauri::AppHandle, book: &OfferBook) {
+    if let Err(e) = crate::store::save(app, OFFERS_STORE, book) {
+        log::warn!("saving offers failed: {e}");
+    }
+}
+
+fn nudge_key(offer: &OfferRecord) -> String {
+    format!("{}:{}:{}", offer.account, offer.buyer, offer.product_id)
+}
+
+fn nudge_text(template: &str, offer: &OfferRecord, now: u64) -> String {
+    let hours = offer.expires_at.saturating_sub(now).div_ceil(3600);
+    let amount = match &offer.currency {
+        Some(c) => format!("{:.2} {c}", offer.amount),
+        None => format!("{:.2}", offer.amount),
+    };
+    template
+        .replace("{buyer}", &offer.buyer)
+        .replace("{item}", offer.product_title.as_deref().unwrap_or("the item"))
+        .replace("{amount}", &amount)
+        .replace("{hours}", &hours.to_string())
+}
+
+/// Send one chat message through the Depop write lane. Messages aren't safe
+/// to repeat, so an ambiguous failure is recorded rather than retried.
+async fn send(
+    app: &tauri::AppHandle,
+    priority: Priority,
+    token: &str,
+    recipient: &str,
+    product_id: &str,
+    text: &str,
+) -> Result<serde_json::Value, String> {
+    crate::write_queue::submit(app, "depop", priority, || {
+        crate::write_queue::idempotent(
+            app,
+            "depop",
+            "send_message",
+            Some(product_id),
+            |_| async { Err("sent messages can't be looked up".to_string()) },
+            |_| super::send_message(token, recipient, product_id, text),
+        )
+    })
+    .await
+}
+
+/// One scheduler pass: expire, then nudge or announce counters close to expiry.
+async fn check(app: &tauri::AppHandle) {
+    let state = app.state::<OfferState>();
+    let now = crate::unix_now();
+    let settings = state.settings.lock().unwrap().clone();
+
+    let (expired, due) = {
+        let mut book = state.book.lock().unwrap();
+        let expired = expire(&mut book, now);
+        let window = settings.hours_before * 3600;
+        let due: Vec<OfferRecord> = book
+            .offers
+            .values()
+            .filter(|o| {
+                o.status == OfferStatus::Open
+                    && o.kind == OfferKind::Counter
+                    && o.direction == Direction::Sent
+                    && o.expires_at.saturating_sub(now) <= window
+                    && o.nudged_at.is_none()
+                    && !o.expiring_notified
+            })
+            .cloned()
+            .collect();
+        if !expired.is_empty() {
+            save_book(app, &book);
+        }
+        (expired, due)
+    };
+    if !expired.is_empty() {
+        let _ = app.emit("offers-expired", &expired);
+    }
+
+    for offer in due {
+        let key = nudge_key(&offer);
+        let already = state.book.lock().unwrap().nudged.contains(&key);
+        let token = state.tokens.lock().unwrap().get(&offer.account).cloned();
+        let under_cap = {
+            let mut sent = state.sent_times.lock().unwrap();
+            sent.retain(|t| now.saturating_sub(*t) < 3600);
+            sent.len() < MAX_NUDGES_PER_HOUR
+        };
+
+        match token {
+            Some(token) if settings.auto_nudge && !already => {
+                if !under_cap || crate::outage::is_degraded("depop") {
+                    // Try again next tick.
+                    continue;
+                }
+                let text = nudge_text(&settings.message, &offer, now);
+                let result = send(app, Priority::Background, &token, &offer.buyer, &offer.product_id, &text).await;
+                crate::audit::record(
+                    app,
+                    "depop_offer_nudge",
+                    Some(&offer.account),
+                    false,
+                    serde_json::json!({
+                        "offer": offer.id,
+                        "product_id": offer.product_id,
+                        "buyer": offer.buyer,
+                        "message": text,
+                        "error": result.as_ref().err(),
+                    }),
+                );
+                state.sent_times.lock().unwrap().push(now);
+                let mut book = state.book.lock().unwrap();
+                // Failed or not, one attempt per buyer per item.
+                book.nudged.insert(key);
+                if let Some(o) = book.offers.get_mut(&offer.id) {
+                    o.nudged_at = Some(now);
+                    o.expiring_notified = true;
+                }
+                save_book(app, &book);
+            }
+            _ => {
+                let _ = app.emit(
+                    "offer-expiring",
+                    Private::new(ExpiringOffer {
+                        account_meta: crate::accounts::meta(app, &offer.account),
+                        seconds_left: offer.expires_at.saturating_sub(now),
+                        offer: offer.clone(),
+                    }),
+                );
+                let mut book = state.book.lock().unwrap();
+                if let Some(o) = book.offers.get_mut(&offer.id) {
+                    o.expiring_notified = true;
+                }
+                save_book(app, &book);
+            }
+        }
+    }
+}
+
+pub fn start_nudge_job(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            check(&app).await;
+        }
+    });
+}
+
+/// Record offers from a sync or a send. Known ids are updated in place (a
+/// newer status wins); `token` is kept in memory for nudges.
+#[tauri::command]
+pub fn record_depop_offers(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, OfferState>,
+    account: String,
+    token: Option<String>,
+    offers: Vec<OfferInput>,
+) -> Result<Private<Vec<OfferRecord>>, String> {
+    crate::accounts::remember(&app, &account);
+    if let Some(token) = token {
+        state.tokens.lock().unwrap().insert(account.clone(), token);
+    }
+    let now = crate::unix_now();
+    let mut book = state.book.lock().unwrap();
+    let mut recorded = Vec::with_capacity(offers.len());
+    for input in offers {
+        if !input.amount.is_finite() || input.amount < 0.0 {
+            return Err(format!("Offer {} has an invalid amount", input.id));
+        }
+        let created_at = input.created_at.unwrap_or(now);
+        let expires_at = input.expires_at.unwrap_or(created_at + OFFER_LIFETIME_SECS);
+        let record = match book.offers.get(&input.id) {
+            Some(existing) => {
+                let mut r = existing.clone();
+                if let Some(status) = input.status.filter(|s| *s != r.status) {
+                    r.status = status;
+                    r.status_changed_at = Some(now);
+                }
+                r.expires_at = expires_at;
+                r.amount = input.amount;
+                r
+            }
+            None => OfferRecord {
+                id: input.id.clone(),
+                account: account.clone(),
+                product_id: input.product_id,
+                product_title: input.product_title,
+                buyer: input.buyer,
+                kind: input.kind,
+                direction: input.direction,
+                amount: input.amount,
+                currency: input.currency,
+                created_at,
+                expires_at,
+                status: input.status.unwrap_or(OfferStatus::Open),
+                status_changed_at: None,
+                expiring_notified: false,
+                nudged_at: None,
+            },
+        };
+        book.offers.insert(record.id.clone(), record.clone());
+        recorded.push(record);
+    }
+    expire(&mut book, now);
+    crate::store::save(&app, OFFERS_STORE, &*book)?;
+    Ok(Private::new(recorded))
+}
+
+/// Offers, soonest-expiring first. Expiry is applied before reading.
+#[tauri::command]
+pub fn list_depop_offers(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, OfferState>,
+    account: Option<String>,
+    include_closed: Option<bool>,
+) -> Private<Vec<OfferRecord>> {
+    let include_closed = include_closed.unwrap_or(false);
+    let mut book = state.book.lock().unwrap();
+    if !expire(&mut book, crate::unix_now()).is_empty() {
+        save_book(&app, &book);
+    }
+    let mut offers: Vec<OfferRecord> = book
+        .offers
+        .values()
+        .filter(|o| account.as_deref().map_or(true, |a| o.account == a))
+        .filter(|o| include_closed || o.status == OfferStatus::Open)
+        .cloned()
+        .collect();
+    offers.sort_by_key(|o| o.expires_at);
+    Private::new(offers)
+}
+
+#[tauri::command]
+pub fn get_offer_nudge_settings(state: tauri::State<'_, OfferState>) -> NudgeSettings {
+    state.settings.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_offer_nudge_settings(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, OfferState>,
+    settings: NudgeSettings,
+) -> Result<(), String> {
+    if settings.hours_before == 0 || settings.hours_before >= 24 {
+        return Err("Nudges must go out between 1 and 23 hours before expiry".to_string());
+    }
+    if settings.message.trim().is_empty() {
+        return Err("The nudge message can't be empty".to_string());
+    }
+    let mut current = state.settings.lock().unwrap();
+    *current = settings;
+    crate::store::save(&app, SETTINGS_STORE, &*current)
+}
+
+/// Send a Depop chat message about a product. Audited; not retried on an
+/// ambiguous failure.
+#[tauri::command]
+pub async fn depop_send_message(
+    app: tauri::AppHandle,
+    account: String,
+    token: String,
+    recipient: String,
+    product_id: String,
+    text: String,
+) -> Result<serde_json::Value, String> {
+    if text.trim().is_empty() {
+        return Err("Message is empty".to_string());
+    }
+    let result = send(&app, Priority::Interactive, &token, &recipient, &product_id, &text).await;
+    crate::audit::record(
+        &app,
+        "depop_send_message",
+        Some(&account),
+        false,
+        serde_json::json!({ "recipient": recipient, "product_id": product_id, "error": result.as_ref().err() }),
+    );
+    result
+}
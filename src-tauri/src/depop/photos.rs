@@ -0,0 +1,359 @@
+// Photo repair: Depop occasionally loses listing images (they render as grey
+// squares). A photo counts as broken when its URL no longer serves an image.
+// Broken listings are fixed by re-uploading the original files, in order, and
+// re-saving the listing with the new picture ids.
+//
+// The frontend resolves which local files belong to each listing (inventory
+// images linked to the marketplace listing) and passes them in. Listings
+// without a complete local set come back as unrepairable, with the photo URLs
+// that still resolve so `download_listing_photos` can save them first.
+
+use crate::accounts::Guarded;
+use crate::write_queue::{Checked, Expected, ListingConflict};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tauri::Emitter;
+
+#[derive(Deserialize)]
+pub struct RepairTarget {
+    product_id: String,
+    /// Original photo files, in listing order.
+    #[serde(default)]
+    local_photos: Vec<String>,
+}
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RepairOutcome {
+    Repaired,
+    /// Every photo still resolves; nothing was changed.
+    Healthy,
+    /// Originals aren't all stored locally.
+    Unrepairable,
+    DryRun,
+    /// The listing changed elsewhere after the repair was queued; skipped.
+    Conflict,
+    Failed,
+}
+
+#[derive(Serialize)]
+pub struct PhotoRepairResult {
+    product_id: String,
+    outcome: RepairOutcome,
+    photo_count: usize,
+    broken: usize,
+    /// Picture ids now on the listing, in order.
+    uploaded: Vec<String>,
+    missing_local: Vec<String>,
+    /// Remote photos that still load — downloadable before a repair.
+    remote_resolvable: Vec<String>,
+    /// Both versions of the listing when the outcome is `Conflict`.
+    conflict: Option<ListingConflict>,
+    error: Option<String>,
+}
+
+impl PhotoRepairResult {
+    fn new(product_id: &str, outcome: RepairOutcome) -> Self {
+        PhotoRepairResult {
+            product_id: product_id.to_string(),
+            outcome,
+            photo_count: 0,
+            broken: 0,
+            uploaded: Vec::new(),
+            missing_local: Vec::new(),
+            remote_resolvable: Vec::new(),
+            conflict: None,
+            error: None,
+        }
+    }
+
+    fn failed(product_id: &str, error: String) -> Self {
+        PhotoRepairResult { error: Some(error), ..Self::new(product_id, RepairOutcome::Failed) }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct RepairProgress<'a> {
+    done: usize,
+    total: usize,
+    product_id: &'a str,
+    outcome: RepairOutcome,
+}
+
+/// URL of a product photo entry, whichever shape this API version returns.
+pub(crate) fn photo_url(photo: &Value) -> Option<String> {
+    match photo {
+        Value::String(s) if s.starts_with("http") => Some(s.clone()),
+        Value::Object(o) => ["url", "src", "href"]
+            .iter()
+            .find_map(|k| o.get(*k).and_then(Value::as_str).map(str::to_string))
+            .or_else(|| o.values().find_map(photo_url)),
+        _ => None,
+    }
+}
+
+/// A photo resolves when its URL answers 2xx with a non-empty image body.
+async fn resolves(client: &reqwest::Client, url: &str) -> bool {
+    let Ok(resp) = client.get(url).send().await else {
+        return false;
+    };
+    let is_image = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("image/"));
+    resp.status().is_success() && is_image && resp.content_length() != Some(0)
+}
+
+fn content_type_for(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        Some("heic") => "image/heic",
+        _ => "image/jpeg",
+    }
+}
+
+pub(super) fn id_of(uploaded: &Value) -> Option<String> {
+    match uploaded.get("id")? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Pictures in a `list_pictures` response (a bare array or `{"pictures": [...]}`).
+fn pictures(listed: &Value) -> &[Value] {
+    listed
+        .as_array()
+        .or_else(|| listed.get("pictures").and_then(Value::as_array))
+        .map_or(&[], Vec::as_slice)
+}
+
+fn picture_ids(listed: &Value) -> HashSet<String> {
+    pictures(listed).iter().filter_map(id_of).collect()
+}
+
+/// The newest picture that isn't in `known`: the upload an ambiguous attempt
+/// made, if it went through. Listings come back oldest first.
+fn new_picture(listed: &Value, known: &HashSet<String>) -> Option<Value> {
+    pictures(listed).iter().rev().find(|p| id_of(p).is_some_and(|id| !known.contains(&id))).cloned()
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn repair_listing_photos(
+    app: tauri::AppHandle,
+    account: String,
+    token: String,
+    targets: Vec<RepairTarget>,
+    dry_run: Option<bool>,
+    confirmation_token: Option<String>,
+    force: Option<bool>,
+) -> Result<Guarded<Vec<PhotoRepairResult>>, String> {
+    if targets.is_empty() {
+        return Err("No listings selected".to_string());
+    }
+    let dry_run = dry_run.unwrap_or(false);
+    let force = force.unwrap_or(false);
+    if !dry_run {
+        let ids: Vec<&str> = targets.iter().map(|t| t.product_id.as_str()).collect();
+        if let Some(request) =
+            crate::accounts::confirm(&app, &account, "depop_repair_photos", &ids, confirmation_token.as_deref())?
+        {
+            return Ok(Guarded::ConfirmationRequired(request));
+        }
+    }
+    let checker = super::client()?;
+    let total = targets.len();
+    let mut results = Vec::with_capacity(total);
+
+    for (i, target) in targets.iter().enumerate() {
+        let result = repair_one(&app, &token, &checker, target, dry_run, force).await;
+        crate::audit::record(
+            &app,
+            "depop_repair_photos",
+            Some(&account),
+            dry_run,
+            json!({
+                "product_id": result.product_id,
+                "outcome": result.outcome,
+                "broken": result.broken,
+                "uploaded": result.uploaded,
+                "missing_local": result.missing_local,
+                "error": result.error,
+            }),
+        );
+        let _ = app.emit(
+            "photo-repair-progress",
+            RepairProgress { done: i + 1, total, product_id: &result.product_id, outcome: result.outcome },
+        );
+        results.push(result);
+    }
+    Ok(Guarded::Completed { results })
+}
+
+async fn repair_one(
+    app: &tauri::AppHandle,
+    token: &str,
+    checker: &reqwest::Client,
+    target: &RepairTarget,
+    dry_run: bool,
+    force: bool,
+) -> PhotoRepairResult {
+    let product_id = target.product_id.as_str();
+    let product = match super::get_product(token, product_id).await {
+        Ok(p) => p,
+        Err(e) => return PhotoRepairResult::failed(product_id, e),
+    };
+    let photos = product.get("photos").and_then(Value::as_array).cloned().unwrap_or_default();
+
+    let mut result = PhotoRepairResult::new(product_id, RepairOutcome::Healthy);
+    result.photo_count = photos.len();
+    for url in photos.iter().filter_map(photo_url) {
+        if resolves(checker, &url).await {
+            result.remote_resolvable.push(url);
+        }
+    }
+    result.broken = photos.len() - result.remote_resolvable.len();
+    if result.broken == 0 {
+        return result;
+    }
+
+    result.missing_local = target
+        .local_photos
+        .iter()
+        .filter(|p| !Path::new(p).is_file())
+        .cloned()
+        .collect();
+    if target.local_photos.is_empty() || !result.missing_local.is_empty() {
+        result.outcome = RepairOutcome::Unrepairable;
+        return result;
+    }
+    if dry_run {
+        result.outcome = RepairOutcome::DryRun;
+        return result;
+    }
+
+    let objects = photos.first().is_some_and(Value::is_object);
+    let expected = Expected { listing_id: product_id, record: &product };
+    let saved = crate::write_queue::submit_checked(
+        app,
+        "depop",
+        crate::rate_limit::Priority::Background,
+        &super::VERSIONING,
+        expected,
+        force,
+        || super::get_product(token, product_id),
+        |current| async move {
+            let mut ids = Vec::with_capacity(target.local_photos.len());
+            for path in &target.local_photos {
+                let path = Path::new(path);
+                let bytes = std::fs::read(path).map_err(|e| format!("read {}: {e}", path.display()))?;
+                // Pictures already there before this upload; an ambiguous attempt
+                // that went through shows up as a new one.
+                let known = picture_ids(&super::list_pictures(token, product_id).await?);
+                let uploaded = crate::write_queue::idempotent(
+                    app,
+                    "depop",
+                    "upload_picture",
+                    Some(product_id),
+                    |_| {
+                        let known = &known;
+                        async move { Ok(new_picture(&super::list_pictures(token, product_id).await?, known)) }
+                    },
+                    |_| super::upload_picture(token, bytes.clone(), content_type_for(path)),
+                )
+                .await?;
+                ids.push(id_of(&uploaded).ok_or("Depop didn't return a picture id")?);
+            }
+            let mut payload = super::editable_payload(&current);
+            payload["photos"] = ids
+                .iter()
+                .map(|id| if objects { json!({ "id": id }) } else { json!(id) })
+                .collect();
+            crate::write_queue::resend(app, "depop", "update_listing", Some(product_id), |_| {
+                super::put_product(token, product_id, &payload)
+            })
+            .await?;
+            Ok(ids)
+        },
+    )
+    .await;
+
+    match saved {
+        Ok(Checked::Applied(ids)) => {
+            result.uploaded = ids;
+            result.outcome = RepairOutcome::Repaired;
+        }
+        Ok(Checked::Conflict(conflict)) => {
+            result.outcome = RepairOutcome::Conflict;
+            result.conflict = Some(conflict);
+        }
+        Err(e) => {
+            result.outcome = RepairOutcome::Failed;
+            result.error = Some(e);
+        }
+    }
+    result
+}
+
+/// Save whatever still resolves for a listing into `dest_dir` as
+/// `<product_id>-<n>.<ext>`, so an unrepairable listing gets local originals.
+#[tauri::command]
+pub async fn download_listing_photos(
+    product_id: String,
+    urls: Vec<String>,
+    dest_dir: String,
+) -> Result<Vec<String>, String> {
+    let dir = PathBuf::from(&dest_dir);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("create {dest_dir}: {e}"))?;
+    let client = super::client()?;
+    let mut saved = Vec::with_capacity(urls.len());
+
+    for (i, url) in urls.iter().enumerate() {
+        let resp = client.get(url).send().await.map_err(|e| format!("request: {e}"))?;
+        if !resp.status().is_success() {
+            return Err(format!("{url} returned {}", resp.status().as_u16()));
+        }
+        let ext = match resp.headers().get(reqwest::header::CONTENT_TYPE).and_then(|v| v.to_str().ok()) {
+            Some(ct) if ct.starts_with("image/png") => "png",
+            Some(ct) if ct.starts_with("image/webp") => "webp",
+            _ => "jpg",
+        };
+        let bytes = resp.bytes().await.map_err(|e| format!("body: {e}"))?;
+        let path = dir.join(format!("{product_id}-{}.{ext}", i + 1));
+        std::fs::write(&path, &bytes).map_err(|e| format!("write {}: {e}", path.display()))?;
+        saved.push(path.to_string_lossy().into_owned());
+    }
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconcile_finds_the_upload_an_ambiguous_attempt_made() {
+        let before = json!([{ "id": 1 }, { "id": "2" }]);
+        let known = picture_ids(&before);
+        assert_eq!(new_picture(&before, &known), None);
+
+        let after = json!({ "pictures": [{ "id": 1 }, { "id": "2" }, { "id": 3, "url": "https://x/3.jpg" }] });
+        assert_eq!(new_picture(&after, &known), Some(json!({ "id": 3, "url": "https://x/3.jpg" })));
+
+        // Two uploads landed (one from elsewhere); the newest one is taken.
+        let after = json!([{ "id": 1 }, { "id": 5 }, { "id": 6 }]);
+        assert_eq!(new_picture(&after, &known), Some(json!({ "id": 6 })));
+    }
+
+    #[test]
+    fn unexpected_listing_shapes_find_nothing() {
+        let known = HashSet::new();
+        assert_eq!(new_picture(&Value::Null, &known), None);
+        assert_eq!(new_picture(&json!({ "pictures": "none" }), &known), None);
+        assert_eq!(new_picture(&json!([{ "url": "https://x/1.jpg" }]), &known), None);
+    }
+}
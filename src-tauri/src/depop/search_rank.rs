@@ -0,0 +1,281 @@
+// Search rank: where one of our listings shows up when a buyer searches a
+// term. `check_search_rank` runs each query against Depop's public search
+// (no token) and scans result pages until the listing turns up or the depth
+// runs out. Every result is kept as a snapshot, so `get_rank_history` can
+// chart movement after a relist or title change.
+//
+// Listings can be tracked for a weekly re-check by the background job.
+// Searching is paced through the host rate limiter plus a fixed gap, and the
+// pages read per day are capped across manual and scheduled checks. A search
+// response without a recognisable product list goes to the schema-drift
+// report instead of reading as "not found".
+
+use super::sync::{next_page, product_id, urlencode};
+use super::API_URL;
+use crate::rate_limit::{Priority, RateLimitState};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+const TRACKED_STORE: &str = "depop_rank_tracking";
+const HISTORY_STORE: &str = "depop_rank_history";
+const ITEMS_PER_PAGE: u32 = 24;
+const DEFAULT_DEPTH: u32 = 5;
+const MAX_DEPTH: u32 = 10;
+/// Search pages read per day, manual and scheduled checks together.
+const DAILY_PAGE_BUDGET: u32 = 200;
+/// Gap between search pages, on top of the host limiter.
+const SEARCH_PACING: Duration = Duration::from_secs(3);
+/// Snapshots kept per listing.
+const MAX_SNAPSHOTS: usize = 500;
+const RECHECK_EVERY_SECS: u64 = 7 * 24 * 60 * 60;
+/// How often the background job looks for tracked listings that are due.
+const CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TrackedListing {
+    pub account: String,
+    pub queries: Vec<String>,
+    #[serde(default)]
+    pub depth: Option<u32>,
+    #[serde(default)]
+    pub last_checked: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RankSnapshot {
+    pub at: u64,
+    pub query: String,
+    /// 1-based position in the results; None = not within `depth` pages.
+    pub rank: Option<u32>,
+    pub depth: u32,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Budget {
+    day: String,
+    pages: u32,
+}
+
+pub struct SearchRankState {
+    tracked: Mutex<BTreeMap<String, TrackedListing>>,
+    history: Mutex<BTreeMap<String, Vec<RankSnapshot>>>,
+    budget: Mutex<Budget>,
+}
+
+impl SearchRankState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        SearchRankState {
+            tracked: Mutex::new(crate::store::load(app, TRACKED_STORE)),
+            history: Mutex::new(crate::store::load(app, HISTORY_STORE)),
+            budget: Mutex::new(Budget::default()),
+        }
+    }
+
+    /// Take one page from today's budget.
+    fn spend_page(&self) -> Result<(), String> {
+        let mut budget = self.budget.lock().unwrap();
+        let today = crate::fees::today();
+        if budget.day != today {
+            *budget = Budget { day: today, pages: 0 };
+        }
+        if budget.pages >= DAILY_PAGE_BUDGET {
+            return Err(format!("Daily search budget of {DAILY_PAGE_BUDGET} pages used up; try again tomorrow"));
+        }
+        budget.pages += 1;
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+pub struct RankResult {
+    query: String,
+    rank: Option<u32>,
+    pages_scanned: u32,
+    error: Option<String>,
+    /// Stopped by the daily page budget.
+    #[serde(skip)]
+    out_of_budget: bool,
+}
+
+impl RankResult {
+    fn new(query: &str, rank: Option<u32>, pages_scanned: u32, error: Option<String>) -> Self {
+        RankResult { query: query.to_string(), rank, pages_scanned, error, out_of_budget: false }
+    }
+}
+
+async fn search_page(app: &tauri::AppHandle, query: &str, next: Option<&(&'static str, String)>) -> Result<Value, String> {
+    let mut url = format!("{API_URL}/search/products/?what={}&items_per_page={ITEMS_PER_PAGE}", urlencode(query));
+    if let Some((key, value)) = next {
+        url.push_str(&format!("&{key}={}", urlencode(value)));
+    }
+    app.state::<RateLimitState>().acquire(&url, Priority::Background).await;
+    let started = Instant::now();
+    let resp = super::client()?.get(url).send().await.map_err(|e| format!("request: {e}"));
+    super::timed(started, resp).await
+}
+
+/// Scan up to `depth` pages of `query` for `product`.
+async fn rank_in(app: &tauri::AppHandle, product: &str, query: &str, depth: u32) -> RankResult {
+    let state = app.state::<SearchRankState>();
+    let mut next = None;
+    let mut position = 0;
+    for page in 1..=depth {
+        if page > 1 {
+            tokio::time::sleep(SEARCH_PACING).await;
+        }
+        if let Err(e) = state.spend_page() {
+            return RankResult { out_of_budget: true, ..RankResult::new(query, None, page - 1, Some(e)) };
+        }
+        let body = match search_page(app, query, next.as_ref()).await {
+            Ok(body) => body,
+            Err(e) => return RankResult::new(query, None, page - 1, Some(e)),
+        };
+        let Some(products) = body.get("products").or_else(|| body.get("objects")).and_then(Value::as_array) else {
+            super::attributes::report_format_drift(app, "search", 200, &body.to_string());
+            let error = "Depop's search response wasn't recognised".to_string();
+            return RankResult::new(query, None, page, Some(error));
+        };
+        for p in products {
+            position += 1;
+            if product_id(p).as_deref() == Some(product) {
+                return RankResult::new(query, Some(position), page, None);
+            }
+        }
+        next = next_page(&body);
+        if products.is_empty() || next.is_none() {
+            return RankResult::new(query, None, page, None);
+        }
+    }
+    RankResult::new(query, None, depth, None)
+}
+
+/// Check each query and store the results that completed.
+async fn check(app: &tauri::AppHandle, product: &str, queries: &[String], depth: u32) -> Result<Vec<RankResult>, String> {
+    let mut results = Vec::with_capacity(queries.len());
+    let mut snapshots = Vec::new();
+    for query in queries.iter().map(|q| q.trim()).filter(|q| !q.is_empty()) {
+        let result = rank_in(app, product, query, depth).await;
+        if result.error.is_none() {
+            snapshots.push(RankSnapshot { at: crate::unix_now(), query: query.to_string(), rank: result.rank, depth });
+        }
+        let out_of_budget = result.out_of_budget;
+        results.push(result);
+        if out_of_budget {
+            break;
+        }
+    }
+
+    let state = app.state::<SearchRankState>();
+    let mut history = state.history.lock().unwrap();
+    let entry = history.entry(product.to_string()).or_default();
+    entry.extend(snapshots);
+    if entry.len() > MAX_SNAPSHOTS {
+        entry.drain(..entry.len() - MAX_SNAPSHOTS);
+    }
+    crate::store::save(app, HISTORY_STORE, &*history)?;
+    Ok(results)
+}
+
+fn depth_of(depth: Option<u32>) -> u32 {
+    depth.unwrap_or(DEFAULT_DEPTH).clamp(1, MAX_DEPTH)
+}
+
+#[tauri::command]
+pub async fn check_search_rank(
+    app: tauri::AppHandle,
+    account: String,
+    product_id: String,
+    queries: Vec<String>,
+    depth: Option<u32>,
+) -> Result<Vec<RankResult>, String> {
+    crate::accounts::remember(&app, &account);
+    check(&app, &product_id, &queries, depth_of(depth)).await
+}
+
+#[tauri::command]
+pub fn get_rank_history(state: tauri::State<'_, SearchRankState>, product_id: String) -> Vec<RankSnapshot> {
+    state.history.lock().unwrap().get(&product_id).cloned().unwrap_or_default()
+}
+
+/// Re-check `queries` for the listing weekly. Empty `queries` stops tracking.
+#[tauri::command]
+pub fn track_search_rank(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SearchRankState>,
+    account: String,
+    product_id: String,
+    queries: Vec<String>,
+    depth: Option<u32>,
+) -> Result<(), String> {
+    let queries: Vec<String> = queries.iter().map(|q| q.trim().to_string()).filter(|q| !q.is_empty()).collect();
+    let mut tracked = state.tracked.lock().unwrap();
+    if queries.is_empty() {
+        tracked.remove(&product_id);
+    } else {
+        let last_checked = tracked.get(&product_id).and_then(|t| t.last_checked);
+        tracked.insert(product_id, TrackedListing { account, queries, depth: depth.map(|d| depth_of(Some(d))), last_checked });
+    }
+    crate::store::save(&app, TRACKED_STORE, &*tracked)
+}
+
+#[tauri::command]
+pub fn get_tracked_search_ranks(state: tauri::State<'_, SearchRankState>) -> BTreeMap<String, TrackedListing> {
+    state.tracked.lock().unwrap().clone()
+}
+
+/// Weekly re-check of tracked listings. Stops for the day when the page
+/// budget runs out; the rest stay due and go next time.
+pub fn start_rank_job(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            if crate::outage::is_degraded("depop") {
+                continue;
+            }
+            let now = crate::unix_now();
+            let due: Vec<(String, TrackedListing)> = app
+                .state::<SearchRankState>()
+                .tracked
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(_, t)| t.last_checked.map_or(true, |c| now.saturating_sub(c) >= RECHECK_EVERY_SECS))
+                .map(|(id, t)| (id.clone(), t.clone()))
+                .collect();
+            for (id, listing) in due {
+                let results = match check(&app, &id, &listing.queries, depth_of(listing.depth)).await {
+                    Ok(results) => results,
+                    Err(e) => {
+                        log::warn!("search rank check for {id} failed: {e}");
+                        continue;
+                    }
+                };
+                let out_of_budget = results.iter().any(|r| r.out_of_budget);
+                if !out_of_budget {
+                    let state = app.state::<SearchRankState>();
+                    let mut tracked = state.tracked.lock().unwrap();
+                    if let Some(t) = tracked.get_mut(&id) {
+                        t.last_checked = Some(now);
+                    }
+                    if let Err(e) = crate::store::save(&app, TRACKED_STORE, &*tracked) {
+                        log::warn!("saving search rank tracking: {e}");
+                    }
+                }
+                crate::audit::record(
+                    &app,
+                    "check_search_rank",
+                    Some(&listing.account),
+                    false,
+                    json!({ "product_id": id, "queries": listing.queries.len(), "scheduled": true }),
+                );
+                if out_of_budget {
+                    break;
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,500 @@
+// Listing sync: walks the account's products newest-first and reports what
+// changed since the last sync.
+//
+// Incremental mode stops at the first page that reaches the stored high-water
+// mark (latest updated time / id seen), so an unchanged shop costs one
+// request. Deletions can't be seen that way, so every FULL_SYNC_EVERY_SECS (or
+// with `force_full`) the whole list is walked and anything missing is reported
+// as removed. Both modes go through the same `merge`, so the changeset has the
+// same shape either way.
+//
+// The snapshot kept here is a per-listing fingerprint, not the listings
+// themselves — the frontend merges `changed` into its own copy.
+
+use super::API_URL;
+use crate::request_context::{Finished, RequestContext};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::Emitter;
+
+const STORE_NAME: &str = "depop_listing_sync";
+const PAGE_SIZE: u32 = 100;
+/// Safety stop for a runaway cursor.
+const MAX_PAGES: u32 = 500;
+const FULL_SYNC_EVERY_SECS: u64 = 3 * 24 * 60 * 60;
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HighWater {
+    updated_at: String,
+    id: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SnapshotEntry {
+    updated_at: Option<String>,
+    fingerprint: String,
+    /// Depop's status for the listing when it was last seen, lowercased.
+    #[serde(default)]
+    status: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct AccountSnapshot {
+    #[serde(default)]
+    high_water: Option<HighWater>,
+    #[serde(default)]
+    last_full_sync: Option<u64>,
+    #[serde(default)]
+    listings: BTreeMap<String, SnapshotEntry>,
+}
+
+#[derive(Default)]
+pub struct ListingSyncState {
+    accounts: Mutex<HashMap<String, AccountSnapshot>>,
+}
+
+impl ListingSyncState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        ListingSyncState { accounts: Mutex::new(crate::store::load(app, STORE_NAME)) }
+    }
+
+    /// One fully synced account holding `listings` as (id, status), for
+    /// tests of code that reads the snapshots.
+    #[cfg(test)]
+    pub fn synced(listings: &[(&str, Option<&str>)]) -> Self {
+        let listings = listings
+            .iter()
+            .map(|(id, status)| {
+                let entry =
+                    SnapshotEntry { updated_at: None, fingerprint: String::new(), status: status.map(str::to_string) };
+                (id.to_string(), entry)
+            })
+            .collect();
+        let snapshot = AccountSnapshot { high_water: None, last_full_sync: Some(0), listings };
+        ListingSyncState { accounts: Mutex::new(HashMap::from([("account".to_string(), snapshot)])) }
+    }
+
+    /// Ids of the account's listings as of its last sync.
+    pub fn listing_ids(&self, account: &str) -> Vec<String> {
+        self.accounts.lock().unwrap().get(account).map(|a| a.listings.keys().cloned().collect()).unwrap_or_default()
+    }
+
+    /// What the syncs last saw of listing `id`, across all accounts.
+    pub fn last_seen(&self, id: &str) -> LastSeen {
+        let accounts = self.accounts.lock().unwrap();
+        if let Some(entry) = accounts.values().find_map(|a| a.listings.get(id)) {
+            return LastSeen::Listed { status: entry.status.clone() };
+        }
+        // Only a full sync of every account proves a listing is gone.
+        if !accounts.is_empty() && accounts.values().all(|a| a.last_full_sync.is_some()) {
+            LastSeen::Gone
+        } else {
+            LastSeen::Unknown
+        }
+    }
+}
+
+pub enum LastSeen {
+    Listed { status: Option<String> },
+    /// Missing from the last full sync.
+    Gone,
+    Unknown,
+}
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMode {
+    Full,
+    Incremental,
+}
+
+#[derive(Serialize)]
+pub struct SyncReport {
+    mode: SyncMode,
+    pages: u32,
+    fetched: usize,
+    added: Vec<String>,
+    updated: Vec<String>,
+    /// Only populated by full syncs.
+    removed: Vec<String>,
+    unchanged: usize,
+    /// Full records for `added` + `updated`.
+    changed: Vec<Value>,
+    high_water: Option<HighWater>,
+}
+
+#[derive(Clone, Serialize)]
+struct SyncProgress<'a> {
+    account: &'a str,
+    account_meta: Option<crate::accounts::AccountMetadata>,
+    mode: SyncMode,
+    pages: u32,
+    fetched: usize,
+}
+
+pub fn product_id(product: &Value) -> Option<String> {
+    match product.get("id")? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn listing_status(product: &Value) -> Option<String> {
+    if product.get("sold").and_then(Value::as_bool) == Some(true) {
+        return Some("sold".to_string());
+    }
+    product.get("status").and_then(Value::as_str).map(str::to_lowercase)
+}
+
+/// Compare updated markers: numerically when both are numbers (epoch
+/// timestamps), otherwise as strings (ISO 8601 sorts lexically).
+fn compare_marker(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
+fn fingerprint(product: &Value) -> String {
+    let canonical = serde_json::to_string(product).unwrap_or_default();
+    hex::encode(&Sha256::digest(canonical.as_bytes())[..8])
+}
+
+/// Whether `product` is at or below the high-water mark, i.e. the walk has
+/// reached listings the previous sync already saw.
+fn reached(product: &Value, mark: &HighWater) -> bool {
+    match super::updated_marker(product) {
+        Some(updated) => match compare_marker(&updated, &mark.updated_at) {
+            Ordering::Less => true,
+            Ordering::Equal => product_id(product).as_deref() == Some(mark.id.as_str()),
+            Ordering::Greater => false,
+        },
+        None => product_id(product).as_deref() == Some(mark.id.as_str()),
+    }
+}
+
+/// Merge fetched products into the snapshot and build the changeset. In full
+/// mode listings absent from `fetched` are dropped and reported as removed.
+fn merge(snapshot: &mut AccountSnapshot, fetched: Vec<Value>, mode: SyncMode) -> SyncReport {
+    let mut report = SyncReport {
+        mode,
+        pages: 0,
+        fetched: fetched.len(),
+        added: Vec::new(),
+        updated: Vec::new(),
+        removed: Vec::new(),
+        unchanged: 0,
+        changed: Vec::new(),
+        high_water: None,
+    };
+    let mut seen = std::collections::HashSet::new();
+
+    for product in fetched {
+        let Some(id) = product_id(&product) else {
+            continue;
+        };
+        if !seen.insert(id.clone()) {
+            continue;
+        }
+        let entry = SnapshotEntry {
+            updated_at: super::updated_marker(&product),
+            fingerprint: fingerprint(&product),
+            status: listing_status(&product),
+        };
+        match snapshot.listings.get(&id) {
+            Some(old) if old.fingerprint == entry.fingerprint => report.unchanged += 1,
+            Some(_) => {
+                report.updated.push(id.clone());
+                report.changed.push(product);
+            }
+            None => {
+                report.added.push(id.clone());
+                report.changed.push(product);
+            }
+        }
+        snapshot.listings.insert(id, entry);
+    }
+
+    if mode == SyncMode::Full {
+        report.removed = snapshot.listings.keys().filter(|id| !seen.contains(*id)).cloned().collect();
+        for id in &report.removed {
+            snapshot.listings.remove(id);
+        }
+        snapshot.last_full_sync = Some(crate::unix_now());
+    }
+
+    snapshot.high_water = snapshot
+        .listings
+        .iter()
+        .filter_map(|(id, e)| Some(HighWater { updated_at: e.updated_at.clone()?, id: id.clone() }))
+        .max_by(|a, b| compare_marker(&a.updated_at, &b.updated_at).then_with(|| a.id.cmp(&b.id)));
+    report.high_water = snapshot.high_water.clone();
+    report
+}
+
+/// Next-page query parameter from a page's `meta`, whichever pagination
+/// style the endpoint answered with. None = last page.
+pub fn next_page(body: &Value) -> Option<(&'static str, String)> {
+    let meta = body.get("meta")?;
+    if meta.get("end").and_then(Value::as_bool) == Some(true) {
+        return None;
+    }
+    let as_string = |v: &Value| match v {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    };
+    if let Some(c) = meta.get("cursor").or_else(|| meta.get("next_cursor")).and_then(as_string) {
+        return Some(("cursor", c));
+    }
+    meta.get("last_offset_id").and_then(as_string).map(|o| ("offset_id", o))
+}
+
+/// Walk the listing pages newest-first until the last page or, given the
+/// high-water `mark`, the first listing the previous sync already saw.
+/// `progress` gets (pages, products) after each page.
+async fn walk<F, Fut>(
+    mark: Option<&HighWater>,
+    mut fetch: F,
+    mut progress: impl FnMut(u32, usize),
+) -> Result<(Vec<Value>, u32), String>
+where
+    F: FnMut(Option<(&'static str, String)>) -> Fut,
+    Fut: Future<Output = Result<Value, String>>,
+{
+    let mut fetched = Vec::new();
+    let mut next = None;
+    let mut pages = 0;
+    loop {
+        let body = fetch(next.take()).await?;
+        pages += 1;
+        let products = body
+            .get("products")
+            .or_else(|| body.get("objects"))
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        let page_len = products.len();
+        let mut caught_up = false;
+        for product in products {
+            if mark.is_some_and(|m| reached(&product, m)) {
+                caught_up = true;
+                break;
+            }
+            fetched.push(product);
+        }
+        progress(pages, fetched.len());
+        next = next_page(&body);
+        if caught_up || page_len == 0 || next.is_none() {
+            return Ok((fetched, pages));
+        }
+        if pages >= MAX_PAGES {
+            return Err(format!("Stopped after {MAX_PAGES} pages — Depop kept returning more"));
+        }
+    }
+}
+
+async fn fetch_page(token: &str, next: Option<&(&'static str, String)>) -> Result<Value, String> {
+    let mut url = format!("{API_URL}/products/me/?limit={PAGE_SIZE}");
+    if let Some((key, value)) = next {
+        url.push_str(&format!("&{key}={}", urlencode(value)));
+    }
+    let started = Instant::now();
+    let resp = super::client()?
+        .get(url)
+        .header("Authorization", super::bearer(token)?)
+        .send()
+        .await
+        .map_err(|e| format!("request: {e}"));
+    super::timed(started, resp).await
+}
+
+pub fn urlencode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// Sync the account's Depop listings. Incremental unless `force_full`, there
+/// is no snapshot yet, or the last full sync is older than three days.
+/// Emits "depop-sync-progress" after each page. Cancellable (see
+/// request_context); a cancelled sync leaves the snapshot untouched.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn depop_fetch_listings(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    state: tauri::State<'_, ListingSyncState>,
+    account: String,
+    token: String,
+    force_full: Option<bool>,
+    invocation_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<Finished<SyncReport>, String> {
+    let ctx = RequestContext::begin(&app, &window, "depop_fetch_listings", invocation_id, timeout_ms)?;
+    let result = fetch_listings(&app, &state, &ctx, account, token, force_full).await;
+    ctx.finish(result)
+}
+
+async fn fetch_listings(
+    app: &tauri::AppHandle,
+    state: &ListingSyncState,
+    ctx: &RequestContext,
+    account: String,
+    token: String,
+    force_full: Option<bool>,
+) -> Result<SyncReport, String> {
+    crate::accounts::remember(app, &account);
+    let (mode, mark) = {
+        let accounts = state.accounts.lock().unwrap();
+        let snapshot = accounts.get(&account);
+        let full_due = snapshot
+            .and_then(|s| s.last_full_sync)
+            .map_or(true, |t| crate::unix_now().saturating_sub(t) >= FULL_SYNC_EVERY_SECS);
+        let mark = snapshot.and_then(|s| s.high_water.clone());
+        if force_full.unwrap_or(false) || full_due || mark.is_none() {
+            (SyncMode::Full, None)
+        } else {
+            (SyncMode::Incremental, mark)
+        }
+    };
+    let account_meta = crate::accounts::meta(app, &account);
+
+    let fetch = |next: Option<(&'static str, String)>| {
+        let token = &token;
+        async move { ctx.run(fetch_page(token, next.as_ref())).await? }
+    };
+    let progress = |pages, fetched| {
+        let meta = account_meta.clone();
+        let _ = app.emit("depop-sync-progress", SyncProgress { account: &account, account_meta: meta, mode, pages, fetched });
+    };
+    let (fetched, pages) = walk(mark.as_ref(), fetch, progress).await?;
+
+    let mut accounts = state.accounts.lock().unwrap();
+    let snapshot = accounts.entry(account).or_default();
+    let mut report = merge(snapshot, fetched, mode);
+    report.pages = pages;
+    crate::store::save(app, STORE_NAME, &*accounts)?;
+    drop(accounts);
+    let touched: Vec<&str> =
+        report.added.iter().chain(&report.updated).chain(&report.removed).map(String::as_str).collect();
+    crate::crosslist::notify(app, "depop", &touched);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn product(id: &str, updated: u32, title: &str) -> Value {
+        json!({ "id": id, "updated_at": updated.to_string(), "title": title })
+    }
+
+    /// `products` served newest-first, two to a page, cursored by page number.
+    fn pages(mut products: Vec<Value>) -> Vec<Value> {
+        products.sort_by(|a, b| compare_marker(b["updated_at"].as_str().unwrap(), a["updated_at"].as_str().unwrap()));
+        let chunks: Vec<_> = products.chunks(2).map(<[Value]>::to_vec).collect();
+        let last = chunks.len() - 1;
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(i, products)| {
+                let meta = if i == last { json!({ "end": true }) } else { json!({ "cursor": (i + 1).to_string() }) };
+                json!({ "products": products, "meta": meta })
+            })
+            .collect()
+    }
+
+    async fn sync(snapshot: &mut AccountSnapshot, shop: &[Value], mode: SyncMode) -> SyncReport {
+        let mark = (mode == SyncMode::Incremental).then(|| snapshot.high_water.clone().unwrap());
+        let fetch = |next: Option<(&'static str, String)>| {
+            let page = next.map_or(0, |(_, c)| c.parse::<usize>().unwrap());
+            let body = shop[page].clone();
+            async move { Ok(body) }
+        };
+        let (fetched, pages) = walk(mark.as_ref(), fetch, |_, _| {}).await.unwrap();
+        let mut report = merge(snapshot, fetched, mode);
+        report.pages = pages;
+        report
+    }
+
+    fn before() -> Vec<Value> {
+        (1..=5).map(|n| product(&format!("p{n}"), n, "old")).collect()
+    }
+
+    /// p1 deleted, p3 edited, p6 listed.
+    fn after() -> Vec<Value> {
+        vec![
+            product("p2", 2, "old"),
+            product("p3", 7, "new"),
+            product("p4", 4, "old"),
+            product("p5", 5, "old"),
+            product("p6", 8, "new"),
+        ]
+    }
+
+    async fn synced() -> AccountSnapshot {
+        let mut snapshot = AccountSnapshot::default();
+        let report = sync(&mut snapshot, &pages(before()), SyncMode::Full).await;
+        assert_eq!(report.added.len(), 5);
+        assert_eq!(report.pages, 3);
+        snapshot
+    }
+
+    #[tokio::test]
+    async fn incremental_and_full_report_the_same_changes() {
+        let shop = pages(after());
+        let mut incremental = synced().await;
+        let mut full = synced().await;
+        let inc = sync(&mut incremental, &shop, SyncMode::Incremental).await;
+        let all = sync(&mut full, &shop, SyncMode::Full).await;
+
+        assert_eq!(inc.added, vec!["p6"]);
+        assert_eq!(inc.updated, vec!["p3"]);
+        assert_eq!((&inc.added, &inc.updated, &inc.changed), (&all.added, &all.updated, &all.changed));
+        assert!(inc.high_water == all.high_water);
+        assert_eq!(inc.high_water.as_ref().map(|m| m.id.as_str()), Some("p6"));
+        // Only a full walk can see the deletion, and only it reads every page.
+        assert!(inc.removed.is_empty());
+        assert_eq!(all.removed, vec!["p1"]);
+        assert_eq!((inc.pages, all.pages), (2, 3));
+        // Apart from the deletion, both snapshots end up the same.
+        incremental.listings.remove("p1");
+        let fingerprints =
+            |s: &AccountSnapshot| s.listings.iter().map(|(id, e)| (id.clone(), e.fingerprint.clone())).collect::<Vec<_>>();
+        assert_eq!(fingerprints(&incremental), fingerprints(&full));
+    }
+
+    #[tokio::test]
+    async fn unchanged_shop_costs_one_page() {
+        let mut snapshot = synced().await;
+        let report = sync(&mut snapshot, &pages(before()), SyncMode::Incremental).await;
+        assert_eq!((report.pages, report.fetched), (1, 0));
+        assert!(report.added.is_empty() && report.updated.is_empty() && report.changed.is_empty());
+
+        let mut snapshot = synced().await;
+        let report = sync(&mut snapshot, &pages(before()), SyncMode::Full).await;
+        assert_eq!((report.unchanged, report.changed.len()), (5, 0));
+    }
+
+    #[test]
+    fn next_page_reads_cursor_and_offset_styles() {
+        assert_eq!(next_page(&json!({ "meta": { "cursor": "abc" } })), Some(("cursor", "abc".to_string())));
+        assert_eq!(next_page(&json!({ "meta": { "next_cursor": 7 } })), Some(("cursor", "7".to_string())));
+        assert_eq!(next_page(&json!({ "meta": { "last_offset_id": "p9" } })), Some(("offset_id", "p9".to_string())));
+        assert_eq!(next_page(&json!({ "meta": { "cursor": "abc", "end": true } })), None);
+        assert_eq!(next_page(&json!({ "meta": { "cursor": "" } })), None);
+        assert_eq!(next_page(&json!({ "products": [] })), None);
+    }
+}
@@ -0,0 +1,65 @@
+// Listing video upload. The clip is checked against Depop's video limits
+// first (run `transcode_video` with the `depop` preset for clips that don't
+// fit), then sent to the media endpoint in chunks so the UI can show upload
+// progress. The returned id goes in the listing's `videos` on create/update.
+
+use crate::marketplace_limits::LimitsState;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use tauri::Emitter;
+
+/// Bytes handed to the connection per progress event.
+const CHUNK_BYTES: usize = 256 * 1024;
+
+#[derive(Serialize)]
+pub struct UploadedVideo {
+    id: String,
+    size_bytes: u64,
+    duration_seconds: Option<f64>,
+    response: Value,
+}
+
+#[tauri::command]
+pub async fn depop_upload_video(
+    app: tauri::AppHandle,
+    limits: tauri::State<'_, LimitsState>,
+    account: String,
+    token: String,
+    path: String,
+) -> Result<UploadedVideo, String> {
+    let report = crate::video::report(Path::new(&path), "depop", &limits)?;
+    if !report.ok {
+        return Err(format!("Video doesn't meet Depop's limits: {}", report.problems.join("; ")));
+    }
+    let bytes = std::fs::read(&path).map_err(|e| format!("{path}: {e}"))?;
+    let total = bytes.len() as u64;
+    let content_type = if report.info.container == "mov" { "video/quicktime" } else { "video/mp4" };
+
+    let chunks: Vec<Vec<u8>> = bytes.chunks(CHUNK_BYTES).map(<[u8]>::to_vec).collect();
+    let progress_app = app.clone();
+    let progress_path = path.clone();
+    let mut sent = 0u64;
+    let stream = futures_util::stream::iter(chunks.into_iter().map(move |chunk| {
+        sent += chunk.len() as u64;
+        let _ = progress_app.emit("video-upload-progress", json!({ "path": progress_path, "sent": sent, "total": total }));
+        Ok::<_, std::io::Error>(chunk)
+    }));
+
+    let result = super::upload_video(&token, reqwest::Body::wrap_stream(stream), content_type, total).await;
+    let id = result.as_ref().ok().and_then(|r| match r.get("id") {
+        Some(Value::String(s)) => Some(s.clone()),
+        Some(Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    });
+    crate::audit::record(
+        &app,
+        "depop_upload_video",
+        Some(&account),
+        false,
+        json!({ "path": path, "bytes": total, "video_id": id, "error": result.as_ref().err() }),
+    );
+    let response = result?;
+    let id = id.ok_or("Depop accepted the video but returned no id")?;
+    Ok(UploadedVideo { id, size_bytes: total, duration_seconds: report.info.duration_seconds, response })
+}
@@ -0,0 +1,442 @@
+// ── eBay listing import ────────────────────────────────────────────────────
+// Migration path for sellers arriving from eBay: pages through the account's
+// Sell Inventory API items, keeps the ones with a published offer (i.e. live
+// listings), and maps each into the canonical inventory shape the frontend
+// inserts: plain-text description, price, quantity, brand/size/colour from the
+// item specifics, other specifics into matching custom fields, and photos
+// downloaded locally.
+//
+// Big sellers have thousands of listings, so the real run emits each page as
+// `ebay-import-page` (the frontend creates rows as they arrive) and
+// checkpoints the page offset; calling again resumes after the last finished
+// page. A dry run maps everything and downloads nothing.
+
+use crate::request_context::{Finished, RequestContext};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::Emitter;
+
+pub const API_URL: &str = "https://api.ebay.com";
+const STORE_NAME: &str = "ebay_import";
+const PAGE_SIZE: u32 = 100;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct Checkpoint {
+    /// Offset of the next page to import.
+    offset: u32,
+    total: Option<u64>,
+    imported: usize,
+    started_at: u64,
+}
+
+pub struct EbayImportState {
+    checkpoints: Mutex<HashMap<String, Checkpoint>>,
+}
+
+impl EbayImportState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        EbayImportState { checkpoints: Mutex::new(crate::store::load(app, STORE_NAME)) }
+    }
+}
+
+/// An inventory row the import should not duplicate.
+#[derive(Deserialize)]
+pub struct ExistingItem {
+    id: String,
+    title: String,
+    #[serde(default)]
+    sku: Option<String>,
+    #[serde(default)]
+    ebay_listing_id: Option<String>,
+    /// Trashed items don't count as duplicates.
+    #[serde(default)]
+    deleted_at: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct MarketplaceLink {
+    marketplace: &'static str,
+    external_id: String,
+    url: String,
+    sku: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ImportedItem {
+    pub sku: String,
+    pub title: String,
+    pub description: String,
+    pub price: Option<f64>,
+    pub currency: Option<String>,
+    pub quantity: u64,
+    pub condition: Option<String>,
+    pub brand: Option<String>,
+    pub size: Option<String>,
+    pub color: Option<String>,
+    /// Custom field id -> value.
+    pub custom_fields: Map<String, Value>,
+    /// Local paths after a real run; eBay URLs in a dry run.
+    pub photos: Vec<String>,
+    pub link: MarketplaceLink,
+    /// Existing inventory id this listing matches; link it instead of
+    /// creating a new item.
+    pub duplicate_of: Option<String>,
+    /// Problems that didn't stop the item (e.g. a photo that wouldn't download).
+    pub warnings: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct ImportReport {
+    dry_run: bool,
+    resumed_from: u32,
+    pages: u32,
+    scanned: usize,
+    /// Inventory items without a published offer.
+    skipped_inactive: usize,
+    items: Vec<ImportedItem>,
+    duplicates: usize,
+    /// Item specific name -> how many listings carried it with nowhere to go.
+    unmappable_specifics: BTreeMap<String, usize>,
+    complete: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct ImportProgress<'a> {
+    account: &'a str,
+    processed: u32,
+    total: Option<u64>,
+    imported: usize,
+}
+
+fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .gzip(true)
+        .build()
+        .map_err(|e| format!("client build: {e}"))
+}
+
+async fn get(client: &reqwest::Client, token: &str, path: &str) -> Result<Value, String> {
+    let resp = client
+        .get(format!("{API_URL}{path}"))
+        .bearer_auth(token)
+        .header("Accept-Language", "en-US")
+        .send()
+        .await
+        .map_err(|e| format!("request: {e}"))?;
+    let status = resp.status();
+    let body = resp.text().await.map_err(|e| format!("body: {e}"))?;
+    if !status.is_success() {
+        let snippet: String = body.chars().take(300).collect();
+        return Err(format!("eBay returned {}: {snippet}", status.as_u16()));
+    }
+    serde_json::from_str(&body).map_err(|e| format!("invalid JSON from eBay: {e}"))
+}
+
+/// Listing description HTML as plain text: block tags become line breaks,
+/// everything else is stripped.
+pub fn html_to_text(html: &str) -> String {
+    let breaks = regex::Regex::new(r"(?i)<\s*(br|/p|/div|/li|/h[1-6]|/tr)\b[^>]*>").expect("valid regex");
+    let bullets = regex::Regex::new(r"(?i)<\s*li\b[^>]*>").expect("valid regex");
+    let tags = regex::Regex::new(r"(?s)<[^>]*>").expect("valid regex");
+    let text = breaks.replace_all(html, "\n");
+    let text = bullets.replace_all(&text, "• ");
+    let text = tags.replace_all(&text, "");
+    let text = crate::links::decode_entities(&text.replace("&nbsp;", " "));
+
+    let mut out = String::new();
+    let mut blank = 0;
+    for line in text.lines().map(|l| l.split_whitespace().collect::<Vec<_>>().join(" ")) {
+        if line.is_empty() {
+            blank += 1;
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(if blank > 0 { "\n\n" } else { "\n" });
+        }
+        out.push_str(&line);
+        blank = 0;
+    }
+    out
+}
+
+fn normalise_title(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn find_duplicate(existing: &[ExistingItem], sku: &str, listing_id: &str, title: &str) -> Option<String> {
+    let title = normalise_title(title);
+    existing
+        .iter()
+        .filter(|e| e.deleted_at.is_none())
+        .find(|e| {
+            e.ebay_listing_id.as_deref() == Some(listing_id)
+                || e.sku.as_deref() == Some(sku)
+                || (!title.is_empty() && normalise_title(&e.title) == title)
+        })
+        .map(|e| e.id.clone())
+}
+
+/// The live offer for an inventory item, if it has one.
+async fn published_offer(client: &reqwest::Client, token: &str, sku: &str) -> Result<Option<Value>, String> {
+    let body = get(client, token, &format!("/sell/inventory/v1/offer?sku={}", crate::depop::sync::urlencode(sku))).await?;
+    Ok(body
+        .get("offers")
+        .and_then(Value::as_array)
+        .and_then(|offers| offers.iter().find(|o| o.get("status").and_then(Value::as_str) == Some("PUBLISHED")))
+        .cloned())
+}
+
+/// One live listing, by its inventory SKU, mapped as the import maps it;
+/// photos stay as eBay URLs.
+pub async fn fetch_listing(
+    token: &str,
+    sku: &str,
+    fields: &[crate::custom_fields::CustomField],
+) -> Result<ImportedItem, String> {
+    let client = client()?;
+    let item = get(&client, token, &format!("/sell/inventory/v1/inventory_item/{}", crate::depop::sync::urlencode(sku))).await?;
+    let offer = published_offer(&client, token, sku).await?.ok_or_else(|| format!("{sku} has no live eBay listing"))?;
+    map_item(&item, &offer, fields, &mut BTreeMap::new()).ok_or_else(|| format!("{sku} couldn't be read"))
+}
+
+fn map_item(
+    item: &Value,
+    offer: &Value,
+    fields: &[crate::custom_fields::CustomField],
+    unmappable: &mut BTreeMap<String, usize>,
+) -> Option<ImportedItem> {
+    let sku = item.get("sku")?.as_str()?.to_string();
+    let listing_id = offer.pointer("/listing/listingId").and_then(Value::as_str)?.to_string();
+    let product = item.get("product").cloned().unwrap_or(Value::Null);
+    let str_at = |v: &Value, ptr: &str| v.pointer(ptr).and_then(Value::as_str).map(str::to_string);
+
+    let mut brand = None;
+    let mut size = None;
+    let mut color = None;
+    let mut custom_fields = Map::new();
+    if let Some(aspects) = product.get("aspects").and_then(Value::as_object) {
+        for (name, values) in aspects {
+            let value = values
+                .as_array()
+                .map(|v| v.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(", "))
+                .unwrap_or_default();
+            if value.is_empty() {
+                continue;
+            }
+            match name.to_lowercase().as_str() {
+                "brand" => brand = Some(value),
+                "size" | "us shoe size" | "size (men's)" | "size (women's)" => size = size.or(Some(value)),
+                "color" | "colour" => color = Some(value),
+                _ => match fields.iter().find(|f| f.name.eq_ignore_ascii_case(name)) {
+                    Some(field) => {
+                        custom_fields.insert(field.id.clone(), Value::String(value));
+                    }
+                    None => *unmappable.entry(name.clone()).or_default() += 1,
+                },
+            }
+        }
+    }
+
+    Some(ImportedItem {
+        title: str_at(&product, "/title").unwrap_or_else(|| sku.clone()),
+        description: html_to_text(
+            &str_at(offer, "/listingDescription")
+                .or_else(|| str_at(&product, "/description"))
+                .unwrap_or_default(),
+        ),
+        price: str_at(offer, "/pricingSummary/price/value").and_then(|p| p.parse().ok()),
+        currency: str_at(offer, "/pricingSummary/price/currency"),
+        quantity: item
+            .pointer("/availability/shipToLocationAvailability/quantity")
+            .and_then(Value::as_u64)
+            .unwrap_or(1),
+        condition: str_at(item, "/condition"),
+        brand,
+        size,
+        color,
+        custom_fields,
+        photos: product
+            .get("imageUrls")
+            .and_then(Value::as_array)
+            .map(|urls| urls.iter().filter_map(Value::as_str).map(str::to_string).collect())
+            .unwrap_or_default(),
+        link: MarketplaceLink {
+            marketplace: "ebay",
+            url: format!("https://www.ebay.com/itm/{listing_id}"),
+            external_id: listing_id,
+            sku: sku.clone(),
+        },
+        sku,
+        duplicate_of: None,
+        warnings: Vec::new(),
+    })
+}
+
+/// Import the account's active eBay listings. Resumes an interrupted run
+/// unless `restart`; `existing` is the current inventory, for dedupe.
+/// Photos go to `photo_dir` (default: the app's ebay-import folder).
+/// Cancellable (see request_context); a cancelled import resumes from the
+/// last finished page.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn ebay_import_active_listings(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    state: tauri::State<'_, EbayImportState>,
+    fields: tauri::State<'_, crate::custom_fields::CustomFieldState>,
+    account: String,
+    token: String,
+    existing: Vec<ExistingItem>,
+    photo_dir: Option<String>,
+    dry_run: Option<bool>,
+    restart: Option<bool>,
+    invocation_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<Finished<ImportReport>, String> {
+    let ctx = RequestContext::begin(&app, &window, "ebay_import_active_listings", invocation_id, timeout_ms)?;
+    let result = import_active(&app, &state, &fields, &ctx, account, token, existing, photo_dir, dry_run, restart).await;
+    ctx.finish(result)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn import_active(
+    app: &tauri::AppHandle,
+    state: &EbayImportState,
+    fields: &crate::custom_fields::CustomFieldState,
+    ctx: &RequestContext,
+    account: String,
+    token: String,
+    existing: Vec<ExistingItem>,
+    photo_dir: Option<String>,
+    dry_run: Option<bool>,
+    restart: Option<bool>,
+) -> Result<ImportReport, String> {
+    let dry_run = dry_run.unwrap_or(false);
+    crate::accounts::remember(app, &account);
+    let fields = fields.active_fields();
+    let photo_dir = match photo_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => crate::store::data_path(app, "ebay-import")?,
+    };
+
+    let mut checkpoint = {
+        let checkpoints = state.checkpoints.lock().unwrap();
+        match checkpoints.get(&account) {
+            Some(c) if !dry_run && !restart.unwrap_or(false) => c.clone(),
+            _ => Checkpoint { started_at: crate::unix_now(), ..Checkpoint::default() },
+        }
+    };
+    let client = client()?;
+    let mut report = ImportReport {
+        dry_run,
+        resumed_from: checkpoint.offset,
+        pages: 0,
+        scanned: 0,
+        skipped_inactive: 0,
+        items: Vec::new(),
+        duplicates: 0,
+        unmappable_specifics: BTreeMap::new(),
+        complete: false,
+    };
+
+    loop {
+        ctx.check()?;
+        let page = ctx
+            .run(get(
+                &client,
+                &token,
+                &format!("/sell/inventory/v1/inventory_item?limit={PAGE_SIZE}&offset={}", checkpoint.offset),
+            ))
+            .await??;
+        report.pages += 1;
+        checkpoint.total = page.get("total").and_then(Value::as_u64).or(checkpoint.total);
+        let inventory = page.get("inventoryItems").and_then(Value::as_array).cloned().unwrap_or_default();
+
+        let mut mapped = Vec::new();
+        for item in &inventory {
+            report.scanned += 1;
+            let Some(sku) = item.get("sku").and_then(Value::as_str) else {
+                continue;
+            };
+            let Some(offer) = ctx.run(published_offer(&client, &token, sku)).await?? else {
+                report.skipped_inactive += 1;
+                continue;
+            };
+            let Some(mut imported) = map_item(item, &offer, &fields, &mut report.unmappable_specifics) else {
+                report.skipped_inactive += 1;
+                continue;
+            };
+            imported.duplicate_of = find_duplicate(&existing, &imported.sku, &imported.link.external_id, &imported.title);
+            if imported.duplicate_of.is_some() {
+                report.duplicates += 1;
+            } else if !dry_run && !imported.photos.is_empty() {
+                let urls = std::mem::take(&mut imported.photos);
+                let dir = photo_dir.join(&imported.sku);
+                match ctx
+                    .run(crate::depop::photos::download_listing_photos(
+                        imported.sku.clone(),
+                        urls.clone(),
+                        dir.to_string_lossy().into_owned(),
+                    ))
+                    .await?
+                {
+                    Ok(paths) => imported.photos = paths,
+                    Err(e) => {
+                        imported.warnings.push(format!("photos not downloaded: {e}"));
+                        imported.photos = urls;
+                    }
+                }
+            }
+            mapped.push(imported);
+        }
+
+        checkpoint.offset += inventory.len() as u32;
+        checkpoint.imported += mapped.len();
+        let done = inventory.len() < PAGE_SIZE as usize
+            || checkpoint.total.is_some_and(|t| u64::from(checkpoint.offset) >= t);
+        if !dry_run {
+            let _ = app.emit("ebay-import-page", serde_json::json!({ "account": &account, "items": &mapped }));
+            let mut checkpoints = state.checkpoints.lock().unwrap();
+            if done {
+                checkpoints.remove(&account);
+            } else {
+                checkpoints.insert(account.clone(), checkpoint.clone());
+            }
+            crate::store::save(app, STORE_NAME, &*checkpoints)?;
+        }
+        let _ = app.emit(
+            "ebay-import-progress",
+            ImportProgress {
+                account: &account,
+                processed: checkpoint.offset,
+                total: checkpoint.total,
+                imported: checkpoint.imported,
+            },
+        );
+        report.items.extend(mapped);
+        if done {
+            report.complete = true;
+            break;
+        }
+    }
+
+    if !dry_run {
+        crate::audit::record(
+            app,
+            "ebay_import_active_listings",
+            Some(&account),
+            false,
+            serde_json::json!({ "imported": report.items.len(), "duplicates": report.duplicates }),
+        );
+    }
+    Ok(report)
+}
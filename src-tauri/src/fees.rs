@@ -0,0 +1,320 @@
+// ── Marketplace fee models ─────────────────────────────────────────────────
+// Rust mirror of the `calculateFees` implementations in src/api/platforms so
+// backend computations (simulations, reports) agree with the profit calculator.
+//
+// Fee rates are data, not code: each marketplace has a list of schedules with
+// effective-date ranges, seeded from fee_schedules.json and editable from the
+// settings screen. A sale is always priced with the schedule in force on its
+// own date, so a fee change doesn't silently rewrite last year's margins.
+// Ranges are `[effective_from, effective_to)` and may not overlap.
+
+use crate::repricing::{in_range, DateRange};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+const STORE_NAME: &str = "fee_schedules";
+
+#[derive(Clone, Copy, Serialize, Default, Debug)]
+pub struct FeeBreakdown {
+    pub final_value_fee: f64,
+    pub payment_processing_fee: f64,
+    /// Depop's Boosted Listings fee, when the sale was boosted.
+    pub boost_fee: f64,
+    pub total_fees: f64,
+    pub net_proceeds: f64,
+}
+
+pub fn round_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+impl FeeBreakdown {
+    /// Add the Boosted Listings fee on a boosted Depop sale at `price`.
+    pub fn with_boost(self, price: f64) -> FeeBreakdown {
+        let boost_fee = round_cents(price * crate::depop::boost::BOOST_FEE_PERCENT / 100.0);
+        FeeBreakdown {
+            boost_fee,
+            total_fees: round_cents(self.total_fees + boost_fee),
+            net_proceeds: round_cents(self.net_proceeds - boost_fee),
+            ..self
+        }
+    }
+}
+
+/// One marketplace's rates. Percentages are of the sale price.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    pub final_value_percent: f64,
+    #[serde(default)]
+    pub payment_percent: f64,
+    /// Flat per-order processing fee.
+    #[serde(default)]
+    pub fixed_per_order: f64,
+    /// Per-item listing fee, charged with the sale.
+    #[serde(default)]
+    pub listing_fee: f64,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScheduleRow {
+    /// `YYYY-MM-DD`, inclusive.
+    pub effective_from: String,
+    /// `YYYY-MM-DD`, exclusive; open-ended when None.
+    #[serde(default)]
+    pub effective_to: Option<String>,
+    #[serde(flatten)]
+    pub schedule: FeeSchedule,
+}
+
+impl ScheduleRow {
+    fn covers(&self, date: &str) -> bool {
+        date >= self.effective_from.as_str() && self.effective_to.as_deref().map_or(true, |to| date < to)
+    }
+
+    fn overlaps(&self, other: &ScheduleRow) -> bool {
+        let before = |end: &Option<String>, start: &str| end.as_deref().is_some_and(|e| e <= start);
+        !before(&self.effective_to, &other.effective_from) && !before(&other.effective_to, &self.effective_from)
+    }
+}
+
+impl FeeSchedule {
+    pub fn apply(&self, price: f64) -> FeeBreakdown {
+        let final_value_fee = price * self.final_value_percent / 100.0;
+        let processing = price * self.payment_percent / 100.0 + self.fixed_per_order + self.listing_fee;
+        let total_fees = final_value_fee + processing;
+        FeeBreakdown {
+            final_value_fee: round_cents(final_value_fee),
+            payment_processing_fee: round_cents(processing),
+            boost_fee: 0.0,
+            total_fees: round_cents(total_fees),
+            net_proceeds: round_cents(price - total_fees),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (name, pct) in [("Final value", self.final_value_percent), ("Payment", self.payment_percent)] {
+            if !pct.is_finite() || !(0.0..=100.0).contains(&pct) {
+                return Err(format!("{name} percentage must be between 0 and 100"));
+            }
+        }
+        for (name, amount) in [("Per-order fee", self.fixed_per_order), ("Listing fee", self.listing_fee)] {
+            if !amount.is_finite() || amount < 0.0 {
+                return Err(format!("{name} must be a non-negative amount"));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn seed_schedules() -> BTreeMap<String, Vec<ScheduleRow>> {
+    serde_json::from_str(include_str!("fee_schedules.json")).expect("embedded fee schedules are valid JSON")
+}
+
+/// Today's UTC date, `YYYY-MM-DD`.
+pub fn today() -> String {
+    crate::netlog::iso8601(crate::unix_now() * 1000)[..10].to_string()
+}
+
+pub struct FeeScheduleState {
+    /// Marketplaces the user hasn't edited fall back to the seed rows, so new
+    /// seeded marketplaces still show up after an update.
+    schedules: Mutex<BTreeMap<String, Vec<ScheduleRow>>>,
+}
+
+impl FeeScheduleState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        let mut schedules = seed_schedules();
+        let saved: BTreeMap<String, Vec<ScheduleRow>> = crate::store::load(app, STORE_NAME);
+        schedules.extend(saved);
+        FeeScheduleState { schedules: Mutex::new(schedules) }
+    }
+
+    /// Fees for a sale at `price` on `platform` on `date` (`YYYY-MM-DD`).
+    /// Unknown platforms, and dates no schedule covers, are fee-free.
+    pub fn calculate(&self, platform: &str, price: f64, date: &str) -> FeeBreakdown {
+        let schedules = self.schedules.lock().unwrap();
+        schedules
+            .get(platform)
+            .and_then(|rows| rows.iter().find(|r| r.covers(date)))
+            .map(|r| r.schedule.apply(price))
+            .unwrap_or(FeeBreakdown { net_proceeds: round_cents(price), ..FeeBreakdown::default() })
+    }
+}
+
+fn check_marketplace(marketplace: &str) -> Result<(), String> {
+    let valid = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_';
+    if marketplace.is_empty() || !marketplace.chars().all(valid) {
+        return Err(format!("Invalid marketplace id: {marketplace:?}"));
+    }
+    Ok(())
+}
+
+fn check_date(date: &str) -> Result<(), String> {
+    if !crate::custom_fields::valid_date(date) {
+        return Err(format!("Invalid date {date:?} (expected YYYY-MM-DD)"));
+    }
+    Ok(())
+}
+
+fn save(app: &tauri::AppHandle, state: &FeeScheduleState) -> Result<(), String> {
+    crate::store::save(app, STORE_NAME, &*state.schedules.lock().unwrap())
+}
+
+#[tauri::command]
+pub fn get_fee_schedules(state: tauri::State<'_, FeeScheduleState>, marketplace: String) -> Vec<ScheduleRow> {
+    state.schedules.lock().unwrap().get(&marketplace).cloned().unwrap_or_default()
+}
+
+/// Add a schedule, or replace the one starting on `effective_from`. To change
+/// rates going forward, end the current open schedule first (set its
+/// `effective_to`), then add the new one from that date.
+#[tauri::command]
+pub fn set_fee_schedule(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, FeeScheduleState>,
+    marketplace: String,
+    effective_from: String,
+    effective_to: Option<String>,
+    schedule: FeeSchedule,
+) -> Result<Vec<ScheduleRow>, String> {
+    check_marketplace(&marketplace)?;
+    check_date(&effective_from)?;
+    if let Some(to) = &effective_to {
+        check_date(to)?;
+        if *to <= effective_from {
+            return Err("The end date must be after the start date".to_string());
+        }
+    }
+    schedule.validate()?;
+
+    let row = ScheduleRow { effective_from, effective_to, schedule };
+    let rows = {
+        let mut schedules = state.schedules.lock().unwrap();
+        let rows = schedules.entry(marketplace).or_default();
+        if let Some(clash) = rows.iter().find(|r| r.effective_from != row.effective_from && r.overlaps(&row)) {
+            return Err(format!(
+                "Overlaps the schedule effective {} to {}",
+                clash.effective_from,
+                clash.effective_to.as_deref().unwrap_or("open")
+            ));
+        }
+        rows.retain(|r| r.effective_from != row.effective_from);
+        rows.push(row);
+        rows.sort_by(|a, b| a.effective_from.cmp(&b.effective_from));
+        rows.clone()
+    };
+    save(&app, &state)?;
+    Ok(rows)
+}
+
+#[tauri::command]
+pub fn remove_fee_schedule(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, FeeScheduleState>,
+    marketplace: String,
+    effective_from: String,
+) -> Result<Vec<ScheduleRow>, String> {
+    let rows = {
+        let mut schedules = state.schedules.lock().unwrap();
+        let rows = schedules.get_mut(&marketplace).ok_or_else(|| format!("No fee schedules for {marketplace}"))?;
+        let before = rows.len();
+        rows.retain(|r| r.effective_from != effective_from);
+        if rows.len() == before {
+            return Err(format!("No {marketplace} schedule starts on {effective_from}"));
+        }
+        rows.clone()
+    };
+    save(&app, &state)?;
+    Ok(rows)
+}
+
+/// Live calculator: fees for `price` under the schedule in force on `date`
+/// (today by default). `boosted` adds the Boosted Listings fee on Depop.
+#[tauri::command]
+pub fn calculate_fees(
+    state: tauri::State<'_, FeeScheduleState>,
+    marketplace: String,
+    price: f64,
+    date: Option<String>,
+    boosted: Option<bool>,
+) -> Result<FeeBreakdown, String> {
+    let date = date.unwrap_or_else(today);
+    check_date(&date)?;
+    let fees = state.calculate(&marketplace, price, &date);
+    if boosted.unwrap_or(false) && marketplace == "depop" {
+        return Ok(fees.with_boost(price));
+    }
+    Ok(fees)
+}
+
+#[derive(Deserialize)]
+pub struct RecordedSale {
+    id: String,
+    platform: String,
+    sale_price: f64,
+    /// ISO-8601 timestamp (`sales.sold_at`).
+    sold_at: String,
+    #[serde(default)]
+    platform_fees: f64,
+    /// Boosted Depop sale; the boost fee is part of the expected fees.
+    #[serde(default)]
+    boosted: bool,
+}
+
+#[derive(Serialize)]
+pub struct FeeCorrection {
+    id: String,
+    platform: String,
+    sold_at: String,
+    recorded_fees: f64,
+    fees: FeeBreakdown,
+    difference: f64,
+}
+
+#[derive(Serialize)]
+pub struct FeeRecompute {
+    matched_sales: usize,
+    /// Only sales whose recorded fee differs from the schedule's.
+    corrections: Vec<FeeCorrection>,
+    total_difference: f64,
+}
+
+/// Re-price the fees of historical sales in `range` against the schedules in
+/// force on each sale's date, e.g. after correcting a schedule. Nothing is
+/// written: the caller applies the corrections to its `sales` rows.
+#[tauri::command]
+pub fn recompute_fees(
+    state: tauri::State<'_, FeeScheduleState>,
+    sales: Vec<RecordedSale>,
+    range: DateRange,
+) -> Result<FeeRecompute, String> {
+    if range.start > range.end {
+        return Err("Range start is after range end".to_string());
+    }
+    let matched: Vec<&RecordedSale> = sales.iter().filter(|s| in_range(&s.sold_at, &range)).collect();
+    let mut corrections = Vec::new();
+    let mut total_difference = 0.0;
+    for sale in &matched {
+        let date = sale.sold_at.get(..10).unwrap_or(&sale.sold_at);
+        let mut fees = state.calculate(&sale.platform, sale.sale_price, date);
+        if sale.boosted && sale.platform == "depop" {
+            fees = fees.with_boost(sale.sale_price);
+        }
+        let difference = round_cents(fees.total_fees - sale.platform_fees);
+        if difference != 0.0 {
+            total_difference += difference;
+            corrections.push(FeeCorrection {
+                id: sale.id.clone(),
+                platform: sale.platform.clone(),
+                sold_at: sale.sold_at.clone(),
+                recorded_fees: sale.platform_fees,
+                fees,
+                difference,
+            });
+        }
+    }
+    Ok(FeeRecompute { matched_sales: matched.len(), corrections, total_difference: round_cents(total_difference) })
+}
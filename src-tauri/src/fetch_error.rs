@@ -0,0 +1,221 @@
+// ── native_fetch errors ────────────────────────────────────────────────────
+// `native_fetch` rejects with a `FetchError` object instead of a bare
+// string, so the frontend can tell a DNS failure from a refused connection
+// or a timeout and decide whether a retry is worth it. reqwest only says
+// "connect error" for DNS and TLS failures alike, so those are told apart by
+// the messages in the error's source chain.
+
+use serde::Serialize;
+use std::error::Error as _;
+use std::fmt;
+
+const PREVIEW_CHARS: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FetchErrorKind {
+    Timeout,
+    /// The host name didn't resolve.
+    Dns,
+    /// Refused, reset or unreachable before a response.
+    Connect,
+    /// Handshake or certificate failure.
+    Tls,
+    TooManyRedirects,
+    /// The response body couldn't be read or decoded.
+    BodyDecode,
+    /// `parse_json` was set and the body isn't JSON.
+    InvalidJson,
+    /// Cancelled through `cancel_fetch`.
+    Cancelled,
+    /// Bad arguments and anything not above.
+    Other,
+}
+
+impl FetchErrorKind {
+    /// Whether the same request might succeed if sent again.
+    pub fn retryable(self) -> bool {
+        matches!(self, Self::Timeout | Self::Dns | Self::Connect | Self::BodyDecode)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FetchError {
+    pub kind: FetchErrorKind,
+    pub message: String,
+    pub url: String,
+    pub retryable: bool,
+    /// HTTP status, for failures after the response arrived.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// The start of the body, for `invalid_json`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_preview: Option<String>,
+}
+
+impl FetchError {
+    pub fn new(kind: FetchErrorKind, message: impl Into<String>, url: &str) -> Self {
+        FetchError {
+            kind,
+            message: message.into(),
+            url: url.to_string(),
+            retryable: kind.retryable(),
+            status: None,
+            body_preview: None,
+        }
+    }
+
+    pub fn other(message: impl Into<String>, url: &str) -> Self {
+        Self::new(FetchErrorKind::Other, message, url)
+    }
+
+    /// A body that `parse_json` couldn't parse, with its first
+    /// PREVIEW_CHARS characters.
+    pub fn invalid_json(e: &serde_json::Error, body: &str, truncated: bool, status: u16, url: &str) -> Self {
+        let mut message = format!("invalid JSON: {e}");
+        if truncated {
+            message.push_str(" (the body was cut off at max_body_bytes)");
+        }
+        FetchError {
+            status: Some(status),
+            body_preview: Some(body.chars().take(PREVIEW_CHARS).collect()),
+            ..Self::new(FetchErrorKind::InvalidJson, message, url)
+        }
+    }
+
+    /// A reqwest failure, its message prefixed with `stage` ("request", "body").
+    pub fn from_reqwest(stage: &str, e: &reqwest::Error, url: &str) -> Self {
+        Self::new(classify(e), format!("{stage}: {e}"), url)
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// The messages of `e` and everything it wraps, lowercased.
+fn source_chain(e: &reqwest::Error) -> String {
+    let mut text = e.to_string();
+    let mut source = e.source();
+    while let Some(inner) = source {
+        text.push_str(" | ");
+        text.push_str(&inner.to_string());
+        source = inner.source();
+    }
+    text.to_lowercase()
+}
+
+const DNS_HINTS: &[&str] = &[
+    "dns error",
+    "failed to lookup address",
+    "name or service not known",
+    "no such host",
+    "nodename nor servname",
+    "temporary failure in name resolution",
+];
+const TLS_HINTS: &[&str] = &["certificate", "tls", "ssl", "handshake"];
+
+pub fn classify(e: &reqwest::Error) -> FetchErrorKind {
+    if e.is_timeout() {
+        return FetchErrorKind::Timeout;
+    }
+    if e.is_redirect() {
+        return FetchErrorKind::TooManyRedirects;
+    }
+    if e.is_decode() || e.is_body() {
+        return FetchErrorKind::BodyDecode;
+    }
+    classify_chain(&source_chain(e), e.is_connect())
+}
+
+/// DNS and TLS failures by the messages in `chain` (see `source_chain`),
+/// then whether reqwest called it a connect error.
+fn classify_chain(chain: &str, is_connect: bool) -> FetchErrorKind {
+    if DNS_HINTS.iter().any(|hint| chain.contains(hint)) {
+        FetchErrorKind::Dns
+    } else if TLS_HINTS.iter().any(|hint| chain.contains(hint)) {
+        FetchErrorKind::Tls
+    } else if is_connect {
+        FetchErrorKind::Connect
+    } else {
+        FetchErrorKind::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn hints_pick_the_kind() {
+        let chain = "error sending request | error trying to connect: dns error: failed to lookup address information";
+        assert_eq!(classify_chain(chain, true), FetchErrorKind::Dns);
+        assert_eq!(classify_chain("connect error | no such host is known.", true), FetchErrorKind::Dns);
+        assert_eq!(
+            classify_chain("error trying to connect: invalid peer certificate: expired", true),
+            FetchErrorKind::Tls
+        );
+        assert_eq!(classify_chain("error:0a00010b:ssl routines::wrong version number", true), FetchErrorKind::Tls);
+        assert_eq!(classify_chain("tcp connect error: connection refused (os error 111)", true), FetchErrorKind::Connect);
+        assert_eq!(classify_chain("builder error: relative url without a base", false), FetchErrorKind::Other);
+    }
+
+    #[test]
+    fn retryable_kinds() {
+        use FetchErrorKind::*;
+        for kind in [Timeout, Dns, Connect, BodyDecode] {
+            assert!(kind.retryable(), "{kind:?}");
+            assert!(FetchError::new(kind, "x", "u").retryable);
+        }
+        for kind in [Tls, TooManyRedirects, InvalidJson, Cancelled, Other] {
+            assert!(!kind.retryable(), "{kind:?}");
+            assert!(!FetchError::new(kind, "x", "u").retryable);
+        }
+    }
+
+    async fn fetch(url: &str, client: reqwest::Client) -> FetchErrorKind {
+        classify(&client.get(url).send().await.expect_err("request should fail"))
+    }
+
+    #[tokio::test]
+    async fn real_failures_are_classified() {
+        // Nothing listening.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed = listener.local_addr().unwrap();
+        drop(listener);
+        assert_eq!(fetch(&format!("http://{closed}/"), reqwest::Client::new()).await, FetchErrorKind::Connect);
+
+        // Never answers.
+        let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let silent_addr = silent.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((stream, _)) = silent.accept().await {
+                held.push(stream);
+            }
+        });
+        let client = reqwest::Client::builder().timeout(Duration::from_millis(200)).build().unwrap();
+        assert_eq!(fetch(&format!("http://{silent_addr}/"), client).await, FetchErrorKind::Timeout);
+
+        // Plain HTTP where TLS was expected, and a redirect loop.
+        let plain = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let plain_addr = plain.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = plain.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream
+                    .write_all(b"HTTP/1.1 302 Found\r\nLocation: /again\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .await;
+            }
+        });
+        assert_eq!(fetch(&format!("https://{plain_addr}/"), reqwest::Client::new()).await, FetchErrorKind::Tls);
+        let client = reqwest::Client::builder().redirect(reqwest::redirect::Policy::limited(2)).build().unwrap();
+        assert_eq!(fetch(&format!("http://{plain_addr}/"), client).await, FetchErrorKind::TooManyRedirects);
+    }
+}
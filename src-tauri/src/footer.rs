@@ -0,0 +1,110 @@
+// ── Managed listing footers ────────────────────────────────────────────────
+// A standard footer per marketplace ("bundle for a discount, check my shop
+// for more vintage Nike") appended to every description between two marker
+// lines, so it can be replaced later without touching what the seller wrote
+// above it. `preview_listing` applies it to new and edited listings, and
+// `propagate_footer` rewrites it on existing Depop listings.
+//
+// When description + footer would exceed the marketplace's description limit
+// the footer is shortened (or left off), never the description.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+const STORE_NAME: &str = "listing_footers";
+/// Marker line around the managed footer.
+pub const MARKER: &str = "———";
+const MAX_FOOTER_CHARS: usize = 1000;
+
+pub struct FooterState {
+    footers: Mutex<BTreeMap<String, String>>,
+}
+
+impl FooterState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        FooterState { footers: Mutex::new(crate::store::load(app, STORE_NAME)) }
+    }
+
+    pub fn get(&self, marketplace: &str) -> Option<String> {
+        self.footers.lock().unwrap().get(marketplace).cloned()
+    }
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct AppliedFooter {
+    pub description: String,
+    /// Set when the footer had to be shortened or left off to fit.
+    pub warning: Option<String>,
+}
+
+/// `description` without a managed footer block at its end.
+pub fn strip(description: &str) -> &str {
+    let start_marker = format!("\n\n{MARKER}\n");
+    let end_marker = format!("\n{MARKER}");
+    let trimmed = description.trim_end();
+    if let Some(body) = trimmed.strip_suffix(&end_marker) {
+        if let Some(start) = body.rfind(&start_marker) {
+            return &description[..start];
+        }
+    }
+    description
+}
+
+/// Replace the managed footer on `description` with `footer` (removing it
+/// when `footer` is empty), fitting the result within `max_chars`.
+pub fn apply(description: &str, footer: Option<&str>, max_chars: Option<usize>) -> AppliedFooter {
+    let body = strip(description).trim_end().to_string();
+    let Some(footer) = footer.map(str::trim).filter(|f| !f.is_empty()) else {
+        return AppliedFooter { description: body, warning: None };
+    };
+    let frame = |text: &str| format!("{body}\n\n{MARKER}\n{text}\n{MARKER}");
+    let full = frame(footer);
+    let Some(max) = max_chars.filter(|max| full.chars().count() > *max) else {
+        return AppliedFooter { description: full, warning: None };
+    };
+    let room = max.saturating_sub(frame("").chars().count());
+    if room < 2 {
+        return AppliedFooter {
+            description: body,
+            warning: Some(format!("Footer left off: the description alone leaves no room under {max} characters")),
+        };
+    }
+    let short: String = footer.chars().take(room - 1).collect();
+    AppliedFooter {
+        description: frame(&format!("{}…", short.trim_end())),
+        warning: Some(format!(
+            "Footer shortened to {room} of {} characters to stay under {max}",
+            footer.chars().count()
+        )),
+    }
+}
+
+#[tauri::command]
+pub fn get_listing_footers(state: tauri::State<'_, FooterState>) -> BTreeMap<String, String> {
+    state.footers.lock().unwrap().clone()
+}
+
+/// Set the footer for `marketplace`; empty text removes it.
+#[tauri::command]
+pub fn set_listing_footer(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, FooterState>,
+    marketplace: String,
+    text: String,
+) -> Result<(), String> {
+    let text = text.trim().to_string();
+    if text.chars().count() > MAX_FOOTER_CHARS {
+        return Err(format!("Footers are at most {MAX_FOOTER_CHARS} characters"));
+    }
+    if text.lines().any(|l| l.trim() == MARKER) {
+        return Err(format!("The footer can't contain the marker line {MARKER}"));
+    }
+    let mut footers = state.footers.lock().unwrap();
+    if text.is_empty() {
+        footers.remove(&marketplace);
+    } else {
+        footers.insert(marketplace, text);
+    }
+    crate::store::save(&app, STORE_NAME, &*footers)
+}
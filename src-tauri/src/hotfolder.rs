@@ -0,0 +1,727 @@
+// ── CSV hot folder ─────────────────────────────────────────────────────────
+// Picks up CSV files dropped into a watched folder (a nightly export from a
+// sourcing spreadsheet, say) and imports them as inventory. Columns are
+// read through a saved import mapping (`save_import_mapping`) that names the
+// header for each item field, so the same export imports the same way every
+// night.
+//
+// The folder is polled every POLL_INTERVAL; a file is only read once its
+// size and modification time have held still across two polls, so a file
+// still being written is left alone. Each row is validated on its own:
+// the valid rows become a batch and the rest are listed by row number.
+//   - A clean file is archived under `archive/` with a `.report.txt`
+//     beside it, or deleted, as configured.
+//   - A file with any invalid row (or that can't be read through the
+//     mapping at all) moves to `quarantine/` with an `.errors.txt` sidecar.
+//     Its valid rows are still imported, and later drops carry on as normal.
+//
+// Inventory is written with the user's Supabase session, which only the
+// frontend holds, so a batch waits in a persisted queue: `hotfolder-batch-ready`
+// tells the frontend, which passes its session to `import_hotfolder_batch`.
+// That inserts the rows (with fresh SKUs for rows without one) and emits
+// `hotfolder-import-completed` plus a desktop notification with the counts.
+//
+// A mapping can also name a lot reference column (`lot`, plus an optional
+// `lot_cost` for the lot's total). Rows sharing a reference become one
+// purchase lot, found by reference or created with the `lot_cost` (else the
+// sum of its rows' costs) as its total. A lot that's new and whose rows carry
+// no costs is split equally straight away (lots.rs).
+
+use crate::request_context::{Finished, RequestContext};
+use crate::supabase::{self, SupabaseSession};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tauri::{Emitter, Manager};
+
+const STORE_NAME: &str = "csv_hotfolder";
+const PENDING_STORE: &str = "hotfolder_pending";
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+const ARCHIVE_DIR: &str = "archive";
+const QUARANTINE_DIR: &str = "quarantine";
+/// Row errors carried in events; the sidecar file has all of them.
+const MAX_REPORTED_ERRORS: usize = 50;
+
+/// Item fields a mapping can fill.
+const FIELDS: &[&str] = &["name", "sku", "description", "cost", "quantity", "category", "location"];
+/// Lot reference and lot total; used to group rows, not stored on the item.
+const LOT_FIELDS: &[&str] = &["lot", "lot_cost"];
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PostAction {
+    /// Move to `archive/` with the validation report beside it.
+    #[default]
+    Archive,
+    Delete,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HotfolderConfig {
+    pub path: String,
+    /// File names to pick up; `*` and `?` wildcards, case-insensitive.
+    pub pattern: String,
+    pub mapping_profile: String,
+    pub post_action: PostAction,
+    pub enabled: bool,
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ImportMapping {
+    pub name: String,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    /// Item field -> column header (matched case-insensitively).
+    pub columns: BTreeMap<String, String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Stored {
+    #[serde(default)]
+    config: Option<HotfolderConfig>,
+    #[serde(default)]
+    mappings: Vec<ImportMapping>,
+}
+
+/// A processed file's valid rows, waiting for `import_hotfolder_batch`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HotfolderBatch {
+    pub id: String,
+    pub file: String,
+    pub rows: Vec<Map<String, Value>>,
+    pub rows_total: usize,
+    pub invalid: usize,
+    /// "row N: problem", at most MAX_REPORTED_ERRORS.
+    pub errors: Vec<String>,
+    /// Where the file went: the archive or quarantine copy, or None when
+    /// deleted.
+    pub moved_to: Option<String>,
+    pub quarantined: bool,
+    pub created_at: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct HotfolderResult {
+    pub batch_id: String,
+    pub file: String,
+    pub rows_total: usize,
+    pub imported: usize,
+    pub invalid: usize,
+    pub errors: Vec<String>,
+    pub moved_to: Option<String>,
+    pub quarantined: bool,
+}
+
+impl HotfolderResult {
+    fn of(batch: &HotfolderBatch, imported: usize) -> Self {
+        HotfolderResult {
+            batch_id: batch.id.clone(),
+            file: batch.file.clone(),
+            rows_total: batch.rows_total,
+            imported,
+            invalid: batch.invalid,
+            errors: batch.errors.clone(),
+            moved_to: batch.moved_to.clone(),
+            quarantined: batch.quarantined,
+        }
+    }
+}
+
+pub struct HotfolderState {
+    stored: Mutex<Stored>,
+    pending: Mutex<Vec<HotfolderBatch>>,
+    /// Size and modification time of each matching file at the last poll.
+    last_seen: Mutex<HashMap<PathBuf, (u64, SystemTime)>>,
+}
+
+impl HotfolderState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        HotfolderState {
+            stored: Mutex::new(crate::store::load(app, STORE_NAME)),
+            pending: Mutex::new(crate::store::load(app, PENDING_STORE)),
+            last_seen: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+fn save_stored(app: &tauri::AppHandle, state: &HotfolderState) -> Result<(), String> {
+    crate::store::save(app, STORE_NAME, &*state.stored.lock().unwrap())
+}
+
+fn save_pending(app: &tauri::AppHandle, state: &HotfolderState) -> Result<(), String> {
+    crate::store::save(app, PENDING_STORE, &*state.pending.lock().unwrap())
+}
+
+/// `*`/`?` wildcard match, ignoring case.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.to_lowercase().chars().collect();
+    let n: Vec<char> = name.to_lowercase().chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((sp, sn)) = star {
+            pi = sp + 1;
+            ni = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// A number from a spreadsheet cell: currency signs and thousands
+/// separators are ignored.
+fn number(cell: &str) -> Option<f64> {
+    let cleaned: String = cell.chars().filter(|c| c.is_ascii_digit() || matches!(c, '.' | '-')).collect();
+    cleaned.parse().ok().filter(|n: &f64| n.is_finite())
+}
+
+/// One CSV row as item fields, or why it can't be imported.
+fn map_row(columns: &[(&str, usize)], row: &[String]) -> Result<Map<String, Value>, String> {
+    let mut item = Map::new();
+    for (field, index) in columns {
+        let cell = row.get(*index).map(|c| c.trim()).unwrap_or_default();
+        if cell.is_empty() {
+            continue;
+        }
+        let value = match *field {
+            "cost" | "lot_cost" => match number(cell) {
+                Some(n) if n >= 0.0 => json!(crate::fees::round_cents(n)),
+                _ => return Err(format!("{field} \"{cell}\" isn't an amount")),
+            },
+            "quantity" => match cell.parse::<i64>() {
+                Ok(n) if n >= 1 => json!(n),
+                _ => return Err(format!("quantity \"{cell}\" isn't a whole number of at least 1")),
+            },
+            _ => json!(cell),
+        };
+        item.insert(field.to_string(), value);
+    }
+    if !item.contains_key("name") {
+        return Err("no name".to_string());
+    }
+    item.entry("quantity").or_insert(json!(1));
+    Ok(item)
+}
+
+#[derive(Default)]
+struct ParsedRows {
+    valid: Vec<Map<String, Value>>,
+    total: usize,
+    errors: Vec<String>,
+}
+
+/// Parse `text` through `mapping`. Fails outright when a mapped column isn't
+/// in the header.
+fn read_rows(text: &str, mapping: &ImportMapping) -> Result<ParsedRows, String> {
+    let mut records = crate::payouts::parse_csv(text, mapping.delimiter).into_iter();
+    let header: Vec<String> = records.next().ok_or("The file is empty")?.iter().map(|h| h.trim().to_lowercase()).collect();
+    let mut columns = Vec::new();
+    for (field, column) in &mapping.columns {
+        let index = header
+            .iter()
+            .position(|h| *h == column.trim().to_lowercase())
+            .ok_or_else(|| format!("The file has no \"{column}\" column (mapped to {field})"))?;
+        columns.push((field.as_str(), index));
+    }
+    let mut parsed = ParsedRows::default();
+    for (i, record) in records.enumerate() {
+        parsed.total += 1;
+        // Row 1 is the header.
+        match map_row(&columns, &record) {
+            Ok(item) => parsed.valid.push(item),
+            Err(e) => parsed.errors.push(format!("row {}: {e}", i + 2)),
+        }
+    }
+    Ok(parsed)
+}
+
+/// Rows of one batch that belong to the same purchase lot.
+#[derive(Debug, PartialEq)]
+struct LotGroup {
+    rows: Vec<usize>,
+    total: f64,
+    /// Some row gave its own cost.
+    costed: bool,
+}
+
+/// Group `rows` by lot reference. A lot's total is its `lot_cost`, which
+/// has to agree on every row that gives one, otherwise the sum of the rows'
+/// costs.
+fn group_lots(rows: &[Map<String, Value>]) -> Result<BTreeMap<String, LotGroup>, String> {
+    let mut groups: BTreeMap<String, (LotGroup, Option<f64>)> = BTreeMap::new();
+    for (i, row) in rows.iter().enumerate() {
+        let Some(reference) = row.get("lot").and_then(Value::as_str) else { continue };
+        let (group, lot_cost) = groups
+            .entry(reference.to_string())
+            .or_insert_with(|| (LotGroup { rows: Vec::new(), total: 0.0, costed: false }, None));
+        group.rows.push(i);
+        if let Some(cost) = row.get("cost").and_then(Value::as_f64) {
+            group.total += cost * row.get("quantity").and_then(Value::as_f64).unwrap_or(1.0);
+            group.costed = true;
+        }
+        if let Some(given) = row.get("lot_cost").and_then(Value::as_f64) {
+            match lot_cost {
+                Some(earlier) if *earlier != given => {
+                    return Err(format!("Lot {reference} is given two different costs ({earlier} and {given})"));
+                }
+                _ => *lot_cost = Some(given),
+            }
+        }
+    }
+    Ok(groups
+        .into_iter()
+        .map(|(reference, (mut group, lot_cost))| {
+            group.total = crate::fees::round_cents(lot_cost.unwrap_or(group.total));
+            (reference, group)
+        })
+        .collect())
+}
+
+/// The lot with `reference`, created with `total` if there isn't one yet,
+/// and whether it has had a cost allocation.
+async fn lot_for(
+    session: &SupabaseSession,
+    client: &reqwest::Client,
+    user_id: &str,
+    reference: &str,
+    total: f64,
+    file: &str,
+) -> Result<(String, bool), String> {
+    let found = crate::lots::rows(
+        session
+            .rest(client, Method::GET, "purchase_lots")
+            .query(&[("select", "id,allocation_method"), ("reference", &format!("eq.{reference}"))]),
+    )
+    .await?;
+    if let Some(lot) = found.first() {
+        let id = lot["id"].as_str().ok_or("purchase lot without an id")?;
+        return Ok((id.to_string(), !lot["allocation_method"].is_null()));
+    }
+    let source = format!("CSV import: {file}");
+    let row = json!({ "user_id": user_id, "reference": reference, "total_cost": total, "source": source });
+    let created = crate::lots::rows(
+        session.rest(client, Method::POST, "purchase_lots").header("Prefer", "return=representation").json(&row),
+    )
+    .await?;
+    let id = created.first().and_then(|l| l["id"].as_str()).ok_or("The new purchase lot wasn't returned")?;
+    Ok((id.to_string(), false))
+}
+
+/// Move `file` into `dir` beside it, prefixed with the time so repeated
+/// drops of the same name don't collide.
+fn move_into(file: &Path, dir: &str) -> Result<PathBuf, String> {
+    let folder = file.parent().ok_or("file has no folder")?.join(dir);
+    std::fs::create_dir_all(&folder).map_err(|e| format!("create {}: {e}", folder.display()))?;
+    let stamp = crate::netlog::iso8601(crate::netlog::unix_millis()).replace([':', '.'], "-");
+    let name = file.file_name().ok_or("file has no name")?.to_string_lossy();
+    let dest = folder.join(format!("{stamp}-{name}"));
+    std::fs::rename(file, &dest).map_err(|e| format!("move {} to {}: {e}", file.display(), dest.display()))?;
+    Ok(dest)
+}
+
+fn sidecar(dest: &Path, suffix: &str) -> PathBuf {
+    let mut name = dest.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Read, validate and file away one dropped CSV.
+fn process_file(path: &Path, config: &HotfolderConfig, mapping: Option<&ImportMapping>) -> Result<HotfolderBatch, String> {
+    let file = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let bytes = std::fs::read(path).map_err(|e| format!("read {}: {e}", path.display()))?;
+    let text = String::from_utf8_lossy(&bytes);
+    let parsed = mapping
+        .ok_or_else(|| format!("The import mapping \"{}\" no longer exists", config.mapping_profile))
+        .and_then(|m| read_rows(&text, m));
+    let ParsedRows { valid: rows, total: rows_total, errors } =
+        parsed.unwrap_or_else(|e| ParsedRows { errors: vec![e], ..ParsedRows::default() });
+
+    let quarantined = !errors.is_empty();
+    let report = format!(
+        "{file}\n{rows_total} rows, {} valid, {} invalid\n\n{}\n",
+        rows.len(),
+        errors.len(),
+        errors.join("\n")
+    );
+    let moved_to = if quarantined {
+        let dest = move_into(path, QUARANTINE_DIR)?;
+        std::fs::write(sidecar(&dest, ".errors.txt"), &report).map_err(|e| format!("write error report: {e}"))?;
+        Some(dest)
+    } else if config.post_action == PostAction::Archive {
+        let dest = move_into(path, ARCHIVE_DIR)?;
+        std::fs::write(sidecar(&dest, ".report.txt"), &report).map_err(|e| format!("write report: {e}"))?;
+        Some(dest)
+    } else {
+        std::fs::remove_file(path).map_err(|e| format!("delete {}: {e}", path.display()))?;
+        None
+    };
+
+    Ok(HotfolderBatch {
+        id: hex::encode(rand::random::<[u8; 8]>()),
+        file,
+        rows,
+        rows_total,
+        invalid: errors.len(),
+        errors: errors.into_iter().take(MAX_REPORTED_ERRORS).collect(),
+        moved_to: moved_to.map(|p| p.to_string_lossy().into_owned()),
+        quarantined,
+        created_at: crate::unix_now(),
+    })
+}
+
+fn announce(app: &tauri::AppHandle, result: &HotfolderResult) {
+    let _ = app.emit("hotfolder-import-completed", result);
+    let mut body = format!("{} of {} rows imported", result.imported, result.rows_total);
+    if result.quarantined {
+        body.push_str(&format!("; {} problems, file quarantined", result.invalid));
+    }
+    crate::notifications::show(app, &format!("Imported {}", result.file), &body);
+}
+
+/// Files in the folder that match and have stopped changing.
+fn settled_files(state: &HotfolderState, config: &HotfolderConfig) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(&config.path) else {
+        return Vec::new();
+    };
+    let mut now = HashMap::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if meta.is_file() && glob_match(&config.pattern, &name) {
+            now.insert(path, (meta.len(), meta.modified().unwrap_or(SystemTime::UNIX_EPOCH)));
+        }
+    }
+    let mut last_seen = state.last_seen.lock().unwrap();
+    let settled = now.iter().filter(|(path, seen)| last_seen.get(*path) == Some(*seen)).map(|(p, _)| p.clone()).collect();
+    *last_seen = now;
+    settled
+}
+
+fn poll(app: &tauri::AppHandle) {
+    let state = app.state::<HotfolderState>();
+    let (config, mapping) = {
+        let stored = state.stored.lock().unwrap();
+        let Some(config) = stored.config.clone().filter(|c| c.enabled) else {
+            return;
+        };
+        let mapping = stored.mappings.iter().find(|m| m.name == config.mapping_profile).cloned();
+        (config, mapping)
+    };
+    for path in settled_files(&state, &config) {
+        let batch = match process_file(&path, &config, mapping.as_ref()) {
+            Ok(batch) => batch,
+            Err(e) => {
+                log::warn!("hot folder: {e}");
+                continue;
+            }
+        };
+        state.last_seen.lock().unwrap().remove(&path);
+        crate::audit::record(
+            app,
+            "hotfolder_file",
+            None,
+            false,
+            json!({ "file": batch.file, "rows": batch.rows_total, "valid": batch.rows.len(), "invalid": batch.invalid }),
+        );
+        if batch.rows.is_empty() {
+            announce(app, &HotfolderResult::of(&batch, 0));
+            continue;
+        }
+        let _ = app.emit("hotfolder-batch-ready", HotfolderResult::of(&batch, 0));
+        state.pending.lock().unwrap().push(batch);
+        if let Err(e) = save_pending(app, &state) {
+            log::warn!("saving hot folder batches: {e}");
+        }
+    }
+}
+
+pub fn start_watch_job(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let app = app.clone();
+            let _ = tauri::async_runtime::spawn_blocking(move || poll(&app)).await;
+        }
+    });
+}
+
+// ── Commands ───────────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn list_import_mappings(state: tauri::State<'_, HotfolderState>) -> Vec<ImportMapping> {
+    state.stored.lock().unwrap().mappings.clone()
+}
+
+/// Add or replace (by name) an import mapping.
+#[tauri::command]
+pub fn save_import_mapping(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, HotfolderState>,
+    mut mapping: ImportMapping,
+) -> Result<ImportMapping, String> {
+    mapping.name = mapping.name.trim().to_string();
+    if mapping.name.is_empty() {
+        return Err("The mapping needs a name".to_string());
+    }
+    mapping.columns.retain(|_, column| !column.trim().is_empty());
+    if let Some(field) =
+        mapping.columns.keys().find(|f| !FIELDS.contains(&f.as_str()) && !LOT_FIELDS.contains(&f.as_str()))
+    {
+        let known: Vec<&str> = FIELDS.iter().chain(LOT_FIELDS).copied().collect();
+        return Err(format!("Unknown item field {field} (use one of {})", known.join(", ")));
+    }
+    if mapping.columns.contains_key("lot_cost") && !mapping.columns.contains_key("lot") {
+        return Err("Map a lot reference column to go with the lot cost".to_string());
+    }
+    if !mapping.columns.contains_key("name") {
+        return Err("Map a column to the item name".to_string());
+    }
+    {
+        let mut stored = state.stored.lock().unwrap();
+        stored.mappings.retain(|m| m.name != mapping.name);
+        stored.mappings.push(mapping.clone());
+    }
+    save_stored(&app, &state)?;
+    Ok(mapping)
+}
+
+#[tauri::command]
+pub fn remove_import_mapping(app: tauri::AppHandle, state: tauri::State<'_, HotfolderState>, name: String) -> Result<(), String> {
+    {
+        let mut stored = state.stored.lock().unwrap();
+        if stored.config.as_ref().is_some_and(|c| c.enabled && c.mapping_profile == name) {
+            return Err(format!("The hot folder uses \"{name}\"; change it first"));
+        }
+        stored.mappings.retain(|m| m.name != name);
+    }
+    save_stored(&app, &state)
+}
+
+#[tauri::command]
+pub fn get_csv_hotfolder(state: tauri::State<'_, HotfolderState>) -> Option<HotfolderConfig> {
+    state.stored.lock().unwrap().config.clone()
+}
+
+/// Watch `path` for CSVs matching `pattern` (default `*.csv`), importing
+/// them through `mapping_profile`. `enabled: false` pauses the folder.
+#[tauri::command]
+pub fn configure_csv_hotfolder(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, HotfolderState>,
+    path: String,
+    mapping_profile: String,
+    post_action: Option<PostAction>,
+    pattern: Option<String>,
+    enabled: Option<bool>,
+) -> Result<HotfolderConfig, String> {
+    let path = path.trim().to_string();
+    if !Path::new(&path).is_dir() {
+        return Err(format!("{path} isn't a folder"));
+    }
+    let config = HotfolderConfig {
+        path,
+        pattern: pattern.map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).unwrap_or_else(|| "*.csv".to_string()),
+        mapping_profile: mapping_profile.trim().to_string(),
+        post_action: post_action.unwrap_or_default(),
+        enabled: enabled.unwrap_or(true),
+    };
+    {
+        let mut stored = state.stored.lock().unwrap();
+        if !stored.mappings.iter().any(|m| m.name == config.mapping_profile) {
+            return Err(format!("No import mapping is called \"{}\"", config.mapping_profile));
+        }
+        stored.config = Some(config.clone());
+    }
+    state.last_seen.lock().unwrap().clear();
+    save_stored(&app, &state)?;
+    Ok(config)
+}
+
+#[tauri::command]
+pub fn get_pending_hotfolder_imports(state: tauri::State<'_, HotfolderState>) -> Vec<HotfolderBatch> {
+    state.pending.lock().unwrap().clone()
+}
+
+/// Insert a waiting batch's rows as inventory items, generating SKUs for
+/// rows without one. Cancellable (see request_context) until the insert
+/// starts; a cancelled batch stays waiting.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn import_hotfolder_batch(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    state: tauri::State<'_, HotfolderState>,
+    skus: tauri::State<'_, crate::sku::SkuState>,
+    session: SupabaseSession,
+    batch_id: String,
+    invocation_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<Finished<HotfolderResult>, String> {
+    let ctx = RequestContext::begin(&app, &window, "import_hotfolder_batch", invocation_id, timeout_ms)?;
+    let result = import_batch(&app, &state, &skus, &ctx, &session, batch_id).await;
+    ctx.finish(result)
+}
+
+async fn import_batch(
+    app: &tauri::AppHandle,
+    state: &HotfolderState,
+    skus: &crate::sku::SkuState,
+    ctx: &RequestContext,
+    session: &SupabaseSession,
+    batch_id: String,
+) -> Result<HotfolderResult, String> {
+    let batch = state
+        .pending
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|b| b.id == batch_id)
+        .cloned()
+        .ok_or("That batch was already imported")?;
+    let client = supabase::client()?;
+    let user_id = ctx.run(session.user_id(&client)).await??;
+
+    let mut rows = batch.rows.clone();
+    let mut without_sku: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, row) in rows.iter().enumerate() {
+        if !row.contains_key("sku") {
+            let category = row.get("category").and_then(Value::as_str).unwrap_or_default();
+            without_sku.entry(category.to_string()).or_default().push(i);
+        }
+    }
+    let settings = skus.settings();
+    for (category, indexes) in without_sku {
+        let fresh = ctx.run(crate::sku::next_skus(&settings, session, &category, indexes.len() as u32)).await??;
+        for (i, sku) in indexes.into_iter().zip(fresh) {
+            rows[i].insert("sku".to_string(), json!(sku));
+        }
+    }
+    let mut lot_of_row = HashMap::new();
+    let mut to_allocate = Vec::new();
+    for (reference, group) in group_lots(&rows)? {
+        let (lot_id, allocated) =
+            ctx.run(lot_for(session, &client, &user_id, &reference, group.total, &batch.file)).await??;
+        if !allocated && !group.costed {
+            to_allocate.push(lot_id.clone());
+        }
+        lot_of_row.extend(group.rows.into_iter().map(|i| (i, lot_id.clone())));
+    }
+    // A bulk insert needs the same keys on every row.
+    for (i, row) in rows.iter_mut().enumerate() {
+        for field in FIELDS {
+            row.entry(*field).or_insert(Value::Null);
+        }
+        for field in LOT_FIELDS {
+            row.remove(*field);
+        }
+        let lot_id = lot_of_row.get(&i);
+        row.insert("lot_id".to_string(), json!(lot_id));
+        row.insert("acquisition_type".to_string(), json!(if lot_id.is_some() { "lot" } else { "purchase" }));
+        row.insert("user_id".to_string(), json!(user_id));
+    }
+    ctx.check()?;
+    supabase::send(
+        session.rest(&client, Method::POST, "inventory").header("Prefer", "return=minimal").json(&rows),
+    )
+    .await?;
+    // The rows are in, so from here on nothing may fail the batch (it would
+    // be imported again).
+    for lot_id in to_allocate {
+        let equal = crate::lots::AllocationStrategy::Equal;
+        if let Err(e) = crate::lots::allocate_and_save(session, &client, &lot_id, &equal, false).await {
+            log::warn!("hotfolder: allocating lot {lot_id}: {e}");
+        }
+    }
+
+    state.pending.lock().unwrap().retain(|b| b.id != batch_id);
+    save_pending(app, state)?;
+    let result = HotfolderResult::of(&batch, rows.len());
+    crate::audit::record(
+        app,
+        "hotfolder_import",
+        None,
+        false,
+        json!({ "file": batch.file, "imported": result.imported, "invalid": result.invalid }),
+    );
+    announce(app, &result);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(columns: &[(&str, &str)]) -> ImportMapping {
+        ImportMapping {
+            name: "haul".to_string(),
+            delimiter: ',',
+            columns: columns.iter().map(|(f, c)| (f.to_string(), c.to_string())).collect(),
+        }
+    }
+
+    const CSV: &str = "Item,Cost,Qty,Box,Box total\n\
+        Levi's 501,,1,BOX-7,£40.00\n\
+        Wool scarf,,2,BOX-7,\n\
+        Nike tee,£6.50,1,,\n\
+        Denim jacket,12,1,BOX-8,\n\
+        Belt,3.5,2,BOX-8,\n";
+
+    #[test]
+    fn lot_columns_are_read_through_the_mapping() {
+        let mapping = mapping(&[
+            ("name", "item"),
+            ("cost", "cost"),
+            ("quantity", "qty"),
+            ("lot", "box"),
+            ("lot_cost", "box total"),
+        ]);
+        let parsed = read_rows(CSV, &mapping).unwrap();
+        assert_eq!((parsed.total, parsed.valid.len()), (5, 5));
+        assert_eq!(parsed.valid[0]["lot"], "BOX-7");
+        assert_eq!(parsed.valid[0]["lot_cost"], 40.0);
+        assert!(!parsed.valid[2].contains_key("lot"));
+
+        let lots = group_lots(&parsed.valid).unwrap();
+        assert_eq!(lots.len(), 2);
+        // BOX-7's total is given; BOX-8's is what its rows cost.
+        assert_eq!(lots["BOX-7"], LotGroup { rows: vec![0, 1], total: 40.0, costed: false });
+        assert_eq!(lots["BOX-8"], LotGroup { rows: vec![3, 4], total: 19.0, costed: true });
+    }
+
+    #[test]
+    fn a_lot_given_two_totals_is_refused() {
+        let csv = "Item,Box,Box total\nA,BOX-1,10\nB,BOX-1,12\n";
+        let parsed = read_rows(csv, &mapping(&[("name", "item"), ("lot", "box"), ("lot_cost", "box total")])).unwrap();
+        assert!(group_lots(&parsed.valid).unwrap_err().contains("BOX-1"));
+
+        let csv = "Item,Box,Box total\nA,BOX-1,ten pounds\n";
+        let parsed = read_rows(csv, &mapping(&[("name", "item"), ("lot", "box"), ("lot_cost", "box total")])).unwrap();
+        assert_eq!(parsed.errors, vec!["row 2: lot_cost \"ten pounds\" isn't an amount"]);
+    }
+
+    #[test]
+    fn files_without_lots_group_nothing() {
+        let parsed = read_rows(CSV, &mapping(&[("name", "item"), ("cost", "cost")])).unwrap();
+        assert!(group_lots(&parsed.valid).unwrap().is_empty());
+        assert!(read_rows(CSV, &mapping(&[("name", "item"), ("lot", "crate")])).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+// ── HTTP cache ─────────────────────────────────────────────────────────────
+// An in-memory revalidation cache for `native_fetch` calls made with
+// `cache: true`, for lookups (price comps, searches) that repeat within a
+// session while the data rarely changes. A GET whose 200 response carries an
+// `ETag` or `Last-Modified` is kept; the next identical request sends
+// `If-None-Match` / `If-Modified-Since`, and a 304 is answered from the
+// stored body with `from_cache: true`. Nothing is served without asking the
+// server first, so a stale body is never returned.
+//
+// Requests are identical when the method, URL and request headers match
+// (conditional headers aside; values are hashed, so credentials aren't kept
+// in the key). Bodies total at most MAX_BYTES, least recently used out
+// first. `Cache-Control: no-store` responses and truncated bodies aren't
+// kept. The cache lives for the session; `clear_http_cache` empties it.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+const MAX_BYTES: usize = 50 * 1024 * 1024;
+
+#[derive(Clone)]
+pub struct CachedResponse {
+    pub status: u16,
+    pub content_type: String,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+struct Entry {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    response: CachedResponse,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct HttpCache {
+    entries: HashMap<String, Entry>,
+    bytes: usize,
+    clock: u64,
+}
+
+static CACHE: OnceLock<Mutex<HttpCache>> = OnceLock::new();
+
+fn cache() -> std::sync::MutexGuard<'static, HttpCache> {
+    CACHE.get_or_init(Default::default).lock().unwrap()
+}
+
+const CONDITIONAL: &[&str] = &["if-none-match", "if-modified-since", "if-match", "if-unmodified-since", "if-range"];
+
+/// Cache key for a request: method, URL and the non-conditional headers.
+pub fn key(method: &str, url: &str, headers: Option<&HashMap<String, String>>) -> String {
+    let mut headers: Vec<(String, &str)> = headers
+        .into_iter()
+        .flatten()
+        .map(|(k, v)| (k.to_ascii_lowercase(), v.as_str()))
+        .filter(|(k, _)| !CONDITIONAL.contains(&k.as_str()))
+        .collect();
+    headers.sort();
+    let mut hasher = Sha256::new();
+    for (k, v) in &headers {
+        hasher.update(k.as_bytes());
+        hasher.update(b":");
+        hasher.update(v.as_bytes());
+        hasher.update(b"\n");
+    }
+    format!("{method} {url} {}", hex::encode(&hasher.finalize()[..12]))
+}
+
+/// Validators to send for `key`, as (header, value) pairs.
+pub fn validators(key: &str) -> Vec<(&'static str, String)> {
+    let cache = cache();
+    let Some(entry) = cache.entries.get(key) else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    if let Some(etag) = &entry.etag {
+        out.push(("If-None-Match", etag.clone()));
+    }
+    if let Some(modified) = &entry.last_modified {
+        out.push(("If-Modified-Since", modified.clone()));
+    }
+    out
+}
+
+/// The stored response for a 304 to `key`.
+fn hit(key: &str) -> Option<CachedResponse> {
+    let mut cache = cache();
+    cache.clock += 1;
+    let clock = cache.clock;
+    let entry = cache.entries.get_mut(key)?;
+    entry.last_used = clock;
+    Some(entry.response.clone())
+}
+
+/// Keep a 200 response if it can be revalidated later.
+fn store(key: String, response: CachedResponse) {
+    let header = |name: &str| response.headers.get(name).cloned();
+    if header("cache-control").is_some_and(|c| c.to_ascii_lowercase().contains("no-store")) {
+        return;
+    }
+    let (etag, last_modified) = (header("etag"), header("last-modified"));
+    if etag.is_none() && last_modified.is_none() || response.body.len() > MAX_BYTES {
+        return;
+    }
+    let mut cache = cache();
+    cache.clock += 1;
+    let entry = Entry { etag, last_modified, last_used: cache.clock, response };
+    cache.bytes += entry.response.body.len();
+    if let Some(old) = cache.entries.insert(key, entry) {
+        cache.bytes -= old.response.body.len();
+    }
+    while cache.bytes > MAX_BYTES {
+        let Some(oldest) = cache.entries.iter().min_by_key(|(_, e)| e.last_used).map(|(k, _)| k.clone()) else {
+            break;
+        };
+        if let Some(evicted) = cache.entries.remove(&oldest) {
+            cache.bytes -= evicted.response.body.len();
+        }
+    }
+}
+
+/// The server's `answer` to a request for `key`, settled against the cache:
+/// a 304 becomes the stored response (`true`: from the cache) and a complete
+/// 200 is stored.
+pub fn settle(key: String, answer: CachedResponse, truncated: bool) -> (CachedResponse, bool) {
+    match (answer.status, hit(&key)) {
+        (304, Some(cached)) => (cached, true),
+        (200, _) if !truncated => {
+            store(key, answer.clone());
+            (answer, false)
+        }
+        _ => (answer, false),
+    }
+}
+
+#[derive(Serialize)]
+pub struct ClearedCache {
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+#[tauri::command]
+pub fn clear_http_cache() -> ClearedCache {
+    let mut cache = cache();
+    let cleared = ClearedCache { entries: cache.entries.len(), bytes: cache.bytes };
+    cache.entries.clear();
+    cache.bytes = 0;
+    cleared
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const ETAG: &str = "\"v1\"";
+    const MODIFIED: &str = "Wed, 14 Oct 2026 09:00:00 GMT";
+
+    /// Serves `/comps` with an ETag and Last-Modified, answering 304 when
+    /// the request carries that ETag, and `/private` the same way but with
+    /// `no-store`. Keeps each request's head, lowercased.
+    async fn revalidating_server() -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let log = log.clone();
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let head = String::from_utf8_lossy(&buf).to_ascii_lowercase();
+                    log.lock().unwrap().push(head.clone());
+                    let store = if head.starts_with("get /private ") { "no-store" } else { "private" };
+                    let reply = if head.contains(&format!("if-none-match: {ETAG}")) {
+                        format!("HTTP/1.1 304 Not Modified\r\nETag: {ETAG}\r\nConnection: close\r\n\r\n")
+                    } else {
+                        let body = "[12.5,14,9.99]";
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                             ETag: {ETAG}\r\nLast-Modified: {MODIFIED}\r\nCache-Control: {store}\r\n\
+                             Connection: close\r\n\r\n{body}",
+                            body.len()
+                        )
+                    };
+                    let _ = stream.write_all(reply.as_bytes()).await;
+                });
+            }
+        });
+        (format!("http://{addr}"), seen)
+    }
+
+    /// A cached GET the way `native_fetch` makes one, as (response, from
+    /// the cache).
+    async fn cached_get(client: &reqwest::Client, url: &str) -> (CachedResponse, bool) {
+        let headers = HashMap::from([("Accept".to_string(), "application/json".to_string())]);
+        let key = key("GET", url, Some(&headers));
+        let validators = validators(&key);
+        let request = crate::FetchRequest {
+            method: reqwest::Method::GET,
+            headers: Some(&headers),
+            validators: &validators,
+            payload: None,
+            timeout: None,
+            max_redirects: 0,
+        };
+        let Ok((resp, _)) = crate::send_following(client, None, request, url.parse().unwrap()).await else {
+            panic!("request to {url} failed");
+        };
+        let answer = CachedResponse {
+            status: resp.status().as_u16(),
+            content_type: resp
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("")
+                .to_string(),
+            headers: crate::header_map(resp.headers()),
+            body: resp.bytes().await.unwrap().to_vec(),
+        };
+        settle(key, answer, false)
+    }
+
+    #[tokio::test]
+    async fn a_304_is_answered_from_the_cached_body() {
+        let (base, seen) = revalidating_server().await;
+        let client = reqwest::Client::new();
+        let url = format!("{base}/comps");
+
+        let (first, from_cache) = cached_get(&client, &url).await;
+        assert_eq!((first.status, from_cache), (200, false));
+        assert_eq!(first.body, b"[12.5,14,9.99]");
+
+        let (second, from_cache) = cached_get(&client, &url).await;
+        assert!(from_cache);
+        assert_eq!(second.status, 200);
+        assert_eq!(second.content_type, "application/json");
+        assert_eq!(second.body, b"[12.5,14,9.99]");
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert!(!seen[0].contains("if-none-match") && !seen[0].contains("if-modified-since"));
+        assert!(seen[1].contains(&format!("if-none-match: {ETAG}")), "{}", seen[1]);
+        assert!(seen[1].contains(&format!("if-modified-since: {}", MODIFIED.to_ascii_lowercase())), "{}", seen[1]);
+    }
+
+    #[tokio::test]
+    async fn no_store_responses_are_not_revalidated() {
+        let (base, seen) = revalidating_server().await;
+        let client = reqwest::Client::new();
+        let url = format!("{base}/private");
+        for _ in 0..2 {
+            let (response, from_cache) = cached_get(&client, &url).await;
+            assert_eq!((response.status, from_cache), (200, false));
+        }
+        assert!(seen.lock().unwrap().iter().all(|head| !head.contains("if-none-match")));
+    }
+}
@@ -0,0 +1,132 @@
+// ── App language & native UI strings ──────────────────────────────────────
+// The app language is kept here so the frontend and the windows Rust builds
+// itself (the Depop login window, its injected status panel) agree on it.
+//
+// Strings come from the embedded locales.json, keyed by language tag and
+// then string key; adding a language means adding an entry there. A lookup
+// tries the exact tag ("es-mx"), then its primary language ("es"), then
+// English, and finally returns the key itself so a missing string shows up
+// rather than breaking the window. `{name}` placeholders are filled by the
+// caller.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, OnceLock};
+use tauri::{Emitter, Manager};
+
+const STORE_NAME: &str = "app_language";
+const FALLBACK_LANGUAGE: &str = "en";
+
+#[derive(Deserialize)]
+struct Locale {
+    /// The language's own name, for pickers.
+    name: String,
+    strings: HashMap<String, String>,
+}
+
+fn locales() -> &'static BTreeMap<String, Locale> {
+    static LOCALES: OnceLock<BTreeMap<String, Locale>> = OnceLock::new();
+    LOCALES.get_or_init(|| serde_json::from_str(include_str!("locales.json")).expect("embedded locales are valid JSON"))
+}
+
+/// `es_MX` and `ES-mx` both become `es-mx`.
+fn normalize(tag: &str) -> String {
+    tag.trim().replace('_', "-").to_ascii_lowercase()
+}
+
+/// Locales to try for `language`, most specific first, English last.
+fn chain(language: &str) -> Vec<&'static Locale> {
+    let primary = language.split('-').next().unwrap_or(language);
+    let locales = locales();
+    let mut out: Vec<&Locale> = Vec::with_capacity(3);
+    for tag in [language, primary, FALLBACK_LANGUAGE] {
+        if let Some(locale) = locales.get(tag) {
+            if !out.iter().any(|l| std::ptr::eq(*l, locale)) {
+                out.push(locale);
+            }
+        }
+    }
+    out
+}
+
+fn lookup(language: &str, key: &str) -> String {
+    chain(language)
+        .into_iter()
+        .find_map(|l| l.strings.get(key))
+        .cloned()
+        .unwrap_or_else(|| key.to_string())
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct LanguageSetting {
+    language: Option<String>,
+}
+
+pub struct LanguageState {
+    setting: Mutex<LanguageSetting>,
+}
+
+impl LanguageState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        LanguageState { setting: Mutex::new(crate::store::load(app, STORE_NAME)) }
+    }
+
+    fn language(&self) -> String {
+        self.setting.lock().unwrap().language.clone().unwrap_or_else(|| FALLBACK_LANGUAGE.to_string())
+    }
+}
+
+/// `key` in the app language.
+pub fn tr(app: &tauri::AppHandle, key: &str) -> String {
+    lookup(&app.state::<LanguageState>().language(), key)
+}
+
+/// `keys` in the app language, keyed by string key, for injected scripts.
+pub fn strings(app: &tauri::AppHandle, keys: &[&str]) -> BTreeMap<String, String> {
+    let language = app.state::<LanguageState>().language();
+    keys.iter().map(|k| (k.to_string(), lookup(&language, k))).collect()
+}
+
+#[derive(Serialize)]
+pub struct AppLanguage {
+    language: String,
+    /// Tag -> the language's own name.
+    available: BTreeMap<String, String>,
+}
+
+fn describe(language: String) -> AppLanguage {
+    let available = locales().iter().map(|(tag, l)| (tag.clone(), l.name.clone())).collect();
+    AppLanguage { language, available }
+}
+
+#[tauri::command]
+pub fn get_app_language(state: tauri::State<'_, LanguageState>) -> AppLanguage {
+    describe(state.language())
+}
+
+/// Set the app language. Regional tags are accepted when their language is
+/// available (`es-mx` uses `es` strings). Emits `app-language-changed` and
+/// retitles open Rust-built windows.
+#[tauri::command]
+pub fn set_app_language(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, LanguageState>,
+    lang: String,
+) -> Result<AppLanguage, String> {
+    let language = normalize(&lang);
+    let primary = language.split('-').next().unwrap_or_default();
+    if !locales().contains_key(&language) && !locales().contains_key(primary) {
+        return Err(format!("No translation for {lang:?}"));
+    }
+    {
+        let mut setting = state.setting.lock().unwrap();
+        setting.language = Some(language.clone());
+        crate::store::save(&app, STORE_NAME, &*setting)?;
+    }
+    if let Some(window) = app.get_webview_window("depop-login") {
+        let _ = window.set_title(&lookup(&language, "depop_login.title"));
+    }
+    let _ = app.emit("app-language-changed", &language);
+    Ok(describe(language))
+}
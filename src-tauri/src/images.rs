@@ -0,0 +1,515 @@
+// ── Image pipeline presets ─────────────────────────────────────────────────
+// Named resize/encode presets ("depop-square", "ebay", ...) and a mapping from
+// marketplace to preset, so callers ask for "photos for Depop" instead of
+// passing dimensions around.
+//
+// Derivatives are written next to the original in a `.fliptools/` folder,
+// named by content hash + preset + options fingerprint, so re-listing the same
+// photo is a cache hit. Every derivative is indexed by preset; changing a
+// preset (or the watermark it uses) deletes only that preset's files.
+
+use crate::metrics::METRICS;
+use crate::request_context::{CancelToken, Finished, RequestContext};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const PRESETS_STORE: &str = "image_presets";
+const INDEX_STORE: &str = "image_derivatives";
+const DERIVATIVE_DIR: &str = ".fliptools";
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AspectMode {
+    /// Scale down to fit inside max_dimension, keeping the aspect ratio.
+    #[default]
+    Fit,
+    /// Fit, then pad to a square on a white background.
+    PadSquare,
+    /// Center-crop to a square.
+    CropSquare,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    Jpeg,
+    Png,
+    /// Lossless WebP; `quality` is ignored.
+    Webp,
+}
+
+impl OutputFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Png => "png",
+            OutputFormat::Webp => "webp",
+        }
+    }
+}
+
+fn default_quality() -> u8 {
+    88
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImageOptions {
+    pub max_dimension: u32,
+    #[serde(default)]
+    pub aspect: AspectMode,
+    pub format: OutputFormat,
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+    #[serde(default)]
+    pub watermark: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PresetSettings {
+    presets: HashMap<String, ImageOptions>,
+    /// marketplace id -> preset name
+    marketplace_presets: HashMap<String, String>,
+    /// PNG stamped bottom-right on presets with `watermark: true`.
+    #[serde(default)]
+    watermark_path: Option<String>,
+}
+
+impl Default for PresetSettings {
+    fn default() -> Self {
+        let presets = HashMap::from([
+            (
+                "depop-square".to_string(),
+                ImageOptions {
+                    max_dimension: 1280,
+                    aspect: AspectMode::PadSquare,
+                    format: OutputFormat::Jpeg,
+                    quality: 90,
+                    watermark: false,
+                },
+            ),
+            (
+                "ebay".to_string(),
+                ImageOptions {
+                    max_dimension: 1600,
+                    aspect: AspectMode::Fit,
+                    format: OutputFormat::Jpeg,
+                    quality: 90,
+                    watermark: false,
+                },
+            ),
+            (
+                "webp".to_string(),
+                ImageOptions {
+                    max_dimension: 2048,
+                    aspect: AspectMode::Fit,
+                    format: OutputFormat::Webp,
+                    quality: default_quality(),
+                    watermark: false,
+                },
+            ),
+        ]);
+        let marketplace_presets = HashMap::from([
+            ("depop".to_string(), "depop-square".to_string()),
+            ("ebay".to_string(), "ebay".to_string()),
+        ]);
+        PresetSettings { presets, marketplace_presets, watermark_path: None }
+    }
+}
+
+pub struct ImagePresetState {
+    settings: Mutex<PresetSettings>,
+    /// preset name -> derivative files written for it
+    index: Mutex<HashMap<String, Vec<String>>>,
+}
+
+impl ImagePresetState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        ImagePresetState {
+            settings: Mutex::new(crate::store::load(app, PRESETS_STORE)),
+            index: Mutex::new(crate::store::load(app, INDEX_STORE)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ProcessedImage {
+    source: String,
+    output: Option<String>,
+    preset: String,
+    /// True when an existing derivative was reused.
+    cached: bool,
+    width: u32,
+    height: u32,
+    error: Option<String>,
+}
+
+fn validate_preset_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err("Preset names may only use letters, digits, '-' and '_'".to_string());
+    }
+    Ok(())
+}
+
+/// Delete every derivative recorded for `preset`.
+fn invalidate(app: &tauri::AppHandle, state: &ImagePresetState, preset: &str) -> Result<(), String> {
+    let mut index = state.index.lock().unwrap();
+    if let Some(files) = index.remove(preset) {
+        for file in files {
+            if let Err(e) = std::fs::remove_file(&file) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("failed to remove stale derivative {file}: {e}");
+                }
+            }
+        }
+        crate::store::save(app, INDEX_STORE, &*index)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_image_presets(state: tauri::State<'_, ImagePresetState>) -> PresetSettings {
+    state.settings.lock().unwrap().clone()
+}
+
+/// Create or replace a preset. Replacing one with different options drops its
+/// cached derivatives; other presets are untouched.
+#[tauri::command]
+pub fn save_image_preset(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ImagePresetState>,
+    name: String,
+    options: ImageOptions,
+) -> Result<(), String> {
+    validate_preset_name(&name)?;
+    if options.max_dimension == 0 {
+        return Err("max_dimension must be greater than 0".to_string());
+    }
+    if !(1..=100).contains(&options.quality) {
+        return Err("quality must be between 1 and 100".to_string());
+    }
+
+    let changed = {
+        let mut settings = state.settings.lock().unwrap();
+        let changed = settings.presets.get(&name) != Some(&options);
+        settings.presets.insert(name.clone(), options);
+        crate::store::save(&app, PRESETS_STORE, &*settings)?;
+        changed
+    };
+    if changed {
+        invalidate(&app, &state, &name)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn remove_image_preset(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ImagePresetState>,
+    name: String,
+) -> Result<(), String> {
+    {
+        let mut settings = state.settings.lock().unwrap();
+        if let Some((marketplace, _)) = settings.marketplace_presets.iter().find(|(_, p)| **p == name) {
+            return Err(format!("Preset '{name}' is still mapped to {marketplace}"));
+        }
+        if settings.presets.remove(&name).is_none() {
+            return Err(format!("No preset '{name}'"));
+        }
+        crate::store::save(&app, PRESETS_STORE, &*settings)?;
+    }
+    invalidate(&app, &state, &name)
+}
+
+/// Map a marketplace to a preset; `preset: None` removes the mapping.
+#[tauri::command]
+pub fn set_marketplace_image_preset(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ImagePresetState>,
+    marketplace: String,
+    preset: Option<String>,
+) -> Result<(), String> {
+    let mut settings = state.settings.lock().unwrap();
+    match preset {
+        Some(preset) => {
+            if !settings.presets.contains_key(&preset) {
+                return Err(format!("No preset '{preset}'"));
+            }
+            settings.marketplace_presets.insert(marketplace, preset);
+        }
+        None => {
+            settings.marketplace_presets.remove(&marketplace);
+        }
+    }
+    crate::store::save(&app, PRESETS_STORE, &*settings)
+}
+
+/// Set (or clear) the watermark image. Invalidates presets that stamp it.
+#[tauri::command]
+pub fn set_image_watermark(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ImagePresetState>,
+    path: Option<String>,
+) -> Result<(), String> {
+    if let Some(p) = &path {
+        image::open(p).map_err(|e| format!("Can't read watermark image: {e}"))?;
+    }
+    let affected: Vec<String> = {
+        let mut settings = state.settings.lock().unwrap();
+        settings.watermark_path = path;
+        crate::store::save(&app, PRESETS_STORE, &*settings)?;
+        settings
+            .presets
+            .iter()
+            .filter(|(_, o)| o.watermark)
+            .map(|(name, _)| name.clone())
+            .collect()
+    };
+    for name in affected {
+        invalidate(&app, &state, &name)?;
+    }
+    Ok(())
+}
+
+fn fit(img: DynamicImage, max: u32) -> DynamicImage {
+    if img.width() > max || img.height() > max {
+        img.resize(max, max, FilterType::Lanczos3)
+    } else {
+        img
+    }
+}
+
+pub fn render(img: DynamicImage, opts: &ImageOptions, watermark: Option<&DynamicImage>) -> DynamicImage {
+    let max = opts.max_dimension;
+    let mut out = match opts.aspect {
+        AspectMode::Fit => fit(img, max),
+        AspectMode::CropSquare => {
+            let side = img.width().min(img.height()).min(max);
+            img.resize_to_fill(side, side, FilterType::Lanczos3)
+        }
+        AspectMode::PadSquare => {
+            let fitted = fit(img, max);
+            let side = fitted.width().max(fitted.height());
+            let mut canvas = RgbaImage::from_pixel(side, side, Rgba([255, 255, 255, 255]));
+            let x = (side - fitted.width()) / 2;
+            let y = (side - fitted.height()) / 2;
+            imageops::overlay(&mut canvas, &fitted.to_rgba8(), x as i64, y as i64);
+            DynamicImage::ImageRgba8(canvas)
+        }
+    };
+
+    if let Some(mark) = watermark {
+        // Stamp at 20% of the image width, inset by 2%.
+        let target_w = (out.width() / 5).max(1);
+        let mark = mark.resize(target_w, out.height(), FilterType::Lanczos3);
+        let margin = (out.width() / 50) as i64;
+        let x = out.width() as i64 - mark.width() as i64 - margin;
+        let y = out.height() as i64 - mark.height() as i64 - margin;
+        let mut canvas = out.to_rgba8();
+        imageops::overlay(&mut canvas, &mark.to_rgba8(), x.max(0), y.max(0));
+        out = DynamicImage::ImageRgba8(canvas);
+    }
+    out
+}
+
+pub fn encode(img: &DynamicImage, opts: &ImageOptions) -> Result<Vec<u8>, String> {
+    let mut buf = Vec::new();
+    let result = match opts.format {
+        OutputFormat::Jpeg => DynamicImage::ImageRgb8(img.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut buf, opts.quality)),
+        OutputFormat::Png => img.write_with_encoder(PngEncoder::new(&mut buf)),
+        OutputFormat::Webp => DynamicImage::ImageRgba8(img.to_rgba8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut buf)),
+    };
+    result.map_err(|e| format!("encode: {e}"))?;
+    Ok(buf)
+}
+
+/// Short fingerprint of everything that affects the output besides the source.
+fn options_fingerprint(opts: &ImageOptions, watermark_path: Option<&str>) -> String {
+    let mut h = Sha256::new();
+    h.update(serde_json::to_vec(opts).unwrap_or_default());
+    if opts.watermark {
+        h.update(watermark_path.unwrap_or("").as_bytes());
+    }
+    hex::encode(&h.finalize()[..4])
+}
+
+fn derivative_path(source: &Path, content_hash: &str, preset: &str, fingerprint: &str, ext: &str) -> PathBuf {
+    source
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(DERIVATIVE_DIR)
+        .join(format!("{content_hash}-{preset}-{fingerprint}.{ext}"))
+}
+
+/// Returns (output path, was cached, width, height).
+fn process_one(
+    source: &str,
+    preset: &str,
+    opts: &ImageOptions,
+    watermark: Option<&DynamicImage>,
+    fingerprint: &str,
+) -> Result<(PathBuf, bool, u32, u32), String> {
+    let bytes = std::fs::read(source).map_err(|e| format!("read: {e}"))?;
+    let content_hash = hex::encode(&Sha256::digest(&bytes)[..8]);
+    let out_path = derivative_path(
+        Path::new(source),
+        &content_hash,
+        preset,
+        fingerprint,
+        opts.format.extension(),
+    );
+
+    if out_path.exists() {
+        if let Ok((w, h)) = image::image_dimensions(&out_path) {
+            return Ok((out_path, true, w, h));
+        }
+    }
+
+    let img = image::load_from_memory(&bytes).map_err(|e| format!("decode: {e}"))?;
+    let rendered = render(img, opts, watermark);
+    let encoded = encode(&rendered, opts)?;
+
+    if let Some(dir) = out_path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("create {}: {e}", dir.display()))?;
+    }
+    let tmp = out_path.with_extension("tmp");
+    std::fs::write(&tmp, encoded).map_err(|e| format!("write: {e}"))?;
+    std::fs::rename(&tmp, &out_path).map_err(|e| format!("write: {e}"))?;
+    let (w, h) = rendered.dimensions();
+    Ok((out_path, false, w, h))
+}
+
+/// Produce derivatives for `paths` with a named preset, or with the preset
+/// mapped to `marketplace` when no name is given (the crosslisting upload path).
+/// Cancellable (see request_context); a cancelled batch removes the
+/// derivatives it had written.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn process_images(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    state: tauri::State<'_, ImagePresetState>,
+    paths: Vec<String>,
+    preset: Option<String>,
+    marketplace: Option<String>,
+    invocation_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<Finished<Vec<ProcessedImage>>, String> {
+    let ctx = RequestContext::begin(&app, &window, "process_images", invocation_id, timeout_ms)?;
+    let result = process(&app, &state, ctx.token(), paths, preset, marketplace).await;
+    ctx.finish(result)
+}
+
+async fn process(
+    app: &tauri::AppHandle,
+    state: &ImagePresetState,
+    token: CancelToken,
+    paths: Vec<String>,
+    preset: Option<String>,
+    marketplace: Option<String>,
+) -> Result<Vec<ProcessedImage>, String> {
+    let (preset, opts, watermark_path) = {
+        let settings = state.settings.lock().unwrap();
+        let name = match (preset, &marketplace) {
+            (Some(name), _) => name,
+            (None, Some(m)) => settings
+                .marketplace_presets
+                .get(m)
+                .cloned()
+                .ok_or_else(|| format!("No image preset mapped to {m}"))?,
+            (None, None) => return Err("Pass a preset name or a marketplace".to_string()),
+        };
+        let opts = settings
+            .presets
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| format!("No preset '{name}'"))?;
+        (name, opts, settings.watermark_path.clone())
+    };
+
+    let watermark = match (opts.watermark, &watermark_path) {
+        (true, Some(p)) => Some(image::open(p).map_err(|e| format!("Can't read watermark image: {e}"))?),
+        (true, None) => return Err(format!("Preset '{preset}' uses a watermark but none is set")),
+        _ => None,
+    };
+    let fingerprint = options_fingerprint(&opts, watermark_path.as_deref());
+
+    let preset_name = preset.clone();
+    let requested = paths.len();
+    let results = tauri::async_runtime::spawn_blocking(move || {
+        paths
+            .into_iter()
+            .take_while(|_| !token.is_cancelled())
+            .map(|source| {
+                let started = std::time::Instant::now();
+                let result = process_one(&source, &preset_name, &opts, watermark.as_ref(), &fingerprint);
+                match &result {
+                    Ok((_, true, _, _)) => METRICS.image_cache_hits.inc(),
+                    Ok(_) => {
+                        METRICS.image_process.since(started);
+                        METRICS.images_processed.inc();
+                    }
+                    Err(_) => METRICS.image_errors.inc(),
+                }
+                match result {
+                    Ok((out, cached, width, height)) => ProcessedImage {
+                        source,
+                        output: Some(out.to_string_lossy().into_owned()),
+                        preset: preset_name.clone(),
+                        cached,
+                        width,
+                        height,
+                        error: None,
+                    },
+                    Err(e) => ProcessedImage {
+                        source,
+                        output: None,
+                        preset: preset_name.clone(),
+                        cached: false,
+                        width: 0,
+                        height: 0,
+                        error: Some(e),
+                    },
+                }
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if results.len() < requested {
+        for output in results.iter().filter(|r| !r.cached).filter_map(|r| r.output.as_ref()) {
+            let _ = std::fs::remove_file(output);
+        }
+        return Err("Cancelled".to_string());
+    }
+
+    let fresh: Vec<String> = results
+        .iter()
+        .filter(|r| !r.cached)
+        .filter_map(|r| r.output.clone())
+        .collect();
+    if !fresh.is_empty() {
+        let mut index = state.index.lock().unwrap();
+        let files = index.entry(preset).or_default();
+        for f in fresh {
+            if !files.contains(&f) {
+                files.push(f);
+            }
+        }
+        crate::store::save(app, INDEX_STORE, &*index)?;
+    }
+
+    Ok(results)
+}
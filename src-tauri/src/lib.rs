@@ -1,158 +1,1072 @@
 use tauri::{Manager, Emitter};
-use tauri_plugin_updater::UpdaterExt;
 use std::sync::Mutex;
 use std::collections::HashMap;
-use serde::Serialize;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use fetch_error::{FetchError, FetchErrorKind};
+
+mod accounts;
+mod aging;
+mod audit;
+mod backup;
+mod capabilities;
+mod changelog;
+mod clipboard;
+mod clones;
+mod compliance;
+mod consignment;
+mod cookie_jar;
+mod credentials;
+mod crosslist;
+mod custom_fields;
+mod depop;
+mod ebay;
+mod fees;
+mod fetch_error;
+mod footer;
+mod hotfolder;
+mod http_cache;
+mod i18n;
+mod images;
+mod links;
+mod listing;
+mod local_api;
+mod lots;
+mod maintenance;
+mod marketplace_limits;
+mod metrics;
+mod netlog;
+mod notifications;
+mod offline_sales;
+mod outage;
+mod payouts;
+mod pdf_layout;
+mod photo_meta;
+mod photo_shoot;
+mod photo_store;
+mod plugins;
+mod price_rules;
+mod price_suggest;
+mod privacy;
+mod pricing_calendar;
+mod proxy;
+mod qr;
+mod rate_limit;
+mod repricing;
+mod request_context;
+mod retention;
+mod shipping;
+mod sku;
+mod sse;
+mod store;
+mod supabase;
+mod system_prefs;
+mod tasks;
+mod tax_summary;
+mod team_sync;
+mod updates;
+mod video;
+mod views;
+mod webhooks;
+mod write_queue;
+mod ws;
+
+/// Seconds since the Unix epoch.
+pub(crate) fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
 
-struct UpdateState {
-    update_available: Mutex<Option<UpdateInfo>>,
+#[tauri::command]
+fn get_current_version() -> String {
+    env!("CARGO_PKG_VERSION").to_string()
 }
 
-#[derive(Clone, Serialize)]
-struct UpdateInfo {
-    current_version: String,
-    new_version: String,
-    notes: String,
+#[derive(Serialize)]
+struct AppStatus {
+    version: String,
+    privacy_mode: bool,
+    /// None until the local API has bound, and while privacy mode is on.
+    local_api_port: Option<u16>,
 }
 
-#[derive(Clone, Serialize)]
-struct UpdateCheckResult {
-    available: bool,
-    current_version: String,
-    new_version: Option<String>,
-    notes: Option<String>,
+/// What the status bar shows.
+#[tauri::command]
+fn get_app_status(local_api: tauri::State<'_, local_api::LocalApiState>) -> AppStatus {
+    let privacy_mode = privacy::enabled();
+    AppStatus {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        privacy_mode,
+        local_api_port: if privacy_mode { None } else { *local_api.port.lock().unwrap() },
+    }
+}
+
+// ── Native HTTP fetch ──────────────────────────────────────────────────────
+/// How `native_fetch` reads the response body.
+#[derive(Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum ResponseType {
+    /// Decoded as UTF-8 text.
+    #[default]
+    Text,
+    /// Raw bytes, returned base64-encoded (images, PDFs, ...).
+    #[serde(alias = "base64")]
+    Binary,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum BodyEncoding {
+    Utf8,
+    Base64,
+}
+
+const DEFAULT_MAX_REDIRECTS: u32 = 5;
+/// Longest `timeout_ms` accepted by `native_fetch`; larger values are clamped.
+const MAX_FETCH_TIMEOUT_MS: u64 = 5 * 60 * 1000;
+/// Bodies past this are cut off unless the caller raises `max_body_bytes`.
+const DEFAULT_MAX_BODY_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Shared `native_fetch` clients, one per redirect limit (the policy is fixed
+/// per client in reqwest; `native_fetch` itself follows redirects by hand), per cookie jar and per proxy, so
+/// bursts of requests share a connection pool and TLS sessions instead of
+/// handshaking each time.
+/// The jar is keyed by address; jars are never dropped while the app runs.
+type FetchClientKey = (u32, Option<usize>, Option<String>);
+static FETCH_CLIENTS: std::sync::OnceLock<Mutex<HashMap<FetchClientKey, reqwest::Client>>> = std::sync::OnceLock::new();
+/// Per-request proxies each get a client; past this many the cache starts over.
+const MAX_FETCH_CLIENTS: usize = 32;
+
+fn fetch_client(
+    max_redirects: u32,
+    cookies: Option<&std::sync::Arc<cookie_jar::CookieJar>>,
+    proxy: Option<&str>,
+) -> Result<reqwest::Client, String> {
+    let key = (max_redirects, cookies.map(|jar| std::sync::Arc::as_ptr(jar) as usize), proxy.map(str::to_string));
+    let mut clients = FETCH_CLIENTS.get_or_init(Default::default).lock().unwrap();
+    if let Some(client) = clients.get(&key) {
+        return Ok(client.clone());
+    }
+    if clients.len() >= MAX_FETCH_CLIENTS {
+        clients.clear();
+    }
+    let redirect = match max_redirects {
+        0 => reqwest::redirect::Policy::none(),
+        n => reqwest::redirect::Policy::limited(n as usize),
+    };
+    let mut builder = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(20))
+        .gzip(true)
+        .deflate(true)
+        .brotli(true)
+        .redirect(redirect);
+    if let Some(jar) = cookies {
+        builder = builder.cookie_provider(jar.clone());
+    }
+    if let Some(url) = proxy {
+        builder = builder.proxy(proxy::reqwest_proxy(url)?);
+    }
+    let client = builder.build().map_err(|e| format!("client build: {}", e))?;
+    clients.insert(key, client.clone());
+    Ok(client)
 }
 
+#[derive(Serialize)]
+struct NativeFetchResponse {
+    status: u16,
+    content_type: String,
+    /// Response headers by lowercase name; repeated headers are joined with ", ".
+    headers: HashMap<String, String>,
+    /// Always empty for HEAD requests, and with `parse_json`.
+    body: String,
+    /// The parsed body with `parse_json`; None for an empty body.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body_json: Option<serde_json::Value>,
+    body_encoding: BodyEncoding,
+    /// The body stopped at `max_body_bytes`; empty when Content-Length was
+    /// already over the limit.
+    truncated: bool,
+    /// A 304 was answered from the HTTP cache (see http_cache).
+    from_cache: bool,
+    /// Time spent waiting for the host's rate limit before sending.
+    rate_limit_wait_ms: u64,
+    /// Where the response came from, after redirects.
+    final_url: String,
+    /// Each URL that answered with a redirect, in order; empty when the
+    /// first response was the final one.
+    redirect_chain: Vec<String>,
+}
+
+/// Headers left off requests redirected to another origin.
+const CROSS_ORIGIN_DROPPED: &[&str] = &["authorization", "cookie", "proxy-authorization"];
+
+/// `method` is GET (default), POST, PUT, DELETE, PATCH, HEAD or OPTIONS;
+/// anything else is rejected rather than sent as a GET.
+/// Binary request bodies go in `body_base64` instead of `body`; with
+/// `response_type: "binary"` (or "base64") the response body comes back
+/// base64-encoded, with `body_encoding: "base64"`.
+/// `priority` marks requests a user is waiting on; they jump ahead of
+/// background traffic to the same host (see rate_limit).
+/// `timeout_ms` overrides the 20s default for this request, up to 5
+/// minutes; leaving it out keeps the default.
+/// `proxy` sends this request through a different proxy than the one set
+/// with `set_proxy`.
+/// Bodies are read up to `max_body_bytes` (default 10MB) and the rest is
+/// dropped, with `truncated` set.
+/// `cache: true` revalidates GETs against the HTTP cache (http_cache.rs).
+/// With `parse_json` the body is parsed here and returned as `body_json`
+/// instead of `body`; a body that isn't JSON fails with `invalid_json` and
+/// the start of the body.
+/// Up to `max_redirects` (default 5) redirects are followed; `final_url` and
+/// `redirect_chain` say where they led, e.g. to spot a bounce to a login page.
+/// `use_cookies` shares the default cookie jar between calls; `session`
+/// uses a separate named jar instead (cookie_jar.rs).
+/// Failures reject with a `FetchError` (fetch_error.rs) saying what kind of
+/// failure it was.
 #[tauri::command]
-async fn check_for_update(app: tauri::AppHandle) -> Result<UpdateCheckResult, String> {
-    let updater = app.updater().map_err(|e| e.to_string())?;
-
-    match updater.check().await {
-        Ok(Some(update)) => {
-            let info = UpdateInfo {
-                current_version: update.current_version.to_string(),
-                new_version: update.version.clone(),
-                notes: update.body.clone().unwrap_or_default(),
-            };
+#[allow(clippy::too_many_arguments)]
+async fn native_fetch(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    rate_limits: tauri::State<'_, rate_limit::RateLimitState>,
+    cookies: tauri::State<'_, cookie_jar::CookieJarState>,
+    proxy_state: tauri::State<'_, proxy::ProxyState>,
+    url: String,
+    method: Option<String>,
+    headers: Option<HashMap<String, String>>,
+    body: Option<String>,
+    body_base64: Option<String>,
+    response_type: Option<ResponseType>,
+    priority: Option<rate_limit::Priority>,
+    timeout_ms: Option<u64>,
+    max_redirects: Option<u32>,
+    use_cookies: Option<bool>,
+    request_id: Option<String>,
+    proxy: Option<String>,
+    max_body_bytes: Option<u64>,
+    parse_json: Option<bool>,
+    cache: Option<bool>,
+    session: Option<String>,
+) -> Result<NativeFetchResponse, FetchError> {
+    if timeout_ms == Some(0) {
+        return Err(FetchError::other("timeout_ms must be greater than 0", &url));
+    }
+    let max_body_bytes = max_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES);
+    let binary_body = match body_base64 {
+        Some(_) if body.is_some() => return Err(FetchError::other("Pass either body or body_base64, not both", &url)),
+        Some(encoded) => Some(
+            base64::engine::general_purpose::STANDARD
+                .decode(encoded.trim())
+                .map_err(|e| FetchError::other(format!("body_base64: {e}"), &url))?,
+        ),
+        None => None,
+    };
+    let response_type = response_type.unwrap_or_default();
+    let parse_json = parse_json.unwrap_or(false);
+    if parse_json && response_type == ResponseType::Binary {
+        return Err(FetchError::other("parse_json needs a text response, not binary", &url));
+    }
+    // With redirects off (max_redirects: 0) the 3xx and its Location header
+    // come back to the caller like any other response.
+    let jar = cookies.for_request(use_cookies, session.as_deref()).map_err(|e| FetchError::other(e, &url))?;
+    let proxy = match proxy {
+        Some(proxy_url) => Some(proxy::parse(&proxy_url).map_err(|e| FetchError::other(e, &url))?),
+        None => proxy_state.url(),
+    };
+    // Redirects are followed below rather than by reqwest, to record the chain.
+    let max_redirects = max_redirects.unwrap_or(DEFAULT_MAX_REDIRECTS);
+    let client = fetch_client(0, jar.as_ref(), proxy.as_deref()).map_err(|e| FetchError::other(e, &url))?;
+    let target = reqwest::Url::parse(&url).map_err(|e| FetchError::other(format!("invalid URL: {e}"), &url))?;
+
+    let (method_str, http_method) = fetch_method(method.as_deref()).map_err(|e| FetchError::other(e, &url))?;
+    let is_head = http_method == reqwest::Method::HEAD;
+    let cache_key = (cache.unwrap_or(false) && http_method == reqwest::Method::GET)
+        .then(|| http_cache::key(&method_str, &url, headers.as_ref()));
+    // The caller's own conditional headers win over the cache's.
+    let validators: Vec<(&str, String)> = cache_key
+        .as_deref()
+        .map(http_cache::validators)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(name, _)| !headers.iter().flatten().any(|(k, _)| k.eq_ignore_ascii_case(name)))
+        .collect();
+
+    let mut log_entry = netlog::NetEntry {
+        started_ms: netlog::unix_millis(),
+        duration_ms: 0,
+        method: method_str.clone(),
+        url: url.clone(),
+        request_headers: netlog::redact_headers(
+            headers.iter().flatten().map(|(k, v)| (k.as_str(), v.as_str())),
+        ),
+        request_body: match &binary_body {
+            Some(bytes) => netlog::NETLOG.body(Some(&format!("<{} bytes binary>", bytes.len()))),
+            None => netlog::NETLOG.body(body.as_deref()),
+        },
+        status: 0,
+        response_headers: Vec::new(),
+        response_body: None,
+        response_size: None,
+        mime_type: String::new(),
+        error: None,
+    };
 
-            if let Some(state) = app.try_state::<UpdateState>() {
-                *state.update_available.lock().unwrap() = Some(info.clone());
-            }
+    let payload = match body {
+        Some(b) => Some(b.into_bytes()),
+        None => binary_body,
+    };
 
-            Ok(UpdateCheckResult {
-                available: true,
-                current_version: info.current_version,
-                new_version: Some(info.new_version),
-                notes: Some(info.notes),
-            })
+    // Registered last so every early return above leaves nothing behind; the
+    // context unregisters itself when this function returns.
+    let ctx = request_id
+        .map(|id| request_context::RequestContext::track(&app, &window, "native_fetch", id))
+        .transpose()
+        .map_err(|e| FetchError::other(e, &url))?;
+    let ctx = ctx.as_ref();
+    let rate_limit_wait = unless_cancelled(ctx, rate_limits.acquire(&url, priority.unwrap_or_default()))
+        .await
+        .map_err(|e| FetchError::new(FetchErrorKind::Cancelled, e, &url))?;
+    let started = std::time::Instant::now();
+    let request = FetchRequest {
+        method: http_method,
+        headers: headers.as_ref(),
+        validators: &validators,
+        payload,
+        timeout: timeout_ms.map(|ms| std::time::Duration::from_millis(ms.min(MAX_FETCH_TIMEOUT_MS))),
+        max_redirects,
+    };
+    let (resp, redirect_chain) = match send_following(&client, ctx, request, target).await {
+        Ok(sent) => sent,
+        Err(SendError::Cancelled(cancelled)) => return Err(record_cancelled(log_entry, started, cancelled, &url)),
+        Err(SendError::Request(e, target)) => {
+            metrics::METRICS.native_fetch_errors.inc();
+            let error = FetchError::from_reqwest("request", &e, &url);
+            outage::record_error(target.as_str(), error.kind);
+            log_entry.duration_ms = started.elapsed().as_millis() as u64;
+            log_entry.error = Some(error.message.clone());
+            netlog::NETLOG.record(log_entry);
+            return Err(error);
+        }
+        Err(SendError::TooManyRedirects(next)) => {
+            let error = FetchError::new(
+                FetchErrorKind::TooManyRedirects,
+                format!("more than {max_redirects} redirects, last to {next}"),
+                &url,
+            );
+            log_entry.duration_ms = started.elapsed().as_millis() as u64;
+            log_entry.error = Some(error.message.clone());
+            netlog::NETLOG.record(log_entry);
+            return Err(error);
         }
-        Ok(None) => {
-            let current = env!("CARGO_PKG_VERSION").to_string();
-            Ok(UpdateCheckResult {
-                available: false,
-                current_version: current,
-                new_version: None,
-                notes: None,
+    };
+    let final_url = resp.url().to_string();
+    let mut status = resp.status().as_u16();
+    outage::record_status(&final_url, status);
+    let mut content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let mut response_headers = header_map(resp.headers());
+    log_entry.status = status;
+    log_entry.response_headers = netlog::response_headers(resp.headers());
+    log_entry.mime_type = content_type.clone();
+    let read_body = async {
+        if is_head {
+            Ok((Vec::new(), false))
+        } else {
+            read_limited(resp, max_body_bytes).await
+        }
+    };
+    let body = match unless_cancelled(ctx, read_body).await {
+        Ok(body) => body,
+        Err(cancelled) => return Err(record_cancelled(log_entry, started, cancelled, &url)),
+    };
+    log_entry.duration_ms = started.elapsed().as_millis() as u64;
+    let (mut bytes, mut truncated) = match body {
+        Ok(body) => body,
+        Err(e) => {
+            metrics::METRICS.native_fetch_errors.inc();
+            let error = FetchError::from_reqwest("body", &e, &url);
+            log_entry.error = Some(error.message.clone());
+            netlog::NETLOG.record(log_entry);
+            return Err(error);
+        }
+    };
+    metrics::METRICS.native_fetch.since(started);
+    // The log keeps what came over the wire, a bare 304 included.
+    let size = bytes.len();
+    log_entry.response_size = Some(size as u64);
+    log_entry.response_body = match response_type {
+        ResponseType::Text => netlog::NETLOG.body(Some(&decode_text(&bytes, &content_type))),
+        ResponseType::Binary => netlog::NETLOG.body(Some(&format!("<{size} bytes binary>"))),
+    };
+    netlog::NETLOG.record(log_entry);
+
+    let mut from_cache = false;
+    if let Some(key) = cache_key {
+        let answer = http_cache::CachedResponse { status, content_type, headers: response_headers, body: bytes };
+        let (settled, hit) = http_cache::settle(key, answer, truncated);
+        http_cache::CachedResponse { status, content_type, headers: response_headers, body: bytes } = settled;
+        truncated &= !hit;
+        from_cache = hit;
+    }
+    let (body, body_encoding) = encode_body(&bytes, &content_type, response_type);
+
+    let (body, body_json) = if parse_json && !body.trim().is_empty() {
+        match serde_json::from_str(&body) {
+            Ok(json) => (String::new(), Some(json)),
+            Err(e) => return Err(FetchError::invalid_json(&e, &body, truncated, status, &url)),
+        }
+    } else {
+        (body, None)
+    };
+    Ok(NativeFetchResponse {
+        status,
+        content_type,
+        headers: response_headers,
+        body,
+        body_json,
+        body_encoding,
+        truncated,
+        from_cache,
+        rate_limit_wait_ms: rate_limit_wait.as_millis() as u64,
+        final_url,
+        redirect_chain,
+    })
+}
+
+/// `method` for `native_fetch`, uppercased, as (name, method).
+fn fetch_method(method: Option<&str>) -> Result<(String, reqwest::Method), String> {
+    let name = method.unwrap_or("GET").trim().to_uppercase();
+    let method = match name.as_str() {
+        "GET" => reqwest::Method::GET,
+        "POST" => reqwest::Method::POST,
+        "PUT" => reqwest::Method::PUT,
+        "DELETE" => reqwest::Method::DELETE,
+        "PATCH" => reqwest::Method::PATCH,
+        "HEAD" => reqwest::Method::HEAD,
+        "OPTIONS" => reqwest::Method::OPTIONS,
+        other => return Err(format!("unsupported method: {other}")),
+    };
+    Ok((name, method))
+}
+
+/// Response headers by lowercase name, repeated ones joined with ", ".
+fn header_map(headers: &reqwest::header::HeaderMap) -> HashMap<String, String> {
+    let mut out: HashMap<String, String> = HashMap::new();
+    for (name, value) in headers {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        out.entry(name.as_str().to_string())
+            .and_modify(|v| {
+                v.push_str(", ");
+                v.push_str(&value);
             })
+            .or_insert_with(|| value.into_owned());
+    }
+    out
+}
+
+/// Read at most `limit` bytes of the body, and whether more was left. A
+/// Content-Length over the limit reads nothing.
+async fn read_limited(mut resp: reqwest::Response, limit: u64) -> Result<(Vec<u8>, bool), reqwest::Error> {
+    if resp.content_length().is_some_and(|len| len > limit) {
+        return Ok((Vec::new(), true));
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = resp.chunk().await? {
+        let room = (limit as usize).saturating_sub(bytes.len());
+        if chunk.len() > room {
+            bytes.extend_from_slice(&chunk[..room]);
+            return Ok((bytes, true));
         }
-        Err(e) => Err(e.to_string()),
+        bytes.extend_from_slice(&chunk);
     }
+    Ok((bytes, false))
 }
 
-#[tauri::command]
-async fn install_update(app: tauri::AppHandle, window: tauri::Window) -> Result<(), String> {
-    let updater = app.updater().map_err(|e| e.to_string())?;
+/// Decode a text body in the charset its Content-Type names (UTF-8 when it
+/// names none), as reqwest's `text()` would.
+fn decode_text(bytes: &[u8], content_type: &str) -> String {
+    let encoding = content_type
+        .split(';')
+        .filter_map(|param| param.trim().split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .and_then(|(_, label)| encoding_rs::Encoding::for_label(label.trim().trim_matches('"').as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    encoding.decode(bytes).0.into_owned()
+}
 
-    let update = updater.check().await.map_err(|e| e.to_string())?;
+/// The body as `native_fetch` returns it: text, or base64 of the exact bytes.
+fn encode_body(bytes: &[u8], content_type: &str, response_type: ResponseType) -> (String, BodyEncoding) {
+    match response_type {
+        ResponseType::Text => (decode_text(bytes, content_type), BodyEncoding::Utf8),
+        ResponseType::Binary => (base64::engine::general_purpose::STANDARD.encode(bytes), BodyEncoding::Base64),
+    }
+}
 
-    if let Some(update) = update {
-        let window_clone = window.clone();
+/// The error a `native_fetch` cancelled through `cancel_fetch` returns.
+const FETCH_CANCELLED: &str = "cancelled";
+
+/// Await `fut`, or give up with FETCH_CANCELLED if `ctx` is cancelled first.
+/// One `native_fetch` request, before redirects.
+struct FetchRequest<'a> {
+    method: reqwest::Method,
+    headers: Option<&'a HashMap<String, String>>,
+    /// Conditional headers from the cache, sent on the first hop only.
+    validators: &'a [(&'a str, String)],
+    payload: Option<Vec<u8>>,
+    timeout: Option<std::time::Duration>,
+    max_redirects: u32,
+}
 
-        update.download_and_install(
-            move |downloaded, total| {
-                let progress = if let Some(total) = total {
-                    if total > 0 {
-                        (downloaded as f64 / total as f64 * 100.0) as u32
-                    } else {
-                        0
-                    }
-                } else {
-                    0
-                };
-                let _ = window_clone.emit("update-progress", progress);
-            },
-            || {}
-        ).await.map_err(|e| e.to_string())?;
+enum SendError {
+    Cancelled(String),
+    /// The request to this URL failed.
+    Request(reqwest::Error, reqwest::Url),
+    /// The redirect that went over the limit, to this URL.
+    TooManyRedirects(reqwest::Url),
+}
 
-        app.restart();
+/// Send `request` to `target`, following redirects as browsers do: a 303
+/// (and a 301/302 after a POST) turns into a body-less GET, while 307/308
+/// repeat the method and body. Credentials don't follow a redirect to
+/// another origin. Returns the final response and the URLs redirected from.
+async fn send_following(
+    client: &reqwest::Client,
+    ctx: Option<&request_context::RequestContext>,
+    request: FetchRequest<'_>,
+    mut target: reqwest::Url,
+) -> Result<(reqwest::Response, Vec<String>), SendError> {
+    let FetchRequest { mut method, headers, validators, mut payload, timeout, max_redirects } = request;
+    let origin = target.origin();
+    let is_head = method == reqwest::Method::HEAD;
+    let mut redirect_chain = Vec::new();
+    loop {
+        let same_origin = target.origin() == origin;
+        let mut req = client.request(method.clone(), target.clone());
+        if let Some(timeout) = timeout {
+            req = req.timeout(timeout);
+        }
+        for (k, v) in headers.into_iter().flatten() {
+            // Credentials don't follow a redirect to another site.
+            if same_origin || !CROSS_ORIGIN_DROPPED.contains(&k.to_ascii_lowercase().as_str()) {
+                req = req.header(k.as_str(), v.as_str());
+            }
+        }
+        if redirect_chain.is_empty() {
+            for (name, value) in validators {
+                req = req.header(*name, value.as_str());
+            }
+        }
+        if let Some(bytes) = &payload {
+            req = req.body(bytes.clone());
+        }
+        let resp = match unless_cancelled(ctx, req.send()).await {
+            Ok(Ok(resp)) => resp,
+            Ok(Err(e)) => return Err(SendError::Request(e, target)),
+            Err(cancelled) => return Err(SendError::Cancelled(cancelled)),
+        };
+        let next = resp
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|location| resp.url().join(location).ok())
+            .filter(|_| max_redirects > 0 && matches!(resp.status().as_u16(), 301 | 302 | 303 | 307 | 308));
+        let Some(next) = next else {
+            return Ok((resp, redirect_chain));
+        };
+        if redirect_chain.len() as u32 >= max_redirects {
+            return Err(SendError::TooManyRedirects(next));
+        }
+        // Browsers turn a redirected POST into a GET for all but 307/308.
+        let status = resp.status().as_u16();
+        if (status == 303 && !is_head) || (matches!(status, 301 | 302) && method == reqwest::Method::POST) {
+            method = reqwest::Method::GET;
+            payload = None;
+        }
+        redirect_chain.push(std::mem::replace(&mut target, next).to_string());
     }
+}
 
-    Ok(())
+async fn unless_cancelled<F: std::future::Future>(
+    ctx: Option<&request_context::RequestContext>,
+    fut: F,
+) -> Result<F::Output, String> {
+    match ctx {
+        Some(ctx) => ctx.run(fut).await.map_err(|_| FETCH_CANCELLED.to_string()),
+        None => Ok(fut.await),
+    }
 }
 
-#[tauri::command]
-fn get_current_version() -> String {
-    env!("CARGO_PKG_VERSION").to_string()
+fn record_cancelled(mut entry: netlog::NetEntry, started: std::time::Instant, error: String, url: &str) -> FetchError {
+    entry.duration_ms = started.elapsed().as_millis() as u64;
+    entry.error = Some(error.clone());
+    netlog::NETLOG.record(entry);
+    FetchError::new(FetchErrorKind::Cancelled, error, url)
 }
 
-#[tauri::command]
-fn get_changelog() -> String {
-    include_str!("../../CHANGELOG.md").to_string()
+/// Streamed responses time out after this unless `timeout_ms` says otherwise.
+const STREAM_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+/// Bytes between two "fetch-progress" events.
+const PROGRESS_STEP: u64 = 256 * 1024;
+
+#[derive(Clone, Serialize)]
+struct FetchProgress<'a> {
+    request_id: &'a str,
+    downloaded: u64,
+    /// From Content-Length; None when the server didn't say.
+    total: Option<u64>,
 }
 
-// ── Native HTTP fetch ──────────────────────────────────────────────────────
 #[derive(Serialize)]
-struct NativeFetchResponse {
+struct StreamedFetchResponse {
     status: u16,
     content_type: String,
+    headers: HashMap<String, String>,
+    /// The decoded body; empty when it went to `dest`.
     body: String,
+    /// Where the body was written, when `dest` was given.
+    saved_to: Option<String>,
+    bytes: u64,
+    /// The in-memory body stopped at `max_body_bytes`. Never set with `dest`.
+    truncated: bool,
+    rate_limit_wait_ms: u64,
 }
 
+/// `native_fetch` for large bodies: the response is read chunk by chunk,
+/// emitting "fetch-progress" with `request_id` as it goes, and either
+/// collected into `body` (up to `max_body_bytes`, default 10MB) or written to
+/// `dest` (no limit; a failed or cancelled download deletes the file).
+/// `timeout_ms` covers the whole transfer and defaults to 10 minutes.
+/// Cancellable with `cancel_fetch(request_id)`.
 #[tauri::command]
-async fn native_fetch(
+#[allow(clippy::too_many_arguments)]
+async fn native_fetch_stream(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    rate_limits: tauri::State<'_, rate_limit::RateLimitState>,
+    cookies: tauri::State<'_, cookie_jar::CookieJarState>,
+    proxy_state: tauri::State<'_, proxy::ProxyState>,
     url: String,
+    request_id: String,
     method: Option<String>,
     headers: Option<HashMap<String, String>>,
     body: Option<String>,
-) -> Result<NativeFetchResponse, String> {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(20))
-        .gzip(true)
-        .deflate(true)
-        .brotli(true)
-        .redirect(reqwest::redirect::Policy::limited(5))
-        .build()
-        .map_err(|e| format!("client build: {}", e))?;
-
-    let method_str = method.as_deref().unwrap_or("GET").to_uppercase();
-    let mut req = match method_str.as_str() {
-        "POST" => client.post(&url),
-        "PUT"  => client.put(&url),
-        _      => client.get(&url),
+    priority: Option<rate_limit::Priority>,
+    timeout_ms: Option<u64>,
+    use_cookies: Option<bool>,
+    proxy: Option<String>,
+    max_body_bytes: Option<u64>,
+    dest: Option<String>,
+    session: Option<String>,
+) -> Result<StreamedFetchResponse, FetchError> {
+    if timeout_ms == Some(0) {
+        return Err(FetchError::other("timeout_ms must be greater than 0", &url));
+    }
+    let max_body_bytes = max_body_bytes.unwrap_or(DEFAULT_MAX_BODY_BYTES);
+    let jar = cookies.for_request(use_cookies, session.as_deref()).map_err(|e| FetchError::other(e, &url))?;
+    let proxy = match proxy {
+        Some(proxy_url) => Some(proxy::parse(&proxy_url).map_err(|e| FetchError::other(e, &url))?),
+        None => proxy_state.url(),
     };
-
-    if let Some(hdrs) = headers {
-        for (k, v) in &hdrs {
-            req = req.header(k.as_str(), v.as_str());
-        }
+    let client =
+        fetch_client(DEFAULT_MAX_REDIRECTS, jar.as_ref(), proxy.as_deref()).map_err(|e| FetchError::other(e, &url))?;
+    let (method_str, http_method) = fetch_method(method.as_deref()).map_err(|e| FetchError::other(e, &url))?;
+    let timeout = timeout_ms.map_or(STREAM_TIMEOUT, std::time::Duration::from_millis);
+    let mut req = client.request(http_method, &url).timeout(timeout);
+    for (k, v) in headers.iter().flatten() {
+        req = req.header(k.as_str(), v.as_str());
     }
 
+    let mut log_entry = netlog::NetEntry {
+        started_ms: netlog::unix_millis(),
+        duration_ms: 0,
+        method: method_str,
+        url: url.clone(),
+        request_headers: netlog::redact_headers(
+            headers.iter().flatten().map(|(k, v)| (k.as_str(), v.as_str())),
+        ),
+        request_body: netlog::NETLOG.body(body.as_deref()),
+        status: 0,
+        response_headers: Vec::new(),
+        response_body: None,
+        response_size: None,
+        mime_type: String::new(),
+        error: None,
+    };
     if let Some(b) = body {
         req = req.body(b);
     }
 
-    let resp = req.send().await.map_err(|e| format!("request: {}", e))?;
+    let ctx = request_context::RequestContext::track(&app, &window, "native_fetch_stream", request_id.clone())
+        .map_err(|e| FetchError::other(e, &url))?;
+    let rate_limit_wait = ctx
+        .run(rate_limits.acquire(&url, priority.unwrap_or_default()))
+        .await
+        .map_err(|_| FetchError::new(FetchErrorKind::Cancelled, FETCH_CANCELLED, &url))?;
+    let started = std::time::Instant::now();
+    let mut resp = match ctx.run(req.send()).await {
+        Ok(Ok(resp)) => resp,
+        Err(_) => return Err(record_cancelled(log_entry, started, FETCH_CANCELLED.to_string(), &url)),
+        Ok(Err(e)) => {
+            metrics::METRICS.native_fetch_errors.inc();
+            let error = FetchError::from_reqwest("request", &e, &url);
+            outage::record_error(&url, error.kind);
+            log_entry.duration_ms = started.elapsed().as_millis() as u64;
+            log_entry.error = Some(error.message.clone());
+            netlog::NETLOG.record(log_entry);
+            return Err(error);
+        }
+    };
     let status = resp.status().as_u16();
+    outage::record_status(&url, status);
     let content_type = resp
         .headers()
         .get(reqwest::header::CONTENT_TYPE)
         .and_then(|v| v.to_str().ok())
         .unwrap_or("")
         .to_string();
-    let body = resp.text().await.map_err(|e| format!("body: {}", e))?;
+    let response_headers = header_map(resp.headers());
+    let total = resp.content_length();
+    log_entry.status = status;
+    log_entry.response_headers = netlog::response_headers(resp.headers());
+    log_entry.mime_type = content_type.clone();
+
+    let mut file = match &dest {
+        Some(dest) => {
+            let path = std::path::Path::new(dest);
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .map_err(|e| FetchError::other(format!("create dir: {e}"), &url))?;
+            }
+            let file = tokio::fs::File::create(path)
+                .await
+                .map_err(|e| FetchError::other(format!("open {dest}: {e}"), &url))?;
+            Some(file)
+        }
+        None => None,
+    };
+    let progress = |downloaded| {
+        let _ = app.emit("fetch-progress", FetchProgress { request_id: &request_id, downloaded, total });
+    };
+    progress(0);
+
+    let mut bytes = Vec::new();
+    let mut downloaded = 0u64;
+    let mut announced = 0u64;
+    let mut truncated = false;
+    let streamed: Result<(), FetchError> = async {
+        loop {
+            let chunk = match ctx.run(resp.chunk()).await {
+                Ok(Ok(Some(chunk))) => chunk,
+                Ok(Ok(None)) => return Ok(()),
+                Ok(Err(e)) => return Err(FetchError::from_reqwest("body", &e, &url)),
+                Err(_) => return Err(FetchError::new(FetchErrorKind::Cancelled, FETCH_CANCELLED, &url)),
+            };
+            match file.as_mut() {
+                Some(file) => {
+                    file.write_all(&chunk).await.map_err(|e| FetchError::other(format!("write: {e}"), &url))?
+                }
+                None => {
+                    let room = max_body_bytes.saturating_sub(bytes.len() as u64) as usize;
+                    bytes.extend_from_slice(&chunk[..chunk.len().min(room)]);
+                    if chunk.len() > room {
+                        truncated = true;
+                    }
+                }
+            }
+            downloaded += chunk.len() as u64;
+            if truncated {
+                return Ok(());
+            }
+            if downloaded - announced >= PROGRESS_STEP {
+                announced = downloaded;
+                progress(downloaded);
+            }
+        }
+    }
+    .await;
+    let streamed = match (streamed, file.as_mut()) {
+        (Ok(()), Some(file)) => file.flush().await.map_err(|e| FetchError::other(format!("write: {e}"), &url)),
+        (result, _) => result,
+    };
+    drop(file);
+    log_entry.duration_ms = started.elapsed().as_millis() as u64;
+    log_entry.response_size = Some(downloaded);
+    if let Err(error) = streamed {
+        if error.kind != FetchErrorKind::Cancelled {
+            metrics::METRICS.native_fetch_errors.inc();
+        }
+        if let Some(dest) = &dest {
+            let _ = tokio::fs::remove_file(dest).await;
+        }
+        log_entry.error = Some(error.message.clone());
+        netlog::NETLOG.record(log_entry);
+        return Err(error);
+    }
+    metrics::METRICS.native_fetch.since(started);
+    log_entry.response_body = netlog::NETLOG.body(Some(&match &dest {
+        Some(dest) => format!("<{downloaded} bytes streamed to {dest}>"),
+        None => format!("<{downloaded} bytes streamed>"),
+    }));
+    netlog::NETLOG.record(log_entry);
+    if announced != downloaded {
+        progress(downloaded);
+    }
+
+    Ok(StreamedFetchResponse {
+        status,
+        content_type: content_type.clone(),
+        headers: response_headers,
+        body: if dest.is_some() { String::new() } else { decode_text(&bytes, &content_type) },
+        saved_to: dest,
+        bytes: downloaded,
+        truncated,
+        rate_limit_wait_ms: rate_limit_wait.as_millis() as u64,
+    })
+}
+
+const DOWNLOAD_MAX_REDIRECTS: u32 = 5;
+const DOWNLOAD_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+#[derive(Serialize)]
+struct NativeDownloadResult {
+    path: String,
+    bytes: u64,
+    /// Bytes that were already on disk and kept (0 for a fresh download).
+    resumed_from: u64,
+    sha256: String,
+}
+
+#[derive(Clone, Serialize)]
+struct DownloadProgress<'a> {
+    url: &'a str,
+    downloaded: u64,
+    total: Option<u64>,
+}
 
-    Ok(NativeFetchResponse { status, content_type, body })
+/// Parse "bytes <start>-<end>/<total|*>" into (start, total).
+fn parse_content_range(value: &str) -> Option<(u64, Option<u64>)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let start = range.split_once('-')?.0.trim().parse().ok()?;
+    Some((start, total.trim().parse().ok()))
+}
+
+/// Total from a 416's "bytes */<total>".
+fn unsatisfied_range_total(value: &str) -> Option<u64> {
+    value.strip_prefix("bytes */")?.trim().parse().ok()
+}
+
+async fn file_sha256(path: &std::path::Path) -> Result<String, String> {
+    use sha2::{Digest, Sha256};
+    let mut file = tokio::fs::File::open(path).await.map_err(|e| format!("open: {}", e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).await.map_err(|e| format!("read: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Stream `url` to `dest`. With `resume`, a partial file at `dest` is
+/// continued with a Range request; servers that ignore Range (200) get a fresh
+/// download instead. `expected_sha256` is checked once the file is complete —
+/// a mismatch deletes the file so the next attempt starts clean. Cancellable
+/// (see request_context); a cancelled download deletes its file unless
+/// `resume` was set, in which case the partial file is kept to continue.
+/// Uses the shared fetch client, so the configured proxy applies.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn native_download(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    proxy_state: tauri::State<'_, proxy::ProxyState>,
+    url: String,
+    dest: String,
+    headers: Option<HashMap<String, String>>,
+    resume: Option<bool>,
+    expected_sha256: Option<String>,
+    invocation_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<request_context::Finished<NativeDownloadResult>, String> {
+    let ctx = request_context::RequestContext::begin(&app, &window, "native_download", invocation_id, timeout_ms)?;
+    let result = match fetch_client(DOWNLOAD_MAX_REDIRECTS, None, proxy_state.url().as_deref()) {
+        Ok(client) => {
+            let progress = |p: DownloadProgress| {
+                let _ = app.emit("download-progress", p);
+            };
+            download(&client, &ctx.token(), url, dest, headers, resume, expected_sha256, progress).await
+        }
+        Err(e) => Err(e),
+    };
+    ctx.finish(result)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn download(
+    client: &reqwest::Client,
+    cancel: &request_context::CancelToken,
+    url: String,
+    dest: String,
+    headers: Option<HashMap<String, String>>,
+    resume: Option<bool>,
+    expected_sha256: Option<String>,
+    progress: impl Fn(DownloadProgress),
+) -> Result<NativeDownloadResult, String> {
+    let path = std::path::PathBuf::from(&dest);
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(parent).await.map_err(|e| format!("create dir: {}", e))?;
+    }
+    let existing = match resume {
+        Some(true) => tokio::fs::metadata(&path).await.map(|m| m.len()).unwrap_or(0),
+        _ => 0,
+    };
+
+    let mut request_headers: Vec<(String, String)> = headers.into_iter().flatten().collect();
+    if existing > 0 {
+        request_headers.push(("Range".to_string(), format!("bytes={}-", existing)));
+    }
+    // Byte ranges refer to the stored file, so ask for it unencoded. The
+    // shared client's 20 s limit is for API calls; `timeout_ms` bounds this.
+    if !request_headers.iter().any(|(k, _)| k.eq_ignore_ascii_case("accept-encoding")) {
+        request_headers.push(("Accept-Encoding".to_string(), "identity".to_string()));
+    }
+    let mut req = client.get(&url).timeout(DOWNLOAD_TIMEOUT);
+    for (k, v) in &request_headers {
+        req = req.header(k.as_str(), v.as_str());
+    }
+
+    // Downloads are logged once the response headers arrive; bodies are
+    // files, never captured.
+    let mut log_entry = netlog::NetEntry {
+        started_ms: netlog::unix_millis(),
+        duration_ms: 0,
+        method: "GET".to_string(),
+        url: url.clone(),
+        request_headers: netlog::redact_headers(request_headers.iter().map(|(k, v)| (k.as_str(), v.as_str()))),
+        request_body: None,
+        status: 0,
+        response_headers: Vec::new(),
+        response_body: None,
+        response_size: None,
+        mime_type: String::new(),
+        error: None,
+    };
+    let started = std::time::Instant::now();
+    let sent = cancel.run(req.send()).await?;
+    log_entry.duration_ms = started.elapsed().as_millis() as u64;
+    let mut resp = match sent {
+        Ok(resp) => resp,
+        Err(e) => {
+            metrics::METRICS.native_fetch_errors.inc();
+            log_entry.error = Some(format!("request: {}", e));
+            netlog::NETLOG.record(log_entry);
+            return Err(format!("request: {}", e));
+        }
+    };
+    log_entry.status = resp.status().as_u16();
+    log_entry.response_headers = netlog::response_headers(resp.headers());
+    log_entry.response_size = resp.content_length();
+    log_entry.mime_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    netlog::NETLOG.record(log_entry);
+    let content_range = resp
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let (append, total) = match resp.status().as_u16() {
+        206 if existing > 0 => {
+            let (start, total) = content_range
+                .as_deref()
+                .and_then(parse_content_range)
+                .ok_or("206 response without a usable Content-Range")?;
+            if start != existing {
+                return Err(format!("server resumed at byte {} but {} bytes are on disk", start, existing));
+            }
+            (true, total)
+        }
+        // Range past the end: the partial file is already the whole thing.
+        416 if existing > 0 && content_range.as_deref().and_then(unsatisfied_range_total) == Some(existing) => {
+            (true, Some(existing))
+        }
+        200 => (false, resp.content_length()),
+        s => {
+            metrics::METRICS.native_fetch_errors.inc();
+            return Err(format!("{} returned {}", url, s));
+        }
+    };
+    let resumed_from = if append { existing } else { 0 };
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(&path)
+        .await
+        .map_err(|e| format!("open {}: {}", dest, e))?;
+
+    let mut downloaded = resumed_from;
+    if resp.status().as_u16() != 416 {
+        loop {
+            let next = match cancel.run(resp.chunk()).await {
+                Ok(next) => next,
+                Err(e) => {
+                    drop(file);
+                    if resume != Some(true) {
+                        let _ = tokio::fs::remove_file(&path).await;
+                    }
+                    return Err(e);
+                }
+            };
+            let chunk = match next {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break,
+                Err(e) => {
+                    metrics::METRICS.native_fetch_errors.inc();
+                    // What arrived stays on disk for a resume.
+                    let _ = file.flush().await;
+                    return Err(format!("body: {} ({} bytes saved, resumable)", e, downloaded));
+                }
+            };
+            file.write_all(&chunk).await.map_err(|e| format!("write: {}", e))?;
+            downloaded += chunk.len() as u64;
+            progress(DownloadProgress { url: &url, downloaded, total });
+        }
+    }
+    file.flush().await.map_err(|e| format!("write: {}", e))?;
+    drop(file);
+    metrics::METRICS.native_fetch.since(started);
+
+    if let Some(total) = total.filter(|t| downloaded < *t) {
+        return Err(format!("connection closed at {} of {} bytes (resumable)", downloaded, total));
+    }
+
+    let sha256 = file_sha256(&path).await?;
+    if let Some(expected) = expected_sha256 {
+        if !sha256.eq_ignore_ascii_case(expected.trim()) {
+            let _ = tokio::fs::remove_file(&path).await;
+            return Err(format!("SHA-256 mismatch: expected {}, got {}", expected.trim(), sha256));
+        }
+    }
+
+    Ok(NativeDownloadResult { path: dest, bytes: downloaded, resumed_from, sha256 })
 }
 
 // ── Depop native login ─────────────────────────────────────────────────────
@@ -164,6 +1078,28 @@ async fn native_fetch(
 struct DepopState {
     port: Mutex<Option<u16>>,
     shutdown_tx: Mutex<Option<tokio::sync::oneshot::Sender<()>>>,
+    /// Set while restore_depop_session waits on the hidden window: receives
+    /// the captured token, or None once the probe reports a signed-out session.
+    restore_tx: Mutex<Option<tokio::sync::oneshot::Sender<Option<String>>>>,
+    /// Set when the init script's "/alive" heartbeat reaches the token server;
+    /// cleared whenever the server restarts.
+    script_alive: Mutex<bool>,
+    /// The account to save the next captured token under in the keychain,
+    /// when open_depop_login was asked to; the token then never reaches an
+    /// event.
+    capture_to_keychain: Mutex<Option<String>>,
+}
+
+/// Keychain service for captured Depop tokens (see credentials.rs).
+const DEPOP_CREDENTIAL_SERVICE: &str = "depop";
+
+#[derive(Clone, Serialize)]
+struct DepopTokenCaptured {
+    account: String,
+    captured: bool,
+    storage: Option<credentials::Storage>,
+    warning: Option<String>,
+    error: Option<String>,
 }
 
 /// Percent-decode a URL-encoded string (for reading tokens from HTTP requests).
@@ -190,15 +1126,36 @@ fn url_decode(s: &str) -> String {
     out
 }
 
-#[tauri::command]
-async fn open_depop_login(app: tauri::AppHandle) -> Result<(), String> {
-    use tauri::{WebviewUrl, WebviewWindowBuilder};
+/// Value of `key` in the query string of a request path ("/token?t=...").
+fn query_param(path: &str, key: &str) -> Option<String> {
+    path.split('?').nth(1)?.split('&').find_map(|pair| {
+        let (k, v) = pair.split_once('=')?;
+        (k == key).then(|| url_decode(v))
+    })
+}
 
-    // Close any stale login window from a previous attempt
-    if let Some(existing) = app.get_webview_window("depop-login") {
-        let _ = existing.close();
-    }
+/// Persistent WebView profile for the Depop login window, one per account, so
+/// the session cookies survive app restarts.
+fn depop_profile_dir(app: &tauri::AppHandle, account: Option<&str>) -> Result<std::path::PathBuf, String> {
+    let name: String = account
+        .unwrap_or("")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect();
+    let name = if name.is_empty() || name.chars().all(|c| c == '.') { "default".to_string() } else { name };
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join("webview-profiles")
+        .join("depop")
+        .join(name);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create profile dir: {e}"))?;
+    Ok(dir)
+}
 
+/// (Re)start the local token-capture server and return its port.
+async fn start_token_server(app: &tauri::AppHandle) -> Result<u16, String> {
     // Cancel any existing token-capture server
     {
         let state = app.state::<DepopState>();
@@ -217,6 +1174,8 @@ async fn open_depop_login(app: tauri::AppHandle) -> Result<(), String> {
     {
         let state = app.state::<DepopState>();
         *state.port.lock().unwrap() = Some(port);
+        *state.script_alive.lock().unwrap() = false;
+        *state.capture_to_keychain.lock().unwrap() = None;
     }
 
     let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
@@ -252,19 +1211,68 @@ async fn open_depop_login(app: tauri::AppHandle) -> Result<(), String> {
 
                     // Parse token from "GET /token?t=<TOKEN> HTTP/1.1"
                     let req = String::from_utf8_lossy(&buf[..n]);
-                    let token = req.lines().next().and_then(|line| {
-                        let path = line.split_whitespace().nth(1)?;
-                        path.split('?').nth(1)
-                            .and_then(|q| q.split('&').find(|p| p.starts_with("t=")))
-                            .map(|p| url_decode(&p[2..]))
-                    });
+                    let path = req
+                        .lines()
+                        .next()
+                        .and_then(|line| line.split_whitespace().nth(1))
+                        .unwrap_or("")
+                        .to_string();
+
+                    // "GET /alive" — the init script is running in the window.
+                    if path.starts_with("/alive") {
+                        *app_srv.state::<DepopState>().script_alive.lock().unwrap() = true;
+                        continue;
+                    }
+
+                    // "GET /probe?state=out" — the restore probe found no live session.
+                    if path.starts_with("/probe") {
+                        if query_param(&path, "state").as_deref() == Some("out") {
+                            let tx = app_srv.state::<DepopState>().restore_tx.lock().unwrap().take();
+                            if let Some(tx) = tx {
+                                let _ = tx.send(None);
+                            }
+                        }
+                        continue;
+                    }
+
+                    let token = query_param(&path, "t");
 
                     if let Some(tok) = token {
                         // Accept JWT/opaque tokens (>= 20 chars) OR DEPOP_WEB:{slug} identifiers.
                         let is_web_token = tok.starts_with("DEPOP_WEB:") && tok.len() > "DEPOP_WEB:".len();
                         let is_bearer = tok.len() >= 20;
                         if (is_web_token || is_bearer) && !tok.chars().any(|c| c.is_whitespace()) {
-                            let _ = app_srv.emit("depop-token", tok);
+                            let restore = app_srv.state::<DepopState>().restore_tx.lock().unwrap().take();
+                            if let Some(tx) = restore {
+                                let _ = tx.send(Some(tok.clone()));
+                            }
+                            let keychain_account = app_srv.state::<DepopState>().capture_to_keychain.lock().unwrap().take();
+                            match keychain_account {
+                                Some(account) => {
+                                    let saved = credentials::save(&app_srv, DEPOP_CREDENTIAL_SERVICE, &account, &tok)
+                                        .map(credentials::CredentialResult::new);
+                                    let event = match saved {
+                                        Ok(saved) => DepopTokenCaptured {
+                                            account,
+                                            captured: true,
+                                            storage: Some(saved.storage),
+                                            warning: saved.warning,
+                                            error: None,
+                                        },
+                                        Err(e) => DepopTokenCaptured {
+                                            account,
+                                            captured: false,
+                                            storage: None,
+                                            warning: None,
+                                            error: Some(e),
+                                        },
+                                    };
+                                    let _ = app_srv.emit("depop-token-captured", event);
+                                }
+                                None => {
+                                    let _ = app_srv.emit("depop-token", tok);
+                                }
+                            }
                             let app2 = app_srv.clone();
                             tokio::spawn(async move {
                                 tokio::time::sleep(std::time::Duration::from_millis(400)).await;
@@ -280,11 +1288,23 @@ async fn open_depop_login(app: tauri::AppHandle) -> Result<(), String> {
         }
     });
 
-    // The init_script runs before every page load in this window.
-    // It patches fetch/XHR to intercept Bearer tokens and polls storage,
-    // sending any found token to our local server via fetch() — which works
-    // reliably from HTTPS pages to 127.0.0.1 (treated as a secure origin).
-    let init_script = format!("var __FLIPTOOLS_PORT = {port};\n") + r#"(function() {
+    Ok(port)
+}
+
+// The init_script runs before every page load in this window.
+// It patches fetch/XHR to intercept Bearer tokens and polls storage,
+// sending any found token to our local server via fetch() — which works
+// reliably from HTTPS pages to 127.0.0.1 (treated as a secure origin).
+fn depop_init_script(port: u16) -> String {
+    format!("var __FLIPTOOLS_PORT = {port};\n") + r#"(function() {
+        // Heartbeat first, so a re-injection into an already patched page
+        // still reports in.
+        setTimeout(function() {
+            var url = 'http://127.0.0.1:' + __FLIPTOOLS_PORT + '/alive';
+            try { fetch(url, { mode: 'no-cors' }).catch(function() {}); } catch(e) {}
+            try { var img = new Image(); img.src = url; } catch(e) {}
+        }, 0);
+
         if (window.__fliptools_patched) return;
         window.__fliptools_patched = true;
 
@@ -480,27 +1500,258 @@ async fn open_depop_login(app: tauri::AppHandle) -> Result<(), String> {
             }, 800);
         }
         window.addEventListener('load', autoCapture);
-    })();"#;
+    })();"#
+}
 
-    let _webview = WebviewWindowBuilder::new(
-        &app,
+const DEPOP_LOGIN_URL: &str = "https://www.depop.com/login/";
+/// How long after a page load the init script gets to send its heartbeat
+/// before it is re-injected.
+const DEPOP_HEARTBEAT_GRACE: std::time::Duration = std::time::Duration::from_secs(4);
+const DEPOP_REINJECT_ATTEMPTS: u32 = 2;
+
+/// What the heartbeat watch needs from a login attempt.
+trait LoginWindow {
+    /// This attempt still owns the token server and its window is open.
+    fn is_open(&self) -> bool;
+    /// The init script has sent its heartbeat.
+    fn script_alive(&self) -> bool;
+    /// Run the init script again (`attempt` counts from 1).
+    fn reinject(&self, attempt: u32);
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Heartbeat {
+    Alive,
+    /// Closed or replaced by a newer attempt; nothing more to do.
+    Closed,
+    /// Still silent after every re-injection.
+    Missing,
+}
+
+/// Wait `grace` for the heartbeat, re-injecting the script each time it's
+/// missing, up to DEPOP_REINJECT_ATTEMPTS times.
+async fn await_heartbeat(window: &impl LoginWindow, grace: std::time::Duration) -> Heartbeat {
+    for attempt in 0..=DEPOP_REINJECT_ATTEMPTS {
+        tokio::time::sleep(grace).await;
+        if !window.is_open() {
+            return Heartbeat::Closed;
+        }
+        if window.script_alive() {
+            return Heartbeat::Alive;
+        }
+        if attempt < DEPOP_REINJECT_ATTEMPTS {
+            window.reinject(attempt + 1);
+        }
+    }
+    Heartbeat::Missing
+}
+
+/// The depop-login window of the attempt serving tokens on `port`.
+struct DepopLoginWindow<'a> {
+    app: &'a tauri::AppHandle,
+    port: u16,
+}
+
+impl LoginWindow for DepopLoginWindow<'_> {
+    fn is_open(&self) -> bool {
+        *self.app.state::<DepopState>().port.lock().unwrap() == Some(self.port)
+            && self.app.get_webview_window("depop-login").is_some()
+    }
+
+    fn script_alive(&self) -> bool {
+        *self.app.state::<DepopState>().script_alive.lock().unwrap()
+    }
+
+    fn reinject(&self, attempt: u32) {
+        log::warn!("depop init script heartbeat missing; re-injecting (attempt {attempt})");
+        if let Some(win) = self.app.get_webview_window("depop-login") {
+            let _ = win.eval(depop_init_script(self.port));
+        }
+    }
+}
+
+/// The init script sometimes never runs (a WebView2 race on the first
+/// navigation). Wait for its heartbeat after the first page load, re-inject it
+/// with `eval` if it's missing, and after DEPOP_REINJECT_ATTEMPTS failures emit
+/// "depop-login-degraded" with `{ account, account_meta, attempts }` so the UI
+/// can point at the manual scan instead.
+fn watch_init_heartbeat(app: tauri::AppHandle, port: u16, account: Option<String>) {
+    tauri::async_runtime::spawn(async move {
+        let window = DepopLoginWindow { app: &app, port };
+        if await_heartbeat(&window, DEPOP_HEARTBEAT_GRACE).await != Heartbeat::Missing {
+            return;
+        }
+        let account_meta = account.as_deref().and_then(|a| accounts::meta(&app, a));
+        let _ = app.emit(
+            "depop-login-degraded",
+            serde_json::json!({
+                "account": account,
+                "account_meta": account_meta,
+                "attempts": DEPOP_REINJECT_ATTEMPTS,
+            }),
+        );
+    });
+}
+
+/// Open the depop-login window on `url` with the token-capture script and the
+/// account's persistent profile. `on_loaded` is evaluated after each page load.
+fn build_depop_window(
+    app: &tauri::AppHandle,
+    port: u16,
+    url: &str,
+    visible: bool,
+    account: Option<&str>,
+    on_loaded: Option<String>,
+) -> Result<tauri::WebviewWindow, String> {
+    use tauri::{WebviewUrl, WebviewWindowBuilder};
+
+    // Close any stale login window from a previous attempt
+    if let Some(existing) = app.get_webview_window("depop-login") {
+        let _ = existing.close();
+    }
+
+    let mut builder = WebviewWindowBuilder::new(
+        app,
         "depop-login",
-        WebviewUrl::External(
-            "https://www.depop.com/login/"
-                .parse()
-                .map_err(|e| format!("URL parse error: {e}"))?,
-        ),
+        WebviewUrl::External(url.parse().map_err(|e| format!("URL parse error: {e}"))?),
     )
-    .title("Sign in to Depop — FlipTools")
+    .title(i18n::tr(app, "depop_login.title"))
+    .background_color(system_prefs::window_background(app))
     .inner_size(460.0, 680.0)
     .resizable(true)
-    .initialization_script(&init_script)
-    .build()
-    .map_err(|e| format!("Failed to open login window: {e}"))?;
+    .visible(visible)
+    .data_directory(depop_profile_dir(app, account)?)
+    .initialization_script(depop_init_script(port));
+
+    let watching = std::sync::atomic::AtomicBool::new(false);
+    let watch_app = app.clone();
+    let watch_account = account.map(str::to_string);
+    builder = builder.on_page_load(move |win, payload| {
+        if !matches!(payload.event(), tauri::webview::PageLoadEvent::Finished) {
+            return;
+        }
+        if let Some(script) = &on_loaded {
+            let _ = win.eval(script);
+        }
+        if !watching.swap(true, std::sync::atomic::Ordering::SeqCst) {
+            watch_init_heartbeat(watch_app.clone(), port, watch_account.clone());
+        }
+    });
+
+    builder
+        .build()
+        .map_err(|e| format!("Failed to open login window: {e}"))
+}
+
+/// `account` selects the persistent WebView profile; omit it for the default one.
+/// With `save_to_keychain` the captured token is saved with the credential
+/// commands (service "depop", the account or "default") and only
+/// "depop-token-captured" {account, captured, storage, warning} is emitted,
+/// never the token itself.
+#[tauri::command]
+async fn open_depop_login(
+    app: tauri::AppHandle,
+    account: Option<String>,
+    save_to_keychain: Option<bool>,
+) -> Result<(), String> {
+    let port = start_token_server(&app).await?;
+    *app.state::<DepopState>().capture_to_keychain.lock().unwrap() = save_to_keychain
+        .unwrap_or(false)
+        .then(|| account.clone().filter(|a| !a.trim().is_empty()).unwrap_or_else(|| "default".to_string()));
+    build_depop_window(&app, port, DEPOP_LOGIN_URL, true, account.as_deref(), None)?;
 
     Ok(())
 }
 
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum DepopRestoreStatus {
+    Restored,
+    NeedsInteractiveLogin,
+}
+
+#[derive(Clone, Serialize)]
+struct DepopRestoreResult {
+    status: DepopRestoreStatus,
+    account: Option<String>,
+    account_meta: Option<accounts::AccountMetadata>,
+}
+
+/// How long the hidden window gets to load Depop and answer the probe.
+const DEPOP_RESTORE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(20);
+
+/// Silently check whether the saved Depop session is still valid.
+/// Opens the login window hidden on the account's persistent profile and
+/// probes accounts/me from inside the page (cookies included). A live session
+/// is re-captured through the usual "depop-token" event and the window closes;
+/// otherwise the window is sent to the login page and shown.
+/// Emits "depop-session-restored" or "depop-login-required" with
+/// `{ account, account_meta }`.
+#[tauri::command]
+async fn restore_depop_session(
+    app: tauri::AppHandle,
+    account: Option<String>,
+) -> Result<DepopRestoreResult, String> {
+    let port = start_token_server(&app).await?;
+
+    let (tx, rx) = tokio::sync::oneshot::channel::<Option<String>>();
+    *app.state::<DepopState>().restore_tx.lock().unwrap() = Some(tx);
+
+    let probe = format!(
+        r#"(function() {{
+            var PORT = {port};
+            function ping(path) {{
+                var url = 'http://127.0.0.1:' + PORT + path;
+                try {{ fetch(url, {{ mode: 'no-cors' }}).catch(function(){{}}); }} catch(e) {{}}
+                try {{ var img = new Image(); img.src = url; }} catch(e) {{}}
+            }}
+            if (/\/(login|signup|register)/.test(window.location.pathname)) {{ ping('/probe?state=out'); return; }}
+            fetch('https://webapi.depop.com/api/v2/accounts/me/', {{ credentials: 'include' }})
+                .then(function(r) {{
+                    if (r.status === 401 || r.status === 403) {{ ping('/probe?state=out'); return null; }}
+                    return r.ok ? r.json() : null;
+                }})
+                .then(function(me) {{
+                    var slug = me && (me.username || me.slug);
+                    if (slug) ping('/token?t=' + encodeURIComponent('DEPOP_WEB:' + slug));
+                }})
+                .catch(function() {{}});
+        }})();"#
+    );
+
+    let win = build_depop_window(
+        &app,
+        port,
+        "https://www.depop.com/",
+        false,
+        account.as_deref(),
+        Some(probe),
+    )?;
+
+    let restored = matches!(
+        tokio::time::timeout(DEPOP_RESTORE_TIMEOUT, rx).await,
+        Ok(Ok(Some(_)))
+    );
+    // Stop a late answer from a slow page landing on a stale sender.
+    app.state::<DepopState>().restore_tx.lock().unwrap().take();
+
+    if let Some(a) = account.as_deref() {
+        accounts::remember(&app, a);
+    }
+    let account_meta = account.as_deref().and_then(|a| accounts::meta(&app, a));
+    let payload = serde_json::json!({ "account": account, "account_meta": account_meta });
+    if restored {
+        let _ = app.emit("depop-session-restored", payload);
+        return Ok(DepopRestoreResult { status: DepopRestoreStatus::Restored, account, account_meta });
+    }
+
+    let _ = app.emit("depop-login-required", payload);
+    let _ = win.eval(format!("window.location.href = {};", serde_json::json!(DEPOP_LOGIN_URL)));
+    let _ = win.show();
+    let _ = win.set_focus();
+    Ok(DepopRestoreResult { status: DepopRestoreStatus::NeedsInteractiveLogin, account, account_meta })
+}
+
 // Manually triggers a storage scan inside the depop-login WebView.
 // Called when the user is already signed in but the token wasn't auto-captured.
 #[tauri::command]
@@ -514,6 +1765,23 @@ async fn scan_depop_auth(app: tauri::AppHandle) -> Result<(), String> {
     let win = app.get_webview_window("depop-login")
         .ok_or_else(|| "Depop login window is not open".to_string())?;
 
+    // Progress lines come from the locale table; the diagnostics under them
+    // stay in English.
+    let strings = i18n::strings(
+        &app,
+        &[
+            "scan.searching",
+            "scan.page",
+            "scan.page_data",
+            "scan.nav_links",
+            "scan.app_state",
+            "scan.api",
+            "scan.found",
+            "scan.api_failed",
+        ],
+    );
+    let strings = serde_json::to_string(&strings).map_err(|e| e.to_string())?;
+
     // Depop uses httpOnly session cookies — no Bearer token accessible via JS.
     // Strategy: look for the logged-in user's slug via multiple methods.
     // Depop profiles are at /{username}/ (NOT /shop/{username}/).
@@ -521,15 +1789,25 @@ async fn scan_depop_auth(app: tauri::AppHandle) -> Result<(), String> {
         r#"(function() {{
             window.__fliptools_token_sent = false;
             var PORT = {port};
+            var T = {strings};
+            function t(key, vars) {{
+                var s = T[key] || key;
+                for (var k in (vars || {{}})) s = s.split('{{' + k + '}}').join(vars[k]);
+                return s;
+            }}
+            function esc(s) {{ var d = document.createElement('div'); d.textContent = s; return d.innerHTML; }}
 
             var old = document.getElementById('__ft_panel');
             if (old) old.remove();
             var panel = document.createElement('div');
             panel.id = '__ft_panel';
             panel.style.cssText = 'position:fixed;bottom:0;left:0;right:0;z-index:2147483647;background:#111;color:#0f0;font:11px/1.5 monospace;padding:8px;max-height:240px;overflow-y:auto;border-top:2px solid #0f0;';
-            panel.innerHTML = '<b>FlipTools: searching for your account...</b><br>';
+            panel.innerHTML = '<b>' + esc(t('scan.searching')) + '</b><br>';
             document.body && document.body.appendChild(panel);
             function log(s) {{ panel.innerHTML += s + '<br>'; panel.scrollTop = 9999; }}
+            function found(source, slug) {{
+                log('<b style="color:#ff0">' + esc(t('scan.found', {{ account: slug }})) + '</b> (' + source + ')');
+            }}
 
             function sendToServer(token) {{
                 var url = 'http://127.0.0.1:' + PORT + '/token?t=' + encodeURIComponent(token);
@@ -537,10 +1815,10 @@ async fn scan_depop_auth(app: tauri::AppHandle) -> Result<(), String> {
                 try {{ var img = new Image(); img.src = url; }} catch(e) {{}}
             }}
 
-            log('Page: ' + window.location.pathname);
+            log(esc(t('scan.page', {{ path: window.location.pathname }})));
 
             // ── Method 1: __NEXT_DATA__ deep scan ────────────────────────────────
-            log('1. __NEXT_DATA__...');
+            log(esc(t('scan.page_data')));
             try {{
                 if (window.__NEXT_DATA__) {{
                     var nd = window.__NEXT_DATA__;
@@ -557,13 +1835,13 @@ async fn scan_depop_auth(app: tauri::AppHandle) -> Result<(), String> {
                         return null;
                     }}
                     var s1 = deepSlug(nd, 0);
-                    if (s1) {{ log('<b style="color:#ff0">NEXT_DATA: ' + s1 + '</b>'); sendToServer('DEPOP_WEB:' + s1); return; }}
+                    if (s1) {{ found('NEXT_DATA', s1); sendToServer('DEPOP_WEB:' + s1); return; }}
                     log('no username in __NEXT_DATA__');
                 }} else {{ log('__NEXT_DATA__ absent'); }}
             }} catch(e) {{ log('NEXT_DATA err: ' + e.message); }}
 
             // ── Method 2: Nav DOM links — Depop uses /{{username}}/ not /shop/ ────
-            log('2. Nav hrefs...');
+            log(esc(t('scan.nav_links')));
             var SYSTEM = /^(login|signup|register|explore|feed|search|sell|help|about|terms|privacy|categories|notifications|en|us|uk|au|de|fr|it|es|products|likes|legal|sitemap|blog|careers|app|download|referral|safety|shipping|payments|returns)$/i;
             try {{
                 var navEl = document.querySelector('nav, header, [role="navigation"]') || document.body;
@@ -578,7 +1856,7 @@ async fn scan_depop_auth(app: tauri::AppHandle) -> Result<(), String> {
                     if (m && !SYSTEM.test(m[1])) candidates.push(m[1]);
                 }}
                 if (candidates.length > 0) {{
-                    log('<b style="color:#ff0">DOM slug: ' + candidates[0] + '</b>');
+                    found('DOM', candidates[0]);
                     sendToServer('DEPOP_WEB:' + candidates[0]);
                     return;
                 }}
@@ -586,7 +1864,7 @@ async fn scan_depop_auth(app: tauri::AppHandle) -> Result<(), String> {
             }} catch(e) {{ log('DOM err: ' + e.message); }}
 
             // ── Method 3: Global window state ────────────────────────────────────
-            log('3. Global state...');
+            log(esc(t('scan.app_state')));
             try {{
                 var GS = ['__STORE__','__APP_STATE__','__INITIAL_STATE__','__REDUX_STATE__','store','App','depop','__depop'];
                 for (var g = 0; g < GS.length; g++) {{
@@ -596,13 +1874,13 @@ async fn scan_depop_auth(app: tauri::AppHandle) -> Result<(), String> {
                     var s2 = (gv.user && (gv.user.username || gv.user.slug)) ||
                               (gv.auth && gv.auth.user && (gv.auth.user.username || gv.auth.user.slug)) ||
                               (gv.me && (gv.me.username || gv.me.slug));
-                    if (s2) {{ log('<b style="color:#ff0">Global: ' + s2 + '</b>'); sendToServer('DEPOP_WEB:' + s2); return; }}
+                    if (s2) {{ found('Global', s2); sendToServer('DEPOP_WEB:' + s2); return; }}
                 }}
                 log('no usable global state');
             }} catch(e) {{ log('global err: ' + e.message); }}
 
             // ── Method 4: Direct API (CORS will likely block) ────────────────────
-            log('4. API call...');
+            log(esc(t('scan.api')));
             fetch('https://api.depop.com/api/v2/accounts/me/', {{
                 credentials: 'include',
                 headers: {{ 'Accept': 'application/json', 'depop-locale': 'en-US' }}
@@ -614,12 +1892,13 @@ async fn scan_depop_auth(app: tauri::AppHandle) -> Result<(), String> {
             }})
             .then(function(data) {{
                 var s3 = data.username || data.slug || data.legacy_id || (data.user && (data.user.username || data.user.slug));
-                if (s3) {{ log('<b style="color:#ff0">API: ' + s3 + '</b>'); sendToServer('DEPOP_WEB:' + s3); }}
+                if (s3) {{ found('API', s3); sendToServer('DEPOP_WEB:' + s3); }}
                 else {{ log('API keys: ' + Object.keys(data || {{}}).join(', ')); }}
             }})
-            .catch(function(e) {{ log('<span style="color:#f44">API fail: ' + e.message + '</span>'); }});
+            .catch(function(e) {{ log('<span style="color:#f44">' + esc(t('scan.api_failed', {{ error: e.message }})) + '</span>'); }});
         }})();"#,
-        port = port
+        port = port,
+        strings = strings
     );
 
     win.eval(&script).map_err(|e| e.to_string())?;
@@ -640,7 +1919,7 @@ async fn navigate_depop_window(app: tauri::AppHandle, url: String) -> Result<(),
     let win = app.get_webview_window("depop-login")
         .ok_or_else(|| "Depop login window is not open".to_string())?;
     let safe_url = serde_json::to_string(&url).map_err(|e| e.to_string())?;
-    win.eval(&format!("window.location.href = {safe_url};"))
+    win.eval(format!("window.location.href = {safe_url};"))
         .map_err(|e| e.to_string())?;
     Ok(())
 }
@@ -651,23 +1930,288 @@ pub fn run() {
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_process::init())
-        .manage(UpdateState {
-            update_available: Mutex::new(None),
-        })
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .manage(DepopState {
             port: Mutex::new(None),
             shutdown_tx: Mutex::new(None),
+            restore_tx: Mutex::new(None),
+            script_alive: Mutex::new(false),
+            capture_to_keychain: Mutex::new(None),
         })
-        .invoke_handler(tauri::generate_handler![
-            check_for_update,
-            install_update,
+        .manage(local_api::LocalApiState {
+            port: Mutex::new(None),
+        })
+        .manage(write_queue::WriteQueueState::default())
+        .manage(clipboard::ClipboardWatchState::default())
+        .manage(request_context::Invocations::default())
+        .manage(sse::SseState::default())
+        .manage(ws::WsState::default())
+        .on_window_event(|window, event| match event {
+            // Focusing any window counts as activity for idle maintenance.
+            tauri::WindowEvent::Focused(true) => {
+                if let Some(state) = window.try_state::<maintenance::MaintenanceState>() {
+                    state.touch();
+                }
+            }
+            // Nobody is left to see what the window started.
+            tauri::WindowEvent::Destroyed => {
+                window.state::<request_context::Invocations>().cancel_window(window.label());
+            }
+            _ => {}
+        })
+        .invoke_handler(capabilities::guarded(tauri::generate_handler![
+            updates::check_for_update,
+            updates::install_update,
+            updates::download_update,
+            updates::apply_downloaded_update,
+            updates::install_update_from_file,
+            updates::cancel_update,
+            updates::get_last_update_check,
+            updates::get_pending_update,
+            updates::get_update_settings,
+            updates::set_update_check_interval,
+            updates::set_update_download_attempts,
             get_current_version,
-            get_changelog,
+            get_app_status,
+            privacy::set_privacy_mode,
+            privacy::toggle_privacy_mode,
+            changelog::get_changelog,
+            changelog::get_changelog_structured,
             native_fetch,
+            native_fetch_stream,
+            sse::start_sse,
+            sse::stop_sse,
+            ws::ws_connect,
+            ws::ws_send,
+            ws::ws_close,
+            native_download,
             open_depop_login,
+            restore_depop_session,
             navigate_depop_window,
-            scan_depop_auth
-        ])
+            scan_depop_auth,
+            webhooks::register_inbound_webhook,
+            webhooks::list_inbound_webhooks,
+            webhooks::remove_inbound_webhook,
+            webhooks::get_inbound_webhook_log,
+            webhooks::set_webhook_session,
+            tasks::list_tasks,
+            tasks::create_task,
+            tasks::set_task_done,
+            tasks::delete_task,
+            audit::get_audit_log,
+            depop::bump::depop_soft_bump,
+            lots::allocate_lot_cost,
+            aging::get_inventory_aging,
+            repricing::simulate_repricing,
+            price_suggest::suggest_price,
+            price_suggest::check_inventory_prices,
+            compliance::scan_listing,
+            compliance::list_compliance_rules,
+            compliance::add_compliance_rule,
+            compliance::remove_compliance_rule,
+            compliance::brands::get_high_risk_brand_settings,
+            compliance::brands::set_high_risk_brand_settings,
+            compliance::brands::set_item_authenticated,
+            listing::preview_listing,
+            listing::approve_listing_publish,
+            images::process_images,
+            images::list_image_presets,
+            images::save_image_preset,
+            images::remove_image_preset,
+            images::set_marketplace_image_preset,
+            images::set_image_watermark,
+            metrics::get_metrics,
+            metrics::record_timing,
+            depop::attributes::get_depop_category_attributes,
+            depop::attributes::refresh_depop_category_attributes,
+            depop::attributes::report_depop_rejection,
+            depop::attributes::get_depop_schema_drift,
+            depop::photos::repair_listing_photos,
+            depop::photos::download_listing_photos,
+            links::resolve_url,
+            maintenance::ping_activity,
+            maintenance::get_maintenance_settings,
+            maintenance::set_maintenance_settings,
+            maintenance::run_maintenance_now,
+            qr::generate_listing_qr,
+            qr::generate_qr_sheet,
+            qr::get_qr_settings,
+            qr::set_qr_marketplace_priority,
+            accounts::list_accounts,
+            accounts::set_account_metadata,
+            depop::sync::depop_fetch_listings,
+            clipboard::get_clipboard_watch,
+            clipboard::set_clipboard_watch,
+            netlog::export_har,
+            netlog::get_network_log_settings,
+            netlog::set_network_capture_bodies,
+            write_queue::list_write_intents,
+            crosslist::get_crosslist_matrix,
+            custom_fields::list_custom_fields,
+            custom_fields::define_custom_field,
+            custom_fields::rename_custom_field,
+            custom_fields::set_custom_field_options,
+            custom_fields::remove_custom_field,
+            custom_fields::restore_custom_field,
+            custom_fields::validate_custom_field_values,
+            notifications::push_marketplace_event,
+            notifications::get_digest_settings,
+            notifications::set_digest_settings,
+            notifications::flush_notification_digest,
+            notifications::get_listing_stats,
+            retention::get_retention_settings,
+            retention::set_retention_settings,
+            retention::preview_retention_run,
+            retention::run_retention,
+            retention::undo_retention_run,
+            retention::locate_photos,
+            fees::get_fee_schedules,
+            fees::set_fee_schedule,
+            fees::remove_fee_schedule,
+            fees::calculate_fees,
+            fees::recompute_fees,
+            depop::liked::depop_fetch_liked,
+            depop::liked::refresh_pipeline_prices,
+            depop::liked::update_pipeline_status,
+            depop::liked::get_pipeline,
+            ebay::ebay_import_active_listings,
+            views::list_views,
+            views::save_view,
+            views::delete_view,
+            views::apply_view,
+            depop::offers::record_depop_offers,
+            depop::offers::list_depop_offers,
+            depop::offers::get_offer_nudge_settings,
+            depop::offers::set_offer_nudge_settings,
+            depop::offers::depop_send_message,
+            depop::messages::depop_sync_message_attachments,
+            depop::messages::get_conversation_attachments,
+            depop::messages::get_message_attachment_settings,
+            depop::messages::set_message_attachment_settings,
+            depop::messages::depop_send_message_with_image,
+            photo_store::analyze_photo_store,
+            photo_store::clean_photo_store,
+            photo_store::get_pending_repoints,
+            photo_store::ack_photo_repoints,
+            shipping::record_unshipped_sales,
+            shipping::record_tracking,
+            shipping::get_shipping_deadlines,
+            shipping::get_shipping_settings,
+            shipping::set_shipping_settings,
+            backup::configure_backup_target,
+            backup::get_backup_target,
+            backup::test_backup_target,
+            backup::upload_backup,
+            backup::get_pending_backup_uploads,
+            marketplace_limits::get_marketplace_limits,
+            marketplace_limits::get_marketplace_limits_snapshot,
+            marketplace_limits::set_marketplace_limit_overrides,
+            video::validate_video,
+            video::transcode_video,
+            video::get_ffmpeg_status,
+            depop::video::depop_upload_video,
+            system_prefs::get_system_preferences,
+            i18n::get_app_language,
+            i18n::set_app_language,
+            rate_limit::get_rate_limit_status,
+            rate_limit::get_rate_limits,
+            rate_limit::set_rate_limit,
+            rate_limit::reset_rate_limit,
+            depop::boost::record_depop_receipts,
+            depop::boost::get_boost_report,
+            depop::boost::depop_set_boost,
+            plugins::list_plugins,
+            plugins::set_plugin_enabled,
+            plugins::test_plugin,
+            plugins::generate_plugin_sku,
+            plugins::get_plugin_price_suggestions,
+            price_rules::get_price_rules,
+            price_rules::set_price_rules,
+            price_rules::refresh_exchange_rates,
+            price_rules::convert_and_round_price,
+            price_rules::apply_bulk_discount,
+            pricing_calendar::get_pricing_rules,
+            pricing_calendar::create_pricing_rule,
+            pricing_calendar::preview_pricing_rule,
+            pricing_calendar::run_due_pricing_rules,
+            pricing_calendar::delete_pricing_rule,
+            photo_meta::get_photo_metadata,
+            photo_meta::set_photo_metadata,
+            photo_meta::set_photos_metadata,
+            tax_summary::get_tax_summary,
+            tax_summary::generate_tax_summary_pdf,
+            consignment::list_consignors,
+            consignment::save_consignor,
+            consignment::assign_consignor,
+            consignment::get_consignor_statement,
+            consignment::generate_consignor_statement_pdf,
+            consignment::record_consignor_payout,
+            tax_summary::get_tax_thresholds,
+            tax_summary::set_tax_threshold,
+            payouts::get_payout_settings,
+            payouts::set_payout_settings,
+            payouts::import_bank_csv,
+            payouts::reconcile_payouts,
+            payouts::get_payout_reconciliation,
+            payouts::match_payout,
+            payouts::unmatch_payout,
+            payouts::export_payout_reconciliation,
+            hotfolder::list_import_mappings,
+            hotfolder::save_import_mapping,
+            hotfolder::remove_import_mapping,
+            hotfolder::get_csv_hotfolder,
+            hotfolder::configure_csv_hotfolder,
+            hotfolder::get_pending_hotfolder_imports,
+            hotfolder::import_hotfolder_batch,
+            proxy::get_proxy,
+            proxy::set_proxy,
+            offline_sales::record_offline_sale,
+            offline_sales::record_offline_sales,
+            offline_sales::get_session_offline_sales,
+            offline_sales::undo_offline_sale,
+            offline_sales::get_market_mode,
+            offline_sales::set_market_mode,
+            offline_sales::handle_scan,
+            outage::get_marketplace_status,
+            outage::set_marketplace_status_page,
+            depop::health::depop_fetch_account_health,
+            depop::health::get_account_health,
+            depop::health::get_health_thresholds,
+            depop::health::set_health_thresholds,
+            footer::get_listing_footers,
+            footer::set_listing_footer,
+            depop::footer::propagate_footer,
+            sku::get_sku_settings,
+            sku::set_sku_settings,
+            sku::generate_sku,
+            clones::clone_item,
+            clones::clone_from_listing,
+            clones::get_item_variants,
+            photo_shoot::assign_photo_groups,
+            shipping::get_carrier_rates,
+            shipping::set_carrier_rates,
+            depop::international::depop_set_international_shipping,
+            depop::international::suggest_international_prices,
+            team_sync::get_team_sync_status,
+            team_sync::export_changes,
+            team_sync::import_changes,
+            depop::search_rank::check_search_rank,
+            depop::search_rank::get_rank_history,
+            depop::search_rank::track_search_rank,
+            depop::search_rank::get_tracked_search_ranks,
+            request_context::cancel_command,
+            cookie_jar::get_cookies,
+            cookie_jar::clear_cookie_jar,
+            cookie_jar::clear_cookies,
+            credentials::save_credential,
+            credentials::get_credential,
+            credentials::delete_credential,
+            request_context::cancel_fetch,
+            http_cache::clear_http_cache,
+            capabilities::get_capability_map
+        ]))
         .setup(|app| {
             if cfg!(debug_assertions) {
                 app.handle().plugin(
@@ -677,6 +2221,18 @@ pub fn run() {
                 )?;
             }
 
+            // Created here rather than from the config so the native
+            // background matches the OS scheme before the page paints.
+            let prefs = system_prefs::read();
+            app.manage(system_prefs::SystemPrefsState::new(prefs.clone()));
+            app.manage(i18n::LanguageState::load(app.handle()));
+            if let Some(config) = app.config().app.windows.iter().find(|w| w.label == "main") {
+                tauri::WebviewWindowBuilder::from_config(app.handle(), config)?
+                    .background_color(prefs.background())
+                    .build()?;
+            }
+            system_prefs::start_watch_job(app.handle().clone(), prefs);
+
             if let Some(window) = app.get_webview_window("main") {
                 let icon_bytes: &[u8] = include_bytes!("../icons/icon.png");
                 if let Ok(icon) = tauri::image::Image::from_bytes(icon_bytes) {
@@ -684,8 +2240,459 @@ pub fn run() {
                 }
             }
 
+            metrics::init();
+            app.manage(webhooks::WebhookState::load(app.handle()));
+            app.manage(tasks::TaskState::load(app.handle()));
+            app.manage(compliance::ComplianceState::load(app.handle()));
+            app.manage(images::ImagePresetState::load(app.handle()));
+            app.manage(depop::attributes::DepopAttributeState::load(app.handle()));
+            app.manage(maintenance::MaintenanceState::load(app.handle()));
+            app.manage(qr::QrState::load(app.handle()));
+            app.manage(accounts::AccountState::load(app.handle()));
+            app.manage(depop::sync::ListingSyncState::load(app.handle()));
+            app.manage(write_queue::IntentState::load(app.handle()));
+            app.manage(custom_fields::CustomFieldState::load(app.handle()));
+            app.manage(notifications::NotificationState::load(app.handle()));
+            app.manage(retention::RetentionState::load(app.handle()));
+            app.manage(fees::FeeScheduleState::load(app.handle()));
+            app.manage(depop::liked::PipelineState::load(app.handle()));
+            app.manage(ebay::EbayImportState::load(app.handle()));
+            app.manage(views::ViewState::load(app.handle()));
+            app.manage(depop::offers::OfferState::load(app.handle()));
+            app.manage(photo_store::PhotoStoreState::load(app.handle()));
+            app.manage(shipping::ShippingState::load(app.handle()));
+            app.manage(backup::BackupState::load(app.handle()));
+            app.manage(marketplace_limits::LimitsState::load(app.handle()));
+            app.manage(depop::boost::BoostState::load(app.handle()));
+            app.manage(plugins::PluginState::load(app.handle()));
+            app.manage(price_rules::PriceRuleState::load(app.handle()));
+            app.manage(depop::health::AccountHealthState::load(app.handle()));
+            app.manage(footer::FooterState::load(app.handle()));
+            app.manage(updates::UpdateState::load(app.handle()));
+            app.manage(sku::SkuState::load(app.handle()));
+            app.manage(team_sync::TeamSyncState::load(app.handle()));
+            app.manage(depop::search_rank::SearchRankState::load(app.handle()));
+            app.manage(pricing_calendar::PricingCalendarState::load(app.handle()));
+            app.manage(photo_meta::PhotoMetaState::load(app.handle()));
+            app.manage(tax_summary::TaxState::load(app.handle()));
+            app.manage(payouts::PayoutState::load(app.handle()));
+            app.manage(rate_limit::RateLimitState::load(app.handle()));
+            app.manage(proxy::ProxyState::load(app.handle()));
+            app.manage(offline_sales::OfflineSaleState::load(app.handle()));
+            app.manage(hotfolder::HotfolderState::load(app.handle()));
+            app.manage(outage::OutageState::load(app.handle()));
+            app.manage(depop::messages::MessageAttachmentState::load(app.handle()));
+            app.manage(cookie_jar::CookieJarState::load(app.handle()));
+            privacy::load(app.handle());
+            local_api::start(app.handle().clone());
+            depop::attributes::start_refresh_job(app.handle().clone());
+            maintenance::start(app.handle().clone());
+            notifications::start_digest_job(app.handle().clone());
+            retention::start_purge_job(app.handle().clone());
+            depop::offers::start_nudge_job(app.handle().clone());
+            photo_store::start_purge_job(app.handle().clone());
+            shipping::start_deadline_job(app.handle().clone());
+            backup::start_retry_job(app.handle().clone());
+            updates::start_check_job(app.handle().clone());
+            depop::search_rank::start_rank_job(app.handle().clone());
+            pricing_calendar::start_calendar_job(app.handle().clone());
+            hotfolder::start_watch_job(app.handle().clone());
+            outage::start_monitor_job(app.handle().clone());
+            cookie_jar::start_persist_job(app.handle().clone());
+
             Ok(())
         })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Keep-alive HTTP server on a loopback port answering "ok" to every
+    /// request; returns its address and how many connections it accepted.
+    async fn keep_alive_server() -> (std::net::SocketAddr, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let count = accepted.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                count.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    loop {
+                        let Ok(n) = stream.read(&mut chunk).await else { return };
+                        if n == 0 {
+                            return;
+                        }
+                        buf.extend_from_slice(&chunk[..n]);
+                        while let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                            buf.drain(..end + 4);
+                            let reply = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: keep-alive\r\n\r\nok";
+                            if stream.write_all(reply).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        (addr, accepted)
+    }
+
+    #[tokio::test]
+    async fn native_fetch_calls_share_one_connection() {
+        let (addr, accepted) = keep_alive_server().await;
+        for i in 0..5 {
+            // As native_fetch does on every call.
+            let client = fetch_client(0, None, None).unwrap();
+            let resp = client.get(format!("http://{addr}/item/{i}")).send().await.unwrap();
+            assert_eq!(resp.text().await.unwrap(), "ok");
+        }
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+
+        // A different cookie jar is a different client, with its own pool.
+        let jar = Arc::new(cookie_jar::CookieJar::default());
+        let client = fetch_client(0, Some(&jar), None).unwrap();
+        client.get(format!("http://{addr}/")).send().await.unwrap().text().await.unwrap();
+        assert_eq!(accepted.load(Ordering::SeqCst), 2);
+    }
+
+    /// Loopback server that answers every request with its method (also in
+    /// an `X-Method` header) and body. `/redirect/<status>` redirects to
+    /// `/echo` with that status; `/raw` sends the request body back as is.
+    async fn echo_server() -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    let end = loop {
+                        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                            break end + 4;
+                        }
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    };
+                    let head = String::from_utf8_lossy(&buf[..end]).to_string();
+                    let length: usize = head
+                        .to_ascii_lowercase()
+                        .lines()
+                        .find_map(|l| l.strip_prefix("content-length:")?.trim().parse().ok())
+                        .unwrap_or(0);
+                    while buf.len() < end + length {
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let mut words = head.split_whitespace();
+                    let (method, path) = (words.next().unwrap_or(""), words.next().unwrap_or(""));
+                    let close = "Connection: close\r\n\r\n";
+                    let body = &buf[end..end + length];
+                    let reply = match path.strip_prefix("/redirect/") {
+                        Some(status) => {
+                            format!("HTTP/1.1 {status} Redirect\r\nLocation: /echo\r\nContent-Length: 0\r\n{close}")
+                                .into_bytes()
+                        }
+                        None if path == "/raw" => {
+                            let head =
+                                format!("HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {length}\r\n{close}");
+                            [head.as_bytes(), body].concat()
+                        }
+                        None => {
+                            let body = format!("{method} {}", String::from_utf8_lossy(body));
+                            let length = body.len();
+                            let mut reply =
+                                format!("HTTP/1.1 200 OK\r\nX-Method: {method}\r\nContent-Length: {length}\r\n{close}");
+                            if method != "HEAD" {
+                                reply.push_str(&body);
+                            }
+                            reply.into_bytes()
+                        }
+                    };
+                    let _ = stream.write_all(&reply).await;
+                });
+            }
+        });
+        addr
+    }
+
+    /// `native_fetch`'s exchange for `method` on `path`: the method the
+    /// server saw, the body it sent back and the redirects followed.
+    async fn round_trip(
+        addr: std::net::SocketAddr,
+        method: &str,
+        path: &str,
+        body: Option<&str>,
+    ) -> (String, String, usize) {
+        let (_, method) = fetch_method(Some(method)).unwrap();
+        let request = FetchRequest {
+            method,
+            headers: None,
+            validators: &[],
+            payload: body.map(|b| b.as_bytes().to_vec()),
+            timeout: None,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+        };
+        let target = reqwest::Url::parse(&format!("http://{addr}{path}")).unwrap();
+        let client = fetch_client(0, None, None).unwrap();
+        let Ok((resp, chain)) = send_following(&client, None, request, target).await else { panic!("request failed") };
+        assert_eq!(resp.status(), 200);
+        let seen = resp.headers()["x-method"].to_str().unwrap().to_string();
+        (seen, resp.text().await.unwrap(), chain.len())
+    }
+
+    #[tokio::test]
+    async fn every_verb_round_trips() {
+        let addr = echo_server().await;
+        let verbs = [
+            ("GET", None),
+            ("POST", Some("a=1")),
+            ("PUT", Some(r#"{"price":12}"#)),
+            ("PATCH", Some("{}")),
+            ("DELETE", None),
+        ];
+        for (method, body) in verbs {
+            let (seen, echoed, _) = round_trip(addr, method, "/echo", body).await;
+            assert_eq!(seen, method);
+            assert_eq!(echoed, format!("{method} {}", body.unwrap_or_default()));
+        }
+        let (seen, echoed, _) = round_trip(addr, "head", "/echo", None).await;
+        assert_eq!((seen.as_str(), echoed.as_str()), ("HEAD", ""));
+    }
+
+    #[tokio::test]
+    async fn redirects_keep_the_body_only_on_307_and_308() {
+        let addr = echo_server().await;
+        for status in [307, 308] {
+            for method in ["POST", "PUT", "PATCH"] {
+                let result = round_trip(addr, method, &format!("/redirect/{status}"), Some("kept")).await;
+                assert_eq!(result, (method.to_string(), format!("{method} kept"), 1), "{method} after {status}");
+            }
+        }
+        // A 303, or a 301/302 after a POST, becomes a GET without the body.
+        for status in [301, 302, 303] {
+            let result = round_trip(addr, "POST", &format!("/redirect/{status}"), Some("dropped")).await;
+            assert_eq!(result, ("GET".to_string(), "GET ".to_string(), 1), "POST after {status}");
+        }
+        let result = round_trip(addr, "PUT", "/redirect/302", Some("kept")).await;
+        assert_eq!(result, ("PUT".to_string(), "PUT kept".to_string(), 1));
+        let result = round_trip(addr, "HEAD", "/redirect/303", None).await;
+        assert_eq!(result, ("HEAD".to_string(), String::new(), 1));
+    }
+
+    #[tokio::test]
+    async fn binary_bodies_round_trip_byte_for_byte() {
+        // A 4x4 PNG with gAMA, tEXt (one with a non-UTF-8 byte), tIME and
+        // eXIf chunks ahead of its pixels.
+        const PNG: &[u8] = include_bytes!("round_trip_fixture.png");
+        let addr = echo_server().await;
+        // Sent as `body_base64`, read back with `response_type: "binary"`.
+        let upload = base64::engine::general_purpose::STANDARD.encode(PNG);
+        let request = FetchRequest {
+            method: reqwest::Method::PUT,
+            headers: None,
+            validators: &[],
+            payload: Some(base64::engine::general_purpose::STANDARD.decode(upload).unwrap()),
+            timeout: None,
+            max_redirects: 0,
+        };
+        let target = reqwest::Url::parse(&format!("http://{addr}/raw")).unwrap();
+        let client = fetch_client(0, None, None).unwrap();
+        let Ok((resp, _)) = send_following(&client, None, request, target).await else { panic!("request failed") };
+        let (bytes, truncated) = read_limited(resp, DEFAULT_MAX_BODY_BYTES).await.unwrap();
+        assert!(!truncated);
+        let (body, encoding) = encode_body(&bytes, "image/png", ResponseType::Binary);
+        assert!(matches!(encoding, BodyEncoding::Base64));
+        assert_eq!(base64::engine::general_purpose::STANDARD.decode(body).unwrap(), PNG);
+
+        // Read as text, the same bytes would not survive.
+        let (text, _) = encode_body(&bytes, "image/png", ResponseType::Text);
+        assert_ne!(text.as_bytes(), PNG);
+    }
+
+    /// A login window whose heartbeat arrives after `alive_after` checks
+    /// and that closes after `open_for` checks.
+    #[derive(Default)]
+    struct FakeLoginWindow {
+        alive_after: Option<u32>,
+        open_for: Option<u32>,
+        checks: std::cell::Cell<u32>,
+        reinjected: std::cell::RefCell<Vec<u32>>,
+    }
+
+    impl LoginWindow for FakeLoginWindow {
+        fn is_open(&self) -> bool {
+            self.checks.set(self.checks.get() + 1);
+            self.open_for.map_or(true, |n| self.checks.get() <= n)
+        }
+
+        fn script_alive(&self) -> bool {
+            self.alive_after.is_some_and(|n| self.checks.get() >= n)
+        }
+
+        fn reinject(&self, attempt: u32) {
+            self.reinjected.borrow_mut().push(attempt);
+        }
+    }
+
+    const GRACE: std::time::Duration = std::time::Duration::from_secs(4);
+
+    #[tokio::test(start_paused = true)]
+    async fn silent_script_is_reinjected_then_reported_missing() {
+        let window = FakeLoginWindow::default();
+        let started = tokio::time::Instant::now();
+        assert_eq!(await_heartbeat(&window, GRACE).await, Heartbeat::Missing);
+        assert_eq!(*window.reinjected.borrow(), [1, 2]);
+        assert_eq!(started.elapsed(), GRACE * (DEPOP_REINJECT_ATTEMPTS + 1));
+
+        // A heartbeat after the first re-injection ends the watch there.
+        let window = FakeLoginWindow { alive_after: Some(2), ..Default::default() };
+        assert_eq!(await_heartbeat(&window, GRACE).await, Heartbeat::Alive);
+        assert_eq!(*window.reinjected.borrow(), [1]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn live_script_is_left_alone() {
+        let window = FakeLoginWindow { alive_after: Some(1), ..Default::default() };
+        let started = tokio::time::Instant::now();
+        assert_eq!(await_heartbeat(&window, GRACE).await, Heartbeat::Alive);
+        assert!(window.reinjected.borrow().is_empty());
+        assert_eq!(started.elapsed(), GRACE);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watch_stops_when_the_window_closes() {
+        let window = FakeLoginWindow { open_for: Some(1), ..Default::default() };
+        assert_eq!(await_heartbeat(&window, GRACE).await, Heartbeat::Closed);
+        assert_eq!(*window.reinjected.borrow(), [1]);
+
+        let closed = FakeLoginWindow { open_for: Some(0), ..Default::default() };
+        assert_eq!(await_heartbeat(&closed, GRACE).await, Heartbeat::Closed);
+        assert!(closed.reinjected.borrow().is_empty());
+    }
+
+    /// Loopback server for `body`, one request per connection. With `ranges`
+    /// it answers "Range: bytes=N-" with a 206; with `cut_after` it closes
+    /// the connection after that many body bytes.
+    async fn file_server(body: &'static [u8], ranges: bool, cut_after: Option<usize>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&buf).to_ascii_lowercase();
+                    let start = request
+                        .lines()
+                        .find_map(|l| l.strip_prefix("range: bytes="))
+                        .and_then(|r| r.trim_end_matches('-').parse::<usize>().ok())
+                        .filter(|_| ranges);
+                    let (head, part) = match start {
+                        Some(start) => (
+                            format!(
+                                "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {start}-{}/{}\r\n",
+                                body.len() - 1,
+                                body.len()
+                            ),
+                            &body[start..],
+                        ),
+                        None => ("HTTP/1.1 200 OK\r\n".to_string(), body),
+                    };
+                    let head = format!("{head}Content-Length: {}\r\nConnection: close\r\n\r\n", part.len());
+                    let sent = &part[..cut_after.unwrap_or(part.len()).min(part.len())];
+                    let _ = stream.write_all(head.as_bytes()).await;
+                    let _ = stream.write_all(sent).await;
+                });
+            }
+        });
+        format!("http://{addr}/bundle.bin")
+    }
+
+    const BUNDLE: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    /// A fresh path under the temp dir.
+    fn scratch_file(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("fliptools-{name}-{}", hex::encode(rand::random::<[u8; 4]>())));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn sha256_hex(bytes: &[u8]) -> String {
+        use sha2::{Digest, Sha256};
+        hex::encode(Sha256::digest(bytes))
+    }
+
+    /// `native_download`'s transfer, with no progress reporting.
+    async fn download_to(url: &str, dest: &std::path::Path, resume: bool) -> Result<NativeDownloadResult, String> {
+        let client = fetch_client(DOWNLOAD_MAX_REDIRECTS, None, None).unwrap();
+        let dest = dest.to_str().unwrap().to_string();
+        let expected = Some(sha256_hex(BUNDLE));
+        let cancel = request_context::CancelToken::never();
+        download(&client, &cancel, url.to_string(), dest, None, Some(resume), expected, |_| {}).await
+    }
+
+    #[tokio::test]
+    async fn download_resumes_from_the_partial_file() {
+        let url = file_server(BUNDLE, true, None).await;
+        let dest = scratch_file("download-resume");
+
+        let fresh = download_to(&url, &dest, true).await.unwrap();
+        assert_eq!((fresh.bytes, fresh.resumed_from), (BUNDLE.len() as u64, 0));
+        assert_eq!(std::fs::read(&dest).unwrap(), BUNDLE);
+
+        std::fs::write(&dest, &BUNDLE[..10]).unwrap();
+        let resumed = download_to(&url, &dest, true).await.unwrap();
+        assert_eq!((resumed.bytes, resumed.resumed_from), (BUNDLE.len() as u64, 10));
+        assert_eq!(resumed.sha256, sha256_hex(BUNDLE));
+        assert_eq!(std::fs::read(&dest).unwrap(), BUNDLE);
+        let _ = std::fs::remove_file(&dest);
+    }
+
+    #[tokio::test]
+    async fn download_starts_over_when_the_server_ignores_range() {
+        let url = file_server(BUNDLE, false, None).await;
+        let dest = scratch_file("download-no-range");
+        std::fs::write(&dest, &BUNDLE[..10]).unwrap();
+
+        let result = download_to(&url, &dest, true).await.unwrap();
+        assert_eq!((result.bytes, result.resumed_from), (BUNDLE.len() as u64, 0));
+        assert_eq!(std::fs::read(&dest).unwrap(), BUNDLE);
+        let _ = std::fs::remove_file(&dest);
+    }
+
+    #[tokio::test]
+    async fn download_cut_off_mid_transfer_keeps_the_partial_file() {
+        let dest = scratch_file("download-cut-off");
+
+        let cut = file_server(BUNDLE, true, Some(12)).await;
+        let Err(err) = download_to(&cut, &dest, true).await else { panic!("a cut-off download succeeded") };
+        assert!(err.contains("resumable"), "{err}");
+        assert_eq!(std::fs::read(&dest).unwrap(), &BUNDLE[..12]);
+
+        // The next attempt picks up at byte 12.
+        let whole = file_server(BUNDLE, true, None).await;
+        let resumed = download_to(&whole, &dest, true).await.unwrap();
+        assert_eq!(resumed.resumed_from, 12);
+        assert_eq!(std::fs::read(&dest).unwrap(), BUNDLE);
+        let _ = std::fs::remove_file(&dest);
+    }
+}
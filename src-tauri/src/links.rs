@@ -0,0 +1,453 @@
+// ── Marketplace link resolver ──────────────────────────────────────────────
+// Recognises Depop / eBay / Etsy URLs pasted anywhere in the app (quick
+// search, comps, watchlist) and turns listing links into a normalised preview
+// the UI can offer actions on.
+//
+// Shortlinks (depop.app.link, ebay.us, etsy.me, ...) are expanded by following
+// redirects first. Listing pages are parsed from their JSON-LD Product block,
+// falling back to OpenGraph tags, with per-marketplace fix-ups on top.
+
+use regex::Regex;
+use reqwest::Url;
+use serde::Serialize;
+use serde_json::Value;
+
+const USER_AGENT: &str =
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0 Safari/537.36";
+
+const SHORTLINK_HOSTS: &[&str] = &["depop.app.link", "ebay.us", "ebay.to", "etsy.me"];
+
+/// Single-segment Depop paths that aren't shops.
+const DEPOP_RESERVED: &[&str] = &[
+    "explore", "sell", "login", "signup", "likes", "messages", "notifications", "settings", "help", "about", "blog",
+];
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityType {
+    Listing,
+    Shop,
+    Search,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkAction {
+    ImportAsComp,
+    WatchPrice,
+    AddToInventory,
+    ImportComps,
+    Open,
+}
+
+#[derive(Clone, Serialize, Default)]
+pub struct LinkPreview {
+    title: Option<String>,
+    price: Option<f64>,
+    currency: Option<String>,
+    photos: Vec<String>,
+    seller: Option<String>,
+    condition: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ResolvedUrl {
+    Recognized {
+        marketplace: &'static str,
+        entity: EntityType,
+        /// Listing id / shop name / search query, depending on `entity`.
+        id: Option<String>,
+        url: String,
+        /// The shortlink this was expanded from.
+        expanded_from: Option<String>,
+        preview: Option<Box<LinkPreview>>,
+        preview_error: Option<String>,
+        actions: Vec<LinkAction>,
+    },
+    Unrecognized {
+        url: String,
+        reason: String,
+    },
+}
+
+struct Classified {
+    marketplace: &'static str,
+    entity: EntityType,
+    id: Option<String>,
+}
+
+fn query_value(url: &Url, key: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.into_owned())
+        .filter(|v| !v.is_empty())
+}
+
+fn path_segments(url: &Url) -> Vec<String> {
+    url.path_segments()
+        .map(|s| s.filter(|p| !p.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Host without "www." / "m." prefixes.
+fn bare_host(url: &Url) -> String {
+    let host = url.host_str().unwrap_or("").to_ascii_lowercase();
+    host.strip_prefix("www.")
+        .or_else(|| host.strip_prefix("m."))
+        .unwrap_or(&host)
+        .to_string()
+}
+
+fn is_ebay_host(host: &str) -> bool {
+    // ebay.com, ebay.co.uk, ebay.de, ebay.com.au, ...
+    host == "ebay.com" || (host.starts_with("ebay.") && !SHORTLINK_HOSTS.contains(&host))
+}
+
+fn classify_depop(url: &Url) -> Option<Classified> {
+    let segs = path_segments(url);
+    let (entity, id) = match segs.first().map(String::as_str) {
+        Some("products") => (EntityType::Listing, segs.get(1).cloned()),
+        Some("search") => (EntityType::Search, query_value(url, "q")),
+        Some(first) if segs.len() == 1 && first.len() >= 2 && !DEPOP_RESERVED.contains(&first) => {
+            (EntityType::Shop, Some(first.to_string()))
+        }
+        _ => return None,
+    };
+    Some(Classified { marketplace: "depop", entity, id })
+}
+
+fn classify_ebay(url: &Url) -> Option<Classified> {
+    let segs = path_segments(url);
+    let (entity, id) = match segs.first().map(String::as_str) {
+        // /itm/<id> or /itm/<title-slug>/<id>
+        Some("itm") => (
+            EntityType::Listing,
+            segs.iter().skip(1).rev().find(|s| s.chars().all(|c| c.is_ascii_digit())).cloned(),
+        ),
+        Some("str") | Some("usr") => (EntityType::Shop, segs.get(1).cloned()),
+        Some("sch") => (EntityType::Search, query_value(url, "_nkw")),
+        _ => return None,
+    };
+    Some(Classified { marketplace: "ebay", entity, id })
+}
+
+fn classify_etsy(url: &Url) -> Option<Classified> {
+    let segs = path_segments(url);
+    // Localised URLs carry a language prefix: /uk/listing/...
+    let segs: &[String] = match segs.first() {
+        Some(s) if s.len() == 2 && segs.len() > 1 => &segs[1..],
+        _ => &segs[..],
+    };
+    let (entity, id) = match segs.first().map(String::as_str) {
+        Some("listing") => (EntityType::Listing, segs.get(1).cloned()),
+        Some("shop") => (EntityType::Shop, segs.get(1).cloned()),
+        Some("search") => (EntityType::Search, query_value(url, "q")),
+        _ => return None,
+    };
+    Some(Classified { marketplace: "etsy", entity, id })
+}
+
+fn classify(url: &Url) -> Option<Classified> {
+    let host = bare_host(url);
+    if host == "depop.com" {
+        classify_depop(url)
+    } else if is_ebay_host(&host) {
+        classify_ebay(url)
+    } else if host == "etsy.com" {
+        classify_etsy(url)
+    } else {
+        None
+    }
+}
+
+fn is_shortlink(url: &Url) -> bool {
+    SHORTLINK_HOSTS.contains(&bare_host(url).as_str())
+}
+
+/// Auction / estate-sale sites worth noticing as sourcing links, even though
+/// they don't get a preview.
+const AUCTION_HOSTS: &[&str] = &[
+    "shopgoodwill.com",
+    "hibid.com",
+    "liveauctioneers.com",
+    "invaluable.com",
+    "proxibid.com",
+    "auctionzip.com",
+    "estatesales.net",
+    "estatesales.org",
+    "maxsold.com",
+    "ctbids.com",
+];
+
+/// The web link in `text` when it's a marketplace or auction link, for
+/// callers that see arbitrary text (the clipboard watcher). Only a single
+/// bare URL counts, not URLs embedded in prose.
+pub fn sourcing_link(text: &str) -> Option<String> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() || trimmed.len() > 2048 || trimmed.contains(char::is_whitespace) {
+        return None;
+    }
+    let url = Url::parse(trimmed).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    let host = bare_host(&url);
+    let auction = AUCTION_HOSTS.iter().any(|h| host == *h || host.ends_with(&format!(".{h}")));
+    (auction || is_shortlink(&url) || classify(&url).is_some()).then(|| url.to_string())
+}
+
+fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(15))
+        .gzip(true)
+        .brotli(true)
+        .user_agent(USER_AGENT)
+        .redirect(reqwest::redirect::Policy::limited(10))
+        .build()
+        .map_err(|e| format!("client build: {e}"))
+}
+
+/// Follow a shortlink's redirects and return where it lands. Some shorteners
+/// reject HEAD, so fall back to GET.
+async fn expand(client: &reqwest::Client, url: &Url) -> Result<Url, String> {
+    if let Ok(resp) = client.head(url.clone()).send().await {
+        if resp.status().is_success() || resp.url() != url {
+            return Ok(resp.url().clone());
+        }
+    }
+    let resp = client
+        .get(url.clone())
+        .send()
+        .await
+        .map_err(|e| format!("expanding shortlink: {e}"))?;
+    Ok(resp.url().clone())
+}
+
+pub fn decode_entities(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// OpenGraph / product meta tags as (property, content) pairs.
+fn meta_tags(html: &str) -> Vec<(String, String)> {
+    let tag = Regex::new(r#"(?is)<meta\s[^>]*>"#).expect("valid regex");
+    let attr = Regex::new(r#"(?is)(property|name|content)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).expect("valid regex");
+    tag.find_iter(html)
+        .filter_map(|m| {
+            let mut key = None;
+            let mut content = None;
+            for c in attr.captures_iter(m.as_str()) {
+                let value = c.get(2).or_else(|| c.get(3)).map_or("", |v| v.as_str());
+                match c[1].to_ascii_lowercase().as_str() {
+                    "content" => content = Some(decode_entities(value)),
+                    _ => key = Some(value.to_ascii_lowercase()),
+                }
+            }
+            Some((key?, content?))
+        })
+        .collect()
+}
+
+/// First JSON-LD object whose @type is Product (searching arrays and @graph).
+fn json_ld_product(html: &str) -> Option<Value> {
+    let re = Regex::new(r#"(?is)<script[^>]*type=["']application/ld\+json["'][^>]*>(.*?)</script>"#)
+        .expect("valid regex");
+    fn find(v: &Value) -> Option<Value> {
+        match v {
+            Value::Array(items) => items.iter().find_map(find),
+            Value::Object(o) => {
+                let is_product = match o.get("@type") {
+                    Some(Value::String(t)) => t == "Product",
+                    Some(Value::Array(ts)) => ts.iter().any(|t| t == "Product"),
+                    _ => false,
+                };
+                if is_product {
+                    Some(v.clone())
+                } else {
+                    o.get("@graph").and_then(find)
+                }
+            }
+            _ => None,
+        }
+    }
+    let product = re
+        .captures_iter(html)
+        .filter_map(|c| serde_json::from_str::<Value>(c[1].trim()).ok())
+        .find_map(|v| find(&v));
+    product
+}
+
+fn as_price(v: &Value) -> Option<f64> {
+    match v {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.replace(',', "").trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn name_of(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Object(o) => o.get("name").and_then(Value::as_str).map(str::to_string),
+        _ => None,
+    }
+}
+
+fn parse_listing(marketplace: &str, html: &str) -> LinkPreview {
+    let mut preview = LinkPreview::default();
+
+    if let Some(product) = json_ld_product(html) {
+        preview.title = product.get("name").and_then(Value::as_str).map(decode_entities);
+        preview.photos = match product.get("image") {
+            Some(Value::String(s)) => vec![s.clone()],
+            Some(Value::Array(a)) => a
+                .iter()
+                .filter_map(|i| match i {
+                    Value::String(s) => Some(s.clone()),
+                    _ => i.get("url").and_then(Value::as_str).map(str::to_string),
+                })
+                .collect(),
+            Some(Value::Object(o)) => o.get("url").and_then(Value::as_str).map(|s| vec![s.to_string()]).unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        let offer = match product.get("offers") {
+            Some(Value::Array(a)) => a.first().cloned(),
+            other => other.cloned(),
+        };
+        if let Some(offer) = offer {
+            preview.price = offer.get("price").or_else(|| offer.get("lowPrice")).and_then(as_price);
+            preview.currency = offer.get("priceCurrency").and_then(Value::as_str).map(str::to_string);
+            preview.seller = offer.get("seller").and_then(name_of);
+            preview.condition = offer
+                .get("itemCondition")
+                .and_then(Value::as_str)
+                .map(|c| c.rsplit('/').next().unwrap_or(c).trim_end_matches("Condition").to_string());
+        }
+    }
+
+    let meta = meta_tags(html);
+    let get = |key: &str| meta.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone());
+    if preview.title.is_none() {
+        preview.title = get("og:title");
+    }
+    if preview.price.is_none() {
+        preview.price = get("product:price:amount")
+            .or_else(|| get("og:price:amount"))
+            .and_then(|p| as_price(&Value::String(p)));
+    }
+    if preview.currency.is_none() {
+        preview.currency = get("product:price:currency").or_else(|| get("og:price:currency"));
+    }
+    if preview.photos.is_empty() {
+        preview.photos = meta.iter().filter(|(k, _)| k == "og:image").map(|(_, v)| v.clone()).collect();
+    }
+
+    // Titles come through as "<title> | Depop" / "... | eBay" / "... - Etsy".
+    if let Some(t) = preview.title.as_mut() {
+        for suffix in [" | Depop", " | eBay", " - Etsy", " | Etsy"] {
+            if let Some(stripped) = t.strip_suffix(suffix) {
+                *t = stripped.trim().to_string();
+            }
+        }
+    }
+    if marketplace == "depop" && preview.seller.is_none() {
+        // Product pages link the seller's shop as /<username>/.
+        let re = Regex::new(r#"href="/([a-z0-9_.-]{2,30})/""#).expect("valid regex");
+        preview.seller = re
+            .captures_iter(html)
+            .map(|c| c[1].to_string())
+            .find(|s| !matches!(s.as_str(), "products" | "search" | "sell" | "login" | "signup" | "explore"));
+    }
+    if marketplace == "ebay" && preview.seller.is_none() {
+        let re = Regex::new(r#"/str/([A-Za-z0-9_.-]+)|/usr/([A-Za-z0-9_.-]+)"#).expect("valid regex");
+        preview.seller = re
+            .captures(html)
+            .and_then(|c| c.get(1).or_else(|| c.get(2)))
+            .map(|m| m.as_str().to_string());
+    }
+    preview
+}
+
+async fn fetch_preview(client: &reqwest::Client, marketplace: &str, url: &Url) -> Result<LinkPreview, String> {
+    let resp = client
+        .get(url.clone())
+        .header(reqwest::header::ACCEPT, "text/html,application/xhtml+xml")
+        .send()
+        .await
+        .map_err(|e| format!("request: {e}"))?;
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(format!("{marketplace} returned {}", status.as_u16()));
+    }
+    let html = resp.text().await.map_err(|e| format!("body: {e}"))?;
+    let preview = parse_listing(marketplace, &html);
+    if preview.title.is_none() && preview.price.is_none() {
+        return Err("Couldn't find listing details on the page".to_string());
+    }
+    Ok(preview)
+}
+
+/// Classify a pasted URL and, for listings, fetch a preview. Anything that
+/// isn't a supported marketplace link comes back as `Unrecognized`.
+#[tauri::command]
+pub async fn resolve_url(url: String) -> Result<ResolvedUrl, String> {
+    let trimmed = url.trim();
+    let with_scheme = if trimmed.contains("://") { trimmed.to_string() } else { format!("https://{trimmed}") };
+    let Ok(mut parsed) = Url::parse(&with_scheme) else {
+        return Ok(ResolvedUrl::Unrecognized { url, reason: "Not a valid URL".to_string() });
+    };
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Ok(ResolvedUrl::Unrecognized { url, reason: "Only web links are supported".to_string() });
+    }
+
+    let client = client()?;
+    let mut expanded_from = None;
+    if is_shortlink(&parsed) {
+        let target = expand(&client, &parsed).await?;
+        expanded_from = Some(parsed.to_string());
+        parsed = target;
+    }
+
+    let Some(found) = classify(&parsed) else {
+        return Ok(ResolvedUrl::Unrecognized {
+            url: parsed.to_string(),
+            reason: "Not a Depop, eBay or Etsy listing, shop or search link".to_string(),
+        });
+    };
+
+    let (preview, preview_error) = if found.entity == EntityType::Listing {
+        match fetch_preview(&client, found.marketplace, &parsed).await {
+            Ok(p) => (Some(Box::new(p)), None),
+            Err(e) => (None, Some(e)),
+        }
+    } else {
+        (None, None)
+    };
+
+    let actions = match found.entity {
+        EntityType::Listing => vec![
+            LinkAction::ImportAsComp,
+            LinkAction::WatchPrice,
+            LinkAction::AddToInventory,
+            LinkAction::Open,
+        ],
+        EntityType::Search => vec![LinkAction::ImportComps, LinkAction::Open],
+        EntityType::Shop => vec![LinkAction::Open],
+    };
+
+    Ok(ResolvedUrl::Recognized {
+        marketplace: found.marketplace,
+        entity: found.entity,
+        id: found.id,
+        url: parsed.to_string(),
+        expanded_from,
+        preview,
+        preview_error,
+        actions,
+    })
+}
@@ -0,0 +1,256 @@
+// ── Listing preview ────────────────────────────────────────────────────────
+// Pre-publish checks run on the canonical listing the crosslist page builds
+// (src/api/platforms/types.ts `ListingData`) before it is sent to a
+// marketplace. `preview_listing` shows the result as the listing is edited;
+// `approve_listing_publish` runs the same checks as the last step before the
+// frontend sends a listing, refusing it unless it's publishable and auditing
+// any compliance override there, once per publish.
+
+use crate::compliance::{self, ComplianceReport, ComplianceState, ScanItem};
+use crate::depop::attributes::{self, DepopAttributeState, FieldError};
+use crate::footer::FooterState;
+use crate::marketplace_limits::{self, LimitsState};
+use crate::photo_meta::PhotoMetaState;
+use crate::price_rules::{PriceRuleState, RoundedPrice};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use tauri::Manager;
+
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct ListingDraft {
+    /// Inventory item id, when the draft is for a saved item.
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub price: f64,
+    /// Currency of `price`; when set, the preview converts and rounds it for
+    /// the marketplace (see price_rules).
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub condition: Option<String>,
+    #[serde(default)]
+    pub images: Vec<String>,
+    /// Photo checklist labels, parallel to `images` ("" = unlabelled).
+    #[serde(default)]
+    pub image_labels: Vec<String>,
+    /// Uploaded video id (or local path before upload).
+    #[serde(default)]
+    pub video: Option<String>,
+    /// Marketplace attributes by canonical name ("size", "shoe_size_eu", "brand", ...).
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
+}
+
+impl ListingDraft {
+    /// Whether a canonical field has a non-empty value.
+    fn has_field(&self, field: &str) -> bool {
+        match field {
+            "title" => !self.title.trim().is_empty(),
+            "description" => !self.description.trim().is_empty(),
+            "price" => self.price > 0.0,
+            "photos" => !self.images.is_empty(),
+            "condition" => self.condition.as_deref().is_some_and(|c| !c.trim().is_empty()),
+            "category" => self.category.as_deref().is_some_and(|c| !c.trim().is_empty()),
+            other => self.attributes.get(other).is_some_and(|v| !v.trim().is_empty()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ListingPreview {
+    marketplace: String,
+    title: String,
+    description: String,
+    price: f64,
+    /// How `price` was converted and rounded, when the draft had a currency.
+    price_rounding: Option<RoundedPrice>,
+    /// Set when the marketplace footer was shortened or left off to keep the
+    /// description under its limit.
+    footer_warning: Option<String>,
+    compliance: ComplianceReport,
+    /// Alt text of each image, parallel to the draft's `images`.
+    image_alt_text: Vec<Option<String>>,
+    /// Missing or invalid marketplace fields (including limits from the
+    /// marketplace_limits registry), keyed by canonical field name. Any
+    /// entry blocks publishing; there is no override.
+    field_errors: Vec<FieldError>,
+    /// Severe compliance hits are overridden by the supplied note.
+    compliance_overridden: bool,
+    publishable: bool,
+}
+
+/// `compliance_override` is a note explaining why severe hits are acceptable
+/// for this listing; supplying one unblocks publishing.
+/// `currency` is the marketplace's listing currency (defaults to the draft's).
+/// The marketplace's managed footer (see footer.rs) is appended to the
+/// returned description.
+#[tauri::command]
+pub async fn preview_listing(
+    app: tauri::AppHandle,
+    item: ListingDraft,
+    marketplace: String,
+    currency: Option<String>,
+    compliance_override: Option<String>,
+) -> Result<ListingPreview, String> {
+    let override_note = compliance_override.filter(|n| !n.trim().is_empty());
+    preview(&app, item, marketplace, currency, override_note.is_some()).await
+}
+
+/// The publish gate: the same checks as `preview_listing`, but an error
+/// unless the listing is publishable. Severe compliance hits need an
+/// override note, which is audited. The frontend publishes the returned
+/// title, description and price.
+#[tauri::command]
+pub async fn approve_listing_publish(
+    app: tauri::AppHandle,
+    item: ListingDraft,
+    marketplace: String,
+    currency: Option<String>,
+    compliance_override: Option<String>,
+) -> Result<ListingPreview, String> {
+    let override_note = compliance_override.filter(|n| !n.trim().is_empty());
+    let preview = preview(&app, item, marketplace, currency, override_note.is_some()).await?;
+    if preview.compliance.blocks(preview.compliance_overridden) {
+        let blocking = severe_rules(&preview.compliance, |h| !(preview.compliance_overridden && h.overridable));
+        let needs_note = override_note.is_none() && !severe_rules(&preview.compliance, |h| h.overridable).is_empty();
+        return Err(if needs_note {
+            format!("Severe compliance issues ({}) need an override note to publish", blocking.join(", "))
+        } else {
+            format!("Severe compliance issues ({}) can't be overridden", blocking.join(", "))
+        });
+    }
+    if !preview.field_errors.is_empty() {
+        let errors: Vec<String> = preview.field_errors.iter().map(|e| format!("{}: {}", e.field, e.message)).collect();
+        return Err(format!("Fix these before publishing: {}", errors.join("; ")));
+    }
+    if preview.compliance_overridden {
+        crate::audit::record(
+            &app,
+            "compliance_override",
+            None,
+            false,
+            json!({
+                "marketplace": preview.marketplace,
+                "title": preview.title,
+                "note": override_note,
+                "rules": severe_rules(&preview.compliance, |h| h.overridable),
+            }),
+        );
+    }
+    Ok(preview)
+}
+
+/// Ids of severe hits matching `filter`.
+fn severe_rules(report: &ComplianceReport, filter: impl Fn(&compliance::ComplianceHit) -> bool) -> Vec<&str> {
+    report
+        .hits
+        .iter()
+        .filter(|h| h.severity == compliance::Severity::Severe && filter(h))
+        .map(|h| h.rule_id.as_str())
+        .collect()
+}
+
+async fn preview(
+    app: &tauri::AppHandle,
+    mut item: ListingDraft,
+    marketplace: String,
+    currency: Option<String>,
+    has_override_note: bool,
+) -> Result<ListingPreview, String> {
+    let compliance_state = app.state::<ComplianceState>();
+    let photo_meta = app.state::<PhotoMetaState>();
+    let limits = app.state::<LimitsState>();
+    let mut report = compliance::scan(
+        &compliance_state,
+        &ScanItem {
+            title: item.title.clone(),
+            description: item.description.clone(),
+            tags: item.tags.clone(),
+        },
+        &marketplace,
+    );
+    report.extend(compliance::brands::check(
+        &compliance_state,
+        &compliance::brands::BrandCheckItem {
+            id: item.id.as_deref(),
+            brand: item.attributes.get("brand").map(String::as_str),
+            description: &item.description,
+            photo_count: item.images.len(),
+            photo_labels: &item.image_labels,
+        },
+        &marketplace,
+    ));
+    report.extend(compliance::flaws::check(&photo_meta, &item.images, &item.description));
+    let overridden = has_override_note
+        && report.hits.iter().any(|h| h.severity == compliance::Severity::Severe && h.overridable);
+
+    let mut field_errors = match marketplace.as_str() {
+        "depop" => attributes::validate(&app.state::<DepopAttributeState>(), item.category.as_deref(), |f| {
+            item.has_field(f)
+        }),
+        _ => Vec::new(),
+    };
+    let mut price_rounding = None;
+    if let Some(from) = item.currency.clone() {
+        let to = currency.unwrap_or_else(|| from.clone());
+        let settings = app.state::<PriceRuleState>().settings();
+        match crate::price_rules::convert_and_round(&settings, &limits, item.price, &from, &to, &marketplace) {
+            Ok(rounded) => {
+                item.price = rounded.price;
+                item.currency = Some(rounded.currency.clone());
+                price_rounding = Some(rounded);
+            }
+            Err(message) => field_errors.push(FieldError { field: "price".to_string(), message }),
+        }
+    }
+    let limits = limits.get(&marketplace);
+    let footer = crate::footer::apply(
+        &item.description,
+        app.state::<FooterState>().get(&marketplace).as_deref(),
+        limits.as_ref().and_then(|l| l.limits.description_max_chars),
+    );
+    item.description = footer.description;
+    if let Some(limits) = limits {
+        field_errors.extend(marketplace_limits::check(
+            &limits,
+            &item.title,
+            &item.description,
+            &item.tags,
+            item.images.len(),
+            item.price,
+            item.video.as_deref().is_some_and(|v| !v.trim().is_empty()),
+        ));
+    }
+    // Plugins read files and run scripts; keep them off the async runtime.
+    let plugin_app = app.clone();
+    let plugin_input = json!({ "item": item, "marketplace": marketplace });
+    field_errors.extend(
+        tauri::async_runtime::spawn_blocking(move || crate::plugins::validation_errors(&plugin_app, &plugin_input))
+            .await
+            .map_err(|e| e.to_string())?,
+    );
+
+    Ok(ListingPreview {
+        publishable: !report.blocks(overridden) && field_errors.is_empty(),
+        field_errors,
+        compliance_overridden: overridden,
+        marketplace,
+        title: item.title,
+        description: item.description,
+        price: item.price,
+        price_rounding,
+        footer_warning: footer.warning,
+        compliance: report,
+        image_alt_text: photo_meta.alt_texts(&item.images),
+    })
+}
@@ -0,0 +1,236 @@
+// ── Local API server ───────────────────────────────────────────────────────
+// Loopback-only HTTP endpoint that lets self-hosted automations (home servers,
+// scripts, cron jobs) push data into the running app. It only ever binds to
+// 127.0.0.1 — anything reaching it from another machine has to be tunnelled
+// by the user on purpose. Every request is refused (503) while privacy mode
+// is on.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::Manager;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Preferred port so automations can be configured once. If something else
+/// holds it we fall back to an OS-assigned port and report it to the UI.
+pub const DEFAULT_PORT: u16 = 17832;
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+pub struct LocalApiState {
+    pub port: Mutex<Option<u16>>,
+}
+
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    /// Header names are lower-cased.
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|s| s.as_str())
+    }
+}
+
+pub struct HttpResponse {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn json(status: u16, value: &serde_json::Value) -> Self {
+        HttpResponse {
+            status,
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    pub fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message }))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let reason = match self.status {
+            200 => "OK",
+            201 => "Created",
+            202 => "Accepted",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            413 => "Payload Too Large",
+            422 => "Unprocessable Entity",
+            500 => "Internal Server Error",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            // Any reason phrase is valid; clients go by the code.
+            _ => "Status",
+        };
+        let mut out = format!(
+            "HTTP/1.1 {} {reason}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            self.content_type,
+            self.body.len()
+        )
+        .into_bytes();
+        out.extend_from_slice(&self.body);
+        out
+    }
+}
+
+/// Bind the server and start accepting connections in the background.
+pub fn start(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let listener = match tokio::net::TcpListener::bind(("127.0.0.1", DEFAULT_PORT)).await {
+            Ok(l) => l,
+            Err(e) => {
+                log::warn!("local API: port {DEFAULT_PORT} unavailable ({e}), using a random port");
+                match tokio::net::TcpListener::bind("127.0.0.1:0").await {
+                    Ok(l) => l,
+                    Err(e) => {
+                        log::error!("local API: failed to bind: {e}");
+                        return;
+                    }
+                }
+            }
+        };
+        let port = listener.local_addr().map(|a| a.port()).ok();
+        *app.state::<LocalApiState>().port.lock().unwrap() = port;
+
+        loop {
+            let (stream, _) = match listener.accept().await {
+                Ok(s) => s,
+                Err(e) => {
+                    log::warn!("local API: accept failed: {e}");
+                    continue;
+                }
+            };
+            let app = app.clone();
+            tokio::spawn(async move {
+                handle_connection(app, stream).await;
+            });
+        }
+    });
+}
+
+async fn handle_connection(app: tauri::AppHandle, mut stream: tokio::net::TcpStream) {
+    crate::metrics::METRICS.local_api_requests.inc();
+    let response = match read_request(&mut stream).await {
+        Ok(req) => route(&app, req).await,
+        Err(resp) => resp,
+    };
+    let _ = stream.write_all(&response.to_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+async fn route(app: &tauri::AppHandle, req: HttpRequest) -> HttpResponse {
+    if let Err(e) = crate::privacy::ensure_off("use the local API") {
+        return HttpResponse::error(503, &e);
+    }
+    if let Some(name) = req.path.strip_prefix("/webhooks/") {
+        if req.method != "POST" {
+            return HttpResponse::error(405, "webhooks only accept POST");
+        }
+        let name = name.trim_end_matches('/').to_string();
+        return crate::webhooks::handle_delivery(app, &name, &req).await;
+    }
+    if req.path == "/metrics" {
+        if req.method != "GET" {
+            return HttpResponse::error(405, "metrics only accept GET");
+        }
+        return HttpResponse {
+            status: 200,
+            content_type: "text/plain; version=0.0.4",
+            body: crate::metrics::prometheus_text().into_bytes(),
+        };
+    }
+    HttpResponse::error(404, "not found")
+}
+
+/// Minimal HTTP/1.1 request reader: request line, headers, Content-Length body.
+async fn read_request(stream: &mut tokio::net::TcpStream) -> Result<HttpRequest, HttpResponse> {
+    let mut buf = Vec::with_capacity(4096);
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        let n = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|_| HttpResponse::error(400, "read failed"))?;
+        if n == 0 {
+            return Err(HttpResponse::error(400, "connection closed"));
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if buf.len() > MAX_HEADER_BYTES {
+            return Err(HttpResponse::error(413, "headers too large"));
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or("").split_whitespace();
+    let method = request_line.next().unwrap_or("").to_uppercase();
+    let target = request_line.next().unwrap_or("/");
+    let path = target.split('?').next().unwrap_or("/").to_string();
+
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| {
+            let (k, v) = line.split_once(':')?;
+            Some((k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        })
+        .collect();
+
+    let content_length: usize = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    if content_length > MAX_BODY_BYTES {
+        return Err(HttpResponse::error(413, "body too large"));
+    }
+
+    let mut body = buf[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        let n = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|_| HttpResponse::error(400, "read failed"))?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(content_length);
+
+    Ok(HttpRequest { method, path, headers, body })
+}
+
+/// Base URL of the running server, if it managed to bind.
+pub fn base_url(app: &tauri::AppHandle) -> Option<String> {
+    let port = (*app.state::<LocalApiState>().port.lock().unwrap())?;
+    Some(format!("http://127.0.0.1:{port}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status_line(status: u16) -> String {
+        let bytes = HttpResponse::error(status, "x").to_bytes();
+        String::from_utf8(bytes).unwrap().lines().next().unwrap().to_string()
+    }
+
+    #[test]
+    fn status_lines_carry_the_right_reason() {
+        assert_eq!(status_line(201), "HTTP/1.1 201 Created");
+        assert_eq!(status_line(500), "HTTP/1.1 500 Internal Server Error");
+        assert_eq!(status_line(502), "HTTP/1.1 502 Bad Gateway");
+        assert_eq!(status_line(418), "HTTP/1.1 418 Status");
+    }
+}
@@ -0,0 +1,439 @@
+// ── Purchase lot cost allocation ───────────────────────────────────────────
+// Splits a lot's total cost across the items it produced. Lots and items
+// live in Supabase (`purchase_lots`, `inventory.lot_id`, migration 015);
+// `allocate_lot_cost` loads the lot and its items, works out each item's
+// share and writes it back to `inventory.cost` and
+// `inventory.cost_allocation_method`, recording the method on the lot.
+//
+// Re-allocation only ever moves cost between *unsold* items: sold items keep
+// the cost their profit was already reported with, and the remainder of the
+// lot total is spread over what's still on the shelf. An item is sold when
+// its quantity is used up or its listing has a sale. Gifts/freebies
+// (`acquisition_type = 'gift'`) are always zero-cost and never absorb a share.
+//
+// Everything downstream reads the allocated `inventory.cost`: sales record it
+// as their cost (offline_sales.rs, the frontend's recordSale), so profit does
+// too, and the aging report (aging.rs) values stock with it. The CSV importer
+// groups rows into lots by a lot reference column (hotfolder.rs).
+
+use crate::privacy::Private;
+use crate::supabase::{self, SupabaseSession};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Listing ids per sales query, to keep the URL a sensible length.
+const CHUNK: usize = 200;
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AllocationStrategy {
+    Equal,
+    WeightedByExpectedPrice,
+    /// Amounts per unsold item id; must add up to the unallocated remainder.
+    Manual { overrides: HashMap<String, f64> },
+}
+
+impl AllocationStrategy {
+    fn method_name(&self) -> &'static str {
+        match self {
+            AllocationStrategy::Equal => "equal",
+            AllocationStrategy::WeightedByExpectedPrice => "weighted_by_expected_price",
+            AllocationStrategy::Manual { .. } => "manual",
+        }
+    }
+}
+
+/// One item of the lot, as read from `inventory`.
+struct LotItem {
+    id: String,
+    expected_price: Option<f64>,
+    current_cost: Option<f64>,
+    current_method: Option<String>,
+    sold: bool,
+    gift: bool,
+}
+
+impl LotItem {
+    /// `sold_listings`: listing ids that have a sale.
+    fn from_row(row: &Value, sold_listings: &HashSet<String>) -> Option<Self> {
+        let quantity = row["quantity"].as_i64().unwrap_or(1);
+        let listing_sold = row["listing_id"].as_str().is_some_and(|l| sold_listings.contains(l));
+        Some(LotItem {
+            id: row["id"].as_str()?.to_string(),
+            expected_price: number(&row["expected_price"]),
+            current_cost: number(&row["cost"]),
+            current_method: row["cost_allocation_method"].as_str().map(str::to_string),
+            sold: quantity <= 0 || listing_sold,
+            gift: row["acquisition_type"] == "gift",
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct LotAllocation {
+    item_id: String,
+    cost: f64,
+    /// Strategy name, or "sold" / "gift" for items that were left alone.
+    method: String,
+    changed: bool,
+}
+
+#[derive(Serialize)]
+pub struct LotAllocationResult {
+    lot_total: f64,
+    kept_on_sold_items: f64,
+    allocated_to_unsold: f64,
+    method: &'static str,
+    items: Vec<LotAllocation>,
+}
+
+/// PostgREST returns DECIMAL columns as numbers, older rows as strings.
+pub(crate) fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn to_cents(amount: f64) -> i64 {
+    (amount * 100.0).round() as i64
+}
+
+fn from_cents(cents: i64) -> f64 {
+    cents as f64 / 100.0
+}
+
+/// Split `total` cents proportionally to `weights` using the largest-remainder
+/// method so the parts always add up to exactly `total`.
+fn split_cents(total: i64, weights: &[i64]) -> Vec<i64> {
+    let weight_sum: i128 = weights.iter().map(|w| *w as i128).sum();
+    if weight_sum == 0 {
+        return vec![0; weights.len()];
+    }
+    let mut parts: Vec<(usize, i64, i128)> = weights
+        .iter()
+        .enumerate()
+        .map(|(i, w)| {
+            let exact = total as i128 * *w as i128;
+            (i, (exact / weight_sum) as i64, exact % weight_sum)
+        })
+        .collect();
+    let mut leftover = total - parts.iter().map(|p| p.1).sum::<i64>();
+    // Hand the leftover cents to the largest remainders (ties: original order).
+    let mut order: Vec<usize> = (0..parts.len()).collect();
+    order.sort_by(|a, b| parts[*b].2.cmp(&parts[*a].2).then(a.cmp(b)));
+    for idx in order {
+        if leftover == 0 {
+            break;
+        }
+        parts[idx].1 += 1;
+        leftover -= 1;
+    }
+    parts.into_iter().map(|p| p.1).collect()
+}
+
+fn allocate(lot_total: f64, items: &[LotItem], strategy: &AllocationStrategy) -> Result<LotAllocationResult, String> {
+    let total = to_cents(lot_total);
+    if total < 0 {
+        return Err("Lot total can't be negative".to_string());
+    }
+    if items.is_empty() {
+        return Err("Lot has no items".to_string());
+    }
+
+    let kept: i64 = items
+        .iter()
+        .filter(|i| i.sold && !i.gift)
+        .map(|i| to_cents(i.current_cost.unwrap_or(0.0)))
+        .sum();
+    let remaining = total - kept;
+    if remaining < 0 {
+        return Err(format!(
+            "Sold items already carry ${:.2} of cost, more than the lot total of ${:.2}",
+            from_cents(kept),
+            from_cents(total)
+        ));
+    }
+
+    let targets: Vec<&LotItem> = items.iter().filter(|i| !i.sold && !i.gift).collect();
+    if targets.is_empty() && remaining > 0 {
+        return Err(format!(
+            "${:.2} is unallocated but every item in the lot is sold or a gift",
+            from_cents(remaining)
+        ));
+    }
+
+    let shares: Vec<i64> = match strategy {
+        AllocationStrategy::Equal => split_cents(remaining, &vec![1; targets.len()]),
+        AllocationStrategy::WeightedByExpectedPrice => {
+            let missing: Vec<&str> = targets
+                .iter()
+                .filter(|i| i.expected_price.map_or(true, |p| p <= 0.0))
+                .map(|i| i.id.as_str())
+                .collect();
+            if !missing.is_empty() {
+                return Err(format!("Expected price missing for: {}", missing.join(", ")));
+            }
+            let weights: Vec<i64> = targets
+                .iter()
+                .map(|i| to_cents(i.expected_price.unwrap_or(0.0)))
+                .collect();
+            split_cents(remaining, &weights)
+        }
+        AllocationStrategy::Manual { overrides } => {
+            let missing: Vec<&str> = targets
+                .iter()
+                .filter(|i| !overrides.contains_key(&i.id))
+                .map(|i| i.id.as_str())
+                .collect();
+            if !missing.is_empty() {
+                return Err(format!("Manual amount missing for: {}", missing.join(", ")));
+            }
+            let unexpected: Vec<&str> = overrides
+                .keys()
+                .filter(|id| !targets.iter().any(|t| &t.id == *id))
+                .map(|id| id.as_str())
+                .collect();
+            if !unexpected.is_empty() {
+                return Err(format!(
+                    "Manual amounts given for items that are sold, gifts, or not in the lot: {}",
+                    unexpected.join(", ")
+                ));
+            }
+            let shares: Vec<i64> = targets.iter().map(|i| to_cents(overrides[&i.id])).collect();
+            if shares.iter().any(|c| *c < 0) {
+                return Err("Manual amounts can't be negative".to_string());
+            }
+            let sum: i64 = shares.iter().sum();
+            if sum != remaining {
+                return Err(format!(
+                    "Manual amounts add up to ${:.2} but ${:.2} needs allocating",
+                    from_cents(sum),
+                    from_cents(remaining)
+                ));
+            }
+            shares
+        }
+    };
+
+    let share_by_id: HashMap<&str, i64> = targets
+        .iter()
+        .zip(shares)
+        .map(|(item, cents)| (item.id.as_str(), cents))
+        .collect();
+
+    let method = strategy.method_name();
+    let allocations = items
+        .iter()
+        .map(|item| {
+            let previous = item.current_cost.map(to_cents);
+            let (cents, how) = if item.gift {
+                (0, "gift")
+            } else if item.sold {
+                (previous.unwrap_or(0), "sold")
+            } else {
+                (share_by_id[item.id.as_str()], method)
+            };
+            let method_changed = !item.sold && !item.gift && item.current_method.as_deref() != Some(method);
+            LotAllocation {
+                item_id: item.id.clone(),
+                cost: from_cents(cents),
+                method: how.to_string(),
+                changed: previous != Some(cents) || method_changed,
+            }
+        })
+        .collect();
+
+    Ok(LotAllocationResult {
+        lot_total: from_cents(total),
+        kept_on_sold_items: from_cents(kept),
+        allocated_to_unsold: from_cents(remaining),
+        method,
+        items: allocations,
+    })
+}
+
+pub(crate) async fn rows(req: reqwest::RequestBuilder) -> Result<Vec<Value>, String> {
+    match supabase::send(req).await? {
+        Value::Array(rows) => Ok(rows),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// The lot's total and its items (not the ones in the trash).
+async fn load(session: &SupabaseSession, client: &reqwest::Client, lot_id: &str) -> Result<(f64, Vec<LotItem>), String> {
+    let lot = rows(
+        session
+            .rest(client, Method::GET, "purchase_lots")
+            .query(&[("select", "id,total_cost"), ("id", &format!("eq.{lot_id}"))]),
+    )
+    .await?;
+    let lot_total = lot.first().and_then(|l| number(&l["total_cost"])).ok_or("Lot not found")?;
+
+    let items = rows(session.rest(client, Method::GET, "inventory").query(&[
+        ("select", "id,cost,cost_allocation_method,expected_price,quantity,listing_id,acquisition_type"),
+        ("lot_id", &format!("eq.{lot_id}")),
+        ("deleted_at", "is.null"),
+        ("order", "created_at"),
+    ]))
+    .await?;
+
+    let listing_ids: Vec<&str> = items.iter().filter_map(|i| i["listing_id"].as_str()).collect();
+    let mut sold_listings = HashSet::new();
+    for chunk in listing_ids.chunks(CHUNK) {
+        let list: Vec<String> = chunk.iter().map(|id| format!("\"{}\"", id.replace(['"', '\\'], ""))).collect();
+        let sales = rows(session.rest(client, Method::GET, "sales").query(&[
+            ("select", "listing_id".to_string()),
+            ("listing_id", format!("in.({})", list.join(","))),
+        ]))
+        .await?;
+        sold_listings.extend(sales.iter().filter_map(|s| s["listing_id"].as_str().map(str::to_string)));
+    }
+
+    Ok((lot_total, items.iter().filter_map(|row| LotItem::from_row(row, &sold_listings)).collect()))
+}
+
+/// Allocate the lot's cost with `strategy` and save it; with `dry_run` only
+/// report what would change.
+#[tauri::command]
+pub async fn allocate_lot_cost(
+    session: SupabaseSession,
+    lot_id: String,
+    strategy: AllocationStrategy,
+    dry_run: Option<bool>,
+) -> Result<Private<LotAllocationResult>, String> {
+    let client = supabase::client()?;
+    allocate_and_save(&session, &client, &lot_id, &strategy, dry_run.unwrap_or(false)).await.map(Private::new)
+}
+
+pub(crate) async fn allocate_and_save(
+    session: &SupabaseSession,
+    client: &reqwest::Client,
+    lot_id: &str,
+    strategy: &AllocationStrategy,
+    dry_run: bool,
+) -> Result<LotAllocationResult, String> {
+    let (lot_total, items) = load(session, client, lot_id).await?;
+    let result = allocate(lot_total, &items, strategy)?;
+    if dry_run {
+        return Ok(result);
+    }
+
+    let now = crate::netlog::iso8601(crate::netlog::unix_millis());
+    let changed: Vec<&LotAllocation> = result.items.iter().filter(|a| a.changed && a.method != "sold").collect();
+    for (done, allocation) in changed.iter().enumerate() {
+        let patch = if allocation.method == "gift" {
+            json!({ "cost": 0, "updated_at": now })
+        } else {
+            json!({ "cost": allocation.cost, "cost_allocation_method": allocation.method, "updated_at": now })
+        };
+        // Scoped to the lot, so an item moved out meanwhile isn't touched.
+        let req = session
+            .rest(client, Method::PATCH, "inventory")
+            .query(&[("id", format!("eq.{}", allocation.item_id)), ("lot_id", format!("eq.{lot_id}"))])
+            .json(&patch);
+        if let Err(e) = supabase::send(req).await {
+            return Err(format!("Saved {done} of {} item costs before failing: {e}", changed.len()));
+        }
+    }
+    let req = session
+        .rest(client, Method::PATCH, "purchase_lots")
+        .query(&[("id", format!("eq.{lot_id}"))])
+        .json(&json!({ "allocation_method": result.method, "allocated_at": now, "updated_at": now }));
+    supabase::send(req).await?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, cost: Option<f64>, expected: Option<f64>) -> LotItem {
+        LotItem {
+            id: id.to_string(),
+            expected_price: expected,
+            current_cost: cost,
+            current_method: None,
+            sold: false,
+            gift: false,
+        }
+    }
+
+    fn costs(result: &LotAllocationResult) -> Vec<(&str, f64, &str)> {
+        result.items.iter().map(|a| (a.item_id.as_str(), a.cost, a.method.as_str())).collect()
+    }
+
+    #[test]
+    fn equal_split_hands_out_every_cent() {
+        let items = [item("a", None, None), item("b", None, None), item("c", None, None)];
+        let result = allocate(10.0, &items, &AllocationStrategy::Equal).unwrap();
+        assert_eq!(costs(&result), [("a", 3.34, "equal"), ("b", 3.33, "equal"), ("c", 3.33, "equal")]);
+        assert!(result.items.iter().all(|a| a.changed));
+    }
+
+    #[test]
+    fn sold_items_and_gifts_are_left_out_of_rebalancing() {
+        let mut sold = item("sold", Some(15.0), Some(30.0));
+        sold.sold = true;
+        let mut gift = item("gift", Some(2.0), Some(10.0));
+        gift.gift = true;
+        let items = [sold, gift, item("x", Some(5.0), Some(10.0)), item("y", None, Some(30.0))];
+        let result = allocate(40.0, &items, &AllocationStrategy::WeightedByExpectedPrice).unwrap();
+        assert_eq!(result.kept_on_sold_items, 15.0);
+        assert_eq!(result.allocated_to_unsold, 25.0);
+        assert_eq!(
+            costs(&result),
+            [
+                ("sold", 15.0, "sold"),
+                ("gift", 0.0, "gift"),
+                ("x", 6.25, "weighted_by_expected_price"),
+                ("y", 18.75, "weighted_by_expected_price")
+            ]
+        );
+        assert!(!result.items[0].changed);
+    }
+
+    #[test]
+    fn unchanged_costs_still_record_a_new_method() {
+        let mut a = item("a", Some(5.0), None);
+        a.current_method = Some("equal".to_string());
+        let result = allocate(5.0, &[a], &AllocationStrategy::Equal).unwrap();
+        assert!(!result.items[0].changed);
+        let overrides = HashMap::from([("a".to_string(), 5.0)]);
+        let mut a = item("a", Some(5.0), None);
+        a.current_method = Some("equal".to_string());
+        let result = allocate(5.0, &[a], &AllocationStrategy::Manual { overrides }).unwrap();
+        assert!(result.items[0].changed);
+    }
+
+    #[test]
+    fn invalid_allocations_are_refused() {
+        let items = [item("a", None, None), item("b", None, Some(10.0))];
+        let err = allocate(10.0, &items, &AllocationStrategy::WeightedByExpectedPrice).err().unwrap();
+        assert_eq!(err, "Expected price missing for: a");
+        let overrides = HashMap::from([("a".to_string(), 4.0), ("b".to_string(), 5.0)]);
+        let err = allocate(10.0, &items, &AllocationStrategy::Manual { overrides }).err().unwrap();
+        assert!(err.contains("add up"), "{err}");
+        let mut sold = item("s", Some(12.0), None);
+        sold.sold = true;
+        assert!(allocate(10.0, &[sold], &AllocationStrategy::Equal).is_err());
+    }
+
+    #[test]
+    fn sold_comes_from_quantity_or_a_sale() {
+        let sold_listings = HashSet::from(["l1".to_string()]);
+        let row = |quantity: i64, listing: &str| {
+            json!({ "id": "i", "cost": "4.50", "quantity": quantity, "listing_id": listing, "acquisition_type": "lot" })
+        };
+        let fresh = LotItem::from_row(&row(1, "l2"), &sold_listings).unwrap();
+        assert!(!fresh.sold && !fresh.gift);
+        assert_eq!(fresh.current_cost, Some(4.5));
+        assert!(LotItem::from_row(&row(0, "l2"), &sold_listings).unwrap().sold);
+        assert!(LotItem::from_row(&row(1, "l1"), &sold_listings).unwrap().sold);
+        let gift = json!({ "id": "g", "acquisition_type": "gift" });
+        assert!(LotItem::from_row(&gift, &sold_listings).unwrap().gift);
+    }
+}
@@ -0,0 +1,134 @@
+// ── Idle maintenance ───────────────────────────────────────────────────────
+// FlipTools is often left running for weeks. Once the user has been idle for
+// a while (no `ping_activity` from the frontend and no window focus), trim the
+// in-memory buffers back to a floor so long sessions don't creep upwards.
+// Runs at most once per idle period and reports before/after numbers in a
+// "maintenance-ran" event.
+//
+// HTTP clients are built per call and dropped afterwards, so there is no
+// pooled keep-alive connection to recycle, and all persistent data lives in
+// Supabase or JSON files, so there is no local database to checkpoint.
+
+use crate::metrics::{self, ProcessStats};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+const STORE_NAME: &str = "maintenance";
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MaintenanceSettings {
+    /// Minutes without activity before maintenance runs; 0 disables it.
+    pub idle_minutes: u64,
+    /// Entries kept in in-memory ring buffers (webhook delivery log, network
+    /// log, ...).
+    pub buffer_floor: usize,
+}
+
+impl Default for MaintenanceSettings {
+    fn default() -> Self {
+        MaintenanceSettings { idle_minutes: 15, buffer_floor: 50 }
+    }
+}
+
+pub struct MaintenanceState {
+    settings: Mutex<MaintenanceSettings>,
+    last_activity: Mutex<Instant>,
+    /// Set once maintenance has run for the current idle period.
+    ran_since_activity: Mutex<bool>,
+}
+
+impl MaintenanceState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        MaintenanceState {
+            settings: Mutex::new(crate::store::load(app, STORE_NAME)),
+            last_activity: Mutex::new(Instant::now()),
+            ran_since_activity: Mutex::new(false),
+        }
+    }
+
+    pub fn touch(&self) {
+        *self.last_activity.lock().unwrap() = Instant::now();
+        *self.ran_since_activity.lock().unwrap() = false;
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct MaintenanceReport {
+    at: u64,
+    idle_secs: u64,
+    /// Ring-buffer / cache entries released.
+    entries_dropped: usize,
+    before: ProcessStats,
+    after: ProcessStats,
+}
+
+fn run(app: &tauri::AppHandle) -> MaintenanceReport {
+    let state = app.state::<MaintenanceState>();
+    let floor = state.settings.lock().unwrap().buffer_floor;
+    let idle_secs = state.last_activity.lock().unwrap().elapsed().as_secs();
+    let before = metrics::process_stats();
+
+    let entries_dropped =
+        app.state::<crate::webhooks::WebhookState>().compact(floor) + crate::netlog::NETLOG.compact(floor);
+
+    let report = MaintenanceReport {
+        at: crate::unix_now(),
+        idle_secs,
+        entries_dropped,
+        before,
+        after: metrics::process_stats(),
+    };
+    *state.ran_since_activity.lock().unwrap() = true;
+    let _ = app.emit("maintenance-ran", &report);
+    report
+}
+
+/// Check once a minute whether the idle threshold has passed.
+pub fn start(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            let due = {
+                let state = app.state::<MaintenanceState>();
+                let idle_minutes = state.settings.lock().unwrap().idle_minutes;
+                let idle = state.last_activity.lock().unwrap().elapsed();
+                let ran = *state.ran_since_activity.lock().unwrap();
+                idle_minutes > 0 && !ran && idle >= Duration::from_secs(idle_minutes * 60)
+            };
+            if due {
+                let report = run(&app);
+                log::info!("idle maintenance dropped {} entries", report.entries_dropped);
+            }
+        }
+    });
+}
+
+/// Called by the frontend on user input (debounced on its side).
+#[tauri::command]
+pub fn ping_activity(state: tauri::State<'_, MaintenanceState>) {
+    state.touch();
+}
+
+#[tauri::command]
+pub fn get_maintenance_settings(state: tauri::State<'_, MaintenanceState>) -> MaintenanceSettings {
+    state.settings.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_maintenance_settings(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, MaintenanceState>,
+    settings: MaintenanceSettings,
+) -> Result<(), String> {
+    let mut current = state.settings.lock().unwrap();
+    *current = settings;
+    crate::store::save(&app, STORE_NAME, &*current)
+}
+
+#[tauri::command]
+pub fn run_maintenance_now(app: tauri::AppHandle) -> MaintenanceReport {
+    run(&app)
+}
@@ -0,0 +1,281 @@
+// ── Marketplace limits ─────────────────────────────────────────────────────
+// One registry of per-marketplace listing limits (title and description
+// length, photo counts, hashtags, price bounds, video support) shared by the
+// frontend and every Rust-side validator, so the two can't drift apart.
+//
+// Limits ship embedded (marketplace_limits.json) and carry a version stamp.
+// When a marketplace changes a limit before a release ships, settings can
+// override individual fields; overrides are kept separately so the snapshot
+// shows which values are local.
+
+use crate::depop::attributes::FieldError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+
+const STORE_NAME: &str = "marketplace_limit_overrides";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Limits {
+    /// None where the marketplace has no title field or no practical limit.
+    pub title_max_chars: Option<usize>,
+    pub description_max_chars: Option<usize>,
+    pub min_photos: usize,
+    pub max_photos: usize,
+    /// Hashtags (Depop) or tags (Etsy).
+    pub max_hashtags: Option<usize>,
+    pub min_price: Option<f64>,
+    pub max_price: Option<f64>,
+    pub video: bool,
+    #[serde(default)]
+    pub video_max_seconds: Option<f64>,
+    #[serde(default)]
+    pub video_max_bytes: Option<u64>,
+    /// Accepted containers (`mp4`, `mov`).
+    #[serde(default)]
+    pub video_formats: Vec<String>,
+    /// Accepted video codecs as MP4 sample-entry codes (`avc1`, `hvc1`, ...).
+    #[serde(default)]
+    pub video_codecs: Vec<String>,
+    /// Regions international shipping can be priced for; empty where the
+    /// marketplace has no per-region shipping.
+    #[serde(default)]
+    pub shipping_regions: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Registry {
+    version: String,
+    marketplaces: BTreeMap<String, Limits>,
+}
+
+/// Fields to replace in the embedded limits; None keeps the shipped value.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitOverrides {
+    pub title_max_chars: Option<usize>,
+    pub description_max_chars: Option<usize>,
+    pub min_photos: Option<usize>,
+    pub max_photos: Option<usize>,
+    pub max_hashtags: Option<usize>,
+    pub min_price: Option<f64>,
+    pub max_price: Option<f64>,
+    pub video: Option<bool>,
+    pub video_max_seconds: Option<f64>,
+    pub video_max_bytes: Option<u64>,
+}
+
+impl LimitOverrides {
+    fn fields(&self) -> Vec<&'static str> {
+        [
+            ("title_max_chars", self.title_max_chars.is_some()),
+            ("description_max_chars", self.description_max_chars.is_some()),
+            ("min_photos", self.min_photos.is_some()),
+            ("max_photos", self.max_photos.is_some()),
+            ("max_hashtags", self.max_hashtags.is_some()),
+            ("min_price", self.min_price.is_some()),
+            ("max_price", self.max_price.is_some()),
+            ("video", self.video.is_some()),
+            ("video_max_seconds", self.video_max_seconds.is_some()),
+            ("video_max_bytes", self.video_max_bytes.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, set)| set.then_some(name))
+        .collect()
+    }
+
+    fn apply(&self, base: &Limits) -> Limits {
+        Limits {
+            title_max_chars: self.title_max_chars.or(base.title_max_chars),
+            description_max_chars: self.description_max_chars.or(base.description_max_chars),
+            min_photos: self.min_photos.unwrap_or(base.min_photos),
+            max_photos: self.max_photos.unwrap_or(base.max_photos),
+            max_hashtags: self.max_hashtags.or(base.max_hashtags),
+            min_price: self.min_price.or(base.min_price),
+            max_price: self.max_price.or(base.max_price),
+            video: self.video.unwrap_or(base.video),
+            video_max_seconds: self.video_max_seconds.or(base.video_max_seconds),
+            video_max_bytes: self.video_max_bytes.or(base.video_max_bytes),
+            video_formats: base.video_formats.clone(),
+            video_codecs: base.video_codecs.clone(),
+            shipping_regions: base.shipping_regions.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct MarketplaceLimits {
+    pub marketplace: String,
+    /// Version of the embedded registry the values started from.
+    pub version: String,
+    pub limits: Limits,
+    /// Fields whose value comes from a local override.
+    pub overridden: Vec<&'static str>,
+}
+
+#[derive(Serialize)]
+pub struct LimitsSnapshot {
+    version: String,
+    marketplaces: Vec<MarketplaceLimits>,
+    overrides: BTreeMap<String, LimitOverrides>,
+}
+
+#[derive(Default)]
+pub struct LimitsState {
+    overrides: Mutex<BTreeMap<String, LimitOverrides>>,
+}
+
+impl LimitsState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        LimitsState { overrides: Mutex::new(crate::store::load(app, STORE_NAME)) }
+    }
+
+    /// Effective limits for `marketplace`, or None for a marketplace the
+    /// registry doesn't know.
+    pub fn get(&self, marketplace: &str) -> Option<MarketplaceLimits> {
+        let registry = registry();
+        let base = registry.marketplaces.get(marketplace)?;
+        let overrides = self.overrides.lock().unwrap().get(marketplace).cloned().unwrap_or_default();
+        Some(MarketplaceLimits {
+            marketplace: marketplace.to_string(),
+            version: registry.version,
+            limits: overrides.apply(base),
+            overridden: overrides.fields(),
+        })
+    }
+
+    /// Every marketplace's effective limits plus the raw overrides, for
+    /// diagnostics.
+    pub fn snapshot(&self) -> LimitsSnapshot {
+        let registry = registry();
+        LimitsSnapshot {
+            marketplaces: registry.marketplaces.keys().filter_map(|m| self.get(m)).collect(),
+            version: registry.version,
+            overrides: self.overrides.lock().unwrap().clone(),
+        }
+    }
+}
+
+fn registry() -> Registry {
+    serde_json::from_str(include_str!("marketplace_limits.json")).expect("embedded marketplace limits are valid JSON")
+}
+
+/// Distinct hashtags across the tag list and `#words` in the description.
+fn hashtag_count(tags: &[String], description: &str) -> usize {
+    let inline = description
+        .split_whitespace()
+        .filter_map(|w| w.strip_prefix('#'))
+        .map(|w| w.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '_'));
+    tags.iter()
+        .map(|t| t.trim().trim_start_matches('#'))
+        .chain(inline)
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+        .collect::<BTreeSet<_>>()
+        .len()
+}
+
+/// Check a listing's text, photos, price and video against `limits`. A
+/// missing price is left to the required-field checks.
+pub fn check(
+    limits: &MarketplaceLimits,
+    title: &str,
+    description: &str,
+    tags: &[String],
+    photos: usize,
+    price: f64,
+    has_video: bool,
+) -> Vec<FieldError> {
+    let name = &limits.marketplace;
+    let l = &limits.limits;
+    let mut errors = Vec::new();
+    let mut push = |field: &str, message: String| errors.push(FieldError { field: field.to_string(), message });
+
+    if let Some(max) = l.title_max_chars.filter(|max| title.chars().count() > *max) {
+        push("title", format!("{name} titles are at most {max} characters"));
+    }
+    if let Some(max) = l.description_max_chars.filter(|max| description.chars().count() > *max) {
+        push("description", format!("{name} descriptions are at most {max} characters"));
+    }
+    if photos < l.min_photos {
+        push("photos", format!("{name} needs at least {} photo(s)", l.min_photos));
+    }
+    if photos > l.max_photos {
+        push("photos", format!("{name} allows at most {} photos", l.max_photos));
+    }
+    if let Some(max) = l.max_hashtags.filter(|max| hashtag_count(tags, description) > *max) {
+        push("tags", format!("{name} allows at most {max} hashtags"));
+    }
+    if price > 0.0 {
+        if let Some(min) = l.min_price.filter(|min| price < *min) {
+            push("price", format!("{name} prices start at {min:.2}"));
+        }
+        if let Some(max) = l.max_price.filter(|max| price > *max) {
+            push("price", format!("{name} prices are capped at {max:.2}"));
+        }
+    }
+    if has_video && !l.video {
+        push("video", format!("{name} listings can't carry a video"));
+    }
+    errors
+}
+
+#[tauri::command]
+pub fn get_marketplace_limits(
+    state: tauri::State<'_, LimitsState>,
+    marketplace: String,
+) -> Result<MarketplaceLimits, String> {
+    state.get(&marketplace).ok_or_else(|| format!("No limits known for '{marketplace}'"))
+}
+
+#[tauri::command]
+pub fn get_marketplace_limits_snapshot(state: tauri::State<'_, LimitsState>) -> LimitsSnapshot {
+    state.snapshot()
+}
+
+/// Replace the local overrides for `marketplace`. An override with no fields
+/// set clears it.
+#[tauri::command]
+pub fn set_marketplace_limit_overrides(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, LimitsState>,
+    marketplace: String,
+    overrides: LimitOverrides,
+) -> Result<MarketplaceLimits, String> {
+    let registry = registry();
+    let base = registry
+        .marketplaces
+        .get(&marketplace)
+        .ok_or_else(|| format!("No limits known for '{marketplace}'"))?;
+    let merged = overrides.apply(base);
+    if merged.max_photos == 0 || merged.min_photos > merged.max_photos {
+        return Err("Photo limits need 1 <= min <= max".to_string());
+    }
+    if [merged.title_max_chars, merged.description_max_chars].contains(&Some(0)) {
+        return Err("Length limits must be at least 1 character".to_string());
+    }
+    for price in [overrides.min_price, overrides.max_price].into_iter().flatten() {
+        if !price.is_finite() || price < 0.0 {
+            return Err("Price bounds must be non-negative amounts".to_string());
+        }
+    }
+    if overrides.video_max_seconds.is_some_and(|s| !s.is_finite() || s <= 0.0) || overrides.video_max_bytes == Some(0) {
+        return Err("Video limits must be positive".to_string());
+    }
+    if let (Some(min), Some(max)) = (merged.min_price, merged.max_price) {
+        if min > max {
+            return Err("Minimum price is above the maximum".to_string());
+        }
+    }
+
+    {
+        let mut all = state.overrides.lock().unwrap();
+        if overrides.fields().is_empty() {
+            all.remove(&marketplace);
+        } else {
+            all.insert(marketplace.clone(), overrides);
+        }
+        crate::store::save(&app, STORE_NAME, &*all)?;
+    }
+    state.get(&marketplace).ok_or_else(|| format!("No limits known for '{marketplace}'"))
+}
@@ -0,0 +1,360 @@
+// ── Metrics registry ───────────────────────────────────────────────────────
+// Process-wide counters, gauges and latency histograms for spotting slow
+// growth over time (syncs getting slower as the database grows, memory creep).
+// Everything on the hot path is a relaxed atomic add into pre-allocated
+// buckets, so instrumenting a call costs a few nanoseconds.
+//
+// Timings that only the frontend can see (Supabase queries, sync passes) are
+// reported through `record_timing` and kept per name.
+//
+// `get_metrics` returns a structured snapshot; the local API serves the same
+// data in Prometheus text format at GET /metrics.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Histogram bucket upper bounds in milliseconds (a final +Inf bucket is implied).
+const BUCKETS_MS: [u64; 14] = [1, 5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000];
+
+/// Cap on distinct frontend timing names, so a buggy caller can't grow the map forever.
+const MAX_CLIENT_TIMINGS: usize = 64;
+
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    const fn new() -> Self {
+        Counter(AtomicU64::new(0))
+    }
+
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    pub fn add(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+pub struct Gauge(AtomicI64);
+
+impl Gauge {
+    const fn new() -> Self {
+        Gauge(AtomicI64::new(0))
+    }
+
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dec(&self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+pub struct Histogram {
+    /// Non-cumulative counts; index BUCKETS_MS.len() is the +Inf bucket.
+    buckets: [AtomicU64; BUCKETS_MS.len() + 1],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    const fn new() -> Self {
+        // Array-repeat needs a const item on our MSRV (inline const is 1.79+).
+        #[allow(clippy::declare_interior_mutable_const)]
+        const ZERO: AtomicU64 = AtomicU64::new(0);
+        Histogram {
+            buckets: [ZERO; BUCKETS_MS.len() + 1],
+            count: ZERO,
+            sum_micros: ZERO,
+        }
+    }
+
+    pub fn observe(&self, elapsed: Duration) {
+        let micros = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        let idx = BUCKETS_MS
+            .iter()
+            .position(|le| micros <= le * 1_000)
+            .unwrap_or(BUCKETS_MS.len());
+        self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    /// Observe the time since `started`.
+    pub fn since(&self, started: Instant) {
+        self.observe(started.elapsed());
+    }
+
+    fn snapshot(&self) -> HistogramSnapshot {
+        let counts: Vec<u64> = self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).collect();
+        let count: u64 = counts.iter().sum();
+        let sum_ms = self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000.0;
+
+        let mut cumulative = 0;
+        let buckets = counts
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                cumulative += c;
+                (BUCKETS_MS.get(i).copied(), cumulative)
+            })
+            .collect::<Vec<_>>();
+        // Percentiles are bucket upper bounds: coarse, but stable across versions.
+        let percentile = |q: f64| -> Option<u64> {
+            if count == 0 {
+                return None;
+            }
+            let rank = (q * count as f64).ceil().max(1.0) as u64;
+            buckets
+                .iter()
+                .find(|(_, cum)| *cum >= rank)
+                .map(|(le, _)| le.unwrap_or(u64::MAX))
+        };
+
+        HistogramSnapshot {
+            count,
+            sum_ms,
+            mean_ms: if count > 0 { sum_ms / count as f64 } else { 0.0 },
+            p50_ms: percentile(0.50),
+            p95_ms: percentile(0.95),
+            p99_ms: percentile(0.99),
+            buckets: buckets
+                .into_iter()
+                .map(|(le, count)| BucketSnapshot { le_ms: le, count })
+                .collect(),
+        }
+    }
+}
+
+pub struct Metrics {
+    pub native_fetch: Histogram,
+    pub native_fetch_errors: Counter,
+    pub depop_api: Histogram,
+    pub depop_api_errors: Counter,
+    pub write_queue_depth: Gauge,
+    pub write_queue_wait: Histogram,
+    pub image_process: Histogram,
+    pub images_processed: Counter,
+    pub image_cache_hits: Counter,
+    pub image_errors: Counter,
+    pub local_api_requests: Counter,
+    client: Mutex<BTreeMap<String, Arc<Histogram>>>,
+}
+
+pub static METRICS: Metrics = Metrics {
+    native_fetch: Histogram::new(),
+    native_fetch_errors: Counter::new(),
+    depop_api: Histogram::new(),
+    depop_api_errors: Counter::new(),
+    write_queue_depth: Gauge::new(),
+    write_queue_wait: Histogram::new(),
+    image_process: Histogram::new(),
+    images_processed: Counter::new(),
+    image_cache_hits: Counter::new(),
+    image_errors: Counter::new(),
+    local_api_requests: Counter::new(),
+    client: Mutex::new(BTreeMap::new()),
+};
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Mark process start for the uptime figure. Called once from setup.
+pub fn init() {
+    STARTED.get_or_init(Instant::now);
+}
+
+#[derive(Serialize)]
+pub struct BucketSnapshot {
+    /// Upper bound in ms; null for the +Inf bucket.
+    le_ms: Option<u64>,
+    /// Cumulative count of observations <= le_ms.
+    count: u64,
+}
+
+#[derive(Serialize)]
+pub struct HistogramSnapshot {
+    count: u64,
+    sum_ms: f64,
+    mean_ms: f64,
+    p50_ms: Option<u64>,
+    p95_ms: Option<u64>,
+    p99_ms: Option<u64>,
+    buckets: Vec<BucketSnapshot>,
+}
+
+#[derive(Clone, Serialize, Default)]
+pub struct ProcessStats {
+    pub rss_bytes: Option<u64>,
+    pub open_fds: Option<u64>,
+    pub threads: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct MetricsSnapshot {
+    uptime_secs: u64,
+    process: ProcessStats,
+    counters: BTreeMap<&'static str, u64>,
+    gauges: BTreeMap<&'static str, i64>,
+    histograms: BTreeMap<&'static str, HistogramSnapshot>,
+    /// Timings reported by the frontend via `record_timing`.
+    client_timings: BTreeMap<String, HistogramSnapshot>,
+}
+
+/// Best-effort process stats. Linux reads /proc; other platforms report null.
+pub fn process_stats() -> ProcessStats {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+        let field = |name: &str| -> Option<u64> {
+            status
+                .lines()
+                .find(|l| l.starts_with(name))
+                .and_then(|l| l.split_whitespace().nth(1))
+                .and_then(|v| v.parse().ok())
+        };
+        ProcessStats {
+            rss_bytes: field("VmRSS:").map(|kb| kb * 1024),
+            open_fds: std::fs::read_dir("/proc/self/fd").ok().map(|d| d.count() as u64),
+            threads: field("Threads:"),
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        ProcessStats::default()
+    }
+}
+
+impl Metrics {
+    fn counters(&self) -> BTreeMap<&'static str, u64> {
+        BTreeMap::from([
+            ("native_fetch_errors", self.native_fetch_errors.get()),
+            ("depop_api_errors", self.depop_api_errors.get()),
+            ("images_processed", self.images_processed.get()),
+            ("image_cache_hits", self.image_cache_hits.get()),
+            ("image_errors", self.image_errors.get()),
+            ("local_api_requests", self.local_api_requests.get()),
+        ])
+    }
+
+    fn gauges(&self) -> BTreeMap<&'static str, i64> {
+        BTreeMap::from([("write_queue_depth", self.write_queue_depth.get())])
+    }
+
+    fn histograms(&self) -> BTreeMap<&'static str, HistogramSnapshot> {
+        BTreeMap::from([
+            ("native_fetch", self.native_fetch.snapshot()),
+            ("depop_api", self.depop_api.snapshot()),
+            ("write_queue_wait", self.write_queue_wait.snapshot()),
+            ("image_process", self.image_process.snapshot()),
+        ])
+    }
+
+    fn client_timings(&self) -> BTreeMap<String, HistogramSnapshot> {
+        self.client
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, h)| (name.clone(), h.snapshot()))
+            .collect()
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            uptime_secs: STARTED.get().map(|s| s.elapsed().as_secs()).unwrap_or(0),
+            process: process_stats(),
+            counters: self.counters(),
+            gauges: self.gauges(),
+            histograms: self.histograms(),
+            client_timings: self.client_timings(),
+        }
+    }
+}
+
+fn write_histogram(out: &mut String, metric: &str, labels: &str, h: &HistogramSnapshot) {
+    let sep = if labels.is_empty() { "" } else { "," };
+    for b in &h.buckets {
+        let le = match b.le_ms {
+            Some(ms) => format!("{}", ms as f64 / 1_000.0),
+            None => "+Inf".to_string(),
+        };
+        let _ = writeln!(out, "{metric}_bucket{{{labels}{sep}le=\"{le}\"}} {}", b.count);
+    }
+    let braces = if labels.is_empty() { String::new() } else { format!("{{{labels}}}") };
+    let _ = writeln!(out, "{metric}_sum{braces} {}", h.sum_ms / 1_000.0);
+    let _ = writeln!(out, "{metric}_count{braces} {}", h.count);
+}
+
+/// Prometheus text exposition format (v0.0.4).
+pub fn prometheus_text() -> String {
+    let snap = METRICS.snapshot();
+    let mut out = String::new();
+
+    let _ = writeln!(out, "# TYPE fliptools_uptime_seconds gauge\nfliptools_uptime_seconds {}", snap.uptime_secs);
+    if let Some(rss) = snap.process.rss_bytes {
+        let _ = writeln!(out, "# TYPE fliptools_process_resident_memory_bytes gauge\nfliptools_process_resident_memory_bytes {rss}");
+    }
+    if let Some(fds) = snap.process.open_fds {
+        let _ = writeln!(out, "# TYPE fliptools_process_open_fds gauge\nfliptools_process_open_fds {fds}");
+    }
+    if let Some(threads) = snap.process.threads {
+        let _ = writeln!(out, "# TYPE fliptools_process_threads gauge\nfliptools_process_threads {threads}");
+    }
+    for (name, value) in &snap.counters {
+        let _ = writeln!(out, "# TYPE fliptools_{name}_total counter\nfliptools_{name}_total {value}");
+    }
+    for (name, value) in &snap.gauges {
+        let _ = writeln!(out, "# TYPE fliptools_{name} gauge\nfliptools_{name} {value}");
+    }
+    for (name, h) in &snap.histograms {
+        let metric = format!("fliptools_{name}_seconds");
+        let _ = writeln!(out, "# TYPE {metric} histogram");
+        write_histogram(&mut out, &metric, "", h);
+    }
+    if !snap.client_timings.is_empty() {
+        let _ = writeln!(out, "# TYPE fliptools_client_timing_seconds histogram");
+        for (name, h) in &snap.client_timings {
+            let label = format!("name=\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""));
+            write_histogram(&mut out, "fliptools_client_timing_seconds", &label, h);
+        }
+    }
+    out
+}
+
+#[tauri::command]
+pub fn get_metrics() -> MetricsSnapshot {
+    METRICS.snapshot()
+}
+
+/// Record a duration measured in the frontend (e.g. "sync.depop",
+/// "query.inventory") so it shows up next to the backend metrics.
+#[tauri::command]
+pub fn record_timing(name: String, duration_ms: f64) -> Result<(), String> {
+    if name.is_empty() || name.len() > 64 {
+        return Err("Timing name must be 1-64 characters".to_string());
+    }
+    if !duration_ms.is_finite() || duration_ms < 0.0 {
+        return Err("Duration must be a non-negative number".to_string());
+    }
+    let hist = {
+        let mut client = METRICS.client.lock().unwrap();
+        if !client.contains_key(&name) && client.len() >= MAX_CLIENT_TIMINGS {
+            return Err(format!("Too many distinct timing names (max {MAX_CLIENT_TIMINGS})"));
+        }
+        client.entry(name).or_insert_with(|| Arc::new(Histogram::new())).clone()
+    };
+    hist.observe(Duration::from_secs_f64(duration_ms / 1_000.0));
+    Ok(())
+}
@@ -0,0 +1,384 @@
+// ── Network log ────────────────────────────────────────────────────────────
+// Ring buffer of recent `native_fetch` / `native_download` traffic, exportable
+// as a HAR 1.2 file (`export_har`) that opens in any browser's devtools — the
+// thing to attach when a marketplace integration breaks.
+//
+// Credentials are redacted when an entry is recorded, not at export, so they
+// never sit in memory in the log. Bodies are only captured while the
+// `capture_bodies` debug setting is on; it is off on every launch, and both
+// `get_network_log_settings` and the exported file flag when an export may
+// contain sensitive data.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+const CAPACITY: usize = 500;
+/// Bodies longer than this are truncated in the log.
+const BODY_CAP: usize = 64 * 1024;
+const REDACTED: &str = "[redacted]";
+/// Request/response headers whose values are never logged.
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+    "x-auth-token",
+    "x-csrf-token",
+    "x-fliptools-signature",
+];
+
+pub static NETLOG: NetworkLog = NetworkLog {
+    entries: Mutex::new(VecDeque::new()),
+    capture_bodies: AtomicBool::new(false),
+};
+
+pub struct NetworkLog {
+    entries: Mutex<VecDeque<NetEntry>>,
+    capture_bodies: AtomicBool,
+}
+
+#[derive(Clone)]
+pub struct NetEntry {
+    /// Unix milliseconds.
+    pub started_ms: u64,
+    pub duration_ms: u64,
+    pub method: String,
+    pub url: String,
+    pub request_headers: Vec<(String, String)>,
+    pub request_body: Option<String>,
+    /// 0 when the request never got a response.
+    pub status: u16,
+    pub response_headers: Vec<(String, String)>,
+    pub response_body: Option<String>,
+    pub response_size: Option<u64>,
+    pub mime_type: String,
+    pub error: Option<String>,
+}
+
+pub fn unix_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Header pairs with credential values replaced.
+pub fn redact_headers<'a>(headers: impl IntoIterator<Item = (&'a str, &'a str)>) -> Vec<(String, String)> {
+    headers
+        .into_iter()
+        .map(|(k, v)| {
+            let value = if SENSITIVE_HEADERS.contains(&k.to_ascii_lowercase().as_str()) { REDACTED } else { v };
+            (k.to_string(), value.to_string())
+        })
+        .collect()
+}
+
+pub fn response_headers(headers: &reqwest::header::HeaderMap) -> Vec<(String, String)> {
+    redact_headers(headers.iter().map(|(k, v)| (k.as_str(), v.to_str().unwrap_or(""))))
+}
+
+fn cap_body(body: &str) -> String {
+    if body.len() <= BODY_CAP {
+        return body.to_string();
+    }
+    let mut end = BODY_CAP;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    body[..end].to_string()
+}
+
+impl NetworkLog {
+    pub fn capturing_bodies(&self) -> bool {
+        self.capture_bodies.load(Ordering::SeqCst)
+    }
+
+    /// Keep `body` only while body capture is on.
+    pub fn body(&self, body: Option<&str>) -> Option<String> {
+        self.capturing_bodies().then(|| body.map(cap_body)).flatten()
+    }
+
+    pub fn record(&self, entry: NetEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Trim to the newest `floor` entries (idle maintenance). Returns how many
+    /// were dropped.
+    pub fn compact(&self, floor: usize) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let excess = entries.len().saturating_sub(floor);
+        entries.drain(..excess);
+        entries.shrink_to_fit();
+        excess
+    }
+}
+
+/// ISO 8601 UTC timestamp for unix milliseconds.
+pub fn iso8601(ms: u64) -> String {
+    let secs = ms / 1000;
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    // Civil-from-days (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        ms % 1000
+    )
+}
+
+fn har_headers(headers: &[(String, String)]) -> Value {
+    headers.iter().map(|(name, value)| json!({ "name": name, "value": value })).collect()
+}
+
+fn har_query(url: &str) -> Value {
+    reqwest::Url::parse(url)
+        .map(|u| u.query_pairs().map(|(name, value)| json!({ "name": name, "value": value })).collect())
+        .unwrap_or_else(|_| json!([]))
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+}
+
+fn har_entry(e: &NetEntry) -> Value {
+    let mut request = json!({
+        "method": e.method,
+        "url": e.url,
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": har_headers(&e.request_headers),
+        "queryString": har_query(&e.url),
+        "headersSize": -1,
+        "bodySize": e.request_body.as_ref().map_or(-1, |b| b.len() as i64),
+    });
+    if let Some(body) = &e.request_body {
+        let mime = header(&e.request_headers, "content-type").unwrap_or("text/plain");
+        request["postData"] = json!({ "mimeType": mime, "text": body });
+    }
+
+    let mut content = json!({
+        "size": e.response_size.map_or(-1, |s| s as i64),
+        "mimeType": e.mime_type,
+    });
+    match &e.response_body {
+        Some(body) => content["text"] = json!(body),
+        None => content["comment"] = json!("body not captured"),
+    }
+
+    let mut entry = json!({
+        "startedDateTime": iso8601(e.started_ms),
+        "time": e.duration_ms,
+        "request": request,
+        "response": {
+            "status": e.status,
+            "statusText": reqwest::StatusCode::from_u16(e.status)
+                .ok()
+                .and_then(|s| s.canonical_reason())
+                .unwrap_or(""),
+            "httpVersion": "HTTP/1.1",
+            "cookies": [],
+            "headers": har_headers(&e.response_headers),
+            "content": content,
+            "redirectURL": header(&e.response_headers, "location").unwrap_or(""),
+            "headersSize": -1,
+            "bodySize": e.response_size.map_or(-1, |s| s as i64),
+        },
+        "cache": {},
+        "timings": { "send": 0, "wait": e.duration_ms, "receive": 0 },
+    });
+    if let Some(error) = &e.error {
+        entry["comment"] = json!(format!("error: {error}"));
+    }
+    entry
+}
+
+#[derive(Default, Deserialize)]
+pub struct HarFilter {
+    /// Substring the URL must contain (e.g. "depop.com").
+    #[serde(default)]
+    url_contains: Option<String>,
+    /// Only entries from the last N minutes.
+    #[serde(default)]
+    since_minutes: Option<u64>,
+    /// Only failures (transport errors and 4xx/5xx).
+    #[serde(default)]
+    errors_only: bool,
+}
+
+impl HarFilter {
+    fn matches(&self, e: &NetEntry, now_ms: u64) -> bool {
+        self.url_contains.as_deref().map_or(true, |s| e.url.contains(s))
+            && self.since_minutes.map_or(true, |m| now_ms.saturating_sub(e.started_ms) <= m * 60_000)
+            && (!self.errors_only || e.error.is_some() || e.status >= 400)
+    }
+}
+
+#[derive(Serialize)]
+pub struct HarExport {
+    path: String,
+    entries: usize,
+    /// True when any exported entry carries a captured body.
+    contains_bodies: bool,
+}
+
+#[derive(Serialize)]
+pub struct NetworkLogSettings {
+    capture_bodies: bool,
+    entries: usize,
+    /// Shown next to the toggle while it's on.
+    warning: Option<&'static str>,
+}
+
+const BODY_WARNING: &str =
+    "Body capture is on: exports include request/response bodies, which may contain personal or account data.";
+
+fn settings() -> NetworkLogSettings {
+    let capture_bodies = NETLOG.capturing_bodies();
+    NetworkLogSettings {
+        capture_bodies,
+        entries: NETLOG.entries.lock().unwrap().len(),
+        warning: capture_bodies.then_some(BODY_WARNING),
+    }
+}
+
+#[tauri::command]
+pub fn get_network_log_settings() -> NetworkLogSettings {
+    settings()
+}
+
+#[tauri::command]
+pub fn set_network_capture_bodies(enabled: bool) -> NetworkLogSettings {
+    NETLOG.capture_bodies.store(enabled, Ordering::SeqCst);
+    settings()
+}
+
+/// Write the matching log entries to `dest_path` as HAR 1.2.
+#[tauri::command]
+pub fn export_har(dest_path: String, filter: Option<HarFilter>) -> Result<HarExport, String> {
+    crate::privacy::ensure_off("export the network log")?;
+    let filter = filter.unwrap_or_default();
+    let now = unix_millis();
+    let entries: Vec<NetEntry> = NETLOG
+        .entries
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|e| filter.matches(e, now))
+        .cloned()
+        .collect();
+    let contains_bodies = entries.iter().any(|e| e.request_body.is_some() || e.response_body.is_some());
+
+    let mut log = json!({
+        "version": "1.2",
+        "creator": { "name": "FlipTools", "version": env!("CARGO_PKG_VERSION") },
+        "entries": entries.iter().map(har_entry).collect::<Vec<_>>(),
+    });
+    if contains_bodies {
+        log["comment"] = json!(BODY_WARNING);
+    }
+    let har = serde_json::to_string_pretty(&json!({ "log": log })).map_err(|e| e.to_string())?;
+    std::fs::write(&dest_path, har).map_err(|e| format!("write {dest_path}: {e}"))?;
+
+    Ok(HarExport { path: dest_path, entries: entries.len(), contains_bodies })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exported_har_carries_the_exchange_with_credentials_redacted() {
+        let mut response = reqwest::header::HeaderMap::new();
+        response.insert("content-type", "application/json".parse().unwrap());
+        response.insert("set-cookie", "session=s3cr3t-cookie; HttpOnly".parse().unwrap());
+        NETLOG.record(NetEntry {
+            started_ms: 1_719_705_600_250,
+            duration_ms: 182,
+            method: "POST".to_string(),
+            url: "https://har-export.depop.test/api/v2/search?q=levis&limit=2".to_string(),
+            request_headers: redact_headers([
+                ("Authorization", "Bearer s3cr3t-token"),
+                ("Cookie", "session=s3cr3t-cookie"),
+                ("Content-Type", "application/json"),
+                ("Accept", "application/json"),
+            ]),
+            request_body: Some(r#"{"query":"levis"}"#.to_string()),
+            status: 200,
+            response_headers: response_headers(&response),
+            response_body: Some(r#"{"items":[]}"#.to_string()),
+            response_size: Some(12),
+            mime_type: "application/json".to_string(),
+            error: None,
+        });
+
+        let dest = std::env::temp_dir().join(format!("fliptools-{}.har", hex::encode(rand::random::<[u8; 4]>())));
+        let filter = HarFilter { url_contains: Some("har-export.depop.test".to_string()), ..Default::default() };
+        let export = export_har(dest.to_string_lossy().into_owned(), Some(filter)).unwrap();
+        let text = std::fs::read_to_string(&dest).unwrap();
+        let _ = std::fs::remove_file(&dest);
+        assert_eq!((export.entries, export.contains_bodies), (1, true));
+        assert!(!text.contains("s3cr3t"), "a credential reached the export");
+
+        let har: Value = serde_json::from_str(&text).unwrap();
+        let log = &har["log"];
+        assert_eq!(log["version"], "1.2");
+        assert_eq!(log["creator"], json!({ "name": "FlipTools", "version": env!("CARGO_PKG_VERSION") }));
+        assert_eq!(log["comment"], BODY_WARNING);
+
+        let entry = &log["entries"][0];
+        assert_eq!(entry["startedDateTime"], "2024-06-30T00:00:00.250Z");
+        assert_eq!(entry["time"], 182);
+        assert_eq!(entry["timings"], json!({ "send": 0, "wait": 182, "receive": 0 }));
+
+        let request = &entry["request"];
+        assert_eq!((request["method"].as_str(), request["httpVersion"].as_str()), (Some("POST"), Some("HTTP/1.1")));
+        assert_eq!(request["url"], "https://har-export.depop.test/api/v2/search?q=levis&limit=2");
+        assert_eq!(
+            request["headers"],
+            json!([
+                { "name": "Authorization", "value": REDACTED },
+                { "name": "Cookie", "value": REDACTED },
+                { "name": "Content-Type", "value": "application/json" },
+                { "name": "Accept", "value": "application/json" },
+            ])
+        );
+        let query = json!([{ "name": "q", "value": "levis" }, { "name": "limit", "value": "2" }]);
+        assert_eq!(request["queryString"], query);
+        assert_eq!(request["postData"], json!({ "mimeType": "application/json", "text": r#"{"query":"levis"}"# }));
+        assert_eq!(request["bodySize"], 17);
+
+        let response = &entry["response"];
+        assert_eq!((response["status"].as_u64(), response["statusText"].as_str()), (Some(200), Some("OK")));
+        assert_eq!(
+            response["headers"],
+            json!([
+                { "name": "content-type", "value": "application/json" },
+                { "name": "set-cookie", "value": REDACTED },
+            ])
+        );
+        assert_eq!(
+            response["content"],
+            json!({ "size": 12, "mimeType": "application/json", "text": r#"{"items":[]}"# })
+        );
+        assert_eq!(response["redirectURL"], "");
+    }
+}
@@ -0,0 +1,339 @@
+// ── Marketplace notifications ──────────────────────────────────────────────
+// Marketplace events (from the frontend's pollers) come in through
+// `push_marketplace_event`. Offers, sales and messages become a desktop
+// notification straight away. Engagement (likes, saves, follows) is far too
+// chatty for that, so in digest mode it is buffered and summarised once per
+// window ("14 likes across 9 items, top: vintage Levi's 501"). Empty windows
+// send nothing, and digests wait out quiet hours.
+//
+// Per-listing engagement counts are kept in the listing-stats store as events
+// arrive, whether or not they're digested.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+const SETTINGS_STORE: &str = "notification_settings";
+const STATS_STORE: &str = "listing_stats";
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Like,
+    Save,
+    Follow,
+    Offer,
+    Sale,
+    Message,
+}
+
+impl EventKind {
+    /// Engagement can be digested; everything else is always delivered at once.
+    fn is_engagement(self) -> bool {
+        matches!(self, EventKind::Like | EventKind::Save | EventKind::Follow)
+    }
+
+    fn noun(self, n: usize) -> &'static str {
+        match (self, n == 1) {
+            (EventKind::Like, true) => "like",
+            (EventKind::Like, false) => "likes",
+            (EventKind::Save, true) => "save",
+            (EventKind::Save, false) => "saves",
+            (EventKind::Follow, true) => "new follower",
+            (EventKind::Follow, false) => "new followers",
+            (EventKind::Offer, _) => "offer",
+            (EventKind::Sale, _) => "sale",
+            (EventKind::Message, _) => "message",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MarketplaceEvent {
+    pub kind: EventKind,
+    pub marketplace: String,
+    #[serde(default)]
+    pub account: Option<String>,
+    #[serde(default)]
+    pub listing_id: Option<String>,
+    #[serde(default)]
+    pub listing_title: Option<String>,
+    /// Ready-made text for offer/sale/message notifications.
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct QuietHours {
+    /// Minutes after local midnight; the range may wrap past midnight.
+    pub start_minute: u16,
+    pub end_minute: u16,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DigestSettings {
+    pub digest_engagement: bool,
+    pub window_minutes: u64,
+    pub quiet_hours: Option<QuietHours>,
+    /// Local time offset from UTC, supplied by the frontend.
+    pub utc_offset_minutes: i32,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        DigestSettings { digest_engagement: true, window_minutes: 60, quiet_hours: None, utc_offset_minutes: 0 }
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ListingStat {
+    pub marketplace: String,
+    pub listing_id: String,
+    pub title: Option<String>,
+    pub likes: u64,
+    pub saves: u64,
+    pub last_engagement_at: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct DigestSummary {
+    at: u64,
+    title: String,
+    body: String,
+    events: usize,
+    items: usize,
+    counts: HashMap<EventKind, usize>,
+}
+
+pub struct NotificationState {
+    settings: Mutex<DigestSettings>,
+    buffer: Mutex<Vec<MarketplaceEvent>>,
+    window_started: Mutex<u64>,
+    stats: Mutex<BTreeMap<String, ListingStat>>,
+}
+
+impl NotificationState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        NotificationState {
+            settings: Mutex::new(crate::store::load(app, SETTINGS_STORE)),
+            buffer: Mutex::new(Vec::new()),
+            window_started: Mutex::new(crate::unix_now()),
+            stats: Mutex::new(crate::store::load(app, STATS_STORE)),
+        }
+    }
+
+    /// When a listing last got a like or save, if ever.
+    pub fn last_engagement(&self, marketplace: &str, listing_id: &str) -> Option<u64> {
+        self.stats
+            .lock()
+            .unwrap()
+            .get(&format!("{marketplace}:{listing_id}"))
+            .map(|s| s.last_engagement_at)
+    }
+
+    pub fn stat(&self, marketplace: &str, listing_id: &str) -> Option<ListingStat> {
+        self.stats.lock().unwrap().get(&format!("{marketplace}:{listing_id}")).cloned()
+    }
+}
+
+fn in_quiet_hours(settings: &DigestSettings, now: u64) -> bool {
+    let Some(q) = settings.quiet_hours else {
+        return false;
+    };
+    let local = (now / 60) as i64 + i64::from(settings.utc_offset_minutes);
+    let minute = local.rem_euclid(24 * 60) as u16;
+    if q.start_minute <= q.end_minute {
+        (q.start_minute..q.end_minute).contains(&minute)
+    } else {
+        minute >= q.start_minute || minute < q.end_minute
+    }
+}
+
+pub fn show(app: &tauri::AppHandle, title: &str, body: &str) {
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        log::warn!("desktop notification failed: {e}");
+    }
+}
+
+fn record_stat(app: &tauri::AppHandle, state: &NotificationState, event: &MarketplaceEvent) {
+    let Some(listing_id) = &event.listing_id else {
+        return;
+    };
+    if !matches!(event.kind, EventKind::Like | EventKind::Save) {
+        return;
+    }
+    let mut stats = state.stats.lock().unwrap();
+    let stat = stats.entry(format!("{}:{listing_id}", event.marketplace)).or_insert_with(|| ListingStat {
+        marketplace: event.marketplace.clone(),
+        listing_id: listing_id.clone(),
+        ..ListingStat::default()
+    });
+    if event.listing_title.is_some() {
+        stat.title = event.listing_title.clone();
+    }
+    match event.kind {
+        EventKind::Like => stat.likes += 1,
+        _ => stat.saves += 1,
+    }
+    stat.last_engagement_at = crate::unix_now();
+    if let Err(e) = crate::store::save(app, STATS_STORE, &*stats) {
+        log::warn!("saving listing stats failed: {e}");
+    }
+}
+
+/// "14 likes, 2 saves across 9 items, top: vintage Levi's 501".
+fn summarise(events: &[MarketplaceEvent]) -> DigestSummary {
+    let mut counts: HashMap<EventKind, usize> = HashMap::new();
+    let mut per_item: HashMap<(&str, &str), (usize, Option<&str>)> = HashMap::new();
+    for e in events {
+        *counts.entry(e.kind).or_default() += 1;
+        if let Some(id) = &e.listing_id {
+            let item = per_item.entry((e.marketplace.as_str(), id.as_str())).or_default();
+            item.0 += 1;
+            if e.listing_title.is_some() {
+                item.1 = e.listing_title.as_deref();
+            }
+        }
+    }
+
+    let parts: Vec<String> = [EventKind::Like, EventKind::Save, EventKind::Follow]
+        .iter()
+        .filter_map(|k| counts.get(k).map(|n| format!("{n} {}", k.noun(*n))))
+        .collect();
+    let mut body = parts.join(", ");
+    if !per_item.is_empty() {
+        let n = per_item.len();
+        body.push_str(&format!(" across {n} item{}", if n == 1 { "" } else { "s" }));
+        let top = per_item
+            .iter()
+            .max_by(|a, b| a.1 .0.cmp(&b.1 .0).then_with(|| b.0.cmp(a.0)))
+            .map(|((_, id), (_, title))| title.unwrap_or(id).to_string());
+        if let Some(top) = top {
+            body.push_str(&format!(", top: {top}"));
+        }
+    }
+    DigestSummary {
+        at: crate::unix_now(),
+        title: "Shop activity".to_string(),
+        body,
+        events: events.len(),
+        items: per_item.len(),
+        counts,
+    }
+}
+
+/// Send the buffered engagement as one notification. Returns None when there
+/// was nothing to send.
+fn flush_digest(app: &tauri::AppHandle) -> Option<DigestSummary> {
+    let state = app.state::<NotificationState>();
+    let events = std::mem::take(&mut *state.buffer.lock().unwrap());
+    *state.window_started.lock().unwrap() = crate::unix_now();
+    if events.is_empty() {
+        return None;
+    }
+    let summary = summarise(&events);
+    show(app, &summary.title, &summary.body);
+    let _ = app.emit("notification-digest", &summary);
+    Some(summary)
+}
+
+/// Check once a minute whether the digest window has closed.
+pub fn start_digest_job(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            let due = {
+                let state = app.state::<NotificationState>();
+                let settings = state.settings.lock().unwrap().clone();
+                let now = crate::unix_now();
+                let started = *state.window_started.lock().unwrap();
+                let window_closed = now.saturating_sub(started) >= settings.window_minutes.max(1) * 60;
+                if window_closed && state.buffer.lock().unwrap().is_empty() {
+                    // Skip empty windows entirely.
+                    *state.window_started.lock().unwrap() = now;
+                    false
+                } else {
+                    window_closed && !in_quiet_hours(&settings, now)
+                }
+            };
+            if due {
+                flush_digest(&app);
+            }
+        }
+    });
+}
+
+/// Deliver one marketplace event: immediately for offers/sales/messages (and
+/// engagement when digests are off), into the digest buffer otherwise.
+/// Emits "marketplace-event" with the event and its account metadata.
+#[tauri::command]
+pub fn push_marketplace_event(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, NotificationState>,
+    event: MarketplaceEvent,
+) {
+    record_stat(&app, &state, &event);
+    let account_meta = event.account.as_deref().and_then(|a| crate::accounts::meta(&app, a));
+    let _ = app.emit("marketplace-event", serde_json::json!({ "event": &event, "account_meta": &account_meta }));
+
+    let digest = state.settings.lock().unwrap().digest_engagement;
+    if event.kind.is_engagement() && digest {
+        state.buffer.lock().unwrap().push(event);
+        return;
+    }
+    let shop = account_meta
+        .and_then(|m| m.label)
+        .or_else(|| event.account.clone())
+        .unwrap_or_else(|| event.marketplace.clone());
+    let title = format!("New {} · {shop}", event.kind.noun(1));
+    let body = event
+        .message
+        .clone()
+        .or_else(|| event.listing_title.clone())
+        .unwrap_or_default();
+    show(&app, &title, &body);
+}
+
+#[tauri::command]
+pub fn get_digest_settings(state: tauri::State<'_, NotificationState>) -> DigestSettings {
+    state.settings.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_digest_settings(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, NotificationState>,
+    settings: DigestSettings,
+) -> Result<(), String> {
+    if let Some(q) = settings.quiet_hours {
+        if q.start_minute >= 24 * 60 || q.end_minute >= 24 * 60 {
+            return Err("Quiet hours must be within a day".to_string());
+        }
+    }
+    let mut current = state.settings.lock().unwrap();
+    *current = settings;
+    crate::store::save(&app, SETTINGS_STORE, &*current)
+}
+
+/// Send whatever is buffered now, ignoring the window and quiet hours.
+#[tauri::command]
+pub fn flush_notification_digest(app: tauri::AppHandle) -> Option<DigestSummary> {
+    flush_digest(&app)
+}
+
+#[tauri::command]
+pub fn get_listing_stats(state: tauri::State<'_, NotificationState>, marketplace: Option<String>) -> Vec<ListingStat> {
+    state
+        .stats
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|s| marketplace.as_deref().map_or(true, |m| s.marketplace == m))
+        .cloned()
+        .collect()
+}
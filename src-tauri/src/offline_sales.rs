@@ -0,0 +1,410 @@
+// ── Offline sales ──────────────────────────────────────────────────────────
+// Quick entry for items sold in person (markets, pop-ups): scan or type the
+// SKU, enter the price, done. Each sale is a `sales` row on the "offline"
+// platform, so analytics shows it as its own channel, with the payment
+// method and an optional note (migration 019). The item's quantity goes down
+// by one; the last one sold marks its listing sold on "offline" and emits
+// `listing-sold-elsewhere` so the listing is taken down on the marketplaces
+// it is still live on, as a marketplace sale would. `record_offline_sales`
+// takes a day's paper tally at once.
+//
+// With market mode on, `handle_scan` turns a scanned label into a
+// `quick-sale-prompt` event carrying the SKU, so the prompt opens pre-filled.
+//
+// Sales recorded in this session can be undone (`undo_offline_sale`): the
+// sale row is deleted and the quantity and listing put back. Everything is
+// audited.
+
+use crate::depop::sync::urlencode;
+use crate::supabase::{self, SupabaseSession};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Mutex;
+use tauri::Emitter;
+
+const OFFLINE_PLATFORM: &str = "offline";
+const MARKET_MODE_STORE: &str = "market_mode";
+const MAX_NOTE_CHARS: usize = 500;
+const MAX_BATCH: usize = 500;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentMethod {
+    Cash,
+    Card,
+    Transfer,
+    Other,
+}
+
+impl PaymentMethod {
+    fn as_str(self) -> &'static str {
+        match self {
+            PaymentMethod::Cash => "cash",
+            PaymentMethod::Card => "card",
+            PaymentMethod::Transfer => "transfer",
+            PaymentMethod::Other => "other",
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct OfflineSaleInput {
+    pub sku: String,
+    pub price: f64,
+    pub payment_method: PaymentMethod,
+    #[serde(default)]
+    pub note: Option<String>,
+    /// ISO-8601; now when absent. For entering a tally after the day.
+    #[serde(default)]
+    pub sold_at: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct OfflineSale {
+    pub sale_id: String,
+    pub sku: String,
+    pub item_id: String,
+    pub item_name: String,
+    pub price: f64,
+    pub payment_method: PaymentMethod,
+    pub sold_at: String,
+    pub remaining_quantity: i64,
+    pub listing_id: Option<String>,
+    /// The last one was sold and its listing is now marked sold.
+    pub listing_sold: bool,
+}
+
+#[derive(Serialize)]
+pub struct BatchEntry {
+    pub sku: String,
+    pub sale: Option<OfflineSale>,
+    pub error: Option<String>,
+}
+
+/// What to put back when a sale is undone.
+struct UndoEntry {
+    sale: OfflineSale,
+    /// The listing's status before it was marked sold.
+    listing_status: Option<Value>,
+}
+
+#[derive(Default)]
+pub struct OfflineSaleState {
+    market_mode: Mutex<bool>,
+    /// Sales recorded since launch, newest last.
+    session: Mutex<Vec<UndoEntry>>,
+}
+
+impl OfflineSaleState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        OfflineSaleState { market_mode: Mutex::new(crate::store::load(app, MARKET_MODE_STORE)), ..Default::default() }
+    }
+}
+
+fn now_iso() -> String {
+    crate::netlog::iso8601(crate::netlog::unix_millis())
+}
+
+async fn send_json(req: reqwest::RequestBuilder) -> Result<Vec<Value>, String> {
+    match supabase::send(req).await? {
+        Value::Array(rows) => Ok(rows),
+        Value::Null => Ok(Vec::new()),
+        other => Ok(vec![other]),
+    }
+}
+
+async fn record(
+    session: &SupabaseSession,
+    client: &reqwest::Client,
+    user_id: &str,
+    input: &OfflineSaleInput,
+) -> Result<UndoEntry, String> {
+    let sku = input.sku.trim();
+    if sku.is_empty() {
+        return Err("Scan or type a SKU".to_string());
+    }
+    if !input.price.is_finite() || input.price < 0.0 {
+        return Err("Price must be a non-negative amount".to_string());
+    }
+    let note = input.note.as_deref().map(str::trim).filter(|n| !n.is_empty());
+    if note.is_some_and(|n| n.chars().count() > MAX_NOTE_CHARS) {
+        return Err(format!("Notes are limited to {MAX_NOTE_CHARS} characters"));
+    }
+    let sold_at = match input.sold_at.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        Some(s) if crate::custom_fields::valid_date(s.get(..10).unwrap_or_default()) => s.to_string(),
+        Some(s) => return Err(format!("{s} is not a date")),
+        None => now_iso(),
+    };
+
+    let path = format!(
+        "inventory?select=id,name,cost,quantity,listing_id&sku=eq.{}&deleted_at=is.null&limit=2",
+        urlencode(sku)
+    );
+    let items = send_json(session.rest(client, Method::GET, &path)).await?;
+    let item = match &items[..] {
+        [] => return Err(format!("No item has SKU {sku}")),
+        [item] => item,
+        _ => return Err(format!("More than one item has SKU {sku}")),
+    };
+    let item_id = item["id"].as_str().unwrap_or_default().to_string();
+    let item_name = item["name"].as_str().unwrap_or(sku).to_string();
+    let quantity = item["quantity"].as_i64().unwrap_or(1);
+    if quantity < 1 {
+        return Err(format!("{item_name} ({sku}) is out of stock"));
+    }
+    let listing_id = item["listing_id"].as_str().map(str::to_string);
+
+    // Only take one off the quantity we read, so two tills selling the last
+    // one at once can't both succeed.
+    let updated = send_json(
+        session
+            .rest(client, Method::PATCH, &format!("inventory?id=eq.{item_id}&quantity=eq.{quantity}"))
+            .header("Prefer", "return=representation")
+            .json(&json!({ "quantity": quantity - 1, "updated_at": now_iso() })),
+    )
+    .await?;
+    if updated.is_empty() {
+        return Err(format!("{item_name} ({sku}) changed while recording the sale; try again"));
+    }
+
+    let sale = json!({
+        "user_id": user_id,
+        "listing_id": listing_id,
+        "platform": OFFLINE_PLATFORM,
+        "sale_price": input.price,
+        "cost": item["cost"].as_f64().unwrap_or(0.0),
+        "item_title": item_name,
+        "external_id": format!("offline-{}", hex::encode(rand::random::<[u8; 8]>())),
+        "payment_method": input.payment_method.as_str(),
+        "note": note,
+        "sold_at": sold_at,
+    });
+    let inserted = send_json(
+        session.rest(client, Method::POST, "sales").header("Prefer", "return=representation").json(&sale),
+    )
+    .await;
+    let sale_id = inserted.and_then(|rows| {
+        rows.first().and_then(|r| r["id"].as_str()).map(str::to_string).ok_or_else(|| "The sale wasn't saved".to_string())
+    });
+    let sale_id = match sale_id {
+        Ok(id) => id,
+        Err(e) => {
+            let _ = restore_quantity(session, client, &item_id).await;
+            return Err(e);
+        }
+    };
+
+    let mut listing_status = None;
+    if quantity == 1 {
+        if let Some(listing) = &listing_id {
+            let rows = send_json(
+                session.rest(client, Method::GET, &format!("listings?select=status&id=eq.{listing}")),
+            )
+            .await?;
+            if let Some(row) = rows.first().filter(|r| r["status"] != "sold") {
+                send_json(session.rest(client, Method::PATCH, &format!("listings?id=eq.{listing}")).json(&json!({
+                    "status": "sold",
+                    "sold_on_platform": OFFLINE_PLATFORM,
+                    "sold_at": sold_at,
+                })))
+                .await?;
+                listing_status = Some(row["status"].clone());
+            }
+        }
+    }
+
+    Ok(UndoEntry {
+        sale: OfflineSale {
+            sale_id,
+            sku: sku.to_string(),
+            item_id,
+            item_name,
+            price: input.price,
+            payment_method: input.payment_method,
+            sold_at,
+            remaining_quantity: quantity - 1,
+            listing_id,
+            listing_sold: listing_status.is_some(),
+        },
+        listing_status,
+    })
+}
+
+async fn restore_quantity(session: &SupabaseSession, client: &reqwest::Client, item_id: &str) -> Result<(), String> {
+    let rows = send_json(session.rest(client, Method::GET, &format!("inventory?select=quantity&id=eq.{item_id}"))).await?;
+    let quantity = rows.first().and_then(|r| r["quantity"].as_i64()).ok_or("The item no longer exists")?;
+    let updated = send_json(
+        session
+            .rest(client, Method::PATCH, &format!("inventory?id=eq.{item_id}&quantity=eq.{quantity}"))
+            .header("Prefer", "return=representation")
+            .json(&json!({ "quantity": quantity + 1, "updated_at": now_iso() })),
+    )
+    .await?;
+    if updated.is_empty() {
+        return Err("The item changed while restoring its quantity; try again".to_string());
+    }
+    Ok(())
+}
+
+/// Audit, remember for undo and tell the UI about a recorded sale.
+fn finish(app: &tauri::AppHandle, state: &OfflineSaleState, entry: UndoEntry) -> OfflineSale {
+    let sale = entry.sale.clone();
+    crate::audit::record(
+        app,
+        "record_offline_sale",
+        None,
+        false,
+        json!({
+            "sale_id": sale.sale_id,
+            "sku": sale.sku,
+            "price": sale.price,
+            "payment_method": sale.payment_method,
+            "listing_sold": sale.listing_sold,
+        }),
+    );
+    if sale.listing_sold {
+        let _ = app.emit(
+            "listing-sold-elsewhere",
+            json!({ "listing_id": sale.listing_id, "sold_on": OFFLINE_PLATFORM, "sold_at": sale.sold_at }),
+        );
+    }
+    state.session.lock().unwrap().push(entry);
+    sale
+}
+
+/// Record one item sold in person.
+#[tauri::command]
+pub async fn record_offline_sale(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, OfflineSaleState>,
+    session: SupabaseSession,
+    sku: String,
+    price: f64,
+    payment_method: PaymentMethod,
+    note: Option<String>,
+) -> Result<OfflineSale, String> {
+    let client = supabase::client()?;
+    let user_id = session.user_id(&client).await?;
+    let input = OfflineSaleInput { sku, price, payment_method, note, sold_at: None };
+    let entry = record(&session, &client, &user_id, &input).await?;
+    Ok(finish(&app, &state, entry))
+}
+
+/// Record a list of sales, e.g. from a paper tally at the end of the day.
+/// Entries are independent: one that fails is reported and the rest go on.
+#[tauri::command]
+pub async fn record_offline_sales(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, OfflineSaleState>,
+    session: SupabaseSession,
+    sales: Vec<OfflineSaleInput>,
+) -> Result<Vec<BatchEntry>, String> {
+    if sales.len() > MAX_BATCH {
+        return Err(format!("Enter at most {MAX_BATCH} sales at once"));
+    }
+    let client = supabase::client()?;
+    let user_id = session.user_id(&client).await?;
+    let mut results = Vec::with_capacity(sales.len());
+    for input in &sales {
+        let sku = input.sku.trim().to_string();
+        results.push(match record(&session, &client, &user_id, input).await {
+            Ok(entry) => BatchEntry { sku, sale: Some(finish(&app, &state, entry)), error: None },
+            Err(e) => BatchEntry { sku, sale: None, error: Some(e) },
+        });
+    }
+    Ok(results)
+}
+
+/// Offline sales recorded since launch, newest first; these can be undone.
+#[tauri::command]
+pub fn get_session_offline_sales(state: tauri::State<'_, OfflineSaleState>) -> Vec<OfflineSale> {
+    state.session.lock().unwrap().iter().rev().map(|e| e.sale.clone()).collect()
+}
+
+/// Take back a sale recorded this session: delete it, return the item to
+/// stock and reopen its listing if the sale closed it.
+#[tauri::command]
+pub async fn undo_offline_sale(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, OfflineSaleState>,
+    session: SupabaseSession,
+    sale_id: String,
+) -> Result<(), String> {
+    let entry = {
+        let mut recorded = state.session.lock().unwrap();
+        let index = recorded
+            .iter()
+            .position(|e| e.sale.sale_id == sale_id)
+            .ok_or("Only sales recorded since the app started can be undone")?;
+        recorded.remove(index)
+    };
+    let client = supabase::client()?;
+    let result = async {
+        restore_quantity(&session, &client, &entry.sale.item_id).await?;
+        send_json(session.rest(&client, Method::DELETE, &format!("sales?id=eq.{sale_id}"))).await?;
+        if let (Some(listing), Some(status)) = (&entry.sale.listing_id, &entry.listing_status) {
+            send_json(session.rest(&client, Method::PATCH, &format!("listings?id=eq.{listing}")).json(&json!({
+                "status": status,
+                "sold_on_platform": Value::Null,
+                "sold_at": Value::Null,
+            })))
+            .await?;
+        }
+        Ok::<(), String>(())
+    }
+    .await;
+    if let Err(e) = result {
+        state.session.lock().unwrap().push(entry);
+        return Err(e);
+    }
+    crate::audit::record(
+        &app,
+        "undo_offline_sale",
+        None,
+        false,
+        json!({ "sale_id": sale_id, "sku": entry.sale.sku, "listing_reopened": entry.listing_status.is_some() }),
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_market_mode(state: tauri::State<'_, OfflineSaleState>) -> bool {
+    *state.market_mode.lock().unwrap()
+}
+
+#[tauri::command]
+pub fn set_market_mode(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, OfflineSaleState>,
+    enabled: bool,
+) -> Result<(), String> {
+    crate::store::save(&app, MARKET_MODE_STORE, &enabled)?;
+    *state.market_mode.lock().unwrap() = enabled;
+    Ok(())
+}
+
+/// The SKU in a scanned code: a bare SKU, or a URL carrying `?sku=`.
+fn sku_from_scan(code: &str) -> Option<String> {
+    let code = code.trim();
+    if code.is_empty() {
+        return None;
+    }
+    match reqwest::Url::parse(code) {
+        Ok(url) => url.query_pairs().find(|(k, _)| k == "sku").map(|(_, v)| v.trim().to_string()),
+        Err(_) => Some(code.to_string()),
+    }
+    .filter(|sku| !sku.is_empty())
+}
+
+/// A label scanned (or a SKU link opened) while the app is in front. In
+/// market mode this emits `quick-sale-prompt` with the SKU so the quick sale
+/// prompt opens pre-filled. Returns the SKU found, if any.
+#[tauri::command]
+pub fn handle_scan(app: tauri::AppHandle, state: tauri::State<'_, OfflineSaleState>, code: String) -> Option<String> {
+    let sku = sku_from_scan(&code)?;
+    if *state.market_mode.lock().unwrap() {
+        let _ = app.emit("quick-sale-prompt", json!({ "sku": sku }));
+    }
+    Some(sku)
+}
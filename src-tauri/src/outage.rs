@@ -0,0 +1,380 @@
+// ── Marketplace outage detection ───────────────────────────────────────────
+// When a marketplace's API goes down, every request fails on its own and the
+// user gets a wall of unrelated errors. Instead, each request to a known
+// marketplace host (`native_fetch` and the Depop client) is recorded here as
+// a success or a failure: a 5xx or a timeout. Once at least MIN_SAMPLES
+// requests in the last WINDOW have come in and DEGRADED_RATE of them
+// failed, the marketplace is marked degraded:
+//   - `marketplace-status-changed` is emitted and a notification shown,
+//   - background jobs for it (offer nudges, rank checks, attribute
+//     refreshes) skip their runs,
+//   - failed writes are tagged `during_outage` in the write intent journal.
+//
+// While degraded, a probe request goes out on a backoff schedule (30s,
+// doubling up to 10 minutes); the first one that gets a non-5xx answer, or
+// enough successful live traffic, marks it operational again. If a public
+// status page (Statuspage `/api/v2/status.json` format) is configured for
+// the marketplace, it is read with each probe and shown alongside.
+//
+// The Depop client has no app handle, so the detector itself is a static
+// like the metrics; the monitor job sends the events.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+const STORE: &str = "marketplace_status";
+const WINDOW: Duration = Duration::from_secs(5 * 60);
+const MIN_SAMPLES: usize = 8;
+const DEGRADED_RATE: f64 = 0.5;
+const RECOVERED_RATE: f64 = 0.2;
+const TICK: Duration = Duration::from_secs(5);
+const FIRST_PROBE_SECS: u64 = 30;
+const MAX_PROBE_SECS: u64 = 10 * 60;
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// (host, marketplace, display name, probe URL). A host also covers its
+/// subdomains.
+const MARKETPLACES: &[(&str, &str, &str, &str)] = &[
+    ("depop.com", "depop", "Depop", "https://webapi.depop.com/api/v2/"),
+    ("ebay.com", "ebay", "eBay", "https://api.ebay.com/"),
+    ("etsy.com", "etsy", "Etsy", "https://openapi.etsy.com/"),
+    ("poshmark.com", "poshmark", "Poshmark", "https://poshmark.com/"),
+    ("mercari.com", "mercari", "Mercari", "https://www.mercari.com/"),
+];
+
+/// Marketplace a URL belongs to, if it's one we watch.
+pub fn marketplace_of(url: &str) -> Option<&'static str> {
+    let host = reqwest::Url::parse(url).ok()?.host_str()?.to_ascii_lowercase();
+    MARKETPLACES
+        .iter()
+        .find(|(domain, ..)| host == *domain || host.ends_with(&format!(".{domain}")))
+        .map(|(_, marketplace, ..)| *marketplace)
+}
+
+fn display_name(marketplace: &str) -> &str {
+    MARKETPLACES.iter().find(|(_, m, ..)| *m == marketplace).map_or(marketplace, |(_, _, name, _)| *name)
+}
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Operational,
+    Degraded,
+}
+
+#[derive(Clone, Serialize)]
+pub struct StatusPage {
+    /// Statuspage indicator: none, minor, major or critical.
+    pub indicator: String,
+    pub description: Option<String>,
+    pub checked_at: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct MarketplaceStatus {
+    pub marketplace: String,
+    pub status: Status,
+    /// Unix seconds the current status began (app start if it never changed).
+    pub since: u64,
+    /// What tipped it into degraded.
+    pub reason: Option<String>,
+    pub failure_rate: f64,
+    pub samples: usize,
+    pub next_probe_in_secs: Option<u64>,
+    pub status_page: Option<StatusPage>,
+}
+
+#[derive(Default)]
+struct Health {
+    samples: VecDeque<(Instant, bool)>,
+    degraded_since: Option<u64>,
+    since: u64,
+    reason: Option<String>,
+    probe_every_secs: u64,
+    next_probe: Option<Instant>,
+    status_page: Option<StatusPage>,
+}
+
+impl Health {
+    fn new(since: u64) -> Self {
+        Health { since, ..Default::default() }
+    }
+
+    fn failure_rate(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        self.samples.iter().filter(|(_, failed)| *failed).count() as f64 / self.samples.len() as f64
+    }
+
+    fn degrade(&mut self, reason: String) {
+        let now = crate::unix_now();
+        self.degraded_since = Some(now);
+        self.since = now;
+        self.reason = Some(reason);
+        self.samples.clear();
+        self.probe_every_secs = FIRST_PROBE_SECS;
+        self.next_probe = Some(Instant::now() + Duration::from_secs(FIRST_PROBE_SECS));
+    }
+
+    fn recover(&mut self) {
+        self.degraded_since = None;
+        self.since = crate::unix_now();
+        self.reason = None;
+        self.samples.clear();
+        self.next_probe = None;
+    }
+
+    fn snapshot(&self, marketplace: &str) -> MarketplaceStatus {
+        MarketplaceStatus {
+            marketplace: marketplace.to_string(),
+            status: if self.degraded_since.is_some() { Status::Degraded } else { Status::Operational },
+            since: self.since,
+            reason: self.reason.clone(),
+            failure_rate: self.failure_rate(),
+            samples: self.samples.len(),
+            next_probe_in_secs: self.next_probe.map(|t| t.saturating_duration_since(Instant::now()).as_secs()),
+            status_page: self.status_page.clone(),
+        }
+    }
+}
+
+struct Detector {
+    started: u64,
+    health: HashMap<String, Health>,
+    /// Marketplaces whose status changed since the monitor last looked.
+    changed: Vec<String>,
+}
+
+static DETECTOR: OnceLock<Mutex<Detector>> = OnceLock::new();
+
+fn detector() -> std::sync::MutexGuard<'static, Detector> {
+    DETECTOR
+        .get_or_init(|| Mutex::new(Detector { started: crate::unix_now(), health: HashMap::new(), changed: Vec::new() }))
+        .lock()
+        .unwrap()
+}
+
+/// Record one request to `marketplace`; `failed` for a 5xx or a timeout.
+pub fn record(marketplace: &str, failed: bool) {
+    let mut guard = detector();
+    let Detector { started, health, changed } = &mut *guard;
+    let health = health.entry(marketplace.to_string()).or_insert_with(|| Health::new(*started));
+    let now = Instant::now();
+    health.samples.push_back((now, failed));
+    while health.samples.front().is_some_and(|(at, _)| now.duration_since(*at) > WINDOW) {
+        health.samples.pop_front();
+    }
+    let (samples, rate) = (health.samples.len(), health.failure_rate());
+    if samples < MIN_SAMPLES {
+        return;
+    }
+    if health.degraded_since.is_none() && rate >= DEGRADED_RATE {
+        let failures = health.samples.iter().filter(|(_, failed)| *failed).count();
+        health.degrade(format!("{failures} of the last {samples} requests failed or timed out"));
+        changed.push(marketplace.to_string());
+    } else if health.degraded_since.is_some() && rate <= RECOVERED_RATE {
+        health.recover();
+        changed.push(marketplace.to_string());
+    }
+}
+
+/// Record a response status for a request to `url`.
+pub fn record_status(url: &str, status: u16) {
+    if let Some(marketplace) = marketplace_of(url) {
+        record(marketplace, status >= 500);
+    }
+}
+
+/// Record a transport failure for a request to `url`. Only timeouts count
+/// against the marketplace; DNS and connection failures are as likely to be
+/// the user's own network.
+pub fn record_error(url: &str, kind: crate::fetch_error::FetchErrorKind) {
+    if kind == crate::fetch_error::FetchErrorKind::Timeout {
+        if let Some(marketplace) = marketplace_of(url) {
+            record(marketplace, true);
+        }
+    }
+}
+
+/// Whether `marketplace` is currently marked degraded. Background jobs for
+/// it should skip their run.
+pub fn is_degraded(marketplace: &str) -> bool {
+    detector().health.get(marketplace).is_some_and(|h| h.degraded_since.is_some())
+}
+
+fn snapshot(marketplace: &str) -> MarketplaceStatus {
+    let detector = detector();
+    match detector.health.get(marketplace) {
+        Some(health) => health.snapshot(marketplace),
+        None => Health::new(detector.started).snapshot(marketplace),
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct OutageConfig {
+    /// Marketplace -> public status page JSON URL.
+    #[serde(default)]
+    status_pages: BTreeMap<String, String>,
+}
+
+pub struct OutageState {
+    config: Mutex<OutageConfig>,
+}
+
+impl OutageState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        OutageState { config: Mutex::new(crate::store::load(app, STORE)) }
+    }
+}
+
+/// Whether the marketplace answered with anything but a 5xx.
+async fn probe(client: &reqwest::Client, marketplace: &str) -> bool {
+    let Some((.., url)) = MARKETPLACES.iter().find(|(_, m, ..)| *m == marketplace) else {
+        return true;
+    };
+    match client.get(*url).send().await {
+        Ok(resp) => !resp.status().is_server_error(),
+        Err(e) => {
+            log::info!("{marketplace} probe failed: {e}");
+            false
+        }
+    }
+}
+
+async fn read_status_page(client: &reqwest::Client, url: &str) -> Result<StatusPage, String> {
+    let body: serde_json::Value = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("request: {e}"))?
+        .error_for_status()
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| format!("body: {e}"))?;
+    let indicator = body["status"]["indicator"].as_str().ok_or("no status.indicator in the response")?;
+    Ok(StatusPage {
+        indicator: indicator.to_string(),
+        description: body["status"]["description"].as_str().map(str::to_string),
+        checked_at: crate::unix_now(),
+    })
+}
+
+async fn run_probes(app: &tauri::AppHandle, client: &reqwest::Client) {
+    let now = Instant::now();
+    let due: Vec<String> = detector()
+        .health
+        .iter()
+        .filter(|(_, h)| h.degraded_since.is_some() && h.next_probe.is_some_and(|t| t <= now))
+        .map(|(m, _)| m.clone())
+        .collect();
+    for marketplace in due {
+        let page_url = app.state::<OutageState>().config.lock().unwrap().status_pages.get(&marketplace).cloned();
+        let page = match page_url {
+            Some(url) => match read_status_page(client, &url).await {
+                Ok(page) => Some(page),
+                Err(e) => {
+                    log::warn!("{marketplace} status page check failed: {e}");
+                    None
+                }
+            },
+            None => None,
+        };
+        let healthy = probe(client, &marketplace).await;
+
+        let mut guard = detector();
+        let Detector { health, changed, .. } = &mut *guard;
+        let Some(health) = health.get_mut(&marketplace).filter(|h| h.degraded_since.is_some()) else {
+            continue;
+        };
+        if page.is_some() {
+            health.status_page = page;
+        }
+        if healthy {
+            health.recover();
+            changed.push(marketplace);
+        } else {
+            health.probe_every_secs = (health.probe_every_secs * 2).min(MAX_PROBE_SECS);
+            health.next_probe = Some(Instant::now() + Duration::from_secs(health.probe_every_secs));
+        }
+    }
+}
+
+fn announce(app: &tauri::AppHandle) {
+    let changed = std::mem::take(&mut detector().changed);
+    for marketplace in changed {
+        let status = snapshot(&marketplace);
+        let name = display_name(&marketplace);
+        match status.status {
+            Status::Degraded => {
+                log::warn!("{name} marked degraded: {}", status.reason.as_deref().unwrap_or(""));
+                crate::notifications::show(
+                    app,
+                    &format!("{name} is having problems"),
+                    &format!("Requests to {name} are failing. Background jobs for it are paused until it recovers."),
+                );
+            }
+            Status::Operational => {
+                log::info!("{name} is responding again");
+                crate::notifications::show(app, &format!("{name} is back"), "Background jobs have resumed.");
+            }
+        }
+        let _ = app.emit("marketplace-status-changed", &status);
+    }
+}
+
+/// Probes degraded marketplaces and announces status changes.
+pub fn start_monitor_job(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let client = match reqwest::Client::builder().timeout(PROBE_TIMEOUT).build() {
+            Ok(client) => client,
+            Err(e) => {
+                log::error!("outage monitor disabled: {e}");
+                return;
+            }
+        };
+        loop {
+            tokio::time::sleep(TICK).await;
+            run_probes(&app, &client).await;
+            announce(&app);
+        }
+    });
+}
+
+/// Status of every watched marketplace, for the outage banner.
+#[tauri::command]
+pub fn get_marketplace_status() -> Vec<MarketplaceStatus> {
+    MARKETPLACES.iter().map(|(_, marketplace, ..)| snapshot(marketplace)).collect()
+}
+
+/// Set (or with no `url`, clear) the public status page read while
+/// `marketplace` is degraded.
+#[tauri::command]
+pub fn set_marketplace_status_page(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, OutageState>,
+    marketplace: String,
+    url: Option<String>,
+) -> Result<(), String> {
+    if !MARKETPLACES.iter().any(|(_, m, ..)| *m == marketplace) {
+        return Err(format!("Unknown marketplace: {marketplace}"));
+    }
+    let mut config = state.config.lock().unwrap();
+    match url.map(|u| u.trim().to_string()).filter(|u| !u.is_empty()) {
+        Some(url) => {
+            let parsed = reqwest::Url::parse(&url).map_err(|e| format!("Invalid URL: {e}"))?;
+            if parsed.scheme() != "https" {
+                return Err("The status page URL must use https".to_string());
+            }
+            config.status_pages.insert(marketplace, url);
+        }
+        None => {
+            config.status_pages.remove(&marketplace);
+        }
+    }
+    crate::store::save(&app, STORE, &*config)
+}
@@ -0,0 +1,792 @@
+// ── Payout reconciliation ──────────────────────────────────────────────────
+// Ties bank deposits back to the sales they pay out. Deposits come from a
+// bank statement CSV (`import_bank_csv`, with a column mapping since every
+// bank exports differently). Expected payouts are computed from the synced
+// sales the frontend passes in: each sale's net (price plus shipping, less
+// fees and refunds) lands in a batch per marketplace, currency and expected
+// payout date, which is the sale date plus the marketplace's payout delay.
+// A refund made after the sale is deducted from the batch its own date falls
+// in, the way marketplaces net it out of a later payout.
+//
+// `reconcile_payouts` pairs batches with deposits of the same currency that
+// land within `match_window_days` of the expected date: first those within
+// `tolerance` of the expected amount, then the nearest remaining deposit as
+// an amount mismatch. Whatever is left is a missing payout (once its window
+// has passed) or an unmatched deposit. Manual matches, which may cover
+// several batches paid together, and manual unmatches persist and win over
+// the automatic pairing on every later run. Nothing is converted between
+// currencies: a USD batch only ever matches a USD deposit.
+
+use crate::fees::round_cents;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::Mutex;
+
+const STORE_NAME: &str = "payout_reconciliation";
+const DEFAULT_DELAY_DAYS: u32 = 3;
+const DEFAULT_CURRENCY: &str = "USD";
+/// A deposit further than this share of the expected amount away is not
+/// offered as a mismatch for the batch; it is more likely something else.
+const MISMATCH_RATIO: f64 = 0.5;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PayoutSchedule {
+    /// Days from the sale to the money reaching the bank.
+    pub delay_days: u32,
+    /// Currency payouts arrive in when a sale doesn't say.
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PayoutSettings {
+    /// By marketplace; others use DEFAULT_DELAY_DAYS.
+    pub schedules: BTreeMap<String, PayoutSchedule>,
+    /// Largest difference, in the payout's currency, that still matches.
+    pub tolerance: f64,
+    /// Days after the expected date a deposit may arrive (and one before).
+    pub match_window_days: u32,
+}
+
+impl Default for PayoutSettings {
+    fn default() -> Self {
+        PayoutSettings { schedules: BTreeMap::new(), tolerance: 0.5, match_window_days: 3 }
+    }
+}
+
+impl PayoutSettings {
+    fn schedule(&self, platform: &str) -> (u32, Option<&str>) {
+        match self.schedules.get(platform) {
+            Some(s) => (s.delay_days, s.currency.as_deref()),
+            None => (DEFAULT_DELAY_DAYS, None),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !self.tolerance.is_finite() || self.tolerance < 0.0 {
+            return Err("Tolerance must be a non-negative amount".to_string());
+        }
+        if self.match_window_days > 31 {
+            return Err("The match window is limited to 31 days".to_string());
+        }
+        if let Some((name, _)) = self.schedules.iter().find(|(_, s)| s.delay_days > 90) {
+            return Err(format!("{name}'s payout delay is limited to 90 days"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DateFormat {
+    /// 2024-03-31
+    #[default]
+    Ymd,
+    /// 31/03/2024
+    Dmy,
+    /// 03/31/2024
+    Mdy,
+}
+
+/// Which columns of a bank export hold what, by header name.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BankCsvMapping {
+    pub date_column: String,
+    /// Signed amounts; credits are positive.
+    #[serde(default)]
+    pub amount_column: Option<String>,
+    /// For exports that split money in and money out.
+    #[serde(default)]
+    pub credit_column: Option<String>,
+    #[serde(default)]
+    pub description_column: Option<String>,
+    #[serde(default)]
+    pub currency_column: Option<String>,
+    /// The account's currency when there is no currency column.
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub date_format: DateFormat,
+    #[serde(default)]
+    pub delimiter: Option<char>,
+    /// `1.234,56` rather than `1,234.56`.
+    #[serde(default)]
+    pub decimal_comma: bool,
+    /// Only keep deposits whose description contains this ("DEPOP").
+    #[serde(default)]
+    pub description_contains: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BankDeposit {
+    pub id: String,
+    /// `YYYY-MM-DD`
+    pub date: String,
+    pub amount: f64,
+    pub currency: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Serialize)]
+pub struct BankImport {
+    pub imported: usize,
+    /// Already imported from an earlier statement.
+    pub duplicates: usize,
+    /// Debits, rows filtered out by `description_contains`, and rows that
+    /// couldn't be read.
+    pub skipped: usize,
+    pub errors: Vec<String>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct PayoutSale {
+    pub id: String,
+    pub platform: String,
+    pub sale_price: f64,
+    #[serde(default)]
+    pub shipping_charged: f64,
+    #[serde(default)]
+    pub platform_fees: f64,
+    #[serde(default)]
+    pub refunded: f64,
+    /// When the refund was made; a refund without a date is taken to be
+    /// netted out of the sale's own payout.
+    #[serde(default)]
+    pub refunded_at: Option<String>,
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// ISO-8601 (`sales.sold_at`).
+    pub sold_at: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PayoutBatch {
+    /// `platform:currency:date`
+    pub id: String,
+    pub platform: String,
+    pub currency: String,
+    pub expected_date: String,
+    pub amount: f64,
+    pub sale_ids: Vec<String>,
+    /// Refunds from earlier sales deducted from this batch.
+    pub refund_adjustments: f64,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconStatus {
+    Matched,
+    AmountMismatch,
+    MissingPayout,
+    /// Expected, but its match window hasn't passed yet.
+    Pending,
+    UnmatchedDeposit,
+}
+
+impl ReconStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            ReconStatus::Matched => "matched",
+            ReconStatus::AmountMismatch => "amount_mismatch",
+            ReconStatus::MissingPayout => "missing_payout",
+            ReconStatus::Pending => "pending",
+            ReconStatus::UnmatchedDeposit => "unmatched_deposit",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReconRow {
+    pub status: ReconStatus,
+    pub batches: Vec<PayoutBatch>,
+    pub deposit: Option<BankDeposit>,
+    /// Deposit less expected, when both are present.
+    pub difference: Option<f64>,
+    pub manual: bool,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct CurrencyTotals {
+    pub expected: f64,
+    pub deposited: f64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Reconciliation {
+    pub start: String,
+    pub end: String,
+    pub run_at: u64,
+    pub rows: Vec<ReconRow>,
+    pub totals: BTreeMap<String, CurrencyTotals>,
+}
+
+#[derive(Deserialize)]
+pub struct PayoutRange {
+    pub start: String,
+    pub end: String,
+}
+
+#[derive(Serialize)]
+pub struct ReconExport {
+    pub path: String,
+    pub rows: usize,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ReconStore {
+    settings: PayoutSettings,
+    deposits: Vec<BankDeposit>,
+    /// Deposit id to the batches it pays.
+    manual: BTreeMap<String, Vec<String>>,
+    /// Deposit and batch pairs the user has said don't belong together.
+    rejected: BTreeSet<(String, String)>,
+    last: Option<Reconciliation>,
+}
+
+pub struct PayoutState {
+    store: Mutex<ReconStore>,
+}
+
+impl PayoutState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        PayoutState { store: Mutex::new(crate::store::load(app, STORE_NAME)) }
+    }
+}
+
+/// Days since 1970-01-01 for a `YYYY-MM-DD` date (or the date part of an
+/// ISO-8601 timestamp).
+pub(crate) fn day_number(date: &str) -> Option<i64> {
+    let date = date.get(..10)?;
+    if !crate::custom_fields::valid_date(date) {
+        return None;
+    }
+    let (y, m, d): (i64, i64, i64) = (date[..4].parse().ok()?, date[5..7].parse().ok()?, date[8..].parse().ok()?);
+    // Days-from-civil (Howard Hinnant's algorithm), the inverse of netlog::iso8601.
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Some(era * 146_097 + doe - 719_468)
+}
+
+fn date_of(day: i64) -> String {
+    crate::netlog::iso8601(day.max(0) as u64 * 86_400_000)[..10].to_string()
+}
+
+pub(crate) fn parse_csv(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+        } else if c == '"' {
+            quoted = true;
+        } else if c == delimiter {
+            row.push(std::mem::take(&mut field));
+        } else if c == '\n' || c == '\r' {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            row.push(std::mem::take(&mut field));
+            if row.iter().any(|f| !f.trim().is_empty()) {
+                rows.push(std::mem::take(&mut row));
+            }
+            row.clear();
+        } else {
+            field.push(c);
+        }
+    }
+    row.push(field);
+    if row.iter().any(|f| !f.trim().is_empty()) {
+        rows.push(row);
+    }
+    rows
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn parse_bank_date(s: &str, format: DateFormat) -> Option<String> {
+    let parts: Vec<u32> = s.split(|c: char| !c.is_ascii_digit()).filter(|p| !p.is_empty()).take(3).filter_map(|p| p.parse().ok()).collect();
+    let [a, b, c] = parts[..] else {
+        return None;
+    };
+    let (y, m, d) = match format {
+        DateFormat::Ymd => (a, b, c),
+        DateFormat::Dmy => (c, b, a),
+        DateFormat::Mdy => (c, a, b),
+    };
+    let y = if y < 100 { y + 2000 } else { y };
+    let date = format!("{y:04}-{m:02}-{d:02}");
+    crate::custom_fields::valid_date(&date).then_some(date)
+}
+
+/// `$1,234.56`, `-12.00`, `(12.00)`, `1.234,56 €`.
+fn parse_amount(s: &str, decimal_comma: bool) -> Option<f64> {
+    let s = s.trim();
+    let negative = s.starts_with('-') || s.ends_with('-') || (s.starts_with('(') && s.ends_with(')'));
+    let decimal = if decimal_comma { ',' } else { '.' };
+    let digits: String = s
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == decimal)
+        .map(|c| if c == decimal { '.' } else { c })
+        .collect();
+    let amount: f64 = digits.parse().ok()?;
+    Some(if negative { -amount } else { amount })
+}
+
+fn deposit_id(date: &str, amount: f64, currency: &str, description: &str, occurrence: usize) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{date}|{amount:.2}|{currency}|{description}|{occurrence}"));
+    hex::encode(&hasher.finalize()[..12])
+}
+
+/// Deposits in a bank export, and the rows skipped with why.
+fn read_deposits(text: &str, mapping: &BankCsvMapping) -> Result<(Vec<BankDeposit>, usize, Vec<String>), String> {
+    let rows = parse_csv(text, mapping.delimiter.unwrap_or(','));
+    let (header, rows) = rows.split_first().ok_or("The file has no rows")?;
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| format!("No \"{name}\" column in the file"))
+    };
+    let optional = |name: &Option<String>| name.as_deref().map(column).transpose();
+    let date_col = column(&mapping.date_column)?;
+    let amount_col = optional(&mapping.amount_column)?;
+    let credit_col = optional(&mapping.credit_column)?;
+    let description_col = optional(&mapping.description_column)?;
+    let currency_col = optional(&mapping.currency_column)?;
+    if amount_col.is_none() && credit_col.is_none() {
+        return Err("Map either an amount or a credit column".to_string());
+    }
+    let account_currency = mapping.currency.as_deref().unwrap_or(DEFAULT_CURRENCY).trim().to_ascii_uppercase();
+    let filter = mapping.description_contains.as_deref().map(str::to_lowercase).filter(|f| !f.trim().is_empty());
+
+    let mut deposits = Vec::new();
+    let mut skipped = 0;
+    let mut errors = Vec::new();
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    for (i, row) in rows.iter().enumerate() {
+        let line = i + 2;
+        let cell = |col: Option<usize>| col.and_then(|c| row.get(c)).map(|s| s.trim()).unwrap_or("");
+        let description = cell(description_col).to_string();
+        if filter.as_ref().is_some_and(|f| !description.to_lowercase().contains(f.as_str())) {
+            skipped += 1;
+            continue;
+        }
+        let Some(date) = parse_bank_date(cell(Some(date_col)), mapping.date_format) else {
+            skipped += 1;
+            errors.push(format!("Line {line}: couldn't read the date \"{}\"", cell(Some(date_col))));
+            continue;
+        };
+        let raw = if credit_col.is_some() && !cell(credit_col).is_empty() { cell(credit_col) } else { cell(amount_col) };
+        let amount = match parse_amount(raw, mapping.decimal_comma) {
+            Some(amount) if amount > 0.0 => round_cents(amount),
+            Some(_) => {
+                skipped += 1;
+                continue;
+            }
+            None if raw.is_empty() => {
+                skipped += 1;
+                continue;
+            }
+            None => {
+                skipped += 1;
+                errors.push(format!("Line {line}: couldn't read the amount \"{raw}\""));
+                continue;
+            }
+        };
+        let currency = match cell(currency_col) {
+            "" => account_currency.clone(),
+            c => c.to_ascii_uppercase(),
+        };
+        // Two identical deposits on one day are both real; number them so
+        // they don't collapse into one, while a re-import still dedupes.
+        let key = format!("{date}|{amount:.2}|{currency}|{description}");
+        let occurrence = seen.entry(key).and_modify(|n| *n += 1).or_insert(0);
+        let id = deposit_id(&date, amount, &currency, &description, *occurrence);
+        deposits.push(BankDeposit { id, date, amount, currency, description });
+    }
+    Ok((deposits, skipped, errors))
+}
+
+/// Expected payout batches from `sales`, keyed by batch id.
+fn build_batches(settings: &PayoutSettings, sales: &[PayoutSale]) -> BTreeMap<String, PayoutBatch> {
+    let mut batches: BTreeMap<String, PayoutBatch> = BTreeMap::new();
+    let mut add = |platform: &str, currency: &str, day: i64, amount: f64, sale_id: Option<&str>, refund: f64| {
+        let expected_date = date_of(day);
+        let id = format!("{platform}:{currency}:{expected_date}");
+        let batch = batches.entry(id.clone()).or_insert_with(|| PayoutBatch {
+            id,
+            platform: platform.to_string(),
+            currency: currency.to_string(),
+            expected_date,
+            amount: 0.0,
+            sale_ids: Vec::new(),
+            refund_adjustments: 0.0,
+        });
+        batch.amount += amount;
+        batch.refund_adjustments += refund;
+        if let Some(id) = sale_id {
+            batch.sale_ids.push(id.to_string());
+        }
+    };
+    for sale in sales {
+        let Some(sold) = day_number(&sale.sold_at) else {
+            continue;
+        };
+        let (delay, default_currency) = settings.schedule(&sale.platform);
+        let currency = sale
+            .currency
+            .as_deref()
+            .or(default_currency)
+            .unwrap_or(DEFAULT_CURRENCY)
+            .trim()
+            .to_ascii_uppercase();
+        let delay = i64::from(delay);
+        let gross = sale.sale_price + sale.shipping_charged - sale.platform_fees;
+        let refund_day = sale.refunded_at.as_deref().and_then(day_number).filter(|d| *d > sold);
+        match refund_day {
+            Some(day) if sale.refunded > 0.0 => {
+                add(&sale.platform, &currency, sold + delay, gross, Some(&sale.id), 0.0);
+                add(&sale.platform, &currency, day + delay, -sale.refunded, None, sale.refunded);
+            }
+            _ => add(&sale.platform, &currency, sold + delay, gross - sale.refunded, Some(&sale.id), 0.0),
+        }
+    }
+    for batch in batches.values_mut() {
+        batch.amount = round_cents(batch.amount);
+        batch.refund_adjustments = round_cents(batch.refund_adjustments);
+    }
+    batches
+}
+
+fn row(status: ReconStatus, batches: Vec<PayoutBatch>, deposit: Option<BankDeposit>, manual: bool) -> ReconRow {
+    let difference = deposit
+        .as_ref()
+        .filter(|_| !batches.is_empty())
+        .map(|d| round_cents(d.amount - batches.iter().map(|b| b.amount).sum::<f64>()));
+    ReconRow { status, batches, deposit, difference, manual }
+}
+
+/// Pair `batches` with the imported deposits for `start..=end`.
+fn reconcile(
+    store: &ReconStore,
+    start: &str,
+    end: &str,
+    mut batches: BTreeMap<String, PayoutBatch>,
+    today: i64,
+) -> Result<Reconciliation, String> {
+    let (Some(first), Some(last)) = (day_number(start), day_number(end)) else {
+        return Err("Dates must be YYYY-MM-DD".to_string());
+    };
+    if first > last {
+        return Err("The range starts after it ends".to_string());
+    }
+    let settings = &store.settings;
+    let window = i64::from(settings.match_window_days);
+    batches.retain(|_, b| day_number(&b.expected_date).is_some_and(|d| d >= first && d <= last));
+    // Deposits for batches near the end of the range can land after it.
+    let mut deposits: BTreeMap<&str, &BankDeposit> = store
+        .deposits
+        .iter()
+        .filter(|d| day_number(&d.date).is_some_and(|day| day >= first - 1 && day <= last + window))
+        .map(|d| (d.id.as_str(), d))
+        .collect();
+    let mut rows = Vec::new();
+
+    for (deposit_id, batch_ids) in &store.manual {
+        let Some(deposit) = deposits.get(deposit_id.as_str()).copied() else {
+            continue;
+        };
+        let matched: Vec<PayoutBatch> = batch_ids.iter().filter_map(|id| batches.remove(id)).collect();
+        if matched.is_empty() {
+            continue;
+        }
+        deposits.remove(deposit_id.as_str());
+        rows.push(row(ReconStatus::Matched, matched, Some(deposit.clone()), true));
+    }
+
+    // Candidate pairs in each deposit's window, best first; each batch and
+    // deposit is used once.
+    let mut pairs: Vec<(f64, i64, &str, &str)> = Vec::new();
+    for batch in batches.values() {
+        let expected = day_number(&batch.expected_date).unwrap_or_default();
+        for deposit in deposits.values() {
+            let day = day_number(&deposit.date).unwrap_or_default();
+            if deposit.currency != batch.currency
+                || day < expected - 1
+                || day > expected + window
+                || store.rejected.contains(&(deposit.id.clone(), batch.id.clone()))
+            {
+                continue;
+            }
+            pairs.push(((deposit.amount - batch.amount).abs(), (day - expected).abs(), &batch.id, &deposit.id));
+        }
+    }
+    let tolerance = settings.tolerance + 0.005;
+    let mut taken_batches = HashSet::new();
+    let mut taken_deposits = HashSet::new();
+    let mut paired = Vec::new();
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+    for &(_, _, batch, deposit) in pairs.iter().filter(|p| p.0 <= tolerance) {
+        if !taken_batches.contains(batch) && !taken_deposits.contains(deposit) {
+            taken_batches.insert(batch);
+            taken_deposits.insert(deposit);
+            paired.push((ReconStatus::Matched, batch, deposit));
+        }
+    }
+    pairs.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.total_cmp(&b.0)));
+    for &(diff, _, batch, deposit) in &pairs {
+        let expected = batches[batch].amount.abs();
+        if diff > expected * MISMATCH_RATIO || taken_batches.contains(batch) || taken_deposits.contains(deposit) {
+            continue;
+        }
+        taken_batches.insert(batch);
+        taken_deposits.insert(deposit);
+        paired.push((ReconStatus::AmountMismatch, batch, deposit));
+    }
+    let paired: Vec<(ReconStatus, String, String)> =
+        paired.into_iter().map(|(s, b, d)| (s, b.to_string(), d.to_string())).collect();
+    for (status, batch, deposit) in paired {
+        let deposit = deposits.remove(deposit.as_str()).cloned();
+        if let Some(batch) = batches.remove(&batch) {
+            rows.push(row(status, vec![batch], deposit, false));
+        }
+    }
+
+    for batch in batches.into_values() {
+        let due = day_number(&batch.expected_date).unwrap_or_default() + window;
+        // Nothing to pay out (a batch wiped by refunds) isn't missing.
+        if batch.amount.abs() < 0.005 {
+            continue;
+        }
+        let status = if due < today { ReconStatus::MissingPayout } else { ReconStatus::Pending };
+        rows.push(row(status, vec![batch], None, false));
+    }
+    for deposit in deposits.into_values() {
+        if day_number(&deposit.date).is_some_and(|d| d >= first && d <= last) {
+            rows.push(row(ReconStatus::UnmatchedDeposit, Vec::new(), Some(deposit.clone()), false));
+        }
+    }
+
+    let date = |r: &ReconRow| {
+        r.batches.first().map(|b| b.expected_date.clone()).or_else(|| r.deposit.as_ref().map(|d| d.date.clone()))
+    };
+    rows.sort_by_key(date);
+    let mut totals: BTreeMap<String, CurrencyTotals> = BTreeMap::new();
+    for r in &rows {
+        for b in &r.batches {
+            totals.entry(b.currency.clone()).or_default().expected += b.amount;
+        }
+        if let Some(d) = &r.deposit {
+            totals.entry(d.currency.clone()).or_default().deposited += d.amount;
+        }
+    }
+    for t in totals.values_mut() {
+        t.expected = round_cents(t.expected);
+        t.deposited = round_cents(t.deposited);
+    }
+    Ok(Reconciliation { start: start.to_string(), end: end.to_string(), run_at: crate::unix_now(), rows, totals })
+}
+
+/// Run the last reconciliation again over the same batches, after a manual
+/// change or a new import.
+fn rerun(app: &tauri::AppHandle, store: &mut ReconStore) -> Result<Reconciliation, String> {
+    let last = store.last.as_ref().ok_or("Run reconcile_payouts first")?;
+    let batches = last.rows.iter().flat_map(|r| r.batches.iter()).map(|b| (b.id.clone(), b.clone())).collect();
+    let (start, end) = (last.start.clone(), last.end.clone());
+    let today = day_number(&crate::fees::today()).unwrap_or_default();
+    let result = reconcile(store, &start, &end, batches, today)?;
+    store.last = Some(result.clone());
+    crate::store::save(app, STORE_NAME, &*store)?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn get_payout_settings(state: tauri::State<'_, PayoutState>) -> PayoutSettings {
+    state.store.lock().unwrap().settings.clone()
+}
+
+#[tauri::command]
+pub fn set_payout_settings(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PayoutState>,
+    settings: PayoutSettings,
+) -> Result<(), String> {
+    settings.validate()?;
+    let mut store = state.store.lock().unwrap();
+    store.settings = settings;
+    crate::store::save(&app, STORE_NAME, &*store)
+}
+
+/// Read deposits (credits) from a bank statement CSV. Importing an
+/// overlapping statement again only adds the rows not seen before.
+#[tauri::command]
+pub fn import_bank_csv(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PayoutState>,
+    path: String,
+    mapping: BankCsvMapping,
+) -> Result<BankImport, String> {
+    let bytes = std::fs::read(&path).map_err(|e| format!("read {path}: {e}"))?;
+    let (deposits, skipped, errors) = read_deposits(&String::from_utf8_lossy(&bytes), &mapping)?;
+    let mut store = state.store.lock().unwrap();
+    let known: HashSet<String> = store.deposits.iter().map(|d| d.id.clone()).collect();
+    let (new, old): (Vec<_>, Vec<_>) = deposits.into_iter().partition(|d| !known.contains(&d.id));
+    let imported = new.len();
+    store.deposits.extend(new);
+    store.deposits.sort_by(|a, b| a.date.cmp(&b.date));
+    crate::store::save(&app, STORE_NAME, &*store)?;
+    Ok(BankImport { imported, duplicates: old.len(), skipped, errors })
+}
+
+/// Match the expected payouts from `sales` against the imported deposits
+/// for `range`. The result is kept for `get_payout_reconciliation`.
+#[tauri::command]
+pub fn reconcile_payouts(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PayoutState>,
+    range: PayoutRange,
+    sales: Vec<PayoutSale>,
+) -> Result<Reconciliation, String> {
+    let mut store = state.store.lock().unwrap();
+    let batches = build_batches(&store.settings, &sales);
+    let today = day_number(&crate::fees::today()).unwrap_or_default();
+    let result = reconcile(&store, &range.start, &range.end, batches, today)?;
+    store.last = Some(result.clone());
+    crate::store::save(&app, STORE_NAME, &*store)?;
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn get_payout_reconciliation(state: tauri::State<'_, PayoutState>) -> Option<Reconciliation> {
+    state.store.lock().unwrap().last.clone()
+}
+
+/// Record that `deposit_id` pays `batch_ids` (several when a marketplace
+/// paid a few days at once), whatever the amounts say.
+#[tauri::command]
+pub fn match_payout(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PayoutState>,
+    deposit_id: String,
+    batch_ids: Vec<String>,
+) -> Result<Reconciliation, String> {
+    let mut store = state.store.lock().unwrap();
+    if !store.deposits.iter().any(|d| d.id == deposit_id) {
+        return Err(format!("No deposit {deposit_id}"));
+    }
+    if batch_ids.is_empty() {
+        return Err("Choose at least one payout to match".to_string());
+    }
+    let known: HashSet<&str> = store
+        .last
+        .iter()
+        .flat_map(|l| l.rows.iter())
+        .flat_map(|r| r.batches.iter())
+        .map(|b| b.id.as_str())
+        .collect();
+    if let Some(id) = batch_ids.iter().find(|id| !known.contains(id.as_str())) {
+        return Err(format!("No payout {id} in the current reconciliation"));
+    }
+    for ids in store.manual.values_mut() {
+        ids.retain(|id| !batch_ids.contains(id));
+    }
+    store.manual.retain(|_, ids| !ids.is_empty());
+    store.rejected.retain(|(d, b)| !(*d == deposit_id && batch_ids.contains(b)));
+    store.manual.insert(deposit_id, batch_ids);
+    rerun(&app, &mut store)
+}
+
+/// Undo a match on `deposit_id`, manual or automatic. The pairs are
+/// remembered so later runs don't match them again.
+#[tauri::command]
+pub fn unmatch_payout(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PayoutState>,
+    deposit_id: String,
+) -> Result<Reconciliation, String> {
+    let mut store = state.store.lock().unwrap();
+    let batch_ids: Vec<String> = store
+        .last
+        .iter()
+        .flat_map(|l| l.rows.iter())
+        .filter(|r| r.deposit.as_ref().is_some_and(|d| d.id == deposit_id))
+        .flat_map(|r| r.batches.iter().map(|b| b.id.clone()))
+        .collect();
+    if batch_ids.is_empty() && !store.manual.contains_key(&deposit_id) {
+        return Err("That deposit isn't matched".to_string());
+    }
+    store.manual.remove(&deposit_id);
+    for id in batch_ids {
+        store.rejected.insert((deposit_id.clone(), id));
+    }
+    rerun(&app, &mut store)
+}
+
+/// Write the last reconciliation to `dest_path` as CSV.
+#[tauri::command]
+pub fn export_payout_reconciliation(
+    state: tauri::State<'_, PayoutState>,
+    dest_path: String,
+) -> Result<ReconExport, String> {
+    crate::privacy::ensure_off("export the reconciliation")?;
+    let store = state.store.lock().unwrap();
+    let last = store.last.as_ref().ok_or("Run reconcile_payouts first")?;
+    let mut out = String::from(
+        "status,marketplace,currency,expected_date,expected_amount,refund_adjustments,deposit_date,deposit_amount,difference,manual,description,sale_ids\n",
+    );
+    for r in &last.rows {
+        let join = |f: &dyn Fn(&PayoutBatch) -> String| r.batches.iter().map(f).collect::<Vec<_>>().join(" ");
+        let currency = r.deposit.as_ref().map(|d| d.currency.clone()).unwrap_or_else(|| join(&|b| b.currency.clone()));
+        let fields = [
+            r.status.as_str().to_string(),
+            join(&|b| b.platform.clone()),
+            currency,
+            join(&|b| b.expected_date.clone()),
+            if r.batches.is_empty() { String::new() } else { format!("{:.2}", r.batches.iter().map(|b| b.amount).sum::<f64>()) },
+            if r.batches.is_empty() {
+                String::new()
+            } else {
+                format!("{:.2}", r.batches.iter().map(|b| b.refund_adjustments).sum::<f64>())
+            },
+            r.deposit.as_ref().map(|d| d.date.clone()).unwrap_or_default(),
+            r.deposit.as_ref().map(|d| format!("{:.2}", d.amount)).unwrap_or_default(),
+            r.difference.map(|d| format!("{d:.2}")).unwrap_or_default(),
+            r.manual.to_string(),
+            r.deposit.as_ref().map(|d| d.description.clone()).unwrap_or_default(),
+            join(&|b| b.sale_ids.join(" ")),
+        ];
+        out.push_str(&fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+        out.push('\n');
+    }
+    out.push('\n');
+    out.push_str("currency,expected,deposited,difference\n");
+    for (currency, t) in &last.totals {
+        out.push_str(&format!("{currency},{:.2},{:.2},{:.2}\n", t.expected, t.deposited, t.deposited - t.expected));
+    }
+    std::fs::write(&dest_path, out).map_err(|e| format!("write {dest_path}: {e}"))?;
+    Ok(ReconExport { path: dest_path, rows: last.rows.len() })
+}
@@ -0,0 +1,178 @@
+// ── Printable PDF layout ───────────────────────────────────────────────────
+// Shared page/grid/text helpers for anything FlipTools prints (QR cards,
+// labels, pick sheets). Coordinates are PDF points with the origin at the
+// bottom-left of the page; text uses the built-in Helvetica so nothing has to
+// be embedded.
+
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str};
+use serde::Deserialize;
+
+pub const FONT: Name<'static> = Name(b"F1");
+pub const FONT_BOLD: Name<'static> = Name(b"F2");
+
+#[derive(Clone, Copy, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PaperSize {
+    #[default]
+    A4,
+    Letter,
+}
+
+impl PaperSize {
+    /// (width, height) in points.
+    pub fn dimensions(self) -> (f32, f32) {
+        match self {
+            PaperSize::A4 => (595.28, 841.89),
+            PaperSize::Letter => (612.0, 792.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Cell {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+/// Cells of a `columns` x `rows` grid, row-major starting top-left.
+pub fn grid(page: (f32, f32), columns: u32, rows: u32, margin: f32, gutter: f32) -> Vec<Cell> {
+    let (pw, ph) = page;
+    let columns = columns.max(1);
+    let rows = rows.max(1);
+    let w = (pw - 2.0 * margin - gutter * (columns - 1) as f32) / columns as f32;
+    let h = (ph - 2.0 * margin - gutter * (rows - 1) as f32) / rows as f32;
+    let mut cells = Vec::with_capacity((columns * rows) as usize);
+    for r in 0..rows {
+        for c in 0..columns {
+            cells.push(Cell {
+                x: margin + c as f32 * (w + gutter),
+                y: ph - margin - (r + 1) as f32 * h - r as f32 * gutter,
+                w,
+                h,
+            });
+        }
+    }
+    cells
+}
+
+/// Encode text for the WinAnsi-encoded base fonts; anything outside it becomes '?'.
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' => c as u8,
+            '\u{a0}'..='\u{ff}' => c as u32 as u8,
+            '€' => 0x80,
+            '‘' | '’' => b'\'',
+            '“' | '”' => b'"',
+            '–' | '—' => b'-',
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// Approximate Helvetica advance width (average glyph ≈ 0.52 em).
+pub fn text_width(text: &str, size: f32) -> f32 {
+    text.chars().count() as f32 * size * 0.52
+}
+
+/// Shorten `text` with "..." until it fits `max_width`.
+pub fn fit_text(text: &str, size: f32, max_width: f32) -> String {
+    if text_width(text, size) <= max_width {
+        return text.to_string();
+    }
+    let mut out: String = text.to_string();
+    while !out.is_empty() && text_width(&out, size) + text_width("...", size) > max_width {
+        out.pop();
+    }
+    format!("{}...", out.trim_end())
+}
+
+/// Draw `text` with its left edge at `x` and baseline at `y`.
+pub fn draw_text(content: &mut Content, font: Name, size: f32, x: f32, y: f32, text: &str) {
+    let bytes = win_ansi(text);
+    content.begin_text();
+    content.set_font(font, size);
+    content.next_line(x, y);
+    content.show(Str(&bytes));
+    content.end_text();
+}
+
+/// Draw `text` centred horizontally within `[x, x + width]`.
+pub fn draw_text_centered(content: &mut Content, font: Name, size: f32, x: f32, width: f32, y: f32, text: &str) {
+    let fitted = fit_text(text, size, width);
+    let left = x + (width - text_width(&fitted, size)).max(0.0) / 2.0;
+    draw_text(content, font, size, left, y, &fitted);
+}
+
+/// Multi-page document with Helvetica (F1) and Helvetica-Bold (F2) available
+/// on every page.
+pub struct PdfDocument {
+    pdf: Pdf,
+    next_id: i32,
+    catalog: Ref,
+    tree: Ref,
+    fonts: [Ref; 2],
+    pages: Vec<Ref>,
+}
+
+impl Default for PdfDocument {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PdfDocument {
+    pub fn new() -> Self {
+        let mut doc = PdfDocument {
+            pdf: Pdf::new(),
+            next_id: 1,
+            catalog: Ref::new(1),
+            tree: Ref::new(1),
+            fonts: [Ref::new(1); 2],
+            pages: Vec::new(),
+        };
+        doc.catalog = doc.alloc();
+        doc.tree = doc.alloc();
+        doc.fonts = [doc.alloc(), doc.alloc()];
+        doc
+    }
+
+    fn alloc(&mut self) -> Ref {
+        let r = Ref::new(self.next_id);
+        self.next_id += 1;
+        r
+    }
+
+    pub fn add_page(&mut self, size: (f32, f32), content: Content) {
+        let page_id = self.alloc();
+        let content_id = self.alloc();
+        let mut page = self.pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, size.0, size.1));
+        page.parent(self.tree);
+        page.contents(content_id);
+        page.resources()
+            .fonts()
+            .pair(FONT, self.fonts[0])
+            .pair(FONT_BOLD, self.fonts[1]);
+        page.finish();
+        self.pdf.stream(content_id, &content.finish());
+        self.pages.push(page_id);
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        self.pdf.catalog(self.catalog).pages(self.tree);
+        self.pdf
+            .pages(self.tree)
+            .kids(self.pages.iter().copied())
+            .count(self.pages.len() as i32);
+        for (id, name) in self.fonts.into_iter().zip([&b"Helvetica"[..], &b"Helvetica-Bold"[..]]) {
+            self.pdf
+                .type1_font(id)
+                .base_font(Name(name))
+                .encoding_predefined(Name(b"WinAnsiEncoding"));
+        }
+        self.pdf.finish()
+    }
+}
@@ -0,0 +1,142 @@
+// ── Photo metadata ─────────────────────────────────────────────────────────
+// Per-photo alt text, a private note ("small hole near left cuff") and a
+// flaw flag. Photos are identified by the path stored in the item's
+// `images`, the same key retention's photo-locations index uses, so
+// metadata follows a photo through an archive run.
+//
+// Photos downloaded from another listing for a clone are flagged
+// `reference_only`: fine for measuring against, not for relisting.
+//
+// Alt text is returned with the listing preview, parallel to the images,
+// for exports. Flaw-flagged photos feed a compliance warning when the
+// description never mentions a flaw (compliance/flaws.rs). Private notes
+// never leave the app.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+const STORE_NAME: &str = "photo_metadata";
+const MAX_ALT_TEXT_CHARS: usize = 250;
+const MAX_NOTE_CHARS: usize = 1000;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PhotoMetadata {
+    pub alt_text: Option<String>,
+    pub private_note: Option<String>,
+    /// The photo shows a flaw buyers should be told about.
+    pub flaw: bool,
+    /// Copied from another listing as a reference for a clone; reuse is not
+    /// recommended.
+    pub reference_only: bool,
+}
+
+impl PhotoMetadata {
+    /// Trimmed, with blank strings dropped; Err when something is too long.
+    fn clean(self) -> Result<Self, String> {
+        let trim = |s: Option<String>| s.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+        let meta = PhotoMetadata {
+            alt_text: trim(self.alt_text),
+            private_note: trim(self.private_note),
+            flaw: self.flaw,
+            reference_only: self.reference_only,
+        };
+        if meta.alt_text.as_ref().is_some_and(|a| a.chars().count() > MAX_ALT_TEXT_CHARS) {
+            return Err(format!("Alt text is limited to {MAX_ALT_TEXT_CHARS} characters"));
+        }
+        if meta.private_note.as_ref().is_some_and(|n| n.chars().count() > MAX_NOTE_CHARS) {
+            return Err(format!("Photo notes are limited to {MAX_NOTE_CHARS} characters"));
+        }
+        Ok(meta)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.alt_text.is_none() && self.private_note.is_none() && !self.flaw && !self.reference_only
+    }
+}
+
+pub struct PhotoMetaState {
+    photos: Mutex<BTreeMap<String, PhotoMetadata>>,
+}
+
+impl PhotoMetaState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        PhotoMetaState { photos: Mutex::new(crate::store::load(app, STORE_NAME)) }
+    }
+
+    pub fn get(&self, photo_id: &str) -> Option<PhotoMetadata> {
+        self.photos.lock().unwrap().get(photo_id).cloned()
+    }
+
+    /// Alt text for each of `images`, in order.
+    pub fn alt_texts(&self, images: &[String]) -> Vec<Option<String>> {
+        let photos = self.photos.lock().unwrap();
+        images.iter().map(|i| photos.get(i).and_then(|m| m.alt_text.clone())).collect()
+    }
+
+    /// Flag `photos` as references not meant for reuse, with a note saying
+    /// where they came from.
+    pub fn mark_reference(&self, app: &tauri::AppHandle, photos: &[String], note: &str) -> Result<(), String> {
+        let mut all = self.photos.lock().unwrap();
+        for photo in photos {
+            let meta = all.entry(photo.clone()).or_default();
+            meta.reference_only = true;
+            meta.private_note.get_or_insert_with(|| note.to_string());
+        }
+        crate::store::save(app, STORE_NAME, &*all)
+    }
+
+    fn set(&self, app: &tauri::AppHandle, entries: Vec<(String, PhotoMetadata)>) -> Result<(), String> {
+        let entries = entries
+            .into_iter()
+            .map(|(id, meta)| {
+                let id = id.trim().to_string();
+                if id.is_empty() {
+                    return Err("Photo id can't be empty".to_string());
+                }
+                Ok((id, meta.clean()?))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let mut photos = self.photos.lock().unwrap();
+        for (id, meta) in entries {
+            if meta.is_empty() {
+                photos.remove(&id);
+            } else {
+                photos.insert(id, meta);
+            }
+        }
+        crate::store::save(app, STORE_NAME, &*photos)
+    }
+}
+
+#[tauri::command]
+pub fn get_photo_metadata(
+    state: tauri::State<'_, PhotoMetaState>,
+    photo_ids: Vec<String>,
+) -> BTreeMap<String, PhotoMetadata> {
+    let photos = state.photos.lock().unwrap();
+    photo_ids.into_iter().filter_map(|id| photos.get(&id).cloned().map(|m| (id, m))).collect()
+}
+
+/// Replace a photo's metadata; an empty `meta` clears it.
+#[tauri::command]
+pub fn set_photo_metadata(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PhotoMetaState>,
+    photo_id: String,
+    meta: PhotoMetadata,
+) -> Result<(), String> {
+    state.set(&app, vec![(photo_id, meta)])
+}
+
+/// `set_photo_metadata` for many photos at once (bulk alt text). Nothing is
+/// saved if any entry is invalid.
+#[tauri::command]
+pub fn set_photos_metadata(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PhotoMetaState>,
+    photos: BTreeMap<String, PhotoMetadata>,
+) -> Result<(), String> {
+    state.set(&app, photos.into_iter().collect())
+}
@@ -0,0 +1,188 @@
+// ── Photo shoot assignment ─────────────────────────────────────────────────
+// After a shoot the camera roll is IMG_4032..IMG_4190. The frontend groups
+// the shots per item and hands each group to `assign_photo_groups`, which
+// either adds the photos to an existing item or creates a draft item with a
+// new SKU (see sku.rs), then copies the files into the item's folder as
+// `<sku>-01.jpg`, `<sku>-02.jpg`, ... The drafts come back for the frontend
+// to insert; nothing is written to Supabase here apart from the sequence.
+//
+// Originals are left where they are unless `move_files` is set. Numbering
+// continues after any photos already in the item's folder.
+
+use crate::sku::SkuState;
+use crate::supabase::SupabaseSession;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_DIR: &str = "item-photos";
+
+#[derive(Deserialize)]
+pub struct NewItem {
+    category: String,
+    #[serde(default)]
+    title: Option<String>,
+}
+
+/// An existing item's SKU, or the details of a new draft item.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum SkuOrNewItem {
+    Sku(String),
+    NewItem(NewItem),
+}
+
+#[derive(Deserialize)]
+pub struct GroupAssignment {
+    group_id: String,
+    photos: Vec<String>,
+    sku_or_new_item: SkuOrNewItem,
+}
+
+#[derive(Serialize)]
+pub struct ItemDraft {
+    sku: String,
+    category: String,
+    title: Option<String>,
+    images: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct GroupResult {
+    group_id: String,
+    sku: Option<String>,
+    /// The photos' new paths, in order.
+    photos: Vec<String>,
+    /// Set when the group created a new item.
+    draft: Option<ItemDraft>,
+    error: Option<String>,
+}
+
+/// Highest `<sku>-NN` number already in `dir`.
+fn last_index(dir: &Path, sku: &str) -> usize {
+    let prefix = format!("{sku}-");
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| {
+                    let name = e.file_name().to_string_lossy().into_owned();
+                    let stem = name.strip_prefix(&prefix)?.split('.').next()?.to_string();
+                    stem.parse::<usize>().ok()
+                })
+                .max()
+                .unwrap_or(0)
+        })
+        .unwrap_or(0)
+}
+
+fn place(source: &Path, dest: &Path, move_files: bool) -> Result<(), String> {
+    if move_files && std::fs::rename(source, dest).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(source, dest).map_err(|e| format!("copy {}: {e}", source.display()))?;
+    if move_files {
+        std::fs::remove_file(source).map_err(|e| format!("remove {}: {e}", source.display()))?;
+    }
+    Ok(())
+}
+
+/// Copy (or move) `photos` into `dir` as `<sku>-NN.<ext>`.
+fn file_photos(dir: &Path, sku: &str, photos: &[PathBuf], move_files: bool) -> Result<Vec<String>, String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("create {}: {e}", dir.display()))?;
+    let start = last_index(dir, sku);
+    let mut placed = Vec::with_capacity(photos.len());
+    for (i, source) in photos.iter().enumerate() {
+        let ext = source
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_else(|| "jpg".to_string());
+        let dest = dir.join(format!("{sku}-{:02}.{ext}", start + i + 1));
+        place(source, &dest, move_files)?;
+        placed.push(dest.to_string_lossy().into_owned());
+    }
+    Ok(placed)
+}
+
+/// Assign each photo group to an item. Groups are independent: one failing
+/// (missing file, SKU claim error) doesn't stop the rest. Photos go to
+/// `photo_dir/<sku>/` (default: the app's item-photos folder).
+#[tauri::command]
+pub async fn assign_photo_groups(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SkuState>,
+    session: SupabaseSession,
+    assignments: Vec<GroupAssignment>,
+    photo_dir: Option<String>,
+    move_files: Option<bool>,
+) -> Result<Vec<GroupResult>, String> {
+    let photo_dir = match photo_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => crate::store::data_path(&app, DEFAULT_DIR)?,
+    };
+    let move_files = move_files.unwrap_or(false);
+    let settings = state.settings();
+    let mut results = Vec::with_capacity(assignments.len());
+
+    for assignment in assignments {
+        let mut result = GroupResult {
+            group_id: assignment.group_id,
+            sku: None,
+            photos: Vec::new(),
+            draft: None,
+            error: None,
+        };
+        let photos: Vec<PathBuf> = assignment.photos.iter().map(PathBuf::from).collect();
+        // Check the files before claiming a SKU, so a bad group doesn't use
+        // up a sequence number.
+        if let Some(missing) = photos.iter().find(|p| !p.is_file()) {
+            result.error = Some(format!("{} not found", missing.display()));
+            results.push(result);
+            continue;
+        }
+        let (sku, new_item) = match assignment.sku_or_new_item {
+            SkuOrNewItem::Sku(sku) => (sku.trim().to_string(), None),
+            SkuOrNewItem::NewItem(item) => match crate::sku::next_skus(&settings, &session, &item.category, 1).await {
+                Ok(mut skus) => (skus.pop().unwrap_or_default(), Some(item)),
+                Err(e) => {
+                    result.error = Some(e);
+                    results.push(result);
+                    continue;
+                }
+            },
+        };
+        if sku.is_empty() || sku.contains(['/', '\\']) || sku.starts_with('.') {
+            result.error = Some(format!("Invalid SKU {sku:?}"));
+            results.push(result);
+            continue;
+        }
+        match file_photos(&photo_dir.join(&sku), &sku, &photos, move_files) {
+            Ok(placed) => {
+                result.draft = new_item.map(|item| ItemDraft {
+                    sku: sku.clone(),
+                    category: item.category,
+                    title: item.title,
+                    images: placed.clone(),
+                });
+                result.photos = placed;
+            }
+            Err(e) => result.error = Some(e),
+        }
+        result.sku = Some(sku);
+        results.push(result);
+    }
+
+    crate::audit::record(
+        &app,
+        "assign_photo_groups",
+        None,
+        false,
+        json!({
+            "groups": results.len(),
+            "created": results.iter().filter(|r| r.draft.is_some()).count(),
+            "failed": results.iter().filter(|r| r.error.is_some()).count(),
+            "moved": move_files,
+        }),
+    );
+    Ok(results)
+}
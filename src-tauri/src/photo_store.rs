@@ -0,0 +1,509 @@
+// ── Photo store cleanup ────────────────────────────────────────────────────
+// Finds duplicate and orphaned files in the photo directories and cleans up
+// the subset the user approves.
+//
+// `analyze_photo_store` groups exact duplicates by content hash (files are
+// bucketed by size first, so unique sizes are never hashed) and
+// near-duplicates by a 64-bit difference hash. Files no item, draft or sale
+// record references are reported as orphans. Records are passed in by the
+// frontend; retention's archive copies and thumbnails count as referenced.
+//
+// `clean_photo_store` works in two steps so no record ever points at a
+// missing file:
+// 1. Every record that uses a file being removed gets a repoint (its whole
+//    new photo list) in a persisted journal.
+// 2. Only then do the files move to a per-run trash, purged after 30 days.
+// The frontend applies each repoint as a single record update and
+// acknowledges it. An interrupted run leaves journaled repoints to the
+// surviving copy, which is never touched.
+
+use crate::request_context::{CancelToken, Finished, RequestContext};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+const REPOINTS_STORE: &str = "photo_repoints";
+const RUNS_STORE: &str = "photo_cleanup_runs";
+const TRASH_DIR: &str = "photo-store-trash";
+const TRASH_DAYS: u64 = 30;
+const DAY_SECS: u64 = 24 * 60 * 60;
+const PURGE_CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+/// Hamming distance between difference hashes still counted as the same shot.
+const NEAR_DISTANCE: u32 = 6;
+
+/// A record and the photo paths it uses.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PhotoReference {
+    /// "item", "draft", "sale".
+    pub kind: String,
+    pub id: String,
+    pub photos: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct StoredFile {
+    path: String,
+    bytes: u64,
+    /// Records using this file, as "kind:id".
+    referenced_by: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct DuplicateGroup {
+    /// Suggested survivor: the most referenced copy, then the shortest path.
+    keep: String,
+    files: Vec<StoredFile>,
+    reclaimable_bytes: u64,
+}
+
+#[derive(Serialize)]
+pub struct PhotoStoreReport {
+    files: usize,
+    total_bytes: u64,
+    exact_duplicates: Vec<DuplicateGroup>,
+    /// Visually near-identical (re-encodes, resizes); review before removing.
+    near_duplicates: Vec<DuplicateGroup>,
+    orphans: Vec<StoredFile>,
+    /// Exact duplicates plus orphans.
+    reclaimable_bytes: u64,
+    /// Files that couldn't be read.
+    errors: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DuplicateResolution {
+    keep: String,
+    remove: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CleanPlan {
+    #[serde(default)]
+    duplicates: Vec<DuplicateResolution>,
+    #[serde(default)]
+    orphans: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Repoint {
+    pub id: String,
+    pub run_id: String,
+    pub kind: String,
+    pub record_id: String,
+    /// The record's full photo list after cleanup.
+    pub photos: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CleanupRun {
+    run_id: String,
+    at: u64,
+    /// Original path -> trash path.
+    moved: Vec<(String, String)>,
+}
+
+#[derive(Serialize)]
+pub struct CleanReport {
+    run_id: String,
+    repoints: Vec<Repoint>,
+    trashed: usize,
+    bytes_reclaimed: u64,
+    errors: Vec<String>,
+    purge_after: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct AnalyzeProgress {
+    phase: &'static str,
+    done: usize,
+    total: usize,
+}
+
+pub struct PhotoStoreState {
+    repoints: Mutex<Vec<Repoint>>,
+    runs: Mutex<Vec<CleanupRun>>,
+}
+
+impl PhotoStoreState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        PhotoStoreState {
+            repoints: Mutex::new(crate::store::load(app, REPOINTS_STORE)),
+            runs: Mutex::new(crate::store::load(app, RUNS_STORE)),
+        }
+    }
+}
+
+fn trash_root(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    crate::store::data_path(app, TRASH_DIR)
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+fn walk(dir: &Path, skip: &Path, out: &mut Vec<(PathBuf, u64)>, errors: &mut Vec<String>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(e) => {
+            errors.push(format!("{}: {e}", dir.display()));
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if meta.is_dir() {
+            if !path.starts_with(skip) {
+                walk(&path, skip, out, errors);
+            }
+        } else if meta.is_file() && is_image(&path) {
+            out.push((path, meta.len()));
+        }
+    }
+}
+
+fn file_sha256(path: &Path) -> Result<String, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// 64-bit difference hash: each bit says whether a pixel of the 9x8
+/// grayscale thumbnail is brighter than its right-hand neighbour.
+fn dhash(path: &Path) -> Result<u64, String> {
+    let img = image::open(path).map_err(|e| format!("decode {}: {e}", path.display()))?;
+    let small = img.resize_exact(9, 8, image::imageops::FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash = (hash << 1) | u64::from(small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0]);
+        }
+    }
+    Ok(hash)
+}
+
+fn group(paths: Vec<&StoredFile>) -> DuplicateGroup {
+    let files: Vec<StoredFile> = paths.into_iter().cloned().collect();
+    let keep = files
+        .iter()
+        .max_by(|a, b| {
+            a.referenced_by
+                .len()
+                .cmp(&b.referenced_by.len())
+                .then_with(|| b.path.len().cmp(&a.path.len()))
+                .then_with(|| b.path.cmp(&a.path))
+        })
+        .map(|f| f.path.clone())
+        .unwrap_or_default();
+    let reclaimable_bytes = files.iter().filter(|f| f.path != keep).map(|f| f.bytes).sum();
+    DuplicateGroup { keep, files, reclaimable_bytes }
+}
+
+fn analyze(
+    app: &tauri::AppHandle,
+    token: &CancelToken,
+    dirs: &[String],
+    references: &[PhotoReference],
+    tracked: &HashSet<String>,
+) -> Result<PhotoStoreReport, String> {
+    let trash = trash_root(app)?;
+    let mut errors = Vec::new();
+    let mut found = Vec::new();
+    for dir in dirs {
+        walk(Path::new(dir), &trash, &mut found, &mut errors);
+    }
+
+    let mut refs: HashMap<&str, Vec<String>> = HashMap::new();
+    for r in references {
+        for p in &r.photos {
+            refs.entry(p.as_str()).or_default().push(format!("{}:{}", r.kind, r.id));
+        }
+    }
+    let files: Vec<StoredFile> = found
+        .iter()
+        .map(|(path, bytes)| {
+            let path = path.to_string_lossy().into_owned();
+            StoredFile { referenced_by: refs.get(path.as_str()).cloned().unwrap_or_default(), path, bytes: *bytes }
+        })
+        .collect();
+    let total = files.len();
+    let progress = |phase: &'static str, done: usize| {
+        if done % 50 == 0 || done == total {
+            let _ = app.emit("photo-store-progress", AnalyzeProgress { phase, done, total });
+        }
+    };
+
+    // Exact duplicates: only same-size files can match.
+    let mut by_size: HashMap<u64, Vec<&StoredFile>> = HashMap::new();
+    for f in &files {
+        by_size.entry(f.bytes).or_default().push(f);
+    }
+    let mut by_hash: BTreeMap<String, Vec<&StoredFile>> = BTreeMap::new();
+    let mut done = 0;
+    for same_size in by_size.values().filter(|v| v.len() > 1) {
+        for f in same_size {
+            token.check()?;
+            done += 1;
+            progress("hashing", done);
+            match file_sha256(Path::new(&f.path)) {
+                Ok(h) => by_hash.entry(h).or_default().push(f),
+                Err(e) => errors.push(e),
+            }
+        }
+    }
+    let exact_duplicates: Vec<DuplicateGroup> = by_hash.into_values().filter(|g| g.len() > 1).map(group).collect();
+    let exact_members: HashSet<&str> =
+        exact_duplicates.iter().flat_map(|g| g.files.iter().map(|f| f.path.as_str())).collect();
+
+    // Near duplicates among what's left, one representative per exact group.
+    let mut hashed: Vec<(&StoredFile, u64)> = Vec::new();
+    for (i, f) in files.iter().enumerate() {
+        token.check()?;
+        progress("fingerprinting", i + 1);
+        if exact_members.contains(f.path.as_str()) {
+            continue;
+        }
+        match dhash(Path::new(&f.path)) {
+            Ok(h) => hashed.push((f, h)),
+            Err(e) => errors.push(e),
+        }
+    }
+    let mut claimed = vec![false; hashed.len()];
+    let mut near_duplicates = Vec::new();
+    for i in 0..hashed.len() {
+        if claimed[i] {
+            continue;
+        }
+        let mut members = vec![hashed[i].0];
+        for j in i + 1..hashed.len() {
+            if !claimed[j] && (hashed[i].1 ^ hashed[j].1).count_ones() <= NEAR_DISTANCE {
+                claimed[j] = true;
+                members.push(hashed[j].0);
+            }
+        }
+        if members.len() > 1 {
+            near_duplicates.push(group(members));
+        }
+    }
+
+    let orphans: Vec<StoredFile> = files
+        .iter()
+        .filter(|f| f.referenced_by.is_empty() && !tracked.contains(&f.path))
+        .cloned()
+        .collect();
+    let duplicate_bytes: u64 = exact_duplicates.iter().map(|g| g.reclaimable_bytes).sum();
+    // An orphan that is also a removable duplicate is only counted once.
+    let orphan_bytes: u64 = orphans
+        .iter()
+        .filter(|o| exact_duplicates.iter().all(|g| g.keep == o.path || !g.files.iter().any(|f| f.path == o.path)))
+        .map(|o| o.bytes)
+        .sum();
+
+    Ok(PhotoStoreReport {
+        files: total,
+        total_bytes: files.iter().map(|f| f.bytes).sum(),
+        exact_duplicates,
+        near_duplicates,
+        orphans,
+        reclaimable_bytes: duplicate_bytes + orphan_bytes,
+        errors,
+    })
+}
+
+/// Walk `dirs` and report duplicates, near-duplicates and orphans.
+/// `references` are the item/draft/sale records and their photo paths.
+/// Emits "photo-store-progress". Cancellable (see request_context).
+#[tauri::command]
+pub async fn analyze_photo_store(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    dirs: Vec<String>,
+    references: Vec<PhotoReference>,
+    invocation_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<Finished<PhotoStoreReport>, String> {
+    let ctx = RequestContext::begin(&app, &window, "analyze_photo_store", invocation_id, timeout_ms)?;
+    let tracked = app.state::<crate::retention::RetentionState>().tracked_paths();
+    let token = ctx.token();
+    let result = tauri::async_runtime::spawn_blocking(move || analyze(&app, &token, &dirs, &references, &tracked))
+        .await
+        .map_err(|e| format!("analysis task: {e}"))
+        .and_then(|r| r);
+    ctx.finish(result)
+}
+
+/// Rename, falling back to copy + delete across filesystems.
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if let Some(dir) = to.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("create {}: {e}", dir.display()))?;
+    }
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to).map_err(|e| format!("copy {} -> {}: {e}", from.display(), to.display()))?;
+    std::fs::remove_file(from).map_err(|e| format!("remove {}: {e}", from.display()))
+}
+
+fn save_state(app: &tauri::AppHandle, state: &PhotoStoreState) -> Result<(), String> {
+    crate::store::save(app, REPOINTS_STORE, &*state.repoints.lock().unwrap())?;
+    crate::store::save(app, RUNS_STORE, &*state.runs.lock().unwrap())
+}
+
+/// Carry out an approved plan: journal repoints for every record using a
+/// removed duplicate, then move the duplicates and orphans to trash.
+/// Orphans that a record does reference are refused.
+#[tauri::command]
+pub fn clean_photo_store(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PhotoStoreState>,
+    plan: CleanPlan,
+    references: Vec<PhotoReference>,
+) -> Result<CleanReport, String> {
+    let referenced: HashSet<&str> = references.iter().flat_map(|r| r.photos.iter().map(String::as_str)).collect();
+    let mut replacement: HashMap<&str, &str> = HashMap::new();
+    for d in &plan.duplicates {
+        if !Path::new(&d.keep).is_file() {
+            return Err(format!("The copy to keep is missing: {}", d.keep));
+        }
+        for r in d.remove.iter().filter(|r| **r != d.keep) {
+            replacement.insert(r.as_str(), d.keep.as_str());
+        }
+    }
+    if let Some(o) = plan.orphans.iter().find(|o| referenced.contains(o.as_str())) {
+        return Err(format!("{o} is still used by a record"));
+    }
+
+    let run_id = format!("{}-{}", crate::unix_now(), hex::encode(rand::random::<[u8; 4]>()));
+
+    // 1. Journal every repoint before any file moves.
+    let repoints: Vec<Repoint> = references
+        .iter()
+        .filter(|r| r.photos.iter().any(|p| replacement.contains_key(p.as_str())))
+        .map(|r| {
+            let mut photos: Vec<String> = Vec::with_capacity(r.photos.len());
+            for p in &r.photos {
+                let p = replacement.get(p.as_str()).copied().unwrap_or(p).to_string();
+                if !photos.contains(&p) {
+                    photos.push(p);
+                }
+            }
+            Repoint {
+                id: format!("{run_id}:{}:{}", r.kind, r.id),
+                run_id: run_id.clone(),
+                kind: r.kind.clone(),
+                record_id: r.id.clone(),
+                photos,
+            }
+        })
+        .collect();
+    state.repoints.lock().unwrap().extend(repoints.iter().cloned());
+    save_state(&app, &state)?;
+
+    // 2. Move the files out.
+    let dir = trash_root(&app)?.join(&run_id);
+    let mut moved = Vec::new();
+    let mut errors = Vec::new();
+    let mut bytes = 0;
+    let doomed = replacement.keys().copied().chain(plan.orphans.iter().map(String::as_str));
+    for (n, path) in doomed.enumerate() {
+        let from = Path::new(path);
+        let size = std::fs::metadata(from).map(|m| m.len()).unwrap_or(0);
+        let name = from.file_name().and_then(|n| n.to_str()).unwrap_or("photo");
+        let to = dir.join(format!("{n:05}-{name}"));
+        match move_file(from, &to) {
+            Ok(()) => {
+                bytes += size;
+                moved.push((path.to_string(), to.to_string_lossy().into_owned()));
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+
+    state.runs.lock().unwrap().push(CleanupRun { run_id: run_id.clone(), at: crate::unix_now(), moved: moved.clone() });
+    save_state(&app, &state)?;
+    crate::audit::record(
+        &app,
+        "clean_photo_store",
+        None,
+        false,
+        serde_json::json!({ "run_id": run_id, "trashed": moved.len(), "repoints": repoints.len(), "bytes": bytes }),
+    );
+
+    Ok(CleanReport {
+        run_id,
+        repoints,
+        trashed: moved.len(),
+        bytes_reclaimed: bytes,
+        errors,
+        purge_after: crate::unix_now() + TRASH_DAYS * DAY_SECS,
+    })
+}
+
+/// Repoints not yet applied to their records (e.g. after an interrupted run).
+#[tauri::command]
+pub fn get_pending_repoints(state: tauri::State<'_, PhotoStoreState>) -> Vec<Repoint> {
+    state.repoints.lock().unwrap().clone()
+}
+
+/// Mark repoints as applied to their records.
+#[tauri::command]
+pub fn ack_photo_repoints(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PhotoStoreState>,
+    ids: Vec<String>,
+) -> Result<usize, String> {
+    let removed = {
+        let mut repoints = state.repoints.lock().unwrap();
+        let before = repoints.len();
+        repoints.retain(|r| !ids.contains(&r.id));
+        before - repoints.len()
+    };
+    save_state(&app, &state)?;
+    Ok(removed)
+}
+
+/// Delete cleanup trash older than 30 days.
+fn purge_trash(app: &tauri::AppHandle) -> Result<usize, String> {
+    let state = app.state::<PhotoStoreState>();
+    let cutoff = crate::unix_now().saturating_sub(TRASH_DAYS * DAY_SECS);
+    let trash = trash_root(app)?;
+    let expired: Vec<String> = {
+        let mut runs = state.runs.lock().unwrap();
+        let expired = runs.iter().filter(|r| r.at < cutoff).map(|r| r.run_id.clone()).collect();
+        runs.retain(|r| r.at >= cutoff);
+        expired
+    };
+    for run_id in &expired {
+        let dir = trash.join(run_id);
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).map_err(|e| format!("purge {}: {e}", dir.display()))?;
+        }
+    }
+    if !expired.is_empty() {
+        save_state(app, &state)?;
+    }
+    Ok(expired.len())
+}
+
+pub fn start_purge_job(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match purge_trash(&app) {
+                Ok(0) => {}
+                Ok(n) => log::info!("purged {n} photo cleanup run(s)"),
+                Err(e) => log::warn!("photo cleanup trash purge failed: {e}"),
+            }
+            tokio::time::sleep(PURGE_CHECK_INTERVAL).await;
+        }
+    });
+}
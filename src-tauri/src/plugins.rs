@@ -0,0 +1,544 @@
+// ── User plugins ───────────────────────────────────────────────────────────
+// Small user scripts for customisations that don't justify a core feature
+// (house SKU formats, extra pre-publish checks, personal repricing rules).
+// Plugins are `.js` files in `<app_data>/plugins/` and run in an embedded
+// JS engine (boa) with nothing but the language built-ins: no network, no
+// filesystem, no timers. Each hook gets its input as a deep-frozen copy of
+// plain JSON data and returns plain JSON back.
+//
+// A plugin implements a hook by defining a top-level function:
+//   generateSku(item)                     -> string | null
+//   validateListing({ item, marketplace }) -> [{ field, message }] | []
+//   suggestPrice({ item, ... })           -> { price, reason } | null
+//
+// Every call runs on its own thread with a loop/recursion budget, a memory
+// cap and a wall-clock limit. A plugin that throws is skipped with a
+// warning; one that blows its time budget is also disabled. boa can't be
+// interrupted, so the stray thread may keep spinning (nested loops each get
+// the full loop budget); until it stops, no plugin runs at all, so strays
+// can't pile up. A run that goes over its memory cap is parked for good
+// rather than allowed to take the host down, which makes it such a stray.
+// At most MAX_LIVE_RUNS plugin threads exist at once. New plugins start
+// disabled.
+
+use boa_engine::{Context, Source};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+const STORE_NAME: &str = "plugin_settings";
+const PLUGIN_DIR: &str = "plugins";
+const MAX_SCRIPT_BYTES: u64 = 256 * 1024;
+const TIME_BUDGET: Duration = Duration::from_millis(250);
+const LOOP_ITERATION_LIMIT: u64 = 1_000_000;
+const RECURSION_LIMIT: usize = 256;
+/// Memory one run may hold, the engine's own included.
+const HEAP_LIMIT: usize = 64 * 1024 * 1024;
+/// Longest string `repeat`, `padStart` and `padEnd` may build; the clean
+/// error for the usual way of blowing the memory cap.
+const MAX_STRING_CHARS: usize = 1024 * 1024;
+const MAX_LIVE_RUNS: usize = 4;
+
+/// Plugin threads alive, and how many of those ran past their time budget.
+static LIVE_RUNS: AtomicUsize = AtomicUsize::new(0);
+static STRAY_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+/// Per-thread memory accounting for plugin runs. Threads that never call
+/// `track` (everything but plugin runs) only pay for a thread-local read.
+mod heap {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    thread_local! {
+        /// (bytes held, limit) while the thread is tracked.
+        static USAGE: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+    }
+
+    pub fn track(limit: usize) {
+        USAGE.with(|u| u.set(Some((0, limit))));
+    }
+
+    pub fn untrack() {
+        USAGE.with(|u| u.set(None));
+    }
+
+    fn grow(by: usize) {
+        let _ = USAGE.try_with(|u| {
+            if let Some((used, limit)) = u.get() {
+                if used.saturating_add(by) > limit {
+                    // Failing the allocation would abort the whole app, and
+                    // unwinding out of an allocator isn't allowed: park the
+                    // thread instead. It holds at most `limit` bytes, and
+                    // the caller sees it time out.
+                    loop {
+                        std::thread::park();
+                    }
+                }
+                u.set(Some((used + by, limit)));
+            }
+        });
+    }
+
+    fn shrink(by: usize) {
+        let _ = USAGE.try_with(|u| {
+            if let Some((used, limit)) = u.get() {
+                u.set(Some((used.saturating_sub(by), limit)));
+            }
+        });
+    }
+
+    pub struct Counting;
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            grow(layout.size());
+            System.alloc(layout)
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            grow(layout.size());
+            System.alloc_zeroed(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            shrink(layout.size());
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            if new_size > layout.size() {
+                grow(new_size - layout.size());
+            } else {
+                shrink(layout.size() - new_size);
+            }
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: Counting = Counting;
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Hook {
+    SkuGeneration,
+    PrePublishValidation,
+    RepricingSuggestion,
+}
+
+impl Hook {
+    const ALL: [Hook; 3] = [Hook::SkuGeneration, Hook::PrePublishValidation, Hook::RepricingSuggestion];
+
+    /// The function a plugin defines to implement this hook.
+    fn function(self) -> &'static str {
+        match self {
+            Hook::SkuGeneration => "generateSku",
+            Hook::PrePublishValidation => "validateListing",
+            Hook::RepricingSuggestion => "suggestPrice",
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct PluginSettings {
+    enabled: bool,
+    /// Why the plugin was switched off automatically, if it was.
+    #[serde(default)]
+    disabled_reason: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PluginInfo {
+    name: String,
+    enabled: bool,
+    disabled_reason: Option<String>,
+    hooks: Vec<Hook>,
+    size_bytes: u64,
+    /// Set when the script doesn't load (syntax error, over budget).
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PluginOutput {
+    pub plugin: String,
+    pub value: Value,
+}
+
+#[derive(Serialize)]
+pub struct PluginTestResult {
+    output: Option<Value>,
+    error: Option<String>,
+    elapsed_ms: u64,
+}
+
+pub struct PluginState {
+    settings: Mutex<BTreeMap<String, PluginSettings>>,
+}
+
+impl PluginState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        PluginState { settings: Mutex::new(crate::store::load(app, STORE_NAME)) }
+    }
+}
+
+fn plugin_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let dir = crate::store::data_path(app, PLUGIN_DIR)?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("create {}: {e}", dir.display()))?;
+    Ok(dir)
+}
+
+/// Plugin names (file stems) and paths, sorted by name.
+fn discover(app: &tauri::AppHandle) -> Result<Vec<(String, PathBuf)>, String> {
+    let dir = plugin_dir(app)?;
+    let mut found: Vec<(String, PathBuf)> = std::fs::read_dir(&dir)
+        .map_err(|e| format!("read {}: {e}", dir.display()))?
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|x| x == "js"))
+        .filter_map(|p| Some((p.file_stem()?.to_str()?.to_string(), p)))
+        .collect();
+    found.sort();
+    Ok(found)
+}
+
+fn read_script(path: &PathBuf) -> Result<String, String> {
+    let size = std::fs::metadata(path).map_err(|e| format!("{}: {e}", path.display()))?.len();
+    if size > MAX_SCRIPT_BYTES {
+        return Err(format!("Plugin is {size} bytes; the limit is {MAX_SCRIPT_BYTES}"));
+    }
+    std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+#[derive(Debug)]
+enum RunError {
+    Failed(String),
+    TimedOut,
+}
+
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunError::Failed(e) => f.write_str(e),
+            RunError::TimedOut => write!(f, "exceeded its {} ms time budget", TIME_BUDGET.as_millis()),
+        }
+    }
+}
+
+const RUNNING: u8 = 0;
+const FINISHED: u8 = 1;
+/// The caller gave up waiting; the thread counts as a stray until it ends.
+const ABANDONED: u8 = 2;
+
+/// Runs at the end of a plugin thread, however it ends.
+struct RunGuard(Arc<AtomicU8>);
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        heap::untrack();
+        LIVE_RUNS.fetch_sub(1, Ordering::SeqCst);
+        if self.0.swap(FINISHED, Ordering::SeqCst) == ABANDONED {
+            STRAY_RUNS.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Throws instead of building strings past MAX_STRING_CHARS.
+fn string_guard() -> String {
+    format!(
+        r#"(function () {{
+  for (const name of ["repeat", "padStart", "padEnd"]) {{
+    const original = String.prototype[name];
+    Object.defineProperty(String.prototype, name, {{
+      value: function (...args) {{
+        const size = name === "repeat" ? String(this).length * Number(args[0]) : Number(args[0]);
+        if (size > {MAX_STRING_CHARS}) throw new RangeError(name + ": plugins can't build strings over {MAX_STRING_CHARS} characters");
+        return original.apply(this, args);
+      }},
+      writable: true,
+      configurable: true,
+    }});
+  }}
+}})();"#
+    )
+}
+
+/// Load `script` into a fresh engine, then evaluate `then` and return its
+/// string result.
+fn evaluate(script: String, then: String) -> Result<Option<String>, RunError> {
+    if STRAY_RUNS.load(Ordering::SeqCst) > 0 {
+        return Err(RunError::Failed(
+            "a plugin that ran out of time is still running; plugins are paused until it stops or FlipTools restarts"
+                .to_string(),
+        ));
+    }
+    if LIVE_RUNS.fetch_add(1, Ordering::SeqCst) >= MAX_LIVE_RUNS {
+        LIVE_RUNS.fetch_sub(1, Ordering::SeqCst);
+        return Err(RunError::Failed(format!("more than {MAX_LIVE_RUNS} plugin runs at once")));
+    }
+    let state = Arc::new(AtomicU8::new(RUNNING));
+    let guard = RunGuard(state.clone());
+    let (tx, rx) = mpsc::channel();
+    let spawned = std::thread::Builder::new().name("plugin".to_string()).spawn(move || {
+        let _guard = guard;
+        heap::track(HEAP_LIMIT);
+        let mut context = Context::default();
+        let limits = context.runtime_limits_mut();
+        limits.set_loop_iteration_limit(LOOP_ITERATION_LIMIT);
+        limits.set_recursion_limit(RECURSION_LIMIT);
+        let result = context
+            .eval(Source::from_bytes(&string_guard()))
+            .and_then(|_| context.eval(Source::from_bytes(&script)))
+            .and_then(|_| context.eval(Source::from_bytes(&then)))
+            .map(|v| v.as_string().map(|s| s.to_std_string_escaped()))
+            .map_err(|e| e.to_string());
+        let _ = tx.send(result);
+    });
+    // On a failed spawn the closure, and the guard with it, is dropped here.
+    spawned.map_err(|e| RunError::Failed(format!("spawn: {e}")))?;
+    match rx.recv_timeout(TIME_BUDGET) {
+        Ok(result) => result.map_err(RunError::Failed),
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            if state.compare_exchange(RUNNING, ABANDONED, Ordering::SeqCst, Ordering::SeqCst).is_ok() {
+                STRAY_RUNS.fetch_add(1, Ordering::SeqCst);
+                return Err(RunError::TimedOut);
+            }
+            // It finished just as the budget ran out.
+            rx.recv().map_err(|_| RunError::Failed("engine crashed".to_string()))?.map_err(RunError::Failed)
+        }
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err(RunError::Failed("engine crashed".to_string())),
+    }
+}
+
+/// Hooks `script` defines.
+fn hooks_of(script: String) -> Result<Vec<Hook>, RunError> {
+    let probe = format!(
+        "JSON.stringify([{}])",
+        Hook::ALL.iter().map(|h| format!("typeof {} === 'function'", h.function())).collect::<Vec<_>>().join(",")
+    );
+    let out = evaluate(script, probe)?.unwrap_or_default();
+    let defined: Vec<bool> = serde_json::from_str(&out).map_err(|e| RunError::Failed(e.to_string()))?;
+    Ok(Hook::ALL.into_iter().zip(defined).filter_map(|(h, d)| d.then_some(h)).collect())
+}
+
+/// Call `hook` in `script` with a frozen copy of `input`. None when the
+/// plugin doesn't define the hook or returns null/undefined.
+fn call(script: String, hook: Hook, input: &Value) -> Result<Option<Value>, RunError> {
+    // A JSON string is also a valid JS string literal.
+    let literal = serde_json::to_string(&input.to_string()).map_err(|e| RunError::Failed(e.to_string()))?;
+    let function = hook.function();
+    let invoke = format!(
+        r#"(function () {{
+  const freeze = (o) => {{
+    if (o && typeof o === "object" && !Object.isFrozen(o)) {{
+      Object.freeze(o);
+      Object.values(o).forEach(freeze);
+    }}
+    return o;
+  }};
+  if (typeof {function} !== "function") return undefined;
+  const out = {function}(freeze(JSON.parse({literal})));
+  return out === undefined ? undefined : JSON.stringify(out);
+}})()"#
+    );
+    let Some(out) = evaluate(script, invoke)? else {
+        return Ok(None);
+    };
+    let value: Value = serde_json::from_str(&out).map_err(|e| RunError::Failed(format!("bad return value: {e}")))?;
+    Ok((!value.is_null()).then_some(value))
+}
+
+/// Switch off a plugin that ran out of time.
+fn disable(app: &tauri::AppHandle, name: String, why: &RunError) {
+    let state = app.state::<PluginState>();
+    let mut settings = state.settings.lock().unwrap();
+    let entry = settings.entry(name).or_default();
+    entry.enabled = false;
+    entry.disabled_reason = Some(format!("Disabled automatically: {why}"));
+    if let Err(e) = crate::store::save(app, STORE_NAME, &*settings) {
+        log::warn!("saving plugin settings failed: {e}");
+    }
+}
+
+/// Run `hook` in every enabled plugin, in name order. Failures never reach
+/// the caller: the plugin is skipped with a warning (and disabled if it ran
+/// out of time).
+pub fn run(app: &tauri::AppHandle, hook: Hook, input: &Value) -> Vec<PluginOutput> {
+    let state = app.state::<PluginState>();
+    let Ok(plugins) = discover(app) else {
+        return Vec::new();
+    };
+    let mut outputs = Vec::new();
+    for (name, path) in plugins {
+        let enabled = state.settings.lock().unwrap().get(&name).is_some_and(|s| s.enabled);
+        if !enabled {
+            continue;
+        }
+        let result = read_script(&path).map_err(RunError::Failed).and_then(|script| call(script, hook, input));
+        match result {
+            Ok(Some(value)) => outputs.push(PluginOutput { plugin: name, value }),
+            Ok(None) => {}
+            Err(e) => {
+                log::warn!("plugin {name} skipped for {hook:?}: {e}");
+                if matches!(e, RunError::TimedOut) {
+                    disable(app, name, &e);
+                }
+            }
+        }
+    }
+    outputs
+}
+
+/// Field errors from `validateListing` plugins, in the shape the built-in
+/// validators use. Anything that isn't a `{ field, message }` list is ignored.
+pub fn validation_errors(app: &tauri::AppHandle, input: &Value) -> Vec<crate::depop::attributes::FieldError> {
+    run(app, Hook::PrePublishValidation, input)
+        .into_iter()
+        .filter_map(|out| match out.value {
+            Value::Array(items) => Some(items),
+            _ => {
+                log::warn!("plugin {} returned a non-list from validateListing", out.plugin);
+                None
+            }
+        })
+        .flatten()
+        .filter_map(|e| {
+            let message = e.get("message")?.as_str()?.to_string();
+            let field = e.get("field").and_then(Value::as_str).unwrap_or("plugin").to_string();
+            Some(crate::depop::attributes::FieldError { field, message })
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub async fn list_plugins(app: tauri::AppHandle) -> Result<Vec<PluginInfo>, String> {
+    let plugins = discover(&app)?;
+    let settings = app.state::<PluginState>().settings.lock().unwrap().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        plugins
+            .into_iter()
+            .map(|(name, path)| {
+                let size_bytes = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                let (hooks, error) = match read_script(&path).map_err(RunError::Failed).and_then(hooks_of) {
+                    Ok(hooks) => (hooks, None),
+                    Err(e) => (Vec::new(), Some(e.to_string())),
+                };
+                let s = settings.get(&name).cloned().unwrap_or_default();
+                PluginInfo { name, enabled: s.enabled, disabled_reason: s.disabled_reason, hooks, size_bytes, error }
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_plugin_enabled(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PluginState>,
+    name: String,
+    enabled: bool,
+) -> Result<(), String> {
+    if !discover(&app)?.iter().any(|(n, _)| *n == name) {
+        return Err(format!("No plugin named {name}"));
+    }
+    let mut settings = state.settings.lock().unwrap();
+    settings.insert(name, PluginSettings { enabled, disabled_reason: None });
+    crate::store::save(&app, STORE_NAME, &*settings)
+}
+
+/// Run one hook of one plugin (enabled or not) against sample input. Errors
+/// come back in the result; running out of time also disables the plugin,
+/// as it would in real use.
+#[tauri::command]
+pub async fn test_plugin(
+    app: tauri::AppHandle,
+    name: String,
+    hook: Hook,
+    sample_input: Value,
+) -> Result<PluginTestResult, String> {
+    let (_, path) = discover(&app)?
+        .into_iter()
+        .find(|(n, _)| *n == name)
+        .ok_or_else(|| format!("No plugin named {name}"))?;
+    let script = read_script(&path)?;
+    let started = Instant::now();
+    let result = tauri::async_runtime::spawn_blocking(move || call(script, hook, &sample_input))
+        .await
+        .map_err(|e| e.to_string())?;
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    if let Err(e @ RunError::TimedOut) = &result {
+        disable(&app, name, e);
+    }
+    Ok(match result {
+        Ok(output) => PluginTestResult { output, error: None, elapsed_ms },
+        Err(e) => PluginTestResult { output: None, error: Some(e.to_string()), elapsed_ms },
+    })
+}
+
+/// SKU from the first enabled `generateSku` plugin that returns a non-empty
+/// string; None means fall back to the built-in format.
+pub async fn plugin_sku(app: &tauri::AppHandle, item: Value) -> Result<Option<String>, String> {
+    let app = app.clone();
+    let outputs = tauri::async_runtime::spawn_blocking(move || run(&app, Hook::SkuGeneration, &item))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(outputs.into_iter().find_map(|out| match out.value {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        _ => None,
+    }))
+}
+
+#[tauri::command]
+pub async fn generate_plugin_sku(app: tauri::AppHandle, item: Value) -> Result<Option<String>, String> {
+    plugin_sku(&app, item).await
+}
+
+/// Price suggestions from every enabled `suggestPrice` plugin. `input`
+/// carries the item plus whatever context the caller has (recent sales,
+/// comps).
+#[tauri::command]
+pub async fn get_plugin_price_suggestions(app: tauri::AppHandle, input: Value) -> Result<Vec<PluginOutput>, String> {
+    let outputs = tauri::async_runtime::spawn_blocking(move || run(&app, Hook::RepricingSuggestion, &input))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(outputs
+        .into_iter()
+        .filter(|out| {
+            let ok = out.value.as_f64().or_else(|| out.value.get("price")?.as_f64()).is_some_and(|p| p >= 0.0);
+            if !ok {
+                log::warn!("plugin {} returned no usable price from suggestPrice", out.plugin);
+            }
+            ok
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn hooks_get_frozen_input_and_return_json() {
+        let script = "function generateSku(item) { item.sku = 'changed'; return item.category + '-1'; }".to_string();
+        let out = call(script, Hook::SkuGeneration, &json!({ "category": "JKT" })).unwrap();
+        assert_eq!(out, Some(json!("JKT-1")));
+    }
+
+    #[test]
+    fn huge_strings_are_refused() {
+        let script = "function generateSku() { return 'x'.repeat(1e9); }".to_string();
+        match call(script, Hook::SkuGeneration, &json!({})) {
+            Err(RunError::Failed(e)) => assert!(e.contains("can't build strings"), "{e}"),
+            other => panic!("expected a refusal, got {other:?}"),
+        }
+        let script = "function generateSku() { return ''.padStart(5e8, 'y'); }".to_string();
+        assert!(matches!(call(script, Hook::SkuGeneration, &json!({})), Err(RunError::Failed(_))));
+    }
+}
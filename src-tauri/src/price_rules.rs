@@ -0,0 +1,418 @@
+// ── Price rounding rules ───────────────────────────────────────────────────
+// Converting or discounting a price gives amounts like $57.23 or £35.9999;
+// listings should land on prices a person would pick. Each marketplace gets a
+// rounding strategy (nearest .00/.99/.95 or a custom ending, or up to a
+// minimum increment), falling back to a default. Everything is computed in
+// integer minor units so floating-point noise never survives into a price.
+//
+// Zero-decimal currencies (JPY, KRW, ...) have no cents, so endings apply to
+// the last two digits instead: ".99" on yen gives ¥5,799, ".00" the nearest
+// ¥100.
+//
+// Exchange rates are per US dollar, refreshed from the ECB reference rates
+// (frankfurter.app) on demand and kept between runs.
+
+use crate::marketplace_limits::LimitsState;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+const STORE_NAME: &str = "price_rules";
+const RATES_URL: &str = "https://api.frankfurter.app/latest?from=USD";
+const ZERO_DECIMAL: &[&str] = &["JPY", "KRW", "VND", "CLP", "ISK", "HUF", "TWD", "IDR"];
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RoundingStrategy {
+    /// Only round to the currency's minor unit.
+    #[default]
+    Exact,
+    /// Nearest whole amount (.00).
+    Whole,
+    /// Nearest amount ending in .99.
+    Charm99,
+    /// Nearest amount ending in .95.
+    Charm95,
+    /// Nearest amount ending in `.{cents}` (e.g. 97).
+    Ending { cents: u8 },
+    /// Round up to a multiple of `step` (e.g. 0.5 or 5).
+    Increment { step: f64 },
+}
+
+impl RoundingStrategy {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            RoundingStrategy::Ending { cents } if *cents > 99 => Err("Endings are two digits (0-99)".to_string()),
+            RoundingStrategy::Increment { step } if !step.is_finite() || *step <= 0.0 => {
+                Err("Increments must be positive".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PriceRuleSettings {
+    pub default_strategy: RoundingStrategy,
+    /// Per-marketplace strategy; marketplaces not listed use the default.
+    pub marketplaces: BTreeMap<String, RoundingStrategy>,
+    /// Units of each currency per US dollar.
+    pub rates: BTreeMap<String, f64>,
+    /// Date of the rates (`YYYY-MM-DD`).
+    pub rates_date: Option<String>,
+}
+
+impl PriceRuleSettings {
+    fn strategy(&self, marketplace: &str) -> RoundingStrategy {
+        self.marketplaces.get(marketplace).copied().unwrap_or(self.default_strategy)
+    }
+
+    fn rate(&self, currency: &str) -> Option<f64> {
+        if currency == "USD" {
+            return Some(1.0);
+        }
+        self.rates.get(currency).copied()
+    }
+}
+
+pub struct PriceRuleState {
+    settings: Mutex<PriceRuleSettings>,
+}
+
+impl PriceRuleState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        PriceRuleState { settings: Mutex::new(crate::store::load(app, STORE_NAME)) }
+    }
+
+    pub fn settings(&self) -> PriceRuleSettings {
+        self.settings.lock().unwrap().clone()
+    }
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct RoundedPrice {
+    pub original: f64,
+    pub from: String,
+    /// After conversion, before rounding.
+    pub converted: f64,
+    pub currency: String,
+    pub price: f64,
+    pub strategy: RoundingStrategy,
+    /// Raised to the marketplace's minimum price.
+    pub clamped_to_minimum: bool,
+}
+
+fn decimals(currency: &str) -> u32 {
+    if ZERO_DECIMAL.contains(&currency) {
+        0
+    } else {
+        2
+    }
+}
+
+/// Round `minor` (an amount in minor units) under `strategy`. `unit` is the
+/// size of one "whole" step the endings work against: 100 minor units for
+/// two-decimal currencies, 100 major units for zero-decimal ones.
+fn round_minor(minor: i64, strategy: RoundingStrategy, scale: i64, unit: i64) -> i64 {
+    if minor <= 0 {
+        return minor;
+    }
+    let ending = |end: i64| {
+        let base = minor.div_euclid(unit) * unit + end;
+        // Nearest of the candidates around `minor`, ties going up; prices
+        // never round to zero or below.
+        [base - unit, base, base + unit]
+            .into_iter()
+            .filter(|c| *c > 0)
+            .min_by_key(|c| ((c - minor).abs(), -c))
+            .unwrap_or(base + unit)
+    };
+    match strategy {
+        RoundingStrategy::Exact => minor,
+        RoundingStrategy::Whole => ending(0),
+        RoundingStrategy::Charm99 => ending(unit - unit / 100),
+        RoundingStrategy::Charm95 => ending(unit - unit / 20),
+        RoundingStrategy::Ending { cents } => ending(unit * i64::from(cents) / 100),
+        RoundingStrategy::Increment { step } => {
+            let step = ((step * scale as f64).round() as i64).max(1);
+            (minor + step - 1).div_euclid(step) * step
+        }
+    }
+}
+
+/// Round `amount` in `currency` under `strategy`.
+pub fn round_price(amount: f64, currency: &str, strategy: RoundingStrategy) -> f64 {
+    let scale = 10i64.pow(decimals(currency));
+    let minor = (amount * scale as f64).round() as i64;
+    let unit = if scale == 1 { 100 } else { scale };
+    round_minor(minor, strategy, scale, unit) as f64 / scale as f64
+}
+
+/// Convert `amount` from one currency to another and round it under the
+/// marketplace's strategy, never below the marketplace's minimum price.
+pub fn convert_and_round(
+    settings: &PriceRuleSettings,
+    limits: &LimitsState,
+    amount: f64,
+    from: &str,
+    to: &str,
+    marketplace: &str,
+) -> Result<RoundedPrice, String> {
+    if !amount.is_finite() || amount < 0.0 {
+        return Err("Price must be a non-negative amount".to_string());
+    }
+    let (from, to) = (from.to_uppercase(), to.to_uppercase());
+    let converted = if from == to {
+        amount
+    } else {
+        let missing = |c: &str| format!("No exchange rate for {c}; refresh rates first");
+        let from_rate = settings.rate(&from).ok_or_else(|| missing(&from))?;
+        let to_rate = settings.rate(&to).ok_or_else(|| missing(&to))?;
+        amount / from_rate * to_rate
+    };
+    let strategy = settings.strategy(marketplace);
+    let mut price = round_price(converted, &to, strategy);
+    let minimum = limits.get(marketplace).and_then(|l| l.limits.min_price);
+    let clamped_to_minimum = minimum.is_some_and(|min| price > 0.0 && price < min);
+    if let Some(min) = minimum.filter(|_| clamped_to_minimum) {
+        price = round_price(min, &to, RoundingStrategy::Exact);
+    }
+    let scale = 10f64.powi(decimals(&to) as i32);
+    Ok(RoundedPrice {
+        original: amount,
+        from,
+        converted: (converted * scale).round() / scale,
+        currency: to,
+        price,
+        strategy,
+        clamped_to_minimum,
+    })
+}
+
+#[tauri::command]
+pub fn get_price_rules(state: tauri::State<'_, PriceRuleState>) -> PriceRuleSettings {
+    state.settings.lock().unwrap().clone()
+}
+
+/// Replace the rounding strategies. Exchange rates are kept; use
+/// `refresh_exchange_rates` to update them.
+#[tauri::command]
+pub fn set_price_rules(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PriceRuleState>,
+    default_strategy: RoundingStrategy,
+    marketplaces: BTreeMap<String, RoundingStrategy>,
+) -> Result<PriceRuleSettings, String> {
+    default_strategy.validate()?;
+    marketplaces.values().try_for_each(RoundingStrategy::validate)?;
+    let mut settings = state.settings.lock().unwrap();
+    settings.default_strategy = default_strategy;
+    settings.marketplaces = marketplaces;
+    crate::store::save(&app, STORE_NAME, &*settings)?;
+    Ok(settings.clone())
+}
+
+#[derive(Deserialize)]
+struct RatesResponse {
+    date: String,
+    rates: BTreeMap<String, f64>,
+}
+
+#[tauri::command]
+pub async fn refresh_exchange_rates(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PriceRuleState>,
+) -> Result<PriceRuleSettings, String> {
+    let resp = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(15))
+        .build()
+        .map_err(|e| format!("client build: {e}"))?
+        .get(RATES_URL)
+        .send()
+        .await
+        .map_err(|e| format!("request: {e}"))?;
+    if !resp.status().is_success() {
+        return Err(format!("Exchange rates returned {}", resp.status().as_u16()));
+    }
+    let body: RatesResponse = resp.json().await.map_err(|e| format!("invalid rates: {e}"))?;
+    let mut settings = state.settings.lock().unwrap();
+    settings.rates = body.rates.into_iter().filter(|(_, r)| r.is_finite() && *r > 0.0).collect();
+    settings.rates_date = Some(body.date);
+    crate::store::save(&app, STORE_NAME, &*settings)?;
+    Ok(settings.clone())
+}
+
+#[tauri::command]
+pub fn convert_and_round_price(
+    state: tauri::State<'_, PriceRuleState>,
+    limits: tauri::State<'_, LimitsState>,
+    amount: f64,
+    from: String,
+    to: String,
+    marketplace: String,
+) -> Result<RoundedPrice, String> {
+    convert_and_round(&state.settings.lock().unwrap(), &limits, amount, &from, &to, &marketplace)
+}
+
+#[derive(Deserialize)]
+pub struct DiscountItem {
+    id: String,
+    price: f64,
+    currency: String,
+}
+
+#[derive(Serialize)]
+pub struct DiscountedItem {
+    id: String,
+    old_price: f64,
+    new_price: f64,
+    currency: String,
+    /// What the discount came to before rounding.
+    raw_price: f64,
+}
+
+fn discount(
+    settings: &PriceRuleSettings,
+    limits: &LimitsState,
+    items: Vec<DiscountItem>,
+    percent: f64,
+    marketplace: &str,
+) -> Result<Vec<DiscountedItem>, String> {
+    if !percent.is_finite() || !(0.0..100.0).contains(&percent) {
+        return Err("Discount must be between 0 and 100 percent".to_string());
+    }
+    items
+        .into_iter()
+        .map(|item| {
+            let raw = item.price * (100.0 - percent) / 100.0;
+            let rounded = convert_and_round(settings, limits, raw, &item.currency, &item.currency, marketplace)?;
+            Ok(DiscountedItem {
+                id: item.id,
+                old_price: item.price,
+                new_price: rounded.price,
+                currency: rounded.currency,
+                raw_price: rounded.converted,
+            })
+        })
+        .collect()
+}
+
+/// Take `percent` off each item's price and round the result under
+/// `marketplace`'s strategy. Nothing is written; the caller saves the prices.
+#[tauri::command]
+pub fn apply_bulk_discount(
+    state: tauri::State<'_, PriceRuleState>,
+    limits: tauri::State<'_, LimitsState>,
+    items: Vec<DiscountItem>,
+    percent: f64,
+    marketplace: String,
+) -> Result<Vec<DiscountedItem>, String> {
+    discount(&state.settings.lock().unwrap(), &limits, items, percent, &marketplace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use RoundingStrategy::*;
+
+    const STRATEGIES: [RoundingStrategy; 6] =
+        [Exact, Whole, Charm99, Charm95, Ending { cents: 97 }, Increment { step: 0.5 }];
+
+    #[test]
+    fn two_decimal_endings_pick_the_nearest_candidate() {
+        let cases: [(f64, [f64; 6]); 6] = [
+            (57.23, [57.23, 57.0, 56.99, 56.95, 56.97, 57.5]),
+            (35.9999, [36.0, 36.0, 35.99, 35.95, 35.97, 36.0]),
+            // Ties go up.
+            (57.50, [57.5, 58.0, 57.99, 57.95, 57.97, 57.5]),
+            (20.10, [20.1, 20.0, 19.99, 19.95, 19.97, 20.5]),
+            // Under £1 nothing rounds down to zero.
+            (0.40, [0.4, 1.0, 0.99, 0.95, 0.97, 0.5]),
+            (0.01, [0.01, 1.0, 0.99, 0.95, 0.97, 0.5]),
+        ];
+        for (amount, expected) in cases {
+            for (strategy, want) in STRATEGIES.into_iter().zip(expected) {
+                assert_eq!(round_price(amount, "GBP", strategy), want, "{amount} under {strategy:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn zero_decimal_endings_work_on_the_last_two_digits() {
+        let cases: [(f64, [f64; 6]); 3] = [
+            (5723.4, [5723.0, 5700.0, 5699.0, 5695.0, 5697.0, 5723.0]),
+            (5780.0, [5780.0, 5800.0, 5799.0, 5795.0, 5797.0, 5780.0]),
+            (40.0, [40.0, 100.0, 99.0, 95.0, 97.0, 40.0]),
+        ];
+        for (amount, expected) in cases {
+            for (strategy, want) in STRATEGIES.into_iter().zip(expected) {
+                assert_eq!(round_price(amount, "JPY", strategy), want, "¥{amount} under {strategy:?}");
+            }
+        }
+        assert_eq!(round_price(5701.0, "JPY", Increment { step: 100.0 }), 5800.0);
+        assert_eq!(round_price(12.01, "USD", Increment { step: 5.0 }), 15.0);
+    }
+
+    #[test]
+    fn zero_stays_zero() {
+        for strategy in STRATEGIES {
+            assert_eq!(round_price(0.0, "GBP", strategy), 0.0);
+            assert_eq!(round_price(0.0, "JPY", strategy), 0.0);
+        }
+    }
+
+    fn settings(strategy: RoundingStrategy) -> PriceRuleSettings {
+        PriceRuleSettings {
+            default_strategy: strategy,
+            rates: BTreeMap::from([("GBP".to_string(), 0.8), ("JPY".to_string(), 150.0)]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn converts_then_rounds_then_clamps() {
+        let limits = LimitsState::default();
+        let out = convert_and_round(&settings(Charm99), &limits, 10.0, "usd", "gbp", "depop").unwrap();
+        assert_eq!((out.converted, out.price, out.currency.as_str()), (8.0, 7.99, "GBP"));
+
+        let out = convert_and_round(&settings(Exact), &limits, 1.0, "GBP", "JPY", "depop").unwrap();
+        assert_eq!((out.converted, out.price), (188.0, 188.0));
+
+        // Poshmark's minimum is $3.
+        let out = convert_and_round(&settings(Charm99), &limits, 0.5, "USD", "USD", "poshmark").unwrap();
+        assert!(out.clamped_to_minimum);
+        assert_eq!(out.price, 3.0);
+        let out = convert_and_round(&settings(Exact), &limits, 0.0, "USD", "USD", "poshmark").unwrap();
+        assert!(!out.clamped_to_minimum);
+        assert_eq!(out.price, 0.0);
+
+        let err = convert_and_round(&settings(Exact), &limits, 1.0, "EUR", "GBP", "depop").unwrap_err();
+        assert!(err.contains("EUR"), "{err}");
+        assert!(convert_and_round(&settings(Exact), &limits, -1.0, "USD", "USD", "depop").is_err());
+        assert!(convert_and_round(&settings(Exact), &limits, f64::NAN, "USD", "USD", "depop").is_err());
+    }
+
+    #[test]
+    fn bulk_discounts_round_each_item() {
+        let limits = LimitsState::default();
+        let items = || {
+            vec![
+                DiscountItem { id: "coat".to_string(), price: 45.0, currency: "GBP".to_string() },
+                DiscountItem { id: "tee".to_string(), price: 5000.0, currency: "JPY".to_string() },
+                DiscountItem { id: "sock".to_string(), price: 0.8, currency: "GBP".to_string() },
+            ]
+        };
+        let out = discount(&settings(Charm99), &limits, items(), 20.0, "depop").unwrap();
+        let prices: Vec<(f64, f64)> = out.iter().map(|d| (d.raw_price, d.new_price)).collect();
+        // The 64p sock is raised to Depop's £1 minimum.
+        assert_eq!(prices, [(36.0, 35.99), (4000.0, 3999.0), (0.64, 1.0)]);
+
+        let out = discount(&settings(Exact), &limits, items(), 0.0, "depop").unwrap();
+        let prices: Vec<f64> = out.iter().map(|d| d.new_price).collect();
+        assert_eq!(prices, [45.0, 5000.0, 1.0]);
+        for percent in [100.0, -1.0, f64::NAN] {
+            assert!(discount(&settings(Exact), &limits, items(), percent, "depop").is_err());
+        }
+    }
+}
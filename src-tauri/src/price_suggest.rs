@@ -0,0 +1,380 @@
+// ── Price suggestions from the shop's own sales ────────────────────────────
+// Marketplace comps are noisy; what this shop has sold similar things for is
+// usually a better guide. Given the synced `sales` rows (passed in, like the
+// repricing simulator) and an item's traits, the suggestion is the weighted
+// median price of the most similar past sales:
+//   - similarity scores category and brand matches highest, then size and
+//     condition, over the traits the item actually has;
+//   - each match is weighted by similarity and by recency, halving every
+//     HALF_LIFE_DAYS before the newest sale in the set (not today, so the
+//     same sales always give the same answer);
+//   - with fewer than MIN_MATCHES similar sales it falls back to the
+//     category's recency-weighted median, then to the comps median if the
+//     caller passed one.
+// Pure computation: no network, deterministic for the same input. The
+// contributing sales come back with their weights so the editor can show
+// why.
+
+use crate::fees::round_cents;
+use serde::{Deserialize, Serialize};
+
+const HALF_LIFE_DAYS: f64 = 180.0;
+/// Similar sales used at most.
+const MAX_MATCHES: usize = 15;
+const MIN_MATCHES: usize = 3;
+/// Share of the item's traits a past sale must match to count as similar.
+const MIN_SIMILARITY: f64 = 0.5;
+
+const CATEGORY_WEIGHT: f64 = 3.0;
+const BRAND_WEIGHT: f64 = 3.0;
+const SIZE_WEIGHT: f64 = 1.5;
+const CONDITION_WEIGHT: f64 = 1.5;
+
+#[derive(Deserialize, Clone)]
+pub struct PastSale {
+    id: String,
+    sale_price: f64,
+    /// ISO-8601 timestamp (`sales.sold_at`).
+    sold_at: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    brand: Option<String>,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    size: Option<String>,
+    #[serde(default)]
+    condition: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ItemTraits {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    brand: Option<String>,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    size: Option<String>,
+    #[serde(default)]
+    condition: Option<String>,
+    /// Current asking price, for `check_inventory_prices`.
+    #[serde(default)]
+    price: Option<f64>,
+}
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Confidence {
+    High,
+    Medium,
+    Low,
+}
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Basis {
+    SimilarSales,
+    CategoryMedian,
+    Comps,
+}
+
+#[derive(Serialize)]
+pub struct Contributor {
+    id: String,
+    title: Option<String>,
+    sale_price: f64,
+    sold_at: String,
+    similarity: f64,
+    /// Similarity times recency, as used in the median.
+    weight: f64,
+}
+
+#[derive(Serialize)]
+pub struct PriceSuggestion {
+    /// None when there's nothing to go on.
+    price: Option<f64>,
+    confidence: Confidence,
+    basis: Option<Basis>,
+    /// Middle half of the contributing prices (weighted quartiles).
+    low: Option<f64>,
+    high: Option<f64>,
+    contributors: Vec<Contributor>,
+}
+
+fn norm(value: &Option<String>) -> Option<String> {
+    value.as_deref().map(|v| v.trim().to_lowercase()).filter(|v| !v.is_empty())
+}
+
+/// Share of the item's known traits that `sale` matches, by weight.
+fn similarity(item: &ItemTraits, sale: &PastSale) -> f64 {
+    let traits = [
+        (&item.category, &sale.category, CATEGORY_WEIGHT),
+        (&item.brand, &sale.brand, BRAND_WEIGHT),
+        (&item.size, &sale.size, SIZE_WEIGHT),
+        (&item.condition, &sale.condition, CONDITION_WEIGHT),
+    ];
+    let (mut matched, mut possible) = (0.0, 0.0);
+    for (ours, theirs, weight) in traits {
+        let Some(ours) = norm(ours) else {
+            continue;
+        };
+        possible += weight;
+        if norm(theirs).as_deref() == Some(ours.as_str()) {
+            matched += weight;
+        }
+    }
+    if possible == 0.0 {
+        0.0
+    } else {
+        matched / possible
+    }
+}
+
+fn recency(sold_day: Option<i64>, newest_day: i64) -> f64 {
+    let age = sold_day.map_or(HALF_LIFE_DAYS * 2.0, |d| (newest_day - d).max(0) as f64);
+    0.5f64.powf(age / HALF_LIFE_DAYS)
+}
+
+/// Price at `quantile` of the total weight; `prices` sorted by price.
+fn weighted_quantile(prices: &[(f64, f64)], quantile: f64) -> f64 {
+    let total: f64 = prices.iter().map(|(_, w)| w).sum();
+    let mut seen = 0.0;
+    for (price, weight) in prices {
+        seen += weight;
+        if seen >= total * quantile {
+            return *price;
+        }
+    }
+    prices.last().map_or(0.0, |(p, _)| *p)
+}
+
+fn confidence(matches: usize, low: f64, mid: f64, high: f64) -> Confidence {
+    let spread = if mid > 0.0 { (high - low) / mid } else { f64::INFINITY };
+    if matches >= 8 && spread <= 0.5 {
+        Confidence::High
+    } else if matches >= MIN_MATCHES && spread <= 1.0 {
+        Confidence::Medium
+    } else {
+        Confidence::Low
+    }
+}
+
+fn suggest(item: &ItemTraits, sales: &[PastSale], comps_median: Option<f64>) -> PriceSuggestion {
+    let sales: Vec<&PastSale> = sales.iter().filter(|s| s.sale_price > 0.0).collect();
+    let days: Vec<Option<i64>> = sales.iter().map(|s| crate::payouts::day_number(&s.sold_at)).collect();
+    let newest_day = days.iter().flatten().max().copied().unwrap_or(0);
+    let scored = |min_similarity: f64, score: &dyn Fn(&PastSale) -> f64| {
+        let mut out: Vec<Contributor> = sales
+            .iter()
+            .zip(&days)
+            .filter_map(|(sale, day)| {
+                let similarity = score(sale);
+                (similarity >= min_similarity).then(|| Contributor {
+                    id: sale.id.clone(),
+                    title: sale.title.clone(),
+                    sale_price: sale.sale_price,
+                    sold_at: sale.sold_at.clone(),
+                    similarity: round_cents(similarity),
+                    weight: similarity * recency(*day, newest_day),
+                })
+            })
+            .collect();
+        // Heaviest first; ties by id so the cut is stable.
+        out.sort_by(|a, b| b.weight.total_cmp(&a.weight).then_with(|| a.id.cmp(&b.id)));
+        out.truncate(MAX_MATCHES);
+        out
+    };
+
+    let mut contributors = scored(MIN_SIMILARITY, &|sale| similarity(item, sale));
+    let mut basis = Basis::SimilarSales;
+    if contributors.len() < MIN_MATCHES {
+        if let Some(category) = norm(&item.category) {
+            let in_category = scored(1.0, &|sale| if norm(&sale.category) == Some(category.clone()) { 1.0 } else { 0.0 });
+            if in_category.len() >= contributors.len().max(1) {
+                contributors = in_category;
+                basis = Basis::CategoryMedian;
+            }
+        }
+    }
+    if contributors.is_empty() {
+        let price = comps_median.filter(|p| *p > 0.0).map(round_cents);
+        return PriceSuggestion {
+            price,
+            confidence: Confidence::Low,
+            basis: price.map(|_| Basis::Comps),
+            low: None,
+            high: None,
+            contributors,
+        };
+    }
+
+    let mut prices: Vec<(f64, f64)> = contributors.iter().map(|c| (c.sale_price, c.weight)).collect();
+    prices.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let (low, mid, high) =
+        (weighted_quantile(&prices, 0.25), weighted_quantile(&prices, 0.5), weighted_quantile(&prices, 0.75));
+    let mut confidence = confidence(contributors.len(), low, mid, high);
+    if basis == Basis::CategoryMedian {
+        confidence = Confidence::Low;
+    }
+    for c in &mut contributors {
+        c.weight = (c.weight * 1000.0).round() / 1000.0;
+    }
+    PriceSuggestion {
+        price: Some(round_cents(mid)),
+        confidence,
+        basis: Some(basis),
+        low: Some(round_cents(low)),
+        high: Some(round_cents(high)),
+        contributors,
+    }
+}
+
+/// Suggested price for one item from `sales` (the synced `sales` rows with
+/// the sold item's brand/category/size/condition), for the draft editor.
+/// `comps_median` is used only when no past sale is similar enough.
+#[tauri::command]
+pub fn suggest_price(item: ItemTraits, sales: Vec<PastSale>, comps_median: Option<f64>) -> PriceSuggestion {
+    suggest(&item, &sales, comps_median)
+}
+
+#[derive(Serialize)]
+pub struct PriceCheck {
+    id: Option<String>,
+    price: Option<f64>,
+    suggested: Option<f64>,
+    confidence: Confidence,
+    /// Asking price vs suggestion, in percent (+25 = 25% above).
+    difference_percent: Option<f64>,
+}
+
+/// Suggestions for a page of inventory, to spot items priced well off what
+/// similar ones sold for. Contributors are left out to keep it small.
+#[tauri::command]
+pub fn check_inventory_prices(items: Vec<ItemTraits>, sales: Vec<PastSale>) -> Vec<PriceCheck> {
+    items
+        .iter()
+        .map(|item| {
+            let suggestion = suggest(item, &sales, None);
+            let difference_percent = match (item.price, suggestion.price) {
+                (Some(price), Some(suggested)) if suggested > 0.0 => {
+                    Some(round_cents((price - suggested) / suggested * 100.0))
+                }
+                _ => None,
+            };
+            PriceCheck {
+                id: item.id.clone(),
+                price: item.price,
+                suggested: suggestion.price,
+                confidence: suggestion.confidence,
+                difference_percent,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sales(rows: serde_json::Value) -> Vec<PastSale> {
+        serde_json::from_value(rows).unwrap()
+    }
+
+    fn item(traits: serde_json::Value) -> ItemTraits {
+        serde_json::from_value(traits).unwrap()
+    }
+
+    /// A shop's jacket sales, one of them a £400 rarity.
+    fn jacket_history() -> Vec<PastSale> {
+        sales(json!([
+            { "id": "s1", "sale_price": 40.0, "sold_at": "2026-09-01T10:00:00Z", "title": "Carhartt chore coat",
+              "brand": "Carhartt", "category": "Jackets", "size": "M", "condition": "Good" },
+            { "id": "s2", "sale_price": 45.0, "sold_at": "2026-09-01T12:00:00Z",
+              "brand": "carhartt ", "category": "jackets", "size": "L", "condition": "good" },
+            // 180 days before the newest sale: half weight.
+            { "id": "s3", "sale_price": 35.0, "sold_at": "2026-03-05T09:00:00Z",
+              "brand": "Carhartt", "category": "Jackets", "size": "M", "condition": "Worn" },
+            { "id": "s4", "sale_price": 400.0, "sold_at": "2026-08-20T09:00:00Z", "title": "1970s Detroit jacket",
+              "brand": "Carhartt", "category": "Jackets", "size": "M", "condition": "Good" },
+            { "id": "s5", "sale_price": 25.0, "sold_at": "2026-09-01T15:00:00Z",
+              "brand": "Nike", "category": "Jackets", "size": "M", "condition": "Good" },
+            { "id": "s6", "sale_price": 30.0, "sold_at": "2026-09-01T15:00:00Z",
+              "brand": "Levi's", "category": "Jeans", "size": "32", "condition": "Good" },
+            { "id": "refund", "sale_price": 0.0, "sold_at": "2026-09-01T15:00:00Z",
+              "brand": "Carhartt", "category": "Jackets", "size": "M", "condition": "Good" },
+        ]))
+    }
+
+    fn carhartt_jacket() -> ItemTraits {
+        item(json!({ "brand": "Carhartt", "category": "Jackets", "size": "M", "condition": "good" }))
+    }
+
+    #[test]
+    fn an_outlier_does_not_move_the_weighted_median() {
+        let suggestion = suggest(&carhartt_jacket(), &jacket_history(), Some(90.0));
+        assert_eq!(suggestion.basis, Some(Basis::SimilarSales));
+        assert_eq!((suggestion.low, suggestion.price, suggestion.high), (Some(35.0), Some(40.0), Some(45.0)));
+        assert_eq!(suggestion.confidence, Confidence::Medium);
+        let contributors: Vec<_> =
+            suggestion.contributors.iter().map(|c| (c.id.as_str(), c.similarity, c.weight)).collect();
+        assert_eq!(
+            contributors,
+            vec![("s1", 1.0, 1.0), ("s4", 1.0, 0.955), ("s2", 0.83, 0.833), ("s5", 0.67, 0.667), ("s3", 0.83, 0.417)]
+        );
+    }
+
+    #[test]
+    fn the_same_sales_in_any_order_give_the_same_suggestion() {
+        let mut reversed = jacket_history();
+        reversed.reverse();
+        let (a, b) =
+            (suggest(&carhartt_jacket(), &jacket_history(), None), suggest(&carhartt_jacket(), &reversed, None));
+        assert_eq!((a.price, a.low, a.high), (b.price, b.low, b.high));
+        let ids = |s: &PriceSuggestion| s.contributors.iter().map(|c| c.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&a), ids(&b));
+    }
+
+    #[test]
+    fn sparse_matches_fall_back_to_the_category_median() {
+        let history = sales(json!([
+            { "id": "d1", "sale_price": 20.0, "sold_at": "2026-07-01", "brand": "Zara", "category": "Dresses" },
+            { "id": "d2", "sale_price": 60.0, "sold_at": "2026-07-01", "brand": "Ganni", "category": "Dresses" },
+            { "id": "d3", "sale_price": 28.0, "sold_at": "2026-07-01", "brand": "H&M", "category": "Dresses" },
+            { "id": "j1", "sale_price": 90.0, "sold_at": "2026-07-01", "brand": "Reformation", "category": "Jeans" },
+        ]));
+        // Category alone is 3 of 7.5 trait weight: below MIN_SIMILARITY.
+        let dress = item(json!({ "brand": "Reformation", "category": "Dresses", "size": "8" }));
+        let suggestion = suggest(&dress, &history, None);
+        assert_eq!(suggestion.basis, Some(Basis::CategoryMedian));
+        assert_eq!((suggestion.low, suggestion.price, suggestion.high), (Some(20.0), Some(28.0), Some(60.0)));
+        assert_eq!(suggestion.confidence, Confidence::Low);
+        assert_eq!(suggestion.contributors.len(), 3);
+    }
+
+    #[test]
+    fn an_empty_history_uses_the_comps_median_or_nothing() {
+        let with_comps = suggest(&carhartt_jacket(), &[], Some(31.456));
+        assert_eq!((with_comps.price, with_comps.basis), (Some(31.46), Some(Basis::Comps)));
+        assert_eq!(with_comps.confidence, Confidence::Low);
+        assert!(with_comps.contributors.is_empty());
+
+        let without = suggest(&carhartt_jacket(), &[], None);
+        assert_eq!((without.price, without.basis, without.low, without.high), (None, None, None, None));
+        assert_eq!(without.confidence, Confidence::Low);
+    }
+
+    #[test]
+    fn inventory_checks_report_the_gap_to_the_suggestion() {
+        let items = vec![
+            item(json!({ "id": "a", "brand": "Carhartt", "category": "Jackets", "size": "M", "price": 50.0 })),
+            item(json!({ "id": "b", "category": "Shoes", "price": 10.0 })),
+        ];
+        let checks = check_inventory_prices(items, jacket_history());
+        let summary: Vec<_> = checks.iter().map(|c| (c.id.as_deref(), c.suggested, c.difference_percent)).collect();
+        assert_eq!(summary, vec![(Some("a"), Some(40.0), Some(25.0)), (Some("b"), None, None)]);
+    }
+}
@@ -0,0 +1,578 @@
+// ── Seasonal pricing calendar ──────────────────────────────────────────────
+// A pricing rule adjusts the prices of a set of listings on its start date
+// (coats +15% in October) and, with `revert`, puts them back on its end date.
+// The listings are the active ones matching the rule's filter when it is
+// created; they're fixed then so overlapping rules can be caught up front.
+// Two rules overlap when their dates intersect and they share a listing.
+// Overlaps are rejected unless the new rule gives a `priority` none of the
+// rules it overlaps uses; on the same day lower priorities apply first.
+//
+// Adjusted prices go through the marketplace rounding rules (price_rules.rs),
+// as with bulk discounts. Each listing's price before and after is recorded,
+// and the revert only touches listings still at the price the rule set:
+// anything repriced by hand mid-season keeps its price and is listed as
+// excluded in the rule's report. Stacked rules therefore revert cleanly only
+// when the later one ends first.
+//
+// Prices live in Supabase. The scheduler reuses the last session the
+// frontend passed; sessions are kept in memory only, so after a restart a
+// due rule waits (and emits `pricing-rules-due`) until the frontend calls
+// `run_due_pricing_rules` or passes a session some other way.
+
+use crate::marketplace_limits::LimitsState;
+use crate::price_rules::PriceRuleState;
+use crate::repricing::PriceAdjustment;
+use crate::supabase::{self, SupabaseSession};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+const STORE_NAME: &str = "pricing_calendar";
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Prices closer than this count as unchanged.
+const PRICE_EPSILON: f64 = 0.005;
+const LISTINGS_QUERY: &str = "listings?select=id,title,price&order=id";
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleFilter {
+    pub category: Option<String>,
+    pub brand: Option<String>,
+    pub title_contains: Option<String>,
+    /// Limit the rule to these listings.
+    pub listing_ids: Option<Vec<String>>,
+}
+
+impl RuleFilter {
+    /// PostgREST conditions for the filter, each starting with `&`.
+    fn conditions(&self) -> String {
+        let mut query = String::new();
+        let enc = crate::depop::sync::urlencode;
+        if let Some(category) = self.category.as_deref().filter(|c| !c.is_empty()) {
+            query.push_str(&format!("&category=eq.{}", enc(category)));
+        }
+        if let Some(brand) = self.brand.as_deref().filter(|b| !b.is_empty()) {
+            query.push_str(&format!("&brand=eq.{}", enc(brand)));
+        }
+        if let Some(text) = self.title_contains.as_deref().filter(|t| !t.is_empty()) {
+            query.push_str(&format!("&title=ilike.{}", enc(&format!("*{text}*"))));
+        }
+        if let Some(ids) = &self.listing_ids {
+            query.push_str(&format!("&id=in.({})", enc(&ids.join(","))));
+        }
+        query
+    }
+
+    fn is_empty(&self) -> bool {
+        self.category.as_deref().map_or(true, str::is_empty)
+            && self.brand.as_deref().map_or(true, str::is_empty)
+            && self.title_contains.as_deref().map_or(true, str::is_empty)
+            && self.listing_ids.is_none()
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleStatus {
+    Scheduled,
+    /// Applied; waiting for the end date.
+    Active,
+    Completed,
+}
+
+/// A listing's price around the rule being applied.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RecordedPrice {
+    pub original: f64,
+    pub applied: f64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PriceChange {
+    pub id: String,
+    pub title: Option<String>,
+    pub from: f64,
+    pub to: f64,
+}
+
+/// A listing left alone by the revert because its price moved since the
+/// rule applied.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Excluded {
+    pub id: String,
+    pub title: Option<String>,
+    /// The price the rule set.
+    pub expected: f64,
+    pub found: Option<f64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ItemError {
+    pub id: String,
+    pub error: String,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleReport {
+    pub applied_at: Option<u64>,
+    pub applied: Vec<PriceChange>,
+    pub completed_at: Option<u64>,
+    pub reverted: Vec<PriceChange>,
+    pub excluded: Vec<Excluded>,
+    pub errors: Vec<ItemError>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PricingRule {
+    pub id: String,
+    pub name: Option<String>,
+    pub filter: RuleFilter,
+    pub adjustment: PriceAdjustment,
+    /// `YYYY-MM-DD`, UTC, inclusive.
+    pub start_date: String,
+    pub end_date: String,
+    pub revert: bool,
+    pub priority: Option<i32>,
+    /// Rounding follows this marketplace's strategy (default strategy if unset).
+    pub marketplace: Option<String>,
+    pub currency: String,
+    /// Listings the rule covers, fixed at creation.
+    pub items: Vec<String>,
+    pub status: RuleStatus,
+    #[serde(default)]
+    pub originals: BTreeMap<String, RecordedPrice>,
+    #[serde(default)]
+    pub report: RuleReport,
+    pub created_at: u64,
+}
+
+impl PricingRule {
+    fn overlaps(&self, other: &PricingRule) -> bool {
+        other.status != RuleStatus::Completed
+            && self.start_date <= other.end_date
+            && other.start_date <= self.end_date
+            && self.items.iter().any(|id| other.items.contains(id))
+    }
+
+    /// The step due today, if any.
+    fn due(&self, today: &str) -> Option<Step> {
+        match self.status {
+            RuleStatus::Scheduled if self.start_date.as_str() <= today => Some(Step::Apply),
+            RuleStatus::Active if self.end_date.as_str() < today => Some(Step::Complete),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    Apply,
+    /// End of the rule: revert (when set) and close the report.
+    Complete,
+}
+
+pub struct PricingCalendarState {
+    rules: Mutex<BTreeMap<String, PricingRule>>,
+    session: Mutex<Option<SupabaseSession>>,
+    /// Rules already announced as due without a session.
+    announced: Mutex<BTreeSet<String>>,
+}
+
+impl PricingCalendarState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        PricingCalendarState {
+            rules: Mutex::new(crate::store::load(app, STORE_NAME)),
+            session: Mutex::new(None),
+            announced: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    fn remember(&self, session: &SupabaseSession) {
+        *self.session.lock().unwrap() = Some(session.clone());
+    }
+
+    fn save(&self, app: &tauri::AppHandle) -> Result<(), String> {
+        crate::store::save(app, STORE_NAME, &*self.rules.lock().unwrap())
+    }
+}
+
+#[derive(Deserialize)]
+struct Listing {
+    id: String,
+    title: Option<String>,
+    price: Option<f64>,
+}
+
+async fn fetch_listings(client: &reqwest::Client, session: &SupabaseSession, query: &str) -> Result<Vec<Listing>, String> {
+    let rows = supabase::send(session.rest(client, Method::GET, query)).await?;
+    serde_json::from_value(rows).map_err(|e| format!("invalid listings: {e}"))
+}
+
+async fn current_prices(
+    client: &reqwest::Client,
+    session: &SupabaseSession,
+    ids: &[String],
+) -> Result<BTreeMap<String, Listing>, String> {
+    let mut out = BTreeMap::new();
+    // Keep the id list well inside URL length limits.
+    for chunk in ids.chunks(100) {
+        let filter = RuleFilter { listing_ids: Some(chunk.to_vec()), ..RuleFilter::default() };
+        let query = format!("{LISTINGS_QUERY}{}", filter.conditions());
+        out.extend(fetch_listings(client, session, &query).await?.into_iter().map(|l| (l.id.clone(), l)));
+    }
+    Ok(out)
+}
+
+fn same_price(a: f64, b: f64) -> bool {
+    (a - b).abs() < PRICE_EPSILON
+}
+
+/// What a step would do, without writing anything.
+#[derive(Serialize)]
+pub struct StepPlan {
+    rule_id: String,
+    step: Step,
+    changes: Vec<PriceChange>,
+    excluded: Vec<Excluded>,
+    errors: Vec<ItemError>,
+}
+
+async fn plan(app: &tauri::AppHandle, session: &SupabaseSession, rule: &PricingRule, step: Step) -> Result<StepPlan, String> {
+    let client = supabase::client()?;
+    let listings = current_prices(&client, session, &rule.items).await?;
+    let mut out = StepPlan { rule_id: rule.id.clone(), step, changes: Vec::new(), excluded: Vec::new(), errors: Vec::new() };
+    match step {
+        Step::Apply => {
+            let settings = app.state::<PriceRuleState>().settings();
+            let limits = app.state::<LimitsState>();
+            let marketplace = rule.marketplace.as_deref().unwrap_or_default();
+            for id in &rule.items {
+                let Some(price) = listings.get(id).and_then(|l| l.price) else {
+                    out.errors.push(ItemError { id: id.clone(), error: "Listing has no price or was deleted".to_string() });
+                    continue;
+                };
+                let raw = rule.adjustment.apply(price);
+                match crate::price_rules::convert_and_round(&settings, &limits, raw, &rule.currency, &rule.currency, marketplace) {
+                    Ok(rounded) => out.changes.push(PriceChange {
+                        id: id.clone(),
+                        title: listings[id].title.clone(),
+                        from: price,
+                        to: rounded.price,
+                    }),
+                    Err(e) => out.errors.push(ItemError { id: id.clone(), error: e }),
+                }
+            }
+        }
+        Step::Complete if !rule.revert => {}
+        Step::Complete => {
+            for (id, recorded) in &rule.originals {
+                let listing = listings.get(id);
+                let found = listing.and_then(|l| l.price);
+                let title = listing.and_then(|l| l.title.clone());
+                match found {
+                    Some(price) if same_price(price, recorded.applied) => {
+                        out.changes.push(PriceChange { id: id.clone(), title, from: price, to: recorded.original });
+                    }
+                    _ => out.excluded.push(Excluded { id: id.clone(), title, expected: recorded.applied, found }),
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+async fn write_price(client: &reqwest::Client, session: &SupabaseSession, id: &str, price: f64) -> Result<(), String> {
+    let path = format!("listings?id=eq.{}", crate::depop::sync::urlencode(id));
+    supabase::send(session.rest(client, Method::PATCH, &path).json(&json!({ "price": price }))).await.map(|_| ())
+}
+
+/// Run one step of `rule_id` and record the outcome on the rule.
+async fn run_step(app: &tauri::AppHandle, session: &SupabaseSession, rule_id: &str, step: Step) -> Result<PricingRule, String> {
+    let state = app.state::<PricingCalendarState>();
+    let rule = state.rules.lock().unwrap().get(rule_id).cloned().ok_or("Pricing rule not found")?;
+    let plan = plan(app, session, &rule, step).await?;
+    let client = supabase::client()?;
+    let mut written = Vec::with_capacity(plan.changes.len());
+    let mut errors = plan.errors;
+    for change in plan.changes {
+        match write_price(&client, session, &change.id, change.to).await {
+            Ok(()) => written.push(change),
+            Err(e) => errors.push(ItemError { id: change.id, error: e }),
+        }
+    }
+
+    let now = crate::unix_now();
+    let updated = {
+        let mut rules = state.rules.lock().unwrap();
+        let rule = rules.get_mut(rule_id).ok_or("Pricing rule not found")?;
+        match step {
+            Step::Apply => {
+                for change in &written {
+                    rule.originals.insert(change.id.clone(), RecordedPrice { original: change.from, applied: change.to });
+                }
+                rule.status = RuleStatus::Active;
+                rule.report.applied_at = Some(now);
+                rule.report.applied = written;
+            }
+            Step::Complete => {
+                rule.status = RuleStatus::Completed;
+                rule.report.completed_at = Some(now);
+                rule.report.reverted = written;
+                rule.report.excluded = plan.excluded;
+            }
+        }
+        rule.report.errors.extend(errors);
+        rule.clone()
+    };
+    state.save(app)?;
+    state.announced.lock().unwrap().remove(rule_id);
+
+    let report = &updated.report;
+    crate::audit::record(
+        app,
+        "pricing_rule",
+        None,
+        false,
+        json!({
+            "rule_id": updated.id,
+            "step": step,
+            "applied": report.applied.len(),
+            "reverted": report.reverted.len(),
+            "excluded": report.excluded.len(),
+            "errors": report.errors.len(),
+        }),
+    );
+    if step == Step::Complete {
+        let name = updated.name.as_deref().unwrap_or("Pricing rule");
+        let body = match (updated.revert, report.excluded.len()) {
+            (false, _) => "The season is over".to_string(),
+            (true, 0) => format!("{} prices reverted", report.reverted.len()),
+            (true, n) => format!("{} prices reverted, {n} changed by hand and left as they are", report.reverted.len()),
+        };
+        crate::notifications::show(app, &format!("{name} finished"), &body);
+        let _ = app.emit("pricing-rule-completed", &updated);
+    }
+    Ok(updated)
+}
+
+#[derive(Serialize)]
+pub struct StepOutcome {
+    rule_id: String,
+    step: Step,
+    rule: Option<PricingRule>,
+    error: Option<String>,
+}
+
+/// Run every step that is due, earliest start and lowest priority first.
+/// A failed step stays due and is retried on the next run.
+async fn run_due(app: &tauri::AppHandle, session: &SupabaseSession) -> Vec<StepOutcome> {
+    let today = crate::fees::today();
+    let mut due: Vec<(String, Option<i32>, String, Step)> = app
+        .state::<PricingCalendarState>()
+        .rules
+        .lock()
+        .unwrap()
+        .values()
+        .filter_map(|r| Some((r.start_date.clone(), r.priority, r.id.clone(), r.due(&today)?)))
+        .collect();
+    due.sort_by(|a, b| (&a.0, a.1.unwrap_or(0)).cmp(&(&b.0, b.1.unwrap_or(0))));
+    let mut out = Vec::with_capacity(due.len());
+    for (_, _, id, step) in due {
+        let (rule, error) = match run_step(app, session, &id, step).await {
+            Ok(rule) => (Some(rule), None),
+            Err(e) => {
+                log::warn!("pricing rule {id}: {e}");
+                (None, Some(e))
+            }
+        };
+        out.push(StepOutcome { rule_id: id, step, rule, error });
+    }
+    out
+}
+
+pub fn start_calendar_job(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            let state = app.state::<PricingCalendarState>();
+            let session = state.session.lock().unwrap().clone();
+            match session {
+                Some(session) => {
+                    run_due(&app, &session).await;
+                }
+                None => {
+                    let today = crate::fees::today();
+                    let due: Vec<String> = state
+                        .rules
+                        .lock()
+                        .unwrap()
+                        .values()
+                        .filter(|r| r.due(&today).is_some())
+                        .map(|r| r.id.clone())
+                        .collect();
+                    let mut announced = state.announced.lock().unwrap();
+                    if due.iter().any(|id| !announced.contains(id)) {
+                        let _ = app.emit("pricing-rules-due", &due);
+                        announced.extend(due.iter().cloned());
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_pricing_rules(state: tauri::State<'_, PricingCalendarState>) -> Vec<PricingRule> {
+    state.rules.lock().unwrap().values().cloned().collect()
+}
+
+/// Create a rule over the active listings matching `filter`. Fails when it
+/// overlaps a pending rule, unless `priority` orders it against all of them.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_pricing_rule(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PricingCalendarState>,
+    session: SupabaseSession,
+    filter: RuleFilter,
+    adjustment: PriceAdjustment,
+    start_date: String,
+    end_date: String,
+    revert: bool,
+    name: Option<String>,
+    priority: Option<i32>,
+    marketplace: Option<String>,
+    currency: Option<String>,
+) -> Result<PricingRule, String> {
+    state.remember(&session);
+    if !crate::custom_fields::valid_date(&start_date) || !crate::custom_fields::valid_date(&end_date) {
+        return Err("Dates must be YYYY-MM-DD".to_string());
+    }
+    if end_date < start_date {
+        return Err("The end date is before the start date".to_string());
+    }
+    if start_date < crate::fees::today() {
+        return Err("The start date has already passed".to_string());
+    }
+    let valid = match adjustment {
+        PriceAdjustment::Percent { percent } => percent.is_finite() && percent > -100.0,
+        PriceAdjustment::Fixed { amount } => amount.is_finite(),
+    };
+    if !valid {
+        return Err("Invalid price adjustment".to_string());
+    }
+    if filter.is_empty() {
+        return Err("Narrow the rule down with at least one filter".to_string());
+    }
+
+    let client = supabase::client()?;
+    let query = format!("{LISTINGS_QUERY}&status=eq.active{}", filter.conditions());
+    let items: Vec<String> = fetch_listings(&client, &session, &query).await?.into_iter().map(|l| l.id).collect();
+    if items.is_empty() {
+        return Err("No active listings match the filter".to_string());
+    }
+    let rule = PricingRule {
+        id: hex::encode(rand::random::<[u8; 8]>()),
+        name: name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()),
+        filter,
+        adjustment,
+        start_date,
+        end_date,
+        revert,
+        priority,
+        marketplace: marketplace.filter(|m| !m.is_empty()),
+        currency: currency.unwrap_or_else(|| "USD".to_string()).to_uppercase(),
+        items,
+        status: RuleStatus::Scheduled,
+        originals: BTreeMap::new(),
+        report: RuleReport::default(),
+        created_at: crate::unix_now(),
+    };
+
+    {
+        let mut rules = state.rules.lock().unwrap();
+        let overlapping: Vec<&PricingRule> = rules.values().filter(|r| rule.overlaps(r)).collect();
+        let ordered = priority.is_some() && overlapping.iter().all(|r| r.priority != priority);
+        if !overlapping.is_empty() && !ordered {
+            let names: Vec<String> = overlapping
+                .iter()
+                .map(|r| {
+                    let shared = rule.items.iter().filter(|id| r.items.contains(id)).count();
+                    format!("{} ({shared} listings)", r.name.as_deref().unwrap_or(&r.id))
+                })
+                .collect();
+            return Err(format!(
+                "Overlaps {}; give this rule a priority different from theirs to order them",
+                names.join(", ")
+            ));
+        }
+        rules.insert(rule.id.clone(), rule.clone());
+    }
+    state.save(&app)?;
+    crate::audit::record(
+        &app,
+        "create_pricing_rule",
+        None,
+        false,
+        json!({ "rule_id": rule.id, "items": rule.items.len(), "start": rule.start_date, "end": rule.end_date }),
+    );
+    Ok(rule)
+}
+
+/// Dry run of the rule's next step (applying it, or the end-date revert)
+/// against current prices.
+#[tauri::command]
+pub async fn preview_pricing_rule(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PricingCalendarState>,
+    session: SupabaseSession,
+    id: String,
+) -> Result<StepPlan, String> {
+    state.remember(&session);
+    let rule = state.rules.lock().unwrap().get(&id).cloned().ok_or("Pricing rule not found")?;
+    let step = match rule.status {
+        RuleStatus::Scheduled => Step::Apply,
+        RuleStatus::Active => Step::Complete,
+        RuleStatus::Completed => return Err("This rule has already finished".to_string()),
+    };
+    plan(&app, &session, &rule, step).await
+}
+
+/// Run the steps that are due now (the scheduler does this hourly once it
+/// has a session).
+#[tauri::command]
+pub async fn run_due_pricing_rules(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PricingCalendarState>,
+    session: SupabaseSession,
+) -> Result<Vec<StepOutcome>, String> {
+    state.remember(&session);
+    Ok(run_due(&app, &session).await)
+}
+
+/// Delete a rule that hasn't applied yet, or a finished one. An active rule
+/// holds the original prices for its revert and can't be deleted.
+#[tauri::command]
+pub fn delete_pricing_rule(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, PricingCalendarState>,
+    id: String,
+) -> Result<(), String> {
+    {
+        let mut rules = state.rules.lock().unwrap();
+        match rules.get(&id) {
+            None => return Err("Pricing rule not found".to_string()),
+            Some(rule) if rule.status == RuleStatus::Active => {
+                return Err("This rule is in season; wait for it to end".to_string());
+            }
+            Some(_) => rules.remove(&id),
+        };
+    }
+    state.save(&app)
+}
@@ -0,0 +1,249 @@
+// ── Privacy mode ───────────────────────────────────────────────────────────
+// For screen sharing: while it's on, command responses and events wrapped in
+// `Private` have their sensitive fields (cost, margin, profit, supplier,
+// consignor, buyer details and contact PII, matched by key name at any
+// depth) replaced with REDACTED as they're serialized. Commands opt in by
+// returning `Private<T>`; nothing else about them changes, and stores on disk
+// never go through it.
+//
+// Exports (PDFs, reconciliation CSVs, HAR files, team sync files) and the
+// local API refuse to run at all while it's on (`ensure_off`) rather than
+// write redacted files someone might keep.
+//
+// The flag is process-wide rather than managed state because serializers
+// have no app handle. It's persisted, so a restart mid-stream stays private.
+// SHORTCUT flips it from anywhere, even while the window isn't focused.
+
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::Emitter;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+
+const STORE: &str = "privacy_mode";
+const REDACTED: &str = "[private]";
+/// Keys containing any of these are redacted.
+const SENSITIVE: &[&str] = &["cost", "cogs", "margin", "profit", "supplier", "consignor", "buyer"];
+/// Contact details, wherever they appear.
+const PII: &[&str] = &["email", "phone", "address", "postcode", "postal_code", "zip"];
+/// Also redacted in `Private::people` responses, whose records are people
+/// (consignors) rather than items.
+const PERSON: &[&str] = &["name", "notes"];
+
+/// System-wide shortcut for `toggle_privacy_mode`.
+pub const SHORTCUT: &str = "CommandOrControl+Shift+H";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Default, Serialize, Deserialize)]
+struct PrivacySettings {
+    enabled: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct PrivacyModeChanged {
+    enabled: bool,
+}
+
+/// Restore the saved mode and bind SHORTCUT; called once at startup. Another
+/// app holding the shortcut only costs the shortcut, so that's logged.
+pub fn load(app: &tauri::AppHandle) {
+    let settings: PrivacySettings = crate::store::load(app, STORE);
+    ENABLED.store(settings.enabled, Ordering::Relaxed);
+    let bound = app.global_shortcut().on_shortcut(SHORTCUT, |app, _, event| {
+        if event.state == ShortcutState::Pressed {
+            if let Err(e) = set(app, !enabled()) {
+                log::warn!("privacy shortcut: {e}");
+            }
+        }
+    });
+    if let Err(e) = bound {
+        log::warn!("couldn't bind {SHORTCUT} for privacy mode: {e}");
+    }
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Refuse `what` (an export, the local API) while privacy mode is on.
+pub fn ensure_off(what: &str) -> Result<(), String> {
+    if enabled() {
+        return Err(format!("Privacy mode is on; turn it off to {what}"));
+    }
+    Ok(())
+}
+
+fn sensitive(key: &str, people: bool) -> bool {
+    let key = key.to_ascii_lowercase();
+    SENSITIVE.iter().chain(PII).any(|s| key.contains(s)) || (people && PERSON.iter().any(|s| key.contains(s)))
+}
+
+/// Replace every sensitive, non-null field in `value` with REDACTED.
+fn redact(value: &mut Value, people: bool) {
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                if sensitive(key, people) {
+                    if !v.is_null() {
+                        *v = Value::String(REDACTED.to_string());
+                    }
+                } else {
+                    redact(v, people);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| redact(v, people)),
+        _ => {}
+    }
+}
+
+/// A command response or event payload that's redacted while privacy mode
+/// is on; serialized as-is otherwise.
+#[derive(Clone)]
+pub struct Private<T> {
+    value: T,
+    people: bool,
+}
+
+impl<T> Private<T> {
+    pub fn new(value: T) -> Self {
+        Private { value, people: false }
+    }
+
+    /// Records about people, whose names are redacted too.
+    pub fn people(value: T) -> Self {
+        Private { value, people: true }
+    }
+}
+
+impl<T: Serialize> Serialize for Private<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !enabled() {
+            return self.value.serialize(serializer);
+        }
+        let mut value = serde_json::to_value(&self.value).map_err(serde::ser::Error::custom)?;
+        redact(&mut value, self.people);
+        value.serialize(serializer)
+    }
+}
+
+fn set(app: &tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    crate::store::save(app, STORE, &PrivacySettings { enabled })?;
+    ENABLED.store(enabled, Ordering::Relaxed);
+    log::info!("privacy mode {}", if enabled { "on" } else { "off" });
+    let _ = app.emit("privacy-mode-changed", PrivacyModeChanged { enabled });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_privacy_mode(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    set(&app, enabled)
+}
+
+/// Flip privacy mode, as SHORTCUT does. Returns the new state.
+#[tauri::command]
+pub fn toggle_privacy_mode(app: tauri::AppHandle) -> Result<bool, String> {
+    let enabled = !self::enabled();
+    set(&app, enabled)?;
+    Ok(enabled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+    use std::collections::{HashMap, HashSet};
+    use std::path::Path;
+
+    fn sources(dir: &Path, out: &mut String) {
+        for entry in std::fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                sources(&path, out);
+            } else if path.extension().is_some_and(|e| e == "rs") {
+                out.push_str(&std::fs::read_to_string(&path).unwrap());
+                out.push('\n');
+            }
+        }
+    }
+
+    /// Fields (name, type) of every braced struct in the crate, by name.
+    fn structs(src: &str) -> HashMap<String, Vec<(String, String)>> {
+        let def = Regex::new(r"(?s)\bstruct (\w+)(?:<[^>{]*>)?\s*\{(.*?)\n\}").unwrap();
+        let field = Regex::new(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?(\w+):\s*(.+?),?\s*(?://.*)?$").unwrap();
+        let mut out: HashMap<String, Vec<(String, String)>> = HashMap::new();
+        for c in def.captures_iter(src) {
+            let fields = field.captures_iter(&c[2]).map(|f| (f[1].to_string(), f[2].to_string()));
+            out.entry(c[1].to_string()).or_default().extend(fields);
+        }
+        out
+    }
+
+    /// Fields whose names match but that aren't the seller's business.
+    const NOT_SENSITIVE: &[&str] = &[
+        // Published carrier postage prices.
+        "RateBand.cost",
+    ];
+
+    struct Walk<'a> {
+        structs: &'a HashMap<String, Vec<(String, String)>>,
+        ident: Regex,
+        seen: HashSet<String>,
+        found: Vec<String>,
+    }
+
+    /// Record paths like `command -> Struct.field` to sensitive fields
+    /// reachable from `ty` without passing through `Private`.
+    fn exposed(walk: &mut Walk, ty: &str, path: &str) {
+        if ty.contains("Private<") {
+            return;
+        }
+        let names: Vec<String> = walk.ident.find_iter(ty).map(|m| m.as_str().to_string()).collect();
+        let structs = walk.structs;
+        for name in names {
+            let Some(fields) = structs.get(&name) else { continue };
+            if !walk.seen.insert(name.clone()) {
+                continue;
+            }
+            for (field, field_ty) in fields {
+                let here = format!("{path} -> {name}.{field}");
+                if NOT_SENSITIVE.contains(&format!("{name}.{field}").as_str()) {
+                    continue;
+                }
+                if sensitive(field, false) {
+                    walk.found.push(here);
+                } else {
+                    exposed(walk, field_ty, &here);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sensitive_command_responses_are_wrapped() {
+        let mut src = String::new();
+        sources(&Path::new(env!("CARGO_MANIFEST_DIR")).join("src"), &mut src);
+        let structs = structs(&src);
+        let command = Regex::new(r"(?s)#\[tauri::command\]\s*(?:#\[[^\]]*\]\s*)*pub (?:async )?fn (\w+)[^{]*?->\s*([^{]+?)\s*\{").unwrap();
+        let ident = Regex::new(r"\b[A-Z]\w*").unwrap();
+        let mut walk = Walk { structs: &structs, ident, seen: HashSet::new(), found: Vec::new() };
+        let mut commands = 0;
+        for c in command.captures_iter(&src) {
+            commands += 1;
+            walk.seen.clear();
+            exposed(&mut walk, &c[2], &c[1]);
+        }
+        assert!(commands > 100, "only found {commands} commands");
+        assert!(walk.found.is_empty(), "sensitive fields returned without Private:\n{}", walk.found.join("\n"));
+    }
+
+    #[test]
+    fn redaction_reaches_nested_fields() {
+        let mut value = serde_json::json!({ "items": [{ "name": "Coat", "cost": 4.5, "buyer_email": null }] });
+        redact(&mut value, false);
+        assert_eq!(value, serde_json::json!({ "items": [{ "name": "Coat", "cost": REDACTED, "buyer_email": null }] }));
+        redact(&mut value, true);
+        assert_eq!(value["items"][0]["name"], REDACTED);
+    }
+}
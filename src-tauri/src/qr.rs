@@ -0,0 +1,348 @@
+// ── Listing QR codes ───────────────────────────────────────────────────────
+// QR codes that send a buyer at a market stall to the online listing of the
+// exact item. An item's best URL is picked by a configurable marketplace
+// priority; cards optionally carry the title and price underneath, and
+// `generate_qr_sheet` lays cards out on A4/Letter pages as a PDF.
+//
+// Inventory lives in Supabase, so the frontend passes the item row (sku,
+// title, price, listing URLs by marketplace) along with the SKU.
+
+use crate::pdf_layout::{self, Cell, PaperSize, PdfDocument};
+use base64::Engine;
+use image::{GrayImage, Luma};
+use pdf_writer::Content;
+use qrcode::{Color, EcLevel, QrCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const STORE_NAME: &str = "qr_settings";
+/// Modules of blank border the QR spec asks for.
+const QUIET_ZONE: usize = 4;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct QrSettings {
+    /// Marketplaces in order of preference when an item is listed on several.
+    pub marketplace_priority: Vec<String>,
+}
+
+impl Default for QrSettings {
+    fn default() -> Self {
+        QrSettings {
+            marketplace_priority: vec!["depop".to_string(), "ebay".to_string(), "etsy".to_string()],
+        }
+    }
+}
+
+pub struct QrState {
+    settings: Mutex<QrSettings>,
+}
+
+impl QrState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        QrState { settings: Mutex::new(crate::store::load(app, STORE_NAME)) }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct QrItem {
+    sku: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    price: Option<f64>,
+    /// Live listing URL by marketplace id.
+    #[serde(default)]
+    listing_urls: HashMap<String, String>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QrFormat {
+    Png,
+    Svg,
+    Pdf,
+}
+
+#[derive(Serialize)]
+pub struct QrOutput {
+    /// The encoded URL (single QR only).
+    url: Option<String>,
+    marketplace: Option<String>,
+    mime: &'static str,
+    /// File contents, base64-encoded.
+    data: String,
+}
+
+/// What a single QR points at, resolved from the SKU or URL argument.
+struct Target {
+    url: String,
+    marketplace: Option<String>,
+    title: Option<String>,
+    price: Option<f64>,
+}
+
+fn best_url(item: &QrItem, priority: &[String]) -> Result<(String, String), String> {
+    let live: Vec<(&String, &String)> = item
+        .listing_urls
+        .iter()
+        .filter(|(_, url)| !url.trim().is_empty())
+        .collect();
+    if live.is_empty() {
+        return Err(format!(
+            "{} isn't listed on any marketplace yet — crosslist it first, then generate the QR",
+            item.sku
+        ));
+    }
+    let ranked = priority
+        .iter()
+        .find_map(|m| live.iter().find(|(k, _)| *k == m))
+        // Marketplaces missing from the priority list come last, alphabetically.
+        .or_else(|| live.iter().min_by_key(|(k, _)| k.as_str()))
+        .expect("live is non-empty");
+    Ok((ranked.0.clone(), ranked.1.clone()))
+}
+
+fn resolve_target(sku_or_url: &str, item: Option<&QrItem>, priority: &[String]) -> Result<Target, String> {
+    let s = sku_or_url.trim();
+    if s.starts_with("http://") || s.starts_with("https://") {
+        return Ok(Target {
+            url: s.to_string(),
+            marketplace: None,
+            title: item.and_then(|i| i.title.clone()),
+            price: item.and_then(|i| i.price),
+        });
+    }
+    let item = item
+        .filter(|i| i.sku == s)
+        .ok_or_else(|| format!("No inventory item passed for SKU {s}"))?;
+    let (marketplace, url) = best_url(item, priority)?;
+    Ok(Target { url, marketplace: Some(marketplace), title: item.title.clone(), price: item.price })
+}
+
+fn encode(url: &str) -> Result<QrCode, String> {
+    // Medium error correction survives a crumpled card without making dense codes.
+    QrCode::with_error_correction_level(url.as_bytes(), EcLevel::M).map_err(|e| format!("QR encode: {e}"))
+}
+
+/// Dark modules as (x, y) in module units, quiet zone excluded.
+fn dark_modules(code: &QrCode) -> Vec<(usize, usize)> {
+    let width = code.width();
+    let colors = code.to_colors();
+    (0..width * width)
+        .filter(|i| colors[*i] == Color::Dark)
+        .map(|i| (i % width, i / width))
+        .collect()
+}
+
+fn price_label(price: Option<f64>) -> Option<String> {
+    price.map(|p| format!("${p:.2}"))
+}
+
+fn render_png(code: &QrCode, size_px: u32) -> Result<Vec<u8>, String> {
+    let modules = code.width() + 2 * QUIET_ZONE;
+    let scale = (size_px as usize / modules).max(1);
+    let side = (modules * scale) as u32;
+    let mut img = GrayImage::from_pixel(side, side, Luma([255]));
+    for (mx, my) in dark_modules(code) {
+        let x0 = ((mx + QUIET_ZONE) * scale) as u32;
+        let y0 = ((my + QUIET_ZONE) * scale) as u32;
+        for y in y0..y0 + scale as u32 {
+            for x in x0..x0 + scale as u32 {
+                img.put_pixel(x, y, Luma([0]));
+            }
+        }
+    }
+    let mut buf = Vec::new();
+    image::DynamicImage::ImageLuma8(img)
+        .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+        .map_err(|e| format!("PNG encode: {e}"))?;
+    Ok(buf)
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn render_svg(code: &QrCode, size_px: u32, card: bool, target: &Target) -> String {
+    let modules = code.width() + 2 * QUIET_ZONE;
+    let unit = size_px as f32 / modules as f32;
+    let caption_h = if card { size_px as f32 * 0.28 } else { 0.0 };
+    let height = size_px as f32 + caption_h;
+
+    let mut svg = format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{size_px}" height="{height}" viewBox="0 0 {size_px} {height}"><rect width="100%" height="100%" fill="#fff"/><path fill="#000" d=""##
+    );
+    for (mx, my) in dark_modules(code) {
+        let x = (mx + QUIET_ZONE) as f32 * unit;
+        let y = (my + QUIET_ZONE) as f32 * unit;
+        svg.push_str(&format!("M{x:.2},{y:.2}h{unit:.2}v{unit:.2}h-{unit:.2}z"));
+    }
+    svg.push_str(r#""/>"#);
+
+    if card {
+        let cx = size_px as f32 / 2.0;
+        let font = (size_px as f32 * 0.07).max(8.0);
+        if let Some(title) = &target.title {
+            let fitted = pdf_layout::fit_text(title, font, size_px as f32 * 0.92);
+            svg.push_str(&format!(
+                r#"<text x="{cx}" y="{:.1}" font-family="Helvetica, Arial, sans-serif" font-size="{font:.1}" text-anchor="middle">{}</text>"#,
+                size_px as f32 + font,
+                xml_escape(&fitted)
+            ));
+        }
+        if let Some(price) = price_label(target.price) {
+            svg.push_str(&format!(
+                r#"<text x="{cx}" y="{:.1}" font-family="Helvetica, Arial, sans-serif" font-size="{:.1}" font-weight="bold" text-anchor="middle">{price}</text>"#,
+                size_px as f32 + font * 2.4,
+                font * 1.2
+            ));
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Draw one card (QR with optional caption) into `cell`.
+fn draw_card(content: &mut Content, code: &QrCode, cell: Cell, card: bool, target: &Target) {
+    let caption_h = if card { cell.h * 0.22 } else { 0.0 };
+    let side = cell.w.min(cell.h - caption_h);
+    let modules = code.width() + 2 * QUIET_ZONE;
+    let unit = side / modules as f32;
+    let left = cell.x + (cell.w - side) / 2.0;
+    let top = cell.y + cell.h;
+
+    content.set_fill_gray(0.0);
+    for (mx, my) in dark_modules(code) {
+        let x = left + (mx + QUIET_ZONE) as f32 * unit;
+        let y = top - (my + QUIET_ZONE + 1) as f32 * unit;
+        content.rect(x, y, unit, unit);
+    }
+    content.fill_nonzero();
+
+    if card {
+        let font = (caption_h * 0.3).clamp(6.0, 14.0);
+        let mut baseline = top - side - font;
+        if let Some(title) = &target.title {
+            pdf_layout::draw_text_centered(content, pdf_layout::FONT, font, cell.x, cell.w, baseline, title);
+            baseline -= font * 1.4;
+        }
+        if let Some(price) = price_label(target.price) {
+            pdf_layout::draw_text_centered(content, pdf_layout::FONT_BOLD, font * 1.2, cell.x, cell.w, baseline, &price);
+        }
+    }
+}
+
+/// QR for one item. `sku_or_url` is either a listing URL or the item's SKU,
+/// in which case `item` must be that SKU's inventory row. `card` adds the
+/// title and price underneath (SVG and PDF only).
+#[tauri::command]
+pub fn generate_listing_qr(
+    state: tauri::State<'_, QrState>,
+    sku_or_url: String,
+    item: Option<QrItem>,
+    size_px: Option<u32>,
+    format: QrFormat,
+    card: Option<bool>,
+) -> Result<QrOutput, String> {
+    let priority = state.settings.lock().unwrap().marketplace_priority.clone();
+    let target = resolve_target(&sku_or_url, item.as_ref(), &priority)?;
+    let size_px = size_px.unwrap_or(512).clamp(64, 4096);
+    let card = card.unwrap_or(false);
+    let code = encode(&target.url)?;
+
+    let (mime, bytes) = match format {
+        QrFormat::Png if card => {
+            return Err("Card captions need SVG or PDF output; PNG is the bare code".to_string())
+        }
+        QrFormat::Png => ("image/png", render_png(&code, size_px)?),
+        QrFormat::Svg => ("image/svg+xml", render_svg(&code, size_px, card, &target).into_bytes()),
+        QrFormat::Pdf => {
+            // size_px maps to points for a single-card page.
+            let w = size_px as f32;
+            let h = if card { w * 1.28 } else { w };
+            let mut content = Content::new();
+            draw_card(&mut content, &code, Cell { x: 0.0, y: 0.0, w, h }, card, &target);
+            let mut doc = PdfDocument::new();
+            doc.add_page((w, h), content);
+            ("application/pdf", doc.finish())
+        }
+    };
+
+    Ok(QrOutput {
+        url: Some(target.url),
+        marketplace: target.marketplace,
+        mime,
+        data: base64::engine::general_purpose::STANDARD.encode(bytes),
+    })
+}
+
+/// Printable PDF of QR cards, `columns` x `rows` per page. Fails up front,
+/// naming every SKU that isn't listed anywhere.
+#[tauri::command]
+pub fn generate_qr_sheet(
+    state: tauri::State<'_, QrState>,
+    items: Vec<QrItem>,
+    paper: Option<PaperSize>,
+    columns: Option<u32>,
+    rows: Option<u32>,
+) -> Result<QrOutput, String> {
+    if items.is_empty() {
+        return Err("No items selected".to_string());
+    }
+    let priority = state.settings.lock().unwrap().marketplace_priority.clone();
+    let mut targets = Vec::with_capacity(items.len());
+    let mut unlisted = Vec::new();
+    for item in &items {
+        match best_url(item, &priority) {
+            Ok((marketplace, url)) => targets.push(Target {
+                url,
+                marketplace: Some(marketplace),
+                title: item.title.clone(),
+                price: item.price,
+            }),
+            Err(_) => unlisted.push(item.sku.as_str()),
+        }
+    }
+    if !unlisted.is_empty() {
+        return Err(format!(
+            "Not listed on any marketplace yet (crosslist these first): {}",
+            unlisted.join(", ")
+        ));
+    }
+
+    let page = paper.unwrap_or_default().dimensions();
+    let cells = pdf_layout::grid(page, columns.unwrap_or(3), rows.unwrap_or(4), 28.0, 14.0);
+    let mut doc = PdfDocument::new();
+    for chunk in targets.chunks(cells.len()) {
+        let mut content = Content::new();
+        for (target, cell) in chunk.iter().zip(&cells) {
+            let code = encode(&target.url)?;
+            draw_card(&mut content, &code, *cell, true, target);
+        }
+        doc.add_page(page, content);
+    }
+
+    Ok(QrOutput {
+        url: None,
+        marketplace: None,
+        mime: "application/pdf",
+        data: base64::engine::general_purpose::STANDARD.encode(doc.finish()),
+    })
+}
+
+#[tauri::command]
+pub fn get_qr_settings(state: tauri::State<'_, QrState>) -> QrSettings {
+    state.settings.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_qr_marketplace_priority(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, QrState>,
+    priority: Vec<String>,
+) -> Result<(), String> {
+    let mut settings = state.settings.lock().unwrap();
+    settings.marketplace_priority = priority;
+    crate::store::save(&app, STORE_NAME, &*settings)
+}
@@ -0,0 +1,645 @@
+// ── Request priority & per-host rate limits ────────────────────────────────
+// Two priority classes share each lane (a marketplace's write lane, or a
+// host's request budget): interactive requests, flagged by the command that
+// issues them, and background/bulk traffic. Interactive waiters always go
+// to the front of the queue. While any interactive request has been seen in
+// the last INTERACTIVE_WINDOW, background turns are spaced further apart so
+// only (1 - INTERACTIVE_SHARE) of the budget goes to them and the rest stays
+// free for the user.
+//
+// `native_fetch` paces requests to limited hosts here, with a token bucket per
+// site. The write queue uses the same lanes for its per-marketplace pacing.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+// Tokio's clock, so tests can run the pacing on paused time.
+use tokio::time::Instant;
+
+/// Share of a lane's budget held back for interactive requests.
+pub const INTERACTIVE_SHARE: f64 = 0.3;
+/// How long after an interactive request background traffic stays slowed.
+const INTERACTIVE_WINDOW: Duration = Duration::from_secs(3);
+/// Waits remembered per class for the status percentiles.
+const WAIT_SAMPLES: usize = 200;
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// A user is waiting on the result (opening an item, one comp search).
+    Interactive,
+    /// Syncs, bulk edits, scheduled jobs.
+    #[default]
+    Background,
+}
+
+impl Priority {
+    fn index(self) -> usize {
+        match self {
+            Priority::Interactive => 0,
+            Priority::Background => 1,
+        }
+    }
+}
+
+#[derive(Default)]
+struct LaneState {
+    busy: bool,
+    /// Interactive entries always sit ahead of background ones.
+    waiting: VecDeque<(Priority, oneshot::Sender<()>)>,
+    /// When the lane was last used, for pacing.
+    last: Option<Instant>,
+    last_interactive: Option<Instant>,
+    served: [u64; 2],
+    waits: [VecDeque<Duration>; 2],
+}
+
+/// A FIFO queue per priority class, with interactive served first.
+#[derive(Default)]
+pub struct Lane {
+    state: Mutex<LaneState>,
+}
+
+/// Permission to use the lane; the next waiter goes when it's dropped.
+pub struct Turn {
+    lane: Arc<Lane>,
+    priority: Priority,
+    queued: Instant,
+}
+
+/// Releases a turn that was granted after its waiter gave up.
+struct Waiting {
+    lane: Arc<Lane>,
+    rx: Option<oneshot::Receiver<()>>,
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        if let Some(mut rx) = self.rx.take() {
+            if rx.try_recv().is_ok() {
+                self.lane.release();
+            }
+        }
+    }
+}
+
+impl Lane {
+    /// Wait for this lane's next turn.
+    pub async fn turn(self: &Arc<Self>, priority: Priority) -> Turn {
+        let queued = Instant::now();
+        let rx = {
+            let mut s = self.state.lock().unwrap();
+            if priority == Priority::Interactive {
+                s.last_interactive = Some(queued);
+            }
+            if !s.busy && s.waiting.is_empty() {
+                s.busy = true;
+                None
+            } else {
+                let (tx, rx) = oneshot::channel();
+                let at = match priority {
+                    Priority::Interactive => {
+                        s.waiting.iter().position(|(p, _)| *p == Priority::Background).unwrap_or(s.waiting.len())
+                    }
+                    Priority::Background => s.waiting.len(),
+                };
+                s.waiting.insert(at, (priority, tx));
+                Some(rx)
+            }
+        };
+        let mut waiting = Waiting { lane: self.clone(), rx };
+        if let Some(rx) = waiting.rx.as_mut() {
+            let _ = rx.await;
+        }
+        waiting.rx = None;
+        Turn { lane: self.clone(), priority, queued }
+    }
+
+    /// Hand the lane to the next live waiter, or mark it idle.
+    fn release(&self) {
+        let mut s = self.state.lock().unwrap();
+        while let Some((_, tx)) = s.waiting.pop_front() {
+            if tx.send(()).is_ok() {
+                return;
+            }
+        }
+        s.busy = false;
+    }
+
+    fn interactive_active(s: &LaneState) -> bool {
+        s.last_interactive.is_some_and(|t| t.elapsed() < INTERACTIVE_WINDOW)
+    }
+
+    /// Whether background turns are being slowed for interactive traffic.
+    pub fn has_interactive_traffic(&self) -> bool {
+        Self::interactive_active(&self.state.lock().unwrap())
+    }
+
+    pub fn status(&self, name: &str, base_gap: Duration) -> LaneStatus {
+        let s = self.state.lock().unwrap();
+        let count = |p: Priority| s.waiting.iter().filter(|(q, _)| *q == p).count();
+        let active = Self::interactive_active(&s);
+        LaneStatus {
+            name: name.to_string(),
+            interactive_waiting: count(Priority::Interactive),
+            background_waiting: count(Priority::Background),
+            interactive_active: active,
+            interactive_served: s.served[0],
+            background_served: s.served[1],
+            interactive_p95_wait_ms: p95(&s.waits[0]),
+            background_p95_wait_ms: p95(&s.waits[1]),
+            background_gap_ms: background_gap(base_gap, active).as_millis() as u64,
+        }
+    }
+}
+
+fn background_gap(base: Duration, interactive_active: bool) -> Duration {
+    if interactive_active {
+        base.div_f64(1.0 - INTERACTIVE_SHARE)
+    } else {
+        base
+    }
+}
+
+fn p95(waits: &VecDeque<Duration>) -> Option<u64> {
+    let mut sorted: Vec<Duration> = waits.iter().copied().collect();
+    sorted.sort();
+    let idx = (sorted.len() * 95).div_ceil(100).checked_sub(1)?;
+    sorted.get(idx).map(|d| d.as_millis() as u64)
+}
+
+impl Turn {
+    /// Sleep until `gap` has passed since the lane was last used; background
+    /// turns use a longer gap while interactive traffic is around.
+    pub async fn pace(&self, gap: Duration) {
+        let wait = {
+            let s = self.lane.state.lock().unwrap();
+            let gap = match self.priority {
+                Priority::Interactive => gap,
+                Priority::Background => background_gap(gap, Lane::interactive_active(&s)),
+            };
+            s.last.map_or(Duration::ZERO, |last| gap.saturating_sub(last.elapsed()))
+        };
+        self.wait(wait).await;
+    }
+
+    /// Sleep for `wait` (a rate limiter's answer), then count the turn.
+    pub async fn wait(&self, wait: Duration) {
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        let mut s = self.lane.state.lock().unwrap();
+        let i = self.priority.index();
+        s.served[i] += 1;
+        s.waits[i].push_back(self.queued.elapsed());
+        if s.waits[i].len() > WAIT_SAMPLES {
+            s.waits[i].pop_front();
+        }
+        s.last = Some(Instant::now());
+    }
+
+    /// Restart the pacing gap from now (after a write finishes).
+    pub fn stamp(&self) {
+        self.lane.state.lock().unwrap().last = Some(Instant::now());
+    }
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        self.lane.release();
+    }
+}
+
+#[derive(Serialize)]
+pub struct LaneStatus {
+    pub name: String,
+    interactive_waiting: usize,
+    background_waiting: usize,
+    /// Background traffic is currently slowed for interactive requests.
+    interactive_active: bool,
+    interactive_served: u64,
+    background_served: u64,
+    interactive_p95_wait_ms: Option<u64>,
+    background_p95_wait_ms: Option<u64>,
+    /// Current spacing between background turns.
+    background_gap_ms: u64,
+}
+
+#[derive(Serialize)]
+pub struct RateLimitStatus {
+    interactive_share: f64,
+    hosts: Vec<LaneStatus>,
+    write_lanes: Vec<LaneStatus>,
+}
+
+// ── Per-host limits ────────────────────────────────────────────────────────
+// Each limited site has a token bucket: `rps` tokens a second, holding at
+// most `burst`, one taken per request. Limits are set per domain and cover
+// its subdomains ("depop.com" also limits "webapi.depop.com"); the longest
+// matching domain wins. Hosts share the bucket (and lane) of their
+// registrable domain, so "webapi.depop.com" and "www.depop.com" draw from
+// one "depop.com" budget; only a limit set on a subdomain itself gives it a
+// bucket of its own. Marketplaces are limited out of the box (DEFAULT_LIMIT
+// unless they have their own); other hosts only when the user sets a limit.
+// While interactive requests are around, a background request takes
+// 1 / (1 - INTERACTIVE_SHARE) tokens, keeping the interactive share free.
+
+const STORE_NAME: &str = "rate_limits";
+const DEFAULT_LIMIT: HostLimit = HostLimit { rps: 6.0, burst: 6 };
+const MAX_RPS: f64 = 1000.0;
+const MAX_BURST: u32 = 1000;
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Debug)]
+pub struct HostLimit {
+    /// Sustained requests per second.
+    pub rps: f64,
+    /// Requests that may go back to back after a quiet spell.
+    pub burst: u32,
+}
+
+fn default_limits() -> HashMap<String, HostLimit> {
+    [
+        ("depop.com", HostLimit { rps: 2.0, burst: 2 }),
+        ("ebay.com", HostLimit { rps: 8.0, burst: 8 }),
+        ("etsy.com", DEFAULT_LIMIT),
+        ("poshmark.com", DEFAULT_LIMIT),
+        ("mercari.com", DEFAULT_LIMIT),
+    ]
+    .into_iter()
+    .map(|(domain, limit)| (domain.to_string(), limit))
+    .collect()
+}
+
+/// Tokens refill continuously at `rate` up to `capacity`. A request that
+/// finds too few takes them anyway, leaving the balance negative, and waits
+/// until the refill has paid them back, so concurrent callers queue in
+/// order of arrival.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: HostLimit, now: Instant) -> Self {
+        let capacity = f64::from(limit.burst.max(1));
+        TokenBucket { rate: limit.rps, capacity, tokens: capacity, updated: now }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Take `cost` tokens, returning how long to wait before using them.
+    fn reserve(&mut self, cost: f64, now: Instant) -> Duration {
+        self.refill(now);
+        self.tokens -= cost;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+/// `host` with any `*.` and trailing dot removed, lowercased.
+fn normalize_host(host: &str) -> String {
+    host.trim().trim_start_matches("*.").trim_end_matches('.').to_ascii_lowercase()
+}
+
+fn covers(domain: &str, host: &str) -> bool {
+    host == domain || host.strip_suffix(domain).is_some_and(|rest| rest.ends_with('.'))
+}
+
+/// Public suffixes of two labels that marketplaces sit under. Anything else
+/// is taken to be a one-label suffix ("com", "de").
+const TWO_LABEL_SUFFIXES: &[&str] = &[
+    "co.uk", "org.uk", "me.uk", "com.au", "net.au", "co.nz", "co.jp", "com.br", "com.mx", "com.sg", "com.hk",
+    "co.za", "co.in", "com.tr", "com.cn",
+];
+
+/// The domain a site registers under `host` ("depop.com" for
+/// "webapi.depop.com", "ebay.co.uk" for "www.ebay.co.uk"). IP addresses and
+/// single labels are their own.
+fn registrable_domain(host: &str) -> &str {
+    if host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[') {
+        return host;
+    }
+    let labels = if TWO_LABEL_SUFFIXES.iter().any(|suffix| covers(suffix, host)) { 3 } else { 2 };
+    match host.rmatch_indices('.').nth(labels - 1) {
+        Some((dot, _)) => &host[dot + 1..],
+        None => host,
+    }
+}
+
+#[derive(Default)]
+pub struct RateLimitState {
+    hosts: Mutex<HashMap<String, Arc<Lane>>>,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+    /// Limits by domain; the defaults with the user's changes on top.
+    limits: Mutex<HashMap<String, HostLimit>>,
+}
+
+impl RateLimitState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        let mut limits = default_limits();
+        let saved: HashMap<String, HostLimit> = crate::store::load(app, STORE_NAME);
+        limits.extend(saved);
+        RateLimitState { limits: Mutex::new(limits), ..Default::default() }
+    }
+
+    /// The limit covering `host`, if any does.
+    fn limit_for(&self, host: &str) -> Option<HostLimit> {
+        self.covering(host).map(|(_, limit)| limit)
+    }
+
+    /// The domain whose limit covers `host`, and the limit.
+    fn covering(&self, host: &str) -> Option<(String, HostLimit)> {
+        let limits = self.limits.lock().unwrap();
+        limits
+            .iter()
+            .filter(|(domain, _)| covers(domain, host))
+            .max_by_key(|(domain, _)| domain.len())
+            .map(|(domain, limit)| (domain.clone(), *limit))
+    }
+
+    /// The bucket and lane `host` uses: its registrable domain, or the
+    /// domain of its limit when that's more specific.
+    fn bucket_key(&self, host: &str) -> Option<String> {
+        let (domain, _) = self.covering(host)?;
+        let site = registrable_domain(host);
+        Some(if domain.len() > site.len() { domain } else { site.to_string() })
+    }
+
+    /// Wait for a request slot on `url`'s host, returning how long that took.
+    /// Hosts without a limit and unparseable URLs (the request will fail on
+    /// its own) go straight through.
+    pub async fn acquire(&self, url: &str, priority: Priority) -> Duration {
+        let started = Instant::now();
+        let Some(host) = reqwest::Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_lowercase)) else {
+            return Duration::ZERO;
+        };
+        let Some(key) = self.bucket_key(&host) else {
+            return Duration::ZERO;
+        };
+        let lane = self.hosts.lock().unwrap().entry(key.clone()).or_default().clone();
+        let turn = lane.turn(priority).await;
+        // Reset while this request waited: it goes through unlimited.
+        let Some(limit) = self.limit_for(&host) else {
+            return started.elapsed();
+        };
+        let wait = {
+            let cost = match priority {
+                Priority::Background if lane.has_interactive_traffic() => 1.0 / (1.0 - INTERACTIVE_SHARE),
+                _ => 1.0,
+            };
+            let now = Instant::now();
+            let mut buckets = self.buckets.lock().unwrap();
+            let bucket = buckets.entry(key).or_insert_with(|| TokenBucket::new(limit, now));
+            bucket.reserve(cost, now)
+        };
+        turn.wait(wait).await;
+        started.elapsed()
+    }
+
+    fn save(&self, app: &tauri::AppHandle) -> Result<(), String> {
+        let defaults = default_limits();
+        let limits = self.limits.lock().unwrap();
+        let changed: HashMap<&String, &HostLimit> =
+            limits.iter().filter(|(domain, limit)| defaults.get(*domain) != Some(*limit)).collect();
+        crate::store::save(app, STORE_NAME, &changed)
+    }
+
+    /// Drop the buckets `domain` covers so the new limit applies at once.
+    fn reset_buckets(&self, domain: &str) {
+        self.buckets.lock().unwrap().retain(|key, _| !covers(domain, key));
+    }
+}
+
+/// Limits by domain; hosts none of them cover aren't limited.
+#[tauri::command]
+pub fn get_rate_limits(state: tauri::State<'_, RateLimitState>) -> HashMap<String, HostLimit> {
+    state.limits.lock().unwrap().clone()
+}
+
+/// Limit `host` and its subdomains to `rps` requests a second with bursts
+/// of up to `burst`. `*.depop.com` and `depop.com` mean the same.
+#[tauri::command]
+pub fn set_rate_limit(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, RateLimitState>,
+    host: String,
+    rps: f64,
+    burst: u32,
+) -> Result<(), String> {
+    let domain = normalize_host(&host);
+    if domain.is_empty() || domain.contains(['/', ':', '*', ' ']) {
+        return Err(format!("{host} is not a host name"));
+    }
+    if !rps.is_finite() || rps <= 0.0 || rps > MAX_RPS {
+        return Err(format!("Requests per second must be above 0 and at most {MAX_RPS}"));
+    }
+    if burst == 0 || burst > MAX_BURST {
+        return Err(format!("Burst must be between 1 and {MAX_BURST}"));
+    }
+    state.limits.lock().unwrap().insert(domain.clone(), HostLimit { rps, burst });
+    state.reset_buckets(&domain);
+    state.save(&app)
+}
+
+/// Put `host` back on its built-in limit, or take its limit off if it has
+/// none built in.
+#[tauri::command]
+pub fn reset_rate_limit(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, RateLimitState>,
+    host: String,
+) -> Result<(), String> {
+    let domain = normalize_host(&host);
+    {
+        let mut limits = state.limits.lock().unwrap();
+        match default_limits().remove(&domain) {
+            Some(limit) => limits.insert(domain.clone(), limit),
+            None => limits.remove(&domain),
+        };
+    }
+    state.reset_buckets(&domain);
+    state.save(&app)
+}
+
+#[tauri::command]
+pub fn get_rate_limit_status(
+    hosts: tauri::State<'_, RateLimitState>,
+    writes: tauri::State<'_, crate::write_queue::WriteQueueState>,
+) -> RateLimitStatus {
+    let mut host_status: Vec<LaneStatus> = hosts
+        .hosts
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(host, lane)| {
+            let gap = hosts.limit_for(host).map_or(Duration::ZERO, |l| Duration::from_secs_f64(1.0 / l.rps));
+            lane.status(host, gap)
+        })
+        .collect();
+    host_status.sort_by(|a, b| a.name.cmp(&b.name));
+    RateLimitStatus { interactive_share: INTERACTIVE_SHARE, hosts: host_status, write_lanes: writes.status() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Let spawned tasks run until they're parked on the lane.
+    async fn until_waiting(lane: &Lane, n: usize) {
+        while lane.state.lock().unwrap().waiting.len() < n {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn interactive_waiters_go_first_in_arrival_order() {
+        let lane = Arc::new(Lane::default());
+        let first = lane.turn(Priority::Background).await;
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        let arrivals = [
+            ("bg1", Priority::Background),
+            ("bg2", Priority::Background),
+            ("int1", Priority::Interactive),
+            ("gone", Priority::Interactive),
+            ("int2", Priority::Interactive),
+        ];
+        for (i, (name, priority)) in arrivals.into_iter().enumerate() {
+            let (waiter, order) = (lane.clone(), order.clone());
+            tasks.push(tokio::spawn(async move {
+                let _turn = waiter.turn(priority).await;
+                order.lock().unwrap().push(name);
+            }));
+            until_waiting(&lane, i + 1).await;
+        }
+        // A waiter that gives up doesn't hold the lane.
+        tasks.remove(3).abort();
+        drop(first);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), ["int1", "int2", "bg1", "bg2"]);
+        assert!(!lane.state.lock().unwrap().busy);
+    }
+
+    #[test]
+    fn buckets_allow_a_burst_then_pace() {
+        let start = Instant::now();
+        let at = |secs: f64| start + Duration::from_secs_f64(secs);
+        let mut bucket = TokenBucket::new(HostLimit { rps: 2.0, burst: 2 }, start);
+        let waits: Vec<Duration> = (0..4).map(|_| bucket.reserve(1.0, start)).collect();
+        let secs = |d: &Duration| d.as_secs_f64();
+        assert_eq!(waits.iter().map(secs).collect::<Vec<_>>(), [0.0, 0.0, 0.5, 1.0]);
+        // 1.5s refills 3 tokens, paying back the 2 borrowed.
+        assert_eq!(bucket.reserve(1.0, at(1.5)), Duration::ZERO);
+        // A quiet spell refills to the burst size and no further.
+        let cost = 1.0 / (1.0 - INTERACTIVE_SHARE);
+        assert_eq!(bucket.reserve(cost, at(60.0)), Duration::ZERO);
+        let wait = bucket.reserve(cost, at(60.0));
+        assert!((wait.as_secs_f64() - (2.0 * cost - 2.0) / 2.0).abs() < 1e-9, "{wait:?}");
+    }
+
+    #[test]
+    fn only_covered_hosts_are_limited() {
+        let state = RateLimitState { limits: Mutex::new(default_limits()), ..Default::default() };
+        state.limits.lock().unwrap().insert("api.depop.com".to_string(), HostLimit { rps: 1.0, burst: 1 });
+        assert_eq!(state.limit_for("webapi.depop.com").map(|l| l.rps), Some(2.0));
+        assert_eq!(state.limit_for("api.depop.com").map(|l| l.rps), Some(1.0));
+        assert_eq!(state.limit_for("www.mercari.com"), Some(DEFAULT_LIMIT));
+        assert_eq!(state.limit_for("notdepop.com"), None);
+        assert_eq!(state.limit_for("example.com"), None);
+    }
+
+    #[tokio::test]
+    async fn unlimited_hosts_skip_the_lanes() {
+        let state = RateLimitState { limits: Mutex::new(default_limits()), ..Default::default() };
+        for _ in 0..20 {
+            state.acquire("https://example.com/a", Priority::Background).await;
+        }
+        assert!(state.hosts.lock().unwrap().is_empty());
+        state.acquire("https://webapi.depop.com/api", Priority::Background).await;
+        assert!(state.hosts.lock().unwrap().contains_key("depop.com"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn interactive_requests_stay_fast_under_a_background_batch() {
+        let state = Arc::new(RateLimitState { limits: Mutex::new(default_limits()), ..Default::default() });
+        // 500 background requests to depop.com (2 a second), queued at once.
+        let batch: Vec<_> = (0..500)
+            .map(|i| {
+                let state = state.clone();
+                let url = format!("https://webapi.depop.com/item/{i}");
+                tokio::spawn(async move { state.acquire(&url, Priority::Background).await })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        // A user opens an item every few seconds while the batch runs.
+        let mut interactive = Vec::new();
+        for i in 0..60 {
+            tokio::time::sleep(Duration::from_millis(2500 + 300 * (i % 5))).await;
+            interactive.push(state.acquire("https://www.depop.com/products/x", Priority::Interactive).await);
+        }
+        assert!(batch.iter().any(|task| !task.is_finished()), "the batch ended before the interactive requests");
+
+        let mut background = Vec::new();
+        for task in batch {
+            background.push(task.await.unwrap());
+        }
+        let p95 = |waits: &mut Vec<Duration>| {
+            waits.sort();
+            waits[(waits.len() * 95).div_ceil(100) - 1]
+        };
+        // At worst an interactive request waits out the background turn in
+        // progress (a slowed one: 1 / 0.7 tokens at 2 a second) and then its
+        // own token (0.5s).
+        let bound = Duration::from_secs_f64(1.0 / (1.0 - INTERACTIVE_SHARE) / 2.0 + 0.5);
+        let interactive_p95 = p95(&mut interactive);
+        assert!(interactive_p95 <= bound + Duration::from_millis(10), "interactive p95 {interactive_p95:?}");
+        assert!(p95(&mut background) > Duration::from_secs(100));
+        assert_eq!(state.hosts.lock().unwrap()["depop.com"].status("depop.com", Duration::ZERO).background_waiting, 0);
+    }
+
+    #[test]
+    fn hosts_reduce_to_their_registrable_domain() {
+        assert_eq!(registrable_domain("webapi.depop.com"), "depop.com");
+        assert_eq!(registrable_domain("depop.com"), "depop.com");
+        assert_eq!(registrable_domain("www.ebay.co.uk"), "ebay.co.uk");
+        assert_eq!(registrable_domain("api.v2.etsy.com"), "etsy.com");
+        assert_eq!(registrable_domain("localhost"), "localhost");
+        assert_eq!(registrable_domain("127.0.0.1"), "127.0.0.1");
+    }
+
+    #[tokio::test]
+    async fn subdomains_draw_from_one_bucket() {
+        let state = RateLimitState { limits: Mutex::new(default_limits()), ..Default::default() };
+        // depop.com allows a burst of 2: one request on each subdomain
+        // spends it, so a third anywhere on the site has to wait.
+        state.acquire("https://webapi.depop.com/api/v2/shop", Priority::Interactive).await;
+        state.acquire("https://www.depop.com/products/x", Priority::Interactive).await;
+        {
+            let buckets = state.buckets.lock().unwrap();
+            assert_eq!(buckets.keys().collect::<Vec<_>>(), ["depop.com"]);
+            assert!(buckets["depop.com"].tokens < 0.01, "{:?}", buckets["depop.com"]);
+        }
+        assert_eq!(state.hosts.lock().unwrap().len(), 1);
+
+        // A limit set on a subdomain itself gets its own bucket.
+        state.limits.lock().unwrap().insert("api.depop.com".to_string(), HostLimit { rps: 1.0, burst: 1 });
+        assert_eq!(state.bucket_key("api.depop.com").as_deref(), Some("api.depop.com"));
+        assert_eq!(state.bucket_key("cdn.depop.com").as_deref(), Some("depop.com"));
+        assert_eq!(state.bucket_key("example.com"), None);
+    }
+}
@@ -0,0 +1,374 @@
+// ── What-if repricing simulator ────────────────────────────────────────────
+// Replays historical sales (passed in from the synced `sales` rows) under a
+// hypothetical price adjustment and reports actual vs simulated totals.
+// Pure computation: no network, deterministic for the same input.
+//
+// Fees are simulated as the recorded fee plus the fee-model difference between
+// the old and new price, so marketplace quirks baked into the real fee (promos,
+// shipping-inclusive FVF) carry over instead of being replaced by the model.
+// The model is the fee schedule in force on each sale's date.
+
+use crate::fees::{round_cents, FeeScheduleState};
+use crate::privacy::Private;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Clone)]
+pub struct HistoricalSale {
+    id: String,
+    platform: String,
+    sale_price: f64,
+    #[serde(default)]
+    shipping_cost: f64,
+    #[serde(default)]
+    platform_fees: f64,
+    #[serde(default)]
+    cost: f64,
+    /// ISO-8601 timestamp (`sales.sold_at`).
+    sold_at: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    category: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct SaleFilter {
+    #[serde(default)]
+    platforms: Option<Vec<String>>,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    title_contains: Option<String>,
+    #[serde(default)]
+    min_price: Option<f64>,
+    #[serde(default)]
+    max_price: Option<f64>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PriceAdjustment {
+    /// -20.0 = a 20% sale.
+    Percent { percent: f64 },
+    /// Add (or subtract) a fixed amount per item.
+    Fixed { amount: f64 },
+}
+
+/// Inclusive calendar-date range, `YYYY-MM-DD` (compared against the UTC date of `sold_at`).
+#[derive(Deserialize)]
+pub struct DateRange {
+    pub start: String,
+    pub end: String,
+}
+
+#[derive(Serialize, Default)]
+pub struct Totals {
+    sales: f64,
+    revenue: f64,
+    fees: f64,
+    net: f64,
+}
+
+#[derive(Serialize)]
+pub struct SaleDelta {
+    id: String,
+    title: Option<String>,
+    platform: String,
+    actual_price: f64,
+    simulated_price: f64,
+    actual_net: f64,
+    simulated_net: f64,
+    net_change: f64,
+}
+
+#[derive(Serialize)]
+pub struct RepricingSimulation {
+    matched_sales: usize,
+    elasticity: f64,
+    actual: Totals,
+    simulated: Totals,
+    revenue_change: f64,
+    net_change: f64,
+    biggest_movers: Vec<SaleDelta>,
+}
+
+impl SaleFilter {
+    fn matches(&self, sale: &HistoricalSale) -> bool {
+        if let Some(platforms) = &self.platforms {
+            if !platforms.iter().any(|p| p == &sale.platform) {
+                return false;
+            }
+        }
+        if let Some(cat) = &self.category {
+            if sale.category.as_deref() != Some(cat.as_str()) {
+                return false;
+            }
+        }
+        if let Some(needle) = &self.title_contains {
+            let title = sale.title.as_deref().unwrap_or("").to_lowercase();
+            if !title.contains(&needle.to_lowercase()) {
+                return false;
+            }
+        }
+        self.min_price.map_or(true, |min| sale.sale_price >= min)
+            && self.max_price.map_or(true, |max| sale.sale_price <= max)
+    }
+}
+
+impl PriceAdjustment {
+    pub fn apply(&self, price: f64) -> f64 {
+        let adjusted = match self {
+            PriceAdjustment::Percent { percent } => price * (1.0 + percent / 100.0),
+            PriceAdjustment::Fixed { amount } => price + amount,
+        };
+        round_cents(adjusted.max(0.0))
+    }
+}
+
+pub fn in_range(sold_at: &str, range: &DateRange) -> bool {
+    let date = sold_at.get(..10).unwrap_or(sold_at);
+    date >= range.start.as_str() && date <= range.end.as_str()
+}
+
+/// `elasticity` is the assumed volume multiplier under the new prices:
+/// 1.0 (default) = the same sales happen, 1.2 = 20% more units sell.
+#[tauri::command]
+pub fn simulate_repricing(
+    fee_schedules: tauri::State<'_, FeeScheduleState>,
+    sales: Vec<HistoricalSale>,
+    filter: Option<SaleFilter>,
+    adjustment: PriceAdjustment,
+    range: DateRange,
+    elasticity: Option<f64>,
+    top_movers: Option<usize>,
+) -> Result<Private<RepricingSimulation>, String> {
+    let model = |platform: &str, price: f64, date: &str| fee_schedules.calculate(platform, price, date).total_fees;
+    let filter = filter.unwrap_or_default();
+    simulate(model, &sales, &filter, adjustment, &range, elasticity, top_movers).map(Private::new)
+}
+
+/// The simulation, with `model` giving the modelled fees for a sale on a
+/// platform, at a price, on a date.
+fn simulate(
+    model: impl Fn(&str, f64, &str) -> f64,
+    sales: &[HistoricalSale],
+    filter: &SaleFilter,
+    adjustment: PriceAdjustment,
+    range: &DateRange,
+    elasticity: Option<f64>,
+    top_movers: Option<usize>,
+) -> Result<RepricingSimulation, String> {
+    if range.start > range.end {
+        return Err("Range start is after range end".to_string());
+    }
+    let elasticity = elasticity.unwrap_or(1.0);
+    if !elasticity.is_finite() || elasticity < 0.0 {
+        return Err("Elasticity must be a non-negative number".to_string());
+    }
+
+    let mut actual = Totals::default();
+    let mut simulated = Totals::default();
+    let mut deltas = Vec::new();
+
+    for sale in sales
+        .iter()
+        .filter(|s| in_range(&s.sold_at, range) && filter.matches(s))
+    {
+        let new_price = adjustment.apply(sale.sale_price);
+        let date = sale.sold_at.get(..10).unwrap_or(&sale.sold_at);
+        let model_old = model(&sale.platform, sale.sale_price, date);
+        let model_new = model(&sale.platform, new_price, date);
+        // Sales synced without a fee figure fall back to the model outright.
+        let actual_fees = if sale.platform_fees > 0.0 { sale.platform_fees } else { model_old };
+        let simulated_fees = (actual_fees + model_new - model_old).max(0.0);
+
+        let actual_net = sale.sale_price - actual_fees - sale.shipping_cost - sale.cost;
+        let simulated_net = new_price - simulated_fees - sale.shipping_cost - sale.cost;
+
+        actual.sales += 1.0;
+        actual.revenue += sale.sale_price;
+        actual.fees += actual_fees;
+        actual.net += actual_net;
+
+        simulated.sales += elasticity;
+        simulated.revenue += new_price * elasticity;
+        simulated.fees += simulated_fees * elasticity;
+        simulated.net += simulated_net * elasticity;
+
+        deltas.push(SaleDelta {
+            id: sale.id.clone(),
+            title: sale.title.clone(),
+            platform: sale.platform.clone(),
+            actual_price: sale.sale_price,
+            simulated_price: new_price,
+            actual_net: round_cents(actual_net),
+            simulated_net: round_cents(simulated_net),
+            net_change: round_cents(simulated_net - actual_net),
+        });
+    }
+
+    for t in [&mut actual, &mut simulated] {
+        t.revenue = round_cents(t.revenue);
+        t.fees = round_cents(t.fees);
+        t.net = round_cents(t.net);
+    }
+
+    let matched_sales = deltas.len();
+    deltas.sort_by(|a, b| {
+        b.net_change
+            .abs()
+            .total_cmp(&a.net_change.abs())
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    deltas.truncate(top_movers.unwrap_or(10));
+
+    Ok(RepricingSimulation {
+        matched_sales,
+        elasticity,
+        revenue_change: round_cents(simulated.revenue - actual.revenue),
+        net_change: round_cents(simulated.net - actual.net),
+        actual,
+        simulated,
+        biggest_movers: deltas,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Depop at 10% (12% before 2024), everything else fee-free.
+    fn model(platform: &str, price: f64, date: &str) -> f64 {
+        match platform {
+            "depop" if date < "2024-01-01" => round_cents(price * 0.12),
+            "depop" => round_cents(price * 0.10),
+            _ => 0.0,
+        }
+    }
+
+    fn history() -> Vec<HistoricalSale> {
+        serde_json::from_value(json!([
+            // The recorded fee includes a 50p promo the model doesn't know about.
+            { "id": "s1", "platform": "depop", "sale_price": 50.0, "platform_fees": 5.5, "shipping_cost": 4.0,
+              "cost": 10.0, "sold_at": "2024-03-01T10:00:00Z", "title": "Levi's 501", "category": "jeans" },
+            // Synced without a fee figure.
+            { "id": "s2", "platform": "depop", "sale_price": 20.0, "shipping_cost": 3.0, "cost": 5.0,
+              "sold_at": "2024-03-31T23:59:00Z", "title": "Nike tee", "category": "tops" },
+            { "id": "s3", "platform": "ebay", "sale_price": 100.0, "platform_fees": 13.0, "cost": 40.0,
+              "sold_at": "2024-04-01T00:00:00Z", "title": "Levi's trucker jacket", "category": "jackets" },
+            { "id": "s4", "platform": "depop", "sale_price": 30.0, "sold_at": "2023-12-31T12:00:00Z" },
+        ]))
+        .unwrap()
+    }
+
+    fn range(start: &str, end: &str) -> DateRange {
+        DateRange { start: start.to_string(), end: end.to_string() }
+    }
+
+    fn run(
+        filter: SaleFilter,
+        adjustment: PriceAdjustment,
+        range: DateRange,
+        elasticity: Option<f64>,
+    ) -> RepricingSimulation {
+        simulate(model, &history(), &filter, adjustment, &range, elasticity, None).unwrap()
+    }
+
+    #[test]
+    fn percent_cut_keeps_recorded_fee_quirks() {
+        let sim = run(
+            SaleFilter::default(),
+            PriceAdjustment::Percent { percent: -10.0 },
+            range("2024-03-01", "2024-03-31"),
+            None,
+        );
+        assert_eq!(sim.matched_sales, 2);
+        assert_eq!((sim.actual.sales, sim.actual.revenue, sim.actual.fees, sim.actual.net), (2.0, 70.0, 7.5, 40.5));
+        // s1 pays the promo on top of the modelled fee; s2 is modelled outright.
+        assert_eq!(
+            (sim.simulated.sales, sim.simulated.revenue, sim.simulated.fees, sim.simulated.net),
+            (2.0, 63.0, 6.8, 34.2)
+        );
+        assert_eq!((sim.revenue_change, sim.net_change), (-7.0, -6.3));
+
+        let movers: Vec<_> =
+            sim.biggest_movers.iter().map(|d| (d.id.as_str(), d.simulated_price, d.net_change)).collect();
+        assert_eq!(movers, vec![("s1", 45.0, -4.5), ("s2", 18.0, -1.8)]);
+    }
+
+    #[test]
+    fn elasticity_scales_simulated_volume() {
+        let sim = run(
+            SaleFilter::default(),
+            PriceAdjustment::Fixed { amount: 5.0 },
+            range("2024-03-01", "2024-03-31"),
+            Some(1.5),
+        );
+        assert_eq!(sim.elasticity, 1.5);
+        assert_eq!(sim.actual.sales, 2.0);
+        assert_eq!((sim.simulated.sales, sim.simulated.revenue, sim.simulated.net), (3.0, 120.0, 74.25));
+        assert_eq!(sim.net_change, 33.75);
+    }
+
+    #[test]
+    fn filters_and_dated_fee_schedules_pick_the_sales() {
+        let ids = |sim: RepricingSimulation| sim.biggest_movers.into_iter().map(|d| d.id).collect::<Vec<_>>();
+        let year = || range("2024-01-01", "2024-12-31");
+        let cut = PriceAdjustment::Percent { percent: -20.0 };
+
+        let ebay = SaleFilter { platforms: Some(vec!["ebay".to_string()]), ..SaleFilter::default() };
+        assert_eq!(ids(run(ebay, cut, year(), None)), vec!["s3"]);
+        let levis = SaleFilter {
+            title_contains: Some("LEVI".to_string()),
+            category: Some("jeans".to_string()),
+            ..SaleFilter::default()
+        };
+        assert_eq!(ids(run(levis, cut, year(), None)), vec!["s1"]);
+        let priced = SaleFilter { min_price: Some(25.0), max_price: Some(60.0), ..SaleFilter::default() };
+        assert_eq!(ids(run(priced, cut, range("2023-01-01", "2024-12-31"), None)), vec!["s1", "s4"]);
+
+        // s4 sold under the older 12% schedule.
+        let sim = run(SaleFilter::default(), cut, range("2023-12-31", "2023-12-31"), None);
+        assert_eq!((sim.actual.fees, sim.simulated.fees), (3.6, 2.88));
+    }
+
+    #[test]
+    fn prices_never_go_negative_and_movers_are_capped() {
+        assert_eq!(PriceAdjustment::Fixed { amount: -100.0 }.apply(20.0), 0.0);
+        assert_eq!(PriceAdjustment::Percent { percent: -150.0 }.apply(20.0), 0.0);
+        let sim = simulate(
+            model,
+            &history(),
+            &SaleFilter::default(),
+            PriceAdjustment::Percent { percent: 10.0 },
+            &range("2020-01-01", "2030-01-01"),
+            None,
+            Some(1),
+        )
+        .unwrap();
+        assert_eq!(sim.matched_sales, 4);
+        assert_eq!(sim.biggest_movers.len(), 1);
+        assert_eq!(sim.biggest_movers[0].id, "s3");
+    }
+
+    #[test]
+    fn bad_ranges_and_elasticities_are_refused() {
+        let go = |range: DateRange, elasticity| {
+            simulate(
+                model,
+                &history(),
+                &SaleFilter::default(),
+                PriceAdjustment::Fixed { amount: 1.0 },
+                &range,
+                elasticity,
+                None,
+            )
+            .err()
+        };
+        assert!(go(range("2024-02-01", "2024-01-01"), None).is_some());
+        assert!(go(range("2024-01-01", "2024-02-01"), Some(-1.0)).is_some());
+        assert!(go(range("2024-01-01", "2024-02-01"), Some(f64::NAN)).is_some());
+        assert!(go(range("2024-01-01", "2024-02-01"), Some(0.0)).is_none());
+    }
+}
@@ -0,0 +1,321 @@
+// ── Request contexts ───────────────────────────────────────────────────────
+// Long-running commands (syncs, exports, image batches, downloads) run under
+// a RequestContext so the frontend can abandon them. The convention:
+//
+// - `RequestContext::begin` registers the invocation and emits
+//   "command-started" with its id. Callers may pass their own
+//   `invocation_id`, so they can cancel before the event arrives.
+// - The work calls `ctx.check()?` between steps (`token.check()` in blocking
+//   code) and races long awaits with `ctx.run(..)`.
+// - `cancel_command(invocation_id)` cancels it; so does the `timeout_ms`
+//   deadline passing, or the invoking window being destroyed.
+// - Cancelled work removes any partial output it made and returns its Err;
+//   `ctx.finish(result)` turns that into `Finished::Cancelled`. Every
+//   invocation ends with a "command-finished" event, whatever the outcome,
+//   so progress UIs never hang.
+//
+// `native_fetch` calls given a `request_id` are tracked the same way, minus
+// the events, and cancelled with `cancel_fetch`.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+use tokio::sync::watch;
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelReason {
+    /// `cancel_command` was called.
+    Requested,
+    /// The window that started the command was closed.
+    WindowClosed,
+    /// The command's `timeout_ms` ran out.
+    DeadlineExceeded,
+}
+
+impl CancelReason {
+    fn message(self) -> &'static str {
+        match self {
+            CancelReason::Requested => "Cancelled",
+            CancelReason::WindowClosed => "Cancelled: window closed",
+            CancelReason::DeadlineExceeded => "Cancelled: timed out",
+        }
+    }
+}
+
+/// What a cancellable command returns when it didn't fail.
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Finished<T> {
+    Completed { result: T },
+    Cancelled { reason: CancelReason },
+}
+
+/// The cancellation side of a context, cheap to clone into blocking tasks.
+#[derive(Clone)]
+pub struct CancelToken {
+    rx: watch::Receiver<Option<CancelReason>>,
+    deadline: Option<Instant>,
+}
+
+impl CancelToken {
+    /// A token nothing can cancel, for tests of code that takes one.
+    #[cfg(test)]
+    pub fn never() -> Self {
+        CancelToken { rx: watch::channel(None).1, deadline: None }
+    }
+
+    pub fn reason(&self) -> Option<CancelReason> {
+        let requested = *self.rx.borrow();
+        requested.or_else(|| {
+            self.deadline
+                .filter(|d| Instant::now() >= *d)
+                .map(|_| CancelReason::DeadlineExceeded)
+        })
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.reason().is_some()
+    }
+
+    /// Err once cancelled, for `?` between steps.
+    pub fn check(&self) -> Result<(), String> {
+        match self.reason() {
+            Some(reason) => Err(reason.message().to_string()),
+            None => Ok(()),
+        }
+    }
+
+    /// Resolves when the context is cancelled or its deadline passes.
+    pub async fn cancelled(&self) -> CancelReason {
+        let mut rx = self.rx.clone();
+        loop {
+            if let Some(reason) = self.reason() {
+                return reason;
+            }
+            match self.deadline {
+                Some(deadline) => {
+                    tokio::select! {
+                        _ = rx.changed() => {}
+                        _ = tokio::time::sleep_until(deadline.into()) => {}
+                    }
+                }
+                None => {
+                    // The sender only goes away with the context itself.
+                    if rx.changed().await.is_err() {
+                        std::future::pending::<()>().await;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Run `fut` until it finishes or the context is cancelled, whichever
+    /// comes first. A cancelled future is dropped.
+    pub async fn run<F: Future>(&self, fut: F) -> Result<F::Output, String> {
+        tokio::select! {
+            out = fut => Ok(out),
+            reason = self.cancelled() => Err(reason.message().to_string()),
+        }
+    }
+}
+
+struct Active {
+    command: &'static str,
+    window: String,
+    tx: watch::Sender<Option<CancelReason>>,
+}
+
+impl Active {
+    fn cancel(&self, reason: CancelReason) {
+        self.tx.send_if_modified(|current| {
+            if current.is_some() {
+                return false;
+            }
+            *current = Some(reason);
+            true
+        });
+    }
+}
+
+/// Invocations in flight, by id.
+#[derive(Default)]
+pub struct Invocations {
+    active: Mutex<HashMap<String, Active>>,
+}
+
+impl Invocations {
+    /// Cancel everything started from the window `label`.
+    pub fn cancel_window(&self, label: &str) {
+        for active in self.active.lock().unwrap().values().filter(|a| a.window == label) {
+            log::info!("cancelling {} (window {label} closed)", active.command);
+            active.cancel(CancelReason::WindowClosed);
+        }
+    }
+
+    fn cancel(&self, id: &str) -> bool {
+        match self.active.lock().unwrap().get(id) {
+            Some(active) => {
+                active.cancel(CancelReason::Requested);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct StartedEvent<'a> {
+    invocation_id: &'a str,
+    command: &'a str,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Clone, Serialize)]
+struct FinishedEvent<'a> {
+    invocation_id: &'a str,
+    command: &'a str,
+    outcome: Outcome,
+    reason: Option<CancelReason>,
+    error: Option<&'a str>,
+}
+
+/// One invocation of a long-running command; unregisters itself on drop.
+pub struct RequestContext {
+    app: tauri::AppHandle,
+    id: String,
+    command: &'static str,
+    token: CancelToken,
+}
+
+impl RequestContext {
+    pub fn begin(
+        app: &tauri::AppHandle,
+        window: &tauri::Window,
+        command: &'static str,
+        invocation_id: Option<String>,
+        timeout_ms: Option<u64>,
+    ) -> Result<Self, String> {
+        if timeout_ms == Some(0) {
+            return Err("timeout_ms must be greater than 0".to_string());
+        }
+        let id = invocation_id
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| hex::encode(rand::random::<[u8; 8]>()));
+        let ctx = Self::register(app, window, command, id, timeout_ms)?;
+        let _ = app.emit("command-started", StartedEvent { invocation_id: &ctx.id, command });
+        Ok(ctx)
+    }
+
+    /// Register a short request under the caller's id so it can be
+    /// cancelled, without the started/finished events (`native_fetch`).
+    pub fn track(
+        app: &tauri::AppHandle,
+        window: &tauri::Window,
+        command: &'static str,
+        id: String,
+    ) -> Result<Self, String> {
+        let id = id.trim().to_string();
+        if id.is_empty() {
+            return Err("request_id can't be empty".to_string());
+        }
+        Self::register(app, window, command, id, None)
+    }
+
+    fn register(
+        app: &tauri::AppHandle,
+        window: &tauri::Window,
+        command: &'static str,
+        id: String,
+        timeout_ms: Option<u64>,
+    ) -> Result<Self, String> {
+        let deadline = match timeout_ms {
+            Some(ms) => Some(
+                Instant::now()
+                    .checked_add(Duration::from_millis(ms))
+                    .ok_or_else(|| format!("timeout_ms {ms} is too large"))?,
+            ),
+            None => None,
+        };
+        let (tx, rx) = watch::channel(None);
+        {
+            let invocations = app.state::<Invocations>();
+            let mut active = invocations.active.lock().unwrap();
+            if active.contains_key(&id) {
+                return Err(format!("Invocation {id} is already running"));
+            }
+            active.insert(id.clone(), Active { command, window: window.label().to_string(), tx });
+        }
+        Ok(RequestContext { app: app.clone(), id, command, token: CancelToken { rx, deadline } })
+    }
+
+    pub fn token(&self) -> CancelToken {
+        self.token.clone()
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        self.token.check()
+    }
+
+    pub async fn run<F: Future>(&self, fut: F) -> Result<F::Output, String> {
+        self.token.run(fut).await
+    }
+
+    /// Emit the terminal event and shape the command's return value. An error
+    /// after cancellation is reported as `Finished::Cancelled`.
+    pub fn finish<T>(self, result: Result<T, String>) -> Result<Finished<T>, String> {
+        let event = |outcome, reason, error| FinishedEvent {
+            invocation_id: &self.id,
+            command: self.command,
+            outcome,
+            reason,
+            error,
+        };
+        match result {
+            Ok(result) => {
+                let _ = self.app.emit("command-finished", event(Outcome::Completed, None, None));
+                Ok(Finished::Completed { result })
+            }
+            Err(e) => match self.token.reason() {
+                Some(reason) => {
+                    let _ = self.app.emit("command-finished", event(Outcome::Cancelled, Some(reason), None));
+                    Ok(Finished::Cancelled { reason })
+                }
+                None => {
+                    let _ = self.app.emit("command-finished", event(Outcome::Failed, None, Some(&e)));
+                    Err(e)
+                }
+            },
+        }
+    }
+}
+
+impl Drop for RequestContext {
+    fn drop(&mut self) {
+        self.app.state::<Invocations>().active.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Cancel a running invocation. False when it isn't running (any more).
+#[tauri::command]
+pub fn cancel_command(invocations: tauri::State<'_, Invocations>, invocation_id: String) -> bool {
+    invocations.cancel(&invocation_id)
+}
+
+/// Cancel a `native_fetch` started with `request_id`. False when it isn't in
+/// flight (any more).
+#[tauri::command]
+pub fn cancel_fetch(invocations: tauri::State<'_, Invocations>, request_id: String) -> bool {
+    invocations.cancel(&request_id)
+}
@@ -0,0 +1,535 @@
+// ── Photo retention ────────────────────────────────────────────────────────
+// Rules that shrink the photo footprint of long-sold items without losing
+// anything needed for disputes or tax records: e.g. "sold > 180 days ago:
+// keep a thumbnail of the cover, move the originals to an archive directory".
+//
+// The frontend passes the candidate items (status, sold date, photo paths,
+// cover first); `preview_retention_run` shows exactly what would happen and
+// `run_retention` does it. "Delete" never deletes straight away: files go to a
+// per-run trash folder that is purged after TRASH_DAYS, and until then the
+// whole run can be undone. Where every photo now lives is kept in the
+// photo-locations index (`locate_photos`) and returned per run, so item
+// records and the dossier export can still find archived originals.
+
+use crate::images::{self, AspectMode, ImageOptions, OutputFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+const SETTINGS_STORE: &str = "retention";
+const LOCATIONS_STORE: &str = "photo_locations";
+const RUNS_STORE: &str = "retention_runs";
+const TRASH_DIR: &str = "retention-trash";
+const TRASH_DAYS: u64 = 30;
+const DAY_SECS: u64 = 24 * 60 * 60;
+const PURGE_CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum OriginalsAction {
+    /// Move into `<dir>/<item_id>/`.
+    Archive { dir: String },
+    /// Move to the retention trash; purged after 30 days.
+    Delete,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RetentionRule {
+    pub name: String,
+    /// Item statuses the rule applies to ("sold", "archived", ...).
+    pub statuses: Vec<String>,
+    /// Days since the item was sold / archived.
+    pub min_age_days: u64,
+    /// Keep the cover as a thumbnail of this size (longest side); None keeps
+    /// no local copy of the cover either.
+    #[serde(default)]
+    pub cover_thumbnail: Option<u32>,
+    pub originals: OriginalsAction,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RetentionSettings {
+    /// Evaluated in order; the first matching rule wins.
+    pub rules: Vec<RetentionRule>,
+}
+
+#[derive(Deserialize)]
+pub struct RetentionItem {
+    item_id: String,
+    status: String,
+    /// Unix seconds the item was sold or archived.
+    #[serde(default)]
+    closed_at: Option<u64>,
+    /// Cover first.
+    photos: Vec<String>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PhotoState {
+    Archived,
+    Trashed,
+    /// Trash purged; only a thumbnail (if any) remains.
+    Deleted,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PhotoLocation {
+    original: String,
+    item_id: String,
+    state: PhotoState,
+    /// Where the original is now (archive or trash path); None once purged.
+    current: Option<String>,
+    /// Local stand-in for the cover, if one was made.
+    thumbnail: Option<String>,
+    run_id: String,
+    at: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct PlannedAction {
+    item_id: String,
+    rule: String,
+    path: String,
+    bytes: u64,
+    /// Where the original goes.
+    destination: String,
+    state: PhotoState,
+    /// Thumbnail written in place of the cover.
+    thumbnail: Option<String>,
+    #[serde(skip)]
+    thumbnail_max: u32,
+}
+
+#[derive(Serialize)]
+pub struct RetentionPreview {
+    actions: Vec<PlannedAction>,
+    items: usize,
+    /// Bytes leaving the photo library (thumbnails add a little back).
+    bytes_reclaimed: u64,
+    /// Photos skipped because they are missing on disk.
+    missing: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct RunManifest {
+    run_id: String,
+    at: u64,
+    locations: Vec<PhotoLocation>,
+    #[serde(default)]
+    undone: bool,
+}
+
+#[derive(Serialize)]
+pub struct RetentionRunReport {
+    run_id: String,
+    moved: usize,
+    thumbnails: usize,
+    bytes_reclaimed: u64,
+    /// New location of every photo touched, for updating item records.
+    relocations: Vec<PhotoLocation>,
+    errors: Vec<String>,
+    undo_until: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct RetentionProgress<'a> {
+    run_id: &'a str,
+    done: usize,
+    total: usize,
+    path: &'a str,
+}
+
+pub struct RetentionState {
+    settings: Mutex<RetentionSettings>,
+    locations: Mutex<BTreeMap<String, PhotoLocation>>,
+    runs: Mutex<Vec<RunManifest>>,
+}
+
+impl RetentionState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        RetentionState {
+            settings: Mutex::new(crate::store::load(app, SETTINGS_STORE)),
+            locations: Mutex::new(crate::store::load(app, LOCATIONS_STORE)),
+            runs: Mutex::new(crate::store::load(app, RUNS_STORE)),
+        }
+    }
+
+    /// Archive copies and thumbnails retention is keeping track of.
+    pub fn tracked_paths(&self) -> HashSet<String> {
+        self.locations
+            .lock()
+            .unwrap()
+            .values()
+            .flat_map(|l| l.current.iter().chain(l.thumbnail.iter()).cloned())
+            .collect()
+    }
+}
+
+fn trash_root(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    crate::store::data_path(app, TRASH_DIR)
+}
+
+fn safe_component(s: &str) -> String {
+    let out: String = s
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect();
+    if out.trim_matches('.').is_empty() { "item".to_string() } else { out }
+}
+
+fn thumbnail_path(cover: &Path) -> PathBuf {
+    let stem = cover.file_stem().and_then(|s| s.to_str()).unwrap_or("cover");
+    cover.with_file_name(format!("{stem}-thumb.jpg"))
+}
+
+/// Non-clobbering destination for `file_name` inside `dir`.
+fn free_path(dir: &Path, file_name: &str, taken: &mut HashSet<PathBuf>) -> PathBuf {
+    let mut candidate = dir.join(file_name);
+    let (stem, ext) = match file_name.rsplit_once('.') {
+        Some((s, e)) => (s.to_string(), format!(".{e}")),
+        None => (file_name.to_string(), String::new()),
+    };
+    let mut n = 2;
+    while candidate.exists() || taken.contains(&candidate) {
+        candidate = dir.join(format!("{stem}-{n}{ext}"));
+        n += 1;
+    }
+    taken.insert(candidate.clone());
+    candidate
+}
+
+fn plan(
+    settings: &RetentionSettings,
+    locations: &BTreeMap<String, PhotoLocation>,
+    items: &[RetentionItem],
+    trash: &Path,
+    run_id: &str,
+) -> RetentionPreview {
+    let now = crate::unix_now();
+    let mut preview = RetentionPreview { actions: Vec::new(), items: 0, bytes_reclaimed: 0, missing: Vec::new() };
+    let mut taken = HashSet::new();
+
+    for item in items {
+        let Some(closed_at) = item.closed_at else {
+            continue;
+        };
+        let age_days = now.saturating_sub(closed_at) / DAY_SECS;
+        let Some(rule) = settings.rules.iter().find(|r| {
+            age_days >= r.min_age_days && r.statuses.iter().any(|s| s.eq_ignore_ascii_case(&item.status))
+        }) else {
+            continue;
+        };
+
+        let mut touched = false;
+        for (i, photo) in item.photos.iter().enumerate() {
+            if locations.contains_key(photo) {
+                continue;
+            }
+            let path = Path::new(photo);
+            let Ok(meta) = std::fs::metadata(path) else {
+                preview.missing.push(photo.clone());
+                continue;
+            };
+            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("photo");
+            let (dir, state) = match &rule.originals {
+                OriginalsAction::Archive { dir } => (Path::new(dir).join(safe_component(&item.item_id)), PhotoState::Archived),
+                OriginalsAction::Delete => (trash.join(run_id).join(safe_component(&item.item_id)), PhotoState::Trashed),
+            };
+            let destination = free_path(&dir, file_name, &mut taken);
+            let thumbnail = match (i, rule.cover_thumbnail) {
+                (0, Some(_)) => Some(thumbnail_path(path).to_string_lossy().into_owned()),
+                _ => None,
+            };
+            let thumbnail_max = rule.cover_thumbnail.unwrap_or(0);
+            preview.bytes_reclaimed += meta.len();
+            preview.actions.push(PlannedAction {
+                item_id: item.item_id.clone(),
+                rule: rule.name.clone(),
+                path: photo.clone(),
+                bytes: meta.len(),
+                destination: destination.to_string_lossy().into_owned(),
+                state,
+                thumbnail,
+                thumbnail_max,
+            });
+            touched = true;
+        }
+        if touched {
+            preview.items += 1;
+        }
+    }
+    preview
+}
+
+/// Rename, falling back to copy + delete across filesystems.
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if let Some(dir) = to.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("create {}: {e}", dir.display()))?;
+    }
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to).map_err(|e| format!("copy {} -> {}: {e}", from.display(), to.display()))?;
+    std::fs::remove_file(from).map_err(|e| format!("remove {}: {e}", from.display()))
+}
+
+fn write_thumbnail(cover: &Path, out: &Path, max: u32) -> Result<(), String> {
+    let img = image::open(cover).map_err(|e| format!("decode {}: {e}", cover.display()))?;
+    let opts = ImageOptions {
+        max_dimension: max.max(64),
+        aspect: AspectMode::Fit,
+        format: OutputFormat::Jpeg,
+        quality: 80,
+        watermark: false,
+    };
+    let bytes = images::encode(&images::render(img, &opts, None), &opts)?;
+    std::fs::write(out, bytes).map_err(|e| format!("write {}: {e}", out.display()))
+}
+
+fn save_state(app: &tauri::AppHandle, state: &RetentionState) -> Result<(), String> {
+    crate::store::save(app, LOCATIONS_STORE, &*state.locations.lock().unwrap())?;
+    crate::store::save(app, RUNS_STORE, &*state.runs.lock().unwrap())
+}
+
+#[tauri::command]
+pub fn get_retention_settings(state: tauri::State<'_, RetentionState>) -> RetentionSettings {
+    state.settings.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_retention_settings(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, RetentionState>,
+    settings: RetentionSettings,
+) -> Result<(), String> {
+    for rule in &settings.rules {
+        if rule.statuses.is_empty() {
+            return Err(format!("Rule '{}' needs at least one item status", rule.name));
+        }
+        if let OriginalsAction::Archive { dir } = &rule.originals {
+            if !Path::new(dir).is_absolute() {
+                return Err(format!("Rule '{}': choose an absolute archive directory", rule.name));
+            }
+        }
+    }
+    let mut current = state.settings.lock().unwrap();
+    *current = settings;
+    crate::store::save(&app, SETTINGS_STORE, &*current)
+}
+
+/// Exactly what `run_retention` would do with these items, without touching
+/// anything.
+#[tauri::command]
+pub fn preview_retention_run(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, RetentionState>,
+    items: Vec<RetentionItem>,
+) -> Result<RetentionPreview, String> {
+    let trash = trash_root(&app)?;
+    let settings = state.settings.lock().unwrap().clone();
+    let locations = state.locations.lock().unwrap();
+    Ok(plan(&settings, &locations, &items, &trash, "preview"))
+}
+
+/// Apply the retention rules. Emits "retention-progress" per photo. The run
+/// can be undone with `undo_retention_run` for 30 days.
+#[tauri::command]
+pub async fn run_retention(
+    app: tauri::AppHandle,
+    items: Vec<RetentionItem>,
+) -> Result<RetentionRunReport, String> {
+    let run_id = format!("{}-{}", crate::unix_now(), hex::encode(rand::random::<[u8; 3]>()));
+    let trash = trash_root(&app)?;
+    let preview = {
+        let state = app.state::<RetentionState>();
+        let settings = state.settings.lock().unwrap().clone();
+        let locations = state.locations.lock().unwrap();
+        plan(&settings, &locations, &items, &trash, &run_id)
+    };
+
+    let worker_app = app.clone();
+    let id = run_id.clone();
+    let (relocations, errors, bytes) = tauri::async_runtime::spawn_blocking(move || {
+        let total = preview.actions.len();
+        let mut relocations = Vec::with_capacity(total);
+        let mut errors = Vec::new();
+        let mut bytes = 0;
+        for (i, action) in preview.actions.iter().enumerate() {
+            let from = Path::new(&action.path);
+            let thumbnail = match &action.thumbnail {
+                Some(out) => match write_thumbnail(from, Path::new(out), action.thumbnail_max) {
+                    Ok(()) => Some(out.clone()),
+                    Err(e) => {
+                        // Without a stand-in the cover stays where it is.
+                        errors.push(e);
+                        continue;
+                    }
+                },
+                _ => None,
+            };
+            match move_file(from, Path::new(&action.destination)) {
+                Ok(()) => {
+                    bytes += action.bytes;
+                    relocations.push(PhotoLocation {
+                        original: action.path.clone(),
+                        item_id: action.item_id.clone(),
+                        state: action.state,
+                        current: Some(action.destination.clone()),
+                        thumbnail,
+                        run_id: id.clone(),
+                        at: crate::unix_now(),
+                    });
+                }
+                Err(e) => {
+                    if let Some(t) = &thumbnail {
+                        let _ = std::fs::remove_file(t);
+                    }
+                    errors.push(e);
+                }
+            }
+            let _ = worker_app.emit(
+                "retention-progress",
+                RetentionProgress { run_id: &id, done: i + 1, total, path: &action.path },
+            );
+        }
+        (relocations, errors, bytes)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let state = app.state::<RetentionState>();
+    {
+        let mut locations = state.locations.lock().unwrap();
+        for loc in &relocations {
+            locations.insert(loc.original.clone(), loc.clone());
+        }
+        state.runs.lock().unwrap().push(RunManifest {
+            run_id: run_id.clone(),
+            at: crate::unix_now(),
+            locations: relocations.clone(),
+            undone: false,
+        });
+    }
+    save_state(&app, &state)?;
+
+    Ok(RetentionRunReport {
+        thumbnails: relocations.iter().filter(|l| l.thumbnail.is_some()).count(),
+        moved: relocations.len(),
+        run_id,
+        bytes_reclaimed: bytes,
+        relocations,
+        errors,
+        undo_until: crate::unix_now() + TRASH_DAYS * DAY_SECS,
+    })
+}
+
+/// Put every photo a run moved back where it was and remove its thumbnails.
+/// Returns the restored original paths.
+#[tauri::command]
+pub fn undo_retention_run(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, RetentionState>,
+    run_id: String,
+) -> Result<Vec<String>, String> {
+    let manifest = {
+        let runs = state.runs.lock().unwrap();
+        runs.iter().find(|r| r.run_id == run_id).cloned().ok_or("No such retention run")?
+    };
+    if manifest.undone {
+        return Err("This run was already undone".to_string());
+    }
+    if crate::unix_now().saturating_sub(manifest.at) > TRASH_DAYS * DAY_SECS {
+        return Err(format!("Runs can only be undone for {TRASH_DAYS} days"));
+    }
+
+    let mut restored = Vec::new();
+    let mut errors = Vec::new();
+    for loc in &manifest.locations {
+        let Some(current) = &loc.current else {
+            errors.push(format!("{} was already purged", loc.original));
+            continue;
+        };
+        match move_file(Path::new(current), Path::new(&loc.original)) {
+            Ok(()) => {
+                if let Some(t) = &loc.thumbnail {
+                    let _ = std::fs::remove_file(t);
+                }
+                restored.push(loc.original.clone());
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+
+    {
+        let mut locations = state.locations.lock().unwrap();
+        for path in &restored {
+            locations.remove(path);
+        }
+        let mut runs = state.runs.lock().unwrap();
+        if let Some(run) = runs.iter_mut().find(|r| r.run_id == run_id) {
+            run.undone = errors.is_empty();
+            run.locations.retain(|l| !restored.contains(&l.original));
+        }
+    }
+    save_state(&app, &state)?;
+    if !errors.is_empty() {
+        return Err(format!("Restored {} photo(s), but: {}", restored.len(), errors.join("; ")));
+    }
+    Ok(restored)
+}
+
+/// Where each of `paths` (original photo paths) lives now; paths retention
+/// never touched are omitted.
+#[tauri::command]
+pub fn locate_photos(state: tauri::State<'_, RetentionState>, paths: Vec<String>) -> Vec<PhotoLocation> {
+    let locations = state.locations.lock().unwrap();
+    paths.iter().filter_map(|p| locations.get(p).cloned()).collect()
+}
+
+/// Delete trash older than 30 days and mark those photos as deleted.
+fn purge_trash(app: &tauri::AppHandle) -> Result<usize, String> {
+    let state = app.state::<RetentionState>();
+    let cutoff = crate::unix_now().saturating_sub(TRASH_DAYS * DAY_SECS);
+    let trash = trash_root(app)?;
+    let mut purged = 0;
+    {
+        let mut runs = state.runs.lock().unwrap();
+        let mut locations = state.locations.lock().unwrap();
+        for run in runs.iter_mut().filter(|r| r.at < cutoff) {
+            let dir = trash.join(&run.run_id);
+            if dir.exists() {
+                std::fs::remove_dir_all(&dir).map_err(|e| format!("purge {}: {e}", dir.display()))?;
+                purged += 1;
+            }
+            for loc in run.locations.iter_mut().filter(|l| l.state == PhotoState::Trashed) {
+                loc.state = PhotoState::Deleted;
+                loc.current = None;
+                if let Some(indexed) = locations.get_mut(&loc.original) {
+                    indexed.state = PhotoState::Deleted;
+                    indexed.current = None;
+                }
+            }
+        }
+    }
+    if purged > 0 {
+        save_state(app, &state)?;
+    }
+    Ok(purged)
+}
+
+pub fn start_purge_job(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            match purge_trash(&app) {
+                Ok(0) => {}
+                Ok(n) => log::info!("purged {n} retention trash run(s)"),
+                Err(e) => log::warn!("retention trash purge failed: {e}"),
+            }
+            tokio::time::sleep(PURGE_CHECK_INTERVAL).await;
+        }
+    });
+}
@@ -0,0 +1,517 @@
+// ── Shipping deadlines ─────────────────────────────────────────────────────
+// Ship-by deadlines for unshipped sales across marketplaces. Marketplace
+// policies count whole days in the seller's local calendar: a Depop sale at
+// 23:50 on Monday with 5 days to ship is due by the end of Saturday, local time.
+// eBay counts business days of handling time. A sale can carry its own
+// handling time where the listing specified one.
+//
+// "Local" is the seller's IANA time zone, so a sale's day and the end of the
+// deadline day each use the offset in force then (DST included). Settings
+// saved before zones were supported only have a fixed UTC offset.
+//
+// The sales sync reports unshipped sales with `record_unshipped_sales`. A job
+// escalates as the deadline nears: a notification 24 hours out, another 4
+// hours out, then a red alert on breach, each at most once per sale.
+// Recording tracking clears the sale.
+//
+// Carrier rate tables (cost per weight band, per destination region) also
+// live here; international shipping prices are checked against them.
+
+use chrono::{Datelike, Days, FixedOffset, NaiveDate, Offset, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+const SALES_STORE: &str = "shipping_deadlines";
+const SETTINGS_STORE: &str = "shipping_sla";
+const RATES_STORE: &str = "shipping_rates";
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// Shipped sales are kept this long for the dashboard's history.
+const KEEP_SHIPPED_SECS: u64 = 14 * 24 * 60 * 60;
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DayBasis {
+    Calendar,
+    /// Monday to Friday.
+    Business,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct SlaPolicy {
+    pub days: u32,
+    pub basis: DayBasis,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShippingSettings {
+    /// Marketplace -> policy; marketplaces not listed use DEFAULT_POLICY.
+    pub policies: BTreeMap<String, SlaPolicy>,
+    /// Seller's IANA time zone ("Europe/London"), supplied by the frontend.
+    pub time_zone: Option<String>,
+    /// Fixed offset from UTC, used when there's no `time_zone`.
+    pub utc_offset_minutes: i32,
+}
+
+impl ShippingSettings {
+    fn ship_by(&self, sold_at: u64, policy: SlaPolicy) -> u64 {
+        match self.time_zone.as_deref().and_then(|z| z.parse::<Tz>().ok()) {
+            Some(zone) => ship_by(sold_at, policy, &zone),
+            None => {
+                let offset = FixedOffset::east_opt(self.utc_offset_minutes * 60).unwrap_or(Utc.fix());
+                ship_by(sold_at, policy, &offset)
+            }
+        }
+    }
+}
+
+const DEFAULT_POLICY: SlaPolicy = SlaPolicy { days: 3, basis: DayBasis::Business };
+
+impl Default for ShippingSettings {
+    fn default() -> Self {
+        let policies = [
+            ("depop", SlaPolicy { days: 5, basis: DayBasis::Calendar }),
+            ("ebay", SlaPolicy { days: 3, basis: DayBasis::Business }),
+            ("poshmark", SlaPolicy { days: 7, basis: DayBasis::Calendar }),
+            ("etsy", SlaPolicy { days: 3, basis: DayBasis::Business }),
+        ];
+        ShippingSettings {
+            policies: policies.into_iter().map(|(m, p)| (m.to_string(), p)).collect(),
+            time_zone: None,
+            utc_offset_minutes: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertLevel {
+    None,
+    Due24h,
+    Due4h,
+    Breached,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TrackedSale {
+    pub sale_id: String,
+    pub marketplace: String,
+    #[serde(default)]
+    pub account: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    pub sold_at: u64,
+    /// Listing-specific handling time, overriding the marketplace policy.
+    #[serde(default)]
+    pub handling_days: Option<u32>,
+    pub ship_by: u64,
+    pub alerted: AlertLevel,
+    #[serde(default)]
+    pub tracking_number: Option<String>,
+    #[serde(default)]
+    pub carrier: Option<String>,
+    #[serde(default)]
+    pub shipped_at: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct UnshippedSale {
+    sale_id: String,
+    marketplace: String,
+    #[serde(default)]
+    account: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    /// Unix seconds.
+    sold_at: u64,
+    #[serde(default)]
+    handling_days: Option<u32>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct Deadline {
+    #[serde(flatten)]
+    sale: TrackedSale,
+    /// Negative once breached.
+    seconds_left: i64,
+    level: AlertLevel,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct RateBand {
+    /// Heaviest parcel (kg) this band covers.
+    pub max_weight_kg: f64,
+    pub cost: f64,
+}
+
+/// One carrier's prices to one destination region.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CarrierRate {
+    pub carrier: String,
+    pub region: String,
+    /// Sorted by weight.
+    pub bands: Vec<RateBand>,
+}
+
+impl CarrierRate {
+    fn cost_for(&self, weight_kg: f64) -> Option<f64> {
+        self.bands.iter().find(|b| weight_kg <= b.max_weight_kg).map(|b| b.cost)
+    }
+}
+
+pub struct ShippingState {
+    settings: Mutex<ShippingSettings>,
+    sales: Mutex<BTreeMap<String, TrackedSale>>,
+    rates: Mutex<Vec<CarrierRate>>,
+}
+
+impl ShippingState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        ShippingState {
+            settings: Mutex::new(crate::store::load(app, SETTINGS_STORE)),
+            sales: Mutex::new(crate::store::load(app, SALES_STORE)),
+            rates: Mutex::new(crate::store::load(app, RATES_STORE)),
+        }
+    }
+
+    /// Cheapest configured carrier and its cost for a `weight_kg` parcel to
+    /// `region`.
+    pub fn cheapest_rate(&self, region: &str, weight_kg: f64) -> Option<(String, f64)> {
+        self.rates
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| r.region == region)
+            .filter_map(|r| Some((r.carrier.clone(), r.cost_for(weight_kg)?)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Lowest cost any carrier charges to `region`, for any weight.
+    pub fn lowest_rate(&self, region: &str) -> Option<f64> {
+        self.rates
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| r.region == region)
+            .flat_map(|r| r.bands.iter().map(|b| b.cost))
+            .min_by(f64::total_cmp)
+    }
+
+    /// Seconds from sale to tracking for the shipped sales still on record.
+    pub fn ship_times(&self, marketplace: &str, account: &str) -> Vec<u64> {
+        self.sales
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|s| s.marketplace == marketplace && s.account.as_deref().map_or(true, |a| a == account))
+            .filter_map(|s| Some(s.shipped_at?.saturating_sub(s.sold_at)))
+            .collect()
+    }
+}
+
+/// The first instant of `day` in `zone`. Where a DST change skips local
+/// midnight the day starts at the end of the gap.
+fn start_of<Z: TimeZone>(day: NaiveDate, zone: &Z) -> i64 {
+    let midnight = day.and_hms_opt(0, 0, 0).unwrap_or_default();
+    (0..=120)
+        .step_by(15)
+        .find_map(|minutes| {
+            let local = midnight + chrono::Duration::minutes(minutes);
+            zone.from_local_datetime(&local).earliest()
+        })
+        .map_or(midnight.and_utc().timestamp(), |t| t.timestamp())
+}
+
+/// Ship-by instant: the last second of the local calendar day the policy
+/// lands on, counted from the local day of the sale.
+pub fn ship_by<Z: TimeZone>(sold_at: u64, policy: SlaPolicy, zone: &Z) -> u64 {
+    let sold = i64::try_from(sold_at).ok().and_then(|t| Utc.timestamp_opt(t, 0).single()).unwrap_or_default();
+    let mut day = sold.with_timezone(zone).date_naive();
+    match policy.basis {
+        DayBasis::Calendar => day = day.checked_add_days(Days::new(policy.days.into())).unwrap_or(day),
+        DayBasis::Business => {
+            let mut counted = 0;
+            while counted < policy.days {
+                day = day.succ_opt().unwrap_or(day);
+                if !matches!(day.weekday(), Weekday::Sat | Weekday::Sun) {
+                    counted += 1;
+                }
+            }
+        }
+    }
+    let next = day.succ_opt().unwrap_or(day);
+    (start_of(next, zone) - 1).max(0) as u64
+}
+
+fn level(ship_by: u64, now: u64) -> AlertLevel {
+    let left = ship_by as i64 - now as i64;
+    match left {
+        l if l < 0 => AlertLevel::Breached,
+        l if l <= 4 * 3600 => AlertLevel::Due4h,
+        l if l <= 24 * 3600 => AlertLevel::Due24h,
+        _ => AlertLevel::None,
+    }
+}
+
+fn policy_for(settings: &ShippingSettings, marketplace: &str, handling_days: Option<u32>) -> SlaPolicy {
+    let base = settings.policies.get(marketplace).copied().unwrap_or(DEFAULT_POLICY);
+    SlaPolicy { days: handling_days.unwrap_or(base.days), basis: base.basis }
+}
+
+fn save_sales(app: &tauri::AppHandle, sales: &BTreeMap<String, TrackedSale>) {
+    if let Err(e) = crate::store::save(app, SALES_STORE, sales) {
+        log::warn!("saving shipping deadlines failed: {e}");
+    }
+}
+
+/// Raise any alerts that have become due since the last pass.
+fn check(app: &tauri::AppHandle) {
+    let state = app.state::<ShippingState>();
+    let now = crate::unix_now();
+    let mut raised = Vec::new();
+    {
+        let mut sales = state.sales.lock().unwrap();
+        sales.retain(|_, s| s.shipped_at.map_or(true, |t| now.saturating_sub(t) < KEEP_SHIPPED_SECS));
+        for sale in sales.values_mut().filter(|s| s.shipped_at.is_none()) {
+            let current = level(sale.ship_by, now);
+            if current > sale.alerted {
+                sale.alerted = current;
+                let seconds_left = sale.ship_by as i64 - now as i64;
+                raised.push(Deadline { seconds_left, level: current, sale: sale.clone() });
+            }
+        }
+        if !raised.is_empty() {
+            save_sales(app, &sales);
+        }
+    }
+    for deadline in raised {
+        let sale = &deadline.sale;
+        let what = format!("{} ({})", sale.title.as_deref().unwrap_or(&sale.sale_id), sale.marketplace);
+        let (title, body) = match deadline.level {
+            AlertLevel::Breached => ("Ship-by deadline missed", format!("{what} is late")),
+            AlertLevel::Due4h => ("Ship within 4 hours", what),
+            _ => ("Ship by tomorrow", format!("{what} is due within 24 hours")),
+        };
+        crate::notifications::show(app, title, &body);
+        let _ = app.emit("shipping-deadline", &deadline);
+    }
+}
+
+pub fn start_deadline_job(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            check(&app);
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+}
+
+/// Report unshipped sales from a sync. New sales get a ship-by deadline;
+/// known ones keep their alert state (the deadline is recomputed in case the
+/// policy changed).
+#[tauri::command]
+pub fn record_unshipped_sales(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ShippingState>,
+    sales: Vec<UnshippedSale>,
+) -> Vec<TrackedSale> {
+    let settings = state.settings.lock().unwrap().clone();
+    let mut tracked = state.sales.lock().unwrap();
+    let mut out = Vec::with_capacity(sales.len());
+    for s in sales {
+        let policy = policy_for(&settings, &s.marketplace, s.handling_days);
+        let deadline = settings.ship_by(s.sold_at, policy);
+        let entry = tracked.entry(s.sale_id.clone()).or_insert_with(|| TrackedSale {
+            sale_id: s.sale_id.clone(),
+            marketplace: s.marketplace.clone(),
+            account: None,
+            title: None,
+            sold_at: s.sold_at,
+            handling_days: None,
+            ship_by: deadline,
+            alerted: AlertLevel::None,
+            tracking_number: None,
+            carrier: None,
+            shipped_at: None,
+        });
+        entry.account = s.account.or(entry.account.take());
+        entry.title = s.title.or(entry.title.take());
+        entry.handling_days = s.handling_days.or(entry.handling_days);
+        entry.ship_by = deadline;
+        out.push(entry.clone());
+    }
+    save_sales(&app, &tracked);
+    out
+}
+
+/// Record a tracking number (manually or from a purchased label); the sale
+/// stops counting towards deadlines.
+#[tauri::command]
+pub fn record_tracking(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ShippingState>,
+    sale_id: String,
+    tracking_number: String,
+    carrier: Option<String>,
+) -> Result<TrackedSale, String> {
+    let tracking_number = tracking_number.trim().to_string();
+    if tracking_number.is_empty() {
+        return Err("Tracking number is required".to_string());
+    }
+    let mut sales = state.sales.lock().unwrap();
+    let sale = sales.get_mut(&sale_id).ok_or_else(|| format!("No unshipped sale {sale_id}"))?;
+    sale.tracking_number = Some(tracking_number);
+    sale.carrier = carrier;
+    sale.shipped_at = Some(crate::unix_now());
+    let sale = sale.clone();
+    save_sales(&app, &sales);
+    let _ = app.emit("shipping-deadline-cleared", &sale.sale_id);
+    Ok(sale)
+}
+
+/// Unshipped sales, most urgent first.
+#[tauri::command]
+pub fn get_shipping_deadlines(state: tauri::State<'_, ShippingState>) -> Vec<Deadline> {
+    let now = crate::unix_now();
+    let mut deadlines: Vec<Deadline> = state
+        .sales
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|s| s.shipped_at.is_none())
+        .map(|s| Deadline {
+            seconds_left: s.ship_by as i64 - now as i64,
+            level: level(s.ship_by, now),
+            sale: s.clone(),
+        })
+        .collect();
+    deadlines.sort_by_key(|d| d.sale.ship_by);
+    deadlines
+}
+
+#[tauri::command]
+pub fn get_shipping_settings(state: tauri::State<'_, ShippingState>) -> ShippingSettings {
+    state.settings.lock().unwrap().clone()
+}
+
+/// Save SLA policies and the local time zone, and recompute open deadlines.
+#[tauri::command]
+pub fn set_shipping_settings(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ShippingState>,
+    mut settings: ShippingSettings,
+) -> Result<(), String> {
+    settings.time_zone = settings.time_zone.map(|z| z.trim().to_string()).filter(|z| !z.is_empty());
+    if settings.policies.values().any(|p| p.days == 0 || p.days > 60) {
+        return Err("Ship-by windows must be between 1 and 60 days".to_string());
+    }
+    if settings.utc_offset_minutes.abs() > 14 * 60 {
+        return Err("UTC offset is out of range".to_string());
+    }
+    if let Some(zone) = settings.time_zone.as_deref().filter(|z| z.parse::<Tz>().is_err()) {
+        return Err(format!("{zone} isn't a known time zone"));
+    }
+    {
+        let mut sales = state.sales.lock().unwrap();
+        for sale in sales.values_mut().filter(|s| s.shipped_at.is_none()) {
+            let policy = policy_for(&settings, &sale.marketplace, sale.handling_days);
+            sale.ship_by = settings.ship_by(sale.sold_at, policy);
+        }
+        save_sales(&app, &sales);
+    }
+    let mut current = state.settings.lock().unwrap();
+    *current = settings;
+    crate::store::save(&app, SETTINGS_STORE, &*current)
+}
+
+#[tauri::command]
+pub fn get_carrier_rates(state: tauri::State<'_, ShippingState>) -> Vec<CarrierRate> {
+    state.rates.lock().unwrap().clone()
+}
+
+/// Replace the carrier rate tables.
+#[tauri::command]
+pub fn set_carrier_rates(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ShippingState>,
+    mut rates: Vec<CarrierRate>,
+) -> Result<(), String> {
+    for rate in &mut rates {
+        if rate.carrier.trim().is_empty() || rate.region.trim().is_empty() {
+            return Err("Every rate needs a carrier and a region".to_string());
+        }
+        if rate.bands.is_empty() {
+            return Err(format!("{} to {} has no weight bands", rate.carrier, rate.region));
+        }
+        let valid = |b: &RateBand| {
+            b.max_weight_kg.is_finite() && b.max_weight_kg > 0.0 && b.cost.is_finite() && b.cost >= 0.0
+        };
+        if !rate.bands.iter().all(valid) {
+            return Err(format!("{} to {} has an invalid weight or cost", rate.carrier, rate.region));
+        }
+        rate.bands.sort_by(|a, b| a.max_weight_kg.total_cmp(&b.max_weight_kg));
+    }
+    let mut current = state.rates.lock().unwrap();
+    *current = rates;
+    crate::store::save(&app, RATES_STORE, &*current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> u64 {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap().timestamp() as u64
+    }
+
+    const DEPOP: SlaPolicy = SlaPolicy { days: 5, basis: DayBasis::Calendar };
+    const EBAY: SlaPolicy = SlaPolicy { days: 3, basis: DayBasis::Business };
+
+    #[test]
+    fn the_sale_day_is_the_local_one_either_side_of_midnight() {
+        let london: Tz = "Europe/London".parse().unwrap();
+        // 23:30 BST Monday 1 July: due by the end of Saturday.
+        assert_eq!(ship_by(at(2024, 7, 1, 22, 30), DEPOP, &london), at(2024, 7, 6, 22, 59) + 59);
+        // 00:30 BST Tuesday: a day later.
+        assert_eq!(ship_by(at(2024, 7, 1, 23, 30), DEPOP, &london), at(2024, 7, 7, 22, 59) + 59);
+        // 23:59:59 local is the last second; one more is the next day.
+        let last = at(2024, 7, 1, 22, 59) + 59;
+        assert_eq!(ship_by(last, DEPOP, &london), ship_by(at(2024, 7, 1, 12, 0), DEPOP, &london));
+        assert_ne!(ship_by(last + 1, DEPOP, &london), ship_by(last, DEPOP, &london));
+    }
+
+    #[test]
+    fn the_deadline_uses_the_offset_in_force_on_its_day() {
+        let london: Tz = "Europe/London".parse().unwrap();
+        // Sold in GMT on Thursday 28 March; due after the clocks went forward.
+        assert_eq!(ship_by(at(2024, 3, 28, 12, 0), DEPOP, &london), at(2024, 4, 2, 22, 59) + 59);
+        // Friday 29 March, three business days: Wednesday 3 April, BST.
+        assert_eq!(ship_by(at(2024, 3, 29, 12, 0), EBAY, &london), at(2024, 4, 3, 22, 59) + 59);
+        // And back: sold in BST, due in GMT.
+        assert_eq!(ship_by(at(2024, 10, 24, 12, 0), DEPOP, &london), at(2024, 10, 29, 23, 59) + 59);
+    }
+
+    #[test]
+    fn a_skipped_midnight_starts_the_day_after_the_gap() {
+        // Chile's clocks jump from 24:00 to 01:00 on 8 September 2024.
+        let santiago: Tz = "America/Santiago".parse().unwrap();
+        let day = NaiveDate::from_ymd_opt(2024, 9, 8).unwrap();
+        assert_eq!(start_of(day, &santiago), at(2024, 9, 8, 4, 0) as i64);
+    }
+
+    #[test]
+    fn settings_without_a_zone_use_the_fixed_offset() {
+        let mut settings = ShippingSettings { utc_offset_minutes: -300, ..Default::default() };
+        // 22:00 in New York (UTC-5) on Monday 15 January.
+        let sold = at(2024, 1, 16, 3, 0);
+        assert_eq!(settings.ship_by(sold, DEPOP), at(2024, 1, 21, 4, 59) + 59);
+        settings.time_zone = Some("America/New_York".to_string());
+        assert_eq!(settings.ship_by(sold, DEPOP), at(2024, 1, 21, 4, 59) + 59);
+        // Business days skip the weekend: Tue, Wed, Thu.
+        assert_eq!(settings.ship_by(sold, EBAY), at(2024, 1, 19, 4, 59) + 59);
+    }
+}
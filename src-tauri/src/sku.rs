@@ -0,0 +1,167 @@
+// ── SKU generation ─────────────────────────────────────────────────────────
+// SKUs are rendered from a pattern such as `{category_prefix}{yy}{seq}`.
+// Tokens:
+//   {category_prefix}  per-category prefix from the settings, else the first
+//                      three letters of the category ("Jackets" -> "JAC")
+//   {yy} {yyyy} {mm}   today's date
+//   {seq}              the sequence number, zero-padded to `seq_width`
+//
+// Everything except {seq} is the sequence's scope, and the numbers come from
+// Supabase (`claim_sku_sequence`, migration 017), which hands each number out
+// once even when two devices generate at the same moment. The frontend passes
+// its session in, as the CSV importer, manual item creation and the photo
+// shoot renamer all draw from the same sequences.
+//
+// `generate_sku` asks enabled `generateSku` plugins (plugins.rs) first and
+// only falls back to the pattern when none of them returns a SKU.
+
+use crate::supabase::SupabaseSession;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+const STORE_NAME: &str = "sku_settings";
+const FALLBACK_PREFIX: &str = "ITM";
+const MAX_SEQ_WIDTH: usize = 9;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SkuSettings {
+    pub pattern: String,
+    pub seq_width: usize,
+    /// Category name -> prefix ("Jackets" -> "JKT").
+    pub category_prefixes: BTreeMap<String, String>,
+}
+
+impl Default for SkuSettings {
+    fn default() -> Self {
+        SkuSettings {
+            pattern: "{category_prefix}{yy}{seq}".to_string(),
+            seq_width: 4,
+            category_prefixes: BTreeMap::new(),
+        }
+    }
+}
+
+impl SkuSettings {
+    fn validate(&self) -> Result<(), String> {
+        if self.pattern.matches("{seq}").count() != 1 {
+            return Err("The pattern needs {seq} exactly once".to_string());
+        }
+        if !(1..=MAX_SEQ_WIDTH).contains(&self.seq_width) {
+            return Err(format!("Sequence width must be 1-{MAX_SEQ_WIDTH}"));
+        }
+        let prefixes = self.category_prefixes.values().map(String::as_str).chain([FALLBACK_PREFIX]);
+        for prefix in prefixes {
+            let scope = self.scope(prefix, "2000-01-01");
+            if let Some(c) = scope.chars().find(|c| !c.is_ascii_alphanumeric() && !"-_.".contains(*c)) {
+                return Err(format!("SKUs can't contain {c:?}"));
+            }
+        }
+        Ok(())
+    }
+
+    fn prefix(&self, category: &str) -> String {
+        let key = category.trim();
+        if let Some((_, prefix)) = self.category_prefixes.iter().find(|(c, _)| c.eq_ignore_ascii_case(key)) {
+            return prefix.clone();
+        }
+        let letters: String = key.chars().filter(char::is_ascii_alphanumeric).take(3).collect();
+        if letters.is_empty() {
+            FALLBACK_PREFIX.to_string()
+        } else {
+            letters.to_ascii_uppercase()
+        }
+    }
+
+    /// The pattern with every token but {seq} filled in; `today` is
+    /// `YYYY-MM-DD`.
+    fn render(&self, prefix: &str, today: &str) -> String {
+        self.pattern
+            .replace("{category_prefix}", prefix)
+            .replace("{yyyy}", &today[..4])
+            .replace("{yy}", &today[2..4])
+            .replace("{mm}", &today[5..7])
+    }
+
+    fn scope(&self, prefix: &str, today: &str) -> String {
+        self.render(prefix, today).replace("{seq}", "")
+    }
+}
+
+pub struct SkuState {
+    settings: Mutex<SkuSettings>,
+}
+
+impl SkuState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        SkuState { settings: Mutex::new(crate::store::load(app, STORE_NAME)) }
+    }
+
+    pub fn settings(&self) -> SkuSettings {
+        self.settings.lock().unwrap().clone()
+    }
+}
+
+/// Reserve `count` consecutive numbers in `scope`; returns the first.
+async fn claim_sequence(session: &SupabaseSession, scope: &str, count: u32) -> Result<u64, String> {
+    let client = crate::supabase::client()?;
+    let req = session
+        .rest(&client, reqwest::Method::POST, "rpc/claim_sku_sequence")
+        .json(&json!({ "p_scope": scope, "p_count": count }));
+    let value = crate::supabase::send(req).await?;
+    value.as_u64().ok_or_else(|| format!("unexpected claim_sku_sequence result {value}"))
+}
+
+/// Generate `count` new SKUs for `category`, in sequence order.
+pub async fn next_skus(
+    settings: &SkuSettings,
+    session: &SupabaseSession,
+    category: &str,
+    count: u32,
+) -> Result<Vec<String>, String> {
+    let today = crate::fees::today();
+    let prefix = settings.prefix(category);
+    let rendered = settings.render(&prefix, &today);
+    let first = claim_sequence(session, &settings.scope(&prefix, &today), count).await?;
+    Ok((first..first + u64::from(count))
+        .map(|seq| rendered.replace("{seq}", &format!("{seq:0width$}", width = settings.seq_width)))
+        .collect())
+}
+
+#[tauri::command]
+pub fn get_sku_settings(state: tauri::State<'_, SkuState>) -> SkuSettings {
+    state.settings()
+}
+
+#[tauri::command]
+pub fn set_sku_settings(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SkuState>,
+    settings: SkuSettings,
+) -> Result<SkuSettings, String> {
+    settings.validate()?;
+    *state.settings.lock().unwrap() = settings.clone();
+    crate::store::save(&app, STORE_NAME, &settings)?;
+    Ok(settings)
+}
+
+/// `item` is what plugins get to work from; it defaults to just the
+/// category.
+#[tauri::command]
+pub async fn generate_sku(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, SkuState>,
+    session: SupabaseSession,
+    category: String,
+    item: Option<serde_json::Value>,
+) -> Result<String, String> {
+    let item = item.unwrap_or_else(|| json!({ "category": category }));
+    if let Some(sku) = crate::plugins::plugin_sku(&app, item).await? {
+        return Ok(sku);
+    }
+    let settings = state.settings();
+    let mut skus = next_skus(&settings, &session, &category, 1).await?;
+    skus.pop().ok_or_else(|| "No SKU generated".to_string())
+}
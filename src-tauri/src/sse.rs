@@ -0,0 +1,356 @@
+// ── Server-Sent Events ─────────────────────────────────────────────────────
+// `native_fetch` buffers the whole body, which never ends for an
+// `text/event-stream` endpoint. `start_sse` opens one with the shared fetch
+// client and parses it as it arrives, emitting each event to the window that
+// started it as `sse-message` {stream_id, event, data, id}.
+//
+// A dropped connection is reopened after the server's `retry:` delay (3s by
+// default, backing off on repeated failures) with `Last-Event-ID`, so the
+// server can resume where it left off. A 204 or a 4xx ends the stream, as
+// does MAX_RECONNECTS failures in a row; `sse-error` reports each failure
+// and `sse-closed` the end. At most MAX_STREAMS run at once; `stop_sse`
+// ends one.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+const MAX_STREAMS: usize = 8;
+const MAX_RECONNECTS: u32 = 10;
+const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+const MAX_RETRY: Duration = Duration::from_secs(60);
+/// Longest line, and largest event (all its data lines), accepted; a server
+/// that goes past either is dropped rather than buffered without end.
+const MAX_LINE_BYTES: usize = 1024 * 1024;
+const MAX_EVENT_BYTES: usize = 4 * 1024 * 1024;
+/// No bytes (not even a keep-alive comment) for this long counts as a drop.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+/// Overrides the shared client's 20s request timeout; the stream reconnects
+/// with Last-Event-ID when it runs out.
+const CONNECTION_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Default)]
+pub struct SseState {
+    /// stream id -> (generation, task). The generation keeps a finished
+    /// task from removing a newer stream that reused its id.
+    streams: Mutex<HashMap<String, (u64, tauri::async_runtime::JoinHandle<()>)>>,
+    next_generation: Mutex<u64>,
+}
+
+#[derive(Clone, Serialize)]
+struct SseMessage<'a> {
+    stream_id: &'a str,
+    event: String,
+    data: String,
+    id: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+struct SseError<'a> {
+    stream_id: &'a str,
+    error: String,
+    /// Whether the stream will reconnect.
+    reconnecting: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct SseClosed<'a> {
+    stream_id: &'a str,
+    reason: String,
+}
+
+struct Event {
+    event: String,
+    data: String,
+    id: Option<String>,
+}
+
+/// Incremental `text/event-stream` parser.
+#[derive(Default)]
+struct Parser {
+    line: Vec<u8>,
+    event: String,
+    data: Vec<String>,
+    /// The last event id seen; it carries over to later events.
+    last_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Parser {
+    /// Drop a half-received event after the connection broke.
+    fn discard_partial(&mut self) {
+        self.line.clear();
+        self.event.clear();
+        self.data.clear();
+    }
+
+    fn feed(&mut self, bytes: &[u8], out: &mut Vec<Event>) -> Result<(), String> {
+        for &b in bytes {
+            if b == b'\n' {
+                let mut line = std::mem::take(&mut self.line);
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                self.line_done(&String::from_utf8_lossy(&line), out);
+                if self.data.iter().map(String::len).sum::<usize>() > MAX_EVENT_BYTES {
+                    return Err(format!("event over {MAX_EVENT_BYTES} bytes"));
+                }
+            } else if self.line.len() >= MAX_LINE_BYTES {
+                return Err(format!("line over {MAX_LINE_BYTES} bytes"));
+            } else {
+                self.line.push(b);
+            }
+        }
+        Ok(())
+    }
+
+    fn line_done(&mut self, line: &str, out: &mut Vec<Event>) {
+        if line.is_empty() {
+            if !self.data.is_empty() {
+                out.push(Event {
+                    event: if self.event.is_empty() { "message".to_string() } else { std::mem::take(&mut self.event) },
+                    data: std::mem::take(&mut self.data).join("\n"),
+                    id: self.last_id.clone(),
+                });
+            }
+            self.event.clear();
+            return;
+        }
+        if line.starts_with(':') {
+            return;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => self.data.push(value.to_string()),
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_string()).filter(|v| !v.is_empty()),
+            "retry" => {
+                if let Ok(ms) = value.parse() {
+                    self.retry = Some(Duration::from_millis(ms).min(MAX_RETRY));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// How long to wait before reconnect number `failures`.
+fn backoff(base: Duration, failures: u32) -> Duration {
+    base.saturating_mul(1 << failures.saturating_sub(1).min(5)).min(MAX_RETRY)
+}
+
+enum Ended {
+    /// The connection dropped or failed; try again.
+    Retry(String),
+    /// The server said not to reconnect.
+    Stop(String),
+}
+
+struct Stream {
+    app: tauri::AppHandle,
+    /// Window the events go to.
+    label: String,
+    client: reqwest::Client,
+    url: String,
+    headers: HashMap<String, String>,
+    id: String,
+}
+
+impl Stream {
+    async fn connect(&self, parser: &mut Parser, failures: &mut u32) -> Ended {
+        let url = self.url.as_str();
+        let mut req = self
+            .client
+            .get(url)
+            .timeout(CONNECTION_LIFETIME)
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .header(reqwest::header::CACHE_CONTROL, "no-cache");
+        for (k, v) in &self.headers {
+            req = req.header(k.as_str(), v.as_str());
+        }
+        if let Some(id) = &parser.last_id {
+            req = req.header("Last-Event-ID", id.as_str());
+        }
+        let mut resp = match req.send().await {
+            Ok(resp) => resp,
+            Err(e) => return Ended::Retry(format!("request: {e}")),
+        };
+        let status = resp.status();
+        crate::outage::record_status(url, status.as_u16());
+        if status.as_u16() == 204 {
+            return Ended::Stop("server ended the stream (204)".to_string());
+        }
+        if status.is_client_error() {
+            return Ended::Stop(format!("{url} returned {status}"));
+        }
+        if !status.is_success() {
+            return Ended::Retry(format!("{url} returned {status}"));
+        }
+        let content_type = resp.headers().get(reqwest::header::CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or("");
+        if !content_type.starts_with("text/event-stream") {
+            return Ended::Stop(format!("not an event stream (Content-Type {content_type:?})"));
+        }
+        *failures = 0;
+        let mut events = Vec::new();
+        loop {
+            let chunk = match tokio::time::timeout(IDLE_TIMEOUT, resp.chunk()).await {
+                Ok(Ok(Some(chunk))) => chunk,
+                Ok(Ok(None)) => return Ended::Retry("connection closed".to_string()),
+                Ok(Err(e)) => return Ended::Retry(format!("body: {e}")),
+                Err(_) => return Ended::Retry(format!("no data for {}s", IDLE_TIMEOUT.as_secs())),
+            };
+            if let Err(e) = parser.feed(&chunk, &mut events) {
+                return Ended::Stop(format!("malformed event stream: {e}"));
+            }
+            for Event { event, data, id } in events.drain(..) {
+                let message = SseMessage { stream_id: &self.id, event, data, id };
+                let _ = self.app.emit_to(self.label.as_str(), "sse-message", message);
+            }
+        }
+    }
+
+    /// Connect and reconnect until the stream ends; returns why it ended.
+    async fn run(&self) -> String {
+        let mut parser = Parser::default();
+        let mut failures = 0u32;
+        loop {
+            let error = match self.connect(&mut parser, &mut failures).await {
+                Ended::Stop(reason) => return reason,
+                Ended::Retry(error) => error,
+            };
+            parser.discard_partial();
+            failures += 1;
+            let reconnecting = failures <= MAX_RECONNECTS;
+            log::warn!("SSE stream {} dropped: {error}", self.id);
+            let event = SseError { stream_id: &self.id, error: error.clone(), reconnecting };
+            let _ = self.app.emit_to(self.label.as_str(), "sse-error", event);
+            if !reconnecting {
+                return format!("gave up after {MAX_RECONNECTS} failed reconnects: {error}");
+            }
+            // The server's retry delay, doubling with each failure in a row.
+            let base = parser.retry.unwrap_or(DEFAULT_RETRY);
+            tokio::time::sleep(backoff(base, failures)).await;
+        }
+    }
+}
+
+/// Open `url` as an event stream under `stream_id`. Events go to the calling
+/// window as `sse-message` until `stop_sse(stream_id)` or the stream ends.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn start_sse(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    state: tauri::State<'_, SseState>,
+    cookies: tauri::State<'_, crate::cookie_jar::CookieJarState>,
+    proxy_state: tauri::State<'_, crate::proxy::ProxyState>,
+    url: String,
+    headers: Option<HashMap<String, String>>,
+    stream_id: String,
+    use_cookies: Option<bool>,
+    session: Option<String>,
+) -> Result<(), String> {
+    let stream_id = stream_id.trim().to_string();
+    if stream_id.is_empty() {
+        return Err("stream_id can't be empty".to_string());
+    }
+    reqwest::Url::parse(&url).map_err(|e| format!("invalid URL: {e}"))?;
+    let jar = cookies.for_request(use_cookies, session.as_deref())?;
+    let client = crate::fetch_client(crate::DEFAULT_MAX_REDIRECTS, jar.as_ref(), proxy_state.url().as_deref())?;
+
+    let mut streams = state.streams.lock().unwrap();
+    if streams.contains_key(&stream_id) {
+        return Err(format!("Stream {stream_id} is already open"));
+    }
+    if streams.len() >= MAX_STREAMS {
+        return Err(format!("At most {MAX_STREAMS} event streams can be open at once"));
+    }
+    let generation = {
+        let mut next = state.next_generation.lock().unwrap();
+        *next += 1;
+        *next
+    };
+    let stream = Stream {
+        app,
+        label: window.label().to_string(),
+        client,
+        url,
+        headers: headers.unwrap_or_default(),
+        id: stream_id.clone(),
+    };
+    let task = tauri::async_runtime::spawn(async move {
+        let reason = stream.run().await;
+        // Waits for start_sse to finish registering this task.
+        let state = stream.app.state::<SseState>();
+        let mut streams = state.streams.lock().unwrap();
+        if streams.get(&stream.id).is_some_and(|(g, _)| *g == generation) {
+            streams.remove(&stream.id);
+        }
+        drop(streams);
+        let _ = stream.app.emit_to(stream.label.as_str(), "sse-closed", SseClosed { stream_id: &stream.id, reason });
+    });
+    streams.insert(stream_id, (generation, task));
+    Ok(())
+}
+
+/// Close a stream opened with `start_sse`. False when it isn't open.
+#[tauri::command]
+pub fn stop_sse(state: tauri::State<'_, SseState>, stream_id: String) -> bool {
+    match state.streams.lock().unwrap().remove(stream_id.trim()) {
+        Some((_, task)) => {
+            task.abort();
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &[u8]) -> (Parser, Vec<Event>, Result<(), String>) {
+        let mut parser = Parser::default();
+        let mut events = Vec::new();
+        let result = parser.feed(input, &mut events);
+        (parser, events, result)
+    }
+
+    #[test]
+    fn huge_retry_is_clamped() {
+        let (parser, _, result) = parse(b"retry: 18446744073709551615\n\n");
+        assert!(result.is_ok());
+        assert_eq!(parser.retry, Some(MAX_RETRY));
+        for failures in 0..=MAX_RECONNECTS + 1 {
+            assert!(backoff(MAX_RETRY, failures) <= MAX_RETRY);
+        }
+        assert_eq!(backoff(Duration::from_secs(1), 3), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn endless_line_is_refused() {
+        let (_, _, result) = parse(&vec![b'a'; MAX_LINE_BYTES + 1]);
+        assert!(result.is_err());
+        let mut many = Vec::new();
+        for _ in 0..=MAX_EVENT_BYTES / MAX_LINE_BYTES {
+            many.extend(b"data: ");
+            many.extend(vec![b'a'; MAX_LINE_BYTES - 16]);
+            many.push(b'\n');
+        }
+        assert!(parse(&many).2.is_err());
+    }
+
+    #[test]
+    fn events_are_split_on_blank_lines() {
+        let (parser, events, _) = parse(b"id: 4\nevent: sold\ndata: a\r\ndata: b\n\n: ping\ndata: c\n\n");
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[0].event.as_str(), events[0].data.as_str()), ("sold", "a\nb"));
+        assert_eq!((events[1].event.as_str(), events[1].data.as_str()), ("message", "c"));
+        assert_eq!(parser.last_id.as_deref(), Some("4"));
+    }
+}
@@ -0,0 +1,47 @@
+// ── Local JSON store ───────────────────────────────────────────────────────
+// Small settings/state files that live in the app data directory.
+// User data lives in Supabase; this is only for desktop-side configuration
+// that the Rust layer owns (webhook registrations, presets, etc.).
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::PathBuf;
+use tauri::Manager;
+
+/// Path of a file inside the app data directory (the directory is created).
+pub fn data_path(app: &tauri::AppHandle, file_name: &str) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("app data dir: {e}"))?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("create {}: {e}", dir.display()))?;
+    Ok(dir.join(file_name))
+}
+
+fn store_path(app: &tauri::AppHandle, name: &str) -> Result<PathBuf, String> {
+    data_path(app, &format!("{name}.json"))
+}
+
+/// Load `<app_data>/<name>.json`, falling back to the default when the file
+/// is missing or unreadable (a corrupt settings file must never block startup).
+pub fn load<T: DeserializeOwned + Default>(app: &tauri::AppHandle, name: &str) -> T {
+    let Ok(path) = store_path(app, name) else {
+        return T::default();
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(raw) => serde_json::from_str(&raw).unwrap_or_else(|e| {
+            log::warn!("ignoring unreadable store {}: {e}", path.display());
+            T::default()
+        }),
+        Err(_) => T::default(),
+    }
+}
+
+/// Write `<app_data>/<name>.json` atomically (temp file + rename).
+pub fn save<T: Serialize>(app: &tauri::AppHandle, name: &str, value: &T) -> Result<(), String> {
+    let path = store_path(app, name)?;
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| format!("write {}: {e}", tmp.display()))?;
+    std::fs::rename(&tmp, &path).map_err(|e| format!("rename {}: {e}", path.display()))?;
+    Ok(())
+}
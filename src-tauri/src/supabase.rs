@@ -0,0 +1,60 @@
+// ── Supabase REST access ───────────────────────────────────────────────────
+// Almost all database access happens in the frontend. The few commands that
+// need the database themselves (SKU sequences, team sync) are handed the
+// signed-in session and talk to PostgREST directly, so row-level security
+// applies exactly as it does for the frontend.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+/// The signed-in Supabase session, passed in by the frontend.
+#[derive(Clone, Deserialize)]
+pub struct SupabaseSession {
+    url: String,
+    anon_key: String,
+    access_token: String,
+}
+
+impl SupabaseSession {
+    /// A request to `/rest/v1/<path>` carrying the session's credentials.
+    pub fn rest(&self, client: &reqwest::Client, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.authed(client, method, &format!("rest/v1/{path}"))
+    }
+
+    fn authed(&self, client: &reqwest::Client, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        client
+            .request(method, format!("{}/{path}", self.url.trim_end_matches('/')))
+            .header("apikey", &self.anon_key)
+            .bearer_auth(&self.access_token)
+    }
+
+    /// The signed-in user's id.
+    pub async fn user_id(&self, client: &reqwest::Client) -> Result<String, String> {
+        let user = send(self.authed(client, reqwest::Method::GET, "auth/v1/user")).await?;
+        user.get("id")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| "Supabase session has no user".to_string())
+    }
+}
+
+pub fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(15))
+        .build()
+        .map_err(|e| format!("client build: {e}"))
+}
+
+/// Send `req` and parse the JSON response (Null for an empty body).
+pub async fn send(req: reqwest::RequestBuilder) -> Result<Value, String> {
+    let resp = req.send().await.map_err(|e| format!("request: {e}"))?;
+    let status = resp.status();
+    let body = resp.text().await.map_err(|e| format!("body: {e}"))?;
+    if !status.is_success() {
+        return Err(format!("Supabase returned {}: {body}", status.as_u16()));
+    }
+    if body.trim().is_empty() {
+        return Ok(Value::Null);
+    }
+    serde_json::from_str(&body).map_err(|e| format!("invalid response: {e}"))
+}
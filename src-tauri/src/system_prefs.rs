@@ -0,0 +1,198 @@
+// ── OS appearance & accessibility preferences ──────────────────────────────
+// Colour scheme, high contrast, reduced motion and accent colour as the OS
+// reports them, for a frontend that can't read them reliably from the
+// webview on every platform. Sources: the registry on Windows, `defaults`
+// on macOS, and GNOME/GTK settings via `gsettings` on Linux. None of these
+// give us a change notification without native bindings, so a job polls and
+// emits "system-preferences-changed" when anything differs.
+//
+// The main window is created from setup (not tauri.conf.json) so its
+// native background matches the scheme before the page paints, avoiding a
+// white flash on dark-mode startup. The last reading is kept so windows
+// opened later (the Depop login) start with the same background.
+
+use serde::Serialize;
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::window::Color;
+use tauri::{Emitter, Manager};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Window backgrounds before the page loads; the dark one is the app's
+/// `--bg-dark`.
+const DARK_BACKGROUND: Color = Color(0x0a, 0x0a, 0x0a, 0xff);
+const LIGHT_BACKGROUND: Color = Color(0xff, 0xff, 0xff, 0xff);
+
+#[derive(Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+#[derive(Clone, Serialize, PartialEq, Eq, Default)]
+pub struct SystemPreferences {
+    /// None when the OS doesn't express a preference.
+    pub color_scheme: Option<ColorScheme>,
+    pub high_contrast: bool,
+    pub reduced_motion: bool,
+    /// `#rrggbb`.
+    pub accent_color: Option<String>,
+}
+
+impl SystemPreferences {
+    /// Native background for windows; dark unless the OS asks for light.
+    pub fn background(&self) -> Color {
+        match self.color_scheme {
+            Some(ColorScheme::Light) => LIGHT_BACKGROUND,
+            _ => DARK_BACKGROUND,
+        }
+    }
+}
+
+/// The latest reading, kept current by the watch job.
+pub struct SystemPrefsState {
+    current: Mutex<SystemPreferences>,
+}
+
+impl SystemPrefsState {
+    pub fn new(initial: SystemPreferences) -> Self {
+        SystemPrefsState { current: Mutex::new(initial) }
+    }
+}
+
+/// Background for a window opened now.
+pub fn window_background(app: &tauri::AppHandle) -> Color {
+    app.state::<SystemPrefsState>().current.lock().unwrap().background()
+}
+
+/// Trimmed stdout of a successful command.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    let out = cmd.output().ok().filter(|o| o.status.success())?;
+    Some(String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+#[cfg(target_os = "windows")]
+pub fn read() -> SystemPreferences {
+    // `reg query` prints "    Name    REG_TYPE    value".
+    fn reg(key: &str, name: &str) -> Option<String> {
+        let out = output("reg", &["query", key, "/v", name])?;
+        let line = out.lines().find(|l| l.trim_start().starts_with(name))?;
+        line.split_whitespace().nth(2).map(str::to_string)
+    }
+    fn dword(key: &str, name: &str) -> Option<u32> {
+        u32::from_str_radix(reg(key, name)?.trim_start_matches("0x"), 16).ok()
+    }
+    let personalize = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize";
+    let color_scheme = dword(personalize, "AppsUseLightTheme").map(|light| match light {
+        0 => ColorScheme::Dark,
+        _ => ColorScheme::Light,
+    });
+    // HCF_HIGHCONTRASTON is bit 0 of the flags string.
+    let high_contrast = reg(r"HKCU\Control Panel\Accessibility\HighContrast", "Flags")
+        .and_then(|f| f.parse::<u32>().ok())
+        .is_some_and(|f| f & 1 == 1);
+    let reduced_motion = reg(r"HKCU\Control Panel\Desktop\WindowMetrics", "MinAnimate").as_deref() == Some("0");
+    // AccentColor is 0xAABBGGRR.
+    let accent_color = dword(r"HKCU\Software\Microsoft\Windows\DWM", "AccentColor")
+        .map(|c| format!("#{:02x}{:02x}{:02x}", c & 0xff, (c >> 8) & 0xff, (c >> 16) & 0xff));
+    SystemPreferences { color_scheme, high_contrast, reduced_motion, accent_color }
+}
+
+#[cfg(target_os = "macos")]
+pub fn read() -> SystemPreferences {
+    let defaults = |domain: &str, key: &str| output("defaults", &["read", domain, key]);
+    // AppleInterfaceStyle is only present in dark mode.
+    let color_scheme = Some(match defaults("-g", "AppleInterfaceStyle").as_deref() {
+        Some("Dark") => ColorScheme::Dark,
+        _ => ColorScheme::Light,
+    });
+    let high_contrast = defaults("com.apple.universalaccess", "increaseContrast").as_deref() == Some("1");
+    let reduced_motion = defaults("com.apple.universalaccess", "reduceMotion").as_deref() == Some("1");
+    // Absent means the default (multicolour, shown as blue).
+    let accent = match defaults("-g", "AppleAccentColor").as_deref() {
+        Some("-1") => "#989898",
+        Some("0") => "#e0383e",
+        Some("1") => "#f7821b",
+        Some("2") => "#fcb827",
+        Some("3") => "#62ba46",
+        Some("5") => "#953d96",
+        Some("6") => "#f74f9e",
+        _ => "#007aff",
+    };
+    SystemPreferences { color_scheme, high_contrast, reduced_motion, accent_color: Some(accent.to_string()) }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub fn read() -> SystemPreferences {
+    // gsettings prints strings quoted: 'prefer-dark'.
+    let get = |schema: &str, key: &str| {
+        output("gsettings", &["get", schema, key]).map(|v| v.trim_matches('\'').to_string())
+    };
+    let iface = "org.gnome.desktop.interface";
+    let gtk_theme = get(iface, "gtk-theme").unwrap_or_default().to_lowercase();
+    let color_scheme = match get(iface, "color-scheme").as_deref() {
+        Some("prefer-dark") => Some(ColorScheme::Dark),
+        Some("prefer-light") => Some(ColorScheme::Light),
+        _ if gtk_theme.ends_with("-dark") || gtk_theme.ends_with(":dark") => Some(ColorScheme::Dark),
+        _ if !gtk_theme.is_empty() => Some(ColorScheme::Light),
+        _ => None,
+    };
+    let high_contrast = get("org.gnome.desktop.a11y.interface", "high-contrast").as_deref() == Some("true")
+        || gtk_theme.contains("highcontrast");
+    let reduced_motion = get(iface, "enable-animations").as_deref() == Some("false");
+    // libadwaita's accent palette (GNOME 47+).
+    let accent_color = get(iface, "accent-color").and_then(|name| {
+        let hex = match name.as_str() {
+            "blue" => "#3584e4",
+            "teal" => "#2190a4",
+            "green" => "#3a944a",
+            "yellow" => "#c88800",
+            "orange" => "#ed5b00",
+            "red" => "#e62d42",
+            "pink" => "#d56199",
+            "purple" => "#9141ac",
+            "slate" => "#6f8396",
+            _ => return None,
+        };
+        Some(hex.to_string())
+    });
+    SystemPreferences { color_scheme, high_contrast, reduced_motion, accent_color }
+}
+
+/// Poll the OS and emit "system-preferences-changed" (and repaint window
+/// backgrounds) whenever something changes.
+pub fn start_watch_job(app: tauri::AppHandle, initial: SystemPreferences) {
+    tauri::async_runtime::spawn(async move {
+        let mut last = initial;
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let Ok(now) = tauri::async_runtime::spawn_blocking(read).await else {
+                continue;
+            };
+            if now == last {
+                continue;
+            }
+            for window in app.webview_windows().values() {
+                let _ = window.set_background_color(Some(now.background()));
+            }
+            *app.state::<SystemPrefsState>().current.lock().unwrap() = now.clone();
+            let _ = app.emit("system-preferences-changed", &now);
+            last = now;
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_system_preferences() -> Result<SystemPreferences, String> {
+    tauri::async_runtime::spawn_blocking(read).await.map_err(|e| e.to_string())
+}
@@ -0,0 +1,129 @@
+// ── Tasks ──────────────────────────────────────────────────────────────────
+// A local to-do list ("ship the Levi's before Friday", "check auction lot
+// 14"). Tasks come from the UI or from a `create-task` inbound webhook, and
+// each new one raises a desktop notification. They're kept on this device
+// only.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::Emitter;
+
+const STORE_NAME: &str = "tasks";
+const MAX_TITLE_CHARS: usize = 200;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// `YYYY-MM-DD`.
+    #[serde(default)]
+    pub due_date: Option<String>,
+    /// Where it came from: "manual" or "webhook:<name>".
+    pub source: String,
+    pub created_at: u64,
+    #[serde(default)]
+    pub done_at: Option<u64>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct NewTask {
+    pub title: String,
+    pub notes: Option<String>,
+    pub due_date: Option<String>,
+}
+
+impl NewTask {
+    fn into_task(self, source: &str) -> Result<Task, String> {
+        let title = self.title.trim();
+        if title.is_empty() {
+            return Err("A task needs a title".to_string());
+        }
+        if title.chars().count() > MAX_TITLE_CHARS {
+            return Err(format!("Task titles are limited to {MAX_TITLE_CHARS} characters"));
+        }
+        if let Some(due) = &self.due_date {
+            if !crate::custom_fields::valid_date(due) {
+                return Err("Due dates must be YYYY-MM-DD".to_string());
+            }
+        }
+        Ok(Task {
+            id: hex::encode(rand::random::<[u8; 8]>()),
+            title: title.to_string(),
+            notes: self.notes.filter(|n| !n.trim().is_empty()),
+            due_date: self.due_date,
+            source: source.to_string(),
+            created_at: crate::unix_now(),
+            done_at: None,
+        })
+    }
+}
+
+pub struct TaskState {
+    tasks: Mutex<BTreeMap<String, Task>>,
+}
+
+impl TaskState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        TaskState { tasks: Mutex::new(crate::store::load(app, STORE_NAME)) }
+    }
+}
+
+/// Add a task from `source`, notify and tell the UI.
+pub fn add(app: &tauri::AppHandle, state: &TaskState, new: NewTask, source: &str) -> Result<Task, String> {
+    let task = new.into_task(source)?;
+    let mut tasks = state.tasks.lock().unwrap();
+    tasks.insert(task.id.clone(), task.clone());
+    crate::store::save(app, STORE_NAME, &*tasks)?;
+    drop(tasks);
+    crate::notifications::show(app, "New task", &task.title);
+    let _ = app.emit("task-added", &task);
+    Ok(task)
+}
+
+/// Open tasks first (soonest due, undated last), then done ones.
+#[tauri::command]
+pub fn list_tasks(state: tauri::State<'_, TaskState>) -> Vec<Task> {
+    let mut list: Vec<Task> = state.tasks.lock().unwrap().values().cloned().collect();
+    list.sort_by(|a, b| {
+        (a.done_at.is_some(), a.due_date.is_none(), &a.due_date, a.created_at).cmp(&(
+            b.done_at.is_some(),
+            b.due_date.is_none(),
+            &b.due_date,
+            b.created_at,
+        ))
+    });
+    list
+}
+
+#[tauri::command]
+pub fn create_task(app: tauri::AppHandle, state: tauri::State<'_, TaskState>, task: NewTask) -> Result<Task, String> {
+    add(&app, &state, task, "manual")
+}
+
+#[tauri::command]
+pub fn set_task_done(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, TaskState>,
+    id: String,
+    done: bool,
+) -> Result<Task, String> {
+    let mut tasks = state.tasks.lock().unwrap();
+    let task = tasks.get_mut(&id).ok_or_else(|| format!("No task {id}"))?;
+    task.done_at = done.then(crate::unix_now);
+    let task = task.clone();
+    crate::store::save(&app, STORE_NAME, &*tasks)?;
+    Ok(task)
+}
+
+#[tauri::command]
+pub fn delete_task(app: tauri::AppHandle, state: tauri::State<'_, TaskState>, id: String) -> Result<(), String> {
+    let mut tasks = state.tasks.lock().unwrap();
+    if tasks.remove(&id).is_none() {
+        return Err(format!("No task {id}"));
+    }
+    crate::store::save(&app, STORE_NAME, &*tasks)
+}
@@ -0,0 +1,528 @@
+// ── Yearly tax summary ─────────────────────────────────────────────────────
+// Gross payments, transactions, refunds and fees per marketplace for a
+// calendar year, from the synced sales the frontend passes in, checked
+// against the reporting thresholds of a jurisdiction (US 1099-K, UK
+// trading allowance and platform reporting).
+//
+// Marketplaces report on the payout date, so that is the default basis; the
+// sale date is available for accrual-style books. Either way the summary
+// shows the sales that fall in the year on one basis but not the other,
+// since that difference is what makes a 1099 "not match" the sales screen.
+// Sales without a payout date count on their sale date and are flagged.
+//
+// Thresholds are data: seeded from tax_thresholds.json and replaceable from
+// settings, each with yearly periods so a law change is a new period rather
+// than a code change. Amounts are taken to be in the threshold's currency;
+// no conversion is done. Nothing here is tax advice.
+
+use crate::fees::round_cents;
+use crate::pdf_layout::{self, PaperSize, PdfDocument};
+use base64::Engine;
+use pdf_writer::Content;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+const STORE_NAME: &str = "tax_thresholds";
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThresholdScope {
+    /// Each marketplace reports on its own.
+    PerMarketplace,
+    /// All marketplaces together.
+    Combined,
+}
+
+/// Whether every limit set in a period has to be reached, or any one.
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Combine {
+    All,
+    Any,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ThresholdPeriod {
+    pub from_year: u32,
+    #[serde(default)]
+    pub gross_at_least: Option<f64>,
+    #[serde(default)]
+    pub transactions_at_least: Option<u32>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TaxThreshold {
+    pub jurisdiction: String,
+    pub name: String,
+    pub currency: String,
+    pub scope: ThresholdScope,
+    pub combine: Combine,
+    pub periods: Vec<ThresholdPeriod>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl TaxThreshold {
+    fn period(&self, year: u32) -> Option<&ThresholdPeriod> {
+        self.periods.iter().filter(|p| p.from_year <= year).max_by_key(|p| p.from_year)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.jurisdiction.trim().is_empty() || self.name.trim().is_empty() {
+            return Err("A threshold needs a jurisdiction and a name".to_string());
+        }
+        if self.periods.is_empty() {
+            return Err("A threshold needs at least one period".to_string());
+        }
+        for p in &self.periods {
+            if p.gross_at_least.is_none() && p.transactions_at_least.is_none() {
+                return Err(format!("The period from {} sets no limit", p.from_year));
+            }
+            if p.gross_at_least.is_some_and(|g| !g.is_finite() || g < 0.0) {
+                return Err(format!("The period from {} has an invalid amount", p.from_year));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn seed_thresholds() -> BTreeMap<String, TaxThreshold> {
+    serde_json::from_str(include_str!("tax_thresholds.json")).expect("embedded tax thresholds are valid JSON")
+}
+
+pub struct TaxState {
+    /// Seeded thresholds the user hasn't replaced still come from the seed,
+    /// so an app update can correct them.
+    thresholds: Mutex<BTreeMap<String, TaxThreshold>>,
+}
+
+impl TaxState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        let mut thresholds = seed_thresholds();
+        let saved: BTreeMap<String, TaxThreshold> = crate::store::load(app, STORE_NAME);
+        thresholds.extend(saved);
+        TaxState { thresholds: Mutex::new(thresholds) }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TaxSale {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub platform: String,
+    pub sale_price: f64,
+    /// Shipping the buyer paid, part of the gross payment.
+    #[serde(default)]
+    pub shipping_charged: f64,
+    #[serde(default)]
+    pub platform_fees: f64,
+    /// Amount refunded to the buyer.
+    #[serde(default)]
+    pub refunded: f64,
+    /// ISO-8601 (`sales.sold_at`).
+    pub sold_at: String,
+    /// ISO-8601; when the marketplace paid the seller.
+    #[serde(default)]
+    pub paid_out_at: Option<String>,
+}
+
+impl TaxSale {
+    fn gross(&self) -> f64 {
+        self.sale_price + self.shipping_charged
+    }
+
+    fn year(&self, basis: DateBasis) -> Option<u32> {
+        let date = match basis {
+            DateBasis::Payout => self.paid_out_at.as_deref().unwrap_or(&self.sold_at),
+            DateBasis::Sale => &self.sold_at,
+        };
+        date.get(..4)?.parse().ok()
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DateBasis {
+    /// Cash basis: the year the marketplace paid out (what platforms report).
+    #[default]
+    Payout,
+    /// Accrual basis: the year of the sale.
+    Sale,
+}
+
+impl DateBasis {
+    fn other(self) -> Self {
+        match self {
+            DateBasis::Payout => DateBasis::Sale,
+            DateBasis::Sale => DateBasis::Payout,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            DateBasis::Payout => "payout date",
+            DateBasis::Sale => "sale date",
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Default)]
+pub struct Totals {
+    gross: f64,
+    transactions: u32,
+    refunds: f64,
+    fees: f64,
+    net: f64,
+}
+
+impl Totals {
+    fn add(&mut self, sale: &TaxSale) {
+        self.gross += sale.gross();
+        self.transactions += 1;
+        self.refunds += sale.refunded;
+        self.fees += sale.platform_fees;
+    }
+
+    fn rounded(mut self) -> Self {
+        self.gross = round_cents(self.gross);
+        self.refunds = round_cents(self.refunds);
+        self.fees = round_cents(self.fees);
+        self.net = round_cents(self.gross - self.refunds - self.fees);
+        self
+    }
+}
+
+#[derive(Serialize, Default)]
+pub struct Shifted {
+    count: u32,
+    gross: f64,
+    /// The sales involved, for those that have an id.
+    sale_ids: Vec<String>,
+}
+
+impl Shifted {
+    fn add(&mut self, sale: &TaxSale) {
+        self.count += 1;
+        self.gross += sale.gross();
+        self.sale_ids.extend(sale.id.clone());
+    }
+}
+
+/// Sales counted in the year on one basis but not the other.
+#[derive(Serialize)]
+pub struct BasisDiscrepancy {
+    /// Sold in the year, paid out in another (usually late December sales).
+    sold_in_year_paid_out_other_year: Shifted,
+    /// Paid out in the year, sold in another.
+    paid_out_in_year_sold_other_year: Shifted,
+    /// Gross on the chosen basis minus gross on the other.
+    gross_difference: f64,
+    other_basis_totals: Totals,
+}
+
+#[derive(Serialize)]
+pub struct ThresholdResult {
+    id: String,
+    name: String,
+    currency: String,
+    scope: ThresholdScope,
+    gross_at_least: Option<f64>,
+    transactions_at_least: Option<u32>,
+    /// Whether the limits are reached (by any marketplace, for per-marketplace thresholds).
+    met: bool,
+    /// Marketplaces that reach it on their own (per-marketplace thresholds).
+    marketplaces: Vec<String>,
+    note: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TaxSummary {
+    year: u32,
+    jurisdiction: String,
+    basis: DateBasis,
+    marketplaces: BTreeMap<String, Totals>,
+    totals: Totals,
+    discrepancy: BasisDiscrepancy,
+    thresholds: Vec<ThresholdResult>,
+    /// Sales without a payout date, counted on their sale date.
+    missing_payout_dates: u32,
+}
+
+fn reaches(period: &ThresholdPeriod, combine: Combine, totals: &Totals) -> bool {
+    let checks = [
+        period.gross_at_least.map(|g| totals.gross >= g),
+        period.transactions_at_least.map(|t| totals.transactions >= t),
+    ];
+    let mut set = checks.into_iter().flatten().peekable();
+    set.peek().is_some()
+        && match combine {
+            Combine::All => set.all(|met| met),
+            Combine::Any => set.any(|met| met),
+        }
+}
+
+fn summarize(state: &TaxState, year: u32, jurisdiction: &str, basis: DateBasis, sales: &[TaxSale]) -> Result<TaxSummary, String> {
+    let jurisdiction = jurisdiction.trim().to_ascii_lowercase();
+    let thresholds: Vec<(String, TaxThreshold)> = state
+        .thresholds
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, t)| t.jurisdiction.eq_ignore_ascii_case(&jurisdiction))
+        .map(|(id, t)| (id.clone(), t.clone()))
+        .collect();
+    if thresholds.is_empty() {
+        return Err(format!("No reporting thresholds for {jurisdiction:?}"));
+    }
+
+    let mut marketplaces: BTreeMap<String, Totals> = BTreeMap::new();
+    let mut totals = Totals::default();
+    let mut other = Totals::default();
+    let mut sold_not_paid = Shifted::default();
+    let mut paid_not_sold = Shifted::default();
+    let mut missing_payout_dates = 0;
+    for sale in sales {
+        let this_year = sale.year(basis) == Some(year);
+        let other_year = sale.year(basis.other()) == Some(year);
+        if this_year {
+            marketplaces.entry(sale.platform.to_lowercase()).or_default().add(sale);
+            totals.add(sale);
+            if sale.paid_out_at.is_none() {
+                missing_payout_dates += 1;
+            }
+        }
+        if other_year {
+            other.add(sale);
+        }
+        let (sold, paid) = (sale.year(DateBasis::Sale) == Some(year), sale.year(DateBasis::Payout) == Some(year));
+        if sold && !paid {
+            sold_not_paid.add(sale);
+        } else if paid && !sold {
+            paid_not_sold.add(sale);
+        }
+    }
+    let marketplaces: BTreeMap<String, Totals> = marketplaces.into_iter().map(|(m, t)| (m, t.rounded())).collect();
+    let totals = totals.rounded();
+    let other = other.rounded();
+    sold_not_paid.gross = round_cents(sold_not_paid.gross);
+    paid_not_sold.gross = round_cents(paid_not_sold.gross);
+
+    let thresholds = thresholds
+        .into_iter()
+        .filter_map(|(id, t)| {
+            let period = t.period(year)?.clone();
+            let reached: Vec<String> = match t.scope {
+                ThresholdScope::PerMarketplace => marketplaces
+                    .iter()
+                    .filter(|(_, totals)| reaches(&period, t.combine, totals))
+                    .map(|(m, _)| m.clone())
+                    .collect(),
+                ThresholdScope::Combined => Vec::new(),
+            };
+            let met = match t.scope {
+                ThresholdScope::PerMarketplace => !reached.is_empty(),
+                ThresholdScope::Combined => reaches(&period, t.combine, &totals),
+            };
+            Some(ThresholdResult {
+                id,
+                name: t.name,
+                currency: t.currency,
+                scope: t.scope,
+                gross_at_least: period.gross_at_least,
+                transactions_at_least: period.transactions_at_least,
+                met,
+                marketplaces: reached,
+                note: t.note,
+            })
+        })
+        .collect();
+
+    Ok(TaxSummary {
+        year,
+        jurisdiction,
+        basis,
+        discrepancy: BasisDiscrepancy {
+            sold_in_year_paid_out_other_year: sold_not_paid,
+            paid_out_in_year_sold_other_year: paid_not_sold,
+            gross_difference: round_cents(totals.gross - other.gross),
+            other_basis_totals: other,
+        },
+        marketplaces,
+        totals,
+        thresholds,
+        missing_payout_dates,
+    })
+}
+
+#[tauri::command]
+pub fn get_tax_summary(
+    state: tauri::State<'_, TaxState>,
+    year: u32,
+    jurisdiction: String,
+    basis: Option<DateBasis>,
+    sales: Vec<TaxSale>,
+) -> Result<TaxSummary, String> {
+    summarize(&state, year, &jurisdiction, basis.unwrap_or_default(), &sales)
+}
+
+fn money(amount: f64) -> String {
+    format!("{amount:.2}")
+}
+
+fn render(summary: &TaxSummary, paper: PaperSize) -> Vec<u8> {
+    use pdf_layout::{draw_text, FONT, FONT_BOLD};
+
+    let page = paper.dimensions();
+    let margin = 48.0;
+    let columns = [margin, margin + 150.0, margin + 230.0, margin + 310.0, margin + 390.0];
+    let mut doc = PdfDocument::new();
+    let mut content = Content::new();
+    let mut y = page.1 - margin;
+    let line = |content: &mut Content, y: &mut f32, font, size: f32, text: &str| {
+        draw_text(content, font, size, margin, *y, &pdf_layout::fit_text(text, size, page.0 - 2.0 * margin));
+        *y -= size * 1.6;
+    };
+
+    let title = format!("Tax summary {} ({})", summary.year, summary.jurisdiction.to_uppercase());
+    line(&mut content, &mut y, FONT_BOLD, 16.0, &title);
+    line(&mut content, &mut y, FONT, 10.0, &format!("Counted by {}", summary.basis.label()));
+    y -= 8.0;
+
+    let header = ["Marketplace", "Sales", "Gross", "Refunds", "Fees"];
+    for (x, h) in columns.iter().zip(header) {
+        draw_text(&mut content, FONT_BOLD, 10.0, *x, y, h);
+    }
+    y -= 16.0;
+    let rows = summary.marketplaces.iter().map(|(m, t)| (m.as_str(), t)).chain([("Total", &summary.totals)]);
+    for (name, t) in rows {
+        if y < margin + 40.0 {
+            doc.add_page(page, std::mem::replace(&mut content, Content::new()));
+            y = page.1 - margin;
+        }
+        let font = if name == "Total" { FONT_BOLD } else { FONT };
+        let cells = [
+            pdf_layout::fit_text(name, 10.0, 140.0),
+            t.transactions.to_string(),
+            money(t.gross),
+            money(t.refunds),
+            money(t.fees),
+        ];
+        for (x, cell) in columns.iter().zip(&cells) {
+            draw_text(&mut content, font, 10.0, *x, y, cell);
+        }
+        y -= 16.0;
+    }
+    y -= 12.0;
+
+    line(&mut content, &mut y, FONT_BOLD, 12.0, "Reporting thresholds");
+    for t in &summary.thresholds {
+        let limits = [
+            t.gross_at_least.map(|g| format!("{} {}", money(g), t.currency)),
+            t.transactions_at_least.map(|n| format!("{n} sales")),
+        ];
+        let limits: Vec<String> = limits.into_iter().flatten().collect();
+        let status = match (t.met, t.marketplaces.is_empty()) {
+            (false, _) => "not reached".to_string(),
+            (true, true) => "reached".to_string(),
+            (true, false) => format!("reached on {}", t.marketplaces.join(", ")),
+        };
+        line(&mut content, &mut y, FONT, 10.0, &format!("{} ({}): {status}", t.name, limits.join(" / ")));
+        if let Some(note) = &t.note {
+            line(&mut content, &mut y, FONT, 8.0, note);
+        }
+    }
+    y -= 8.0;
+
+    let d = &summary.discrepancy;
+    line(&mut content, &mut y, FONT_BOLD, 12.0, "Payout date vs sale date");
+    let other = summary.basis.other().label();
+    let lines = [
+        format!(
+            "Gross by {}: {}; by {other}: {} (difference {})",
+            summary.basis.label(),
+            money(summary.totals.gross),
+            money(d.other_basis_totals.gross),
+            money(d.gross_difference)
+        ),
+        format!(
+            "Sold in {} but paid out in another year: {} sales, {}",
+            summary.year,
+            d.sold_in_year_paid_out_other_year.count,
+            money(d.sold_in_year_paid_out_other_year.gross)
+        ),
+        format!(
+            "Paid out in {} but sold in another year: {} sales, {}",
+            summary.year,
+            d.paid_out_in_year_sold_other_year.count,
+            money(d.paid_out_in_year_sold_other_year.gross)
+        ),
+    ];
+    for text in &lines {
+        line(&mut content, &mut y, FONT, 10.0, text);
+    }
+    if summary.missing_payout_dates > 0 {
+        let text = format!("{} sales have no payout date and were counted on their sale date.", summary.missing_payout_dates);
+        line(&mut content, &mut y, FONT, 10.0, &text);
+    }
+    y -= 8.0;
+    line(&mut content, &mut y, FONT, 8.0, "Generated by FlipTools from your synced sales. Not tax advice.");
+
+    doc.add_page(page, content);
+    doc.finish()
+}
+
+#[derive(Serialize)]
+pub struct TaxSummaryPdf {
+    mime: &'static str,
+    /// File contents, base64-encoded.
+    data: String,
+}
+
+/// The same summary as `get_tax_summary`, as a printable PDF.
+#[tauri::command]
+pub fn generate_tax_summary_pdf(
+    state: tauri::State<'_, TaxState>,
+    year: u32,
+    jurisdiction: String,
+    basis: Option<DateBasis>,
+    sales: Vec<TaxSale>,
+    paper: Option<PaperSize>,
+) -> Result<TaxSummaryPdf, String> {
+    crate::privacy::ensure_off("generate the tax summary PDF")?;
+    let summary = summarize(&state, year, &jurisdiction, basis.unwrap_or_default(), &sales)?;
+    Ok(TaxSummaryPdf {
+        mime: "application/pdf",
+        data: base64::engine::general_purpose::STANDARD.encode(render(&summary, paper.unwrap_or_default())),
+    })
+}
+
+#[tauri::command]
+pub fn get_tax_thresholds(state: tauri::State<'_, TaxState>) -> BTreeMap<String, TaxThreshold> {
+    state.thresholds.lock().unwrap().clone()
+}
+
+/// Add a threshold or replace the one with the same id (including seeded ones).
+#[tauri::command]
+pub fn set_tax_threshold(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, TaxState>,
+    id: String,
+    threshold: TaxThreshold,
+) -> Result<BTreeMap<String, TaxThreshold>, String> {
+    let id = id.trim().to_string();
+    if id.is_empty() {
+        return Err("Threshold id can't be empty".to_string());
+    }
+    threshold.validate()?;
+    let seed = seed_thresholds();
+    let mut thresholds = state.thresholds.lock().unwrap();
+    thresholds.insert(id, threshold);
+    // Only what differs from the seed is saved.
+    let saved: BTreeMap<&String, &TaxThreshold> = thresholds
+        .iter()
+        .filter(|(id, t)| seed.get(*id).map_or(true, |s| serde_json::to_value(s).ok() != serde_json::to_value(t).ok()))
+        .collect();
+    crate::store::save(&app, STORE_NAME, &saved)?;
+    Ok(thresholds.clone())
+}
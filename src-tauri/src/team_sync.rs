@@ -0,0 +1,625 @@
+// ── Team sync ──────────────────────────────────────────────────────────────
+// Two installs working the same physical inventory from separate accounts
+// exchange changes as delta files (Dropbox, USB stick, ...), no server
+// involved. Database triggers (migration 018) log every write to the synced
+// tables with the changed columns and the writing device.
+//
+// `export_changes` writes this install's changes since a cursor to a file.
+// `import_changes` applies another install's file field by field, last writer
+// wins: an incoming field is skipped when this database changed the same
+// field later (equal timestamps go to the higher device id, so both sides
+// settle the same way), and the losing value is recorded in
+// `sync_conflicts` for review rather than dropped. Imported writes carry the
+// originating device's id, so they are never exported back. Re-importing a
+// file is harmless: each device's last imported change id is remembered.
+
+use crate::request_context::{Finished, RequestContext};
+use crate::supabase::{self, SupabaseSession};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+const STORE_NAME: &str = "team_sync";
+const FORMAT: &str = "fliptools-delta";
+const FORMAT_VERSION: u32 = 1;
+const PAGE_SIZE: usize = 1000;
+const DEVICE_HEADER: &str = "x-fliptools-device";
+/// Tables the triggers log; a delta file can't touch anything else.
+const SYNCED_TABLES: &[&str] = &["inventory", "listings", "sales", "purchase_lots"];
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncCursors {
+    device_id: String,
+    /// Highest change id exported so far.
+    export_cursor: i64,
+    /// Device id -> highest change id imported from it.
+    imported: BTreeMap<String, i64>,
+}
+
+pub struct TeamSyncState {
+    cursors: Mutex<SyncCursors>,
+}
+
+impl TeamSyncState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        let mut cursors: SyncCursors = crate::store::load(app, STORE_NAME);
+        if cursors.device_id.is_empty() {
+            cursors.device_id = hex::encode(rand::random::<[u8; 8]>());
+            if let Err(e) = crate::store::save(app, STORE_NAME, &cursors) {
+                log::warn!("saving team sync device id: {e}");
+            }
+        }
+        TeamSyncState { cursors: Mutex::new(cursors) }
+    }
+}
+
+/// One `change_log` row.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Change {
+    id: i64,
+    table_name: String,
+    row_id: String,
+    op: String,
+    #[serde(default)]
+    fields: Option<Map<String, Value>>,
+    #[serde(default)]
+    device_id: Option<String>,
+    changed_at: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DeltaFile {
+    format: String,
+    version: u32,
+    device_id: String,
+    exported_at: u64,
+    from_cursor: i64,
+    to_cursor: i64,
+    changes: Vec<Change>,
+}
+
+/// Whether a change written at `(at, device)` beats one at `(other_at,
+/// other_device)`. Timestamps are ISO 8601 in UTC from Postgres, so they
+/// compare as strings once normalised to the same precision.
+fn newer(at: &str, device: &str, other_at: &str, other_device: &str) -> bool {
+    let (a, b) = (normalise_ts(at), normalise_ts(other_at));
+    a > b || (a == b && device > other_device)
+}
+
+/// `2026-10-16T09:30:00.12+00:00` -> `2026-10-16T09:30:00.120000`.
+fn normalise_ts(ts: &str) -> String {
+    let ts = ts.trim_end_matches('Z');
+    let ts = ts.strip_suffix("+00:00").unwrap_or(ts);
+    let (secs, frac) = ts.split_once('.').unwrap_or((ts, ""));
+    format!("{secs}.{frac:0<6}")
+}
+
+#[derive(Serialize)]
+pub struct TeamSyncStatus {
+    device_id: String,
+    export_cursor: i64,
+    imported: BTreeMap<String, i64>,
+}
+
+#[tauri::command]
+pub fn get_team_sync_status(state: tauri::State<'_, TeamSyncState>) -> TeamSyncStatus {
+    let cursors = state.cursors.lock().unwrap();
+    TeamSyncStatus {
+        device_id: cursors.device_id.clone(),
+        export_cursor: cursors.export_cursor,
+        imported: cursors.imported.clone(),
+    }
+}
+
+#[derive(Serialize)]
+pub struct ExportReport {
+    path: String,
+    changes: usize,
+    from_cursor: i64,
+    to_cursor: i64,
+}
+
+/// Write this install's changes after `since_cursor` (default: everything
+/// not yet exported) to `dest`, and advance the export cursor. Cancellable
+/// (see request_context); nothing is written until every page is read.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_changes(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    state: tauri::State<'_, TeamSyncState>,
+    session: SupabaseSession,
+    since_cursor: Option<i64>,
+    dest: String,
+    invocation_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<Finished<ExportReport>, String> {
+    crate::privacy::ensure_off("export changes")?;
+    let ctx = RequestContext::begin(&app, &window, "export_changes", invocation_id, timeout_ms)?;
+    let result = export(&app, &state, &ctx, &session, since_cursor, dest).await;
+    ctx.finish(result)
+}
+
+async fn export(
+    app: &tauri::AppHandle,
+    state: &TeamSyncState,
+    ctx: &RequestContext,
+    session: &SupabaseSession,
+    since_cursor: Option<i64>,
+    dest: String,
+) -> Result<ExportReport, String> {
+    let (device_id, from_cursor) = {
+        let cursors = state.cursors.lock().unwrap();
+        (cursors.device_id.clone(), since_cursor.unwrap_or(cursors.export_cursor))
+    };
+    let client = supabase::client()?;
+    let mut changes: Vec<Change> = Vec::new();
+    let mut cursor = from_cursor;
+    loop {
+        // Changes made here have no device (the frontend doesn't send one) or
+        // this device's id; anything else was imported.
+        let req = session.rest(&client, Method::GET, "change_log").query(&[
+            ("select", "id,table_name,row_id,op,fields,device_id,changed_at".to_string()),
+            ("id", format!("gt.{cursor}")),
+            ("or", format!("(device_id.is.null,device_id.eq.{device_id})")),
+            ("order", "id.asc".to_string()),
+            ("limit", PAGE_SIZE.to_string()),
+        ]);
+        let page = ctx.run(supabase::send(req)).await??;
+        let page: Vec<Change> = serde_json::from_value(page).map_err(|e| format!("invalid change log: {e}"))?;
+        let full = page.len() == PAGE_SIZE;
+        cursor = page.last().map_or(cursor, |c| c.id);
+        changes.extend(page);
+        if !full {
+            break;
+        }
+    }
+
+    let file = DeltaFile {
+        format: FORMAT.to_string(),
+        version: FORMAT_VERSION,
+        device_id,
+        exported_at: crate::unix_now(),
+        from_cursor,
+        to_cursor: cursor,
+        changes,
+    };
+    ctx.check()?;
+    let json = serde_json::to_string(&file).map_err(|e| e.to_string())?;
+    let path = std::path::PathBuf::from(&dest);
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, json).map_err(|e| format!("write {}: {e}", tmp.display()))?;
+    std::fs::rename(&tmp, &path).map_err(|e| format!("rename {}: {e}", path.display()))?;
+
+    let mut cursors = state.cursors.lock().unwrap();
+    cursors.export_cursor = cursors.export_cursor.max(cursor);
+    crate::store::save(app, STORE_NAME, &*cursors)?;
+    Ok(ExportReport { path: dest, changes: file.changes.len(), from_cursor, to_cursor: cursor })
+}
+
+#[derive(Default, Serialize)]
+pub struct ImportReport {
+    device_id: String,
+    applied: usize,
+    /// Already imported, or originally from this install.
+    skipped: usize,
+    /// Fields (or deletes) that lost to a newer local change.
+    conflicts: usize,
+    /// Set when the import stopped early; re-importing the same file resumes
+    /// after the last applied change.
+    error: Option<String>,
+}
+
+/// What this database did to a row at or after an incoming change.
+#[derive(Default)]
+struct LocalHistory {
+    /// Field -> (latest value, when).
+    fields: HashMap<String, (Value, String)>,
+    deleted: bool,
+}
+
+impl LocalHistory {
+    /// Fold the row's local `change_log` entries into the ones that beat
+    /// `change`. Entries from `origin` itself (earlier imports of the same
+    /// file) and older ones don't count; entries without a device were
+    /// written here.
+    fn from_rows(rows: Vec<Change>, change: &Change, origin: &str, own_device: &str) -> Self {
+        let mut history = LocalHistory::default();
+        for row in rows {
+            let device = row.device_id.as_deref().unwrap_or(own_device);
+            if device == origin || !newer(&row.changed_at, device, &change.changed_at, origin) {
+                continue;
+            }
+            if row.op == "delete" {
+                history.deleted = true;
+                continue;
+            }
+            for (field, value) in row.fields.unwrap_or_default() {
+                history.fields.insert(field, (value, row.changed_at.clone()));
+            }
+        }
+        history
+    }
+}
+
+/// Something an incoming change lost to a newer local change. `field` is
+/// "*" when the whole change lost (a delete against later edits, or edits
+/// to a row deleted here later).
+#[derive(Debug, PartialEq)]
+struct Conflict {
+    field: String,
+    /// The local value and when it was written.
+    local: Option<(Value, String)>,
+    incoming: Option<Value>,
+}
+
+/// What applying one incoming change comes to.
+#[derive(Debug, Default, PartialEq)]
+struct Plan {
+    delete: bool,
+    /// Fields to write; nothing is written when empty.
+    write: Map<String, Value>,
+    conflicts: Vec<Conflict>,
+}
+
+/// Resolve `change` against what this database did to the row since.
+/// Fields the local side also changed later lose (recorded as conflicts
+/// unless both sides wrote the same value); the rest are written.
+fn plan(change: &Change, history: LocalHistory) -> Plan {
+    let mut plan = Plan::default();
+    if change.op == "delete" {
+        if history.deleted {
+            return plan;
+        }
+        if history.fields.is_empty() {
+            plan.delete = true;
+        } else {
+            plan.conflicts.push(Conflict { field: "*".to_string(), local: None, incoming: None });
+        }
+        return plan;
+    }
+
+    let incoming = change.fields.clone().unwrap_or_default();
+    if history.deleted {
+        plan.conflicts.push(Conflict { field: "*".to_string(), local: None, incoming: Some(Value::Object(incoming)) });
+        return plan;
+    }
+    for (field, value) in incoming {
+        match history.fields.get(&field) {
+            Some((local, _)) if *local == value => {}
+            Some((local, at)) => plan.conflicts.push(Conflict {
+                field,
+                local: Some((local.clone(), at.clone())),
+                incoming: Some(value),
+            }),
+            None => {
+                plan.write.insert(field, value);
+            }
+        }
+    }
+    plan
+}
+
+struct Importer<'a> {
+    client: reqwest::Client,
+    session: &'a SupabaseSession,
+    own_device: String,
+    user_id: String,
+}
+
+impl Importer<'_> {
+    async fn local_history(&self, change: &Change, origin: &str) -> Result<LocalHistory, String> {
+        let req = self.session.rest(&self.client, Method::GET, "change_log").query(&[
+            ("select", "id,table_name,row_id,op,fields,device_id,changed_at".to_string()),
+            ("table_name", format!("eq.{}", change.table_name)),
+            ("row_id", format!("eq.{}", change.row_id)),
+            ("changed_at", format!("gte.{}", change.changed_at)),
+            ("order", "id.asc".to_string()),
+        ]);
+        let rows: Vec<Change> =
+            serde_json::from_value(supabase::send(req).await?).map_err(|e| format!("invalid change log: {e}"))?;
+        Ok(LocalHistory::from_rows(rows, change, origin, &self.own_device))
+    }
+
+    /// A write to `change`'s table, tagged with the originating device.
+    fn write(&self, method: Method, change: &Change, origin: &str) -> reqwest::RequestBuilder {
+        self.session.rest(&self.client, method, &change.table_name).header(DEVICE_HEADER, origin)
+    }
+
+    async fn record_conflict(&self, change: &Change, origin: &str, conflict: &Conflict) -> Result<(), String> {
+        let local = conflict.local.as_ref();
+        let row = json!({
+            "table_name": change.table_name,
+            "row_id": change.row_id,
+            "field": conflict.field,
+            "local_value": local.map(|(v, _)| v),
+            "local_changed_at": local.map(|(_, at)| at),
+            "incoming_value": conflict.incoming,
+            "incoming_changed_at": change.changed_at,
+            "incoming_device": origin,
+        });
+        supabase::send(self.session.rest(&self.client, Method::POST, "sync_conflicts").json(&row)).await?;
+        Ok(())
+    }
+
+    /// Apply one change; returns the number of conflicts recorded.
+    async fn apply(&self, change: &Change, origin: &str) -> Result<usize, String> {
+        let history = self.local_history(change, origin).await?;
+        let mut plan = plan(change, history);
+        for conflict in &plan.conflicts {
+            self.record_conflict(change, origin, conflict).await?;
+        }
+        let row_filter = [("id", format!("eq.{}", change.row_id))];
+
+        if plan.delete {
+            supabase::send(self.write(Method::DELETE, change, origin).query(&row_filter)).await?;
+            return Ok(plan.conflicts.len());
+        }
+        if plan.write.is_empty() {
+            return Ok(plan.conflicts.len());
+        }
+
+        if change.op == "insert" {
+            plan.write.insert("id".to_string(), Value::String(change.row_id.clone()));
+            plan.write.insert("user_id".to_string(), Value::String(self.user_id.clone()));
+            let req = self
+                .write(Method::POST, change, origin)
+                .header("Prefer", "resolution=merge-duplicates")
+                .json(&plan.write);
+            supabase::send(req).await?;
+        } else {
+            let req = self
+                .write(Method::PATCH, change, origin)
+                .query(&row_filter)
+                .header("Prefer", "return=representation")
+                .json(&plan.write);
+            let updated = supabase::send(req).await?;
+            if updated.as_array().is_some_and(Vec::is_empty) {
+                return Err(format!(
+                    "{} {} doesn't exist here; import the file that created it first",
+                    change.table_name, change.row_id
+                ));
+            }
+        }
+        Ok(plan.conflicts.len())
+    }
+}
+
+/// Row ids are UUIDs; anything else in a delta file is refused before it
+/// gets near a query.
+fn is_uuid(id: &str) -> bool {
+    id.len() == 36
+        && id.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// Apply a delta file from another install. Changes are applied in order;
+/// the first failure stops the import and the rest are applied by importing
+/// the file again.
+#[tauri::command]
+pub async fn import_changes(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, TeamSyncState>,
+    session: SupabaseSession,
+    path: String,
+) -> Result<ImportReport, String> {
+    let raw = std::fs::read_to_string(&path).map_err(|e| format!("read {path}: {e}"))?;
+    let file: DeltaFile = serde_json::from_str(&raw).map_err(|e| format!("not a FlipTools delta file: {e}"))?;
+    if file.format != FORMAT || file.version > FORMAT_VERSION {
+        return Err(format!("Unsupported delta file ({} v{})", file.format, file.version));
+    }
+    let (own_device, last_imported) = {
+        let cursors = state.cursors.lock().unwrap();
+        (cursors.device_id.clone(), cursors.imported.get(&file.device_id).copied().unwrap_or(0))
+    };
+    if file.device_id == own_device {
+        return Err("This file was exported by this install".to_string());
+    }
+    if let Some(change) = file.changes.iter().find(|c| !SYNCED_TABLES.contains(&c.table_name.as_str())) {
+        return Err(format!("Delta file touches unsupported table {:?}", change.table_name));
+    }
+    if let Some(change) = file.changes.iter().find(|c| !is_uuid(&c.row_id)) {
+        return Err(format!("Delta file has an invalid row id {:?}", change.row_id));
+    }
+
+    let client = supabase::client()?;
+    let importer = Importer { user_id: session.user_id(&client).await?, client, session: &session, own_device };
+    let mut report = ImportReport { device_id: file.device_id.clone(), ..ImportReport::default() };
+    let mut cursor = last_imported;
+
+    for change in &file.changes {
+        let origin = change.device_id.clone().unwrap_or_else(|| file.device_id.clone());
+        if change.id <= last_imported || origin == importer.own_device {
+            report.skipped += 1;
+            continue;
+        }
+        match importer.apply(change, &origin).await {
+            Ok(conflicts) => {
+                report.applied += 1;
+                report.conflicts += conflicts;
+                cursor = change.id;
+            }
+            Err(e) => {
+                report.error = Some(format!("change {}: {e}", change.id));
+                break;
+            }
+        }
+    }
+
+    {
+        let mut cursors = state.cursors.lock().unwrap();
+        cursors.imported.insert(file.device_id.clone(), cursor);
+        crate::store::save(&app, STORE_NAME, &*cursors)?;
+    }
+    crate::audit::record(
+        &app,
+        "import_changes",
+        None,
+        false,
+        json!({
+            "device_id": file.device_id,
+            "applied": report.applied,
+            "skipped": report.skipped,
+            "conflicts": report.conflicts,
+            "error": report.error,
+        }),
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROW: &str = "5b0e4c1e-8a53-4a4b-9d2f-0c1d2e3f4a5b";
+    const HERE: &str = "aaaa";
+    const THEM: &str = "bbbb";
+
+    fn change(id: i64, op: &str, fields: Value, device: Option<&str>, at: &str) -> Change {
+        Change {
+            id,
+            table_name: "inventory".to_string(),
+            row_id: ROW.to_string(),
+            op: op.to_string(),
+            fields: fields.as_object().cloned(),
+            device_id: device.map(str::to_string),
+            changed_at: at.to_string(),
+        }
+    }
+
+    /// Plan `incoming` (from THEM) against this database's change_log rows.
+    fn plan_against(incoming: &Change, local: Vec<Change>) -> Plan {
+        plan(incoming, LocalHistory::from_rows(local, incoming, THEM, HERE))
+    }
+
+    fn fields(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn normalise_ts_ignores_zone_spelling_and_precision() {
+        assert_eq!(normalise_ts("2026-10-16T09:30:00Z"), normalise_ts("2026-10-16T09:30:00+00:00"));
+        assert_eq!(normalise_ts("2026-10-16T09:30:00"), "2026-10-16T09:30:00.000000");
+        assert_eq!(normalise_ts("2026-10-16T09:30:00.12+00:00"), "2026-10-16T09:30:00.120000");
+        assert_eq!(normalise_ts("2026-10-16T09:30:00.5Z"), normalise_ts("2026-10-16T09:30:00.500000"));
+    }
+
+    #[test]
+    fn newer_compares_times_then_devices() {
+        assert!(newer("2026-10-16T09:30:01Z", "a", "2026-10-16T09:30:00.999999+00:00", "b"));
+        assert!(!newer("2026-10-16T09:30:00.5Z", "z", "2026-10-16T09:30:00.6Z", "a"));
+        // A missing fraction is .000000, not later than .1.
+        assert!(newer("2026-10-16T09:30:00.1Z", "a", "2026-10-16T09:30:00Z", "b"));
+        // Equal times go to the higher device id, the same way on both sides.
+        assert!(newer("2026-10-16T09:30:00Z", "b", "2026-10-16T09:30:00.000+00:00", "a"));
+        assert!(!newer("2026-10-16T09:30:00.000+00:00", "a", "2026-10-16T09:30:00Z", "b"));
+        assert!(!newer("2026-10-16T09:30:00Z", "a", "2026-10-16T09:30:00Z", "a"));
+    }
+
+    #[test]
+    fn incoming_field_wins_over_older_local_edit() {
+        let incoming = change(7, "update", json!({"price": 20}), None, "2026-10-16T10:00:00Z");
+        let local = vec![change(3, "update", json!({"price": 15}), None, "2026-10-16T09:00:00Z")];
+        let plan = plan_against(&incoming, local);
+        assert_eq!(plan.write, fields(json!({"price": 20})));
+        assert!(plan.conflicts.is_empty());
+    }
+
+    #[test]
+    fn incoming_field_loses_to_newer_local_edit() {
+        let incoming = change(7, "update", json!({"price": 20, "title": "Jacket"}), None, "2026-10-16T10:00:00Z");
+        let local = vec![change(9, "update", json!({"price": 18}), None, "2026-10-16T11:00:00Z")];
+        let plan = plan_against(&incoming, local);
+        assert_eq!(plan.write, fields(json!({"title": "Jacket"})));
+        assert_eq!(
+            plan.conflicts,
+            vec![Conflict {
+                field: "price".to_string(),
+                local: Some((json!(18), "2026-10-16T11:00:00Z".to_string())),
+                incoming: Some(json!(20)),
+            }]
+        );
+    }
+
+    #[test]
+    fn same_value_on_both_sides_is_not_a_conflict() {
+        let incoming = change(7, "update", json!({"price": 20}), None, "2026-10-16T10:00:00Z");
+        let local = vec![change(9, "update", json!({"price": 20}), None, "2026-10-16T11:00:00Z")];
+        assert_eq!(plan_against(&incoming, local), Plan::default());
+    }
+
+    #[test]
+    fn simultaneous_edits_settle_on_the_higher_device() {
+        let at = "2026-10-16T10:00:00Z";
+        // THEM > HERE, so the incoming edit wins here...
+        let incoming = change(7, "update", json!({"price": 20}), None, at);
+        let here = plan_against(&incoming, vec![change(9, "update", json!({"price": 18}), None, at)]);
+        assert_eq!(here.write, fields(json!({"price": 20})));
+        // ...and HERE's edit loses on THEM's side, where it's the incoming one.
+        let ours = change(9, "update", json!({"price": 18}), None, at);
+        let theirs = vec![change(7, "update", json!({"price": 20}), None, at)];
+        let there = plan(&ours, LocalHistory::from_rows(theirs, &ours, HERE, THEM));
+        assert!(there.write.is_empty());
+        assert_eq!(there.conflicts.len(), 1);
+    }
+
+    #[test]
+    fn delete_loses_to_later_local_edit() {
+        let incoming = change(7, "delete", Value::Null, None, "2026-10-16T10:00:00Z");
+        let local = vec![change(9, "update", json!({"price": 18}), None, "2026-10-16T11:00:00Z")];
+        let plan = plan_against(&incoming, local);
+        assert!(!plan.delete);
+        assert_eq!(plan.conflicts, vec![Conflict { field: "*".to_string(), local: None, incoming: None }]);
+    }
+
+    #[test]
+    fn delete_wins_over_earlier_local_edit() {
+        let incoming = change(7, "delete", Value::Null, None, "2026-10-16T10:00:00Z");
+        let local = vec![change(9, "update", json!({"price": 18}), None, "2026-10-16T09:00:00Z")];
+        assert_eq!(plan_against(&incoming, local), Plan { delete: true, ..Plan::default() });
+    }
+
+    #[test]
+    fn edit_loses_to_later_local_delete() {
+        let incoming = change(7, "update", json!({"price": 20}), None, "2026-10-16T10:00:00Z");
+        let local = vec![change(9, "delete", Value::Null, None, "2026-10-16T11:00:00Z")];
+        let plan = plan_against(&incoming, local);
+        assert!(plan.write.is_empty() && !plan.delete);
+        assert_eq!(
+            plan.conflicts,
+            vec![Conflict { field: "*".to_string(), local: None, incoming: Some(json!({"price": 20})) }]
+        );
+    }
+
+    #[test]
+    fn delete_of_a_row_already_deleted_here_does_nothing() {
+        let incoming = change(7, "delete", Value::Null, None, "2026-10-16T10:00:00Z");
+        let local = vec![change(9, "delete", Value::Null, None, "2026-10-16T11:00:00Z")];
+        assert_eq!(plan_against(&incoming, local), Plan::default());
+    }
+
+    #[test]
+    fn reimport_plans_the_same_writes() {
+        let incoming = change(7, "update", json!({"price": 20, "title": "Jacket"}), None, "2026-10-16T10:00:00Z");
+        let mut local = vec![change(9, "update", json!({"price": 18}), None, "2026-10-16T11:00:00Z")];
+        let first = plan_against(&incoming, local.clone());
+        // The first import logged its write with THEM's device id; a second
+        // import must not treat that as a newer local edit.
+        local.push(change(10, "update", json!({"title": "Jacket"}), Some(THEM), "2026-10-16T12:00:00Z"));
+        assert_eq!(plan_against(&incoming, local), first);
+    }
+
+    #[test]
+    fn row_ids_must_be_uuids() {
+        assert!(is_uuid(ROW));
+        assert!(!is_uuid("5b0e4c1e-8a53-4a4b-9d2f-0c1d2e3f4a5"));
+        assert!(!is_uuid("5b0e4c1e-8a53-4a4b-9d2f-0c1d2e3f4a5#"));
+        assert!(!is_uuid("5b0e4c1e-8a53-4a4b-9d2f&0c1d2e3f4a5b"));
+        assert!(!is_uuid("1,2"));
+    }
+}
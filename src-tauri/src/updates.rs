@@ -0,0 +1,1221 @@
+// ── App updates ────────────────────────────────────────────────────────────
+// Update checks and installs through the Tauri updater. Releases are
+// published to two channels: stable (the latest GitHub release) and beta (a
+// rolling `beta` release that pre-releases overwrite). The chosen channel is
+// remembered so later checks, including the background one, stay on it.
+//
+// A background job checks every `check_interval_hours` (default 6; 0 turns
+// it off), waiting a few minutes after launch so starting the app never
+// costs a request, and skipping the check when the last one (from either
+// path) is recent. The latest result is kept across restarts for
+// `get_last_update_check`, and a newly found version emits `update-available`.
+// `check_for_update` answers from that result for CHECK_CACHE_TTL unless
+// forced, so screens that each ask on mount don't each cost a request.
+//
+// A release only counts as available when its manifest has an installable
+// build for this platform and architecture (`windows-aarch64`,
+// `linux-x86_64`, ...): the right key, an installer the updater can apply
+// here (an AppImage on Linux) and no other architecture in the file name.
+// A newer release without one is reported as `no_asset_for_platform` so the
+// UI can point to a manual download instead of offering a broken install.
+//
+// Downloads that fail on the network (a dropped connection, a timeout, a
+// 5xx from the release host) are retried up to `max_download_attempts`
+// times (default 3) with exponential backoff, emitting `update-retry` before
+// each retry. Anything else, a bad signature above all, fails at once.
+//
+// Every package is checked against the updater's public key before it can
+// be installed. A good signature emits `update-verified` (and is reported on
+// `download_update`'s result); a bad one fails with "signature verification
+// failed", so tampering reads differently from a broken download.
+//
+// Installs emit `update-phase` as they move through checking, downloading,
+// verifying, installing and restarting; the last comes just before the app
+// restarts, so the UI can say goodbye.
+//
+// Checks and downloads go through the proxy set with `set_proxy` (proxy.rs),
+// except `install_update_from_file`, whose server is on this machine.
+//
+// An install can be cancelled while it downloads. The updater holds the
+// download in memory and only writes the installer once it is complete, so
+// dropping the download leaves nothing behind for the next attempt; once the
+// install itself has started it runs to the end.
+//
+// `download_update` stops after the download: the signature-checked package
+// stays in memory until `apply_downloaded_update` installs it (or
+// `install_update` finds it already there). A staged package doesn't survive
+// a restart; the next check simply offers the release again.
+//
+// `install_update_from_file` installs a bundle copied onto the machine by
+// hand. It is served to the updater from 127.0.0.1 so it takes exactly the
+// download path above: the `.sig` beside it is checked against the same
+// public key, and a bundle for another OS or architecture is refused. Only
+// a newer release installs unless the caller passes `allow_downgrade`, and
+// a downgrade is marked as one in the audit log.
+//
+// A release whose notes carry a `mandatory: true` line, or end in a JSON
+// footer with `"mandatory": true`, is reported as `mandatory` so the UI can
+// drop its "later" button. Anything else, a malformed marker included,
+// leaves the update optional.
+
+use futures_util::future::{AbortHandle, Abortable};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+use tauri_plugin_updater::{RemoteRelease, RemoteReleaseInner, Update, Updater, UpdaterExt};
+
+const STORE_NAME: &str = "update_settings";
+const LAST_CHECK_STORE: &str = "update_last_check";
+const STARTUP_DELAY: Duration = Duration::from_secs(5 * 60);
+const JOB_TICK: Duration = Duration::from_secs(15 * 60);
+/// How long `check_for_update` reuses the last result.
+const CHECK_CACHE_TTL: Duration = Duration::from_secs(30 * 60);
+const MAX_INTERVAL_HOURS: u32 = 24 * 7;
+/// Download speed is averaged over this much recent history.
+const SPEED_WINDOW: Duration = Duration::from_secs(3);
+const MAX_DOWNLOAD_ATTEMPTS: u32 = 10;
+/// Wait before the first retry; doubled for each one after.
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+const STABLE_ENDPOINT: &str = "https://github.com/xmoto1337/fliptools/releases/latest/download/latest.json";
+const BETA_ENDPOINT: &str = "https://github.com/xmoto1337/fliptools/releases/download/beta/latest.json";
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+impl UpdateChannel {
+    /// Unknown names fall back to stable.
+    fn parse(name: &str) -> Self {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "beta" => UpdateChannel::Beta,
+            "stable" => UpdateChannel::Stable,
+            other => {
+                log::warn!("unknown update channel {other:?}; using stable");
+                UpdateChannel::Stable
+            }
+        }
+    }
+
+    fn endpoint(self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => STABLE_ENDPOINT,
+            UpdateChannel::Beta => BETA_ENDPOINT,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateSettings {
+    channel: UpdateChannel,
+    /// Hours between background checks; 0 disables them.
+    check_interval_hours: u32,
+    /// Tries per download when the network fails, the first included.
+    max_download_attempts: u32,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        UpdateSettings { channel: UpdateChannel::Stable, check_interval_hours: 6, max_download_attempts: 3 }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LastUpdateCheck {
+    result: UpdateCheckResult,
+    checked_at: u64,
+}
+
+/// The running install, for `cancel_update`.
+struct Download {
+    abort: AbortHandle,
+    cancelled: Arc<AtomicBool>,
+    /// Set once the download is complete and the installer is running.
+    installing: Arc<AtomicBool>,
+}
+
+/// A downloaded, verified package waiting to be installed.
+struct Staged {
+    update: Update,
+    bytes: Vec<u8>,
+}
+
+/// Payload of `update-downloaded` and `download_update`'s result.
+#[derive(Clone, Serialize)]
+pub struct StagedUpdate {
+    version: String,
+    size: usize,
+    /// Always true: only packages that passed the signature check are staged.
+    signature_verified: bool,
+}
+
+impl StagedUpdate {
+    fn of(update: &Update, bytes: &[u8]) -> Self {
+        StagedUpdate { version: update.version.clone(), size: bytes.len(), signature_verified: true }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct VerifiedEvent<'a> {
+    version: &'a str,
+    signature_verified: bool,
+}
+
+/// Where an install is, emitted as `update-phase` alongside the percentage.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum UpdatePhase {
+    Checking,
+    Downloading,
+    /// The download is complete and its signature is being checked.
+    Verifying,
+    Installing,
+    Restarting,
+}
+
+fn emit_phase(app: &tauri::AppHandle, phase: UpdatePhase) {
+    let _ = app.emit("update-phase", phase);
+}
+
+pub struct UpdateState {
+    update_available: Mutex<Option<UpdateInfo>>,
+    settings: Mutex<UpdateSettings>,
+    last_check: Mutex<Option<LastUpdateCheck>>,
+    download: Mutex<Option<Download>>,
+    staged: Mutex<Option<Staged>>,
+}
+
+impl UpdateState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        UpdateState {
+            update_available: Mutex::new(None),
+            settings: Mutex::new(crate::store::load(app, STORE_NAME)),
+            last_check: Mutex::new(crate::store::load(app, LAST_CHECK_STORE)),
+            download: Mutex::new(None),
+            staged: Mutex::new(None),
+        }
+    }
+
+    /// The last check's result if it is for `channel`, from this version
+    /// of the app, and younger than CHECK_CACHE_TTL.
+    fn fresh_check(&self, channel: UpdateChannel) -> Option<UpdateCheckResult> {
+        let now = crate::unix_now();
+        let last_check = self.last_check.lock().unwrap();
+        last_check
+            .as_ref()
+            .filter(|c| c.result.channel == channel && c.result.current_version == env!("CARGO_PKG_VERSION"))
+            .filter(|c| c.checked_at <= now && now - c.checked_at < CHECK_CACHE_TTL.as_secs())
+            .map(|c| c.result.clone())
+    }
+
+    fn channel(&self) -> UpdateChannel {
+        self.settings.lock().unwrap().channel
+    }
+}
+
+/// Payload of `update-progress`.
+#[derive(Clone, Serialize)]
+pub struct UpdateProgress {
+    percent: u32,
+    downloaded: u64,
+    total: Option<u64>,
+    bytes_per_sec: u64,
+    eta_secs: Option<u64>,
+}
+
+/// Running download totals. The updater reports each chunk's size, and the
+/// speed comes from the bytes received over the last SPEED_WINDOW rather
+/// than the last chunk, so it doesn't jump around.
+struct ProgressMeter {
+    downloaded: u64,
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl ProgressMeter {
+    fn new() -> Self {
+        Self::starting_at(Instant::now())
+    }
+
+    fn starting_at(start: Instant) -> Self {
+        ProgressMeter { downloaded: 0, samples: VecDeque::from([(start, 0)]) }
+    }
+
+    fn chunk(&mut self, len: usize, total: Option<u64>) -> UpdateProgress {
+        self.chunk_at(len, total, Instant::now())
+    }
+
+    fn chunk_at(&mut self, len: usize, total: Option<u64>, now: Instant) -> UpdateProgress {
+        self.downloaded += len as u64;
+        self.samples.push_back((now, self.downloaded));
+        while self.samples.len() > 2 && now.duration_since(self.samples[0].0) > SPEED_WINDOW {
+            self.samples.pop_front();
+        }
+        let (since, base) = self.samples[0];
+        let elapsed = now.duration_since(since).as_secs_f64();
+        let bytes_per_sec = if elapsed > 0.0 { ((self.downloaded - base) as f64 / elapsed) as u64 } else { 0 };
+        let total = total.filter(|t| *t > 0);
+        UpdateProgress {
+            percent: total.map_or(0, |t| (self.downloaded.min(t) * 100 / t) as u32),
+            downloaded: self.downloaded,
+            total,
+            bytes_per_sec,
+            eta_secs: total
+                .filter(|_| bytes_per_sec > 0)
+                .map(|t| t.saturating_sub(self.downloaded).div_ceil(bytes_per_sec)),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct UpdateInfo {
+    current_version: String,
+    new_version: String,
+    notes: String,
+    mandatory: bool,
+    /// Unix seconds of the first check that found this version.
+    discovered_at: u64,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckOutcome {
+    #[default]
+    UpToDate,
+    Available,
+    /// A newer release exists but has no build that installs here.
+    NoAssetForPlatform,
+}
+
+/// The manifest entry an update would install.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PlatformAsset {
+    platform: String,
+    arch: String,
+    url: String,
+    /// From the download's Content-Length, when the server sends one.
+    size: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UpdateCheckResult {
+    available: bool,
+    #[serde(default)]
+    outcome: CheckOutcome,
+    current_version: String,
+    new_version: Option<String>,
+    notes: Option<String>,
+    /// The release notes mark this update as one that can't be put off.
+    #[serde(default)]
+    mandatory: bool,
+    channel: UpdateChannel,
+    #[serde(default)]
+    asset: Option<PlatformAsset>,
+    /// Why the release can't be installed here, for `no_asset_for_platform`.
+    #[serde(default)]
+    unavailable_reason: Option<String>,
+}
+
+/// Whether release `notes` mark the update mandatory, by a `mandatory: true`
+/// line (`=` and yes/1 work too, inside a list item or HTML comment) or a
+/// trailing JSON object with `"mandatory": true`.
+fn is_mandatory(notes: &str) -> bool {
+    let flag = |value: &str| {
+        matches!(value.trim().trim_matches(['"', '\'', '*', '`']).to_ascii_lowercase().as_str(), "true" | "yes" | "1")
+    };
+    let marked_line = notes.lines().any(|line| {
+        let line = line.trim().trim_start_matches("<!--").trim_end_matches("-->");
+        let line = line.trim().trim_start_matches(['-', '*', '>', '`']).trim();
+        line.split_once([':', '='])
+            .filter(|(key, _)| key.trim().trim_matches(['*', '`', '"']).eq_ignore_ascii_case("mandatory"))
+            .is_some_and(|(_, value)| flag(value))
+    });
+    if marked_line {
+        return true;
+    }
+    // A JSON footer, possibly fenced or in a comment; only the tail is
+    // searched so long notes stay cheap.
+    let tail = notes.trim_end().trim_end_matches("```").trim_end().trim_end_matches("-->").trim_end();
+    let start = (tail.len().saturating_sub(2048)..tail.len()).find(|&i| tail.is_char_boundary(i)).unwrap_or(0);
+    let tail = &tail[start..];
+    tail.rmatch_indices('{')
+        .find_map(|(i, _)| match serde_json::from_str(&tail[i..]) {
+            Ok(serde_json::Value::Object(footer)) => Some(footer),
+            _ => None,
+        })
+        .and_then(|footer| footer.get("mandatory").cloned())
+        .is_some_and(|value| match value {
+            serde_json::Value::Bool(b) => b,
+            serde_json::Value::String(s) => flag(&s),
+            serde_json::Value::Number(n) => n.as_u64() == Some(1),
+            _ => false,
+        })
+}
+
+/// Architecture names that show up in installer file names.
+const ARCH_ALIASES: &[(&str, &[&str])] = &[
+    ("x86_64", &["x86_64", "x64", "amd64"]),
+    ("aarch64", &["aarch64", "arm64"]),
+    ("i686", &["i686", "x86."]),
+    ("armv7", &["armv7", "armhf"]),
+];
+
+/// The build in `release` for the manifest key `key` (`<os>-<arch>`), if
+/// one exists and the updater can install it here.
+fn platform_asset(release: &RemoteRelease, key: &str) -> Result<PlatformAsset, String> {
+    let (platform, arch) = key.split_once('-').ok_or_else(|| format!("unrecognised platform {key}"))?;
+    let url = match &release.data {
+        // A dynamic server has already picked the build for the target it
+        // was asked about.
+        RemoteReleaseInner::Dynamic(entry) => entry.url.clone(),
+        RemoteReleaseInner::Static { platforms } => {
+            platforms.get(key).ok_or_else(|| format!("release {} has no {key} build", release.version))?.url.clone()
+        }
+    };
+    let file = url.path().rsplit('/').next().unwrap_or_default().to_ascii_lowercase();
+    check_installable(&file, platform, arch)?;
+    Ok(PlatformAsset { platform: platform.to_string(), arch: arch.to_string(), url: url.to_string(), size: None })
+}
+
+/// Whether the updater can install `file` (lowercased) on `platform`/`arch`:
+/// an installer format it applies there and no other architecture in the name.
+fn check_installable(file: &str, platform: &str, arch: &str) -> Result<(), String> {
+    let installable = match platform {
+        "linux" => file.contains(".appimage"),
+        "windows" => [".exe", ".msi", ".nsis.zip", ".msi.zip"].iter().any(|ext| file.ends_with(ext)),
+        "darwin" => file.ends_with(".app.tar.gz"),
+        _ => false,
+    };
+    if !installable {
+        return Err(format!("{file} can't be installed by the updater on {platform}"));
+    }
+    let other_arch = ARCH_ALIASES
+        .iter()
+        .filter(|(name, _)| *name != arch)
+        .flat_map(|(_, aliases)| aliases.iter())
+        .find(|alias| file.contains(*alias));
+    if let Some(alias) = other_arch {
+        return Err(format!("{file} looks like a {} build, not {arch}", alias.trim_end_matches('.')));
+    }
+    Ok(())
+}
+
+async fn download_size(app: &tauri::AppHandle, url: &str) -> Option<u64> {
+    let mut builder = reqwest::Client::builder().timeout(Duration::from_secs(10));
+    if let Some(proxy) = crate::proxy::current(app) {
+        builder = builder.proxy(crate::proxy::reqwest_proxy(&proxy).ok()?);
+    }
+    let resp = builder
+        .build()
+        .ok()?
+        .head(url)
+        .send()
+        .await
+        .ok()?;
+    resp.status().is_success().then(|| resp.content_length()).flatten()
+}
+
+/// The release an updater's last check saw, whether or not it installs
+/// here, and whether it is newer than this build.
+type SeenRelease = Arc<Mutex<Option<(RemoteRelease, bool)>>>;
+
+/// Updater pointed at `channel`'s manifest that only accepts releases with a
+/// build for this platform.
+fn updater_for(app: &tauri::AppHandle, channel: UpdateChannel) -> Result<(Updater, SeenRelease), String> {
+    let key = tauri_plugin_updater::target().ok_or("Updates aren't built for this platform")?;
+    let endpoint = channel.endpoint().parse().map_err(|e| format!("update endpoint: {e}"))?;
+    let seen = SeenRelease::default();
+    let seen_by_check = seen.clone();
+    let mut builder = app.updater_builder();
+    if let Some(proxy) = crate::proxy::current(app) {
+        builder = builder.proxy(tauri::Url::parse(&proxy).map_err(|e| format!("proxy: {e}"))?);
+    }
+    let updater = builder
+        .version_comparator(move |current, release| {
+            let newer = release.version > current;
+            let install = newer && platform_asset(&release, &key).is_ok();
+            *seen_by_check.lock().unwrap() = Some((release, newer));
+            install
+        })
+        .endpoints(vec![endpoint])
+        .and_then(|b| b.build())
+        .map_err(|e| e.to_string())?;
+    Ok((updater, seen))
+}
+
+fn up_to_date(current_version: String, channel: UpdateChannel) -> UpdateCheckResult {
+    UpdateCheckResult {
+        available: false,
+        outcome: CheckOutcome::UpToDate,
+        current_version,
+        new_version: None,
+        notes: None,
+        mandatory: false,
+        channel,
+        asset: None,
+        unavailable_reason: None,
+    }
+}
+
+/// Check `channel` and remember the result. Emits `update-available` when
+/// the version found differs from the last check's.
+async fn check(app: &tauri::AppHandle, channel: UpdateChannel) -> Result<UpdateCheckResult, String> {
+    let state = app.state::<UpdateState>();
+    let (updater, seen) = updater_for(app, channel)?;
+    let key = tauri_plugin_updater::target().unwrap_or_default();
+    let current_version = env!("CARGO_PKG_VERSION").to_string();
+
+    let update = updater.check().await.map_err(|e| e.to_string())?;
+    let seen = seen.lock().unwrap().take();
+    let result = match (update, seen) {
+        (Some(update), seen) => {
+            let info = {
+                let mut pending = state.update_available.lock().unwrap();
+                let discovered_at = pending
+                    .as_ref()
+                    .filter(|p| p.new_version == update.version)
+                    .map_or_else(crate::unix_now, |p| p.discovered_at);
+                let info = UpdateInfo {
+                    current_version: update.current_version.to_string(),
+                    new_version: update.version.clone(),
+                    notes: update.body.clone().unwrap_or_default(),
+                    mandatory: update.body.as_deref().is_some_and(is_mandatory),
+                    discovered_at,
+                };
+                *pending = Some(info.clone());
+                info
+            };
+
+            let mut asset = seen.and_then(|(release, _)| platform_asset(&release, &key).ok());
+            if let Some(asset) = asset.as_mut() {
+                asset.size = download_size(app, &asset.url).await;
+            }
+            UpdateCheckResult {
+                available: true,
+                outcome: CheckOutcome::Available,
+                current_version: info.current_version,
+                new_version: Some(info.new_version),
+                notes: Some(info.notes),
+                mandatory: info.mandatory,
+                channel,
+                asset,
+                unavailable_reason: None,
+            }
+        }
+        (None, Some((release, true))) => UpdateCheckResult {
+            available: false,
+            outcome: CheckOutcome::NoAssetForPlatform,
+            unavailable_reason: platform_asset(&release, &key).err(),
+            current_version,
+            new_version: Some(release.version.to_string()),
+            mandatory: release.notes.as_deref().is_some_and(is_mandatory),
+            notes: release.notes,
+            channel,
+            asset: None,
+        },
+        (None, _) => up_to_date(current_version, channel),
+    };
+    if !result.available {
+        state.update_available.lock().unwrap().take();
+    }
+
+    let mut last_check = state.last_check.lock().unwrap();
+    let previous = last_check.as_ref().and_then(|c| c.result.new_version.clone());
+    if result.available && result.new_version != previous {
+        let _ = app.emit("update-available", &result);
+    }
+    *last_check = Some(LastUpdateCheck { result: result.clone(), checked_at: crate::unix_now() });
+    if let Err(e) = crate::store::save(app, LAST_CHECK_STORE, &*last_check) {
+        log::warn!("saving update check: {e}");
+    }
+    Ok(result)
+}
+
+/// `channel` ("stable" or "beta") switches tracks and is remembered; without
+/// it the last chosen channel is used. A check of the same channel from the
+/// last 30 minutes is returned as is unless `force` is set.
+#[tauri::command]
+pub async fn check_for_update(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, UpdateState>,
+    channel: Option<String>,
+    force: Option<bool>,
+) -> Result<UpdateCheckResult, String> {
+    let channel = match channel {
+        Some(name) => {
+            let channel = UpdateChannel::parse(&name);
+            let mut settings = state.settings.lock().unwrap();
+            if settings.channel != channel {
+                settings.channel = channel;
+                crate::store::save(&app, STORE_NAME, &*settings)?;
+            }
+            channel
+        }
+        None => state.channel(),
+    };
+    if !force.unwrap_or(false) {
+        if let Some(cached) = state.fresh_check(channel) {
+            return Ok(cached);
+        }
+    }
+    check(&app, channel).await
+}
+
+/// The most recent check's result, from either the background job or
+/// `check_for_update`; None before the first check.
+#[tauri::command]
+pub fn get_last_update_check(state: tauri::State<'_, UpdateState>) -> Option<LastUpdateCheck> {
+    state.last_check.lock().unwrap().clone()
+}
+
+/// The update the last check found, if any, without checking again, so a
+/// reloaded UI can show its banner at once. `discovered_at` says how old it
+/// is; re-check with `check_for_update` when it matters.
+#[tauri::command]
+pub fn get_pending_update(state: tauri::State<'_, UpdateState>) -> Option<UpdateInfo> {
+    state.update_available.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn get_update_settings(state: tauri::State<'_, UpdateState>) -> UpdateSettings {
+    state.settings.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn set_update_check_interval(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, UpdateState>,
+    hours: u32,
+) -> Result<UpdateSettings, String> {
+    if hours > MAX_INTERVAL_HOURS {
+        return Err(format!("Check at least every {MAX_INTERVAL_HOURS} hours"));
+    }
+    let mut settings = state.settings.lock().unwrap();
+    settings.check_interval_hours = hours;
+    crate::store::save(&app, STORE_NAME, &*settings)?;
+    Ok(settings.clone())
+}
+
+/// How many times a download is tried before giving up (1 = no retries).
+#[tauri::command]
+pub fn set_update_download_attempts(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, UpdateState>,
+    attempts: u32,
+) -> Result<UpdateSettings, String> {
+    if !(1..=MAX_DOWNLOAD_ATTEMPTS).contains(&attempts) {
+        return Err(format!("Attempts must be between 1 and {MAX_DOWNLOAD_ATTEMPTS}"));
+    }
+    let mut settings = state.settings.lock().unwrap();
+    settings.max_download_attempts = attempts;
+    crate::store::save(&app, STORE_NAME, &*settings)?;
+    Ok(settings.clone())
+}
+
+pub fn start_check_job(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(STARTUP_DELAY).await;
+        loop {
+            let state = app.state::<UpdateState>();
+            let (channel, hours) = {
+                let settings = state.settings.lock().unwrap();
+                (settings.channel, settings.check_interval_hours)
+            };
+            let checked_at = state.last_check.lock().unwrap().as_ref().map(|c| c.checked_at);
+            let due = hours > 0
+                && checked_at.map_or(true, |t| crate::unix_now().saturating_sub(t) >= u64::from(hours) * 3600);
+            if due {
+                if let Err(e) = check(&app, channel).await {
+                    log::warn!("background update check failed: {e}");
+                }
+            }
+            tokio::time::sleep(JOB_TICK).await;
+        }
+    });
+}
+
+/// The error for the frontend, naming a failed signature check as such.
+fn download_error(error: tauri_plugin_updater::Error) -> String {
+    use tauri_plugin_updater::Error;
+    match error {
+        Error::Minisign(_) | Error::SignatureUtf8(_) | Error::Base64(_) => {
+            format!("signature verification failed: {error}")
+        }
+        error => error.to_string(),
+    }
+}
+
+/// Worth another try: the connection failed or the host had a bad moment.
+/// Signature, format and client errors are not.
+fn is_transient(error: &tauri_plugin_updater::Error) -> bool {
+    match error {
+        tauri_plugin_updater::Error::Reqwest(e) => e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+        // The updater reports an unsuccessful status as "... with status: 503
+        // Service Unavailable"; a 4xx won't fix itself.
+        tauri_plugin_updater::Error::Network(message) => !message.contains("status: 4"),
+        _ => false,
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct RetryEvent {
+    /// The attempt about to start (2 = first retry).
+    attempt: u32,
+    max_attempts: u32,
+    delay_ms: u64,
+    error: String,
+}
+
+/// Run `attempt` until it succeeds, fails for good or has been tried
+/// `max_attempts` times, backing off between tries. `on_retry` hears about
+/// each retry before its delay.
+async fn with_retries<T, Fut>(
+    max_attempts: u32,
+    mut attempt: impl FnMut() -> Fut,
+    mut on_retry: impl FnMut(&RetryEvent),
+) -> Result<T, tauri_plugin_updater::Error>
+where
+    Fut: std::future::Future<Output = Result<T, tauri_plugin_updater::Error>>,
+{
+    let mut tries = 1;
+    loop {
+        match attempt().await {
+            Err(e) if tries < max_attempts && is_transient(&e) => {
+                let delay = (RETRY_BASE_DELAY * 2u32.pow(tries - 1)).min(RETRY_MAX_DELAY);
+                tries += 1;
+                log::warn!("update download failed ({e}); retrying in {}s", delay.as_secs());
+                let delay_ms = delay.as_millis() as u64;
+                on_retry(&RetryEvent { attempt: tries, max_attempts, delay_ms, error: e.to_string() });
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Download and verify `update`, emitting `update-progress` and retrying
+/// network failures, then `update-verified`. None when `cancel_update`
+/// stopped it (`update-cancelled` has been emitted then).
+async fn download(state: &UpdateState, window: &tauri::Window, update: &Update) -> Result<Option<Vec<u8>>, String> {
+    let (abort, registration) = AbortHandle::new_pair();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let installing = Arc::new(AtomicBool::new(false));
+    {
+        let mut download = state.download.lock().unwrap();
+        if download.is_some() {
+            return Err("An update is already downloading".to_string());
+        }
+        *download = Some(Download { abort, cancelled: cancelled.clone(), installing: installing.clone() });
+    }
+    let max_attempts = state.settings.lock().unwrap().max_download_attempts.max(1);
+
+    let fetch = with_retries(
+        max_attempts,
+        || {
+            emit_phase(window.app_handle(), UpdatePhase::Downloading);
+            let window_clone = window.clone();
+            let app = window.app_handle().clone();
+            let cancelled = cancelled.clone();
+            let installing = installing.clone();
+            let mut meter = ProgressMeter::new();
+            update.download(
+                move |chunk, total| {
+                    let progress = meter.chunk(chunk, total);
+                    if !cancelled.load(Ordering::Relaxed) {
+                        let _ = window_clone.emit("update-progress", progress);
+                    }
+                },
+                move || {
+                    installing.store(true, Ordering::Relaxed);
+                    emit_phase(&app, UpdatePhase::Verifying);
+                },
+            )
+        },
+        |event| {
+            let _ = window.emit("update-retry", event);
+        },
+    );
+    let outcome = Abortable::new(fetch, registration).await;
+    state.download.lock().unwrap().take();
+    match outcome {
+        Ok(Ok(bytes)) => {
+            let _ = window.emit("update-verified", VerifiedEvent { version: &update.version, signature_verified: true });
+            Ok(Some(bytes))
+        }
+        Ok(Err(e)) => Err(download_error(e)),
+        Err(_) => {
+            let _ = window.emit("update-cancelled", ());
+            Ok(None)
+        }
+    }
+}
+
+/// The staged package if it is for `version`, removing it from the state.
+fn take_staged(state: &UpdateState, version: &str) -> Option<Staged> {
+    let mut staged = state.staged.lock().unwrap();
+    if staged.as_ref().is_some_and(|s| s.update.version == version) {
+        staged.take()
+    } else {
+        None
+    }
+}
+
+/// Install a downloaded package and restart. A failed install puts the
+/// package back so it can be retried.
+fn apply(app: &tauri::AppHandle, staged: Staged) -> Result<(), String> {
+    emit_phase(app, UpdatePhase::Installing);
+    if let Err(e) = staged.update.install(&staged.bytes) {
+        *app.state::<UpdateState>().staged.lock().unwrap() = Some(staged);
+        return Err(e.to_string());
+    }
+    emit_phase(app, UpdatePhase::Restarting);
+    app.restart();
+}
+
+/// Download and install the latest update on the current channel, then
+/// restart. A download stopped by `cancel_update` emits `update-cancelled`
+/// and returns without installing. An already staged package for the same
+/// version is installed without downloading again.
+#[tauri::command]
+pub async fn install_update(app: tauri::AppHandle, window: tauri::Window) -> Result<(), String> {
+    let state = app.state::<UpdateState>();
+    let (updater, _) = updater_for(&app, state.channel())?;
+
+    emit_phase(&app, UpdatePhase::Checking);
+    let update = updater.check().await.map_err(|e| e.to_string())?;
+
+    if let Some(update) = update {
+        let staged = match take_staged(&state, &update.version) {
+            Some(staged) => staged,
+            None => match download(&state, &window, &update).await? {
+                Some(bytes) => Staged { update, bytes },
+                None => return Ok(()),
+            },
+        };
+        apply(&app, staged)?;
+    }
+
+    Ok(())
+}
+
+/// Download the latest update on the current channel without installing
+/// it, for `apply_downloaded_update` later. None when there is no update or
+/// the download was cancelled. Emits `update-downloaded` when staged.
+#[tauri::command]
+pub async fn download_update(app: tauri::AppHandle, window: tauri::Window) -> Result<Option<StagedUpdate>, String> {
+    let state = app.state::<UpdateState>();
+    let (updater, _) = updater_for(&app, state.channel())?;
+    emit_phase(&app, UpdatePhase::Checking);
+    let Some(update) = updater.check().await.map_err(|e| e.to_string())? else {
+        return Ok(None);
+    };
+    {
+        let staged = state.staged.lock().unwrap();
+        if let Some(staged) = staged.as_ref().filter(|s| s.update.version == update.version) {
+            return Ok(Some(StagedUpdate::of(&staged.update, &staged.bytes)));
+        }
+    }
+    let Some(bytes) = download(&state, &window, &update).await? else {
+        return Ok(None);
+    };
+    let info = StagedUpdate::of(&update, &bytes);
+    *state.staged.lock().unwrap() = Some(Staged { update, bytes });
+    let _ = app.emit("update-downloaded", &info);
+    Ok(Some(info))
+}
+
+/// Install the package staged by `download_update` and restart. Fails when
+/// nothing is staged rather than downloading again.
+#[tauri::command]
+pub fn apply_downloaded_update(app: tauri::AppHandle) -> Result<(), String> {
+    let staged = app
+        .state::<UpdateState>()
+        .staged
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or("No update has been downloaded; run download_update first")?;
+    apply(&app, staged)
+}
+
+/// The version in a bundle's file name (`FlipTools_1.4.2_amd64.AppImage`).
+fn version_in_file_name(file: &str) -> Option<String> {
+    file.split(['_', '-'])
+        .find(|part| semver::Version::parse(part).is_ok())
+        .map(str::to_string)
+}
+
+/// Serves one bundle and its manifest on 127.0.0.1 so a file on disk goes
+/// through the updater like any release: the same download, signature check
+/// and install. Stops when dropped.
+struct LocalRelease {
+    manifest_url: tauri::Url,
+    _shutdown: tokio::sync::oneshot::Sender<()>,
+}
+
+impl LocalRelease {
+    async fn serve(key: &str, version: &str, signature: &str, bundle: Vec<u8>) -> Result<Self, String> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.map_err(|e| e.to_string())?;
+        let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+        let manifest = serde_json::json!({
+            "version": version,
+            "platforms": { key: { "url": format!("http://127.0.0.1:{port}/bundle"), "signature": signature } },
+        })
+        .to_string();
+        let bundle = Arc::new(bundle);
+        let (shutdown, mut stopped) = tokio::sync::oneshot::channel::<()>();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                let (mut stream, _) = tokio::select! {
+                    _ = &mut stopped => break,
+                    accepted = listener.accept() => match accepted {
+                        Ok(accepted) => accepted,
+                        Err(_) => continue,
+                    },
+                };
+                let manifest = manifest.clone();
+                let bundle = bundle.clone();
+                tauri::async_runtime::spawn(async move {
+                    let mut buf = vec![0u8; 4096];
+                    let n = stream.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]);
+                    let path = request.split_whitespace().nth(1).unwrap_or("");
+                    let (status, kind, body): (&str, &str, &[u8]) = match path {
+                        "/latest.json" => ("200 OK", "application/json", manifest.as_bytes()),
+                        "/bundle" => ("200 OK", "application/octet-stream", &bundle),
+                        _ => ("404 Not Found", "text/plain", b"not found"),
+                    };
+                    let head = format!(
+                        "HTTP/1.1 {status}\r\nContent-Type: {kind}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    let _ = stream.write_all(head.as_bytes()).await;
+                    let _ = stream.write_all(body).await;
+                    let _ = stream.flush().await;
+                });
+            }
+        });
+        let manifest_url =
+            tauri::Url::parse(&format!("http://127.0.0.1:{port}/latest.json")).map_err(|e| e.to_string())?;
+        Ok(LocalRelease { manifest_url, _shutdown: shutdown })
+    }
+}
+
+/// Install an update bundle from disk (for machines that can't reach the
+/// release host) and restart. The release's `.sig` file must sit next to
+/// the bundle: the package is checked against the updater's public key like
+/// a downloaded one, and a bundle built for another OS or architecture is
+/// refused. `version` is only needed when the file name doesn't carry one.
+/// A release that isn't newer than this one needs `allow_downgrade`.
+#[tauri::command]
+pub async fn install_update_from_file(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    path: String,
+    version: Option<String>,
+    allow_downgrade: Option<bool>,
+) -> Result<(), String> {
+    let allow_downgrade = allow_downgrade.unwrap_or(false);
+    let key = tauri_plugin_updater::target().ok_or("Updates aren't supported on this platform")?;
+    let (platform, arch) = key.split_once('-').ok_or_else(|| format!("unrecognised platform {key}"))?;
+    let path = std::path::PathBuf::from(path);
+    let file = path
+        .file_name()
+        .map(|f| f.to_string_lossy().to_ascii_lowercase())
+        .ok_or_else(|| format!("{} is not a file", path.display()))?;
+    check_installable(&file, platform, arch)?;
+
+    let mut sig_path = path.clone().into_os_string();
+    sig_path.push(".sig");
+    let sig_path = std::path::PathBuf::from(sig_path);
+    let signature = tokio::fs::read_to_string(&sig_path)
+        .await
+        .map_err(|_| format!("{} is missing; the release's .sig file must sit next to the bundle", sig_path.display()))?;
+    let bundle = tokio::fs::read(&path).await.map_err(|e| format!("Couldn't read {}: {e}", path.display()))?;
+
+    let version = match version.map(|v| v.trim().trim_start_matches('v').to_string()) {
+        Some(v) => {
+            semver::Version::parse(&v).map_err(|_| format!("{v} is not a version"))?;
+            v
+        }
+        None => version_in_file_name(&file)
+            .ok_or("The file name doesn't include a version; pass the version of the release")?,
+    };
+    let current = env!("CARGO_PKG_VERSION");
+    let downgrade = !is_newer(&version, current);
+    if downgrade && !allow_downgrade {
+        return Err(format!(
+            "{version} isn't newer than the installed {current}; pass allow_downgrade to install it anyway"
+        ));
+    }
+
+    let release = LocalRelease::serve(&key, &version, signature.trim(), bundle).await?;
+    let updater = app
+        .updater_builder()
+        .version_comparator(move |current, release| allow_downgrade || release.version > current)
+        .endpoints(vec![release.manifest_url.clone()])
+        .and_then(|b| b.build())
+        .map_err(|e| e.to_string())?;
+    let update = updater
+        .check()
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("The updater didn't accept the bundle")?;
+
+    let state = app.state::<UpdateState>();
+    let Some(bytes) = download(&state, &window, &update).await? else {
+        return Ok(());
+    };
+    drop(release);
+    crate::audit::record(
+        &app,
+        "install_update_from_file",
+        None,
+        false,
+        serde_json::json!({
+            "file": file,
+            "version": update.version,
+            "from": update.current_version,
+            "downgrade": downgrade,
+        }),
+    );
+    apply(&app, Staged { update, bytes })
+}
+
+/// Whether `version` is a later release than `current`; anything unparseable
+/// isn't.
+fn is_newer(version: &str, current: &str) -> bool {
+    match (semver::Version::parse(version), semver::Version::parse(current)) {
+        (Ok(version), Ok(current)) => version > current,
+        _ => false,
+    }
+}
+
+/// Stop a running `install_update` or `download_update` download. Returns false when nothing is
+/// downloading; fails once the download is done and the install has begun.
+#[tauri::command]
+pub fn cancel_update(state: tauri::State<'_, UpdateState>) -> Result<bool, String> {
+    let download = state.download.lock().unwrap();
+    let Some(download) = download.as_ref() else {
+        return Ok(false);
+    };
+    if download.installing.load(Ordering::Relaxed) {
+        return Err("The update is already installing and can't be cancelled".to_string());
+    }
+    download.cancelled.store(true, Ordering::Relaxed);
+    download.abort.abort();
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_later_releases_are_newer() {
+        assert!(is_newer("1.4.0", "1.3.9"));
+        assert!(is_newer("1.4.0", "1.4.0-beta.2"));
+        assert!(!is_newer("1.4.0", "1.4.0"));
+        assert!(!is_newer("1.3.9", "1.4.0"));
+        assert!(!is_newer("1.4.0-beta.1", "1.4.0"));
+        assert!(!is_newer("not-a-version", "1.4.0"));
+    }
+
+    #[test]
+    fn mandatory_flag_is_read_as_bool_string_or_missing() {
+        // A JSON footer, as a bool, a string or a number.
+        assert!(is_mandatory("Security fix.\n\n{\"mandatory\": true}"));
+        assert!(is_mandatory("Security fix.\n\n```json\n{\"mandatory\": \"yes\"}\n```"));
+        assert!(is_mandatory("Security fix.\n<!-- {\"mandatory\": 1, \"min\": \"1.2.0\"} -->"));
+        assert!(!is_mandatory("Security fix.\n\n{\"mandatory\": false}"));
+        assert!(!is_mandatory("Security fix.\n\n{\"mandatory\": \"no\"}"));
+        assert!(!is_mandatory("Security fix.\n\n{\"mandatory\": null}"));
+        // A marker line, in any of the spellings the notes use.
+        assert!(is_mandatory("mandatory: true\n\nFixes a crash."));
+        assert!(is_mandatory("- **Mandatory**: `yes`"));
+        assert!(is_mandatory("<!-- mandatory = 1 -->"));
+        assert!(!is_mandatory("mandatory: false"));
+        // Missing or malformed markers leave the update optional.
+        assert!(!is_mandatory(""));
+        assert!(!is_mandatory("Fixes a crash when listing to Depop."));
+        assert!(!is_mandatory("Not mandatory, but recommended."));
+        assert!(!is_mandatory("{\"mandatory\": tru"));
+        assert!(!is_mandatory("{\"version\": \"1.5.0\"}"));
+        assert!(!is_mandatory("mandatory:"));
+    }
+
+    /// Summary of a progress report: (percent, bytes/s, ETA).
+    fn rates(progress: &UpdateProgress) -> (u32, u64, Option<u64>) {
+        (progress.percent, progress.bytes_per_sec, progress.eta_secs)
+    }
+
+    #[test]
+    fn speed_is_smoothed_over_the_window() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut meter = ProgressMeter::starting_at(start);
+        let mut last = None;
+        // 1 kB every 100 ms for 4 s.
+        for k in 1..=40 {
+            let progress = meter.chunk_at(1000, Some(100_000), at(k * 100));
+            if k == 20 {
+                assert_eq!(rates(&progress), (20, 10_000, Some(8)));
+            }
+            last = Some(progress);
+        }
+        assert_eq!(rates(&last.unwrap()), (40, 10_000, Some(6)));
+        // A burst moves the speed a little, not to the burst's own 50 kB/s.
+        let burst = meter.chunk_at(5000, Some(100_000), at(4100));
+        assert_eq!(rates(&burst), (45, 11_333, Some(5)));
+    }
+
+    #[test]
+    fn unknown_or_zero_totals_have_no_percent_or_eta() {
+        let start = Instant::now();
+        for total in [None, Some(0)] {
+            let mut meter = ProgressMeter::starting_at(start);
+            meter.chunk_at(500, total, start + Duration::from_millis(500));
+            let progress = meter.chunk_at(500, total, start + Duration::from_secs(1));
+            assert_eq!(progress.total, None);
+            assert_eq!(progress.downloaded, 1000);
+            assert_eq!(rates(&progress), (0, 1000, None));
+        }
+        // The first chunk arriving at the start has no elapsed time to
+        // measure a speed over.
+        let progress = ProgressMeter::starting_at(start).chunk_at(500, Some(1000), start);
+        assert_eq!(rates(&progress), (50, 0, None));
+    }
+
+    #[test]
+    fn a_stalled_transfer_slows_the_speed_and_stretches_the_eta() {
+        let start = Instant::now();
+        let mut meter = ProgressMeter::starting_at(start);
+        for k in 1..=10 {
+            meter.chunk_at(1000, Some(100_000), start + Duration::from_millis(k * 100));
+        }
+        // Nothing for ten seconds, then one more chunk: the speed is the
+        // rate across the gap.
+        let progress = meter.chunk_at(1000, Some(100_000), start + Duration::from_secs(11));
+        assert_eq!(rates(&progress), (11, 100, Some(890)));
+    }
+
+    /// Release host on a loopback port that answers the first `failures`
+    /// requests with `failure` and the rest with `bundle`; also returns how
+    /// many requests it served.
+    async fn flaky_host(failures: usize, failure: &'static str, bundle: &'static [u8]) -> (String, Arc<Mutex<usize>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let served = Arc::new(Mutex::new(0));
+        let count = served.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 4096];
+                let _ = stream.read(&mut buf).await;
+                let n = {
+                    let mut count = count.lock().unwrap();
+                    *count += 1;
+                    *count
+                };
+                let (status, body) = if n <= failures { (failure, &b"unavailable"[..]) } else { ("200 OK", bundle) };
+                let head =
+                    format!("HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len());
+                let _ = stream.write_all(head.as_bytes()).await;
+                let _ = stream.write_all(body).await;
+            }
+        });
+        (format!("http://{addr}/FlipTools_1.5.0_amd64.AppImage"), served)
+    }
+
+    /// One download as `Update::download` makes it: non-2xx statuses are
+    /// `Error::Network`.
+    async fn fetch_bundle(url: &str) -> Result<Vec<u8>, tauri_plugin_updater::Error> {
+        let resp = reqwest_updater::Client::new().get(url).send().await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            return Err(tauri_plugin_updater::Error::Network(format!("Download request failed with status: {status}")));
+        }
+        Ok(resp.bytes().await?.to_vec())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_failed_download_is_retried_and_the_retry_reported() {
+        const BUNDLE: &[u8] = b"signed bundle bytes";
+        let (url, served) = flaky_host(1, "503 Service Unavailable", BUNDLE).await;
+        let mut retries = Vec::new();
+        let bytes = with_retries(3, || fetch_bundle(&url), |event| retries.push(event.clone())).await.unwrap();
+        assert_eq!(bytes, BUNDLE);
+        assert_eq!(*served.lock().unwrap(), 2);
+        assert_eq!(retries.len(), 1);
+        assert_eq!((retries[0].attempt, retries[0].max_attempts, retries[0].delay_ms), (2, 3, 2000));
+        assert!(retries[0].error.contains("503"), "{}", retries[0].error);
+
+        // A 4xx won't fix itself, so it isn't retried.
+        let (url, served) = flaky_host(usize::MAX, "404 Not Found", BUNDLE).await;
+        let mut retries = 0;
+        let err = with_retries(3, || fetch_bundle(&url), |_| retries += 1).await.map(|_| ()).unwrap_err();
+        assert!(err.to_string().contains("404"), "{err}");
+        assert_eq!((*served.lock().unwrap(), retries), (1, 0));
+    }
+
+    fn release(platforms: serde_json::Value) -> RemoteRelease {
+        serde_json::from_value(serde_json::json!({
+            "version": "1.5.0",
+            "notes": "Bug fixes",
+            "pub_date": "2024-06-01T12:00:00Z",
+            "platforms": platforms,
+        }))
+        .unwrap()
+    }
+
+    const SIG: &str = "dW50cnVzdGVkIGNvbW1lbnQ=";
+
+    #[test]
+    fn platform_asset_picks_the_manifest_entry_for_the_key() {
+        let release = release(serde_json::json!({
+            "linux-x86_64": { "url": "https://example.com/v1.5.0/FlipTools_1.5.0_amd64.AppImage", "signature": SIG },
+            "windows-x86_64": { "url": "https://example.com/v1.5.0/FlipTools_1.5.0_x64-setup.exe", "signature": SIG },
+        }));
+        let asset = platform_asset(&release, "linux-x86_64").unwrap();
+        assert_eq!((asset.platform.as_str(), asset.arch.as_str()), ("linux", "x86_64"));
+        assert_eq!(asset.url, "https://example.com/v1.5.0/FlipTools_1.5.0_amd64.AppImage");
+        assert!(platform_asset(&release, "windows-x86_64").is_ok());
+    }
+
+    #[test]
+    fn platform_asset_reports_a_missing_key() {
+        let release = release(serde_json::json!({
+            "linux-x86_64": { "url": "https://example.com/FlipTools_1.5.0_amd64.AppImage", "signature": SIG },
+        }));
+        let err = platform_asset(&release, "darwin-aarch64").map(|a| a.url).unwrap_err();
+        assert_eq!(err, "release 1.5.0 has no darwin-aarch64 build");
+    }
+
+    #[test]
+    fn platform_asset_refuses_malformed_keys_and_foreign_builds() {
+        let release = release(serde_json::json!({
+            "linux": { "url": "https://example.com/FlipTools_1.5.0_amd64.AppImage", "signature": SIG },
+            "linux-aarch64": { "url": "https://example.com/FlipTools_1.5.0_amd64.AppImage", "signature": SIG },
+            "windows-x86_64": { "url": "https://example.com/FlipTools_1.5.0_x64.dmg", "signature": SIG },
+        }));
+        assert_eq!(platform_asset(&release, "linux").map(|a| a.url).unwrap_err(), "unrecognised platform linux");
+        assert_eq!(
+            platform_asset(&release, "linux-aarch64").map(|a| a.url).unwrap_err(),
+            "fliptools_1.5.0_amd64.appimage looks like a amd64 build, not aarch64"
+        );
+        assert_eq!(
+            platform_asset(&release, "windows-x86_64").map(|a| a.url).unwrap_err(),
+            "fliptools_1.5.0_x64.dmg can't be installed by the updater on windows"
+        );
+        assert!(check_installable("fliptools_1.5.0_aarch64.app.tar.gz", "darwin", "aarch64").is_ok());
+    }
+}
@@ -0,0 +1,401 @@
+// ── Listing videos ─────────────────────────────────────────────────────────
+// Checks a clip against a marketplace's video limits and, when it doesn't
+// fit, re-encodes it with ffmpeg. Container, codec and duration come from
+// the MP4/QuickTime box structure directly, so validation works without
+// ffmpeg installed; only transcoding needs it.
+//
+// ffmpeg is looked up next to the app executable first (a bundled sidecar)
+// and then on PATH.
+
+use crate::request_context::{Finished, RequestContext};
+use crate::marketplace_limits::{Limits, LimitsState};
+use serde::Serialize;
+use serde_json::json;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tauri::Emitter;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+
+/// moov boxes are small; anything past this is not a sane file.
+const MAX_MOOV_BYTES: u64 = 64 * 1024 * 1024;
+/// Encoder settings tried in order until the output fits the size cap:
+/// (x264 CRF, max width).
+const PASSES: &[(u32, u32)] = &[(26, 1080), (30, 720), (34, 540)];
+
+#[derive(Clone, Serialize, Default)]
+pub struct VideoInfo {
+    pub path: String,
+    /// `mp4` or `mov`.
+    pub container: String,
+    /// Sample-entry code of the first video track (`avc1`, `hvc1`, ...).
+    pub codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub duration_seconds: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub size_bytes: u64,
+}
+
+#[derive(Serialize)]
+pub struct VideoReport {
+    pub marketplace: String,
+    pub info: VideoInfo,
+    /// Why the clip doesn't fit; empty when it does.
+    pub problems: Vec<String>,
+    pub ok: bool,
+}
+
+#[derive(Serialize)]
+pub struct FfmpegStatus {
+    available: bool,
+    path: Option<String>,
+    /// `bundled` or `path`.
+    source: Option<&'static str>,
+    version: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TranscodeResult {
+    output_path: String,
+    report: VideoReport,
+}
+
+// ── MP4 parsing ────────────────────────────────────────────────────────────
+
+fn be_u32(b: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(b.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be_u64(b: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(b.get(at..at + 8)?.try_into().ok()?))
+}
+
+/// Child boxes of `data` as (type, payload).
+fn boxes(data: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut out = Vec::new();
+    let mut at = 0;
+    while let (Some(size), Some(kind)) = (be_u32(data, at), data.get(at + 4..at + 8)) {
+        let (header, size) = match size {
+            0 => (8, data.len() - at),
+            1 => match be_u64(data, at + 8) {
+                Some(large) => (16, large as usize),
+                None => break,
+            },
+            n => (8, n as usize),
+        };
+        if size < header || at + size > data.len() {
+            break;
+        }
+        out.push((kind, &data[at + header..at + size]));
+        at += size;
+    }
+    out
+}
+
+fn child<'a>(data: &'a [u8], kind: &[u8]) -> Option<&'a [u8]> {
+    boxes(data).into_iter().find(|(k, _)| *k == kind).map(|(_, body)| body)
+}
+
+fn fourcc(b: &[u8]) -> String {
+    String::from_utf8_lossy(b).trim().to_string()
+}
+
+struct Track {
+    /// `vide`, `soun`, ...
+    handler: String,
+    codec: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+fn track(trak: &[u8]) -> Option<Track> {
+    let mdia = child(trak, b"mdia")?;
+    let handler = fourcc(child(mdia, b"hdlr")?.get(8..12)?);
+    let codec = child(mdia, b"minf")
+        .and_then(|m| child(m, b"stbl"))
+        .and_then(|s| child(s, b"stsd"))
+        .and_then(|s| s.get(12..16))
+        .map(fourcc);
+    // tkhd ends with width and height as 16.16 fixed point.
+    let (width, height) = match child(trak, b"tkhd") {
+        Some(t) if t.len() >= 8 => {
+            (be_u32(t, t.len() - 8).map(|w| w >> 16), be_u32(t, t.len() - 4).map(|h| h >> 16))
+        }
+        _ => (None, None),
+    };
+    Some(Track { handler, codec, width, height })
+}
+
+/// Read container, tracks and duration from an MP4/MOV file.
+pub fn probe(path: &Path) -> Result<VideoInfo, String> {
+    let shown = path.display().to_string();
+    let mut file = File::open(path).map_err(|e| format!("{shown}: {e}"))?;
+    let size_bytes = file.metadata().map_err(|e| format!("{shown}: {e}"))?.len();
+    let mut info = VideoInfo { path: shown.clone(), size_bytes, ..Default::default() };
+    let mut moov = None;
+    let mut at = 0u64;
+    while at + 8 <= size_bytes {
+        let mut header = [0u8; 16];
+        file.seek(SeekFrom::Start(at)).map_err(|e| format!("{shown}: {e}"))?;
+        file.read_exact(&mut header[..8]).map_err(|e| format!("{shown}: {e}"))?;
+        let (mut size, mut header_len) = (u64::from(be_u32(&header, 0).unwrap_or(0)), 8);
+        if size == 1 {
+            file.read_exact(&mut header[8..]).map_err(|e| format!("{shown}: {e}"))?;
+            size = be_u64(&header, 8).unwrap_or(0);
+            header_len = 16;
+        } else if size == 0 {
+            size = size_bytes - at;
+        }
+        if size < header_len {
+            break;
+        }
+        match &header[4..8] {
+            b"ftyp" => {
+                let mut brand = [0u8; 4];
+                file.read_exact(&mut brand).map_err(|e| format!("{shown}: {e}"))?;
+                info.container = if &brand == b"qt  " { "mov" } else { "mp4" }.to_string();
+            }
+            b"moov" => {
+                let len = size - header_len;
+                if len > MAX_MOOV_BYTES {
+                    return Err(format!("{shown}: movie header is implausibly large"));
+                }
+                let mut body = vec![0; len as usize];
+                file.read_exact(&mut body).map_err(|e| format!("{shown}: {e}"))?;
+                moov = Some(body);
+            }
+            _ => {}
+        }
+        at += size;
+    }
+    if info.container.is_empty() {
+        return Err(format!("{shown} isn't an MP4 or MOV file"));
+    }
+    let moov = moov.ok_or_else(|| format!("{shown} has no movie header (incomplete recording?)"))?;
+
+    if let Some(mvhd) = child(&moov, b"mvhd") {
+        // Version 1 widens the times and duration to 64 bits.
+        let (timescale, duration) = match mvhd.first() {
+            Some(1) => (be_u32(mvhd, 20), be_u64(mvhd, 24)),
+            _ => (be_u32(mvhd, 12), be_u32(mvhd, 16).map(u64::from)),
+        };
+        if let (Some(scale), Some(duration)) = (timescale.filter(|s| *s > 0), duration) {
+            info.duration_seconds = Some(duration as f64 / f64::from(scale));
+        }
+    }
+    for (kind, trak) in boxes(&moov) {
+        if kind != b"trak" {
+            continue;
+        }
+        match track(trak) {
+            Some(t) if t.handler == "vide" && info.codec.is_none() => {
+                info.codec = t.codec;
+                info.width = t.width;
+                info.height = t.height;
+            }
+            Some(t) if t.handler == "soun" && info.audio_codec.is_none() => info.audio_codec = t.codec,
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+/// Everything about `info` that breaks `limits`.
+fn problems(info: &VideoInfo, marketplace: &str, limits: &Limits) -> Vec<String> {
+    if !limits.video {
+        return vec![format!("{marketplace} listings can't carry a video")];
+    }
+    let mut problems = Vec::new();
+    if !limits.video_formats.is_empty() && !limits.video_formats.contains(&info.container) {
+        problems.push(format!("{marketplace} takes {} files, not {}", limits.video_formats.join("/"), info.container));
+    }
+    match &info.codec {
+        None => problems.push("No video track found".to_string()),
+        Some(codec) if !limits.video_codecs.is_empty() && !limits.video_codecs.contains(codec) => {
+            problems.push(format!("Video codec {codec} isn't accepted (needs {})", limits.video_codecs.join("/")));
+        }
+        Some(_) => {}
+    }
+    if let (Some(max), Some(secs)) = (limits.video_max_seconds, info.duration_seconds) {
+        // Encoders round the last frame; allow a few hundredths over.
+        if secs > max + 0.05 {
+            problems.push(format!("{secs:.1}s long; {marketplace} allows {max}s"));
+        }
+    }
+    if let Some(max) = limits.video_max_bytes.filter(|max| info.size_bytes > *max) {
+        problems.push(format!("{} MB; {marketplace} allows {} MB", info.size_bytes / 1_000_000, max / 1_000_000));
+    }
+    problems
+}
+
+pub fn report(path: &Path, marketplace: &str, limits: &LimitsState) -> Result<VideoReport, String> {
+    let limits = limits.get(marketplace).ok_or_else(|| format!("No limits known for '{marketplace}'"))?;
+    let info = probe(path)?;
+    let problems = problems(&info, marketplace, &limits.limits);
+    Ok(VideoReport { marketplace: marketplace.to_string(), ok: problems.is_empty(), problems, info })
+}
+
+// ── ffmpeg ─────────────────────────────────────────────────────────────────
+
+fn exe_name() -> &'static str {
+    if cfg!(windows) { "ffmpeg.exe" } else { "ffmpeg" }
+}
+
+fn find_ffmpeg() -> Option<(PathBuf, &'static str)> {
+    let bundled = std::env::current_exe().ok().and_then(|exe| Some(exe.parent()?.join(exe_name())));
+    if let Some(path) = bundled.filter(|p| p.is_file()) {
+        return Some((path, "bundled"));
+    }
+    let dirs = std::env::var_os("PATH")?;
+    std::env::split_paths(&dirs).map(|d| d.join(exe_name())).find(|p| p.is_file()).map(|p| (p, "path"))
+}
+
+fn ffmpeg() -> Result<PathBuf, String> {
+    find_ffmpeg().map(|(path, _)| path).ok_or_else(|| {
+        "ffmpeg isn't available: install it (https://ffmpeg.org/download.html) and make sure it's on PATH".to_string()
+    })
+}
+
+#[tauri::command]
+pub async fn get_ffmpeg_status() -> FfmpegStatus {
+    let Some((path, source)) = find_ffmpeg() else {
+        return FfmpegStatus { available: false, path: None, source: None, version: None };
+    };
+    let version = tokio::process::Command::new(&path)
+        .arg("-version")
+        .output()
+        .await
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8_lossy(&o.stdout).lines().next().map(str::to_string));
+    FfmpegStatus { available: version.is_some(), path: Some(path.display().to_string()), source: Some(source), version }
+}
+
+/// One ffmpeg run, emitting "video-transcode-progress" as it goes.
+async fn encode(
+    app: &tauri::AppHandle,
+    input: &Path,
+    output: &Path,
+    limits: &Limits,
+    expected_secs: f64,
+    (crf, width): (u32, u32),
+    pass: usize,
+) -> Result<(), String> {
+    let mut cmd = tokio::process::Command::new(ffmpeg()?);
+    cmd.args(["-y", "-hide_banner", "-loglevel", "error", "-nostats", "-progress", "pipe:1", "-i"])
+        .arg(input);
+    if let Some(max) = limits.video_max_seconds {
+        cmd.args(["-t", &format!("{max}")]);
+    }
+    cmd.args(["-vf", &format!("scale='min({width},iw)':-2")])
+        .args(["-c:v", "libx264", "-preset", "veryfast", "-crf", &crf.to_string(), "-pix_fmt", "yuv420p"])
+        .args(["-c:a", "aac", "-b:a", "128k", "-movflags", "+faststart"])
+        .arg(output)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // A cancelled transcode drops this future; ffmpeg goes with it.
+        .kill_on_drop(true);
+    let mut child = cmd.spawn().map_err(|e| format!("starting ffmpeg: {e}"))?;
+
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let errors = tokio::spawn(async move {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text).await;
+        text
+    });
+    let mut lines = BufReader::new(child.stdout.take().expect("stdout is piped")).lines();
+    let source = input.display().to_string();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Some(us) = line.strip_prefix("out_time_us=").and_then(|v| v.trim().parse::<f64>().ok()) else {
+            continue;
+        };
+        let percent = (us / 1e6 / expected_secs * 100.0).clamp(0.0, 100.0);
+        let _ = app.emit("video-transcode-progress", json!({ "path": source, "pass": pass, "percent": percent }));
+    }
+    let status = child.wait().await.map_err(|e| format!("ffmpeg: {e}"))?;
+    if !status.success() {
+        let text = errors.await.unwrap_or_default();
+        let last = text.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("no output");
+        return Err(format!("ffmpeg failed ({status}): {last}"));
+    }
+    Ok(())
+}
+
+// ── Commands ───────────────────────────────────────────────────────────────
+
+/// Check container, codec, duration and size against `marketplace`'s limits
+/// (Depop when omitted).
+#[tauri::command]
+pub fn validate_video(
+    limits: tauri::State<'_, LimitsState>,
+    path: String,
+    marketplace: Option<String>,
+) -> Result<VideoReport, String> {
+    report(Path::new(&path), marketplace.as_deref().unwrap_or("depop"), &limits)
+}
+
+/// Re-encode `path` to fit the limits of the marketplace named by `preset`
+/// (H.264/AAC MP4, trimmed to the duration cap, stepping quality down until
+/// it fits the size cap). The output goes in the app data `videos` folder;
+/// the original is left alone. Cancellable (see request_context); a
+/// cancelled transcode removes its output.
+#[tauri::command]
+pub async fn transcode_video(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    limits: tauri::State<'_, LimitsState>,
+    path: String,
+    preset: String,
+    invocation_id: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<Finished<TranscodeResult>, String> {
+    let ctx = RequestContext::begin(&app, &window, "transcode_video", invocation_id, timeout_ms)?;
+    let result = transcode(&app, &ctx, &limits, path, preset).await;
+    ctx.finish(result)
+}
+
+async fn transcode(
+    app: &tauri::AppHandle,
+    ctx: &RequestContext,
+    limits: &LimitsState,
+    path: String,
+    preset: String,
+) -> Result<TranscodeResult, String> {
+    let marketplace_limits = limits.get(&preset).ok_or_else(|| format!("No video preset '{preset}'"))?;
+    if !marketplace_limits.limits.video {
+        return Err(format!("{preset} listings can't carry a video"));
+    }
+    let input = PathBuf::from(&path);
+    let info = probe(&input)?;
+    let cap = marketplace_limits.limits.video_max_seconds.unwrap_or(f64::MAX);
+    let expected_secs = info.duration_seconds.unwrap_or(cap).min(cap).max(0.1);
+
+    let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("video");
+    let dir = crate::store::data_path(app, "videos")?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("create {}: {e}", dir.display()))?;
+    let output = dir.join(format!("{stem}-{preset}.mp4"));
+
+    let mut pass = 0;
+    let report = loop {
+        let limits_now = &marketplace_limits.limits;
+        let encoded = ctx.run(encode(app, &input, &output, limits_now, expected_secs, PASSES[pass], pass + 1)).await;
+        if let Err(e) = ctx.check() {
+            let _ = std::fs::remove_file(&output);
+            return Err(e);
+        }
+        encoded??;
+        let report = report(&output, &preset, limits)?;
+        let too_big = marketplace_limits.limits.video_max_bytes.is_some_and(|max| report.info.size_bytes > max);
+        pass += 1;
+        // The last pass's output is returned even if it's still too big; the
+        // report says so.
+        if !too_big || pass == PASSES.len() {
+            break report;
+        }
+    };
+    let _ = app.emit("video-transcode-progress", json!({ "path": path, "pass": pass, "percent": 100.0 }));
+    Ok(TranscodeResult { output_path: output.display().to_string(), report })
+}
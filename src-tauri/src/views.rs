@@ -0,0 +1,276 @@
+// ── Saved inventory views ──────────────────────────────────────────────────
+// Named filter + sort + column sets ("listed on Depop only", "photographed
+// but not listed") that the inventory list can switch between.
+//
+// Listings live in the frontend's Supabase tables, so `apply_view` takes the
+// rows and evaluates the stored spec here, including the predicates the UI
+// can't express on one row alone: marketplace links, photo counts, and
+// staleness from the listing-stats store. Field paths are dotted
+// (`platforms.depop.status`).
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+const STORE_NAME: &str = "saved_views";
+const MAX_NAME_CHARS: usize = 60;
+
+/// When a date predicate starts counting from.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Since {
+    /// `YYYY-MM-DD`.
+    Date { date: String },
+    DaysAgo { days: u64 },
+    StartOfMonth,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Predicate {
+    All { of: Vec<Predicate> },
+    Any { of: Vec<Predicate> },
+    Not { predicate: Box<Predicate> },
+    Equals { field: String, value: Value },
+    OneOf { field: String, values: Vec<Value> },
+    /// Case-insensitive substring.
+    Contains { field: String, value: String },
+    AtLeast { field: String, value: f64 },
+    AtMost { field: String, value: f64 },
+    IsEmpty { field: String },
+    /// ISO date/timestamp field on or after `since`.
+    Since { field: String, since: Since },
+    /// Linked to `marketplace` (any marketplace when None).
+    HasLink {
+        #[serde(default)]
+        marketplace: Option<String>,
+    },
+    FewerPhotos { than: usize },
+    /// Linked somewhere, with no likes or saves recorded in `days` days.
+    Stale { days: u64 },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ViewSort {
+    pub field: String,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SavedView {
+    pub name: String,
+    pub filter: Predicate,
+    #[serde(default)]
+    pub sort: Vec<ViewSort>,
+    #[serde(default)]
+    pub columns: Vec<String>,
+    pub updated_at: u64,
+}
+
+pub struct ViewState {
+    views: Mutex<BTreeMap<String, SavedView>>,
+}
+
+impl ViewState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        ViewState { views: Mutex::new(crate::store::load(app, STORE_NAME)) }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ViewResult {
+    columns: Vec<String>,
+    total: usize,
+    items: Vec<Value>,
+}
+
+fn field<'a>(item: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(item, |v, key| v.get(key)).filter(|v| !v.is_null())
+}
+
+fn as_number(v: &Value) -> Option<f64> {
+    match v {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// `YYYY-MM-DD` the predicate counts from.
+fn since_date(since: &Since) -> String {
+    match since {
+        Since::Date { date } => date.clone(),
+        Since::DaysAgo { days } => {
+            let then = crate::unix_now().saturating_sub(days * 86_400);
+            crate::netlog::iso8601(then * 1000)[..10].to_string()
+        }
+        Since::StartOfMonth => format!("{}-01", &crate::fees::today()[..7]),
+    }
+}
+
+/// Marketplace -> listing id for the item's links (`platforms` on a listing).
+fn links(item: &Value) -> Vec<(&str, &str)> {
+    item.get("platforms")
+        .and_then(Value::as_object)
+        .map(|p| {
+            p.iter()
+                .filter_map(|(m, link)| Some((m.as_str(), link.get("id")?.as_str()?)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+struct Context<'a> {
+    stats: &'a crate::notifications::NotificationState,
+    now: u64,
+}
+
+impl Predicate {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Predicate::All { of } | Predicate::Any { of } => of.iter().try_for_each(Predicate::validate),
+            Predicate::Not { predicate } => predicate.validate(),
+            Predicate::Since { since: Since::Date { date }, .. } if !crate::custom_fields::valid_date(date) => {
+                Err(format!("Invalid date {date:?} (expected YYYY-MM-DD)"))
+            }
+            Predicate::AtLeast { value, .. } | Predicate::AtMost { value, .. } if !value.is_finite() => {
+                Err("Numeric filters need a finite number".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn matches(&self, item: &Value, ctx: &Context) -> bool {
+        match self {
+            Predicate::All { of } => of.iter().all(|p| p.matches(item, ctx)),
+            Predicate::Any { of } => of.iter().any(|p| p.matches(item, ctx)),
+            Predicate::Not { predicate } => !predicate.matches(item, ctx),
+            Predicate::Equals { field: f, value } => field(item, f) == Some(value),
+            Predicate::OneOf { field: f, values } => field(item, f).is_some_and(|v| values.contains(v)),
+            Predicate::Contains { field: f, value } => field(item, f)
+                .and_then(Value::as_str)
+                .is_some_and(|s| s.to_lowercase().contains(&value.to_lowercase())),
+            Predicate::AtLeast { field: f, value } => field(item, f).and_then(as_number).is_some_and(|n| n >= *value),
+            Predicate::AtMost { field: f, value } => field(item, f).and_then(as_number).is_some_and(|n| n <= *value),
+            Predicate::IsEmpty { field: f } => match field(item, f) {
+                None => true,
+                Some(Value::String(s)) => s.trim().is_empty(),
+                Some(Value::Array(a)) => a.is_empty(),
+                Some(Value::Object(o)) => o.is_empty(),
+                Some(_) => false,
+            },
+            Predicate::Since { field: f, since } => field(item, f)
+                .and_then(Value::as_str)
+                .is_some_and(|d| d.get(..10).unwrap_or(d) >= since_date(since).as_str()),
+            Predicate::HasLink { marketplace } => {
+                links(item).iter().any(|(m, _)| marketplace.as_deref().map_or(true, |want| *m == want))
+            }
+            Predicate::FewerPhotos { than } => {
+                field(item, "images").and_then(Value::as_array).map_or(0, Vec::len) < *than
+            }
+            Predicate::Stale { days } => {
+                let links = links(item);
+                let cutoff = ctx.now.saturating_sub(days * 86_400);
+                !links.is_empty()
+                    && links
+                        .iter()
+                        .all(|(m, id)| ctx.stats.last_engagement(m, id).map_or(true, |t| t < cutoff))
+            }
+        }
+    }
+}
+
+/// Nulls sort last in either direction.
+fn compare(a: Option<&Value>, b: Option<&Value>, descending: bool) -> Ordering {
+    let (x, y) = match (a, b) {
+        (None, None) => return Ordering::Equal,
+        (None, Some(_)) => return Ordering::Greater,
+        (Some(_), None) => return Ordering::Less,
+        (Some(x), Some(y)) => (x, y),
+    };
+    let ord = match (as_number(x), as_number(y)) {
+        (Some(x), Some(y)) => x.total_cmp(&y),
+        _ => x.as_str().unwrap_or_default().cmp(y.as_str().unwrap_or_default()),
+    };
+    if descending { ord.reverse() } else { ord }
+}
+
+#[tauri::command]
+pub fn list_views(state: tauri::State<'_, ViewState>) -> Vec<SavedView> {
+    state.views.lock().unwrap().values().cloned().collect()
+}
+
+/// Create or replace the view called `name`.
+#[tauri::command]
+pub fn save_view(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, ViewState>,
+    name: String,
+    filter_spec: Predicate,
+    sort: Option<Vec<ViewSort>>,
+    columns: Option<Vec<String>>,
+) -> Result<SavedView, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("View name is required".to_string());
+    }
+    if name.chars().count() > MAX_NAME_CHARS {
+        return Err(format!("View names are at most {MAX_NAME_CHARS} characters"));
+    }
+    filter_spec.validate()?;
+    let view = SavedView {
+        name: name.clone(),
+        filter: filter_spec,
+        sort: sort.unwrap_or_default(),
+        columns: columns.unwrap_or_default(),
+        updated_at: crate::unix_now(),
+    };
+    let mut views = state.views.lock().unwrap();
+    views.insert(name, view.clone());
+    crate::store::save(&app, STORE_NAME, &*views)?;
+    Ok(view)
+}
+
+#[tauri::command]
+pub fn delete_view(app: tauri::AppHandle, state: tauri::State<'_, ViewState>, name: String) -> Result<(), String> {
+    let mut views = state.views.lock().unwrap();
+    if views.remove(&name).is_none() {
+        return Err(format!("No view called '{name}'"));
+    }
+    crate::store::save(&app, STORE_NAME, &*views)
+}
+
+/// Filter and sort `items` (listing rows) with the saved view `name`. Rows
+/// in the trash (`deleted_at` set) are left out unless `include_deleted`.
+#[tauri::command]
+pub fn apply_view(
+    state: tauri::State<'_, ViewState>,
+    stats: tauri::State<'_, crate::notifications::NotificationState>,
+    name: String,
+    items: Vec<Value>,
+    include_deleted: Option<bool>,
+) -> Result<ViewResult, String> {
+    let view = state
+        .views
+        .lock()
+        .unwrap()
+        .get(&name)
+        .cloned()
+        .ok_or_else(|| format!("No view called '{name}'"))?;
+    let ctx = Context { stats: &stats, now: crate::unix_now() };
+    let include_deleted = include_deleted.unwrap_or(false);
+    let mut items: Vec<Value> = items
+        .into_iter()
+        .filter(|i| include_deleted || field(i, "deleted_at").map_or(true, Value::is_null))
+        .filter(|i| view.filter.matches(i, &ctx))
+        .collect();
+    items.sort_by(|a, b| {
+        view.sort.iter().fold(Ordering::Equal, |acc, s| {
+            acc.then_with(|| compare(field(a, &s.field), field(b, &s.field), s.descending))
+        })
+    });
+    Ok(ViewResult { columns: view.columns, total: items.len(), items })
+}
@@ -0,0 +1,685 @@
+// ── Inbound webhooks ───────────────────────────────────────────────────────
+// `POST /webhooks/<name>` on the local API server. Each registered webhook has
+// its own secret, a JSON schema the payload must satisfy, and one of a fixed
+// set of handlers, run here once the delivery checks out:
+//   create-draft    a draft row in `listings`
+//   add-watch-item  a watched saved search in `saved_searches`
+//   create-task     a local task (tasks.rs)
+// The Supabase handlers write with the last session the frontend passed to
+// `set_webhook_session`; it's kept in memory only, so until the frontend
+// signs in again they answer 503 and the sender should retry. The
+// `inbound-webhook` event tells the UI what was created so it can refresh.
+//
+// Signing scheme (all three headers required):
+//   X-FlipTools-Timestamp: unix seconds
+//   X-FlipTools-Nonce:     any unique string per delivery
+//   X-FlipTools-Signature: hex(HMAC-SHA256(secret, "<timestamp>.<nonce>.<body>"))
+//                          (an optional "sha256=" prefix is accepted)
+
+use crate::local_api::{HttpRequest, HttpResponse};
+use crate::supabase::{self, SupabaseSession};
+use crate::tasks::{NewTask, TaskState};
+use hmac::{Hmac, Mac};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+const STORE_NAME: &str = "inbound_webhooks";
+const LOG_CAPACITY: usize = 200;
+/// Deliveries whose timestamp is further than this from our clock are rejected,
+/// and nonces only need to be remembered for this long.
+const REPLAY_WINDOW_SECS: u64 = 300;
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebhookHandler {
+    CreateDraft,
+    AddWatchItem,
+    CreateTask,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct InboundWebhook {
+    name: String,
+    secret: String,
+    schema: Value,
+    handler: WebhookHandler,
+    created_at: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct DeliveryLogEntry {
+    id: String,
+    webhook: String,
+    received_at: u64,
+    accepted: bool,
+    status: u16,
+    reason: Option<String>,
+    handler: Option<WebhookHandler>,
+}
+
+#[derive(Serialize)]
+pub struct RegisteredWebhook {
+    name: String,
+    handler: WebhookHandler,
+    url: Option<String>,
+    /// Only returned from `register_inbound_webhook`; never listed afterwards.
+    secret: String,
+}
+
+#[derive(Serialize)]
+pub struct WebhookSummary {
+    name: String,
+    handler: WebhookHandler,
+    schema: Value,
+    created_at: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct InboundWebhookEvent {
+    delivery_id: String,
+    webhook: String,
+    handler: WebhookHandler,
+    /// Id of the draft, saved search or task created.
+    created_id: String,
+    payload: Value,
+}
+
+pub struct WebhookState {
+    hooks: Mutex<HashMap<String, InboundWebhook>>,
+    seen_nonces: Mutex<HashMap<String, u64>>,
+    log: Mutex<VecDeque<DeliveryLogEntry>>,
+    session: Mutex<Option<SupabaseSession>>,
+}
+
+impl WebhookState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        WebhookState {
+            hooks: Mutex::new(crate::store::load(app, STORE_NAME)),
+            seen_nonces: Mutex::new(HashMap::new()),
+            log: Mutex::new(VecDeque::with_capacity(LOG_CAPACITY)),
+            session: Mutex::new(None),
+        }
+    }
+
+    fn record(&self, entry: DeliveryLogEntry) {
+        let mut log = self.log.lock().unwrap();
+        if log.len() == LOG_CAPACITY {
+            log.pop_front();
+        }
+        log.push_back(entry);
+    }
+
+    /// Idle maintenance: keep the newest `floor` log entries, forget nonces
+    /// outside the replay window and release spare capacity. Returns the
+    /// number of entries dropped.
+    pub fn compact(&self, floor: usize) -> usize {
+        let now = crate::unix_now();
+        let mut seen = self.seen_nonces.lock().unwrap();
+        let before = seen.len();
+        seen.retain(|_, at| now.saturating_sub(*at) <= REPLAY_WINDOW_SECS);
+        seen.shrink_to_fit();
+        let mut dropped = before - seen.len();
+
+        let mut log = self.log.lock().unwrap();
+        let excess = log.len().saturating_sub(floor);
+        log.drain(..excess);
+        dropped += excess;
+        log.shrink_to_fit();
+        dropped
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+#[tauri::command]
+pub fn register_inbound_webhook(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, WebhookState>,
+    name: String,
+    schema: Value,
+    handler: WebhookHandler,
+) -> Result<RegisteredWebhook, String> {
+    if !valid_name(&name) {
+        return Err("Webhook name must be 1-64 chars of a-z, 0-9, '-' or '_'".to_string());
+    }
+    if !schema.is_object() {
+        return Err("Schema must be a JSON object".to_string());
+    }
+
+    let mut hooks = state.hooks.lock().unwrap();
+    // Re-registering keeps the secret so the sender doesn't need reconfiguring.
+    let secret = hooks
+        .get(&name)
+        .map(|h| h.secret.clone())
+        .unwrap_or_else(|| hex::encode(rand::random::<[u8; 32]>()));
+    hooks.insert(
+        name.clone(),
+        InboundWebhook {
+            name: name.clone(),
+            secret: secret.clone(),
+            schema,
+            handler,
+            created_at: crate::unix_now(),
+        },
+    );
+    crate::store::save(&app, STORE_NAME, &*hooks)?;
+
+    Ok(RegisteredWebhook {
+        url: crate::local_api::base_url(&app).map(|base| format!("{base}/webhooks/{name}")),
+        name,
+        handler,
+        secret,
+    })
+}
+
+#[tauri::command]
+pub fn list_inbound_webhooks(state: tauri::State<'_, WebhookState>) -> Vec<WebhookSummary> {
+    let mut list: Vec<WebhookSummary> = state
+        .hooks
+        .lock()
+        .unwrap()
+        .values()
+        .map(|h| WebhookSummary {
+            name: h.name.clone(),
+            handler: h.handler,
+            schema: h.schema.clone(),
+            created_at: h.created_at,
+        })
+        .collect();
+    list.sort_by(|a, b| a.name.cmp(&b.name));
+    list
+}
+
+#[tauri::command]
+pub fn remove_inbound_webhook(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, WebhookState>,
+    name: String,
+) -> Result<(), String> {
+    let mut hooks = state.hooks.lock().unwrap();
+    if hooks.remove(&name).is_none() {
+        return Err(format!("No webhook named '{name}'"));
+    }
+    crate::store::save(&app, STORE_NAME, &*hooks)
+}
+
+/// Newest deliveries first.
+#[tauri::command]
+pub fn get_inbound_webhook_log(state: tauri::State<'_, WebhookState>) -> Vec<DeliveryLogEntry> {
+    state.log.lock().unwrap().iter().rev().cloned().collect()
+}
+
+/// The signed-in session for the create-draft and add-watch-item handlers.
+#[tauri::command]
+pub fn set_webhook_session(state: tauri::State<'_, WebhookState>, session: SupabaseSession) {
+    *state.session.lock().unwrap() = Some(session);
+}
+
+/// Called by the local API server for `POST /webhooks/<name>`.
+pub async fn handle_delivery(app: &tauri::AppHandle, name: &str, req: &HttpRequest) -> HttpResponse {
+    let state = app.state::<WebhookState>();
+    let delivery_id = hex::encode(rand::random::<[u8; 8]>());
+    let hook = state.hooks.lock().unwrap().get(name).cloned();
+
+    let outcome = match &hook {
+        Some(hook) => match verify_and_parse(&state, hook, req) {
+            Ok(payload) => dispatch(app, &state, hook, &payload).await.map(|id| (id, payload)),
+            Err(e) => Err(e),
+        },
+        None => Err((404, "unknown webhook".to_string())),
+    };
+
+    let (status, reason, response) = match outcome {
+        Ok((created_id, payload)) => {
+            let hook = hook.as_ref().expect("verified delivery has a webhook");
+            let _ = app.emit(
+                "inbound-webhook",
+                crate::privacy::Private::new(InboundWebhookEvent {
+                    delivery_id: delivery_id.clone(),
+                    webhook: hook.name.clone(),
+                    handler: hook.handler,
+                    created_id: created_id.clone(),
+                    payload,
+                }),
+            );
+            let body = json!({ "accepted": true, "delivery_id": delivery_id, "id": created_id });
+            (201, None, HttpResponse::json(201, &body))
+        }
+        Err((status, reason)) => (status, Some(reason.clone()), HttpResponse::error(status, &reason)),
+    };
+
+    state.record(DeliveryLogEntry {
+        id: delivery_id,
+        webhook: name.to_string(),
+        received_at: crate::unix_now(),
+        accepted: reason.is_none(),
+        status,
+        reason,
+        handler: hook.map(|h| h.handler),
+    });
+    response
+}
+
+/// Run `hook`'s handler on a verified payload, returning the created id.
+async fn dispatch(
+    app: &tauri::AppHandle,
+    state: &WebhookState,
+    hook: &InboundWebhook,
+    payload: &Value,
+) -> Result<String, (u16, String)> {
+    if hook.handler == WebhookHandler::CreateTask {
+        let source = format!("webhook:{}", hook.name);
+        return crate::tasks::add(app, &app.state::<TaskState>(), new_task(payload), &source)
+            .map(|task| task.id)
+            .map_err(|e| (422, e));
+    }
+
+    let session = state.session.lock().unwrap().clone();
+    let session = session.ok_or_else(|| (503, "FlipTools isn't signed in; retry later".to_string()))?;
+    let client = supabase::client().map_err(|e| (500, e))?;
+    let user_id = session.user_id(&client).await.map_err(|e| (502, e))?;
+    let (table, row) = match hook.handler {
+        WebhookHandler::CreateDraft => ("listings", draft_row(payload, &user_id)),
+        WebhookHandler::AddWatchItem => ("saved_searches", watch_row(payload, &user_id)),
+        WebhookHandler::CreateTask => unreachable!("handled above"),
+    };
+    let row = row.map_err(|e| (422, e))?;
+    let req = session.rest(&client, Method::POST, table).header("Prefer", "return=representation").json(&row);
+    let inserted = supabase::send(req).await.map_err(|e| (502, e))?;
+    inserted[0]["id"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| (502, format!("The new {table} row wasn't returned")))
+}
+
+/// The first of `keys` holding a non-blank string, trimmed.
+fn text(payload: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .find_map(|k| payload.get(*k)?.as_str().map(str::trim).filter(|s| !s.is_empty()))
+        .map(str::to_string)
+}
+
+fn amount(payload: &Value, key: &str) -> Result<Option<f64>, String> {
+    match payload.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(v) => match v.as_f64() {
+            Some(n) if n.is_finite() && n >= 0.0 => Ok(Some((n * 100.0).round() / 100.0)),
+            _ => Err(format!("{key} must be a non-negative number")),
+        },
+    }
+}
+
+fn strings(payload: &Value, key: &str) -> Vec<String> {
+    payload
+        .get(key)
+        .and_then(Value::as_array)
+        .map(|a| a.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// A `listings` row for a create-draft payload: `title` plus optional
+/// description, price, cost, category, condition, images and tags.
+fn draft_row(payload: &Value, user_id: &str) -> Result<Value, String> {
+    let title = text(payload, &["title"]).ok_or("title is required")?;
+    Ok(json!({
+        "user_id": user_id,
+        "title": title,
+        "description": text(payload, &["description"]),
+        "price": amount(payload, "price")?,
+        "cost": amount(payload, "cost")?,
+        "category": text(payload, &["category"]),
+        "condition": text(payload, &["condition"]),
+        "images": strings(payload, "images"),
+        "tags": strings(payload, "tags"),
+        "status": "draft",
+        "platforms": {},
+    }))
+}
+
+/// A watched `saved_searches` row for an add-watch-item payload: `query`
+/// (or the item's `url`), with an optional name, notes and price.
+fn watch_row(payload: &Value, user_id: &str) -> Result<Value, String> {
+    let query = text(payload, &["query", "url"]).ok_or("query or url is required")?;
+    let price = amount(payload, "price")?;
+    let history: Vec<Value> = price
+        .map(|p| {
+            let date = crate::netlog::iso8601(crate::netlog::unix_millis());
+            json!({ "date": date, "avgPrice": p, "resultCount": 1 })
+        })
+        .into_iter()
+        .collect();
+    Ok(json!({
+        "user_id": user_id,
+        "name": text(payload, &["name", "title"]).unwrap_or_else(|| query.clone()),
+        "query": query,
+        "search_type": "keyword",
+        "notes": text(payload, &["notes"]),
+        "last_avg_price": price,
+        "result_snapshot": [],
+        "price_history": history,
+        "is_watching": true,
+    }))
+}
+
+fn new_task(payload: &Value) -> NewTask {
+    NewTask {
+        title: text(payload, &["title"]).unwrap_or_default(),
+        notes: text(payload, &["notes", "description"]),
+        due_date: text(payload, &["due_date"]),
+    }
+}
+
+fn verify_and_parse(
+    state: &WebhookState,
+    hook: &InboundWebhook,
+    req: &HttpRequest,
+) -> Result<Value, (u16, String)> {
+    let (Some(timestamp), Some(nonce), Some(signature)) = (
+        req.header("x-fliptools-timestamp"),
+        req.header("x-fliptools-nonce"),
+        req.header("x-fliptools-signature"),
+    ) else {
+        return Err((401, "missing signature headers".to_string()));
+    };
+
+    let now = crate::unix_now();
+    let ts: u64 = timestamp
+        .parse()
+        .map_err(|_| (401, "invalid timestamp".to_string()))?;
+    if now.abs_diff(ts) > REPLAY_WINDOW_SECS {
+        return Err((401, "timestamp outside replay window".to_string()));
+    }
+    if nonce.is_empty() || nonce.len() > 128 {
+        return Err((401, "invalid nonce".to_string()));
+    }
+
+    let expected = hex::decode(signature.trim_start_matches("sha256="))
+        .map_err(|_| (401, "malformed signature".to_string()))?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(hook.secret.as_bytes())
+        .map_err(|e| (500, e.to_string()))?;
+    mac.update(format!("{timestamp}.{nonce}.").as_bytes());
+    mac.update(&req.body);
+    mac.verify_slice(&expected)
+        .map_err(|_| (401, "signature mismatch".to_string()))?;
+
+    // Only remember nonces from correctly signed requests, otherwise anyone
+    // could burn nonces a legitimate sender is about to use.
+    {
+        let mut seen = state.seen_nonces.lock().unwrap();
+        seen.retain(|_, at| now.saturating_sub(*at) <= REPLAY_WINDOW_SECS);
+        let key = format!("{}:{nonce}", hook.name);
+        if seen.contains_key(&key) {
+            return Err((409, "replayed nonce".to_string()));
+        }
+        seen.insert(key, now);
+    }
+
+    let payload: Value = serde_json::from_slice(&req.body)
+        .map_err(|e| (400, format!("invalid JSON: {e}")))?;
+
+    let mut errors = Vec::new();
+    validate_schema(&hook.schema, &payload, "$", &mut errors);
+    if !errors.is_empty() {
+        return Err((422, format!("schema validation failed: {}", errors.join("; "))));
+    }
+    Ok(payload)
+}
+
+/// Validates the commonly used subset of JSON Schema: `type`, `enum`,
+/// `required`, `properties`, `additionalProperties: false`, `items`,
+/// `minLength`/`maxLength`, `minimum`/`maximum` and `minItems`/`maxItems`.
+fn validate_schema(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+
+    if let Some(ty) = schema.get("type") {
+        let allowed: Vec<&str> = match ty {
+            Value::String(s) => vec![s.as_str()],
+            Value::Array(a) => a.iter().filter_map(|v| v.as_str()).collect(),
+            _ => vec![],
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| matches_type(t, value)) {
+            errors.push(format!("{path}: expected {}", allowed.join(" | ")));
+            return;
+        }
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            errors.push(format!("{path}: value not in enum"));
+        }
+    }
+
+    match value {
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(|v| v.as_u64()) {
+                if len < min {
+                    errors.push(format!("{path}: shorter than {min} characters"));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(|v| v.as_u64()) {
+                if len > max {
+                    errors.push(format!("{path}: longer than {max} characters"));
+                }
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or(0.0);
+            if let Some(min) = schema.get("minimum").and_then(|v| v.as_f64()) {
+                if n < min {
+                    errors.push(format!("{path}: less than {min}"));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(|v| v.as_f64()) {
+                if n > max {
+                    errors.push(format!("{path}: greater than {max}"));
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(|v| v.as_u64()) {
+                if (items.len() as u64) < min {
+                    errors.push(format!("{path}: fewer than {min} items"));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(|v| v.as_u64()) {
+                if items.len() as u64 > max {
+                    errors.push(format!("{path}: more than {max} items"));
+                }
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_schema(item_schema, item, &format!("{path}[{i}]"), errors);
+                }
+            }
+        }
+        Value::Object(obj) => {
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for key in required.iter().filter_map(|k| k.as_str()) {
+                    if !obj.contains_key(key) {
+                        errors.push(format!("{path}.{key}: required"));
+                    }
+                }
+            }
+            let props = schema.get("properties").and_then(|p| p.as_object());
+            if let Some(props) = props {
+                for (key, sub) in props {
+                    if let Some(v) = obj.get(key) {
+                        validate_schema(sub, v, &format!("{path}.{key}"), errors);
+                    }
+                }
+            }
+            if schema.get("additionalProperties") == Some(&Value::Bool(false)) {
+                for key in obj.keys() {
+                    if !props.is_some_and(|p| p.contains_key(key)) {
+                        errors.push(format!("{path}.{key}: unexpected property"));
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn matches_type(ty: &str, value: &Value) -> bool {
+    match ty {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|f| f.fract() == 0.0),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "s3cret";
+
+    fn hook(name: &str) -> InboundWebhook {
+        InboundWebhook {
+            name: name.to_string(),
+            secret: SECRET.to_string(),
+            schema: json!({ "type": "object", "required": ["title"] }),
+            handler: WebhookHandler::CreateTask,
+            created_at: 0,
+        }
+    }
+
+    fn state() -> WebhookState {
+        WebhookState {
+            hooks: Mutex::new(HashMap::new()),
+            seen_nonces: Mutex::new(HashMap::new()),
+            log: Mutex::new(VecDeque::new()),
+            session: Mutex::new(None),
+        }
+    }
+
+    fn sign(secret: &str, timestamp: u64, nonce: &str, body: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("{timestamp}.{nonce}.{body}").as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    fn request(timestamp: u64, nonce: &str, signature: &str, body: &str) -> HttpRequest {
+        let headers = [
+            ("x-fliptools-timestamp", timestamp.to_string()),
+            ("x-fliptools-nonce", nonce.to_string()),
+            ("x-fliptools-signature", signature.to_string()),
+        ];
+        HttpRequest {
+            method: "POST".to_string(),
+            path: "/webhooks/scraper".to_string(),
+            headers: headers.into_iter().map(|(k, v)| (k.to_string(), v)).collect(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn signed(timestamp: u64, nonce: &str, body: &str) -> HttpRequest {
+        request(timestamp, nonce, &sign(SECRET, timestamp, nonce, body), body)
+    }
+
+    fn status(result: Result<Value, (u16, String)>) -> u16 {
+        result.map_or_else(|(status, _)| status, |_| 200)
+    }
+
+    const BODY: &str = r#"{"title":"Check lot 14"}"#;
+
+    #[test]
+    fn signature_must_match_secret_and_body() {
+        let (state, hook, now) = (state(), hook("scraper"), crate::unix_now());
+        assert_eq!(status(verify_and_parse(&state, &hook, &signed(now, "a", BODY))), 200);
+
+        let prefixed = format!("sha256={}", sign(SECRET, now, "b", BODY));
+        assert_eq!(status(verify_and_parse(&state, &hook, &request(now, "b", &prefixed, BODY))), 200);
+
+        let wrong_secret = sign("other", now, "c", BODY);
+        assert_eq!(status(verify_and_parse(&state, &hook, &request(now, "c", &wrong_secret, BODY))), 401);
+
+        let tampered = request(now, "d", &sign(SECRET, now, "d", BODY), r#"{"title":"Check lot 15"}"#);
+        assert_eq!(status(verify_and_parse(&state, &hook, &tampered)), 401);
+
+        // The nonce and timestamp are signed too.
+        let moved = request(now, "e", &sign(SECRET, now, "f", BODY), BODY);
+        assert_eq!(status(verify_and_parse(&state, &hook, &moved)), 401);
+        assert_eq!(status(verify_and_parse(&state, &hook, &request(now, "g", "not hex", BODY))), 401);
+
+        let mut unsigned = signed(now, "h", BODY);
+        unsigned.headers.remove("x-fliptools-signature");
+        assert_eq!(status(verify_and_parse(&state, &hook, &unsigned)), 401);
+    }
+
+    #[test]
+    fn timestamps_outside_the_replay_window_are_refused() {
+        let (state, hook, now) = (state(), hook("scraper"), crate::unix_now());
+        let edge = now - REPLAY_WINDOW_SECS + 5;
+        assert_eq!(status(verify_and_parse(&state, &hook, &signed(edge, "a", BODY))), 200);
+        let stale = now - REPLAY_WINDOW_SECS - 5;
+        assert_eq!(status(verify_and_parse(&state, &hook, &signed(stale, "b", BODY))), 401);
+        let future = now + REPLAY_WINDOW_SECS + 5;
+        assert_eq!(status(verify_and_parse(&state, &hook, &signed(future, "c", BODY))), 401);
+    }
+
+    #[test]
+    fn nonces_are_single_use_per_webhook() {
+        let (state, now) = (state(), crate::unix_now());
+        let (scraper, other) = (hook("scraper"), hook("other"));
+        assert_eq!(status(verify_and_parse(&state, &scraper, &signed(now, "n1", BODY))), 200);
+        assert_eq!(status(verify_and_parse(&state, &scraper, &signed(now, "n1", BODY))), 409);
+        assert_eq!(status(verify_and_parse(&state, &other, &signed(now, "n1", BODY))), 200);
+
+        // A forged delivery doesn't use up the nonce.
+        assert_eq!(status(verify_and_parse(&state, &scraper, &request(now, "n2", "00", BODY))), 401);
+        assert_eq!(status(verify_and_parse(&state, &scraper, &signed(now, "n2", BODY))), 200);
+
+        // Nonces are forgotten once their window has passed.
+        state.seen_nonces.lock().unwrap().values_mut().for_each(|at| *at -= REPLAY_WINDOW_SECS + 1);
+        assert_eq!(state.compact(LOG_CAPACITY), 3);
+    }
+
+    #[test]
+    fn payload_must_satisfy_the_schema() {
+        let (state, hook, now) = (state(), hook("scraper"), crate::unix_now());
+        assert_eq!(status(verify_and_parse(&state, &hook, &signed(now, "a", "{}"))), 422);
+        assert_eq!(status(verify_and_parse(&state, &hook, &signed(now, "b", "not json"))), 400);
+    }
+
+    #[test]
+    fn payloads_map_to_handler_rows() {
+        let draft = draft_row(&json!({ "title": " Levi's 501 ", "price": 24.999, "tags": ["denim", 3] }), "u1").unwrap();
+        assert_eq!(draft["title"], "Levi's 501");
+        assert_eq!(draft["price"], 25.0);
+        assert_eq!(draft["tags"], json!(["denim"]));
+        assert_eq!((&draft["status"], &draft["user_id"], &draft["cost"]), (&json!("draft"), &json!("u1"), &Value::Null));
+        assert!(draft_row(&json!({ "title": "" }), "u1").is_err());
+        assert!(draft_row(&json!({ "title": "Coat", "price": -1 }), "u1").is_err());
+
+        let watch = watch_row(&json!({ "url": "https://example.com/lot/14", "price": 40 }), "u1").unwrap();
+        assert_eq!(watch["query"], "https://example.com/lot/14");
+        assert_eq!(watch["name"], watch["query"]);
+        assert_eq!(watch["is_watching"], true);
+        assert_eq!(watch["price_history"][0]["avgPrice"], 40.0);
+        assert!(watch_row(&json!({ "name": "No query" }), "u1").is_err());
+
+        let task = new_task(&json!({ "title": "Ship it", "description": "Before Friday", "due_date": "2026-10-20" }));
+        assert_eq!((task.title.as_str(), task.notes.as_deref()), ("Ship it", Some("Before Friday")));
+        assert_eq!(task.due_date.as_deref(), Some("2026-10-20"));
+    }
+}
@@ -0,0 +1,578 @@
+// ── Marketplace write queue ────────────────────────────────────────────────
+// Every mutating marketplace call issued from Rust goes through `submit`.
+// Writes to the same marketplace run one at a time with a minimum gap between
+// them, so bulk commands can't trip anti-spam heuristics no matter how many
+// the frontend fires at once. Writes a user is waiting on are submitted as
+// `Priority::Interactive` and jump ahead of queued bulk work (see rate_limit).
+
+use crate::metrics::METRICS;
+use crate::rate_limit::{Lane, LaneStatus, Priority};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+#[derive(Default)]
+pub struct WriteQueueState {
+    lanes: Mutex<HashMap<String, Arc<Lane>>>,
+}
+
+impl WriteQueueState {
+    fn lane(&self, marketplace: &str) -> Arc<Lane> {
+        self.lanes
+            .lock()
+            .unwrap()
+            .entry(marketplace.to_string())
+            .or_default()
+            .clone()
+    }
+
+    pub fn status(&self) -> Vec<LaneStatus> {
+        let mut lanes: Vec<LaneStatus> =
+            self.lanes.lock().unwrap().iter().map(|(m, lane)| lane.status(m, pacing(m))).collect();
+        lanes.sort_by(|a, b| a.name.cmp(&b.name));
+        lanes
+    }
+}
+
+/// Minimum gap between two writes to the same marketplace.
+fn pacing(marketplace: &str) -> Duration {
+    match marketplace {
+        "depop" => Duration::from_secs(3),
+        "ebay" => Duration::from_millis(750),
+        _ => Duration::from_secs(2),
+    }
+}
+
+/// Run one write operation in the marketplace's lane.
+pub async fn submit<F, Fut, T>(app: &tauri::AppHandle, marketplace: &str, priority: Priority, op: F) -> T
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = T>,
+{
+    let lane = app.state::<WriteQueueState>().lane(marketplace);
+    let queued = Instant::now();
+    METRICS.write_queue_depth.inc();
+    let turn = lane.turn(priority).await;
+    turn.pace(pacing(marketplace)).await;
+    METRICS.write_queue_wait.since(queued);
+    let out = op().await;
+    METRICS.write_queue_depth.dec();
+    // The gap runs from the end of this write.
+    turn.stamp();
+    out
+}
+
+// ── Conflict detection ─────────────────────────────────────────────────────
+// A listing can be edited elsewhere (the marketplace's own app) while a write
+// waits in its lane. `submit_checked` re-fetches the listing right before the
+// write and compares it with what the caller saw when it queued the job; on a
+// mismatch the write is skipped and a `listing-conflict` event carries both
+// versions so the user can decide.
+
+/// How to read a marketplace's listing records.
+pub struct Versioning {
+    /// Last-modified marker / version of a record, if it exposes one.
+    pub version_of: fn(&Value) -> Option<String>,
+    /// Fields worth showing the user (and compared when there's no version).
+    pub fields: &'static [&'static str],
+}
+
+impl Versioning {
+    fn relevant(&self, record: &Value) -> Value {
+        let mut out = serde_json::Map::new();
+        for key in self.fields {
+            if let Some(v) = record.get(*key) {
+                out.insert((*key).to_string(), v.clone());
+            }
+        }
+        Value::Object(out)
+    }
+}
+
+/// The listing as it was when the job was queued.
+pub struct Expected<'a> {
+    pub listing_id: &'a str,
+    pub record: &'a Value,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ListingConflict {
+    marketplace: String,
+    listing_id: String,
+    queued_version: Option<String>,
+    current_version: Option<String>,
+    queued: Value,
+    current: Value,
+}
+
+pub enum Checked<T> {
+    Applied(T),
+    Conflict(ListingConflict),
+}
+
+/// The conflict, if `current` differs from `expected`: by version when both
+/// have one, otherwise by the fields worth showing.
+fn conflict(
+    versioning: &Versioning,
+    marketplace: &str,
+    expected: &Expected<'_>,
+    current: &Value,
+) -> Option<ListingConflict> {
+    let queued_version = (versioning.version_of)(expected.record);
+    let current_version = (versioning.version_of)(current);
+    let queued = versioning.relevant(expected.record);
+    let current = versioning.relevant(current);
+    let changed = match (&queued_version, &current_version) {
+        (Some(q), Some(c)) => q != c,
+        _ => queued != current,
+    };
+    changed.then(|| ListingConflict {
+        marketplace: marketplace.to_string(),
+        listing_id: expected.listing_id.to_string(),
+        queued_version,
+        current_version,
+        queued,
+        current,
+    })
+}
+
+/// `submit` with a conflict check. Inside the lane, `fetch_current` reloads
+/// the listing; if it changed since `expected` the write is skipped (unless
+/// `force`) and "listing-conflict" is emitted. Otherwise `op` runs with the
+/// freshly fetched record.
+#[allow(clippy::too_many_arguments)]
+pub async fn submit_checked<Fc, FcFut, F, Fut, T>(
+    app: &tauri::AppHandle,
+    marketplace: &str,
+    priority: Priority,
+    versioning: &Versioning,
+    expected: Expected<'_>,
+    force: bool,
+    fetch_current: Fc,
+    op: F,
+) -> Result<Checked<T>, String>
+where
+    Fc: FnOnce() -> FcFut,
+    FcFut: Future<Output = Result<Value, String>>,
+    F: FnOnce(Value) -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    submit(app, marketplace, priority, || async {
+        let current = fetch_current().await?;
+        if !force {
+            if let Some(conflict) = conflict(versioning, marketplace, &expected, &current) {
+                let _ = app.emit("listing-conflict", conflict.clone());
+                return Ok(Checked::Conflict(conflict));
+            }
+        }
+        op(current).await.map(Checked::Applied)
+    })
+    .await
+}
+
+// ── Idempotency ────────────────────────────────────────────────────────────
+// Every write run through `idempotent` gets a key that is persisted before the
+// first attempt. When an attempt fails ambiguously (timed out or dropped after
+// the request went out) the operation may or may not have happened, so before
+// trying again the caller's `lookup` is asked for evidence that it did:
+//
+//   - updates (PUT of a whole listing) are safe to repeat: use `resend`, whose
+//     lookup never finds anything;
+//   - create-type operations pass a real lookup (e.g. search recent listings
+//     for a fingerprint or an exact title + price inside a time window) and
+//     reconcile to what they find instead of creating a duplicate.
+//
+// Intents still pending at startup were cut off by a crash or quit and are
+// marked unknown, for the user to check with `list_write_intents`.
+
+const INTENT_STORE: &str = "write_intents";
+const MAX_ATTEMPTS: u32 = 3;
+/// Finished intents kept for review; unresolved ones are always kept.
+const KEEP_FINISHED: usize = 200;
+/// Prefix of error strings whose outcome is unknown.
+pub const AMBIGUOUS: &str = "outcome unknown";
+
+/// Transport error -> error string, marking failures that may have reached
+/// the marketplace as ambiguous.
+pub fn request_error(e: reqwest::Error) -> String {
+    if e.is_timeout() || !(e.is_connect() || e.is_builder()) {
+        format!("{AMBIGUOUS}: {e}")
+    } else {
+        format!("request: {e}")
+    }
+}
+
+pub fn is_ambiguous(error: &str) -> bool {
+    error.starts_with(AMBIGUOUS)
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IntentStatus {
+    Pending,
+    Succeeded,
+    /// An ambiguous attempt turned out to have worked.
+    Reconciled,
+    Failed,
+    /// Gave up (or was interrupted) without knowing whether it happened.
+    Unknown,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WriteIntent {
+    key: String,
+    marketplace: String,
+    operation: String,
+    target: Option<String>,
+    created_at: u64,
+    attempts: u32,
+    status: IntentStatus,
+    error: Option<String>,
+    /// It failed while the marketplace was marked degraded (see outage.rs).
+    #[serde(default)]
+    during_outage: bool,
+}
+
+pub struct IntentState {
+    intents: Mutex<Vec<WriteIntent>>,
+}
+
+impl IntentState {
+    pub fn load(app: &tauri::AppHandle) -> Self {
+        let mut intents: Vec<WriteIntent> = crate::store::load(app, INTENT_STORE);
+        for intent in intents.iter_mut().filter(|i| i.status == IntentStatus::Pending) {
+            intent.status = IntentStatus::Unknown;
+            intent.error.get_or_insert_with(|| "interrupted before the outcome was known".to_string());
+        }
+        IntentState { intents: Mutex::new(intents) }
+    }
+
+    /// Intents given as (marketplace, operation, target, status), for tests
+    /// of code that reads them.
+    #[cfg(test)]
+    pub fn with(intents: &[(&str, &str, &str, IntentStatus)]) -> Self {
+        let intents = intents
+            .iter()
+            .enumerate()
+            .map(|(i, (marketplace, operation, target, status))| WriteIntent {
+                key: i.to_string(),
+                marketplace: marketplace.to_string(),
+                operation: operation.to_string(),
+                target: Some(target.to_string()),
+                created_at: 0,
+                attempts: 1,
+                status: *status,
+                error: None,
+                during_outage: false,
+            })
+            .collect();
+        IntentState { intents: Mutex::new(intents) }
+    }
+
+    /// Unresolved (pending or unknown) intents aimed at `target` on
+    /// `marketplace`, newest first, as (operation, status).
+    pub fn unresolved_for(&self, marketplace: &str, target: &str) -> Vec<(String, IntentStatus)> {
+        self.intents
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|i| i.marketplace == marketplace && i.target.as_deref() == Some(target))
+            .filter(|i| matches!(i.status, IntentStatus::Pending | IntentStatus::Unknown))
+            .map(|i| (i.operation.clone(), i.status))
+            .collect()
+    }
+}
+
+fn save_intent(app: &tauri::AppHandle, intent: &WriteIntent) {
+    let state = app.state::<IntentState>();
+    let mut intents = state.intents.lock().unwrap();
+    let status_changed = intents.iter().find(|i| i.key == intent.key).map_or(true, |i| i.status != intent.status);
+    match intents.iter_mut().find(|i| i.key == intent.key) {
+        Some(existing) => *existing = intent.clone(),
+        None => intents.push(intent.clone()),
+    }
+    let finished = intents
+        .iter()
+        .filter(|i| matches!(i.status, IntentStatus::Succeeded | IntentStatus::Reconciled | IntentStatus::Failed))
+        .count();
+    let mut excess = finished.saturating_sub(KEEP_FINISHED);
+    intents.retain(|i| {
+        let finished = matches!(i.status, IntentStatus::Succeeded | IntentStatus::Reconciled | IntentStatus::Failed);
+        if finished && excess > 0 {
+            excess -= 1;
+            return false;
+        }
+        true
+    });
+    if let Err(e) = crate::store::save(app, INTENT_STORE, &*intents) {
+        log::warn!("saving write intents failed: {e}");
+    }
+    drop(intents);
+    if let Some(target) = intent.target.as_deref().filter(|_| status_changed) {
+        crate::crosslist::notify(app, &intent.marketplace, &[target]);
+    }
+}
+
+/// Run `op` with an idempotency key, retrying ambiguous failures up to
+/// MAX_ATTEMPTS times. Before each retry `lookup` gets the key and may return
+/// the result of the earlier attempt it found evidence of. Call inside a lane
+/// (`submit` / `submit_checked`).
+pub async fn idempotent<F, Fut, L, LFut, T>(
+    app: &tauri::AppHandle,
+    marketplace: &str,
+    operation: &str,
+    target: Option<&str>,
+    lookup: L,
+    op: F,
+) -> Result<T, String>
+where
+    F: FnMut(&str) -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+    L: FnMut(&str) -> LFut,
+    LFut: Future<Output = Result<Option<T>, String>>,
+{
+    let intent = WriteIntent {
+        key: hex::encode(rand::random::<[u8; 16]>()),
+        marketplace: marketplace.to_string(),
+        operation: operation.to_string(),
+        target: target.map(str::to_string),
+        created_at: crate::unix_now(),
+        attempts: 0,
+        status: IntentStatus::Pending,
+        error: None,
+        during_outage: false,
+    };
+    let degraded = || crate::outage::is_degraded(marketplace);
+    attempt(intent, |intent| save_intent(app, intent), degraded, lookup, op).await
+}
+
+/// The retry loop behind `idempotent`; `record` persists each change to the
+/// intent and `degraded` says whether the marketplace is in an outage.
+async fn attempt<R, D, F, Fut, L, LFut, T>(
+    mut intent: WriteIntent,
+    mut record: R,
+    degraded: D,
+    mut lookup: L,
+    mut op: F,
+) -> Result<T, String>
+where
+    R: FnMut(&WriteIntent),
+    D: Fn() -> bool,
+    F: FnMut(&str) -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+    L: FnMut(&str) -> LFut,
+    LFut: Future<Output = Result<Option<T>, String>>,
+{
+    let operation = intent.operation.clone();
+    record(&intent);
+
+    loop {
+        if intent.attempts > 0 {
+            match lookup(&intent.key).await {
+                Ok(Some(found)) => {
+                    intent.status = IntentStatus::Reconciled;
+                    record(&intent);
+                    return Ok(found);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    intent.status = IntentStatus::Unknown;
+                    intent.error = Some(format!("couldn't check for an earlier attempt: {e}"));
+                    intent.during_outage = degraded();
+                    record(&intent);
+                    return Err(format!("{AMBIGUOUS}: couldn't confirm whether {operation} went through ({e})"));
+                }
+            }
+        }
+        intent.attempts += 1;
+        match op(&intent.key).await {
+            Ok(out) => {
+                intent.status = IntentStatus::Succeeded;
+                intent.error = None;
+                record(&intent);
+                return Ok(out);
+            }
+            Err(e) if is_ambiguous(&e) && intent.attempts < MAX_ATTEMPTS => {
+                log::warn!("{operation} attempt {} ambiguous, reconciling: {e}", intent.attempts);
+                intent.error = Some(e);
+                record(&intent);
+                tokio::time::sleep(Duration::from_secs(2u64 << intent.attempts)).await;
+            }
+            Err(e) => {
+                intent.status = if is_ambiguous(&e) { IntentStatus::Unknown } else { IntentStatus::Failed };
+                intent.error = Some(e.clone());
+                intent.during_outage = degraded();
+                record(&intent);
+                return Err(e);
+            }
+        }
+    }
+}
+
+/// `idempotent` for operations that are safe to repeat (full updates).
+pub async fn resend<F, Fut, T>(
+    app: &tauri::AppHandle,
+    marketplace: &str,
+    operation: &str,
+    target: Option<&str>,
+    op: F,
+) -> Result<T, String>
+where
+    F: FnMut(&str) -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    idempotent(app, marketplace, operation, target, |_| async { Ok(None) }, op).await
+}
+
+/// Recent write intents, newest first; `unresolved_only` keeps pending and
+/// unknown ones — the writes that need a manual check.
+#[tauri::command]
+pub fn list_write_intents(state: tauri::State<'_, IntentState>, unresolved_only: Option<bool>) -> Vec<WriteIntent> {
+    let unresolved_only = unresolved_only.unwrap_or(false);
+    state
+        .intents
+        .lock()
+        .unwrap()
+        .iter()
+        .rev()
+        .filter(|i| !unresolved_only || matches!(i.status, IntentStatus::Pending | IntentStatus::Unknown))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::cell::{Cell, RefCell};
+
+    fn intent() -> WriteIntent {
+        WriteIntent {
+            key: "k".to_string(),
+            marketplace: "depop".to_string(),
+            operation: "upload_picture".to_string(),
+            target: Some("p1".to_string()),
+            created_at: 0,
+            attempts: 0,
+            status: IntentStatus::Pending,
+            error: None,
+            during_outage: false,
+        }
+    }
+
+    fn ambiguous() -> String {
+        format!("{AMBIGUOUS}: timed out")
+    }
+
+    /// Runs `attempt` with `outcomes` as the op's results in order, returning
+    /// the result, how many times the op ran and the final intent status.
+    async fn run(
+        outcomes: Vec<Result<u32, String>>,
+        lookup: impl FnMut(&str) -> std::future::Ready<Result<Option<u32>, String>>,
+    ) -> (Result<u32, String>, usize, IntentStatus) {
+        let outcomes = RefCell::new(outcomes.into_iter());
+        let calls = Cell::new(0);
+        let last = RefCell::new(IntentStatus::Pending);
+        let out = attempt(
+            intent(),
+            |i| *last.borrow_mut() = i.status,
+            || false,
+            lookup,
+            |_| {
+                calls.set(calls.get() + 1);
+                std::future::ready(outcomes.borrow_mut().next().expect("op ran too often"))
+            },
+        )
+        .await;
+        let status = *last.borrow();
+        (out, calls.get(), status)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resend_repeats_an_ambiguous_write() {
+        let (out, calls, status) = run(vec![Err(ambiguous()), Ok(7)], |_| std::future::ready(Ok(None))).await;
+        assert_eq!(out, Ok(7));
+        assert_eq!(calls, 2);
+        assert!(status == IntentStatus::Succeeded);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_found_earlier_attempt_is_reconciled_without_repeating() {
+        let (out, calls, status) = run(vec![Err(ambiguous())], |_| std::future::ready(Ok(Some(3)))).await;
+        assert_eq!(out, Ok(3));
+        assert_eq!(calls, 1);
+        assert!(status == IntentStatus::Reconciled);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn an_unverifiable_write_is_left_unknown() {
+        let (out, calls, status) =
+            run(vec![Err(ambiguous())], |_| std::future::ready(Err("can't be looked up".to_string()))).await;
+        assert!(is_ambiguous(&out.unwrap_err()));
+        assert_eq!(calls, 1);
+        assert!(status == IntentStatus::Unknown);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn definite_failures_and_exhausted_retries() {
+        let (out, calls, status) = run(vec![Err("Depop returned 400".to_string())], |_| std::future::ready(Ok(None))).await;
+        assert_eq!(out, Err("Depop returned 400".to_string()));
+        assert_eq!(calls, 1);
+        assert!(status == IntentStatus::Failed);
+
+        let attempts = (0..MAX_ATTEMPTS).map(|_| Err(ambiguous())).collect();
+        let (out, calls, status) = run(attempts, |_| std::future::ready(Ok(None))).await;
+        assert!(is_ambiguous(&out.unwrap_err()));
+        assert_eq!(calls, MAX_ATTEMPTS as usize);
+        assert!(status == IntentStatus::Unknown);
+    }
+
+    fn versioning() -> Versioning {
+        Versioning { version_of: crate::depop::updated_marker, fields: &["title", "price"] }
+    }
+
+    fn check(queued: &Value, current: &Value) -> Option<ListingConflict> {
+        conflict(&versioning(), "depop", &Expected { listing_id: "p1", record: queued }, current)
+    }
+
+    #[test]
+    fn unchanged_listings_pass_through() {
+        let queued = json!({ "title": "Coat", "price": 20, "updated_at": "2024-05-01T10:00:00Z", "likes": 3 });
+        assert!(check(&queued, &queued).is_none());
+        // Fields outside the version and `fields` don't count.
+        let liked = json!({ "title": "Coat", "price": 20, "updated_at": "2024-05-01T10:00:00Z", "likes": 9 });
+        assert!(check(&queued, &liked).is_none());
+    }
+
+    #[test]
+    fn a_newer_version_is_a_conflict() {
+        let queued = json!({ "title": "Coat", "price": 20, "updated_at": "2024-05-01T10:00:00Z" });
+        let current = json!({ "title": "Coat", "price": 18, "updated_at": "2024-05-02T08:00:00Z" });
+        let conflict = check(&queued, &current).unwrap();
+        assert_eq!(conflict.listing_id, "p1");
+        assert_eq!(conflict.queued_version.as_deref(), Some("2024-05-01T10:00:00Z"));
+        assert_eq!(conflict.current_version.as_deref(), Some("2024-05-02T08:00:00Z"));
+        assert_eq!((&conflict.queued["price"], &conflict.current["price"]), (&json!(20), &json!(18)));
+
+        // Versions decide when both sides have one, even if the fields match.
+        let touched = json!({ "title": "Coat", "price": 20, "updated_at": "2024-05-03T00:00:00Z" });
+        assert!(check(&queued, &touched).is_some());
+    }
+
+    #[test]
+    fn without_versions_the_fields_are_compared() {
+        let queued = json!({ "title": "Coat", "price": 20 });
+        assert!(check(&queued, &json!({ "title": "Coat", "price": 20, "likes": 4 })).is_none());
+        let conflict = check(&queued, &json!({ "title": "Wool coat", "price": 20 })).unwrap();
+        assert_eq!((conflict.queued_version, conflict.current_version), (None, None));
+        assert_eq!(conflict.current["title"], "Wool coat");
+        // Only one side versioned: fall back to the fields.
+        let versioned = json!({ "title": "Coat", "price": 20, "updated_at": "2024-05-01T10:00:00Z" });
+        assert!(check(&queued, &versioned).is_none());
+    }
+}
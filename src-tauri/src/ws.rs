@@ -0,0 +1,322 @@
+// ── WebSocket client ───────────────────────────────────────────────────────
+// Depop's web app gets sold/offer notifications over a WebSocket; `ws_connect`
+// lets the UI subscribe to the same channel instead of polling. The socket is
+// a tokio-tungstenite client over a connection opened through the configured
+// proxy (proxy::connect), so it goes where every other request goes.
+//
+// Connections belong to the app, not a window: they live in WsState keyed
+// by connection id, survive a reload, and their events (`ws-message`,
+// `ws-closed`) go to every window. Binary frames arrive base64-encoded. A
+// ping goes out every PING_INTERVAL; a socket that's silent (no pong or any
+// other frame) for IDLE_TIMEOUT is dropped. There's no automatic reconnect;
+// `ws-closed` says why a socket ended so the caller can open a new one.
+
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, WebSocketConfig};
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+const MAX_CONNECTIONS: usize = 8;
+/// Largest message accepted, after joining fragments.
+const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+const PING_INTERVAL: Duration = Duration::from_secs(20);
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(20);
+/// How long to wait for the server to answer our close frame.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Socket = WebSocketStream<MaybeTlsStream<Box<dyn crate::proxy::Socket>>>;
+
+enum Outgoing {
+    Text(String),
+    Binary(Vec<u8>),
+    Close,
+}
+
+struct Connection {
+    /// Keeps a finished task from removing a newer connection that reused
+    /// its id.
+    generation: u64,
+    sender: mpsc::UnboundedSender<Outgoing>,
+}
+
+#[derive(Default)]
+pub struct WsState {
+    connections: Mutex<HashMap<String, Connection>>,
+    next_generation: Mutex<u64>,
+}
+
+#[derive(Clone, Serialize)]
+struct WsMessage<'a> {
+    connection_id: &'a str,
+    data: String,
+    /// `data` is base64 of a binary frame.
+    binary: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct WsClosed<'a> {
+    connection_id: &'a str,
+    /// The close code the server sent, if it sent one.
+    code: Option<u16>,
+    reason: String,
+}
+
+/// The ws:// or wss:// URL for `url` (http(s) URLs are accepted too).
+fn ws_url(url: &str) -> Result<reqwest::Url, String> {
+    let mut parsed = reqwest::Url::parse(url).map_err(|e| format!("invalid URL: {e}"))?;
+    let scheme = match parsed.scheme() {
+        "ws" | "http" => "ws",
+        "wss" | "https" => "wss",
+        other => return Err(format!("unsupported scheme {other} (use ws:// or wss://)")),
+    };
+    parsed.set_scheme(scheme).map_err(|_| "invalid URL".to_string())?;
+    if parsed.host_str().is_none() {
+        return Err("URL has no host".to_string());
+    }
+    Ok(parsed)
+}
+
+async fn handshake(url: reqwest::Url, headers: &HashMap<String, String>, proxy: Option<&str>) -> Result<Socket, String> {
+    let host = url.host_str().unwrap_or_default();
+    let port = url.port_or_known_default().unwrap_or(443);
+    let stream = crate::proxy::connect(proxy, host, port).await?;
+
+    let mut request = url.as_str().into_client_request().map_err(|e| format!("request: {e}"))?;
+    for (k, v) in headers {
+        let name = tungstenite::http::HeaderName::from_bytes(k.as_bytes()).map_err(|e| format!("header {k}: {e}"))?;
+        let value = tungstenite::http::HeaderValue::from_str(v).map_err(|e| format!("header {k}: {e}"))?;
+        request.headers_mut().insert(name, value);
+    }
+    let config = WebSocketConfig::default()
+        .max_message_size(Some(MAX_MESSAGE_BYTES))
+        .max_frame_size(Some(MAX_MESSAGE_BYTES));
+    match tokio_tungstenite::client_async_tls_with_config(request, stream, Some(config), None).await {
+        Ok((socket, resp)) => {
+            crate::outage::record_status(url.as_str(), resp.status().as_u16());
+            Ok(socket)
+        }
+        Err(tungstenite::Error::Http(resp)) => {
+            crate::outage::record_status(url.as_str(), resp.status().as_u16());
+            Err(format!("{url} answered {} instead of switching to WebSocket", resp.status()))
+        }
+        Err(e) => Err(format!("handshake: {e}")),
+    }
+}
+
+/// Pump one socket until it closes; returns the server's close code, if
+/// any, and why it ended.
+async fn run(
+    app: &tauri::AppHandle,
+    id: &str,
+    socket: Socket,
+    mut outgoing: mpsc::UnboundedReceiver<Outgoing>,
+) -> (Option<u16>, String) {
+    let (mut writer, mut reader) = socket.split();
+    let mut ping = tokio::time::interval_at(Instant::now() + PING_INTERVAL, PING_INTERVAL);
+    let mut last_seen = Instant::now();
+    let mut closing: Option<Instant> = None;
+    let ended = loop {
+        if closing.is_some_and(|since| since.elapsed() > CLOSE_TIMEOUT) {
+            break (None, "closed".to_string());
+        }
+        tokio::select! {
+            message = reader.next() => {
+                let message = match message {
+                    Some(Ok(message)) => message,
+                    Some(Err(tungstenite::Error::ConnectionClosed)) | None => {
+                        break (None, "connection closed".to_string());
+                    }
+                    Some(Err(e)) => break (None, e.to_string()),
+                };
+                last_seen = Instant::now();
+                // Pings are answered by tungstenite itself.
+                let (data, binary) = match message {
+                    Message::Text(text) => (text.to_string(), false),
+                    Message::Binary(bytes) => (base64::engine::general_purpose::STANDARD.encode(&bytes), true),
+                    Message::Close(frame) => {
+                        let code = frame.as_ref().map(|f| u16::from(f.code));
+                        let reason = frame.map(|f| f.reason.to_string()).unwrap_or_default();
+                        // tungstenite queues the reply; this sends it.
+                        let _ = writer.flush().await;
+                        break (code, if reason.is_empty() { "server closed the connection".to_string() } else { reason });
+                    }
+                    Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => continue,
+                };
+                let _ = app.emit("ws-message", WsMessage { connection_id: id, data, binary });
+            }
+            message = outgoing.recv(), if closing.is_none() => {
+                let sent = match message {
+                    Some(Outgoing::Text(text)) => writer.send(Message::text(text)).await,
+                    Some(Outgoing::Binary(bytes)) => writer.send(Message::binary(bytes)).await,
+                    Some(Outgoing::Close) | None => {
+                        closing = Some(Instant::now());
+                        writer.send(Message::Close(Some(CloseFrame { code: CloseCode::Normal, reason: "".into() }))).await
+                    }
+                };
+                if let Err(e) = sent {
+                    break (None, e.to_string());
+                }
+            }
+            _ = ping.tick() => {
+                if last_seen.elapsed() > IDLE_TIMEOUT {
+                    break (None, format!("no response for {}s", IDLE_TIMEOUT.as_secs()));
+                }
+                if let Err(e) = writer.send(Message::Ping(Default::default())).await {
+                    break (None, e.to_string());
+                }
+            }
+            _ = tokio::time::sleep(CLOSE_TIMEOUT), if closing.is_some() => {}
+        }
+    };
+    let _ = writer.close().await;
+    ended
+}
+
+/// Open a WebSocket to `url` (ws:// or wss://) under `connection_id`. Resolves
+/// once the handshake is done; frames then arrive as `ws-message` until
+/// `ws_close(connection_id)` or the socket ends (`ws-closed`).
+#[tauri::command]
+pub async fn ws_connect(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, WsState>,
+    proxy_state: tauri::State<'_, crate::proxy::ProxyState>,
+    url: String,
+    headers: Option<HashMap<String, String>>,
+    connection_id: String,
+) -> Result<(), String> {
+    let id = connection_id.trim().to_string();
+    if id.is_empty() {
+        return Err("connection_id can't be empty".to_string());
+    }
+    let target = ws_url(&url)?;
+    {
+        let connections = state.connections.lock().unwrap();
+        if connections.contains_key(&id) {
+            return Err(format!("Connection {id} is already open"));
+        }
+        if connections.len() >= MAX_CONNECTIONS {
+            return Err(format!("At most {MAX_CONNECTIONS} WebSocket connections can be open at once"));
+        }
+    }
+    let headers = headers.unwrap_or_default();
+    let socket = tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake(target, &headers, proxy_state.url().as_deref()))
+        .await
+        .map_err(|_| format!("no handshake within {}s", HANDSHAKE_TIMEOUT.as_secs()))??;
+
+    let mut connections = state.connections.lock().unwrap();
+    // Other connects may have finished while this one was handshaking.
+    if connections.contains_key(&id) {
+        return Err(format!("Connection {id} is already open"));
+    }
+    if connections.len() >= MAX_CONNECTIONS {
+        return Err(format!("At most {MAX_CONNECTIONS} WebSocket connections can be open at once"));
+    }
+    let generation = {
+        let mut next = state.next_generation.lock().unwrap();
+        *next += 1;
+        *next
+    };
+    let (sender, outgoing) = mpsc::unbounded_channel();
+    let task_app = app.clone();
+    let task_id = id.clone();
+    tauri::async_runtime::spawn(async move {
+        let (code, reason) = run(&task_app, &task_id, socket, outgoing).await;
+        log::info!("WebSocket {task_id} closed: {reason}");
+        // Waits for ws_connect to finish registering this task.
+        let state = task_app.state::<WsState>();
+        let mut connections = state.connections.lock().unwrap();
+        if connections.get(&task_id).is_some_and(|c| c.generation == generation) {
+            connections.remove(&task_id);
+        }
+        drop(connections);
+        let _ = task_app.emit("ws-closed", WsClosed { connection_id: &task_id, code, reason });
+    });
+    connections.insert(id, Connection { generation, sender });
+    Ok(())
+}
+
+/// Send a text message, or a binary one with `binary: true` (`message` is
+/// then base64).
+#[tauri::command]
+pub fn ws_send(
+    state: tauri::State<'_, WsState>,
+    connection_id: String,
+    message: String,
+    binary: Option<bool>,
+) -> Result<(), String> {
+    let outgoing = if binary.unwrap_or(false) {
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(message.trim())
+            .map_err(|e| format!("message: {e}"))?;
+        Outgoing::Binary(bytes)
+    } else {
+        Outgoing::Text(message)
+    };
+    let connections = state.connections.lock().unwrap();
+    let connection =
+        connections.get(connection_id.trim()).ok_or_else(|| format!("Connection {connection_id} isn't open"))?;
+    connection.sender.send(outgoing).map_err(|_| format!("Connection {connection_id} is closing"))
+}
+
+/// Close a connection opened with `ws_connect`. False when it isn't open;
+/// `ws-closed` follows once the server has answered.
+#[tauri::command]
+pub fn ws_close(state: tauri::State<'_, WsState>, connection_id: String) -> bool {
+    match state.connections.lock().unwrap().get(connection_id.trim()) {
+        Some(connection) => connection.sender.send(Outgoing::Close).is_ok(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn handshakes_with_headers_and_reads_messages() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+            #[allow(clippy::result_large_err)] // the callback's signature is tungstenite's
+            fn check(req: &Request, resp: Response) -> Result<Response, ErrorResponse> {
+                assert_eq!(req.headers()["x-token"], "abc");
+                Ok(resp)
+            }
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_hdr_async(tcp, check).await.unwrap();
+            socket.send(Message::text("sold")).await.unwrap();
+        });
+        let headers = HashMap::from([("X-Token".to_string(), "abc".to_string())]);
+        let url = ws_url(&format!("http://127.0.0.1:{port}/events")).unwrap();
+        let mut socket = handshake(url, &headers, None).await.unwrap();
+        assert_eq!(socket.next().await.unwrap().unwrap(), Message::text("sold"));
+    }
+
+    #[tokio::test]
+    async fn a_refused_upgrade_is_an_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut tcp, _) = listener.accept().await.unwrap();
+            let _ = tcp.write_all(b"HTTP/1.1 403 Forbidden\r\ncontent-length: 0\r\n\r\n").await;
+        });
+        let url = ws_url(&format!("ws://127.0.0.1:{port}/")).unwrap();
+        let err = handshake(url, &HashMap::new(), None).await.err().unwrap();
+        assert!(err.contains("403"), "{err}");
+        assert!(ws_url("ftp://example.com").is_err());
+    }
+}
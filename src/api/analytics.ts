@@ -86,6 +86,22 @@ export const analyticsApi = {
     };
   },
 
+  /**
+   * Cost of goods for a listing's inventory item. For items from a purchase
+   * lot this is their allocated share of the lot total.
+   */
+  async itemCostForListing(listingId: string): Promise<number> {
+    const { data, error } = await supabase
+      .from('inventory')
+      .select('cost')
+      .eq('listing_id', listingId)
+      .is('deleted_at', null)
+      .limit(1);
+
+    if (error || !data?.length) return 0;
+    return Number(data[0].cost) || 0;
+  },
+
   async recordSale(sale: {
     listing_id?: string;
     platform: string;
@@ -99,6 +115,10 @@ export const analyticsApi = {
     const { data: { user } } = await supabase.auth.getUser();
     if (!user) throw new Error('Not authenticated');
 
+    // Profit is computed from the recorded cost, so default it to the item's
+    // (lot-allocated) cost rather than zero.
+    const cost = sale.cost ?? (sale.listing_id ? await analyticsApi.itemCostForListing(sale.listing_id) : 0);
+
     const { data, error } = await supabase
       .from('sales')
       .insert({
@@ -106,7 +126,7 @@ export const analyticsApi = {
         ...sale,
         shipping_cost: sale.shipping_cost || 0,
         platform_fees: sale.platform_fees || 0,
-        cost: sale.cost || 0,
+        cost,
         sold_at: sale.sold_at || new Date().toISOString(),
       })
       .select()
@@ -219,6 +239,8 @@ export const analyticsApi = {
 
         // Single batch upsert — handles new and existing in one round-trip.
         // ignoreDuplicates: false means existing rows get updated with fresh eBay data.
+        // cost is left out so a cost already recorded on the sale (e.g. an item's
+        // lot-allocated cost) isn't reset; new rows get the column default.
         const rows = platformSales
           .filter((item) => item.orderId)
           .map((item) => ({
@@ -227,7 +249,6 @@ export const analyticsApi = {
             sale_price: item.price,
             shipping_cost: item.shippingCost || 0,
             platform_fees: item.platformFees || 0,
-            cost: 0,
             buyer_username: item.buyerUsername || null,
             sold_at: item.soldDate || new Date().toISOString(),
             external_id: item.orderId,
@@ -0,0 +1,100 @@
+import { supabase } from './supabase';
+import type { InventoryItem } from '../stores/inventoryStore';
+
+// Deleting an item moves it to the trash (deleted_at is set); trashed items
+// are left out of every query below unless asked for. They keep their
+// listing link and photos until purged (migration 016).
+
+let lastAutoPurge = 0;
+const AUTO_PURGE_EVERY_MS = 24 * 60 * 60 * 1000;
+const IMAGE_BUCKET = 'listing-images';
+
+/**
+ * Remove the uploaded photos of purged items. Items still linked to a listing
+ * leave their photos alone, since the listing uses the same files.
+ */
+async function removePhotos(items: InventoryItem[]): Promise<void> {
+  const marker = `/${IMAGE_BUCKET}/`;
+  const paths = items
+    .filter((item) => !item.listing_id)
+    .flatMap((item) => item.images || [])
+    .filter((url) => url.includes(marker))
+    .map((url) => decodeURIComponent(url.split(marker)[1].split('?')[0]));
+  if (paths.length === 0) return;
+
+  const { error } = await supabase.storage.from(IMAGE_BUCKET).remove(paths);
+  if (error) console.error('Purged item photo cleanup error:', error);
+}
+
+export const inventoryApi = {
+  async getAll(options?: { includeDeleted?: boolean }): Promise<InventoryItem[]> {
+    let query = supabase
+      .from('inventory')
+      .select('*')
+      .order('created_at', { ascending: false });
+
+    if (!options?.includeDeleted) {
+      query = query.is('deleted_at', null);
+    }
+
+    const { data, error } = await query;
+    if (error) throw error;
+    return data || [];
+  },
+
+  /** Move items to the trash, or remove them for good with `hard` (erasure requests). */
+  async deleteItems(ids: string[], options?: { hard?: boolean }): Promise<void> {
+    if (options?.hard) {
+      const { data, error } = await supabase.from('inventory').delete().in('id', ids).select();
+      if (error) throw error;
+      await removePhotos(data || []);
+      return;
+    }
+
+    const { error } = await supabase
+      .from('inventory')
+      .update({ deleted_at: new Date().toISOString() })
+      .in('id', ids)
+      .is('deleted_at', null);
+    if (error) throw error;
+  },
+
+  async restoreItem(sku: string): Promise<InventoryItem | null> {
+    const { data, error } = await supabase.rpc('restore_inventory_item', { p_sku: sku });
+    if (error) throw error;
+    return data?.[0] ?? null;
+  },
+
+  async listDeletedItems(): Promise<InventoryItem[]> {
+    const { data, error } = await supabase
+      .from('inventory')
+      .select('*')
+      .not('deleted_at', 'is', null)
+      .order('deleted_at', { ascending: false });
+
+    if (error) throw error;
+    return data || [];
+  },
+
+  /** Permanently remove trashed items and their photos; defaults to the profile's trash retention. */
+  async purgeDeleted(olderThanDays?: number): Promise<InventoryItem[]> {
+    const { data, error } = await supabase.rpc('purge_deleted_inventory', {
+      p_older_than_days: olderThanDays ?? null,
+    });
+    if (error) throw error;
+    const purged: InventoryItem[] = data || [];
+    await removePhotos(purged);
+    return purged;
+  },
+
+  /** Purge at the configured retention, at most once a day per session. */
+  async autoPurge(): Promise<void> {
+    if (Date.now() - lastAutoPurge < AUTO_PURGE_EVERY_MS) return;
+    lastAutoPurge = Date.now();
+    try {
+      await inventoryApi.purgeDeleted();
+    } catch (err) {
+      console.error('Inventory trash purge error:', err);
+    }
+  },
+};
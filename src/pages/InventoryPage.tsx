@@ -4,6 +4,7 @@ import { useAuthStore } from '../stores/authStore';
 import { useInventoryStore } from '../stores/inventoryStore';
 import { useRequireAuth } from '../hooks/useRequireAuth';
 import { supabase } from '../api/supabase';
+import { inventoryApi } from '../api/inventory';
 import { formatCurrency } from '../utils/formatters';
 
 export default function InventoryPage() {
@@ -42,11 +43,8 @@ export default function InventoryPage() {
     const load = async () => {
       setLoading(true);
       try {
-        const { data, error } = await supabase
-          .from('inventory')
-          .select('*')
-          .order('created_at', { ascending: false });
-        if (!error && data) setItems(data);
+        setItems(await inventoryApi.getAll());
+        inventoryApi.autoPurge();
       } catch (err) {
         console.error('Inventory load error:', err);
       } finally {
@@ -79,7 +77,7 @@ export default function InventoryPage() {
 
   const handleDelete = async (id: string) => {
     try {
-      await supabase.from('inventory').delete().eq('id', id);
+      await inventoryApi.deleteItems([id]);
       removeItem(id);
     } catch (err) {
       console.error('Delete inventory error:', err);
@@ -12,6 +12,7 @@ export interface InventoryItem {
   location: string | null;
   sku: string | null;
   listing_id: string | null;
+  deleted_at?: string | null;
   created_at: string;
   updated_at: string;
 }
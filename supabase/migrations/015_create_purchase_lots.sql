@@ -0,0 +1,52 @@
+-- Purchase lots: one row per haul bought as a bundle ("$40 for the whole box").
+-- Each inventory item from the lot references it, and its cost is the share
+-- allocated by the desktop app's allocate_lot_cost command.
+
+CREATE TABLE IF NOT EXISTS purchase_lots (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  user_id UUID REFERENCES profiles(id) ON DELETE CASCADE,
+  reference TEXT NOT NULL,
+  total_cost DECIMAL(10,2) NOT NULL CHECK (total_cost >= 0),
+  source TEXT,
+  purchased_at DATE,
+  allocation_method TEXT CHECK (allocation_method IN ('equal', 'weighted_by_expected_price', 'manual')),
+  allocated_at TIMESTAMPTZ,
+  notes TEXT,
+  created_at TIMESTAMPTZ DEFAULT NOW(),
+  updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- The CSV importer groups rows into lots by this reference
+CREATE UNIQUE INDEX IF NOT EXISTS idx_purchase_lots_reference ON purchase_lots(user_id, reference);
+
+ALTER TABLE inventory
+  ADD COLUMN IF NOT EXISTS lot_id UUID REFERENCES purchase_lots(id) ON DELETE SET NULL,
+  ADD COLUMN IF NOT EXISTS acquisition_type TEXT NOT NULL DEFAULT 'purchase'
+    CHECK (acquisition_type IN ('purchase', 'lot', 'gift')),
+  ADD COLUMN IF NOT EXISTS expected_price DECIMAL(10,2),
+  ADD COLUMN IF NOT EXISTS cost_allocation_method TEXT;
+
+COMMENT ON COLUMN inventory.acquisition_type IS 'purchase = bought individually, lot = cost allocated from purchase_lots, gift = freebie with zero cost';
+COMMENT ON COLUMN inventory.expected_price IS 'Expected sale price, used to weight lot cost allocation';
+COMMENT ON COLUMN inventory.cost_allocation_method IS 'How inventory.cost was derived from the lot total (equal / weighted_by_expected_price / manual)';
+
+CREATE INDEX IF NOT EXISTS idx_inventory_lot_id ON inventory(lot_id);
+
+-- RLS
+ALTER TABLE purchase_lots ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY "Users can read own purchase lots"
+  ON purchase_lots FOR SELECT
+  USING (auth.uid() = user_id);
+
+CREATE POLICY "Users can create own purchase lots"
+  ON purchase_lots FOR INSERT
+  WITH CHECK (auth.uid() = user_id);
+
+CREATE POLICY "Users can update own purchase lots"
+  ON purchase_lots FOR UPDATE
+  USING (auth.uid() = user_id);
+
+CREATE POLICY "Users can delete own purchase lots"
+  ON purchase_lots FOR DELETE
+  USING (auth.uid() = user_id);
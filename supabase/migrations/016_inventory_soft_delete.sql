@@ -0,0 +1,54 @@
+-- Soft delete for inventory: deleting an item stamps deleted_at instead of
+-- removing the row, so a wrong filter + bulk delete can be undone. Deleted
+-- rows keep their listing link and photos until they are purged, either by
+-- purge_deleted_inventory (run by the app at the user's retention) or by an
+-- explicit hard delete for erasure requests.
+
+ALTER TABLE inventory ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+-- Normal queries only ever look at live rows
+CREATE INDEX IF NOT EXISTS idx_inventory_live ON inventory(user_id, created_at DESC) WHERE deleted_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_inventory_deleted ON inventory(user_id, deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- Days a soft-deleted item stays in the trash before the app purges it
+ALTER TABLE profiles ADD COLUMN IF NOT EXISTS trash_retention_days INTEGER NOT NULL DEFAULT 30
+  CHECK (trash_retention_days BETWEEN 1 AND 3650);
+
+COMMENT ON COLUMN inventory.deleted_at IS 'Set when the item is moved to the trash; NULL for live items';
+COMMENT ON COLUMN profiles.trash_retention_days IS 'Soft-deleted inventory older than this is purged';
+
+-- Restore the most recently deleted item with this SKU. Runs as the caller,
+-- so RLS limits it to their own rows.
+CREATE OR REPLACE FUNCTION restore_inventory_item(p_sku TEXT)
+RETURNS SETOF inventory
+LANGUAGE sql
+SECURITY INVOKER
+AS $$
+  UPDATE inventory SET deleted_at = NULL, updated_at = NOW()
+  WHERE id = (
+    SELECT id FROM inventory
+    WHERE sku = p_sku AND deleted_at IS NOT NULL AND user_id = auth.uid()
+    ORDER BY deleted_at DESC
+    LIMIT 1
+  )
+  RETURNING *;
+$$;
+
+-- Permanently remove trashed items deleted more than p_older_than_days ago
+-- (the profile's retention when NULL). Returns the purged rows so the app
+-- can remove their photos.
+CREATE OR REPLACE FUNCTION purge_deleted_inventory(p_older_than_days INTEGER DEFAULT NULL)
+RETURNS SETOF inventory
+LANGUAGE sql
+SECURITY INVOKER
+AS $$
+  DELETE FROM inventory
+  WHERE user_id = auth.uid()
+    AND deleted_at IS NOT NULL
+    AND deleted_at < NOW() - make_interval(days => COALESCE(
+      p_older_than_days,
+      (SELECT trash_retention_days FROM profiles WHERE id = auth.uid()),
+      30
+    ))
+  RETURNING *;
+$$;
@@ -0,0 +1,41 @@
+-- SKU sequences: the desktop app's SKU generator renders a pattern such as
+-- {category_prefix}{yy}{seq}; everything but {seq} forms the scope ("JKT26"),
+-- and this table holds the last number handed out per scope. Numbers are
+-- claimed through claim_sku_sequence, which increments in a single statement,
+-- so two devices generating at once never get the same SKU.
+
+CREATE TABLE IF NOT EXISTS sku_sequences (
+  user_id UUID REFERENCES profiles(id) ON DELETE CASCADE DEFAULT auth.uid(),
+  scope TEXT NOT NULL,
+  last_value INTEGER NOT NULL DEFAULT 0 CHECK (last_value >= 0),
+  updated_at TIMESTAMPTZ DEFAULT NOW(),
+  PRIMARY KEY (user_id, scope)
+);
+
+-- RLS
+ALTER TABLE sku_sequences ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY "Users can read own SKU sequences"
+  ON sku_sequences FOR SELECT
+  USING (auth.uid() = user_id);
+
+CREATE POLICY "Users can create own SKU sequences"
+  ON sku_sequences FOR INSERT
+  WITH CHECK (auth.uid() = user_id);
+
+CREATE POLICY "Users can update own SKU sequences"
+  ON sku_sequences FOR UPDATE
+  USING (auth.uid() = user_id);
+
+-- Reserve p_count consecutive numbers in p_scope and return the first.
+CREATE OR REPLACE FUNCTION claim_sku_sequence(p_scope TEXT, p_count INTEGER DEFAULT 1)
+RETURNS INTEGER
+LANGUAGE sql
+SECURITY INVOKER
+AS $$
+  INSERT INTO sku_sequences (user_id, scope, last_value)
+  VALUES (auth.uid(), p_scope, GREATEST(p_count, 1))
+  ON CONFLICT (user_id, scope) DO UPDATE
+    SET last_value = sku_sequences.last_value + GREATEST(p_count, 1), updated_at = NOW()
+  RETURNING last_value - GREATEST(p_count, 1) + 1;
+$$;
@@ -0,0 +1,118 @@
+-- Change log for team sync between two FlipTools installs. Every insert,
+-- update and delete on the synced tables is recorded with the columns that
+-- changed, when, and by which device, so the desktop app can export deltas
+-- since a cursor and apply another install's deltas field by field.
+--
+-- The device comes from the x-fliptools-device request header (PostgREST
+-- exposes it as request.headers). Imported changes are written with the
+-- originating device's id, so an install never re-exports what it imported.
+
+CREATE TABLE IF NOT EXISTS change_log (
+  id BIGSERIAL PRIMARY KEY,
+  user_id UUID REFERENCES profiles(id) ON DELETE CASCADE DEFAULT auth.uid(),
+  table_name TEXT NOT NULL,
+  row_id UUID NOT NULL,
+  op TEXT NOT NULL CHECK (op IN ('insert', 'update', 'delete')),
+  -- New values of the changed columns (every column for inserts, NULL for deletes)
+  fields JSONB,
+  device_id TEXT,
+  changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_change_log_user_cursor ON change_log(user_id, id);
+CREATE INDEX IF NOT EXISTS idx_change_log_row ON change_log(user_id, table_name, row_id, changed_at);
+
+-- Incoming changes that lost to a newer local edit of the same field, kept
+-- for the user to review instead of being dropped
+CREATE TABLE IF NOT EXISTS sync_conflicts (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  user_id UUID REFERENCES profiles(id) ON DELETE CASCADE DEFAULT auth.uid(),
+  table_name TEXT NOT NULL,
+  row_id UUID NOT NULL,
+  -- Column name, or '*' when a whole-row delete lost
+  field TEXT NOT NULL,
+  local_value JSONB,
+  incoming_value JSONB,
+  local_changed_at TIMESTAMPTZ,
+  incoming_changed_at TIMESTAMPTZ NOT NULL,
+  incoming_device TEXT,
+  resolved_at TIMESTAMPTZ,
+  created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_sync_conflicts_open ON sync_conflicts(user_id, created_at) WHERE resolved_at IS NULL;
+
+-- RLS
+ALTER TABLE change_log ENABLE ROW LEVEL SECURITY;
+ALTER TABLE sync_conflicts ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY "Users can read own change log"
+  ON change_log FOR SELECT
+  USING (auth.uid() = user_id);
+
+CREATE POLICY "Users can read own sync conflicts"
+  ON sync_conflicts FOR SELECT
+  USING (auth.uid() = user_id);
+
+CREATE POLICY "Users can create own sync conflicts"
+  ON sync_conflicts FOR INSERT
+  WITH CHECK (auth.uid() = user_id);
+
+CREATE POLICY "Users can update own sync conflicts"
+  ON sync_conflicts FOR UPDATE
+  USING (auth.uid() = user_id);
+
+-- Runs as the table owner so the log can't be written to directly; rows are
+-- still attributed to the row's owner.
+CREATE OR REPLACE FUNCTION record_change()
+RETURNS TRIGGER
+LANGUAGE plpgsql
+SECURITY DEFINER
+SET search_path = public
+AS $$
+DECLARE
+  device TEXT := NULLIF(current_setting('request.headers', true), '')::json->>'x-fliptools-device';
+  new_row JSONB;
+  old_row JSONB;
+  changed JSONB;
+BEGIN
+  IF TG_OP = 'DELETE' THEN
+    INSERT INTO change_log (user_id, table_name, row_id, op, fields, device_id)
+    VALUES (OLD.user_id, TG_TABLE_NAME, OLD.id, 'delete', NULL, device);
+    RETURN OLD;
+  END IF;
+
+  new_row := to_jsonb(NEW) - 'user_id' - 'updated_at';
+  IF TG_OP = 'INSERT' THEN
+    changed := new_row;
+  ELSE
+    old_row := to_jsonb(OLD) - 'user_id' - 'updated_at';
+    SELECT COALESCE(jsonb_object_agg(key, value), '{}'::jsonb) INTO changed
+    FROM jsonb_each(new_row)
+    WHERE old_row->key IS DISTINCT FROM value;
+    IF changed = '{}'::jsonb THEN
+      RETURN NEW;
+    END IF;
+  END IF;
+
+  INSERT INTO change_log (user_id, table_name, row_id, op, fields, device_id)
+  VALUES (NEW.user_id, TG_TABLE_NAME, NEW.id, lower(TG_OP), changed, device);
+  RETURN NEW;
+END;
+$$;
+
+DROP TRIGGER IF EXISTS inventory_change_log ON inventory;
+CREATE TRIGGER inventory_change_log AFTER INSERT OR UPDATE OR DELETE ON inventory
+  FOR EACH ROW EXECUTE FUNCTION record_change();
+
+DROP TRIGGER IF EXISTS listings_change_log ON listings;
+CREATE TRIGGER listings_change_log AFTER INSERT OR UPDATE OR DELETE ON listings
+  FOR EACH ROW EXECUTE FUNCTION record_change();
+
+DROP TRIGGER IF EXISTS sales_change_log ON sales;
+CREATE TRIGGER sales_change_log AFTER INSERT OR UPDATE OR DELETE ON sales
+  FOR EACH ROW EXECUTE FUNCTION record_change();
+
+DROP TRIGGER IF EXISTS purchase_lots_change_log ON purchase_lots;
+CREATE TRIGGER purchase_lots_change_log AFTER INSERT OR UPDATE OR DELETE ON purchase_lots
+  FOR EACH ROW EXECUTE FUNCTION record_change();
@@ -0,0 +1,12 @@
+-- Offline sales: items sold in person are recorded by the desktop app as
+-- sales on the 'offline' platform, with how the buyer paid and an optional
+-- note. Marketplace sales leave both NULL.
+
+ALTER TABLE sales ADD COLUMN IF NOT EXISTS payment_method TEXT
+  CHECK (payment_method IS NULL OR payment_method IN ('cash', 'card', 'transfer', 'other'));
+ALTER TABLE sales ADD COLUMN IF NOT EXISTS note TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_sales_offline ON sales(user_id, sold_at DESC) WHERE platform = 'offline';
+
+COMMENT ON COLUMN sales.payment_method IS 'How an offline (in-person) sale was paid; NULL for marketplace sales';
+COMMENT ON COLUMN sales.note IS 'Free-text note on an offline sale';
@@ -0,0 +1,14 @@
+-- Item clones: an inventory item copied by the desktop app remembers where it
+-- came from, so the app can show an item's variants. cloned_from points at
+-- the original item (clones of clones point at the same original);
+-- cloned_from_listing records an outside listing a clone was started from,
+-- as '<marketplace>:<listing id>'.
+
+ALTER TABLE inventory ADD COLUMN IF NOT EXISTS cloned_from UUID REFERENCES inventory(id) ON DELETE SET NULL;
+ALTER TABLE inventory ADD COLUMN IF NOT EXISTS cloned_from_listing TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_inventory_cloned_from ON inventory(cloned_from) WHERE cloned_from IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_inventory_cloned_from_listing ON inventory(user_id, cloned_from_listing) WHERE cloned_from_listing IS NOT NULL;
+
+COMMENT ON COLUMN inventory.cloned_from IS 'The original item this one was cloned from; NULL for originals';
+COMMENT ON COLUMN inventory.cloned_from_listing IS 'Marketplace listing this item was cloned from, as marketplace:listing_id';
@@ -0,0 +1,6 @@
+-- The desktop app's crosslisting matrix looks up a page of inventory by SKU
+-- (up to a few hundred at once) and joins each item's listing. Index live
+-- items by owner and SKU so that lookup doesn't scan the table.
+
+CREATE INDEX IF NOT EXISTS idx_inventory_user_sku ON inventory(user_id, sku) WHERE deleted_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_inventory_listing_id ON inventory(listing_id) WHERE listing_id IS NOT NULL;
@@ -0,0 +1,72 @@
+-- Consignment: items sold on behalf of someone else. Each consignor has a
+-- default split (their share of the net after marketplace fees, in percent).
+-- An item assigned to a consignor keeps the split it was assigned with in
+-- consignor_split_percent, so changing the default later doesn't rewrite
+-- what earlier sales owe. Settlements paid out are recorded in
+-- consignor_payouts and reduce the running balance.
+
+CREATE TABLE IF NOT EXISTS consignors (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  user_id UUID REFERENCES profiles(id) ON DELETE CASCADE,
+  name TEXT NOT NULL,
+  email TEXT,
+  phone TEXT,
+  notes TEXT,
+  default_split_percent DECIMAL(5,2) NOT NULL CHECK (default_split_percent BETWEEN 0 AND 100),
+  created_at TIMESTAMPTZ DEFAULT NOW(),
+  updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS consignor_payouts (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  user_id UUID REFERENCES profiles(id) ON DELETE CASCADE,
+  consignor_id UUID NOT NULL REFERENCES consignors(id) ON DELETE CASCADE,
+  amount DECIMAL(10,2) NOT NULL CHECK (amount > 0),
+  paid_on DATE NOT NULL,
+  note TEXT,
+  created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+ALTER TABLE inventory
+  ADD COLUMN IF NOT EXISTS consignor_id UUID REFERENCES consignors(id) ON DELETE SET NULL,
+  ADD COLUMN IF NOT EXISTS consignor_split_percent DECIMAL(5,2)
+    CHECK (consignor_split_percent IS NULL OR consignor_split_percent BETWEEN 0 AND 100);
+
+COMMENT ON COLUMN inventory.consignor_id IS 'Who the item is sold for; NULL for the seller''s own stock';
+COMMENT ON COLUMN inventory.consignor_split_percent IS 'Consignor''s share of the net after fees, fixed when the item was assigned';
+
+CREATE INDEX IF NOT EXISTS idx_consignors_user_id ON consignors(user_id);
+CREATE INDEX IF NOT EXISTS idx_consignor_payouts_consignor ON consignor_payouts(consignor_id, paid_on);
+CREATE INDEX IF NOT EXISTS idx_inventory_consignor_id ON inventory(consignor_id) WHERE consignor_id IS NOT NULL;
+
+-- RLS
+ALTER TABLE consignors ENABLE ROW LEVEL SECURITY;
+ALTER TABLE consignor_payouts ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY "Users can read own consignors"
+  ON consignors FOR SELECT
+  USING (auth.uid() = user_id);
+
+CREATE POLICY "Users can create own consignors"
+  ON consignors FOR INSERT
+  WITH CHECK (auth.uid() = user_id);
+
+CREATE POLICY "Users can update own consignors"
+  ON consignors FOR UPDATE
+  USING (auth.uid() = user_id);
+
+CREATE POLICY "Users can delete own consignors"
+  ON consignors FOR DELETE
+  USING (auth.uid() = user_id);
+
+CREATE POLICY "Users can read own consignor payouts"
+  ON consignor_payouts FOR SELECT
+  USING (auth.uid() = user_id);
+
+CREATE POLICY "Users can create own consignor payouts"
+  ON consignor_payouts FOR INSERT
+  WITH CHECK (auth.uid() = user_id);
+
+CREATE POLICY "Users can delete own consignor payouts"
+  ON consignor_payouts FOR DELETE
+  USING (auth.uid() = user_id);